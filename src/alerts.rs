@@ -0,0 +1,55 @@
+// src/alerts.rs
+use crate::publisher::Publisher;
+use crate::utils::current_time_millis;
+use serde::Serialize;
+use tracing::error;
+
+pub const ALERTS_TOPIC: &str = "engine.alerts";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    InvariantViolation,
+    FairValueDeviation,
+    ReplayDivergence,
+    EnginePanic,
+    ProcessingStall,
+    OmsHeartbeatLost,
+    SequenceGap,
+}
+
+/// An operator-facing alert published to `ALERTS_TOPIC`
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub instrument_id: String,
+    pub message: String,
+    pub timestamp: u64,
+    pub details: serde_json::Value,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, instrument_id: &str, message: String) -> Self {
+        Self {
+            kind,
+            instrument_id: instrument_id.to_string(),
+            message,
+            timestamp: current_time_millis(),
+            details: serde_json::Value::Null,
+        }
+    }
+
+    pub fn with_details<D: Serialize>(mut self, details: &D) -> Self {
+        self.details = serde_json::to_value(details).unwrap_or(serde_json::Value::Null);
+        self
+    }
+}
+
+/// Logs the alert and publishes it keyed by instrument
+pub fn emit_alert(publisher: &Publisher, alert: Alert) {
+    error!(
+        "[ALERT] {:?} on {}: {}",
+        alert.kind, alert.instrument_id, alert.message
+    );
+    publisher.publish(ALERTS_TOPIC, &alert.instrument_id, &alert);
+}
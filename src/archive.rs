@@ -0,0 +1,299 @@
+// src/archive.rs
+use crate::blocking_worker::BlockingWorker;
+use crate::config::archive::ArchiveConfig;
+use crate::orderbook::history::BookArchive;
+use crate::orderbook::snapshot::OrderBookSnapshot;
+use crate::sinks::{EventSink, LevelChange, SinkEvent};
+use serde::Serialize;
+use std::io;
+use tracing::{info, warn};
+
+/// How busy the engine is, sampled on each archive tick
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EngineLoad {
+    /// Commands waiting in the engine channel, as a fraction of its capacity
+    pub queue_fill: f64,
+    /// Slowest command processed since the previous tick, in microseconds
+    pub command_latency_us: u64,
+}
+
+/// Counters of the snapshot scheduler
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotStats {
+    /// Snapshot cycles run
+    pub cycles: u64,
+    /// Ticks on which a due cycle was deferred because the engine was busy
+    pub skipped_cycles: u64,
+    /// Cycles run while the engine was busy, after the maximum delay
+    pub forced_cycles: u64,
+    /// Batches of writes dropped because the writer thread fell behind
+    pub dropped_writes: u64,
+}
+
+/// Archives level changes and depth snapshots of every book, and schedules the
+/// snapshot cycles
+///
+/// Level changes are batched and written on each tick by a dedicated writer
+/// thread, so neither matching nor the engine task ever waits on the disk. If the
+/// writer falls behind far enough to fill its queue, writes are dropped and the
+/// archived history has a gap until the next snapshot cycle. Snapshot cycles are
+/// deferred while the engine is busy so a burst of commands is not slowed down by
+/// a full dump of every book.
+pub struct BookArchiver {
+    config: ArchiveConfig,
+    writer: BlockingWorker<BookArchive>,
+    /// Level changes not yet handed to the writer
+    pending: Vec<LevelChange>,
+    last_snapshot_at: u64,
+    /// Ticks the current cycle has been deferred
+    deferred: u64,
+    stats: SnapshotStats,
+}
+
+impl BookArchiver {
+    pub fn new(config: ArchiveConfig, now: u64) -> Self {
+        if config.enabled {
+            info!("Archiving book depth to {}", config.directory);
+        }
+        Self {
+            writer: BlockingWorker::spawn(
+                "book-archive-writer",
+                BookArchive::new(&config.directory),
+                config.writer_queue_capacity,
+            ),
+            config,
+            pending: Vec::new(),
+            last_snapshot_at: now,
+            deferred: 0,
+            stats: SnapshotStats::default(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Whether a snapshot cycle should run now
+    ///
+    /// A due cycle is deferred while the engine is busy, up to the maximum delay.
+    /// Returning true starts the next interval.
+    pub fn snapshot_due(&mut self, load: EngineLoad, now: u64) -> bool {
+        let due_at = self.last_snapshot_at + self.config.snapshot_interval_ms;
+        if now < due_at {
+            return false;
+        }
+        if self.is_busy(load) {
+            if now - due_at < self.config.max_snapshot_delay_ms {
+                self.deferred += 1;
+                self.stats.skipped_cycles += 1;
+                return false;
+            }
+            self.stats.forced_cycles += 1;
+            warn!(
+                "Engine still busy after deferring snapshots for {} ms, taking them anyway",
+                now - due_at
+            );
+        } else if self.deferred > 0 {
+            info!(
+                "Taking snapshots after deferring {} ticks while the engine was busy",
+                self.deferred
+            );
+        }
+        self.stats.cycles += 1;
+        self.deferred = 0;
+        self.last_snapshot_at = now;
+        true
+    }
+
+    fn is_busy(&self, load: EngineLoad) -> bool {
+        load.queue_fill > self.config.busy_queue_fill
+            || load.command_latency_us > self.config.busy_command_latency_us
+    }
+
+    pub fn stats(&self) -> SnapshotStats {
+        SnapshotStats {
+            dropped_writes: self.writer.rejected(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Reconstructs an instrument's depth as it was at `timestamp`
+    ///
+    /// The archive is read on the writer thread after every write queued so far,
+    /// and `on_result` is called there. Returns false if the query could not be
+    /// queued.
+    pub fn query_book_as_of(
+        &mut self,
+        instrument_id: &str,
+        timestamp: u64,
+        on_result: impl FnOnce(io::Result<Option<OrderBookSnapshot>>) + Send + 'static,
+    ) -> bool {
+        self.flush();
+        let instrument_id = instrument_id.to_string();
+        self.writer.submit(move |archive| {
+            on_result(archive.book_as_of(&instrument_id, timestamp));
+        })
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let changes = std::mem::take(&mut self.pending);
+        let count = changes.len();
+        let queued = self.writer.submit(move |archive| {
+            for change in changes {
+                if let Err(e) = archive.append_delta(
+                    &change.instrument_id,
+                    change.timestamp,
+                    change.side,
+                    change.price,
+                    change.quantity,
+                ) {
+                    warn!(
+                        "Failed to archive level change of {}: {}",
+                        change.instrument_id, e
+                    );
+                }
+            }
+        });
+        if !queued {
+            warn!(
+                "Archive writer is behind, dropped {} level changes; history has a gap until the next snapshot",
+                count
+            );
+        }
+    }
+
+    fn write_snapshot(&mut self, snapshot: OrderBookSnapshot) {
+        // The snapshot supersedes the changes before it, so they are written first
+        self.flush();
+        let symbol = snapshot.symbol.clone();
+        let queued = self.writer.submit(move |archive| {
+            if let Err(e) = archive.append_snapshot(&snapshot) {
+                warn!("Failed to archive snapshot of {}: {}", snapshot.symbol, e);
+            }
+        });
+        if !queued {
+            warn!("Archive writer is behind, dropped snapshot of {}", symbol);
+        }
+    }
+}
+
+impl EventSink for BookArchiver {
+    fn name(&self) -> &str {
+        "archive"
+    }
+
+    fn accept(&mut self, event: &SinkEvent<'_>) {
+        if !self.config.enabled {
+            return;
+        }
+        match event {
+            SinkEvent::Delta(change) => self.pending.push((*change).clone()),
+            SinkEvent::Snapshot(snapshot) => self.write_snapshot((*snapshot).clone()),
+            SinkEvent::Trade(_) | SinkEvent::Order(_) | SinkEvent::Execution(_) => {}
+        }
+    }
+
+    fn on_tick(&mut self, _now: u64) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::Side;
+
+    fn archiver(name: &str, config: ArchiveConfig) -> (BookArchiver, std::path::PathBuf) {
+        let directory =
+            std::env::temp_dir().join(format!("book-archiver-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let config = ArchiveConfig {
+            directory: directory.to_string_lossy().into_owned(),
+            ..config
+        };
+        (BookArchiver::new(config, 0), directory)
+    }
+
+    fn empty_book(timestamp: u64) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            symbol: "BTC".to_string(),
+            timestamp,
+            bids: Vec::new(),
+            asks: Vec::new(),
+            extra_fields: Vec::new(),
+        }
+    }
+
+    fn book_as_of(archiver: &mut BookArchiver, timestamp: u64) -> Option<OrderBookSnapshot> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        assert!(archiver.query_book_as_of("BTC", timestamp, move |result| {
+            tx.send(result.unwrap()).unwrap();
+        }));
+        rx.recv().unwrap()
+    }
+
+    #[test]
+    fn test_archived_book_can_be_queried_as_of_a_past_time() {
+        let (mut archiver, directory) = archiver("as-of", ArchiveConfig::default());
+        archiver.accept(&SinkEvent::Snapshot(&empty_book(0)));
+        archiver.accept(&SinkEvent::Delta(&LevelChange {
+            instrument_id: "BTC".to_string(),
+            timestamp: 10,
+            side: Side::Buy,
+            price: 100,
+            quantity: 5,
+            replenished: None,
+        }));
+        archiver.on_tick(10);
+
+        assert!(book_as_of(&mut archiver, 5).unwrap().bids.is_empty());
+        let after = book_as_of(&mut archiver, 10).unwrap();
+        assert_eq!(
+            (after.bids[0].price, after.bids[0].visible_quantity),
+            (100, 5)
+        );
+
+        // The book was deleted
+        archiver.accept(&SinkEvent::Snapshot(&empty_book(20)));
+        assert!(book_as_of(&mut archiver, 20).unwrap().bids.is_empty());
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_snapshots_are_deferred_while_the_engine_is_busy() {
+        let (mut archiver, directory) = archiver(
+            "busy",
+            ArchiveConfig {
+                snapshot_interval_ms: 100,
+                max_snapshot_delay_ms: 50,
+                ..ArchiveConfig::default()
+            },
+        );
+        let busy = EngineLoad {
+            queue_fill: 0.9,
+            command_latency_us: 0,
+        };
+        let slow = EngineLoad {
+            queue_fill: 0.0,
+            command_latency_us: 5_000,
+        };
+
+        assert!(!archiver.snapshot_due(EngineLoad::default(), 50));
+        assert!(!archiver.snapshot_due(busy, 100));
+        assert!(!archiver.snapshot_due(slow, 120));
+        assert!(archiver.snapshot_due(EngineLoad::default(), 130));
+
+        // Busy for longer than the maximum delay
+        assert!(!archiver.snapshot_due(busy, 230));
+        assert!(archiver.snapshot_due(busy, 280));
+        let stats = archiver.stats();
+        assert_eq!(
+            (stats.cycles, stats.skipped_cycles, stats.forced_cycles),
+            (2, 3, 1)
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
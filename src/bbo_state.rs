@@ -0,0 +1,310 @@
+// src/bbo_state.rs
+use crate::config::bbo_state::BboStateConfig;
+use crate::orderbook::{MarkSource, MetricFlags, OrderBook};
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Top of book and last trade of an instrument, as published on the BBO state
+/// topic
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BboState {
+    pub instrument_id: String,
+    pub best_bid: Option<u64>,
+    /// Quantity resting at the best bid, hidden quantity included
+    pub bid_quantity: u64,
+    pub best_ask: Option<u64>,
+    /// Quantity resting at the best ask, hidden quantity included
+    pub ask_quantity: u64,
+    pub mid_price: Option<f64>,
+    pub spread_bps: Option<f64>,
+    pub last_trade_price: Option<u64>,
+    /// Mid price, or the price the book falls back to while a side is empty
+    pub mark_price: Option<f64>,
+    /// Source of the mark price
+    pub mark_source: Option<MarkSource>,
+    pub timestamp: u64,
+}
+
+impl BboState {
+    /// State of a book, taken from an enriched snapshot of its touch
+    pub fn of(book: &OrderBook<OrderTags>, now: u64) -> Self {
+        let snapshot =
+            book.enriched_snapshot_with_metrics(1, MetricFlags::MID_PRICE | MetricFlags::SPREAD);
+        let touch = |levels: &[pricelevel::PriceLevelSnapshot]| {
+            levels.first().map_or((None, 0), |level| {
+                (
+                    Some(level.price),
+                    level.visible_quantity + level.hidden_quantity,
+                )
+            })
+        };
+        let (best_bid, bid_quantity) = touch(&snapshot.bids);
+        let (best_ask, ask_quantity) = touch(&snapshot.asks);
+        let mark = book.mark_price();
+        Self {
+            instrument_id: snapshot.symbol,
+            best_bid,
+            bid_quantity,
+            best_ask,
+            ask_quantity,
+            mid_price: snapshot.mid_price,
+            spread_bps: snapshot.spread_bps,
+            last_trade_price: book.last_trade_price(),
+            mark_price: mark.map(|mark| mark.price),
+            mark_source: mark.map(|mark| mark.source),
+            timestamp: now,
+        }
+    }
+
+    /// State of a deleted instrument, which quotes and trades no more
+    fn deleted(instrument_id: &str, now: u64) -> Self {
+        Self {
+            instrument_id: instrument_id.to_string(),
+            best_bid: None,
+            bid_quantity: 0,
+            best_ask: None,
+            ask_quantity: 0,
+            mid_price: None,
+            spread_bps: None,
+            last_trade_price: None,
+            mark_price: None,
+            mark_source: None,
+            timestamp: now,
+        }
+    }
+
+    /// Best bid and ask of the state, with the quantity at each
+    fn touch(&self) -> BboEvent {
+        BboEvent {
+            instrument_id: self.instrument_id.clone(),
+            best_bid: self.best_bid,
+            bid_quantity: self.bid_quantity,
+            best_ask: self.best_ask,
+            ask_quantity: self.ask_quantity,
+            timestamp: self.timestamp,
+        }
+    }
+
+    fn same_quote(&self, other: &Self) -> bool {
+        Self {
+            timestamp: other.timestamp,
+            ..self.clone()
+        } == *other
+    }
+}
+
+/// A change of the best bid or ask price or size of an instrument, as
+/// published on the BBO event topic
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BboEvent {
+    pub instrument_id: String,
+    pub best_bid: Option<u64>,
+    /// Quantity resting at the best bid, hidden quantity included
+    pub bid_quantity: u64,
+    pub best_ask: Option<u64>,
+    /// Quantity resting at the best ask, hidden quantity included
+    pub ask_quantity: u64,
+    pub timestamp: u64,
+}
+
+impl BboEvent {
+    fn same_touch(&self, other: &Self) -> bool {
+        Self {
+            timestamp: other.timestamp,
+            ..self.clone()
+        } == *other
+    }
+}
+
+/// Publishes the state of an instrument whenever its best bid, best ask or
+/// last trade changes, and an event whenever its touch changes
+///
+/// The state is keyed by instrument on a compacted topic, so a consumer
+/// starting up reads one message per instrument to learn the current market
+/// instead of replaying the depth feeds. The events leave out trades and
+/// marks, so a pricing service following the touch hears of nothing else.
+pub struct BboStatePublisher {
+    config: BboStateConfig,
+    published: HashMap<String, BboState>,
+    /// Touch of each instrument as last published on the event topic
+    touches: HashMap<String, BboEvent>,
+}
+
+impl BboStatePublisher {
+    pub fn new(config: BboStateConfig) -> Self {
+        Self {
+            config,
+            published: HashMap::new(),
+            touches: HashMap::new(),
+        }
+    }
+
+    /// Publishes the book's state and touch if they changed since they were
+    /// last published
+    pub fn on_book_change(&mut self, book: &OrderBook<OrderTags>, publisher: &Publisher, now: u64) {
+        if !self.config.enabled && !self.config.events_enabled {
+            return;
+        }
+        let state = BboState::of(book, now);
+        if self.config.events_enabled {
+            self.publish_touch(state.touch(), publisher);
+        }
+        if !self.config.enabled
+            || self
+                .published
+                .get(book.symbol())
+                .is_some_and(|published| published.same_quote(&state))
+        {
+            return;
+        }
+        publisher.publish(&self.config.topic, book.symbol(), &state);
+        self.published.insert(book.symbol().to_string(), state);
+    }
+
+    fn publish_touch(&mut self, touch: BboEvent, publisher: &Publisher) {
+        if self
+            .touches
+            .get(&touch.instrument_id)
+            .is_some_and(|published| published.same_touch(&touch))
+        {
+            return;
+        }
+        publisher.publish(&self.config.events_topic, &touch.instrument_id, &touch);
+        self.touches.insert(touch.instrument_id.clone(), touch);
+    }
+
+    /// Publishes an empty state and touch for a deleted instrument, replacing
+    /// its last ones
+    pub fn forget(&mut self, instrument_id: &str, publisher: &Publisher, now: u64) {
+        let deleted = BboState::deleted(instrument_id, now);
+        if self.touches.remove(instrument_id).is_some() {
+            publisher.publish(&self.config.events_topic, instrument_id, &deleted.touch());
+        }
+        if self.published.remove(instrument_id).is_some() {
+            publisher.publish(&self.config.topic, instrument_id, &deleted);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::manager::{BookManager, BookManagerStd};
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    #[test]
+    fn test_state_is_published_when_the_touch_or_last_trade_changes() {
+        let (publisher, mut outbound) = Publisher::channel(16);
+        let mut bbo = BboStatePublisher::new(BboStateConfig::default());
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let book = manager.get_book("BTC").unwrap();
+        let rest = |id: u64, side, price| {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                5,
+                side,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        };
+        rest(1, Side::Buy, 99);
+        rest(2, Side::Sell, 101);
+        bbo.on_book_change(book, &publisher, 1);
+        let state = outbound.try_recv().unwrap();
+        assert_eq!(
+            (state.topic.as_str(), state.key.as_str()),
+            ("marketdata.bbo_state", "BTC")
+        );
+        let state: serde_json::Value = serde_json::from_str(&state.payload).unwrap();
+        assert_eq!(
+            (state["best_bid"].as_u64(), state["best_ask"].as_u64()),
+            (Some(99), Some(101))
+        );
+        assert_eq!(state["mid_price"], 100.0);
+        assert_eq!(state["mark_source"], "mid");
+        assert!(state["last_trade_price"].is_null());
+
+        // Behind the touch, nothing changes
+        rest(3, Side::Buy, 98);
+        bbo.on_book_change(book, &publisher, 2);
+        assert!(outbound.try_recv().is_err());
+
+        book.submit_market_order(OrderId::from_u64(4), 2, Side::Buy)
+            .unwrap();
+        bbo.on_book_change(book, &publisher, 3);
+        let state: serde_json::Value =
+            serde_json::from_str(&outbound.try_recv().unwrap().payload).unwrap();
+        assert_eq!(
+            (
+                state["ask_quantity"].as_u64(),
+                state["last_trade_price"].as_u64()
+            ),
+            (Some(3), Some(101))
+        );
+
+        bbo.forget("BTC", &publisher, 4);
+        let state: serde_json::Value =
+            serde_json::from_str(&outbound.try_recv().unwrap().payload).unwrap();
+        assert!(state["best_bid"].is_null());
+        bbo.forget("BTC", &publisher, 5);
+        assert!(outbound.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_events_are_published_when_the_touch_changes() {
+        let (publisher, mut outbound) = Publisher::channel(16);
+        let mut bbo = BboStatePublisher::new(BboStateConfig {
+            enabled: false,
+            events_enabled: true,
+            ..BboStateConfig::default()
+        });
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let book = manager.get_book("BTC").unwrap();
+        let mut touches = || {
+            std::iter::from_fn(|| outbound.try_recv().ok())
+                .map(|message| {
+                    assert_eq!(message.topic, "marketdata.bbo");
+                    let event: serde_json::Value = serde_json::from_str(&message.payload).unwrap();
+                    (event["best_bid"].as_u64(), event["bid_quantity"].as_u64())
+                })
+                .collect::<Vec<_>>()
+        };
+        for (id, price) in [(1, 99), (2, 98)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                5,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+            bbo.on_book_change(book, &publisher, id);
+        }
+        // A level behind the touch changes nothing
+        assert_eq!(touches(), [(Some(99), Some(5))]);
+
+        // More size at the best bid does, though the price stays
+        book.add_limit_order(
+            OrderId::from_u64(3),
+            99,
+            2,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        bbo.on_book_change(book, &publisher, 3);
+        bbo.on_book_change(book, &publisher, 4);
+        assert_eq!(touches(), [(Some(99), Some(7))]);
+
+        bbo.forget("BTC", &publisher, 5);
+        assert_eq!(touches(), [(None, Some(0))]);
+    }
+}
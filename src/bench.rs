@@ -0,0 +1,188 @@
+// src/bench.rs
+//! `bench-book`: a single in-memory book saturated with generated load, to
+//! size shard counts before going to production
+
+use crate::config::bench::BenchConfig;
+use crate::config::soak::SoakConfig;
+use crate::execution_quality::Distribution;
+use crate::helpers::{
+    EngineCommand, handle_order_cancel, handle_order_create, handle_order_modify,
+};
+use crate::orderbook::manager::BookManagerStd;
+use crate::soak::LoadGenerator;
+use crate::tags::OrderTags;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Instrument of the benchmarked book
+const INSTRUMENT: &str = "BENCH";
+
+/// Latencies of each kind of command, in microseconds; a kind is missing when
+/// none was generated
+#[derive(Debug, Serialize)]
+pub struct Latencies {
+    pub all: Option<Distribution>,
+    /// Passive limit orders that rest
+    pub add: Option<Distribution>,
+    pub cancel: Option<Distribution>,
+    pub modify: Option<Distribution>,
+    /// Market orders that trade against resting ones
+    #[serde(rename = "match")]
+    pub matches: Option<Distribution>,
+}
+
+/// Outcome of a `bench-book` run
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub commands: u64,
+    /// Commands the book refused, e.g. cancels of orders already filled
+    pub rejected: u64,
+    /// Time spent applying the measured commands, generation excluded
+    pub busy_secs: f64,
+    /// Commands a single book sustains per second: dividing the peak rate
+    /// expected on the busiest instrument by it gives the share of a shard
+    /// that instrument needs
+    pub ops_per_sec: f64,
+    pub latency_us: Latencies,
+}
+
+#[derive(Clone, Copy)]
+enum Operation {
+    Add,
+    Cancel,
+    Modify,
+    Match,
+}
+
+impl Operation {
+    fn of(cmd: &EngineCommand) -> Self {
+        match cmd {
+            EngineCommand::OrderCreate(order) if order.order_type.is_priced_by_book() => {
+                Operation::Match
+            }
+            EngineCommand::OrderCancel(_) => Operation::Cancel,
+            EngineCommand::OrderModify(_) => Operation::Modify,
+            _ => Operation::Add,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Samples {
+    add: Vec<f64>,
+    cancel: Vec<f64>,
+    modify: Vec<f64>,
+    matches: Vec<f64>,
+}
+
+impl Samples {
+    fn record(&mut self, operation: Operation, elapsed: Duration) {
+        let samples = match operation {
+            Operation::Add => &mut self.add,
+            Operation::Cancel => &mut self.cancel,
+            Operation::Modify => &mut self.modify,
+            Operation::Match => &mut self.matches,
+        };
+        samples.push(elapsed.as_secs_f64() * 1e6);
+    }
+}
+
+/// Applies a generated command to the book as the engine does, trades drained
+fn apply(manager: &mut BookManagerStd<OrderTags>, cmd: EngineCommand) -> bool {
+    let outcome = match cmd {
+        EngineCommand::OrderCreate(order) => handle_order_create(manager, order),
+        EngineCommand::OrderCancel(order) => handle_order_cancel(manager, order),
+        EngineCommand::OrderModify(order) => handle_order_modify(manager, order),
+        _ => Ok(()),
+    };
+    manager.drain_trade_events();
+    outcome.is_ok()
+}
+
+/// Drives one book with the load of a soak run as fast as it takes it
+///
+/// Runs on the calling thread, without the engine around the book, so the
+/// figures are an upper bound of what a shard does with one busy instrument.
+pub fn run(config: &BenchConfig) -> BenchReport {
+    let mut generator = LoadGenerator::new(&SoakConfig {
+        instruments: vec![INSTRUMENT.to_string()],
+        max_resting_orders: config.resting_orders,
+        seed: config.seed,
+        ..SoakConfig::default()
+    });
+    let mut manager = BookManagerStd::<OrderTags>::new();
+    for _ in 0..config.warmup_commands {
+        apply(&mut manager, generator.next_command());
+    }
+
+    let mut samples = Samples::default();
+    let mut busy = Duration::ZERO;
+    let mut rejected = 0;
+    for _ in 0..config.commands {
+        let cmd = generator.next_command();
+        let operation = Operation::of(&cmd);
+        let started = Instant::now();
+        let applied = apply(&mut manager, cmd);
+        let elapsed = started.elapsed();
+        busy += elapsed;
+        rejected += u64::from(!applied);
+        samples.record(operation, elapsed);
+    }
+
+    let Samples {
+        mut add,
+        mut cancel,
+        mut modify,
+        mut matches,
+    } = samples;
+    let mut all: Vec<f64> = [&add, &cancel, &modify, &matches]
+        .into_iter()
+        .flatten()
+        .copied()
+        .collect();
+    BenchReport {
+        commands: config.commands,
+        rejected,
+        busy_secs: busy.as_secs_f64(),
+        ops_per_sec: config.commands as f64 / busy.as_secs_f64().max(f64::MIN_POSITIVE),
+        latency_us: Latencies {
+            all: Distribution::of(&mut all),
+            add: Distribution::of(&mut add),
+            cancel: Distribution::of(&mut cancel),
+            modify: Distribution::of(&mut modify),
+            matches: Distribution::of(&mut matches),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_measured_command_is_reported_once() {
+        let report = run(&BenchConfig {
+            commands: 2_000,
+            warmup_commands: 500,
+            resting_orders: 100,
+            seed: 7,
+        });
+        let latency = &report.latency_us;
+        let count = |distribution: &Option<Distribution>| {
+            distribution
+                .as_ref()
+                .map_or(0, |distribution| distribution.count)
+        };
+        assert_eq!(count(&latency.all), 2_000);
+        assert_eq!(
+            count(&latency.add)
+                + count(&latency.cancel)
+                + count(&latency.modify)
+                + count(&latency.matches),
+            2_000
+        );
+        assert!(count(&latency.matches) > 0);
+        assert!(report.rejected < report.commands);
+        assert!(report.ops_per_sec > 0.0);
+    }
+}
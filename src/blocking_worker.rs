@@ -0,0 +1,114 @@
+// src/blocking_worker.rs
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use tracing::{info, warn};
+
+type Job<S> = Box<dyn FnOnce(&mut S) + Send>;
+
+/// Runs blocking sink work (disk writes, fsyncs, uploads) on a dedicated thread
+///
+/// The thread owns the sink state `S`; callers hand it jobs through a bounded
+/// queue and never wait on it. When the queue is full, jobs are refused instead of
+/// blocking the caller, so a stalled disk cannot back up into the engine.
+pub struct BlockingWorker<S> {
+    name: String,
+    sender: Option<SyncSender<Job<S>>>,
+    handle: Option<JoinHandle<()>>,
+    rejected: Arc<AtomicU64>,
+}
+
+impl<S: Send + 'static> BlockingWorker<S> {
+    pub fn spawn(name: &str, mut state: S, queue_capacity: usize) -> Self {
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<Job<S>>(queue_capacity.max(1));
+        let thread_name = name.to_string();
+        let handle = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                for job in receiver {
+                    job(&mut state);
+                }
+                info!("Blocking worker {} stopped", thread_name);
+            })
+            .map_err(|e| warn!("Failed to start blocking worker {}: {}", name, e))
+            .ok();
+        Self {
+            name: name.to_string(),
+            sender: handle.is_some().then_some(sender),
+            handle,
+            rejected: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Queues a job, returning false if the queue is full or the worker has stopped
+    pub fn submit(&self, job: impl FnOnce(&mut S) + Send + 'static) -> bool {
+        let Some(sender) = &self.sender else {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+        match sender.try_send(Box::new(job)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                warn!("Blocking worker {} has stopped", self.name);
+                false
+            }
+        }
+    }
+
+    /// Jobs refused because the queue was full or the worker had stopped
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+impl<S> Drop for BlockingWorker<S> {
+    /// Lets the worker finish the jobs already queued
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            warn!("Blocking worker {} panicked", self.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_jobs_run_in_order_and_full_queue_rejects() {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let worker = BlockingWorker::spawn("test-worker", Vec::new(), 1);
+
+        // Hold the worker inside a job so the queue fills up behind it
+        assert!(worker.submit(move |seen: &mut Vec<u32>| {
+            started_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            seen.push(1);
+        }));
+        started_rx.recv().unwrap();
+        assert!(worker.submit(|seen| seen.push(2)));
+        assert!(!worker.submit(|seen| seen.push(3)));
+        assert_eq!(worker.rejected(), 1);
+
+        let (done_tx, done_rx) = mpsc::channel();
+        release_tx.send(()).unwrap();
+        while !worker.submit({
+            let done_tx = done_tx.clone();
+            move |seen| done_tx.send(seen.clone()).unwrap()
+        }) {
+            std::thread::yield_now();
+        }
+        assert_eq!(done_rx.recv().unwrap(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,393 @@
+// src/chaos.rs
+//! Fault injection for chaos tests, compiled only with the `chaos` feature
+//!
+//! Faults are switched on and off through the admin topic while the engine
+//! runs: inbound messages can be dropped, duplicated or delayed before they
+//! reach the engine, sink writes can fail, and the clock can be skewed.
+
+use crate::sinks::{EventSink, SinkEvent};
+use crate::utils::time::skew::set_clock_skew_ms;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Faults to inject, each drawn independently per message or write
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultPlan {
+    /// Chance that an inbound message is dropped
+    pub drop_probability: f64,
+    /// Chance that an inbound message is delivered twice
+    pub duplicate_probability: f64,
+    /// Chance that the consumer stalls for `delay_ms` before delivering a message
+    pub delay_probability: f64,
+    pub delay_ms: u64,
+    /// Chance that an event handed to a sink is lost as if its write failed
+    pub sink_failure_probability: f64,
+    /// Added to every clock reading; may be negative
+    pub clock_skew_ms: i64,
+    /// Seed of the fault draws, so a failing run can be repeated
+    pub seed: u64,
+}
+
+/// What happens to one inbound message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Drop,
+    Deliver,
+    Duplicate,
+    Delay(Duration),
+}
+
+impl Delivery {
+    /// Times the message reaches the engine
+    pub fn copies(self) -> usize {
+        match self {
+            Delivery::Drop => 0,
+            Delivery::Deliver | Delivery::Delay(_) => 1,
+            Delivery::Duplicate => 2,
+        }
+    }
+}
+
+/// Faults injected since the plan was last changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FaultStats {
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub delayed: u64,
+    pub sink_failures: u64,
+}
+
+struct Faults {
+    plan: FaultPlan,
+    /// State of the xorshift generator behind fault draws
+    rng: u64,
+    stats: FaultStats,
+}
+
+impl Faults {
+    fn next_unit(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Decides which faults hit each message and write, following the current plan
+pub struct FaultInjector {
+    faults: Mutex<Faults>,
+}
+
+static GLOBAL: LazyLock<Arc<FaultInjector>> = LazyLock::new(|| Arc::new(FaultInjector::new()));
+
+/// The injector behind the consumer and sink hooks
+pub fn global() -> Arc<FaultInjector> {
+    GLOBAL.clone()
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaultInjector {
+    pub fn new() -> Self {
+        Self {
+            faults: Mutex::new(Faults {
+                plan: FaultPlan::default(),
+                rng: 1,
+                stats: FaultStats::default(),
+            }),
+        }
+    }
+
+    /// Replaces the plan, returning the faults injected under the previous one
+    pub fn set_plan(&self, plan: FaultPlan) -> FaultStats {
+        let mut faults = self.faults.lock().unwrap();
+        // xorshift never leaves an all-zero state
+        faults.rng = plan.seed.max(1);
+        faults.plan = plan;
+        std::mem::take(&mut faults.stats)
+    }
+
+    pub fn plan(&self) -> FaultPlan {
+        self.faults.lock().unwrap().plan.clone()
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        self.faults.lock().unwrap().stats
+    }
+
+    /// Draws the fate of the next inbound message
+    pub fn inbound(&self) -> Delivery {
+        let mut faults = self.faults.lock().unwrap();
+        let draw = faults.next_unit();
+        let drop = faults.plan.drop_probability;
+        let duplicate = drop + faults.plan.duplicate_probability;
+        let delay = duplicate + faults.plan.delay_probability;
+        if draw < drop {
+            faults.stats.dropped += 1;
+            Delivery::Drop
+        } else if draw < duplicate {
+            faults.stats.duplicated += 1;
+            Delivery::Duplicate
+        } else if draw < delay {
+            faults.stats.delayed += 1;
+            Delivery::Delay(Duration::from_millis(faults.plan.delay_ms))
+        } else {
+            Delivery::Deliver
+        }
+    }
+
+    /// Draws whether the next sink write fails
+    pub fn sink_write_fails(&self) -> bool {
+        let mut faults = self.faults.lock().unwrap();
+        if faults.plan.sink_failure_probability <= 0.0 {
+            return false;
+        }
+        let fails = faults.next_unit() < faults.plan.sink_failure_probability;
+        if fails {
+            faults.stats.sink_failures += 1;
+        }
+        fails
+    }
+}
+
+/// Switches the process to a new fault plan, including its clock skew
+pub fn inject(plan: FaultPlan) {
+    warn!("Injecting faults: {:?}", plan);
+    set_clock_skew_ms(plan.clock_skew_ms);
+    let stats = global().set_plan(plan);
+    info!("Faults injected under the previous plan: {:?}", stats);
+}
+
+/// Stops injecting faults and restores the clock
+pub fn clear() {
+    set_clock_skew_ms(0);
+    let stats = global().set_plan(FaultPlan::default());
+    info!("Cleared injected faults, injected so far: {:?}", stats);
+}
+
+/// Sink whose writes fail as often as the fault plan says
+pub struct FaultySink {
+    inner: Box<dyn EventSink>,
+    injector: Arc<FaultInjector>,
+}
+
+impl FaultySink {
+    pub fn new(inner: Box<dyn EventSink>, injector: Arc<FaultInjector>) -> Self {
+        Self { inner, injector }
+    }
+}
+
+impl EventSink for FaultySink {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn accept(&mut self, event: &SinkEvent<'_>) {
+        if self.injector.sink_write_fails() {
+            warn!(
+                "Injected write failure in sink {}, lost a {:?} event",
+                self.inner.name(),
+                event.kind()
+            );
+            return;
+        }
+        self.inner.accept(event);
+    }
+
+    fn on_tick(&mut self, now: u64) {
+        self.inner.on_tick(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::dedup::DedupConfig;
+    use crate::config::topics::CommandKind;
+    use crate::dedup::DuplicateWindow;
+    use crate::helpers::{EngineCommand, handle_order_cancel, handle_order_create};
+    use crate::orderbook::manager::{BookManager, BookManagerStd};
+    use crate::sinks::LevelChange;
+    use crate::tags::OrderTags;
+    use crate::utils::current_time_millis;
+    use pricelevel::Side;
+
+    fn plan(configure: impl FnOnce(&mut FaultPlan)) -> FaultPlan {
+        let mut plan = FaultPlan {
+            seed: 42,
+            delay_ms: 5,
+            ..FaultPlan::default()
+        };
+        configure(&mut plan);
+        plan
+    }
+
+    #[test]
+    fn test_faults_follow_the_plan_and_seed() {
+        let injector = FaultInjector::new();
+        assert_eq!(injector.inbound(), Delivery::Deliver);
+
+        injector.set_plan(plan(|plan| plan.drop_probability = 1.0));
+        assert_eq!(injector.inbound(), Delivery::Drop);
+        injector.set_plan(plan(|plan| plan.duplicate_probability = 1.0));
+        assert_eq!(injector.inbound().copies(), 2);
+        injector.set_plan(plan(|plan| plan.delay_probability = 1.0));
+        assert_eq!(
+            injector.inbound(),
+            Delivery::Delay(Duration::from_millis(5))
+        );
+
+        let mixed = plan(|plan| {
+            plan.drop_probability = 0.2;
+            plan.duplicate_probability = 0.2;
+            plan.delay_probability = 0.2;
+        });
+        injector.set_plan(mixed.clone());
+        let first: Vec<Delivery> = (0..200).map(|_| injector.inbound()).collect();
+        let stats = injector.set_plan(mixed);
+        let again: Vec<Delivery> = (0..200).map(|_| injector.inbound()).collect();
+        assert_eq!(first, again);
+        assert!(stats.dropped > 0 && stats.duplicated > 0 && stats.delayed > 0);
+        assert_eq!(
+            first.iter().filter(|d| **d == Delivery::Drop).count() as u64,
+            stats.dropped
+        );
+    }
+
+    /// Resting orders and cancels, run through the consumer hook as main does
+    /// and the duplicate window as the engine does
+    fn replay(injector: &FaultInjector) -> BookManagerStd<OrderTags> {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        let mut duplicates = DuplicateWindow::new(&DedupConfig {
+            enabled: true,
+            ..DedupConfig::default()
+        });
+        let messages = [
+            (
+                CommandKind::OrderCreate,
+                r#"{"instrument_id":"BTC","order_id":1,"side":"BUY","price":100,"quantity":5,"time_in_force":"GTC","order_type":"LIMIT"}"#,
+            ),
+            (
+                CommandKind::OrderCreate,
+                r#"{"instrument_id":"BTC","order_id":2,"side":"SELL","price":102,"quantity":3,"time_in_force":"GTC","order_type":"LIMIT"}"#,
+            ),
+            (
+                CommandKind::OrderCreate,
+                r#"{"instrument_id":"BTC","order_id":3,"side":"BUY","price":99,"quantity":7,"time_in_force":"GTC","order_type":"LIMIT"}"#,
+            ),
+            (
+                CommandKind::OrderCancel,
+                r#"{"instrument_id":"BTC","order_id":3}"#,
+            ),
+        ];
+        for (kind, payload) in messages {
+            for _ in 0..injector.inbound().copies() {
+                let cmd = EngineCommand::parse(kind, payload).unwrap();
+                if cmd.as_ref().is_some_and(|cmd| duplicates.is_duplicate(cmd)) {
+                    continue;
+                }
+                // A redelivered cancel is rejected, only the book is compared
+                let _ = match cmd {
+                    Some(EngineCommand::OrderCreate(order)) => {
+                        handle_order_create(&mut manager, order)
+                    }
+                    Some(EngineCommand::OrderCancel(order)) => {
+                        handle_order_cancel(&mut manager, order)
+                    }
+                    other => panic!("unexpected command {other:?}"),
+                };
+            }
+        }
+        manager
+    }
+
+    fn state_hash(manager: &BookManagerStd<OrderTags>) -> String {
+        manager
+            .get_book("BTC")
+            .unwrap()
+            .create_snapshot(usize::MAX)
+            .state_hash()
+    }
+
+    #[test]
+    fn test_redelivered_messages_leave_the_book_unchanged() {
+        let clean = state_hash(&replay(&FaultInjector::new()));
+
+        // Redelivered creates are dropped by the duplicate window rather than
+        // resting a second order under the same id
+        let injector = FaultInjector::new();
+        injector.set_plan(plan(|plan| plan.duplicate_probability = 1.0));
+        let duplicated = replay(&injector);
+        assert_eq!(state_hash(&duplicated), clean);
+        assert_eq!(injector.stats().duplicated, 4);
+        let book = duplicated.get_book("BTC").unwrap();
+        assert_eq!(book.best_bid(), Some(100));
+        assert_eq!(book.create_snapshot(1).bids[0].order_count, 1);
+
+        // Dropping messages is visible in the book, which is what the
+        // verification hashes are there to catch
+        injector.set_plan(plan(|plan| plan.drop_probability = 1.0));
+        assert!(replay(&injector).get_book("BTC").is_none());
+    }
+
+    struct CountingSink(Arc<Mutex<usize>>);
+
+    impl EventSink for CountingSink {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn accept(&mut self, _event: &SinkEvent<'_>) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn test_sink_recovers_once_failures_stop() {
+        let accepted = Arc::new(Mutex::new(0));
+        let injector = Arc::new(FaultInjector::new());
+        let mut sink = FaultySink::new(Box::new(CountingSink(accepted.clone())), injector.clone());
+        let change = LevelChange {
+            instrument_id: "BTC".to_string(),
+            side: Side::Buy,
+            price: 100,
+            quantity: 5,
+            replenished: None,
+            timestamp: 1,
+        };
+
+        injector.set_plan(plan(|plan| plan.sink_failure_probability = 1.0));
+        for _ in 0..3 {
+            sink.accept(&SinkEvent::Delta(&change));
+        }
+        assert_eq!(*accepted.lock().unwrap(), 0);
+
+        let stats = injector.set_plan(FaultPlan::default());
+        assert_eq!(stats.sink_failures, 3);
+        sink.accept(&SinkEvent::Delta(&change));
+        assert_eq!(*accepted.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_inject_skews_the_clock_until_cleared() {
+        let day_ms = 86_400_000;
+        let before = current_time_millis();
+        inject(FaultPlan {
+            clock_skew_ms: -day_ms,
+            ..FaultPlan::default()
+        });
+        let skewed = current_time_millis();
+        clear();
+        let after = current_time_millis();
+        assert!(skewed + (day_ms as u64) >= before && skewed < before);
+        assert!(after >= before);
+        assert_eq!(global().plan(), FaultPlan::default());
+    }
+}
@@ -0,0 +1,400 @@
+// src/clearing.rs
+use crate::config::clearing::{ClearingConfig, ClearingField, ClearingFormat};
+use crate::orderbook::block_trade::BlockTrade;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::rfq::RfqExecution;
+use crate::orderbook::trade::TradeEvent;
+use crate::tags::OrderTags;
+use pricelevel::{OrderId, Side};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+const MILLIS_PER_DAY: u64 = 86_400_000;
+
+/// A trade as reported to clearing
+#[derive(Debug, Clone)]
+pub struct ClearingRecord {
+    pub trade_id: String,
+    pub instrument_id: String,
+    pub timestamp: u64,
+    pub price: u64,
+    pub quantity: u64,
+    pub buyer_id: Option<String>,
+    pub seller_id: Option<String>,
+    pub buyer_fee: f64,
+    pub seller_fee: f64,
+    pub off_book: bool,
+    /// Set when the side is in breach of its order-to-trade limit
+    pub buyer_surcharge: bool,
+    pub seller_surcharge: bool,
+}
+
+/// Collects the day's trades and writes the clearing export at end of day
+pub struct ClearingLedger {
+    config: ClearingConfig,
+    /// Participant owning each order, keyed by instrument and order id
+    participants: HashMap<(String, OrderId), String>,
+    records: Vec<ClearingRecord>,
+    next_export_at: u64,
+}
+
+impl ClearingLedger {
+    pub fn new(config: ClearingConfig, now: u64) -> Self {
+        let next_export_at = next_eod(now, config.eod_time_utc_secs);
+        Self {
+            config,
+            participants: HashMap::new(),
+            records: Vec::new(),
+            next_export_at,
+        }
+    }
+
+    pub fn register_order(
+        &mut self,
+        instrument_id: &str,
+        order_id: OrderId,
+        participant_id: String,
+    ) {
+        self.participants
+            .insert((instrument_id.to_string(), order_id), participant_id);
+    }
+
+    /// Participant owning an order, if it was registered
+    pub fn participant(&self, instrument_id: &str, order_id: OrderId) -> Option<String> {
+        self.participants
+            .get(&(instrument_id.to_string(), order_id))
+            .cloned()
+    }
+
+    fn fee(&self, price: u64, quantity: u64, bps: f64) -> f64 {
+        price as f64 * quantity as f64 * bps / 10_000.0
+    }
+
+    /// Records the fills of a match; `surcharged` tells whether a participant's
+    /// trades on the instrument are flagged for a surcharge
+    pub fn record_trade_event(
+        &mut self,
+        event: &TradeEvent,
+        surcharged: impl Fn(&str, &str) -> bool,
+    ) {
+        if !self.config.enabled {
+            return;
+        }
+        for transaction in event.trade_result.match_result.transactions.as_vec() {
+            let taker = self.participant(&event.symbol, transaction.taker_order_id);
+            let maker = self.participant(&event.symbol, transaction.maker_order_id);
+            let taker_fee = self.fee(
+                transaction.price,
+                transaction.quantity,
+                self.config.taker_fee_bps,
+            );
+            let maker_fee = self.fee(
+                transaction.price,
+                transaction.quantity,
+                self.config.maker_fee_bps,
+            );
+            let (buyer_id, seller_id, buyer_fee, seller_fee) = match transaction.taker_side {
+                Side::Buy => (taker, maker, taker_fee, maker_fee),
+                Side::Sell => (maker, taker, maker_fee, taker_fee),
+            };
+            let flagged = |participant: &Option<String>| {
+                participant
+                    .as_deref()
+                    .is_some_and(|participant| surcharged(participant, &event.symbol))
+            };
+            let (buyer_surcharge, seller_surcharge) = (flagged(&buyer_id), flagged(&seller_id));
+            self.records.push(ClearingRecord {
+                trade_id: transaction.transaction_id.to_string(),
+                instrument_id: event.symbol.clone(),
+                timestamp: transaction.timestamp,
+                price: transaction.price,
+                quantity: transaction.quantity,
+                buyer_id,
+                seller_id,
+                buyer_fee,
+                seller_fee,
+                off_book: false,
+                buyer_surcharge,
+                seller_surcharge,
+            });
+        }
+    }
+
+    pub fn record_block_trade(&mut self, instrument_id: &str, trade: &BlockTrade) {
+        if !self.config.enabled {
+            return;
+        }
+        let fee = self.fee(trade.price, trade.quantity, self.config.off_book_fee_bps);
+        self.records.push(ClearingRecord {
+            trade_id: trade.trade_id.clone(),
+            instrument_id: instrument_id.to_string(),
+            timestamp: trade.timestamp,
+            price: trade.price,
+            quantity: trade.quantity,
+            buyer_id: Some(trade.buyer_id.clone()),
+            seller_id: Some(trade.seller_id.clone()),
+            buyer_fee: fee,
+            seller_fee: fee,
+            off_book: true,
+            buyer_surcharge: false,
+            seller_surcharge: false,
+        });
+    }
+
+    pub fn record_rfq_execution(&mut self, execution: &RfqExecution) {
+        if !self.config.enabled {
+            return;
+        }
+        let fee = self.fee(
+            execution.price,
+            execution.quantity,
+            self.config.off_book_fee_bps,
+        );
+        let (buyer_id, seller_id) = match execution.side {
+            Side::Buy => (&execution.requester_id, &execution.dealer_id),
+            Side::Sell => (&execution.dealer_id, &execution.requester_id),
+        };
+        self.records.push(ClearingRecord {
+            trade_id: format!("{}-{}", execution.rfq_id, execution.quote_id),
+            instrument_id: execution.instrument_id.clone(),
+            timestamp: execution.timestamp,
+            price: execution.price,
+            quantity: execution.quantity,
+            buyer_id: Some(buyer_id.clone()),
+            seller_id: Some(seller_id.clone()),
+            buyer_fee: fee,
+            seller_fee: fee,
+            off_book: true,
+            buyer_surcharge: false,
+            seller_surcharge: false,
+        });
+    }
+
+    /// Writes the export once the end-of-day time has passed
+    pub fn on_tick(&mut self, manager: &BookManagerStd<OrderTags>, now: u64) {
+        if !self.config.enabled || now < self.next_export_at {
+            return;
+        }
+        self.next_export_at = next_eod(now, self.config.eod_time_utc_secs);
+        self.export(manager, now);
+    }
+
+    /// Forgets the participants of the orders of a deleted instrument
+    pub fn forget(&mut self, instrument_id: &str) {
+        self.participants
+            .retain(|(symbol, _), _| symbol != instrument_id);
+    }
+
+    /// Writes all trades recorded since the last export and starts a new day
+    pub fn export(&mut self, manager: &BookManagerStd<OrderTags>, now: u64) {
+        let records = std::mem::take(&mut self.records);
+        // Participants of orders that are no longer resting are not needed
+        // tomorrow; those of evicted books are kept until the books are deleted
+        self.participants.retain(|(symbol, order_id), _| {
+            manager
+                .get_book(symbol)
+                .is_none_or(|book| book.get_order(*order_id).is_some())
+        });
+
+        match self.write(&records, now) {
+            Ok(path) => info!(
+                "Wrote clearing export with {} trades to {}",
+                records.len(),
+                path.display()
+            ),
+            Err(e) => {
+                warn!("Failed to write clearing export: {}", e);
+                // Keep the trades so the next export includes them
+                let mut records = records;
+                records.append(&mut self.records);
+                self.records = records;
+            }
+        }
+    }
+
+    fn write(&self, records: &[ClearingRecord], now: u64) -> std::io::Result<PathBuf> {
+        let (contents, extension) = match self.config.format {
+            ClearingFormat::Csv => (self.to_csv(records), "csv"),
+            ClearingFormat::Fixml => (self.to_fixml(records), "xml"),
+        };
+        let directory = PathBuf::from(&self.config.archive_dir);
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join(format!(
+            "clearing-{}.{}",
+            format_date(now / MILLIS_PER_DAY),
+            extension
+        ));
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    fn field(&self, record: &ClearingRecord, field: ClearingField) -> String {
+        match field {
+            ClearingField::TradeId => record.trade_id.clone(),
+            ClearingField::InstrumentId => record.instrument_id.clone(),
+            ClearingField::Timestamp => record.timestamp.to_string(),
+            ClearingField::TradeDate => format_date(record.timestamp / MILLIS_PER_DAY),
+            ClearingField::SettlementDate => format_date(self.settlement_day(record.timestamp)),
+            ClearingField::Price => record.price.to_string(),
+            ClearingField::Quantity => record.quantity.to_string(),
+            ClearingField::BuyerId => record.buyer_id.clone().unwrap_or_default(),
+            ClearingField::SellerId => record.seller_id.clone().unwrap_or_default(),
+            ClearingField::BuyerFee => format!("{:.8}", record.buyer_fee),
+            ClearingField::SellerFee => format!("{:.8}", record.seller_fee),
+            ClearingField::OffBook => record.off_book.to_string(),
+            ClearingField::BuyerSurcharge => record.buyer_surcharge.to_string(),
+            ClearingField::SellerSurcharge => record.seller_surcharge.to_string(),
+        }
+    }
+
+    fn to_csv(&self, records: &[ClearingRecord]) -> String {
+        let delimiter = self.config.csv_delimiter.to_string();
+        let header: Vec<String> = self
+            .config
+            .csv_columns
+            .iter()
+            .map(|field| format!("{field:?}"))
+            .collect();
+        let mut out = header.join(&delimiter);
+        out.push('\n');
+        for record in records {
+            let row: Vec<String> = self
+                .config
+                .csv_columns
+                .iter()
+                .map(|&field| escape_csv(&self.field(record, field), &delimiter))
+                .collect();
+            out.push_str(&row.join(&delimiter));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn to_fixml(&self, records: &[ClearingRecord]) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<FIXML v=\"5.0 SP2\">\n<Batch>\n",
+        );
+        for record in records {
+            let _ = write!(
+                out,
+                "<TrdCaptRpt RptID=\"{}\" TrdID=\"{}\" LastPx=\"{}\" LastQty=\"{}\" TrdDt=\"{}\" SettlDt=\"{}\" TxnTm=\"{}\" OffBook=\"{}\">\
+                 <Instrmt ID=\"{}\"/>",
+                escape_xml(&record.trade_id),
+                escape_xml(&record.trade_id),
+                record.price,
+                record.quantity,
+                format_date(record.timestamp / MILLIS_PER_DAY),
+                format_date(self.settlement_day(record.timestamp)),
+                record.timestamp,
+                if record.off_book { "Y" } else { "N" },
+                escape_xml(&record.instrument_id),
+            );
+            for (side, party, fee) in [
+                (1, &record.buyer_id, record.buyer_fee),
+                (2, &record.seller_id, record.seller_fee),
+            ] {
+                let _ = write!(
+                    out,
+                    "<RptSide Side=\"{}\"><Pty ID=\"{}\" R=\"1\"/><MiscFees Amt=\"{:.8}\" Typ=\"4\"/></RptSide>",
+                    side,
+                    escape_xml(party.as_deref().unwrap_or("")),
+                    fee
+                );
+            }
+            out.push_str("</TrdCaptRpt>\n");
+        }
+        out.push_str("</Batch>\n</FIXML>\n");
+        out
+    }
+
+    /// Settlement date as days since the epoch, skipping weekends
+    fn settlement_day(&self, timestamp: u64) -> u64 {
+        let mut day = timestamp / MILLIS_PER_DAY;
+        let mut remaining = self.config.settlement_lag_days;
+        while remaining > 0 {
+            day += 1;
+            if !is_weekend(day) {
+                remaining -= 1;
+            }
+        }
+        day
+    }
+}
+
+/// Next end-of-day instant strictly after `now`
+fn next_eod(now: u64, eod_time_utc_secs: u32) -> u64 {
+    let eod_offset = u64::from(eod_time_utc_secs) * 1_000 % MILLIS_PER_DAY;
+    let today_eod = now / MILLIS_PER_DAY * MILLIS_PER_DAY + eod_offset;
+    if today_eod > now {
+        today_eod
+    } else {
+        today_eod + MILLIS_PER_DAY
+    }
+}
+
+fn is_weekend(days_since_epoch: u64) -> bool {
+    // 1970-01-01 was a Thursday
+    matches!((days_since_epoch + 3) % 7, 5 | 6)
+}
+
+/// Formats days since the Unix epoch as YYYY-MM-DD
+fn format_date(days_since_epoch: u64) -> String {
+    // Civil-from-days conversion (Howard Hinnant)
+    let z = days_since_epoch as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn escape_csv(value: &str, delimiter: &str) -> String {
+    if value.contains(delimiter) || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        // 2024-02-29 is 19782 days after the epoch
+        assert_eq!(format_date(19_782), "2024-02-29");
+    }
+
+    #[test]
+    fn test_settlement_skips_weekends() {
+        let ledger = ClearingLedger::new(ClearingConfig::default(), 0);
+        // Thursday 1970-01-01 settles T+2 on Monday 1970-01-05
+        assert_eq!(format_date(ledger.settlement_day(0)), "1970-01-05");
+    }
+
+    #[test]
+    fn test_next_eod() {
+        let eod = 22 * 3_600;
+        assert_eq!(next_eod(0, eod), 22 * 3_600_000);
+        assert_eq!(
+            next_eod(22 * 3_600_000, eod),
+            MILLIS_PER_DAY + 22 * 3_600_000
+        );
+    }
+}
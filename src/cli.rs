@@ -0,0 +1,133 @@
+// src/cli.rs
+use crate::config::app::{CONFIG_PATH_ENV, ConfigOverrides, LogFormat, LogLevel};
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Matching engine consuming order commands from Kafka
+///
+/// Settings are read from `ORDERBOOK_` environment variables, with nested keys
+/// separated by `__` (`ORDERBOOK_KAFKA__BROKERS`), then from the config file,
+/// then from the flags below; each overrides the one before.
+#[derive(Debug, Parser)]
+#[command(name = "orderbook", version)]
+pub struct Cli {
+    /// Config file, TOML, YAML or JSON by its extension
+    #[arg(short, long, global = true, env = CONFIG_PATH_ENV)]
+    pub config: Option<String>,
+    /// Comma-separated Kafka brokers, overriding `kafka.brokers`
+    #[arg(long, global = true)]
+    pub brokers: Option<String>,
+    /// Most verbose level logged, overriding `log_level`
+    #[arg(long, global = true, value_enum)]
+    pub log_level: Option<LogLevel>,
+    /// How log lines are written, overriding `log_format`
+    #[arg(long, global = true, value_enum)]
+    pub log_format: Option<LogFormat>,
+    /// Port serving metrics on `/metrics`, overriding `metrics_port`
+    #[arg(long, global = true)]
+    pub metrics_port: Option<u16>,
+    /// Where the engine takes its commands from
+    #[arg(long, value_enum, default_value_t = Mode::Run)]
+    pub mode: Mode,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Consume the command topics from the committed offsets, recovering the
+    /// books from the write-ahead log first
+    Run,
+    /// Rebuild the books of every shard from its write-ahead log and report
+    /// them, without connecting to Kafka
+    Replay,
+    /// Consume the command topics from their earliest offsets into empty books,
+    /// under a consumer group of its own and without a write-ahead log
+    Backfill,
+    /// Validate and preview commands against the books without applying them,
+    /// publishing the would-be results to `engine.dry_run.shadow_topic`, e.g.
+    /// to certify a new producer on topics of its own
+    DryRun,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print the JSON Schema of every message
+    Schema,
+    /// Measure a single book under generated load and print the report as JSON
+    BenchBook {
+        /// Commands to apply
+        commands: Option<u64>,
+        /// Orders resting in the book before the run
+        resting_orders: Option<usize>,
+    },
+    /// Drive the engine with generated load instead of Kafka
+    Soak {
+        /// How long to run for
+        seconds: Option<u64>,
+    },
+    /// Check or compact the write-ahead log of every shard
+    Journal {
+        #[arg(value_enum)]
+        action: JournalAction,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum JournalAction {
+    /// Check every segment and snapshot of the log
+    Verify,
+    /// Snapshot the log and delete the segments it covers; needs the engine
+    /// stopped
+    Compact,
+}
+
+impl Cli {
+    /// Config values set by flags
+    pub fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            brokers: self.brokers.clone(),
+            log_level: self.log_level,
+            log_format: self.log_format,
+            metrics_port: self.metrics_port,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_flags_and_subcommands() {
+        let cli = Cli::try_parse_from([
+            "orderbook",
+            "--config=a.toml",
+            "--mode",
+            "backfill",
+            "--brokers",
+            "kafka:9092",
+            "--log-format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(cli.config.as_deref(), Some("a.toml"));
+        assert_eq!(cli.mode, Mode::Backfill);
+        assert!(cli.command.is_none());
+        let overrides = cli.overrides();
+        assert_eq!(overrides.brokers.as_deref(), Some("kafka:9092"));
+        assert_eq!(overrides.log_format, Some(LogFormat::Json));
+        assert_eq!(overrides.log_level, None);
+
+        // Global flags may follow the subcommand
+        let cli = Cli::try_parse_from(["orderbook", "soak", "60", "--config", "b.yaml"]).unwrap();
+        assert_eq!(cli.config.as_deref(), Some("b.yaml"));
+        assert_eq!(cli.mode, Mode::Run);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Soak { seconds: Some(60) })
+        ));
+
+        assert!(Cli::try_parse_from(["orderbook", "journal", "rotate"]).is_err());
+        assert!(Cli::try_parse_from(["orderbook", "--log-level", "loud"]).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+// src/client_orders.rs
+use crate::helpers::EngineCommand;
+use crate::order_responses::{RejectReason, Rejection};
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::tags::OrderTags;
+use pricelevel::OrderId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// A registered client order id, as journal snapshots store it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientOrder {
+    pub participant_id: String,
+    pub client_order_id: String,
+    pub instrument_id: String,
+    pub order_id: u64,
+}
+
+/// Client order ids of resting orders, unique per participant
+///
+/// Upstream OMSes key cancels and modifies on their own ids. An order entered
+/// with a `client_order_id` is registered under its participant once it rests,
+/// and later commands naming that id are resolved to the engine's order id.
+/// Orders without a participant share one namespace. An id becomes free again
+/// once its order stops resting, however that happens.
+#[derive(Default)]
+pub struct ClientOrderIds {
+    /// (participant, client order id) to (instrument, order id as received)
+    orders: HashMap<(String, String), (String, u64)>,
+    /// The reverse, to forget an order given its engine id
+    client_ids: HashMap<(String, OrderId), (String, String)>,
+}
+
+impl ClientOrderIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the client order id of an order that rests in the book
+    pub fn register(
+        &mut self,
+        participant_id: Option<&str>,
+        client_order_id: &str,
+        instrument_id: &str,
+        order_id: u64,
+    ) {
+        let key = (
+            participant_id.unwrap_or_default().to_string(),
+            client_order_id.to_string(),
+        );
+        if let Some((instrument_id, order_id)) = self
+            .orders
+            .insert(key.clone(), (instrument_id.to_string(), order_id))
+        {
+            self.client_ids
+                .remove(&(instrument_id, OrderId::from_u64(order_id)));
+        }
+        let order = (instrument_id.to_string(), OrderId::from_u64(order_id));
+        if let Some(previous) = self.client_ids.insert(order, key.clone())
+            && previous != key
+        {
+            self.orders.remove(&previous);
+        }
+    }
+
+    /// The resting order a participant knows by `client_order_id`, forgetting
+    /// it if it no longer rests
+    pub fn lookup(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        participant_id: Option<&str>,
+        client_order_id: &str,
+    ) -> Option<(String, u64)> {
+        let key = (
+            participant_id.unwrap_or_default().to_string(),
+            client_order_id.to_string(),
+        );
+        let (instrument_id, order_id) = self.orders.get(&key)?.clone();
+        let resting = manager
+            .get_book(&instrument_id)
+            .is_some_and(|book| book.get_order(OrderId::from_u64(order_id)).is_some());
+        if !resting {
+            self.order_done(&instrument_id, OrderId::from_u64(order_id));
+            return None;
+        }
+        Some((instrument_id, order_id))
+    }
+
+    /// Instrument of the order a participant registered `client_order_id` for,
+    /// without checking that it still rests
+    pub fn instrument_of(
+        &self,
+        participant_id: Option<&str>,
+        client_order_id: &str,
+    ) -> Option<&str> {
+        let key = (
+            participant_id.unwrap_or_default().to_string(),
+            client_order_id.to_string(),
+        );
+        self.orders
+            .get(&key)
+            .map(|(instrument_id, _)| instrument_id.as_str())
+    }
+
+    /// Forgets an order that filled or was cancelled
+    pub fn order_done(&mut self, instrument_id: &str, order_id: OrderId) {
+        if let Some(key) = self
+            .client_ids
+            .remove(&(instrument_id.to_string(), order_id))
+        {
+            self.orders.remove(&key);
+        }
+    }
+
+    /// Every registered id, in no particular order
+    pub fn entries(&self) -> Vec<ClientOrder> {
+        self.orders
+            .iter()
+            .map(
+                |((participant_id, client_order_id), (instrument_id, order_id))| ClientOrder {
+                    participant_id: participant_id.clone(),
+                    client_order_id: client_order_id.clone(),
+                    instrument_id: instrument_id.clone(),
+                    order_id: *order_id,
+                },
+            )
+            .collect()
+    }
+
+    /// Registers ids taken from `entries`
+    pub fn restore(&mut self, entries: Vec<ClientOrder>) {
+        for entry in entries {
+            self.register(
+                Some(&entry.participant_id),
+                &entry.client_order_id,
+                &entry.instrument_id,
+                entry.order_id,
+            );
+        }
+    }
+
+    /// The client order id of a resting order, if it was entered with one
+    pub fn client_order_id(&self, instrument_id: &str, order_id: OrderId) -> Option<String> {
+        self.client_ids
+            .get(&(instrument_id.to_string(), order_id))
+            .map(|(_, client_order_id)| client_order_id.clone())
+    }
+
+    /// Fills in the order id of cancels, modifies and replaces naming a client
+    /// order id, and rejects orders reusing the id of one still resting
+    pub fn resolve(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        mut cmd: EngineCommand,
+    ) -> Result<EngineCommand, Rejection> {
+        match &mut cmd {
+            EngineCommand::OrderCreate(order) => {
+                if let Some(client_order_id) = &order.client_order_id {
+                    self.ensure_free(
+                        manager,
+                        order.participant_id.as_deref(),
+                        client_order_id,
+                        None,
+                    )?;
+                }
+            }
+            EngineCommand::OrderCancel(order) => self.resolve_order_id(
+                manager,
+                &order.instrument_id,
+                order.participant_id.as_deref(),
+                order.client_order_id.as_deref(),
+                &mut order.order_id,
+            )?,
+            EngineCommand::OrderModify(order) => self.resolve_order_id(
+                manager,
+                &order.instrument_id,
+                order.participant_id.as_deref(),
+                order.client_order_id.as_deref(),
+                &mut order.order_id,
+            )?,
+            EngineCommand::OrderReplace(replace) => {
+                self.resolve_order_id(
+                    manager,
+                    &replace.instrument_id,
+                    replace.participant_id.as_deref(),
+                    replace.client_order_id.as_deref(),
+                    &mut replace.order_id,
+                )?;
+                if let Some(client_order_id) = &replace.new_client_order_id {
+                    let replaced = (replace.instrument_id.as_str(), replace.order_id);
+                    self.ensure_free(
+                        manager,
+                        replace.participant_id.as_deref(),
+                        client_order_id,
+                        Some(replaced),
+                    )?;
+                }
+            }
+            _ => {}
+        }
+        Ok(cmd)
+    }
+
+    /// Sets `order_id` to the resting order known by `client_order_id`, if given
+    fn resolve_order_id(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        instrument_id: &str,
+        participant_id: Option<&str>,
+        client_order_id: Option<&str>,
+        order_id: &mut u64,
+    ) -> Result<(), Rejection> {
+        let Some(client_order_id) = client_order_id else {
+            return Ok(());
+        };
+        match self.lookup(manager, participant_id, client_order_id) {
+            Some((resting_on, resting_id)) if resting_on == instrument_id => {
+                *order_id = resting_id;
+                Ok(())
+            }
+            _ => {
+                warn!(
+                    "No resting order with client order id {} of {} on {}",
+                    client_order_id,
+                    participant_id.unwrap_or("unknown participant"),
+                    instrument_id
+                );
+                Err(Rejection::new(
+                    RejectReason::UnknownOrder,
+                    format!("no resting order with client order id {client_order_id}"),
+                ))
+            }
+        }
+    }
+
+    /// Rejects a client order id in use by a resting order other than the
+    /// `replaced` one, as (instrument, order id)
+    fn ensure_free(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        participant_id: Option<&str>,
+        client_order_id: &str,
+        replaced: Option<(&str, u64)>,
+    ) -> Result<(), Rejection> {
+        let Some((instrument_id, order_id)) = self.lookup(manager, participant_id, client_order_id)
+        else {
+            return Ok(());
+        };
+        if replaced == Some((instrument_id.as_str(), order_id)) {
+            return Ok(());
+        }
+        warn!(
+            "Rejecting client order id {} of {}: it is in use by order {} on {}",
+            client_order_id,
+            participant_id.unwrap_or("unknown participant"),
+            order_id,
+            instrument_id
+        );
+        Err(Rejection::new(
+            RejectReason::DuplicateClientOrderId,
+            format!("client order id {client_order_id} is in use by order {order_id}"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::types::OrderType;
+    use crate::helpers::{
+        OrderCancelPayload, OrderCreatePayload, OrderModifyPayload, OrderReplacePayload,
+    };
+    use pricelevel::{Side, TimeInForce};
+
+    fn create(order_id: u64, participant_id: &str, client_order_id: &str) -> EngineCommand {
+        EngineCommand::OrderCreate(OrderCreatePayload {
+            order_id,
+            instrument_id: "BTC".to_string(),
+            quantity: 5,
+            price: 100,
+            side: Side::Buy,
+            time_in_force: TimeInForce::Gtc,
+            order_type: OrderType::LIMIT,
+            participant_id: Some(participant_id.to_string()),
+            oms_id: None,
+            client_order_id: Some(client_order_id.to_string()),
+            tags: OrderTags::new(),
+            visible_quantity: None,
+            hidden_quantity: None,
+            liquidation: false,
+            min_quantity: None,
+            all_or_none: false,
+            sweep_limit: None,
+            sequence: None,
+        })
+    }
+
+    fn cancel(participant_id: &str, client_order_id: &str) -> EngineCommand {
+        EngineCommand::OrderCancel(OrderCancelPayload {
+            order_id: 0,
+            instrument_id: "BTC".to_string(),
+            client_order_id: Some(client_order_id.to_string()),
+            participant_id: Some(participant_id.to_string()),
+            sequence: None,
+        })
+    }
+
+    fn rest(
+        manager: &BookManagerStd<OrderTags>,
+        ids: &mut ClientOrderIds,
+        order_id: u64,
+        participant_id: &str,
+        client_order_id: &str,
+    ) {
+        manager
+            .get_book("BTC")
+            .unwrap()
+            .add_limit_order(
+                OrderId::from_u64(order_id),
+                100,
+                5,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        ids.register(Some(participant_id), client_order_id, "BTC", order_id);
+    }
+
+    #[test]
+    fn test_cancels_and_modifies_resolve_per_participant() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let mut ids = ClientOrderIds::new();
+        rest(&manager, &mut ids, 1, "desk-a", "abc");
+        rest(&manager, &mut ids, 2, "desk-b", "abc");
+
+        let Ok(EngineCommand::OrderCancel(resolved)) =
+            ids.resolve(&manager, cancel("desk-b", "abc"))
+        else {
+            panic!("cancel did not resolve");
+        };
+        assert_eq!(resolved.order_id, 2);
+
+        let modify = EngineCommand::OrderModify(OrderModifyPayload {
+            instrument_id: "BTC".to_string(),
+            order_id: 0,
+            price: 101,
+            quantity: 5,
+            client_order_id: Some("abc".to_string()),
+            participant_id: Some("desk-a".to_string()),
+            sequence: None,
+        });
+        let Ok(EngineCommand::OrderModify(resolved)) = ids.resolve(&manager, modify) else {
+            panic!("modify did not resolve");
+        };
+        assert_eq!(resolved.order_id, 1);
+
+        assert_eq!(
+            ids.resolve(&manager, cancel("desk-c", "abc"))
+                .unwrap_err()
+                .reason,
+            RejectReason::UnknownOrder
+        );
+        // Commands naming the engine's id pass through untouched
+        let by_order_id = EngineCommand::OrderCancel(OrderCancelPayload {
+            order_id: 7,
+            instrument_id: "BTC".to_string(),
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        });
+        assert!(ids.resolve(&manager, by_order_id).is_ok());
+    }
+
+    #[test]
+    fn test_client_order_ids_are_unique_among_resting_orders() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let mut ids = ClientOrderIds::new();
+        rest(&manager, &mut ids, 1, "desk-a", "abc");
+
+        assert_eq!(
+            ids.resolve(&manager, create(2, "desk-a", "abc"))
+                .unwrap_err()
+                .reason,
+            RejectReason::DuplicateClientOrderId
+        );
+        assert!(ids.resolve(&manager, create(2, "desk-b", "abc")).is_ok());
+
+        // Once the order stops resting its id may be used again
+        manager
+            .get_book("BTC")
+            .unwrap()
+            .cancel_order(OrderId::from_u64(1))
+            .unwrap();
+        assert!(ids.resolve(&manager, create(3, "desk-a", "abc")).is_ok());
+        assert!(ids.orders.is_empty() && ids.client_ids.is_empty());
+    }
+
+    #[test]
+    fn test_replaces_resolve_and_may_keep_their_client_order_id() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let mut ids = ClientOrderIds::new();
+        rest(&manager, &mut ids, 1, "desk-a", "abc");
+        rest(&manager, &mut ids, 2, "desk-a", "xyz");
+        assert_eq!(
+            ids.client_order_id("BTC", OrderId::from_u64(2)).as_deref(),
+            Some("xyz")
+        );
+        let replace = |new_client_order_id: &str| {
+            EngineCommand::OrderReplace(OrderReplacePayload {
+                instrument_id: "BTC".to_string(),
+                order_id: 0,
+                client_order_id: Some("abc".to_string()),
+                participant_id: Some("desk-a".to_string()),
+                new_client_order_id: Some(new_client_order_id.to_string()),
+                price: 101,
+                quantity: 5,
+                side: None,
+                time_in_force: None,
+                sequence: None,
+            })
+        };
+
+        let Ok(EngineCommand::OrderReplace(resolved)) = ids.resolve(&manager, replace("abc"))
+        else {
+            panic!("replace did not resolve");
+        };
+        assert_eq!(resolved.order_id, 1);
+        assert!(ids.resolve(&manager, replace("abd")).is_ok());
+        assert_eq!(
+            ids.resolve(&manager, replace("xyz")).unwrap_err().reason,
+            RejectReason::DuplicateClientOrderId
+        );
+    }
+}
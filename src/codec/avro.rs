@@ -0,0 +1,569 @@
+// src/codec/avro.rs
+//! Decoding of Avro messages framed for the Confluent Schema Registry
+//!
+//! Each message starts with a zero magic byte and the big-endian id of the
+//! schema it was written with, which is fetched from the registry on first
+//! sight and cached. Records are read into JSON values and mapped onto the
+//! payload structs the way JSON payloads are, so Avro field names and enum
+//! symbols follow the JSON ones. Logical types are read as their underlying
+//! type.
+
+use super::registry::SchemaRegistry;
+use super::{CodecError, PayloadCodec};
+use crate::config::topics::CommandKind;
+use crate::helpers::EngineCommand;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const MAGIC_BYTE: u8 = 0;
+
+/// How long a schema the registry could not provide is not asked for again;
+/// messages written with it are dead-lettered meanwhile
+const RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// Why an Avro payload could not be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvroError {
+    /// The payload does not start with the magic byte and a schema id
+    Framing,
+    /// The writer schema could not be fetched from the registry
+    Unavailable { id: u32, reason: String },
+    /// The writer schema is not a valid Avro schema, or uses named types
+    /// recursively
+    Schema { id: u32, reason: String },
+    /// The payload does not match its writer schema
+    Malformed { id: u32 },
+}
+
+impl fmt::Display for AvroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AvroError::Framing => write!(f, "payload lacks the schema registry framing"),
+            AvroError::Unavailable { id, reason } => {
+                write!(f, "Avro schema {id} is unavailable: {reason}")
+            }
+            AvroError::Schema { id, reason } => write!(f, "Avro schema {id} is unusable: {reason}"),
+            AvroError::Malformed { id } => {
+                write!(f, "payload does not match Avro schema {id}")
+            }
+        }
+    }
+}
+
+/// A parsed writer schema; references to named types are resolved by copying
+/// the named type in
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Record(Vec<(String, Schema)>),
+    Enum(Vec<String>),
+    Array(Box<Schema>),
+    Map(Box<Schema>),
+    Union(Vec<Schema>),
+    Fixed(usize),
+}
+
+impl Schema {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        Parser::default().parse(&value, "")
+    }
+}
+
+/// Named types defined so far, by full name
+#[derive(Default)]
+struct Parser {
+    names: HashMap<String, Schema>,
+}
+
+fn full_name(name: &str, namespace: &str) -> String {
+    if name.contains('.') || namespace.is_empty() {
+        name.to_string()
+    } else {
+        format!("{namespace}.{name}")
+    }
+}
+
+impl Parser {
+    fn parse(&mut self, schema: &Value, namespace: &str) -> Result<Schema, String> {
+        match schema {
+            Value::String(name) => self.reference(name, namespace),
+            Value::Array(branches) => branches
+                .iter()
+                .map(|branch| self.parse(branch, namespace))
+                .collect::<Result<_, _>>()
+                .map(Schema::Union),
+            Value::Object(object) => self.complex(object, namespace),
+            other => Err(format!("{other} is not a schema")),
+        }
+    }
+
+    fn reference(&self, name: &str, namespace: &str) -> Result<Schema, String> {
+        Ok(match name {
+            "null" => Schema::Null,
+            "boolean" => Schema::Boolean,
+            "int" => Schema::Int,
+            "long" => Schema::Long,
+            "float" => Schema::Float,
+            "double" => Schema::Double,
+            "bytes" => Schema::Bytes,
+            "string" => Schema::String,
+            name => self
+                .names
+                .get(&full_name(name, namespace))
+                .or_else(|| self.names.get(name))
+                .cloned()
+                .ok_or_else(|| format!("unknown or recursive type {name}"))?,
+        })
+    }
+
+    fn complex(&mut self, object: &Map<String, Value>, namespace: &str) -> Result<Schema, String> {
+        let kind = match object.get("type") {
+            Some(Value::String(kind)) => kind.as_str(),
+            Some(nested) => return self.parse(nested, namespace),
+            None => return Err("schema object without a type".to_string()),
+        };
+        let name = object.get("name").and_then(Value::as_str).map(|name| {
+            let namespace = object
+                .get("namespace")
+                .and_then(Value::as_str)
+                .unwrap_or(namespace);
+            full_name(name, namespace)
+        });
+        // Names within a named type are relative to its namespace
+        let inner = name
+            .as_deref()
+            .and_then(|name| name.rsplit_once('.'))
+            .map_or(namespace, |(namespace, _)| namespace)
+            .to_string();
+        let attribute = |key: &str| {
+            object
+                .get(key)
+                .ok_or_else(|| format!("{kind} schema without {key}"))
+        };
+        let schema = match kind {
+            "record" | "error" => {
+                let fields = attribute("fields")?
+                    .as_array()
+                    .ok_or("record fields are not an array")?;
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        let name = field
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .ok_or("record field without a name")?;
+                        let schema = field
+                            .get("type")
+                            .ok_or_else(|| format!("field {name} without a type"))?;
+                        Ok((name.to_string(), self.parse(schema, &inner)?))
+                    })
+                    .collect::<Result<_, String>>()?;
+                Schema::Record(fields)
+            }
+            "enum" => Schema::Enum(
+                attribute("symbols")?
+                    .as_array()
+                    .ok_or("enum symbols are not an array")?
+                    .iter()
+                    .map(|symbol| symbol.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or("enum symbols are not strings")?,
+            ),
+            "array" => Schema::Array(Box::new(self.parse(attribute("items")?, &inner)?)),
+            "map" => Schema::Map(Box::new(self.parse(attribute("values")?, &inner)?)),
+            "fixed" => Schema::Fixed(
+                attribute("size")?
+                    .as_u64()
+                    .ok_or("fixed size is not a count")? as usize,
+            ),
+            // A primitive, possibly with a logical type
+            primitive => return self.reference(primitive, namespace),
+        };
+        if let Some(name) = name {
+            self.names.insert(name, schema.clone());
+        }
+        Ok(schema)
+    }
+}
+
+/// Reads the Avro binary encoding
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    /// A zigzag varint, the encoding of both `int` and `long`
+    fn long(&mut self) -> Option<i64> {
+        let mut value = 0_u64;
+        for shift in (0..70).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+        }
+        None
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        usize::try_from(self.long()?).ok()
+    }
+
+    fn float(&mut self) -> Option<Value> {
+        let bytes = self.take(4)?.try_into().ok()?;
+        Some(number(f64::from(f32::from_le_bytes(bytes))))
+    }
+
+    fn double(&mut self) -> Option<Value> {
+        let bytes = self.take(8)?.try_into().ok()?;
+        Some(number(f64::from_le_bytes(bytes)))
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<Value> {
+        Some(Value::Array(
+            self.take(len)?.iter().map(|&byte| byte.into()).collect(),
+        ))
+    }
+
+    /// Items of an array or map, which come in blocks ended by an empty one
+    fn blocks(&mut self, mut item: impl FnMut(&mut Self) -> Option<()>) -> Option<()> {
+        loop {
+            let count = self.long()?;
+            if count == 0 {
+                return Some(());
+            }
+            if count < 0 {
+                // Followed by the block's size in bytes
+                self.long()?;
+            }
+            for _ in 0..count.unsigned_abs() {
+                item(self)?;
+            }
+        }
+    }
+
+    fn read(&mut self, schema: &Schema) -> Option<Value> {
+        Some(match schema {
+            Schema::Null => Value::Null,
+            Schema::Boolean => match self.take(1)?[0] {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                _ => return None,
+            },
+            Schema::Int | Schema::Long => self.long()?.into(),
+            Schema::Float => self.float()?,
+            Schema::Double => self.double()?,
+            Schema::Bytes => {
+                let len = self.len()?;
+                self.bytes(len)?
+            }
+            Schema::String => {
+                let len = self.len()?;
+                std::str::from_utf8(self.take(len)?).ok()?.into()
+            }
+            Schema::Record(fields) => {
+                let mut record = Map::new();
+                for (name, schema) in fields {
+                    record.insert(name.clone(), self.read(schema)?);
+                }
+                Value::Object(record)
+            }
+            Schema::Enum(symbols) => symbols.get(self.len()?)?.as_str().into(),
+            Schema::Array(items) => {
+                let mut array = Vec::new();
+                self.blocks(|reader| {
+                    array.push(reader.read(items)?);
+                    Some(())
+                })?;
+                Value::Array(array)
+            }
+            Schema::Map(values) => {
+                let mut map = Map::new();
+                self.blocks(|reader| {
+                    let key = reader.read(&Schema::String)?;
+                    map.insert(key.as_str()?.to_string(), reader.read(values)?);
+                    Some(())
+                })?;
+                Value::Object(map)
+            }
+            Schema::Union(branches) => {
+                let branch = self.len()?;
+                self.read(branches.get(branch)?)?
+            }
+            Schema::Fixed(size) => self.bytes(*size)?,
+        })
+    }
+}
+
+/// Non-finite numbers have no JSON representation and read as null
+fn number(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// The schema id and Avro body of a framed payload
+fn frame(payload: &[u8]) -> Result<(u32, &[u8]), AvroError> {
+    match payload {
+        [MAGIC_BYTE, a, b, c, d, body @ ..] => Ok((u32::from_be_bytes([*a, *b, *c, *d]), body)),
+        _ => Err(AvroError::Framing),
+    }
+}
+
+/// Avro payloads, decoded with the writer schemas of the registry
+///
+/// Schemas are fetched by [`AvroCodec::resolve`] ahead of decoding, as decoding
+/// itself cannot wait on the registry. [`AvroCodec::writer_schema`] hands out
+/// the schema of a payload to decode it elsewhere with [`decode_with`].
+pub struct AvroCodec {
+    registry: Option<SchemaRegistry>,
+    /// Schemas by id, or why they cannot be used
+    schemas: HashMap<u32, Result<Arc<Schema>, String>>,
+    /// Schemas the registry failed to provide, with the time of the next
+    /// attempt and the failure
+    unavailable: HashMap<u32, (Instant, String)>,
+}
+
+impl AvroCodec {
+    pub fn new(registry: Option<SchemaRegistry>) -> Self {
+        Self {
+            registry,
+            schemas: HashMap::new(),
+            unavailable: HashMap::new(),
+        }
+    }
+
+    /// Fetches the writer schema of a payload unless it is known, or was
+    /// unavailable too recently to ask again
+    pub async fn resolve(&mut self, payload: &[u8]) {
+        let Ok((id, _)) = frame(payload) else {
+            return;
+        };
+        if self.schemas.contains_key(&id)
+            || self
+                .unavailable
+                .get(&id)
+                .is_some_and(|(retry_at, _)| Instant::now() < *retry_at)
+        {
+            return;
+        }
+        let Some(registry) = &self.registry else {
+            return;
+        };
+        match registry.fetch(id).await {
+            Ok(schema) => {
+                let schema = Schema::parse(&schema).map(Arc::new);
+                match &schema {
+                    Ok(_) => info!("Fetched Avro schema {} from the schema registry", id),
+                    Err(e) => warn!("Avro schema {} is unusable: {}", id, e),
+                }
+                self.unavailable.remove(&id);
+                self.schemas.insert(id, schema);
+            }
+            Err(reason) => {
+                warn!("Failed to fetch Avro schema {}: {}", id, reason);
+                self.unavailable
+                    .insert(id, (Instant::now() + RETRY_AFTER, reason));
+            }
+        }
+    }
+
+    fn schema(&self, id: u32) -> Result<&Arc<Schema>, AvroError> {
+        match self.schemas.get(&id) {
+            Some(Ok(schema)) => Ok(schema),
+            Some(Err(reason)) => Err(AvroError::Schema {
+                id,
+                reason: reason.clone(),
+            }),
+            None => Err(AvroError::Unavailable {
+                id,
+                reason: match (&self.registry, self.unavailable.get(&id)) {
+                    (None, _) => "no schema registry is configured".to_string(),
+                    (Some(_), Some((_, reason))) => reason.clone(),
+                    (Some(_), None) => "not fetched yet".to_string(),
+                },
+            }),
+        }
+    }
+
+    /// The writer schema of a payload as resolved so far
+    pub fn writer_schema(&self, payload: &[u8]) -> Result<Arc<Schema>, AvroError> {
+        let (id, _) = frame(payload)?;
+        self.schema(id).cloned()
+    }
+}
+
+impl PayloadCodec for AvroCodec {
+    fn decode(
+        &self,
+        kind: CommandKind,
+        payload: &[u8],
+    ) -> Result<Option<EngineCommand>, CodecError> {
+        decode_with(self.writer_schema(payload), kind, payload)
+    }
+}
+
+/// Decodes a payload with the writer schema [`AvroCodec::writer_schema`] gave
+pub fn decode_with(
+    schema: Result<Arc<Schema>, AvroError>,
+    kind: CommandKind,
+    payload: &[u8],
+) -> Result<Option<EngineCommand>, CodecError> {
+    if kind == CommandKind::Alert {
+        return Ok(None);
+    }
+    let (id, body) = frame(payload).map_err(CodecError::Avro)?;
+    let schema = schema.map_err(CodecError::Avro)?;
+    let mut reader = Reader { bytes: body };
+    let value = reader
+        .read(&schema)
+        .filter(|_| reader.bytes.is_empty())
+        .ok_or(CodecError::Avro(AvroError::Malformed { id }))?;
+    EngineCommand::from_value(kind, value).map_err(CodecError::Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER_CANCEL: &str = r#"{
+        "type": "record",
+        "name": "OrderCancel",
+        "namespace": "orderbook.commands",
+        "fields": [
+            {"name": "order_id", "type": "long"},
+            {"name": "instrument_id", "type": "string"}
+        ]
+    }"#;
+
+    fn zigzag(value: i64) -> Vec<u8> {
+        let mut value = ((value << 1) ^ (value >> 63)) as u64;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        [zigzag(value.len() as i64), value.as_bytes().to_vec()].concat()
+    }
+
+    fn framed(id: u32, body: &[u8]) -> Vec<u8> {
+        [&[MAGIC_BYTE][..], &id.to_be_bytes(), body].concat()
+    }
+
+    fn codec_with(id: u32, schema: &str) -> AvroCodec {
+        let mut codec = AvroCodec::new(None);
+        codec
+            .schemas
+            .insert(id, Schema::parse(schema).map(Arc::new));
+        codec
+    }
+
+    #[test]
+    fn test_records_map_onto_payloads() {
+        let codec = codec_with(7, ORDER_CANCEL);
+        let payload = framed(7, &[zigzag(300), string("BTC")].concat());
+        let cancel = codec.decode(CommandKind::OrderCancel, &payload).unwrap();
+        let Some(EngineCommand::OrderCancel(cancel)) = cancel else {
+            panic!("expected a cancel, got {cancel:?}");
+        };
+        assert_eq!(
+            (cancel.order_id, cancel.instrument_id.as_str()),
+            (300, "BTC")
+        );
+
+        // Trailing bytes mean the payload was written with another schema
+        let mut trailing = payload.clone();
+        trailing.push(0);
+        assert!(matches!(
+            codec.decode(CommandKind::OrderCancel, &trailing),
+            Err(CodecError::Avro(AvroError::Malformed { id: 7 }))
+        ));
+        assert!(matches!(
+            codec.decode(CommandKind::OrderCancel, &framed(8, &payload[5..])),
+            Err(CodecError::Avro(AvroError::Unavailable { id: 8, .. }))
+        ));
+        assert!(matches!(
+            codec.decode(CommandKind::OrderCancel, b"{}"),
+            Err(CodecError::Avro(AvroError::Framing))
+        ));
+    }
+
+    #[test]
+    fn test_unions_enums_and_named_types() {
+        let schema = r#"{
+            "type": "record",
+            "name": "OrderCreate",
+            "namespace": "orderbook.commands",
+            "fields": [
+                {"name": "order_id", "type": "long"},
+                {"name": "instrument_id", "type": "string"},
+                {"name": "quantity", "type": "long"},
+                {"name": "price", "type": {"type": "long", "logicalType": "price"}},
+                {"name": "side", "type": {"type": "enum", "name": "Side", "symbols": ["BUY", "SELL"]}},
+                {"name": "participant_id", "type": ["null", "string"], "default": null},
+                {"name": "tags", "type": {"type": "map", "values": "Side"}}
+            ]
+        }"#;
+        let parsed = Schema::parse(schema).unwrap();
+        let Schema::Record(fields) = &parsed else {
+            panic!("expected a record, got {parsed:?}");
+        };
+        // The reference to Side resolves within the record's namespace
+        assert_eq!(
+            fields[6].1,
+            Schema::Map(Box::new(Schema::Enum(vec![
+                "BUY".to_string(),
+                "SELL".to_string()
+            ])))
+        );
+
+        let body = [
+            zigzag(1),
+            string("BTC"),
+            zigzag(5),
+            zigzag(100),
+            zigzag(1),
+            zigzag(1),
+            string("desk-1"),
+            // A block of one entry given with its byte size, then the end
+            zigzag(-1),
+            zigzag(6),
+            string("hedge"),
+            zigzag(0),
+            zigzag(0),
+        ]
+        .concat();
+        let value = Reader { bytes: &body }.read(&parsed).unwrap();
+        assert_eq!(value["side"], "SELL");
+        assert_eq!(value["participant_id"], "desk-1");
+        assert_eq!(value["tags"]["hedge"], "BUY");
+        assert!(Schema::parse(r#"{"type": "record", "name": "Node", "fields": [{"name": "next", "type": "Node"}]}"#).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+// src/codec/mod.rs
+pub mod avro;
+pub mod protobuf;
+pub mod registry;
+
+use crate::config::kafka::KafkaConfig;
+use crate::config::topics::{CommandKind, PayloadFormat};
+use crate::helpers::EngineCommand;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+pub use avro::AvroCodec;
+pub use protobuf::ProtobufCodec;
+use registry::SchemaRegistry;
+
+/// Why an inbound payload could not be decoded
+#[derive(Debug)]
+pub enum CodecError {
+    Utf8(std::str::Utf8Error),
+    Json(serde_json::Error),
+    Protobuf(protobuf::DecodeError),
+    Avro(avro::AvroError),
+    /// The codec has no encoding for the command
+    Unsupported {
+        format: PayloadFormat,
+        kind: CommandKind,
+    },
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Utf8(e) => write!(f, "payload is not UTF-8: {e}"),
+            CodecError::Json(e) => write!(f, "{e}"),
+            CodecError::Protobuf(e) => write!(f, "{e}"),
+            CodecError::Avro(e) => write!(f, "{e}"),
+            CodecError::Unsupported { format, kind } => {
+                write!(f, "{kind:?} commands have no {format:?} encoding")
+            }
+        }
+    }
+}
+
+/// Decodes the messages of an inbound topic into engine commands
+pub trait PayloadCodec: Send + Sync {
+    /// Decodes a message received on the topic of `kind`; alerts are not engine
+    /// commands
+    fn decode(
+        &self,
+        kind: CommandKind,
+        payload: &[u8],
+    ) -> Result<Option<EngineCommand>, CodecError>;
+}
+
+/// The JSON payloads documented by `schema`
+pub struct JsonCodec;
+
+impl PayloadCodec for JsonCodec {
+    fn decode(
+        &self,
+        kind: CommandKind,
+        payload: &[u8],
+    ) -> Result<Option<EngineCommand>, CodecError> {
+        let payload = std::str::from_utf8(payload).map_err(CodecError::Utf8)?;
+        EngineCommand::parse(kind, payload).map_err(CodecError::Json)
+    }
+}
+
+/// The codec of one message, holding what it needs to decode the message away
+/// from the consumer
+pub enum Decoder {
+    Json,
+    Protobuf,
+    /// The writer schema of the message, or why it cannot be had
+    Avro(Result<Arc<avro::Schema>, avro::AvroError>),
+}
+
+impl Decoder {
+    pub fn decode(
+        self,
+        kind: CommandKind,
+        payload: &[u8],
+    ) -> Result<Option<EngineCommand>, CodecError> {
+        match self {
+            Decoder::Json => JsonCodec.decode(kind, payload),
+            Decoder::Protobuf => ProtobufCodec.decode(kind, payload),
+            Decoder::Avro(schema) => avro::decode_with(schema, kind, payload),
+        }
+    }
+}
+
+/// The codec of each inbound topic
+pub struct Codecs {
+    formats: HashMap<CommandKind, PayloadFormat>,
+    avro: AvroCodec,
+}
+
+impl Codecs {
+    pub fn new(config: &KafkaConfig) -> Self {
+        let formats = config
+            .topics
+            .commands()
+            .into_iter()
+            .filter_map(|(kind, topic)| Some((kind, *config.payload_formats.get(topic)?)))
+            .collect();
+        // An unusable URL fails config validation
+        let registry = config
+            .schema_registry_url
+            .as_deref()
+            .and_then(|url| SchemaRegistry::new(url).ok());
+        Self {
+            formats,
+            avro: AvroCodec::new(registry),
+        }
+    }
+
+    pub fn format(&self, kind: CommandKind) -> PayloadFormat {
+        self.formats.get(&kind).copied().unwrap_or_default()
+    }
+
+    /// The decoder of a message, first fetching its Avro writer schema if
+    /// needed
+    pub async fn decoder(&mut self, kind: CommandKind, payload: &[u8]) -> Decoder {
+        match self.format(kind) {
+            PayloadFormat::Json => Decoder::Json,
+            PayloadFormat::Protobuf => Decoder::Protobuf,
+            PayloadFormat::Avro => {
+                self.avro.resolve(payload).await;
+                Decoder::Avro(self.avro.writer_schema(payload))
+            }
+        }
+    }
+}
+
+/// Problems with `kafka.payload_formats`, for config validation
+pub fn validate(config: &KafkaConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut topics: Vec<&String> = config.payload_formats.keys().collect();
+    topics.sort();
+    for topic in topics {
+        let format = config.payload_formats[topic];
+        match config.topics.kind_of(topic) {
+            None => problems.push(format!(
+                "kafka.payload_formats names {topic}, which is not an inbound topic"
+            )),
+            Some(kind) if format == PayloadFormat::Protobuf && !protobuf::supports(kind) => {
+                problems.push(format!(
+                    "kafka.payload_formats: {kind:?} commands on {topic} have no protobuf encoding"
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+    let uses_avro = config
+        .payload_formats
+        .values()
+        .any(|&format| format == PayloadFormat::Avro);
+    match &config.schema_registry_url {
+        None if uses_avro => problems.push(
+            "kafka.payload_formats names Avro topics but kafka.schema_registry_url is not set"
+                .to_string(),
+        ),
+        Some(url) => {
+            if let Err(e) = SchemaRegistry::new(url) {
+                problems.push(format!("kafka.schema_registry_url: {e}"));
+            }
+        }
+        None => {}
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn decode(
+        codecs: &mut Codecs,
+        kind: CommandKind,
+        payload: &[u8],
+    ) -> Result<Option<EngineCommand>, CodecError> {
+        codecs.decoder(kind, payload).await.decode(kind, payload)
+    }
+
+    #[tokio::test]
+    async fn test_formats_are_chosen_per_topic() {
+        let mut config = KafkaConfig::default();
+        config
+            .payload_formats
+            .insert("order.cancelled".to_string(), PayloadFormat::Protobuf);
+        let mut codecs = Codecs::new(&config);
+        assert_eq!(
+            codecs.format(CommandKind::OrderCancel),
+            PayloadFormat::Protobuf
+        );
+        assert_eq!(codecs.format(CommandKind::OrderCreate), PayloadFormat::Json);
+
+        // order_id 7 on BTC
+        let cancel = decode(
+            &mut codecs,
+            CommandKind::OrderCancel,
+            &[0x08, 0x07, 0x12, 0x03, b'B', b'T', b'C'],
+        )
+        .await
+        .unwrap();
+        let Some(EngineCommand::OrderCancel(cancel)) = cancel else {
+            panic!("expected a cancel, got {cancel:?}");
+        };
+        assert_eq!((cancel.order_id, cancel.instrument_id.as_str()), (7, "BTC"));
+        let modify = decode(
+            &mut codecs,
+            CommandKind::OrderModify,
+            br#"{"instrument_id":"BTC","order_id":7,"price":100,"quantity":5}"#,
+        )
+        .await;
+        assert!(matches!(modify, Ok(Some(EngineCommand::OrderModify(_)))));
+        assert!(matches!(
+            decode(&mut codecs, CommandKind::OrderCreate, &[0xff]).await,
+            Err(CodecError::Utf8(_))
+        ));
+    }
+
+    #[test]
+    fn test_unusable_formats_are_reported() {
+        let mut config = KafkaConfig::default();
+        for topic in ["order.create", "engine.admin", "orders.unknown"] {
+            config
+                .payload_formats
+                .insert(topic.to_string(), PayloadFormat::Protobuf);
+        }
+        config
+            .payload_formats
+            .insert("rfq.quote".to_string(), PayloadFormat::Json);
+        let problems = validate(&config);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("Admin"));
+        assert!(problems[1].contains("orders.unknown"));
+    }
+
+    #[test]
+    fn test_avro_topics_need_a_registry() {
+        let mut config = KafkaConfig::default();
+        config
+            .payload_formats
+            .insert("order.create".to_string(), PayloadFormat::Avro);
+        let problems = validate(&config);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("schema_registry_url"));
+
+        config.schema_registry_url = Some("https://registry:8081".to_string());
+        assert_eq!(validate(&config).len(), 1);
+        config.schema_registry_url = Some("http://registry:8081".to_string());
+        assert!(validate(&config).is_empty());
+    }
+}
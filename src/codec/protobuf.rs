@@ -0,0 +1,1033 @@
+// src/codec/protobuf.rs
+//! Decoding of the protobuf messages of `proto/commands.proto`
+//!
+//! A small reader of the protobuf wire format rather than generated code, as
+//! only a handful of flat messages are accepted. Unknown fields are skipped, so
+//! producers can move to a newer schema first.
+
+use super::{CodecError, PayloadCodec};
+use crate::config::topics::{CommandKind, PayloadFormat};
+use crate::helpers::timestamp;
+use crate::helpers::types::{InstrumentExpiry, KillSwitchAction, OrderType};
+use crate::helpers::{
+    AuctionPayload, DeleteInstrumentPayload, EngineCommand, InstrumentAdjustPayload,
+    InstrumentCreatePayload, KillSwitchPayload, MassCancelPayload, OrderCancelPayload,
+    OrderCreatePayload, OrderModifyPayload, OrderReplacePayload, TradingHaltPayload,
+};
+use crate::orderbook::corporate_action::CorporateAction;
+use crate::orderbook::instrument_spec::InstrumentSpec;
+use crate::orderbook::matching_policy::MatchingPolicy;
+use crate::orderbook::scale::InstrumentScale;
+use crate::orderbook::sweep_limit::{SweepLimit, SweepRemainder};
+use crate::tags::OrderTags;
+use pricelevel::{Side, TimeInForce};
+use std::fmt;
+
+/// Why a protobuf payload could not be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The payload ends inside a field, or a varint runs past ten bytes
+    Malformed {
+        message: &'static str,
+    },
+    /// A field's wire type is not the one of its declaration, or is a group
+    WireType {
+        message: &'static str,
+        field: u32,
+        wire_type: u8,
+    },
+    InvalidUtf8 {
+        message: &'static str,
+        field: u32,
+    },
+    /// A value too large for its field, or a timestamp outside 2000 to 2200
+    OutOfRange {
+        message: &'static str,
+        field: u32,
+    },
+    /// A field the command cannot do without is unset or empty
+    Missing {
+        message: &'static str,
+        field: &'static str,
+    },
+    UnknownEnum {
+        message: &'static str,
+        field: &'static str,
+        value: u64,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Malformed { message } => write!(f, "{message} is truncated or malformed"),
+            DecodeError::WireType {
+                message,
+                field,
+                wire_type,
+            } => write!(
+                f,
+                "{message} field {field} has unexpected wire type {wire_type}"
+            ),
+            DecodeError::InvalidUtf8 { message, field } => {
+                write!(f, "{message} field {field} is not UTF-8")
+            }
+            DecodeError::OutOfRange { message, field } => {
+                write!(f, "{message} field {field} is out of range")
+            }
+            DecodeError::Missing { message, field } => write!(f, "{message}.{field} is not set"),
+            DecodeError::UnknownEnum {
+                message,
+                field,
+                value,
+            } => write!(f, "{message}.{field} has unknown value {value}"),
+        }
+    }
+}
+
+/// Whether commands of `kind` have a protobuf encoding
+pub fn supports(kind: CommandKind) -> bool {
+    matches!(
+        kind,
+        CommandKind::OrderCreate
+            | CommandKind::OrderCancel
+            | CommandKind::OrderModify
+            | CommandKind::OrderReplace
+            | CommandKind::MassCancel
+            | CommandKind::KillSwitch
+            | CommandKind::InstrumentCreate
+            | CommandKind::InstrumentDelete
+            | CommandKind::InstrumentAdjust
+            | CommandKind::Halt
+            | CommandKind::Resume
+            | CommandKind::AuctionStart
+            | CommandKind::AuctionUncross
+    )
+}
+
+/// The messages of `proto/commands.proto`, for order and instrument commands
+pub struct ProtobufCodec;
+
+impl PayloadCodec for ProtobufCodec {
+    fn decode(
+        &self,
+        kind: CommandKind,
+        payload: &[u8],
+    ) -> Result<Option<EngineCommand>, CodecError> {
+        let command = match kind {
+            CommandKind::OrderCreate => order_create(payload).map(EngineCommand::OrderCreate),
+            CommandKind::OrderCancel => order_cancel(payload).map(EngineCommand::OrderCancel),
+            CommandKind::OrderModify => order_modify(payload).map(EngineCommand::OrderModify),
+            CommandKind::OrderReplace => order_replace(payload).map(EngineCommand::OrderReplace),
+            CommandKind::MassCancel => mass_cancel(payload).map(EngineCommand::MassCancel),
+            CommandKind::KillSwitch => kill_switch(payload).map(EngineCommand::KillSwitch),
+            CommandKind::InstrumentCreate => {
+                instrument_create(payload).map(EngineCommand::InstrumentCreate)
+            }
+            CommandKind::InstrumentDelete => {
+                instrument_delete(payload).map(EngineCommand::InstrumentDelete)
+            }
+            CommandKind::InstrumentAdjust => {
+                instrument_adjust(payload).map(EngineCommand::InstrumentAdjust)
+            }
+            CommandKind::Halt => trading_halt(payload).map(EngineCommand::Halt),
+            CommandKind::Resume => trading_halt(payload).map(EngineCommand::Resume),
+            CommandKind::AuctionStart => auction(payload).map(EngineCommand::AuctionStart),
+            CommandKind::AuctionUncross => auction(payload).map(EngineCommand::AuctionUncross),
+            CommandKind::Alert => return Ok(None),
+            kind => {
+                return Err(CodecError::Unsupported {
+                    format: PayloadFormat::Protobuf,
+                    kind,
+                });
+            }
+        };
+        command.map(Some).map_err(CodecError::Protobuf)
+    }
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    /// No field of ours is `fixed32`, so these are only ever skipped
+    Fixed32,
+}
+
+/// A field of a message as read off the wire
+struct Field<'a> {
+    message: &'static str,
+    number: u32,
+    value: Value<'a>,
+}
+
+impl<'a> Field<'a> {
+    fn wire_type_error(&self) -> DecodeError {
+        let wire_type = match self.value {
+            Value::Varint(_) => 0,
+            Value::Fixed64(_) => 1,
+            Value::Bytes(_) => 2,
+            Value::Fixed32 => 5,
+        };
+        DecodeError::WireType {
+            message: self.message,
+            field: self.number,
+            wire_type,
+        }
+    }
+
+    fn uint(&self) -> Result<u64, DecodeError> {
+        match self.value {
+            Value::Varint(value) => Ok(value),
+            _ => Err(self.wire_type_error()),
+        }
+    }
+
+    fn uint32(&self) -> Result<u32, DecodeError> {
+        u32::try_from(self.uint()?).map_err(|_| DecodeError::OutOfRange {
+            message: self.message,
+            field: self.number,
+        })
+    }
+
+    fn usize(&self) -> Result<usize, DecodeError> {
+        usize::try_from(self.uint()?).map_err(|_| DecodeError::OutOfRange {
+            message: self.message,
+            field: self.number,
+        })
+    }
+
+    /// An epoch timestamp in seconds or milliseconds, as Unix milliseconds
+    fn timestamp(&self) -> Result<u64, DecodeError> {
+        timestamp::normalize(self.uint()?).map_err(|_| DecodeError::OutOfRange {
+            message: self.message,
+            field: self.number,
+        })
+    }
+
+    fn bool(&self) -> Result<bool, DecodeError> {
+        Ok(self.uint()? != 0)
+    }
+
+    /// A zigzag-encoded `sint64`
+    fn sint(&self) -> Result<i64, DecodeError> {
+        let value = self.uint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn double(&self) -> Result<f64, DecodeError> {
+        match self.value {
+            Value::Fixed64(bits) => Ok(f64::from_bits(bits)),
+            _ => Err(self.wire_type_error()),
+        }
+    }
+
+    fn bytes(&self) -> Result<&'a [u8], DecodeError> {
+        match self.value {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(self.wire_type_error()),
+        }
+    }
+
+    fn string(&self) -> Result<String, DecodeError> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8 {
+            message: self.message,
+            field: self.number,
+        })
+    }
+}
+
+/// Reads the fields of one message in wire order
+struct Reader<'a> {
+    message: &'static str,
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(message: &'static str, buf: &'a [u8]) -> Self {
+        Self { message, buf }
+    }
+
+    fn malformed(&self) -> DecodeError {
+        DecodeError::Malformed {
+            message: self.message,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.buf.len() < len {
+            return Err(self.malformed());
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(taken)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.malformed())
+    }
+
+    fn field(&mut self) -> Result<Field<'a>, DecodeError> {
+        let key = self.varint()?;
+        let number = u32::try_from(key >> 3).map_err(|_| self.malformed())?;
+        let wire_type = (key & 0x7) as u8;
+        let value = match wire_type {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                let bytes = self.take(8)?;
+                Value::Fixed64(u64::from_le_bytes(bytes.try_into().expect("took 8 bytes")))
+            }
+            2 => {
+                let len = usize::try_from(self.varint()?).map_err(|_| self.malformed())?;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed32
+            }
+            _ => {
+                return Err(DecodeError::WireType {
+                    message: self.message,
+                    field: number,
+                    wire_type,
+                });
+            }
+        };
+        Ok(Field {
+            message: self.message,
+            number,
+            value,
+        })
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<Field<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            // Nothing after a malformed field can be trusted
+            self.buf = &[];
+        }
+        Some(field)
+    }
+}
+
+fn required(
+    message: &'static str,
+    field: &'static str,
+    value: String,
+) -> Result<String, DecodeError> {
+    if value.is_empty() {
+        Err(DecodeError::Missing { message, field })
+    } else {
+        Ok(value)
+    }
+}
+
+fn order_create(payload: &[u8]) -> Result<OrderCreatePayload, DecodeError> {
+    const MESSAGE: &str = "OrderCreate";
+    let (mut order_id, mut quantity, mut price, mut expires_at) = (0, 0, 0, 0);
+    let (mut side, mut time_in_force, mut order_type) = (0, 0, 0);
+    let mut instrument_id = String::new();
+    let (mut participant_id, mut oms_id, mut client_order_id) = (None, None, None);
+    let mut tags = OrderTags::new();
+    let (mut visible_quantity, mut hidden_quantity) = (None, None);
+    let mut liquidation = false;
+    let (mut min_quantity, mut all_or_none) = (None, false);
+    let (mut sweep_limit, mut sequence) = (None, None);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => order_id = field.uint()?,
+            2 => instrument_id = field.string()?,
+            3 => quantity = field.uint()?,
+            4 => price = field.uint()?,
+            5 => side = field.uint()?,
+            6 => time_in_force = field.uint()?,
+            7 => order_type = field.uint()?,
+            8 => participant_id = Some(field.string()?),
+            9 => oms_id = Some(field.string()?),
+            10 => expires_at = field.timestamp()?,
+            11 => client_order_id = Some(field.string()?),
+            12 => {
+                let (key, value) = string_entry(MESSAGE, field.bytes()?)?;
+                tags.insert(key, value);
+            }
+            13 => visible_quantity = Some(field.uint()?),
+            14 => hidden_quantity = Some(field.uint()?),
+            15 => liquidation = field.bool()?,
+            16 => min_quantity = Some(field.uint()?),
+            17 => all_or_none = field.bool()?,
+            18 => sweep_limit = Some(sweep_limit_of(field.bytes()?)?),
+            19 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    let unknown = |field, value| DecodeError::UnknownEnum {
+        message: MESSAGE,
+        field,
+        value,
+    };
+    Ok(OrderCreatePayload {
+        order_id,
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        quantity,
+        price,
+        side: side_of(MESSAGE, side)?.ok_or(DecodeError::Missing {
+            message: MESSAGE,
+            field: "side",
+        })?,
+        time_in_force: time_in_force_of(MESSAGE, time_in_force, expires_at)?,
+        order_type: match order_type {
+            1 => OrderType::LIMIT,
+            2 => OrderType::MARKET,
+            3 => OrderType::ICEBERG,
+            4 => OrderType::MARKET_TO_LIMIT,
+            0 => {
+                return Err(DecodeError::Missing {
+                    message: MESSAGE,
+                    field: "order_type",
+                });
+            }
+            value => return Err(unknown("order_type", value)),
+        },
+        participant_id,
+        oms_id,
+        client_order_id,
+        tags,
+        visible_quantity,
+        hidden_quantity,
+        liquidation,
+        min_quantity,
+        all_or_none,
+        sweep_limit,
+        sequence,
+    })
+}
+
+/// A `Side`, `None` when unspecified
+fn side_of(message: &'static str, value: u64) -> Result<Option<Side>, DecodeError> {
+    match value {
+        0 => Ok(None),
+        1 => Ok(Some(Side::Buy)),
+        2 => Ok(Some(Side::Sell)),
+        value => Err(DecodeError::UnknownEnum {
+            message,
+            field: "side",
+            value,
+        }),
+    }
+}
+
+/// A `TimeInForce`, expiring at `expires_at` when GTD
+fn time_in_force_of(
+    message: &'static str,
+    value: u64,
+    expires_at: u64,
+) -> Result<TimeInForce, DecodeError> {
+    match value {
+        0 => Ok(TimeInForce::Gtc),
+        1 => Ok(TimeInForce::Ioc),
+        2 => Ok(TimeInForce::Fok),
+        3 => Ok(TimeInForce::Gtd(expires_at)),
+        4 => Ok(TimeInForce::Day),
+        value => Err(DecodeError::UnknownEnum {
+            message,
+            field: "time_in_force",
+            value,
+        }),
+    }
+}
+
+fn matching_policy_of(message: &'static str, value: u64) -> Result<MatchingPolicy, DecodeError> {
+    match value {
+        0 => Ok(MatchingPolicy::Fifo),
+        1 => Ok(MatchingPolicy::ProRata),
+        2 => Ok(MatchingPolicy::SizeTime),
+        value => Err(DecodeError::UnknownEnum {
+            message,
+            field: "matching_policy",
+            value,
+        }),
+    }
+}
+
+/// An entry of a `map<string, string>` field
+fn string_entry(message: &'static str, payload: &[u8]) -> Result<(String, String), DecodeError> {
+    let (mut key, mut value) = (String::new(), String::new());
+    for field in Reader::new(message, payload) {
+        let field = field?;
+        match field.number {
+            1 => key = field.string()?,
+            2 => value = field.string()?,
+            _ => {}
+        }
+    }
+    Ok((key, value))
+}
+
+fn order_cancel(payload: &[u8]) -> Result<OrderCancelPayload, DecodeError> {
+    const MESSAGE: &str = "OrderCancel";
+    let mut order_id = 0;
+    let mut instrument_id = String::new();
+    let (mut client_order_id, mut participant_id, mut sequence) = (None, None, None);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => order_id = field.uint()?,
+            2 => instrument_id = field.string()?,
+            3 => client_order_id = Some(field.string()?),
+            4 => participant_id = Some(field.string()?),
+            5 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(OrderCancelPayload {
+        order_id,
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        client_order_id,
+        participant_id,
+        sequence,
+    })
+}
+
+fn order_modify(payload: &[u8]) -> Result<OrderModifyPayload, DecodeError> {
+    const MESSAGE: &str = "OrderModify";
+    let (mut order_id, mut price, mut quantity) = (0, 0, 0);
+    let mut instrument_id = String::new();
+    let (mut client_order_id, mut participant_id, mut sequence) = (None, None, None);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = field.string()?,
+            2 => order_id = field.uint()?,
+            3 => price = field.uint()?,
+            4 => quantity = field.uint()?,
+            5 => client_order_id = Some(field.string()?),
+            6 => participant_id = Some(field.string()?),
+            7 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(OrderModifyPayload {
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        order_id,
+        price,
+        quantity,
+        client_order_id,
+        participant_id,
+        sequence,
+    })
+}
+
+fn order_replace(payload: &[u8]) -> Result<OrderReplacePayload, DecodeError> {
+    const MESSAGE: &str = "OrderReplace";
+    let (mut order_id, mut price, mut quantity, mut side, mut expires_at) = (0, 0, 0, 0, 0);
+    let mut instrument_id = String::new();
+    let (mut client_order_id, mut participant_id, mut new_client_order_id) = (None, None, None);
+    let (mut time_in_force, mut sequence) = (None, None);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = field.string()?,
+            2 => order_id = field.uint()?,
+            3 => client_order_id = Some(field.string()?),
+            4 => participant_id = Some(field.string()?),
+            5 => new_client_order_id = Some(field.string()?),
+            6 => price = field.uint()?,
+            7 => quantity = field.uint()?,
+            8 => side = field.uint()?,
+            9 => time_in_force = Some(field.uint()?),
+            10 => expires_at = field.timestamp()?,
+            11 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(OrderReplacePayload {
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        order_id,
+        client_order_id,
+        participant_id,
+        new_client_order_id,
+        price,
+        quantity,
+        side: side_of(MESSAGE, side)?,
+        time_in_force: time_in_force
+            .map(|value| time_in_force_of(MESSAGE, value, expires_at))
+            .transpose()?,
+        sequence,
+    })
+}
+
+fn mass_cancel(payload: &[u8]) -> Result<MassCancelPayload, DecodeError> {
+    const MESSAGE: &str = "MassCancel";
+    let (mut instrument_id, mut participant_id, mut side) = (None, None, 0);
+    let mut sequence = None;
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = Some(field.string()?),
+            2 => side = field.uint()?,
+            3 => participant_id = Some(field.string()?),
+            4 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(MassCancelPayload {
+        instrument_id,
+        side: side_of(MESSAGE, side)?,
+        participant_id,
+        sequence,
+    })
+}
+
+fn kill_switch(payload: &[u8]) -> Result<KillSwitchPayload, DecodeError> {
+    const MESSAGE: &str = "KillSwitch";
+    let (mut participant_id, mut action) = (String::new(), KillSwitchAction::Engage);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => participant_id = field.string()?,
+            2 => {
+                action = match field.uint()? {
+                    0 => KillSwitchAction::Engage,
+                    1 => KillSwitchAction::Release,
+                    value => {
+                        return Err(DecodeError::UnknownEnum {
+                            message: MESSAGE,
+                            field: "action",
+                            value,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(KillSwitchPayload {
+        participant_id: required(MESSAGE, "participant_id", participant_id)?,
+        action,
+    })
+}
+
+fn instrument_expiry(payload: &[u8]) -> Result<InstrumentExpiry, DecodeError> {
+    let mut expiry = InstrumentExpiry {
+        expires_at: 0,
+        roll_to: None,
+    };
+    for field in Reader::new("InstrumentExpiry", payload) {
+        let field = field?;
+        match field.number {
+            1 => expiry.expires_at = field.timestamp()?,
+            2 => expiry.roll_to = Some(field.string()?),
+            _ => {}
+        }
+    }
+    Ok(expiry)
+}
+
+fn sweep_limit_of(payload: &[u8]) -> Result<SweepLimit, DecodeError> {
+    const MESSAGE: &str = "SweepLimit";
+    let mut limit = SweepLimit::default();
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => limit.max_levels = Some(field.usize()?),
+            2 => limit.max_quantity = Some(field.uint()?),
+            3 => {
+                limit.remainder = match field.uint()? {
+                    0 => SweepRemainder::Cancel,
+                    1 => SweepRemainder::Requeue,
+                    value => {
+                        return Err(DecodeError::UnknownEnum {
+                            message: MESSAGE,
+                            field: "remainder",
+                            value,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(limit)
+}
+
+fn instrument_scale(payload: &[u8]) -> Result<InstrumentScale, DecodeError> {
+    let mut scale = InstrumentScale::default();
+    for field in Reader::new("InstrumentScale", payload) {
+        let field = field?;
+        match field.number {
+            1 => scale.price_decimals = field.uint32()?,
+            2 => scale.quantity_decimals = field.uint32()?,
+            _ => {}
+        }
+    }
+    Ok(scale)
+}
+
+fn instrument_create(payload: &[u8]) -> Result<InstrumentCreatePayload, DecodeError> {
+    const MESSAGE: &str = "InstrumentCreate";
+    let mut instrument_id = String::new();
+    let mut create = InstrumentCreatePayload {
+        instrument_id: String::new(),
+        flight_recorder_capacity: None,
+        trade_tape_capacity: None,
+        impact_model: None,
+        block_trade_rules: None,
+        mark_pricing: None,
+        matching_policy: MatchingPolicy::Fifo,
+        peg_repricing: None,
+        market_protection: None,
+        min_fill_notional: None,
+        sweep_limit: None,
+        price_band: None,
+        perpetual: None,
+        expiry: None,
+        scale: None,
+        sequence: None,
+        spec: InstrumentSpec::default(),
+    };
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = field.string()?,
+            2 => create.flight_recorder_capacity = Some(field.usize()?),
+            3 => create.min_fill_notional = Some(field.uint()?),
+            4 => create.expiry = Some(instrument_expiry(field.bytes()?)?),
+            5 => create.scale = Some(instrument_scale(field.bytes()?)?),
+            6 => create.spec.tick_size = Some(field.uint()?),
+            7 => create.spec.lot_size = Some(field.uint()?),
+            8 => create.spec.min_quantity = Some(field.uint()?),
+            9 => create.spec.max_quantity = Some(field.uint()?),
+            10 => create.trade_tape_capacity = Some(field.usize()?),
+            11 => create.matching_policy = matching_policy_of(MESSAGE, field.uint()?)?,
+            12 => create.sweep_limit = Some(sweep_limit_of(field.bytes()?)?),
+            13 => create.sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    create.instrument_id = required(MESSAGE, "instrument_id", instrument_id)?;
+    Ok(create)
+}
+
+fn trading_halt(payload: &[u8]) -> Result<TradingHaltPayload, DecodeError> {
+    const MESSAGE: &str = "TradingHalt";
+    let (mut instrument_id, mut reason, mut sequence) = (String::new(), None, None);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = field.string()?,
+            2 => reason = Some(field.string()?),
+            3 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(TradingHaltPayload {
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        reason,
+        sequence,
+    })
+}
+
+fn auction(payload: &[u8]) -> Result<AuctionPayload, DecodeError> {
+    const MESSAGE: &str = "Auction";
+    let (mut instrument_id, mut sequence) = (String::new(), None);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = field.string()?,
+            2 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(AuctionPayload {
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        sequence,
+    })
+}
+
+fn instrument_delete(payload: &[u8]) -> Result<DeleteInstrumentPayload, DecodeError> {
+    const MESSAGE: &str = "InstrumentDelete";
+    let (mut instrument_id, mut sequence) = (String::new(), None);
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = field.string()?,
+            2 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(DeleteInstrumentPayload {
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        sequence,
+    })
+}
+
+fn instrument_adjust(payload: &[u8]) -> Result<InstrumentAdjustPayload, DecodeError> {
+    const MESSAGE: &str = "InstrumentAdjust";
+    let mut instrument_id = String::new();
+    let (mut action_id, mut sequence) = (None, None);
+    let mut action = CorporateAction {
+        ratio: 1.0,
+        cash_adjustment: 0,
+    };
+    for field in Reader::new(MESSAGE, payload) {
+        let field = field?;
+        match field.number {
+            1 => instrument_id = field.string()?,
+            2 => action_id = Some(field.string()?),
+            3 => action.ratio = field.double()?,
+            4 => action.cash_adjustment = field.sint()?,
+            5 => sequence = Some(field.uint()?),
+            _ => {}
+        }
+    }
+    Ok(InstrumentAdjustPayload {
+        instrument_id: required(MESSAGE, "instrument_id", instrument_id)?,
+        action_id,
+        sequence,
+        action,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes fields the way protoc-generated producers would
+    #[derive(Default)]
+    struct Writer {
+        buf: Vec<u8>,
+    }
+
+    impl Writer {
+        fn varint(&mut self, mut value: u64) -> &mut Self {
+            while value >= 0x80 {
+                self.buf.push(value as u8 | 0x80);
+                value >>= 7;
+            }
+            self.buf.push(value as u8);
+            self
+        }
+
+        fn uint(&mut self, field: u32, value: u64) -> &mut Self {
+            self.varint(u64::from(field) << 3).varint(value)
+        }
+
+        fn bytes(&mut self, field: u32, value: &[u8]) -> &mut Self {
+            self.varint(u64::from(field) << 3 | 2)
+                .varint(value.len() as u64);
+            self.buf.extend_from_slice(value);
+            self
+        }
+
+        fn double(&mut self, field: u32, value: f64) -> &mut Self {
+            self.varint(u64::from(field) << 3 | 1);
+            self.buf.extend_from_slice(&value.to_le_bytes());
+            self
+        }
+    }
+
+    #[test]
+    fn test_order_create_decodes_every_field() {
+        let payload = Writer::default()
+            .uint(1, 42)
+            .bytes(2, b"BTC-PERP")
+            .uint(3, 1_500)
+            .uint(4, 300)
+            .uint(5, 2)
+            .uint(6, 3)
+            .uint(7, 1)
+            .bytes(8, b"firm-a")
+            // A field added by a newer schema
+            .uint(99, 1)
+            .uint(10, 1_700_000_000)
+            .uint(15, 1)
+            .bytes(
+                12,
+                &Writer::default()
+                    .bytes(1, b"strategy")
+                    .bytes(2, b"twap")
+                    .buf,
+            )
+            .buf
+            .clone();
+        let order = order_create(&payload).unwrap();
+        assert_eq!(order.order_id, 42);
+        assert_eq!(order.instrument_id, "BTC-PERP");
+        assert_eq!((order.quantity, order.price), (1_500, 300));
+        assert_eq!(order.side, Side::Sell);
+        // Epoch seconds, normalized to millis
+        assert_eq!(order.time_in_force, TimeInForce::Gtd(1_700_000_000_000));
+        assert_eq!(order.order_type, OrderType::LIMIT);
+        assert_eq!(order.participant_id.as_deref(), Some("firm-a"));
+        assert_eq!(order.tags["strategy"], "twap");
+        assert_eq!(order.oms_id, None);
+        assert!(order.liquidation);
+
+        // Unset enums fall back to proto3 defaults only where JSON has one
+        let payload = Writer::default()
+            .bytes(2, b"BTC")
+            .uint(5, 1)
+            .uint(7, 2)
+            .buf
+            .clone();
+        let order = order_create(&payload).unwrap();
+        assert_eq!(order.time_in_force, TimeInForce::Gtc);
+        assert_eq!(order.order_type, OrderType::MARKET);
+        assert_eq!(order.visible_quantity, None);
+        assert!(!order.liquidation);
+
+        let payload = Writer::default()
+            .bytes(2, b"BTC")
+            .uint(3, 100)
+            .uint(5, 1)
+            .uint(7, 3)
+            .uint(13, 10)
+            .buf
+            .clone();
+        let order = order_create(&payload).unwrap();
+        assert_eq!(order.order_type, OrderType::ICEBERG);
+        assert_eq!(order.iceberg_quantities(), Ok((10, 90)));
+        assert_eq!(
+            order_create(&Writer::default().bytes(2, b"BTC").uint(7, 1).buf).unwrap_err(),
+            DecodeError::Missing {
+                message: "OrderCreate",
+                field: "side"
+            }
+        );
+    }
+
+    #[test]
+    fn test_order_replace_keeps_unset_side_and_time_in_force() {
+        let payload = Writer::default()
+            .bytes(1, b"BTC")
+            .bytes(3, b"abc")
+            .bytes(5, b"abd")
+            .uint(6, 101)
+            .uint(7, 5)
+            .buf
+            .clone();
+        let replace = order_replace(&payload).unwrap();
+        assert_eq!(replace.client_order_id.as_deref(), Some("abc"));
+        assert_eq!(replace.new_client_order_id.as_deref(), Some("abd"));
+        assert_eq!((replace.price, replace.quantity), (101, 5));
+        assert_eq!((replace.side, replace.time_in_force), (None, None));
+
+        // GTC is the zero value, so only an explicit one replaces the order's
+        let payload = Writer::default()
+            .bytes(1, b"BTC")
+            .uint(8, 2)
+            .uint(9, 0)
+            .buf
+            .clone();
+        let replace = order_replace(&payload).unwrap();
+        assert_eq!(replace.side, Some(Side::Sell));
+        assert_eq!(replace.time_in_force, Some(TimeInForce::Gtc));
+    }
+
+    #[test]
+    fn test_mass_cancel_decodes_its_filters() {
+        let payload = Writer::default().uint(2, 1).bytes(3, b"desk-a").buf.clone();
+        let mass_cancel = mass_cancel(&payload).unwrap();
+        assert_eq!(mass_cancel.instrument_id, None);
+        assert_eq!(mass_cancel.side, Some(Side::Buy));
+        assert_eq!(mass_cancel.participant_id.as_deref(), Some("desk-a"));
+
+        let release = Writer::default().bytes(1, b"desk-a").uint(2, 1).buf.clone();
+        let kill_switch = kill_switch(&release).unwrap();
+        assert_eq!(kill_switch.participant_id, "desk-a");
+        assert_eq!(kill_switch.action, KillSwitchAction::Release);
+        assert!(super::kill_switch(&[]).is_err());
+    }
+
+    #[test]
+    fn test_instrument_messages_decode() {
+        let expiry = Writer::default()
+            .uint(1, 1_800_000_000_000)
+            .bytes(2, b"BTC-DEC")
+            .buf
+            .clone();
+        let scale = Writer::default().uint(1, 2).uint(2, 3).buf.clone();
+        let payload = Writer::default()
+            .bytes(1, b"BTC-SEP")
+            .uint(3, 1_000)
+            .bytes(4, &expiry)
+            .bytes(5, &scale)
+            .uint(6, 5)
+            .uint(7, 10)
+            .buf
+            .clone();
+        let create = instrument_create(&payload).unwrap();
+        assert_eq!(create.instrument_id, "BTC-SEP");
+        assert_eq!(create.flight_recorder_capacity, None);
+        assert_eq!(create.min_fill_notional, Some(1_000));
+        assert_eq!(create.expiry.unwrap().roll_to.as_deref(), Some("BTC-DEC"));
+        let scale = create.scale.unwrap();
+        assert_eq!((scale.price_decimals, scale.quantity_decimals), (2, 3));
+        assert_eq!(
+            (
+                create.spec.tick_size,
+                create.spec.lot_size,
+                create.spec.max_quantity
+            ),
+            (Some(5), Some(10), None)
+        );
+
+        // -5 zigzags to 9
+        let payload = Writer::default()
+            .bytes(1, b"ACME")
+            .double(3, 2.0)
+            .uint(4, 9)
+            .buf
+            .clone();
+        let adjust = instrument_adjust(&payload).unwrap();
+        assert_eq!(adjust.action.ratio, 2.0);
+        assert_eq!(adjust.action.cash_adjustment, -5);
+        let adjust = instrument_adjust(&Writer::default().bytes(1, b"ACME").buf).unwrap();
+        assert_eq!(adjust.action.ratio, 1.0);
+    }
+
+    #[test]
+    fn test_malformed_payloads_are_rejected() {
+        // Declares 8 bytes of instrument id but carries 3
+        assert_eq!(
+            order_cancel(&[0x12, 0x08, b'B', b'T', b'C']).unwrap_err(),
+            DecodeError::Malformed {
+                message: "OrderCancel"
+            }
+        );
+        // The instrument id sent as a varint
+        assert_eq!(
+            order_cancel(&Writer::default().uint(2, 7).buf).unwrap_err(),
+            DecodeError::WireType {
+                message: "OrderCancel",
+                field: 2,
+                wire_type: 0
+            }
+        );
+        assert!(matches!(
+            instrument_delete(&[]).unwrap_err(),
+            DecodeError::Missing { .. }
+        ));
+        assert!(matches!(
+            ProtobufCodec.decode(CommandKind::Admin, &[]),
+            Err(CodecError::Unsupported { .. })
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+// src/codec/registry.rs
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Longest wait for the registry to answer a schema lookup
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct SchemaResponse {
+    schema: String,
+    /// Missing for Avro schemas
+    #[serde(rename = "schemaType")]
+    schema_type: Option<String>,
+}
+
+/// Client of the Confluent Schema Registry REST API, limited to looking up
+/// schemas by id over plain HTTP
+#[derive(Debug)]
+pub struct SchemaRegistry {
+    /// `host[:port]` as given in the URL, for the `Host` header
+    host: String,
+    /// `host:port` to connect to
+    address: String,
+    /// Path prefix of the API, without a trailing slash
+    base_path: String,
+}
+
+impl SchemaRegistry {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("{url} is not an http:// URL"))?;
+        let (host, base_path) = match rest.split_once('/') {
+            Some((host, path)) => (host, format!("/{path}")),
+            None => (rest, String::new()),
+        };
+        if host.is_empty() {
+            return Err(format!("{url} names no host"));
+        }
+        let address = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{host}:80")
+        };
+        Ok(Self {
+            host: host.to_string(),
+            address,
+            base_path: base_path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// The Avro schema registered under `id`, as JSON text
+    pub async fn fetch(&self, id: u32) -> Result<String, String> {
+        let path = format!("{}/schemas/ids/{}", self.base_path, id);
+        let body = tokio::time::timeout(REQUEST_TIMEOUT, self.get(&path))
+            .await
+            .map_err(|_| format!("no answer within {} ms", REQUEST_TIMEOUT.as_millis()))??;
+        let response: SchemaResponse =
+            serde_json::from_str(&body).map_err(|e| format!("unexpected answer: {e}"))?;
+        match response.schema_type.as_deref() {
+            None | Some("AVRO") => Ok(response.schema),
+            Some(other) => Err(format!("schema {id} is {other}, not Avro")),
+        }
+    }
+
+    /// Body of a successful GET; HTTP/1.0 keeps the response unchunked and
+    /// ends it by closing the connection
+    async fn get(&self, path: &str) -> Result<String, String> {
+        let mut stream = TcpStream::connect(&self.address)
+            .await
+            .map_err(|e| format!("cannot connect to {}: {e}", self.address))?;
+        let request = format!(
+            "GET {path} HTTP/1.0\r\nHost: {}\r\nAccept: application/vnd.schemaregistry.v1+json, application/json\r\n\r\n",
+            self.host
+        );
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .await
+            .map_err(|e| e.to_string())?;
+        let response = String::from_utf8_lossy(&response);
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or("malformed HTTP response")?;
+        let status = head.split_whitespace().nth(1).unwrap_or_default();
+        if status != "200" {
+            return Err(format!("GET {path} answered {status}: {}", body.trim()));
+        }
+        Ok(body.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_only_plain_http_urls_are_accepted() {
+        let registry = SchemaRegistry::new("http://registry:8081/api/").unwrap();
+        assert_eq!(registry.address, "registry:8081");
+        assert_eq!(registry.base_path, "/api");
+        let registry = SchemaRegistry::new("http://registry").unwrap();
+        assert_eq!(
+            (registry.address.as_str(), registry.base_path.as_str()),
+            ("registry:80", "")
+        );
+        assert!(SchemaRegistry::new("https://registry:8081").is_err());
+        assert!(SchemaRegistry::new("http:///schemas").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_schemas_are_looked_up_by_id() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for response in [
+                "HTTP/1.0 200 OK\r\n\r\n{\"schema\":\"\\\"long\\\"\"}",
+                "HTTP/1.0 404 Not Found\r\n\r\n{\"error_code\":40403}",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let read = stream.read(&mut request).await.unwrap();
+                assert!(String::from_utf8_lossy(&request[..read]).starts_with("GET /schemas/ids/"));
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        let registry = SchemaRegistry::new(&url).unwrap();
+        assert_eq!(registry.fetch(1).await.unwrap(), r#""long""#);
+        let missing = registry.fetch(2).await.unwrap_err();
+        assert!(missing.contains("404"), "{missing}");
+    }
+}
@@ -0,0 +1,37 @@
+use serde::Deserialize;
+
+/// Price series used for analytics sampling
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// Book mark price: the mid, falling back along the instrument's mark
+    /// sources when one side is empty
+    Mid,
+    /// Last trade price
+    LastTrade,
+}
+
+/// Settings for the engine's periodic analytics sampling
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    /// Interval between price samples, in milliseconds
+    pub sample_interval_ms: u64,
+    /// Number of return samples in each rolling correlation window
+    pub correlation_window: usize,
+    /// Instrument pairs whose return correlation is tracked from startup
+    pub correlation_pairs: Vec<(String, String)>,
+    /// Price series sampled for each instrument
+    pub price_source: PriceSource,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_ms: 1_000,
+            correlation_window: 300,
+            correlation_pairs: Vec::new(),
+            price_source: PriceSource::Mid,
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use crate::codec;
+use crate::config::kafka::{Compression, KafkaConfig};
+use crate::config::redaction::RedactionConfig;
+use crate::config::sessions::SessionConfig;
+use crate::config::sharding::ShardingConfig;
+use crate::config::watchdog::WatchdogConfig;
+use crate::engine::EngineConfig;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+/// Environment variable naming the config file when `--config` is not given
+pub const CONFIG_PATH_ENV: &str = "ORDERBOOK_CONFIG";
+
+/// Prefix of the environment variables setting config values, with nested keys
+/// separated by `__`, as in `ORDERBOOK_KAFKA__BROKERS`
+pub const ENV_PREFIX: &str = "ORDERBOOK";
+
+/// Most verbose level of the logs written
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_level(self) -> tracing::Level {
+        match self {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+/// How log lines are written
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log shippers
+    Json,
+}
+
+/// Capacities of the channels between the consumer, the engine and the publisher
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ChannelConfig {
+    /// Commands parsed by the consumer and not yet taken by the engine
+    pub engine_commands: usize,
+    /// Liquidation orders queued on the engine's priority lane
+    pub liquidation_commands: usize,
+    /// Messages queued for the Kafka producer
+    pub outbound: usize,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            engine_commands: 1024,
+            liquidation_commands: 256,
+            outbound: 1024,
+        }
+    }
+}
+
+/// Everything the process reads from its config file
+///
+/// Every section is optional; what a file leaves out keeps its default.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    pub log_level: LogLevel,
+    pub log_format: LogFormat,
+    /// Port serving the pipeline counters to Prometheus on `/metrics`; none
+    /// are served when unset
+    pub metrics_port: Option<u16>,
+    pub kafka: KafkaConfig,
+    pub channels: ChannelConfig,
+    pub engine: EngineConfig,
+    /// Policies of direct order-entry sessions
+    pub sessions: SessionConfig,
+    pub watchdog: WatchdogConfig,
+    pub sharding: ShardingConfig,
+    /// Fields stripped or hashed from messages on public topics
+    pub redaction: RedactionConfig,
+}
+
+#[derive(Debug)]
+pub enum AppConfigError {
+    /// The file could not be read or parsed
+    Load { path: String, error: String },
+    /// The file parsed, but some of its values are unusable
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for AppConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppConfigError::Load { path, error } => {
+                write!(f, "Failed to load config from {path}: {error}")
+            }
+            AppConfigError::Invalid(problems) => {
+                write!(f, "Invalid configuration: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+/// Settings given on the command line, which win over the config file
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    pub brokers: Option<String>,
+    pub log_level: Option<LogLevel>,
+    pub log_format: Option<LogFormat>,
+    pub metrics_port: Option<u16>,
+}
+
+impl ConfigOverrides {
+    fn apply(&self, config: &mut AppConfig) {
+        if let Some(brokers) = &self.brokers {
+            config.kafka.brokers = brokers.clone();
+        }
+        if let Some(log_level) = self.log_level {
+            config.log_level = log_level;
+        }
+        if let Some(log_format) = self.log_format {
+            config.log_format = log_format;
+        }
+        if let Some(port) = self.metrics_port {
+            config.metrics_port = Some(port);
+        }
+    }
+}
+
+/// Config values set by `ENV_PREFIX` environment variables, read from
+/// `variables` instead of the process environment when given
+fn environment(variables: Option<::config::Map<String, String>>) -> ::config::Environment {
+    ::config::Environment::with_prefix(ENV_PREFIX)
+        .prefix_separator("_")
+        .separator("__")
+        .try_parsing(true)
+        .source(variables)
+}
+
+impl AppConfig {
+    /// Reads and validates the configuration: the environment, overridden by
+    /// the config file at `path` (TOML, YAML or JSON by its extension),
+    /// overridden by `overrides`. What none of them sets keeps its default.
+    pub fn load(path: Option<&str>, overrides: &ConfigOverrides) -> Result<Self, AppConfigError> {
+        Self::load_from(path, environment(None), overrides)
+    }
+
+    fn load_from(
+        path: Option<&str>,
+        environment: ::config::Environment,
+        overrides: &ConfigOverrides,
+    ) -> Result<Self, AppConfigError> {
+        let mut config = Self::read(path, environment).map_err(|error| AppConfigError::Load {
+            path: path.unwrap_or("the environment").to_string(),
+            error: error.to_string(),
+        })?;
+        overrides.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn read(
+        path: Option<&str>,
+        environment: ::config::Environment,
+    ) -> Result<Self, ::config::ConfigError> {
+        let mut builder = ::config::Config::builder().add_source(environment);
+        if let Some(path) = path {
+            builder = builder.add_source(::config::File::from(Path::new(path)).required(true));
+        }
+        builder.build()?.try_deserialize()
+    }
+
+    /// Checks the values that would otherwise only fail once the engine runs,
+    /// reporting every problem at once
+    pub fn validate(&self) -> Result<(), AppConfigError> {
+        let mut problems = Vec::new();
+        if self.kafka.brokers.trim().is_empty() {
+            problems.push("kafka.brokers is empty".to_string());
+        }
+        if self.kafka.group_id.trim().is_empty() {
+            problems.push("kafka.group_id is empty".to_string());
+        }
+        if self
+            .kafka
+            .group_instance_id
+            .as_deref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            problems.push("kafka.group_instance_id is empty".to_string());
+        }
+        if let Err(e) = self.kafka.topics.validate() {
+            problems.push(e.to_string());
+        }
+        if self.kafka.compression == Compression::Zstd && !cfg!(feature = "zstd") {
+            problems.push("zstd compression requires building with the `zstd` feature".to_string());
+        }
+        problems.extend(codec::validate(&self.kafka));
+        if self.kafka.ingest.decode_workers == 0 {
+            problems.push("kafka.ingest.decode_workers must be positive".to_string());
+        }
+        let mut limited: Vec<_> = self.kafka.ingest.topics.iter().collect();
+        limited.sort();
+        for (topic, &limit) in limited {
+            if self.kafka.topics.kind_of(topic).is_none() {
+                problems.push(format!(
+                    "kafka.ingest.topics names {topic}, which is not an inbound topic"
+                ));
+            } else if limit == 0 {
+                problems.push(format!("kafka.ingest.topics.{topic} must be positive"));
+            }
+        }
+        if self.kafka.message_max_bytes == 0 {
+            problems.push("kafka.message_max_bytes must be positive".to_string());
+        }
+        if self.channels.engine_commands == 0 {
+            problems.push("channels.engine_commands must be positive".to_string());
+        }
+        if self.channels.liquidation_commands == 0 {
+            problems.push("channels.liquidation_commands must be positive".to_string());
+        }
+        if self.channels.outbound == 0 {
+            problems.push("channels.outbound must be positive".to_string());
+        }
+        if self.watchdog.enabled && self.watchdog.stall_secs == 0 {
+            problems.push("watchdog.stall_secs must be positive".to_string());
+        }
+        if self.sharding.shards == 0 {
+            problems.push("sharding.shards must be positive".to_string());
+        }
+        if self.redaction.salt.is_empty()
+            && self
+                .redaction
+                .rules
+                .iter()
+                .any(|rule| !rule.hash.is_empty())
+        {
+            problems.push("redaction.salt is required to hash fields".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(AppConfigError::Invalid(problems))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::kafka::RebalanceStrategy;
+    use crate::config::supervisor::PanicPolicy;
+
+    fn write(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_load_toml_keeps_defaults_for_missing_keys() {
+        let path = write(
+            "orderbook.toml",
+            r#"
+log_level = "debug"
+
+[kafka]
+brokers = "kafka-1:9092,kafka-2:9092"
+
+[kafka.topics]
+order_create = "orders.new"
+
+[channels]
+engine_commands = 4096
+
+[engine.supervisor]
+policy = "restart"
+
+[engine.features.instruments.BTC-PERP]
+market_protection = false
+
+[sessions.participants.MM-1]
+cancel_on_disconnect = false
+"#,
+        );
+        let config = AppConfig::load(Some(&path), &ConfigOverrides::default()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert_eq!(config.kafka.brokers, "kafka-1:9092,kafka-2:9092");
+        assert_eq!(config.kafka.group_id, "orderbook_group");
+        assert_eq!(config.kafka.compression, Compression::Lz4);
+        assert_eq!(config.kafka.topics.order_create, "orders.new");
+        assert_eq!(config.channels.engine_commands, 4096);
+        assert_eq!(config.channels.outbound, 1024);
+        assert_eq!(config.engine.supervisor.policy, PanicPolicy::Restart);
+        assert_eq!(config.engine.supervisor.max_restarts, 3);
+        // Instrument ids keep their case
+        assert!(config.engine.features.instruments.contains_key("BTC-PERP"));
+        let policy = config.sessions.policy("MM-1");
+        assert!(!policy.cancel_on_disconnect);
+        assert_eq!(policy.heartbeat_timeout_ms, 10_000);
+        assert!(config.sessions.policy("other").cancel_on_disconnect);
+    }
+
+    #[test]
+    fn test_load_yaml() {
+        let path = write(
+            "orderbook.yaml",
+            "kafka:\n  group_id: replica-2\n  group_instance_id: replica-2-a\n  rebalance_strategy: cooperative-sticky\n  compression: gzip\nengine:\n  diagnostics:\n    enabled: false\n",
+        );
+        let config = AppConfig::load(Some(&path), &ConfigOverrides::default()).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(config.kafka.group_id, "replica-2");
+        assert_eq!(
+            config.kafka.group_instance_id.as_deref(),
+            Some("replica-2-a")
+        );
+        assert_eq!(
+            config.kafka.rebalance_strategy,
+            RebalanceStrategy::CooperativeSticky
+        );
+        assert_eq!(config.kafka.compression, Compression::Gzip);
+        assert!(!config.engine.diagnostics.enabled);
+        assert_eq!(config.engine.diagnostics.snapshot_depth, 50);
+    }
+
+    #[test]
+    fn test_load_reports_every_invalid_value() {
+        let path = write(
+            "invalid.toml",
+            "[kafka]\nbrokers = \"\"\n[kafka.topics]\norder_cancel = \"order.create\"\n[channels]\noutbound = 0\n",
+        );
+        let error = AppConfig::load(Some(&path), &ConfigOverrides::default()).unwrap_err();
+        let _ = std::fs::remove_file(&path);
+
+        let AppConfigError::Invalid(problems) = error else {
+            panic!("expected validation errors, got {error}");
+        };
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].contains("kafka.brokers"));
+        assert!(problems[1].contains("order.create"));
+        assert!(problems[2].contains("channels.outbound"));
+    }
+
+    #[test]
+    fn test_load_fails_on_unreadable_file() {
+        assert!(matches!(
+            AppConfig::load(
+                Some("/nonexistent/orderbook.toml"),
+                &ConfigOverrides::default()
+            ),
+            Err(AppConfigError::Load { .. })
+        ));
+        let path = write("malformed.toml", "[kafka]\nmessage_max_bytes = \"big\"\n");
+        let result = AppConfig::load(Some(&path), &ConfigOverrides::default());
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(result, Err(AppConfigError::Load { .. })));
+    }
+
+    #[test]
+    fn test_flags_override_the_file_which_overrides_the_environment() {
+        let path = write(
+            "precedence.toml",
+            "log_level = \"debug\"\n[kafka]\ngroup_id = \"from-file\"\n",
+        );
+        let variables = [
+            ("ORDERBOOK_LOG_LEVEL", "warn"),
+            ("ORDERBOOK_LOG_FORMAT", "json"),
+            ("ORDERBOOK_KAFKA__GROUP_ID", "from-env"),
+            ("ORDERBOOK_KAFKA__BROKERS", "env:9092"),
+            ("ORDERBOOK_CHANNELS__OUTBOUND", "64"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        let overrides = ConfigOverrides {
+            brokers: Some("flag:9092".to_string()),
+            metrics_port: Some(9100),
+            ..ConfigOverrides::default()
+        };
+        let config =
+            AppConfig::load_from(Some(&path), environment(Some(variables)), &overrides).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.kafka.group_id, "from-file");
+        assert_eq!(config.kafka.brokers, "flag:9092");
+        assert_eq!(config.channels.outbound, 64);
+        assert_eq!(config.metrics_port, Some(9100));
+    }
+}
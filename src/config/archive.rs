@@ -0,0 +1,34 @@
+use serde::Deserialize;
+
+/// Settings for archiving book depth for historical queries
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    /// Directory holding one subdirectory of snapshots and deltas per instrument
+    pub directory: String,
+    /// How often a full depth snapshot of every book is archived
+    pub snapshot_interval_ms: u64,
+    /// Engine channel fill (0.0 to 1.0) above which a due snapshot is deferred
+    pub busy_queue_fill: f64,
+    /// Slowest command since the last tick above which a due snapshot is deferred
+    pub busy_command_latency_us: u64,
+    /// Longest a snapshot is deferred past its due time before it is taken anyway
+    pub max_snapshot_delay_ms: u64,
+    /// Batches of writes queued for the archive writer thread before new ones are dropped
+    pub writer_queue_capacity: usize,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: "archive/books".to_string(),
+            snapshot_interval_ms: 60_000,
+            busy_queue_fill: 0.5,
+            busy_command_latency_us: 1_000,
+            max_snapshot_delay_ms: 300_000,
+            writer_queue_capacity: 1_024,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+/// Latest top of book of every instrument, for consumers starting cold, and
+/// the stream of its changes
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct BboStateConfig {
+    pub enabled: bool,
+    /// Topic keyed by instrument receiving its state on every change; create
+    /// it with `cleanup.policy=compact` so it keeps the latest per instrument
+    pub topic: String,
+    /// Whether changes of the best bid or ask price or size are published as
+    /// events
+    pub events_enabled: bool,
+    /// Topic keyed by instrument receiving the touch events
+    pub events_topic: String,
+}
+
+impl Default for BboStateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            topic: "marketdata.bbo_state".to_string(),
+            events_enabled: false,
+            events_topic: "marketdata.bbo".to_string(),
+        }
+    }
+}
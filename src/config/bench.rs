@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+/// Settings of `bench-book`, which measures how fast a single book absorbs
+/// generated load on the current hardware
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct BenchConfig {
+    /// Commands measured, after the warmup
+    pub commands: u64,
+    /// Commands applied before measuring, so the book starts at a steady size
+    pub warmup_commands: u64,
+    /// Orders kept resting; the oldest are cancelled beyond it
+    pub resting_orders: usize,
+    /// Seed of the generated load, so runs on different machines compare
+    pub seed: u64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            commands: 1_000_000,
+            warmup_commands: 100_000,
+            resting_orders: 10_000,
+            seed: 1,
+        }
+    }
+}
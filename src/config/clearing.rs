@@ -0,0 +1,83 @@
+use serde::Deserialize;
+
+/// File layout of the end-of-day clearing export
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClearingFormat {
+    /// Delimited text with the columns listed in `csv_columns`
+    Csv,
+    /// FIXML `TrdCaptRpt` messages wrapped in a batch
+    Fixml,
+}
+
+/// A column of the CSV clearing export
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClearingField {
+    TradeId,
+    InstrumentId,
+    Timestamp,
+    TradeDate,
+    SettlementDate,
+    Price,
+    Quantity,
+    BuyerId,
+    SellerId,
+    BuyerFee,
+    SellerFee,
+    OffBook,
+    /// Whether the buyer's trade is flagged for an order-to-trade surcharge
+    BuyerSurcharge,
+    SellerSurcharge,
+}
+
+/// Settings for the end-of-day clearing export
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ClearingConfig {
+    pub enabled: bool,
+    pub format: ClearingFormat,
+    pub csv_columns: Vec<ClearingField>,
+    pub csv_delimiter: char,
+    /// Directory the export files are written to for back-office pickup
+    pub archive_dir: String,
+    /// Time of day in seconds after midnight UTC at which the day is closed
+    pub eod_time_utc_secs: u32,
+    /// Settlement lag in business days (T+N, weekends skipped)
+    pub settlement_lag_days: u32,
+    /// Fee charged to the resting side of on-book trades, in basis points of notional
+    pub maker_fee_bps: f64,
+    /// Fee charged to the aggressing side of on-book trades, in basis points of notional
+    pub taker_fee_bps: f64,
+    /// Fee charged to both sides of off-book trades, in basis points of notional
+    pub off_book_fee_bps: f64,
+}
+
+impl Default for ClearingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            format: ClearingFormat::Csv,
+            csv_columns: vec![
+                ClearingField::TradeId,
+                ClearingField::InstrumentId,
+                ClearingField::TradeDate,
+                ClearingField::SettlementDate,
+                ClearingField::Price,
+                ClearingField::Quantity,
+                ClearingField::BuyerId,
+                ClearingField::SellerId,
+                ClearingField::BuyerFee,
+                ClearingField::SellerFee,
+                ClearingField::OffBook,
+            ],
+            csv_delimiter: ',',
+            archive_dir: "archive/clearing".to_string(),
+            eod_time_utc_secs: 22 * 3600,
+            settlement_lag_days: 2,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            off_book_fee_bps: 0.0,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+/// Window of recent commands in which a redelivered one is recognised
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    /// Commands remembered, the least recently seen forgotten first
+    pub window: usize,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window: 100_000,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use serde::Deserialize;
+
+/// Incremental L2 feed built from the price level changes of every book
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DepthDeltaConfig {
+    pub enabled: bool,
+    /// Each instrument publishes to this prefix followed by its id, so these
+    /// topics are created along with instruments rather than checked up front
+    pub topic_prefix: String,
+    /// How often a full snapshot of each book is published for consumers
+    /// joining the feed late
+    pub snapshot_interval_ms: u64,
+    /// How often batches of an elapsed millisecond are checked for release
+    pub flush_interval_ms: u64,
+}
+
+impl Default for DepthDeltaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            topic_prefix: "marketdata.l2.".to_string(),
+            snapshot_interval_ms: 5_000,
+            flush_interval_ms: 1,
+        }
+    }
+}
+
+impl DepthDeltaConfig {
+    pub fn topic(&self, instrument_id: &str) -> String {
+        format!("{}{}", self.topic_prefix, instrument_id)
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Deserialize;
+
+/// Controls what the engine does when a book fails its invariant checks
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DiagnosticsConfig {
+    /// Run invariant checks after every command that touches a book
+    pub enabled: bool,
+    /// Directory where book state dumps are written
+    pub directory: String,
+    /// Number of price levels per side included in the dumped snapshot
+    pub snapshot_depth: usize,
+    /// Price levels per side of the ladder logged with a violation; 0 logs none
+    pub log_ladder_depth: usize,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: "diagnostics".to_string(),
+            snapshot_depth: 50,
+            log_ladder_depth: 10,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+/// Settings for validating commands without applying them
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DryRunConfig {
+    /// Validate and preview every command instead of applying it, publishing
+    /// nothing but the previews and alerts
+    pub enabled: bool,
+    /// Topic receiving the would-be result of every command
+    pub shadow_topic: String,
+}
+
+impl Default for DryRunConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shadow_topic: "engine.dry_run".to_string(),
+        }
+    }
+}
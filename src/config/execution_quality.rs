@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+/// Execution quality analytics of aggressive orders
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExecutionQualityConfig {
+    pub enabled: bool,
+    /// How often the distributions are published and restarted, in milliseconds
+    pub report_interval_ms: u64,
+    /// Topic receiving the distributions of every instrument that traded
+    pub metrics_topic: String,
+}
+
+impl Default for ExecutionQualityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            report_interval_ms: 60_000,
+            metrics_topic: "metrics.execution_quality".to_string(),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use serde::Deserialize;
+
+/// Settings for comparing book mids against upstream theoretical prices
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FairValueConfig {
+    /// Deviation of mid from theo, in basis points, above which an alert fires
+    pub alert_threshold_bps: f64,
+    /// Topic receiving a deviation metric for every theoretical price update
+    pub metrics_topic: String,
+}
+
+impl Default for FairValueConfig {
+    fn default() -> Self {
+        Self {
+            alert_threshold_bps: 50.0,
+            metrics_topic: "metrics.fair_value".to_string(),
+        }
+    }
+}
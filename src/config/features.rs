@@ -0,0 +1,41 @@
+use crate::orderbook::features::BookFeatures;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Experimental behavior that can be switched on and off per instrument
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    MarketProtection,
+    MinFillNotional,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 2] = [Feature::MarketProtection, Feature::MinFillNotional];
+
+    pub fn flag(self) -> BookFeatures {
+        match self {
+            Feature::MarketProtection => BookFeatures::MARKET_PROTECTION,
+            Feature::MinFillNotional => BookFeatures::MIN_FILL_NOTIONAL,
+        }
+    }
+}
+
+/// Which experimental behaviors apply to which instruments at startup
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FeatureFlagConfig {
+    /// Features enabled on instruments without an override of their own
+    pub enabled: Vec<Feature>,
+    /// Features switched on or off for single instruments
+    pub instruments: HashMap<String, HashMap<Feature, bool>>,
+}
+
+impl Default for FeatureFlagConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Feature::ALL.to_vec(),
+            instruments: HashMap::new(),
+        }
+    }
+}
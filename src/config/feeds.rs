@@ -0,0 +1,139 @@
+use crate::orderbook::scale::NumberFormat;
+use serde::Deserialize;
+
+/// One market data feed, published to its own pair of topics
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeedProfile {
+    /// Name used in logs
+    pub name: String,
+    /// Topic receiving depth updates, keyed by instrument
+    pub depth_topic: String,
+    /// Topic receiving trade prints, keyed by instrument
+    pub trades_topic: String,
+    /// Price levels per side in depth updates
+    pub depth_levels: usize,
+    /// Minimum time between depth updates of an instrument; 0 publishes every change
+    pub min_interval_ms: u64,
+    /// Time every message is held back before being published
+    pub delay_ms: u64,
+    /// Whether trade prints identify the taker and maker orders
+    pub include_order_ids: bool,
+    /// Instruments carried by the feed; every instrument when empty
+    #[serde(default)]
+    pub instruments: Vec<String>,
+    /// Whether prices and quantities are raw integers or decimal strings
+    pub number_format: NumberFormat,
+    /// Bound on messages held back by the delay; the oldest are dropped beyond it
+    #[serde(default = "default_max_buffered_bytes")]
+    pub max_buffered_bytes: usize,
+    /// Directory journaling delayed messages so they survive restarts
+    #[serde(default)]
+    pub journal_dir: Option<String>,
+    /// Conflates busy instruments beyond `min_interval_ms` to keep the feed
+    /// under a bandwidth cap
+    #[serde(default)]
+    pub adaptive_conflation: Option<AdaptiveConflationConfig>,
+}
+
+/// Conflation following each instrument's top-of-book change rate
+///
+/// Instruments whose best bid or ask changes at most `quiet_changes_per_sec`
+/// keep the profile's `min_interval_ms`, so set that to 0 for quiet instruments
+/// to publish every change. When the feed would exceed `max_bytes_per_sec`, the
+/// busiest instruments are given coarser intervals first.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AdaptiveConflationConfig {
+    /// Depth and trade bytes per second the feed stays under, across instruments
+    pub max_bytes_per_sec: u64,
+    /// Top-of-book changes per second up to which an instrument is never
+    /// conflated beyond `min_interval_ms`
+    pub quiet_changes_per_sec: f64,
+    /// Coarsest interval a busy instrument is conflated to; the cap is exceeded
+    /// rather than going beyond it
+    pub max_interval_ms: u64,
+    /// How often change rates are measured and intervals reassigned
+    pub window_ms: u64,
+}
+
+impl Default for AdaptiveConflationConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_sec: 1_000_000,
+            quiet_changes_per_sec: 5.0,
+            max_interval_ms: 5_000,
+            window_ms: 1_000,
+        }
+    }
+}
+
+fn default_max_buffered_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+impl FeedProfile {
+    pub fn carries(&self, instrument_id: &str) -> bool {
+        self.instruments.is_empty() || self.instruments.iter().any(|id| id == instrument_id)
+    }
+}
+
+/// Market data feeds published from the engine
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FeedConfig {
+    pub profiles: Vec<FeedProfile>,
+    /// How often throttled depth and delayed messages are checked for release
+    pub flush_interval_ms: u64,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            profiles: vec![
+                FeedProfile {
+                    name: "internal".to_string(),
+                    depth_topic: "md.internal.depth".to_string(),
+                    trades_topic: "md.internal.trades".to_string(),
+                    depth_levels: 50,
+                    min_interval_ms: 0,
+                    delay_ms: 0,
+                    include_order_ids: true,
+                    instruments: Vec::new(),
+                    number_format: NumberFormat::Raw,
+                    max_buffered_bytes: default_max_buffered_bytes(),
+                    journal_dir: None,
+                    adaptive_conflation: None,
+                },
+                FeedProfile {
+                    name: "public".to_string(),
+                    depth_topic: "md.public.depth".to_string(),
+                    trades_topic: "md.public.trades".to_string(),
+                    depth_levels: 10,
+                    min_interval_ms: 1_000,
+                    delay_ms: 0,
+                    include_order_ids: false,
+                    instruments: Vec::new(),
+                    number_format: NumberFormat::Decimal,
+                    max_buffered_bytes: default_max_buffered_bytes(),
+                    journal_dir: None,
+                    adaptive_conflation: None,
+                },
+                FeedProfile {
+                    name: "delayed".to_string(),
+                    depth_topic: "md.delayed.depth".to_string(),
+                    trades_topic: "md.delayed.trades".to_string(),
+                    depth_levels: 10,
+                    min_interval_ms: 1_000,
+                    delay_ms: 15 * 60 * 1_000,
+                    include_order_ids: false,
+                    instruments: Vec::new(),
+                    number_format: NumberFormat::Decimal,
+                    max_buffered_bytes: default_max_buffered_bytes(),
+                    journal_dir: Some("archive/feeds".to_string()),
+                    adaptive_conflation: None,
+                },
+            ],
+            flush_interval_ms: 50,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use serde::Deserialize;
+
+/// Premium and funding rates of perpetual instruments, from their mark and
+/// index prices
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FundingConfig {
+    pub enabled: bool,
+    /// How often the premium of mark over index is sampled and published, in
+    /// milliseconds
+    pub premium_interval_ms: u64,
+    /// Time between fundings, in milliseconds; fundings fall on multiples of
+    /// it since the Unix epoch, e.g. 00:00, 08:00 and 16:00 UTC for 8 hours
+    pub funding_interval_ms: u64,
+    /// Interest rate per funding interval, e.g. `0.0001` for 0.01%
+    pub interest_rate: f64,
+    /// Largest amount the interest rate may move the funding rate away from
+    /// the premium index
+    pub interest_clamp: f64,
+    /// Largest absolute funding rate
+    pub max_funding_rate: f64,
+    /// Topic receiving every premium sample, with the funding rate it implies
+    /// so far
+    pub premium_topic: String,
+    /// Topic receiving the funding rate of every perpetual at each funding
+    pub funding_topic: String,
+}
+
+impl Default for FundingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            premium_interval_ms: 60_000,
+            funding_interval_ms: 8 * 60 * 60 * 1000,
+            interest_rate: 0.0001,
+            interest_clamp: 0.0005,
+            max_funding_rate: 0.0075,
+            premium_topic: "funding.premium".to_string(),
+            funding_topic: "funding.rate".to_string(),
+        }
+    }
+}
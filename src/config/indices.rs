@@ -0,0 +1,25 @@
+use crate::config::analytics::PriceSource;
+use crate::orderbook::index::IndexDefinition;
+use serde::Deserialize;
+
+/// Settings for index calculation from constituent books
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct IndexConfig {
+    /// Indices defined at startup; more can be added on the `index.define` topic
+    pub indices: Vec<IndexDefinition>,
+    /// Topic receiving index ticks, keyed by index id
+    pub tick_topic: String,
+    /// Price series used for constituents
+    pub price_source: PriceSource,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            indices: Vec::new(),
+            tick_topic: "index.ticks".to_string(),
+            price_source: PriceSource::Mid,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// How many inbound messages are decoded at once
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct IngestConfig {
+    /// Messages decoded at once on blocking threads; with one, each message is
+    /// decoded by the consumer before the next is taken
+    pub decode_workers: usize,
+    /// Fewer messages of a topic decoded at once, by topic name, so one with
+    /// large payloads leaves workers to the others
+    pub topics: HashMap<String, usize>,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            decode_workers: 1,
+            topics: HashMap::new(),
+        }
+    }
+}
+
+impl IngestConfig {
+    /// Messages of a topic that may be decoding at once
+    pub fn concurrency(&self, topic: &str) -> usize {
+        self.topics
+            .get(topic)
+            .map_or(self.decode_workers, |&limit| limit.min(self.decode_workers))
+    }
+}
@@ -0,0 +1,27 @@
+use crate::orderbook::scale::NumberFormat;
+use serde::Deserialize;
+
+/// Settings for instrument lifecycle events
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct InstrumentEventsConfig {
+    /// Topic receiving corporate action adjustment events, keyed by instrument
+    pub adjustments_topic: String,
+    /// Topic receiving final settlement reports of expired contracts, keyed by instrument
+    pub settlement_topic: String,
+    /// Number of price levels per side in the final settlement snapshot
+    pub settlement_snapshot_depth: usize,
+    /// Whether settlement prices and depth are raw integers or decimal strings
+    pub number_format: NumberFormat,
+}
+
+impl Default for InstrumentEventsConfig {
+    fn default() -> Self {
+        Self {
+            adjustments_topic: "instrument.adjustments".to_string(),
+            settlement_topic: "instrument.settlement".to_string(),
+            settlement_snapshot_depth: 50,
+            number_format: NumberFormat::Raw,
+        }
+    }
+}
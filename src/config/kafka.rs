@@ -1,34 +1,164 @@
+use crate::config::ingest::IngestConfig;
+use crate::config::partitioning::PartitioningConfig;
+use crate::config::preflight::PreflightConfig;
+use crate::config::topics::{PayloadFormat, TopicMap};
+use crate::consumption::ConsumptionControl;
+use crate::rebalance::{CommandConsumer, RebalanceContext};
+use crate::watchdog::Progress;
 use rdkafka::config::ClientConfig;
-use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::consumer::Consumer;
 use rdkafka::error::KafkaError;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// librdkafka's default `message.max.bytes`
+pub const DEFAULT_MESSAGE_MAX_BYTES: usize = 1_000_000;
+
+/// Compression applied by the producer to outbound message batches
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Snappy,
+    Lz4,
+    /// Only available when built with the `zstd` feature
+    Zstd,
+}
+
+impl Compression {
+    /// Value of the librdkafka `compression.type` setting
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Snappy => "snappy",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// How partitions move between the members of the consumer group
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RebalanceStrategy {
+    /// Every member gives up all of its partitions at each rebalance
+    #[default]
+    Eager,
+    /// Only the partitions that move are revoked, the others keep being consumed
+    CooperativeSticky,
+}
+
+impl RebalanceStrategy {
+    /// Value of the librdkafka `partition.assignment.strategy` setting
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RebalanceStrategy::Eager => "range,roundrobin",
+            RebalanceStrategy::CooperativeSticky => "cooperative-sticky",
+        }
+    }
+}
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
 pub struct KafkaConfig {
     pub brokers: String,
     pub group_id: String,
-    pub topics: Vec<String>,
+    /// Static group membership: a consumer restarting under the same id within
+    /// the session timeout gets its partitions back without a rebalance
+    pub group_instance_id: Option<String>,
+    /// How long the group waits for a silent member before rebalancing, in
+    /// milliseconds; librdkafka's default when not set
+    pub session_timeout_ms: Option<u64>,
+    pub rebalance_strategy: RebalanceStrategy,
+    /// Longest wait, when partitions are revoked, for the engine to apply the
+    /// commands consumed from them, in milliseconds
+    pub rebalance_drain_timeout_ms: u64,
+    pub topics: TopicMap,
+    /// Encoding of the messages on each inbound topic, by topic name; topics not
+    /// listed carry JSON
+    pub payload_formats: HashMap<String, PayloadFormat>,
+    /// Schema Registry the writer schemas of Avro topics are fetched from, as an
+    /// `http://` URL
+    pub schema_registry_url: Option<String>,
+    /// How many inbound messages are decoded at once
+    pub ingest: IngestConfig,
+    pub compression: Compression,
+    /// Largest outbound message, before compression; larger messages are sent in chunks
+    pub message_max_bytes: usize,
+    /// How outbound messages are keyed and partitioned, by topic
+    pub partitioning: PartitioningConfig,
+    pub preflight: PreflightConfig,
+}
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            brokers: "localhost:9092".to_string(),
+            group_id: "orderbook_group".to_string(),
+            group_instance_id: None,
+            session_timeout_ms: None,
+            rebalance_strategy: RebalanceStrategy::Eager,
+            rebalance_drain_timeout_ms: 10_000,
+            topics: TopicMap::default(),
+            payload_formats: HashMap::new(),
+            schema_registry_url: None,
+            ingest: IngestConfig::default(),
+            compression: Compression::Lz4,
+            message_max_bytes: DEFAULT_MESSAGE_MAX_BYTES,
+            partitioning: PartitioningConfig::default(),
+            preflight: PreflightConfig::default(),
+        }
+    }
 }
 
-pub fn create_consumer(config: &KafkaConfig) -> Result<StreamConsumer, KafkaError> {
-    let consumer: StreamConsumer = ClientConfig::new()
+/// Creates the consumer of inbound commands, which holds back rebalances until
+/// `progress` shows the engine has applied what was consumed, and keeps the
+/// partitions paused through `consumption` paused
+pub fn create_consumer(
+    config: &KafkaConfig,
+    subscriptions: &[&str],
+    progress: &Progress,
+    consumption: &ConsumptionControl,
+) -> Result<CommandConsumer, KafkaError> {
+    let mut client_config = ClientConfig::new();
+    client_config
         .set("bootstrap.servers", &config.brokers)
         .set("group.id", &config.group_id)
         // .set("enable.partition.eof", "false")
-        // .set("session.timeout.ms", "6000")
         // .set("enable.auto.commit", "true")
         .set("auto.offset.reset", "earliest")
-        .create()?;
+        .set("partition.assignment.strategy", config.rebalance_strategy.as_str());
+    if let Some(group_instance_id) = &config.group_instance_id {
+        client_config.set("group.instance.id", group_instance_id);
+    }
+    if let Some(session_timeout_ms) = config.session_timeout_ms {
+        client_config.set("session.timeout.ms", session_timeout_ms.to_string());
+    }
+    let consumer: CommandConsumer = client_config.create_with_context(RebalanceContext::new(
+        progress.clone(),
+        consumption.clone(),
+        Duration::from_millis(config.rebalance_drain_timeout_ms),
+    ))?;
 
-    consumer.subscribe(&config.topics.iter().map(String::as_str).collect::<Vec<_>>())?;
+    consumer.subscribe(subscriptions)?;
 
     Ok(consumer)
 }
 
-
 pub fn create_producer(config: &KafkaConfig) -> Result<rdkafka::producer::FutureProducer, KafkaError> {
+    if config.compression == Compression::Zstd && !cfg!(feature = "zstd") {
+        return Err(KafkaError::ClientCreation(
+            "zstd compression requires building with the `zstd` feature".to_string(),
+        ));
+    }
     let producer: rdkafka::producer::FutureProducer = ClientConfig::new()
         .set("bootstrap.servers", &config.brokers)
+        .set("compression.type", config.compression.as_str())
+        .set("message.max.bytes", config.message_max_bytes.to_string())
         .create()?;
     Ok(producer)
 }
\ No newline at end of file
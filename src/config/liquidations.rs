@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/// Liquidation volume metrics per instrument
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LiquidationConfig {
+    pub enabled: bool,
+    /// How often the volume liquidated since the last report is published, in
+    /// milliseconds
+    pub metrics_interval_ms: u64,
+    /// Topic receiving the liquidation volume of every instrument liquidated on
+    /// within the interval
+    pub metrics_topic: String,
+}
+
+impl Default for LiquidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            metrics_interval_ms: 5_000,
+            metrics_topic: "metrics.liquidations".to_string(),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::config::sessions::SessionConfig;
+use serde::Deserialize;
+
+/// Heartbeats exchanged with upstream order management systems
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LivenessConfig {
+    pub enabled: bool,
+    /// Topic the engine publishes its own heartbeat to
+    pub heartbeat_topic: String,
+    /// How often the engine heartbeat is published and OMS heartbeats are checked
+    pub heartbeat_interval_ms: u64,
+    /// Heartbeat timeout of each OMS, keyed by OMS id, and whether its resting
+    /// orders are cancelled once the timeout passes
+    pub oms: SessionConfig,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            heartbeat_topic: "engine.heartbeat".to_string(),
+            heartbeat_interval_ms: 1_000,
+            oms: SessionConfig::default(),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+/// Cancellation of many resting orders with a single `order.mass_cancel`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MassCancelConfig {
+    /// Topic receiving the counts of every mass cancel applied
+    pub summary_topic: String,
+}
+
+impl Default for MassCancelConfig {
+    fn default() -> Self {
+        Self {
+            summary_topic: "order.mass_cancelled".to_string(),
+        }
+    }
+}
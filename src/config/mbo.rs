@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+/// Market-by-order (L3) feed of every resting order's changes
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MboConfig {
+    pub enabled: bool,
+    /// Topic keyed by instrument receiving the order events and snapshots
+    pub topic: String,
+    /// How often every resting order of each book is published for consumers
+    /// joining the feed late
+    pub snapshot_interval_ms: u64,
+}
+
+impl Default for MboConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            topic: "marketdata.mbo".to_string(),
+            snapshot_interval_ms: 30_000,
+        }
+    }
+}
@@ -1 +1,44 @@
+pub mod analytics;
+pub mod app;
+pub mod archive;
+pub mod bbo_state;
+pub mod bench;
+pub mod clearing;
+pub mod dedup;
+pub mod depth_deltas;
+pub mod diagnostics;
+pub mod dry_run;
+pub mod execution_quality;
+pub mod fair_value;
+pub mod features;
+pub mod feeds;
+pub mod funding;
+pub mod indices;
+pub mod ingest;
+pub mod instruments;
 pub mod kafka;
+pub mod liquidations;
+pub mod liveness;
+pub mod mass_cancel;
+pub mod mbo;
+pub mod order_expiry;
+pub mod order_responses;
+pub mod order_to_trade;
+pub mod partitioning;
+pub mod preflight;
+pub mod redaction;
+pub mod rfq;
+pub mod risk;
+pub mod sequencer;
+pub mod sessions;
+pub mod sharding;
+pub mod shutdown;
+pub mod sinks;
+pub mod soak;
+pub mod supervisor;
+pub mod tiering;
+pub mod topics;
+pub mod trades;
+pub mod verification;
+pub mod wal;
+pub mod watchdog;
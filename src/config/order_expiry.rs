@@ -0,0 +1,18 @@
+use serde::Deserialize;
+
+/// Expiry of resting GTD and DAY orders
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct OrderExpiryConfig {
+    /// Interval between sweeps for orders whose time in force has run out, in
+    /// milliseconds; 0 leaves them in the book
+    pub sweep_interval_ms: u64,
+}
+
+impl Default for OrderExpiryConfig {
+    fn default() -> Self {
+        Self {
+            sweep_interval_ms: 1_000,
+        }
+    }
+}
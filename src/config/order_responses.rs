@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+/// Acknowledgements and rejections of order commands, sent back to the
+/// upstream order management systems
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct OrderResponseConfig {
+    pub enabled: bool,
+    /// Topic order commands that were applied are acknowledged on
+    pub ack_topic: String,
+    /// Topic the ones the engine refused are reported on, with the reason
+    pub reject_topic: String,
+}
+
+impl Default for OrderResponseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ack_topic: "order.ack".to_string(),
+            reject_topic: "order.reject".to_string(),
+        }
+    }
+}
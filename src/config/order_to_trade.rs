@@ -0,0 +1,55 @@
+use serde::Deserialize;
+
+/// What happens to a participant whose order-to-trade ratio exceeds the limit
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OtrAction {
+    /// Only report the breach in the metrics
+    Monitor,
+    /// Reject the participant's new orders on the instrument until it recovers
+    Throttle,
+    /// Flag the participant's trades on the instrument for a surcharge in the
+    /// clearing export
+    Surcharge,
+}
+
+/// Order-to-trade ratio monitoring per participant and instrument
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct OrderToTradeConfig {
+    pub enabled: bool,
+    /// Length of the rolling window the ratio is measured over
+    pub window_secs: u64,
+    /// Granularity of the window; counts expire a bucket at a time
+    pub bucket_secs: u64,
+    /// How often ratios are evaluated and published, in milliseconds
+    pub evaluation_interval_ms: u64,
+    /// Order messages (creates, modifies and cancels) per trade above which a
+    /// participant is in breach
+    pub max_ratio: f64,
+    /// Order messages within the window below which no ratio is enforced, so
+    /// light activity is never in breach
+    pub min_messages: u64,
+    pub action: OtrAction,
+    /// Topic receiving the ratio of every active participant and instrument
+    pub metrics_topic: String,
+    /// Topic receiving the throttle state of participants, keyed by
+    /// participant, for gateways to hold their orders while throttled
+    pub throttle_topic: String,
+}
+
+impl Default for OrderToTradeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 3_600,
+            bucket_secs: 60,
+            evaluation_interval_ms: 5_000,
+            max_ratio: 100.0,
+            min_messages: 500,
+            action: OtrAction::Monitor,
+            metrics_topic: "metrics.order_to_trade".to_string(),
+            throttle_topic: "order.throttle".to_string(),
+        }
+    }
+}
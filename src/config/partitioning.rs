@@ -0,0 +1,56 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// What an outbound message is keyed by, and so which messages keep their
+/// relative order on one partition
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKey {
+    /// The key the engine publishes the message under, mostly its instrument
+    #[default]
+    Published,
+    /// The `instrument_id` of the message, or its published key without one
+    Instrument,
+    /// The `participant_id` of the message, or its published key without one
+    Participant,
+    /// Spread evenly over the partitions in turn, keeping the published key
+    /// but no order between messages
+    RoundRobin,
+}
+
+/// Hash choosing the partition of a key in place of librdkafka's default
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Partitioner {
+    /// The murmur2 partitioner of the Java client, so keys land where Java
+    /// producers of the same topic put them
+    Murmur2,
+    /// 32-bit FNV-1a
+    Fnv1a,
+}
+
+/// How the messages of a topic are keyed and partitioned
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct TopicPartitioning {
+    pub key: MessageKey,
+    /// librdkafka's partitioner when not set; round-robin messages take no
+    /// partitioner
+    pub partitioner: Option<Partitioner>,
+}
+
+/// Keying and partitioning of outbound messages, for each topic that needs
+/// other ordering guarantees than the default
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PartitioningConfig {
+    pub default: TopicPartitioning,
+    /// By topic name
+    pub topics: HashMap<String, TopicPartitioning>,
+}
+
+impl PartitioningConfig {
+    pub fn for_topic(&self, topic: &str) -> TopicPartitioning {
+        self.topics.get(topic).copied().unwrap_or(self.default)
+    }
+}
@@ -0,0 +1,25 @@
+use serde::Deserialize;
+
+/// Checks of the Kafka cluster run before the engine starts consuming
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PreflightConfig {
+    pub enabled: bool,
+    /// How long to wait for the cluster to answer each metadata or config request
+    pub timeout_ms: u64,
+    /// Fewest partitions every topic the engine uses must have
+    pub min_partitions: usize,
+    /// Refuse to start on warnings too, not only on errors
+    pub fail_on_warnings: bool,
+}
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_ms: 10_000,
+            min_partitions: 1,
+            fail_on_warnings: false,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use serde::Deserialize;
+
+/// Fields removed or pseudonymised on the way out to the given topics
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RedactionRule {
+    pub topics: Vec<String>,
+    /// Fields removed wherever they occur in a message
+    pub strip: Vec<String>,
+    /// Fields whose values are replaced by a salted hash wherever they occur,
+    /// so messages about the same participant or order can still be related
+    pub hash: Vec<String>,
+}
+
+/// Redaction of outbound messages, such as identities on public feeds, leaving
+/// topics without a rule complete
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RedactionConfig {
+    /// Secret mixed into hashed values, so they cannot be recovered by hashing
+    /// guessed identifiers
+    pub salt: String,
+    pub rules: Vec<RedactionRule>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            salt: String::new(),
+            rules: vec![RedactionRule {
+                topics: [
+                    "md.public.depth",
+                    "md.public.trades",
+                    "md.delayed.depth",
+                    "md.delayed.trades",
+                ]
+                .map(String::from)
+                .to_vec(),
+                strip: [
+                    "participant_id",
+                    "taker_participant_id",
+                    "maker_participant_id",
+                    "client_order_id",
+                    "oms_id",
+                    "tags",
+                    "taker_tags",
+                    "maker_tags",
+                ]
+                .map(String::from)
+                .to_vec(),
+                hash: Vec::new(),
+            }],
+        }
+    }
+}
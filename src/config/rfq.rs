@@ -0,0 +1,26 @@
+use serde::Deserialize;
+
+/// Settings for the request-for-quote workflow
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RfqConfig {
+    /// Quoting window used when a request does not specify one, in milliseconds
+    pub default_window_ms: u64,
+    /// Longest quoting window a request may ask for, in milliseconds
+    pub max_window_ms: u64,
+    /// Interval between sweeps for RFQs whose window has closed, in milliseconds
+    pub sweep_interval_ms: u64,
+    /// Topic receiving RFQ lifecycle events, keyed by RFQ id
+    pub events_topic: String,
+}
+
+impl Default for RfqConfig {
+    fn default() -> Self {
+        Self {
+            default_window_ms: 5_000,
+            max_window_ms: 60_000,
+            sweep_interval_ms: 100,
+            events_topic: "rfq.events".to_string(),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Most a participant may have resting at once; unbounded where absent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticipantLimits {
+    /// Resting orders across every instrument
+    pub max_open_orders: Option<usize>,
+    /// Price times remaining quantity of the resting orders across every
+    /// instrument, in raw price and quantity units
+    pub max_open_notional: Option<u64>,
+}
+
+/// Open order limits of each participant, with a default for the others
+///
+/// A sharded engine applies them on each shard to the orders on its books.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub default: ParticipantLimits,
+    pub participants: HashMap<String, ParticipantLimits>,
+}
+
+impl RiskConfig {
+    pub fn limits(&self, participant_id: &str) -> ParticipantLimits {
+        self.participants
+            .get(participant_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
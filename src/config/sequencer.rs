@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+/// Ordering of the commands of each instrument by their `sequence`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SequencerConfig {
+    pub enabled: bool,
+    /// How long commands ahead of a gap wait for it to fill before the gap is
+    /// alerted on and skipped
+    pub gap_timeout_ms: u64,
+    /// Most commands held back on one instrument; the gap is skipped when one
+    /// more arrives
+    pub max_held: usize,
+    /// How often held-back commands are checked against the timeout
+    pub sweep_interval_ms: u64,
+}
+
+impl Default for SequencerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gap_timeout_ms: 5_000,
+            max_held: 10_000,
+            sweep_interval_ms: 250,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// How a participant's order-entry sessions protect its resting orders
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct SessionPolicy {
+    /// Cancel the orders entered through a session when it drops without logging out
+    pub cancel_on_disconnect: bool,
+    /// A session silent for this long is treated as disconnected; 0 disables the
+    /// heartbeat check
+    pub heartbeat_timeout_ms: u64,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            cancel_on_disconnect: true,
+            heartbeat_timeout_ms: 10_000,
+        }
+    }
+}
+
+/// Session policies for direct order entry, with overrides per participant
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub default: SessionPolicy,
+    pub participants: HashMap<String, SessionPolicy>,
+}
+
+impl SessionConfig {
+    pub fn policy(&self, participant_id: &str) -> SessionPolicy {
+        self.participants
+            .get(participant_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+
+/// Splitting of the books across engine tasks
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ShardingConfig {
+    /// Engine tasks, each owning the books of the instruments hashed to it and
+    /// its own command channel of `channels.engine_commands`
+    pub shards: usize,
+}
+
+impl Default for ShardingConfig {
+    fn default() -> Self {
+        Self { shards: 1 }
+    }
+}
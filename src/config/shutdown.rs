@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+/// What happens on SIGINT or SIGTERM
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// Directory receiving a checksummed snapshot package of every book once
+    /// the queued commands are applied; `None` persists nothing
+    pub snapshot_dir: Option<String>,
+    /// How long the engine is given to drain its queue, persist the books and
+    /// publish what it has left before the process exits anyway, in milliseconds
+    pub drain_timeout_ms: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            snapshot_dir: Some("snapshots".to_string()),
+            drain_timeout_ms: 30_000,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Kinds of engine events a sink can receive
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Trade,
+    Delta,
+    Snapshot,
+    Order,
+    Execution,
+}
+
+/// Which events reach a sink
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct SinkFilter {
+    /// Event kinds passed on; empty passes every kind
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// Instruments passed on, along with the members of `instrument_groups`;
+    /// both empty passes every instrument
+    #[serde(default)]
+    pub instruments: Vec<String>,
+    /// Names of groups in the pipeline's `instrument_groups`
+    #[serde(default)]
+    pub instrument_groups: Vec<String>,
+    /// Participants whose trades and orders are passed on; empty passes every
+    /// participant. Deltas and snapshots belong to no participant and are not
+    /// affected.
+    #[serde(default)]
+    pub participants: Vec<String>,
+}
+
+impl SinkFilter {
+    /// `participants` are the sides of the event, `None` for events that have
+    /// none; an event whose sides are unknown passes no participant filter
+    pub fn accepts(
+        &self,
+        kind: EventKind,
+        instrument_id: &str,
+        participants: Option<&[&str]>,
+        groups: &HashMap<String, Vec<String>>,
+    ) -> bool {
+        let instrument_passes = (self.instruments.is_empty() && self.instrument_groups.is_empty())
+            || self.instruments.iter().any(|id| id == instrument_id)
+            || self.instrument_groups.iter().any(|group| {
+                groups
+                    .get(group)
+                    .is_some_and(|members| members.iter().any(|id| id == instrument_id))
+            });
+        let participant_passes = self.participants.is_empty()
+            || participants.is_none_or(|participants| {
+                participants
+                    .iter()
+                    .any(|participant| self.participants.iter().any(|id| id == participant))
+            });
+        (self.events.is_empty() || self.events.contains(&kind))
+            && instrument_passes
+            && participant_passes
+    }
+}
+
+/// Where a sink writes its events
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkTarget {
+    /// Publishes each event to a Kafka topic, keyed by instrument
+    Kafka { topic: String },
+    /// Appends each event to `<directory>/<sink name>.jsonl` from a writer thread
+    Jsonl {
+        directory: String,
+        /// Batches of events queued for the writer before new ones are dropped
+        queue_capacity: usize,
+    },
+}
+
+/// One output of the event pipeline
+#[derive(Debug, Deserialize, Clone)]
+pub struct SinkConfig {
+    /// Name used in logs and file names
+    pub name: String,
+    #[serde(flatten)]
+    pub target: SinkTarget,
+    #[serde(default)]
+    pub filter: SinkFilter,
+}
+
+/// Outputs receiving engine events, in addition to the market data feeds
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PipelineConfig {
+    pub sinks: Vec<SinkConfig>,
+    /// Named sets of instruments sink filters can refer to
+    #[serde(default)]
+    pub instrument_groups: HashMap<String, Vec<String>>,
+}
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+
+/// Settings of a soak run, which drives the engine with generated load and
+/// reports whether it stayed healthy
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SoakConfig {
+    pub duration_secs: u64,
+    /// Commands generated per second, spread over `instruments`
+    pub commands_per_sec: u64,
+    pub instruments: Vec<String>,
+    /// Orders kept resting per instrument; the oldest are cancelled beyond it
+    pub max_resting_orders: usize,
+    /// Time for books to fill up before the memory baseline is taken
+    pub warmup_secs: u64,
+    /// How often memory is sampled and progress logged
+    pub sample_interval_secs: u64,
+    /// Largest growth of resident memory over the baseline that still passes
+    pub max_memory_growth_pct: f64,
+    /// Seed of the generated load, so a failing run can be repeated
+    pub seed: u64,
+    /// File the JSON report is written to, besides the log
+    pub report_path: Option<String>,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            duration_secs: 4 * 60 * 60,
+            commands_per_sec: 5_000,
+            instruments: vec!["SOAK-1".to_string(), "SOAK-2".to_string()],
+            max_resting_orders: 2_000,
+            warmup_secs: 300,
+            sample_interval_secs: 60,
+            max_memory_growth_pct: 10.0,
+            seed: 1,
+            report_path: Some("diagnostics/soak-report.json".to_string()),
+        }
+    }
+}
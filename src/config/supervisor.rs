@@ -0,0 +1,39 @@
+use serde::Deserialize;
+
+/// What the supervisor does when the engine task panics
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PanicPolicy {
+    /// Log the panic and exit the process, so an orchestrator restarts it
+    Shutdown,
+    /// Respawn the engine with the books of the last checkpoint
+    Restart,
+}
+
+/// Supervision of the engine task
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    pub policy: PanicPolicy,
+    /// Restarts allowed within `restart_window_secs` before shutting down instead;
+    /// an engine that keeps panicking is most likely replaying the same bad state
+    pub max_restarts: u32,
+    pub restart_window_secs: u64,
+    /// Pause before respawning, in milliseconds
+    pub restart_delay_ms: u64,
+    /// How often the books are checkpointed for a restart, in milliseconds.
+    /// Commands applied after the last checkpoint are lost on a restart.
+    pub checkpoint_interval_ms: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            policy: PanicPolicy::Shutdown,
+            max_restarts: 3,
+            restart_window_secs: 300,
+            restart_delay_ms: 500,
+            checkpoint_interval_ms: 5_000,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use serde::Deserialize;
+
+/// Settings for evicting idle books to disk until their next command
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TieringConfig {
+    pub enabled: bool,
+    /// Directory holding one file per evicted book
+    pub directory: String,
+    /// How long a book goes without a command before it may be evicted
+    pub idle_ms: u64,
+    /// How often idle books are looked for
+    pub sweep_interval_ms: u64,
+}
+
+impl Default for TieringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "state/cold_books".to_string(),
+            idle_ms: 900_000,
+            sweep_interval_ms: 60_000,
+        }
+    }
+}
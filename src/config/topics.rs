@@ -0,0 +1,241 @@
+use crate::config::liveness::LivenessConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Inbound command, identified by the topic it arrives on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandKind {
+    InstrumentCreate,
+    InstrumentDelete,
+    InstrumentAdjust,
+    Alert,
+    OrderCreate,
+    OrderCancel,
+    OrderModify,
+    OrderReplace,
+    MassCancel,
+    KillSwitch,
+    Halt,
+    Resume,
+    AuctionStart,
+    AuctionUncross,
+    Admin,
+    TheoreticalPrice,
+    IndexDefine,
+    RfqRequest,
+    RfqQuote,
+    RfqExecute,
+    BlockTrade,
+    OmsHeartbeat,
+}
+
+/// Encoding of the messages on an inbound topic
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    #[default]
+    Json,
+    /// The messages of `proto/commands.proto`; only for order and instrument commands
+    Protobuf,
+    /// Avro framed for the Confluent Schema Registry, whose records mirror the
+    /// JSON payloads; needs `kafka.schema_registry_url`
+    Avro,
+}
+
+/// Names of the topics the engine consumes, and of its dead letter topic
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TopicMap {
+    pub instrument_create: String,
+    pub instrument_delete: String,
+    pub instrument_adjust: String,
+    /// Alerts raised upstream; consumed but not acted on yet
+    pub alert: String,
+    pub order_create: String,
+    pub order_cancel: String,
+    pub order_modify: String,
+    pub order_replace: String,
+    pub mass_cancel: String,
+    /// Cancels every order of a participant and blocks its new ones, or lifts
+    /// the block
+    pub kill_switch: String,
+    /// Halts trading on an instrument; cancels are still accepted
+    pub halt: String,
+    pub resume: String,
+    /// Starts a call auction: orders rest without matching until the uncross
+    pub auction_start: String,
+    pub auction_uncross: String,
+    pub admin: String,
+    pub theoretical_price: String,
+    pub index_define: String,
+    pub rfq_request: String,
+    pub rfq_quote: String,
+    pub rfq_execute: String,
+    pub block_trade: String,
+    /// Heartbeats of upstream order management systems
+    pub oms_heartbeat: String,
+    /// Receives messages that could not be parsed, with the reason
+    pub dead_letter: String,
+    /// Receives the topics and partitions paused by operators whenever they change
+    pub consumption: String,
+}
+
+impl Default for TopicMap {
+    fn default() -> Self {
+        Self {
+            instrument_create: "instrument.create".to_string(),
+            instrument_delete: "instrument.delete".to_string(),
+            instrument_adjust: "instrument.adjust".to_string(),
+            alert: "alert.create".to_string(),
+            order_create: "order.create".to_string(),
+            order_cancel: "order.cancelled".to_string(),
+            order_modify: "order.modify".to_string(),
+            order_replace: "order.replace".to_string(),
+            mass_cancel: "order.mass_cancel".to_string(),
+            kill_switch: "order.kill_switch".to_string(),
+            halt: "instrument.halt".to_string(),
+            resume: "instrument.resume".to_string(),
+            auction_start: "instrument.auction_start".to_string(),
+            auction_uncross: "instrument.auction_uncross".to_string(),
+            admin: "engine.admin".to_string(),
+            theoretical_price: "price.theoretical".to_string(),
+            index_define: "index.define".to_string(),
+            rfq_request: "rfq.request".to_string(),
+            rfq_quote: "rfq.quote".to_string(),
+            rfq_execute: "rfq.execute".to_string(),
+            block_trade: "trade.block".to_string(),
+            oms_heartbeat: "oms.heartbeat".to_string(),
+            dead_letter: "engine.dlq".to_string(),
+            consumption: "engine.consumption".to_string(),
+        }
+    }
+}
+
+/// Misconfigured topics found at startup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopicError {
+    /// A command has no topic name
+    Empty(CommandKind),
+    /// Several commands share a topic, so its messages could not be told apart
+    Shared {
+        topic: String,
+        kinds: Vec<CommandKind>,
+    },
+}
+
+impl fmt::Display for TopicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopicError::Empty(kind) => write!(f, "No topic configured for {kind:?} commands"),
+            TopicError::Shared { topic, kinds } => {
+                write!(
+                    f,
+                    "Topic {topic} is configured for several commands: {kinds:?}"
+                )
+            }
+        }
+    }
+}
+
+impl TopicMap {
+    pub fn commands(&self) -> [(CommandKind, &str); 22] {
+        [
+            (CommandKind::InstrumentCreate, &self.instrument_create),
+            (CommandKind::InstrumentDelete, &self.instrument_delete),
+            (CommandKind::InstrumentAdjust, &self.instrument_adjust),
+            (CommandKind::Alert, &self.alert),
+            (CommandKind::OrderCreate, &self.order_create),
+            (CommandKind::OrderCancel, &self.order_cancel),
+            (CommandKind::OrderModify, &self.order_modify),
+            (CommandKind::OrderReplace, &self.order_replace),
+            (CommandKind::MassCancel, &self.mass_cancel),
+            (CommandKind::KillSwitch, &self.kill_switch),
+            (CommandKind::Halt, &self.halt),
+            (CommandKind::Resume, &self.resume),
+            (CommandKind::AuctionStart, &self.auction_start),
+            (CommandKind::AuctionUncross, &self.auction_uncross),
+            (CommandKind::Admin, &self.admin),
+            (CommandKind::TheoreticalPrice, &self.theoretical_price),
+            (CommandKind::IndexDefine, &self.index_define),
+            (CommandKind::RfqRequest, &self.rfq_request),
+            (CommandKind::RfqQuote, &self.rfq_quote),
+            (CommandKind::RfqExecute, &self.rfq_execute),
+            (CommandKind::BlockTrade, &self.block_trade),
+            (CommandKind::OmsHeartbeat, &self.oms_heartbeat),
+        ]
+    }
+
+    /// Topics to subscribe to: those of every command, but OMS heartbeats
+    /// only while the liveness contract is on
+    pub fn subscriptions(&self, liveness: &LivenessConfig) -> Vec<&str> {
+        self.commands()
+            .into_iter()
+            .filter(|(kind, _)| *kind != CommandKind::OmsHeartbeat || liveness.enabled)
+            .map(|(_, topic)| topic)
+            .collect()
+    }
+
+    pub fn kind_of(&self, topic: &str) -> Option<CommandKind> {
+        self.commands()
+            .into_iter()
+            .find(|(_, name)| *name == topic)
+            .map(|(kind, _)| kind)
+    }
+
+    /// Checks that every command has a topic of its own
+    pub fn validate(&self) -> Result<(), TopicError> {
+        let mut by_topic: HashMap<&str, Vec<CommandKind>> = HashMap::new();
+        for (kind, topic) in self.commands() {
+            if topic.trim().is_empty() {
+                return Err(TopicError::Empty(kind));
+            }
+            by_topic.entry(topic).or_default().push(kind);
+        }
+        match by_topic.into_iter().find(|(_, kinds)| kinds.len() > 1) {
+            Some((topic, kinds)) => Err(TopicError::Shared {
+                topic: topic.to_string(),
+                kinds,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topics_route_to_their_command() {
+        let topics = TopicMap {
+            order_create: "orders.new".to_string(),
+            ..TopicMap::default()
+        };
+        assert_eq!(topics.validate(), Ok(()));
+        assert_eq!(topics.kind_of("orders.new"), Some(CommandKind::OrderCreate));
+        assert_eq!(topics.kind_of("order.create"), None);
+        assert_eq!(topics.kind_of("engine.admin"), Some(CommandKind::Admin));
+    }
+
+    #[test]
+    fn test_misconfigured_topics_are_rejected() {
+        let shared = TopicMap {
+            rfq_quote: "rfq.request".to_string(),
+            ..TopicMap::default()
+        };
+        assert_eq!(
+            shared.validate(),
+            Err(TopicError::Shared {
+                topic: "rfq.request".to_string(),
+                kinds: vec![CommandKind::RfqRequest, CommandKind::RfqQuote],
+            })
+        );
+        let empty = TopicMap {
+            admin: " ".to_string(),
+            ..TopicMap::default()
+        };
+        assert_eq!(empty.validate(), Err(TopicError::Empty(CommandKind::Admin)));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::orderbook::scale::NumberFormat;
+use serde::Deserialize;
+
+/// Encoding of the executions published on the trade topic
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSerializer {
+    /// One JSON object per match, listing its fills
+    #[default]
+    Json,
+    /// One comma-separated line per fill, without a header: trade_id,
+    /// instrument_id, order_id, maker_order_id, aggressor_side, price, quantity,
+    /// timestamp
+    Csv,
+}
+
+/// Settings for outbound trade reports
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TradeReportConfig {
+    /// Topic receiving trade reports, keyed by instrument
+    pub trades_topic: String,
+    /// Whether prices and quantities are raw integers or decimal strings
+    pub number_format: NumberFormat,
+    /// Publish the result of every match on a book, not only block trades
+    pub publish_executions: bool,
+    pub serializer: TradeSerializer,
+    /// Publish every fill on a book to the tape topic as well, for books
+    /// created with a trade tape
+    pub publish_tape: bool,
+    /// Topic receiving one JSON message per fill, keyed by instrument and
+    /// numbered by the book's tape, with prices and quantities raw
+    pub tape_topic: String,
+}
+
+impl Default for TradeReportConfig {
+    fn default() -> Self {
+        Self {
+            trades_topic: "trade.executed".to_string(),
+            number_format: NumberFormat::Raw,
+            publish_executions: true,
+            serializer: TradeSerializer::Json,
+            publish_tape: false,
+            tape_topic: "trade.tape".to_string(),
+        }
+    }
+}
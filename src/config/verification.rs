@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+/// Periodic hashes of book state, for checking that a replay reproduces a run
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct VerificationConfig {
+    pub enabled: bool,
+    /// Commands on an instrument between two hashes of its book; 1 pinpoints the
+    /// exact command a replay diverges on, at the cost of hashing after every one
+    pub interval_commands: u64,
+    /// Topic the hashes are published to
+    pub topic: String,
+    /// Hashes recorded by an earlier run, one JSON object per line. When set,
+    /// hashes are checked against these instead of being published.
+    pub expected_path: Option<String>,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_commands: 1_000,
+            topic: "engine.state_hashes".to_string(),
+            expected_path: None,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use serde::Deserialize;
+
+/// When appended commands are forced to disk
+///
+/// Every command is written to the log file before it is applied, so it
+/// survives the engine process crashing; syncing only decides what survives the
+/// host losing power.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    /// After every command, at the cost of a disk flush per command
+    Always,
+    /// At most every `fsync_interval_ms`, on the next command after it passes
+    Interval,
+    /// Whenever the operating system writes its caches back
+    Never,
+}
+
+/// Write-ahead log of the commands applied by the engine
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct WalConfig {
+    pub enabled: bool,
+    /// Directory of the log's segment files
+    pub directory: String,
+    /// Size past which a new segment file is started
+    pub segment_bytes: u64,
+    /// Age past which a new segment file is started, 0 for none
+    pub segment_ms: u64,
+    pub fsync: FsyncPolicy,
+    pub fsync_interval_ms: u64,
+    /// Rebuild the books on startup by replaying the log; without it the
+    /// engine starts empty and a later recovery replays the older commands too
+    pub recover: bool,
+    /// How often the engine snapshots its books into the log's directory and
+    /// compacts the segments the snapshot covers, 0 for never
+    pub snapshot_interval_ms: u64,
+    /// Segments covered by a snapshot are kept for audit and replay until their
+    /// last write is this old...
+    pub retention_ms: u64,
+    /// ...or until they add up to more than this, newest first
+    pub retention_bytes: u64,
+}
+
+impl Default for WalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "wal".to_string(),
+            segment_bytes: 64 * 1024 * 1024,
+            segment_ms: 60 * 60 * 1000,
+            fsync: FsyncPolicy::Interval,
+            fsync_interval_ms: 100,
+            recover: true,
+            snapshot_interval_ms: 5 * 60 * 1000,
+            retention_ms: 24 * 60 * 60 * 1000,
+            retention_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
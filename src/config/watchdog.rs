@@ -0,0 +1,30 @@
+use serde::Deserialize;
+
+/// Detection of a consumer-to-engine pipeline that stopped moving
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// Seconds without an applied command, while commands are waiting, before
+    /// processing counts as stalled
+    pub stall_secs: u64,
+    /// How often progress is checked, in milliseconds
+    pub check_interval_ms: u64,
+    /// Timeout of the broker queries measuring consumer lag, in milliseconds
+    pub lag_timeout_ms: u64,
+    /// Recreate the Kafka consumer on a stall, resuming from the committed
+    /// offsets; otherwise the stall is only alerted on
+    pub restart_consumer: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stall_secs: 30,
+            check_interval_ms: 1_000,
+            lag_timeout_ms: 2_000,
+            restart_consumer: false,
+        }
+    }
+}
@@ -0,0 +1,245 @@
+// src/conflation.rs
+use crate::config::feeds::AdaptiveConflationConfig;
+use crate::orderbook::trade::BookContext;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// What one instrument did within the current window
+#[derive(Default)]
+struct Activity {
+    last_bbo: Option<BookContext>,
+    bbo_changes: u64,
+    depth_messages: u64,
+    depth_bytes: u64,
+    /// Average size of a depth message, carried over windows without one
+    average_depth_bytes: f64,
+}
+
+/// Per-instrument depth intervals of a feed, reassigned every window from the
+/// instruments' top-of-book change rates
+///
+/// An instrument's demand is estimated as one depth message per top-of-book
+/// change, bounded by the profile's base interval. When the feed's trades and
+/// the demand of every instrument exceed the cap, busy instruments share a
+/// common ceiling on their message rate, found by bisection, so the busiest are
+/// conflated first and quiet ones are left alone.
+pub struct Conflator {
+    config: AdaptiveConflationConfig,
+    base_interval_ms: u64,
+    /// Start of the current window, from the first tick
+    window_start: Option<u64>,
+    instruments: HashMap<String, Activity>,
+    trade_bytes: u64,
+    /// Intervals of the instruments conflated beyond the base interval
+    intervals: HashMap<String, u64>,
+    over_cap: bool,
+}
+
+impl Conflator {
+    pub fn new(config: AdaptiveConflationConfig, base_interval_ms: u64) -> Self {
+        Self {
+            config,
+            base_interval_ms,
+            window_start: None,
+            instruments: HashMap::new(),
+            trade_bytes: 0,
+            intervals: HashMap::new(),
+            over_cap: false,
+        }
+    }
+
+    /// Counts a top-of-book change, if the book's touch moved since last seen
+    pub fn on_book_change(&mut self, instrument_id: &str, bbo: BookContext) {
+        let activity = self
+            .instruments
+            .entry(instrument_id.to_string())
+            .or_default();
+        if activity.last_bbo != Some(bbo) {
+            activity.last_bbo = Some(bbo);
+            activity.bbo_changes += 1;
+        }
+    }
+
+    pub fn record_depth(&mut self, instrument_id: &str, bytes: usize) {
+        let activity = self
+            .instruments
+            .entry(instrument_id.to_string())
+            .or_default();
+        activity.depth_messages += 1;
+        activity.depth_bytes += bytes as u64;
+    }
+
+    pub fn record_trade(&mut self, bytes: usize) {
+        self.trade_bytes += bytes as u64;
+    }
+
+    /// Minimum time between depth updates of the instrument
+    pub fn interval_ms(&self, instrument_id: &str) -> u64 {
+        self.intervals
+            .get(instrument_id)
+            .copied()
+            .unwrap_or(self.base_interval_ms)
+    }
+
+    /// Reassigns the intervals once the window has passed
+    pub fn on_tick(&mut self, now: u64) {
+        let start = *self.window_start.get_or_insert(now);
+        let elapsed_ms = now.saturating_sub(start);
+        if elapsed_ms < self.config.window_ms.max(1) {
+            return;
+        }
+        self.window_start = Some(now);
+        self.reassign(elapsed_ms as f64 / 1_000.0);
+    }
+
+    pub fn forget(&mut self, instrument_id: &str) {
+        self.instruments.remove(instrument_id);
+        self.intervals.remove(instrument_id);
+    }
+
+    fn reassign(&mut self, elapsed_secs: f64) {
+        let base_rate = match self.base_interval_ms {
+            0 => f64::INFINITY,
+            interval => 1_000.0 / interval as f64,
+        };
+        // Bytes a second nothing can reduce: trades, and depth of quiet instruments
+        let mut fixed = std::mem::take(&mut self.trade_bytes) as f64 / elapsed_secs;
+        // Depth messages a second and their size, of each busy instrument
+        let mut busy = Vec::new();
+        for (instrument_id, activity) in &mut self.instruments {
+            if activity.depth_messages > 0 {
+                activity.average_depth_bytes =
+                    activity.depth_bytes as f64 / activity.depth_messages as f64;
+            }
+            let changes = activity.bbo_changes as f64 / elapsed_secs;
+            let rate = changes.min(base_rate);
+            if changes <= self.config.quiet_changes_per_sec {
+                fixed += rate * activity.average_depth_bytes;
+            } else {
+                busy.push((instrument_id.clone(), rate, activity.average_depth_bytes));
+            }
+            activity.bbo_changes = 0;
+            activity.depth_messages = 0;
+            activity.depth_bytes = 0;
+        }
+        let budget = self.config.max_bytes_per_sec as f64 - fixed;
+        let demand = |ceiling: f64| -> f64 {
+            busy.iter()
+                .map(|(_, rate, bytes)| rate.min(ceiling) * bytes)
+                .sum()
+        };
+        self.intervals.clear();
+        let Some(highest) = busy.iter().map(|(_, rate, _)| *rate).reduce(f64::max) else {
+            return;
+        };
+        if demand(highest) <= budget {
+            if std::mem::take(&mut self.over_cap) {
+                info!("Feed is back under its bandwidth cap");
+            }
+            return;
+        }
+        let floor = 1_000.0 / self.config.max_interval_ms.max(1) as f64;
+        let ceiling = if demand(floor) > budget {
+            if !std::mem::replace(&mut self.over_cap, true) {
+                warn!(
+                    "Feed exceeds its cap of {} bytes/s even at the coarsest interval of {} ms",
+                    self.config.max_bytes_per_sec, self.config.max_interval_ms
+                );
+            }
+            floor
+        } else {
+            self.over_cap = false;
+            let (mut low, mut high) = (floor, highest);
+            for _ in 0..32 {
+                let middle = (low + high) / 2.0;
+                if demand(middle) <= budget {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            low
+        };
+        let interval = ((1_000.0 / ceiling).ceil() as u64).clamp(
+            self.base_interval_ms,
+            self.config.max_interval_ms.max(self.base_interval_ms),
+        );
+        for (instrument_id, rate, _) in busy {
+            if rate > ceiling {
+                self.intervals.insert(instrument_id, interval);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbo(best_bid: u64) -> BookContext {
+        BookContext {
+            best_bid: Some(best_bid),
+            ..BookContext::default()
+        }
+    }
+
+    /// Feeds `changes` top-of-book changes and as many 100-byte depth messages
+    fn churn(conflator: &mut Conflator, instrument_id: &str, changes: u64) {
+        for change in 0..changes {
+            conflator.on_book_change(instrument_id, bbo(change + 1));
+            conflator.record_depth(instrument_id, 100);
+        }
+    }
+
+    fn conflator(max_bytes_per_sec: u64) -> Conflator {
+        Conflator::new(
+            AdaptiveConflationConfig {
+                max_bytes_per_sec,
+                quiet_changes_per_sec: 5.0,
+                max_interval_ms: 1_000,
+                window_ms: 1_000,
+            },
+            0,
+        )
+    }
+
+    #[test]
+    fn test_busy_instruments_are_conflated_first() {
+        // 1,000 + 200 + 4 messages of 100 bytes against a cap of 50,000 bytes/s
+        let mut conflator = conflator(50_000);
+        conflator.on_tick(0);
+        churn(&mut conflator, "BTC", 1_000);
+        churn(&mut conflator, "ETH", 200);
+        churn(&mut conflator, "DOGE", 4);
+        // An unchanged touch is not a change
+        conflator.on_book_change("DOGE", bbo(4));
+        conflator.on_tick(999);
+        assert_eq!(conflator.interval_ms("BTC"), 0);
+
+        conflator.on_tick(1_000);
+        // The busy instruments share a ceiling of 296 messages a second, which
+        // only BTC exceeds
+        assert_eq!(conflator.interval_ms("BTC"), 4);
+        assert_eq!(conflator.interval_ms("ETH"), 0);
+        assert_eq!(conflator.interval_ms("DOGE"), 0);
+
+        // Quiet again, back to publishing every change
+        churn(&mut conflator, "BTC", 3);
+        conflator.on_tick(2_000);
+        assert_eq!(conflator.interval_ms("BTC"), 0);
+    }
+
+    #[test]
+    fn test_intervals_stop_at_the_coarsest() {
+        let mut conflator = conflator(100);
+        conflator.on_tick(0);
+        conflator.record_trade(1_000);
+        churn(&mut conflator, "BTC", 50);
+        conflator.on_tick(1_000);
+        assert_eq!(conflator.interval_ms("BTC"), 1_000);
+        assert!(conflator.over_cap);
+
+        conflator.forget("BTC");
+        assert_eq!(conflator.interval_ms("BTC"), 0);
+    }
+}
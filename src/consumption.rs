@@ -0,0 +1,251 @@
+// src/consumption.rs
+use crate::publisher::Publisher;
+use crate::rebalance::RebalanceContext;
+use crate::utils::current_time_millis;
+use rdkafka::TopicPartitionList;
+use rdkafka::consumer::Consumer;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Topics and partitions an operator paused, by topic; an empty set stands
+/// for every partition of the topic
+#[derive(Debug, Default, Clone, PartialEq)]
+struct PausedTopics(BTreeMap<String, BTreeSet<i32>>);
+
+impl PausedTopics {
+    fn pause(&mut self, topic: &str, partitions: &[i32]) {
+        if partitions.is_empty() {
+            self.0.insert(topic.to_string(), BTreeSet::new());
+            return;
+        }
+        match self.0.get_mut(topic) {
+            // Already paused as a whole
+            Some(paused) if paused.is_empty() => {}
+            Some(paused) => paused.extend(partitions),
+            None => {
+                self.0
+                    .insert(topic.to_string(), partitions.iter().copied().collect());
+            }
+        }
+    }
+
+    /// Returns false for partitions of a topic paused as a whole, which can
+    /// only be resumed as a whole
+    fn resume(&mut self, topic: &str, partitions: &[i32]) -> bool {
+        if partitions.is_empty() {
+            self.0.remove(topic);
+            return true;
+        }
+        let Some(paused) = self.0.get_mut(topic) else {
+            return true;
+        };
+        if paused.is_empty() {
+            return false;
+        }
+        for partition in partitions {
+            paused.remove(partition);
+        }
+        if paused.is_empty() {
+            self.0.remove(topic);
+        }
+        true
+    }
+
+    fn is_paused(&self, topic: &str, partition: i32) -> bool {
+        self.0
+            .get(topic)
+            .is_some_and(|paused| paused.is_empty() || paused.contains(&partition))
+    }
+}
+
+/// A topic consumption is paused on
+#[derive(Debug, Serialize)]
+pub struct PausedTopic {
+    pub topic: String,
+    /// Paused partitions, empty when the whole topic is
+    pub partitions: Vec<i32>,
+}
+
+/// Published on `kafka.topics.consumption` whenever topics are paused or resumed
+#[derive(Debug, Serialize)]
+pub struct ConsumptionState {
+    pub timestamp: u64,
+    pub paused: Vec<PausedTopic>,
+}
+
+/// Pauses and resumes the consumption of inbound topics on operator request
+///
+/// Pausing e.g. `order.create` during maintenance stops new orders while
+/// cancels keep being processed. Messages of a paused partition wait in Kafka
+/// until it is resumed. The pauses outlive rebalances and consumer restarts:
+/// they are applied again to every new assignment. The topic of admin commands
+/// is never paused, as nothing could resume it.
+#[derive(Clone)]
+pub struct ConsumptionControl {
+    paused: Arc<Mutex<PausedTopics>>,
+    admin_topic: String,
+    state_topic: String,
+}
+
+impl ConsumptionControl {
+    pub fn new(admin_topic: &str, state_topic: &str) -> Self {
+        Self {
+            paused: Arc::default(),
+            admin_topic: admin_topic.to_string(),
+            state_topic: state_topic.to_string(),
+        }
+    }
+
+    fn paused(&self) -> std::sync::MutexGuard<'_, PausedTopics> {
+        self.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether messages of a partition are held back
+    pub fn is_paused(&self, topic: &str, partition: i32) -> bool {
+        self.paused().is_paused(topic, partition)
+    }
+
+    pub fn pause(
+        &self,
+        consumer: &impl Consumer<RebalanceContext>,
+        publisher: &Publisher,
+        topics: &[String],
+        partitions: &[i32],
+    ) {
+        {
+            let mut paused = self.paused();
+            for topic in topics {
+                if *topic == self.admin_topic {
+                    warn!("Not pausing {}, admin commands could not resume it", topic);
+                    continue;
+                }
+                paused.pause(topic, partitions);
+            }
+        }
+        info!("Paused consumption of {:?} {:?}", topics, partitions);
+        self.changed(consumer, publisher);
+    }
+
+    pub fn resume(
+        &self,
+        consumer: &impl Consumer<RebalanceContext>,
+        publisher: &Publisher,
+        topics: &[String],
+        partitions: &[i32],
+    ) {
+        {
+            let mut paused = self.paused();
+            for topic in topics {
+                if !paused.resume(topic, partitions) {
+                    warn!(
+                        "Topic {} is paused as a whole, it can only be resumed as a whole",
+                        topic
+                    );
+                }
+            }
+        }
+        info!("Resumed consumption of {:?} {:?}", topics, partitions);
+        self.changed(consumer, publisher);
+    }
+
+    fn changed(&self, consumer: &impl Consumer<RebalanceContext>, publisher: &Publisher) {
+        self.apply(consumer);
+        let state = self.state(current_time_millis());
+        publisher.publish(&self.state_topic, "consumption", &state);
+    }
+
+    /// Pauses and resumes the partitions assigned to `consumer` to match what
+    /// operators asked for
+    pub fn apply(&self, consumer: &impl Consumer<RebalanceContext>) {
+        let assignment = match consumer.assignment() {
+            Ok(assignment) => assignment,
+            Err(e) => {
+                warn!("Failed to read the consumer's assignment: {}", e);
+                return;
+            }
+        };
+        let (mut pause, mut resume) = (TopicPartitionList::new(), TopicPartitionList::new());
+        {
+            let paused = self.paused();
+            for partition in assignment.elements() {
+                let list = if paused.is_paused(partition.topic(), partition.partition()) {
+                    &mut pause
+                } else {
+                    &mut resume
+                };
+                list.add_partition(partition.topic(), partition.partition());
+            }
+        }
+        if pause.count() > 0
+            && let Err(e) = consumer.pause(&pause)
+        {
+            warn!("Failed to pause partitions: {}", e);
+        }
+        if resume.count() > 0
+            && let Err(e) = consumer.resume(&resume)
+        {
+            warn!("Failed to resume partitions: {}", e);
+        }
+    }
+
+    pub fn state(&self, timestamp: u64) -> ConsumptionState {
+        ConsumptionState {
+            timestamp,
+            paused: self
+                .paused()
+                .0
+                .iter()
+                .map(|(topic, partitions)| PausedTopic {
+                    topic: topic.clone(),
+                    partitions: partitions.iter().copied().collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pauses_by_topic_and_partition() {
+        let mut paused = PausedTopics::default();
+        paused.pause("order.create", &[]);
+        paused.pause("order.modify", &[1, 2]);
+        assert!(paused.is_paused("order.create", 7));
+        assert!(paused.is_paused("order.modify", 2));
+        assert!(!paused.is_paused("order.modify", 0));
+        assert!(!paused.is_paused("order.cancelled", 0));
+
+        // Pausing part of a topic paused as a whole leaves all of it paused
+        paused.pause("order.create", &[1]);
+        assert!(paused.is_paused("order.create", 0));
+        assert!(!paused.resume("order.create", &[0]));
+        assert!(paused.resume("order.create", &[]));
+        assert!(!paused.is_paused("order.create", 0));
+
+        assert!(paused.resume("order.modify", &[1, 2]));
+        assert_eq!(paused, PausedTopics::default());
+    }
+
+    #[test]
+    fn test_state_lists_paused_topics() {
+        let control = ConsumptionControl::new("engine.admin", "engine.consumption");
+        control.paused().pause("order.create", &[]);
+        control.paused().pause("order.modify", &[3]);
+        let state = serde_json::to_value(control.state(5)).unwrap();
+        assert_eq!(
+            state,
+            serde_json::json!({
+                "timestamp": 5,
+                "paused": [
+                    { "topic": "order.create", "partitions": [] },
+                    { "topic": "order.modify", "partitions": [3] },
+                ],
+            })
+        );
+    }
+}
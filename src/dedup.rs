@@ -0,0 +1,182 @@
+// src/dedup.rs
+use crate::config::dedup::DedupConfig;
+use crate::config::topics::CommandKind;
+use crate::helpers::EngineCommand;
+use std::collections::{HashMap, VecDeque};
+use tracing::warn;
+
+/// What tells a command apart from a redelivery of it: the topic it arrives
+/// on, the instrument and order it names and its producer sequence number
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CommandKey {
+    kind: CommandKind,
+    instrument_id: String,
+    order_id: u64,
+    sequence: Option<u64>,
+}
+
+impl CommandKey {
+    /// Order ids are never reused within an instrument, so a create is keyed
+    /// with or without a sequence; cancels, modifies and replaces of the same
+    /// order only tell a redelivery from another command by theirs
+    fn of(cmd: &EngineCommand) -> Option<Self> {
+        let (instrument_id, order_id, sequence) = match cmd {
+            EngineCommand::OrderCreate(order) => {
+                (&order.instrument_id, order.order_id, order.sequence)
+            }
+            EngineCommand::OrderCancel(cancel) => (
+                &cancel.instrument_id,
+                cancel.order_id,
+                Some(cancel.sequence?),
+            ),
+            EngineCommand::OrderModify(modify) => (
+                &modify.instrument_id,
+                modify.order_id,
+                Some(modify.sequence?),
+            ),
+            EngineCommand::OrderReplace(replace) => (
+                &replace.instrument_id,
+                replace.order_id,
+                Some(replace.sequence?),
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            kind: cmd.kind(),
+            instrument_id: instrument_id.clone(),
+            order_id,
+            sequence,
+        })
+    }
+}
+
+/// The order commands seen most recently, to drop the ones Kafka delivers
+/// again, such as after a rebalance
+///
+/// A command is remembered whether or not it is then applied, so a create
+/// resent after a rejection needs a new sequence. A duplicate is answered with
+/// a reject, the original having been answered already. The window starts
+/// empty with the engine, and each shard keeps its own over the instruments it
+/// owns.
+pub struct DuplicateWindow {
+    enabled: bool,
+    capacity: usize,
+    /// When each command remembered was last seen, counting commands
+    seen: HashMap<CommandKey, u64>,
+    /// Commands in the order they were seen, including earlier sightings of
+    /// ones seen again since
+    recency: VecDeque<(CommandKey, u64)>,
+    clock: u64,
+}
+
+impl DuplicateWindow {
+    pub fn new(config: &DedupConfig) -> Self {
+        Self {
+            enabled: config.enabled && config.window > 0,
+            capacity: config.window,
+            seen: HashMap::new(),
+            recency: VecDeque::new(),
+            clock: 0,
+        }
+    }
+
+    /// Whether a command was seen within the window, remembering it either way
+    pub fn is_duplicate(&mut self, cmd: &EngineCommand) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let Some(key) = CommandKey::of(cmd) else {
+            return false;
+        };
+        self.clock += 1;
+        let duplicate = self.seen.insert(key.clone(), self.clock).is_some();
+        if duplicate {
+            warn!(
+                "Dropping duplicate {:?} command for order {} on {} (sequence {:?})",
+                key.kind, key.order_id, key.instrument_id, key.sequence
+            );
+        }
+        self.recency.push_back((key, self.clock));
+        while self.seen.len() > self.capacity {
+            let Some((key, seen_at)) = self.recency.pop_front() else {
+                break;
+            };
+            if self.seen.get(&key) == Some(&seen_at) {
+                self.seen.remove(&key);
+            }
+        }
+        // Commands seen again leave their earlier sightings behind
+        if self.recency.len() > 2 * self.capacity {
+            let seen = &self.seen;
+            self.recency
+                .retain(|(key, seen_at)| seen.get(key) == Some(seen_at));
+        }
+        duplicate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(kind: CommandKind, order_id: u64, sequence: Option<u64>) -> EngineCommand {
+        on("BTC", kind, order_id, sequence)
+    }
+
+    fn on(
+        instrument_id: &str,
+        kind: CommandKind,
+        order_id: u64,
+        sequence: Option<u64>,
+    ) -> EngineCommand {
+        let mut payload = serde_json::json!({
+            "instrument_id": instrument_id,
+            "order_id": order_id,
+            "side": "BUY",
+            "price": 100,
+            "quantity": 5,
+            "time_in_force": "GTC",
+            "order_type": "LIMIT",
+        });
+        if let Some(sequence) = sequence {
+            payload["sequence"] = sequence.into();
+        }
+        EngineCommand::parse(kind, &payload.to_string())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_redeliveries_within_the_window_are_duplicates() {
+        let mut window = DuplicateWindow::new(&DedupConfig {
+            enabled: true,
+            window: 2,
+        });
+        let create = |order_id| command(CommandKind::OrderCreate, order_id, None);
+        assert!(!window.is_duplicate(&create(1)));
+        assert!(window.is_duplicate(&create(1)));
+        // The same order id on another instrument is another order
+        let eth = on("ETH", CommandKind::OrderCreate, 1, None);
+        assert!(!window.is_duplicate(&eth));
+        assert!(window.is_duplicate(&create(1)));
+        // Without a sequence, modifies of an order cannot be told apart
+        let modify = |sequence| command(CommandKind::OrderModify, 1, sequence);
+        assert!(!window.is_duplicate(&modify(None)));
+        assert!(!window.is_duplicate(&modify(None)));
+        assert!(!window.is_duplicate(&modify(Some(7))));
+        assert!(window.is_duplicate(&modify(Some(7))));
+        assert!(window.is_duplicate(&create(1)));
+
+        // The command seen least recently is forgotten first
+        assert!(!window.is_duplicate(&modify(Some(8))));
+        assert!(window.is_duplicate(&create(1)));
+        assert!(!window.is_duplicate(&modify(Some(7))));
+
+        let mut disabled = DuplicateWindow::new(&DedupConfig {
+            enabled: false,
+            window: 2,
+        });
+        assert!(!disabled.is_duplicate(&create(1)));
+        assert!(!disabled.is_duplicate(&create(1)));
+    }
+}
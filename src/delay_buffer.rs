@@ -0,0 +1,330 @@
+// src/delay_buffer.rs
+use crate::publisher::Publisher;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Released entries left in the journal before it is rewritten
+const COMPACT_AFTER_RELEASED: usize = 10_000;
+
+/// A message held back until its release time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DelayedMessage {
+    seq: u64,
+    release_at: u64,
+    topic: String,
+    key: String,
+    payload: String,
+}
+
+impl DelayedMessage {
+    fn size(&self) -> usize {
+        self.topic.len() + self.key.len() + self.payload.len()
+    }
+}
+
+/// Append-only file of buffered messages plus a cursor of the last one handled
+///
+/// Entries at or below the cursor were published (or dropped) before the restart
+/// and are skipped when the journal is loaded.
+struct Journal {
+    path: PathBuf,
+    cursor_path: PathBuf,
+    file: File,
+    /// Entries in the file at or below the cursor
+    released: usize,
+    /// Sequence number to continue from, past everything already journaled
+    next_seq: u64,
+}
+
+impl Journal {
+    fn open(directory: &str, name: &str) -> io::Result<(Self, Vec<DelayedMessage>)> {
+        std::fs::create_dir_all(directory)?;
+        let path = Path::new(directory).join(format!("{name}.journal"));
+        let cursor_path = Path::new(directory).join(format!("{name}.cursor"));
+        let cursor: Option<u64> = std::fs::read_to_string(&cursor_path)
+            .ok()
+            .and_then(|cursor| cursor.trim().parse().ok());
+
+        let mut pending = Vec::new();
+        let mut released = 0;
+        let mut next_seq = cursor.map_or(0, |cursor| cursor + 1);
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                let entry = serde_json::from_str::<DelayedMessage>(&line);
+                if let Ok(message) = &entry {
+                    next_seq = next_seq.max(message.seq + 1);
+                }
+                match entry {
+                    Ok(message) if cursor.is_some_and(|cursor| message.seq <= cursor) => {
+                        released += 1;
+                    }
+                    Ok(message) => pending.push(message),
+                    // A torn final write after a crash; everything before it is intact
+                    Err(e) => warn!("Skipping unreadable entry in {}: {}", path.display(), e),
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let journal = Self {
+            path,
+            cursor_path,
+            file,
+            released,
+            next_seq,
+        };
+        Ok((journal, pending))
+    }
+
+    fn append(&mut self, message: &DelayedMessage) -> io::Result<()> {
+        let mut line = serde_json::to_string(message).map_err(io::Error::other)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())
+    }
+
+    fn advance(&mut self, cursor: u64, handled: usize) -> io::Result<()> {
+        self.released += handled;
+        let tmp = self.cursor_path.with_extension("cursor.tmp");
+        std::fs::write(&tmp, cursor.to_string())?;
+        std::fs::rename(&tmp, &self.cursor_path)
+    }
+
+    /// Rewrites the journal with only the entries still pending
+    fn compact<'a>(&mut self, pending: impl Iterator<Item = &'a DelayedMessage>) -> io::Result<()> {
+        let tmp = self.path.with_extension("journal.tmp");
+        let mut contents = String::new();
+        for message in pending {
+            contents.push_str(&serde_json::to_string(message).map_err(io::Error::other)?);
+            contents.push('\n');
+        }
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.released = 0;
+        Ok(())
+    }
+}
+
+/// Holds serialized messages until their release time, then publishes them
+///
+/// Memory is bounded by `max_bytes`: when full, the oldest messages are dropped
+/// first. With a journal directory, buffered messages survive restarts.
+pub struct DelayBuffer {
+    name: String,
+    messages: VecDeque<DelayedMessage>,
+    buffered_bytes: usize,
+    max_bytes: usize,
+    next_seq: u64,
+    /// Messages dropped because the buffer was full, since last taken
+    dropped: u64,
+    journal: Option<Journal>,
+}
+
+impl DelayBuffer {
+    pub fn new(name: &str, max_bytes: usize, journal_dir: Option<&str>) -> Self {
+        let mut buffer = Self {
+            name: name.to_string(),
+            messages: VecDeque::new(),
+            buffered_bytes: 0,
+            max_bytes,
+            next_seq: 0,
+            dropped: 0,
+            journal: None,
+        };
+        if let Some(directory) = journal_dir {
+            match Journal::open(directory, name) {
+                Ok((journal, pending)) => {
+                    info!(
+                        "Restored {} delayed messages for {} from {}",
+                        pending.len(),
+                        name,
+                        journal.path.display()
+                    );
+                    buffer.next_seq = journal.next_seq;
+                    buffer.buffered_bytes = pending.iter().map(DelayedMessage::size).sum();
+                    buffer.messages = pending.into();
+                    buffer.journal = Some(journal);
+                }
+                Err(e) => warn!(
+                    "Delay buffer {} is not persisted, failed to open journal in {}: {}",
+                    name, directory, e
+                ),
+            }
+        }
+        buffer
+    }
+
+    /// Buffers a serialized message for publishing at `release_at`
+    ///
+    /// Release times must not decrease, which holds when every message of a buffer
+    /// has the same delay.
+    pub fn push(&mut self, release_at: u64, topic: &str, key: &str, payload: String) {
+        let message = DelayedMessage {
+            seq: self.next_seq,
+            release_at,
+            topic: topic.to_string(),
+            key: key.to_string(),
+            payload,
+        };
+        self.next_seq += 1;
+        let size = message.size();
+        if size > self.max_bytes {
+            self.dropped += 1;
+            warn!(
+                "Dropping {} byte message for {} from delay buffer {}: larger than the {} byte bound",
+                size, message.topic, self.name, self.max_bytes
+            );
+            return;
+        }
+
+        let mut evicted = 0;
+        while self.buffered_bytes + size > self.max_bytes
+            && let Some(oldest) = self.messages.pop_front()
+        {
+            self.buffered_bytes -= oldest.size();
+            evicted += 1;
+        }
+        if evicted > 0 {
+            self.dropped += evicted as u64;
+            warn!(
+                "Delay buffer {} is full, dropped {} oldest messages",
+                self.name, evicted
+            );
+            let cursor = self.messages.front().map_or(message.seq, |m| m.seq) - 1;
+            self.advance_journal(cursor, evicted);
+        }
+
+        if let Some(journal) = &mut self.journal
+            && let Err(e) = journal.append(&message)
+        {
+            warn!("Failed to journal delayed message for {}: {}", self.name, e);
+        }
+        self.buffered_bytes += size;
+        self.messages.push_back(message);
+    }
+
+    /// Publishes every message whose release time has passed, returning how many
+    pub fn release(&mut self, publisher: &Publisher, now: u64) -> usize {
+        let mut released = 0;
+        let mut cursor = None;
+        while self
+            .messages
+            .front()
+            .is_some_and(|message| message.release_at <= now)
+        {
+            let Some(message) = self.messages.pop_front() else {
+                break;
+            };
+            self.buffered_bytes -= message.size();
+            cursor = Some(message.seq);
+            publisher.publish_serialized(&message.topic, &message.key, message.payload);
+            released += 1;
+        }
+        if let Some(cursor) = cursor {
+            self.advance_journal(cursor, released);
+        }
+        released
+    }
+
+    /// Messages dropped because the buffer was full since the last call
+    pub fn take_dropped(&mut self) -> u64 {
+        std::mem::take(&mut self.dropped)
+    }
+
+    fn advance_journal(&mut self, cursor: u64, handled: usize) {
+        let Some(journal) = &mut self.journal else {
+            return;
+        };
+        if let Err(e) = journal.advance(cursor, handled) {
+            warn!("Failed to update journal cursor for {}: {}", self.name, e);
+        }
+        if (journal.released >= COMPACT_AFTER_RELEASED || self.messages.is_empty())
+            && let Err(e) = journal.compact(self.messages.iter())
+        {
+            warn!("Failed to compact journal for {}: {}", self.name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> String {
+        let directory =
+            std::env::temp_dir().join(format!("delay-buffer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_releases_in_order_when_due() {
+        let (publisher, mut rx) = Publisher::channel(8);
+        let mut buffer = DelayBuffer::new("test", 1_024, None);
+        buffer.push(100, "t", "a", "1".to_string());
+        buffer.push(200, "t", "b", "2".to_string());
+
+        assert_eq!(buffer.release(&publisher, 99), 0);
+        assert_eq!(buffer.release(&publisher, 150), 1);
+        assert_eq!(rx.try_recv().unwrap().key, "a");
+        assert_eq!(buffer.release(&publisher, 200), 1);
+        assert_eq!(rx.try_recv().unwrap().key, "b");
+        assert_eq!(buffer.buffered_bytes, 0);
+    }
+
+    #[test]
+    fn test_oldest_messages_are_dropped_when_full() {
+        let mut buffer = DelayBuffer::new("test", 10, None);
+        buffer.push(1, "t", "k", "abcd".to_string());
+        buffer.push(2, "t", "k", "efgh".to_string());
+        assert_eq!(buffer.messages.len(), 1);
+        assert_eq!(buffer.messages[0].payload, "efgh");
+        assert_eq!(buffer.take_dropped(), 1);
+
+        buffer.push(3, "t", "k", "far too large".to_string());
+        assert_eq!(buffer.messages.len(), 1);
+        assert_eq!(buffer.messages[0].payload, "efgh");
+        assert_eq!(buffer.take_dropped(), 1);
+        assert_eq!(buffer.take_dropped(), 0);
+    }
+
+    #[test]
+    fn test_pending_messages_survive_restart() {
+        let directory = temp_dir("restart");
+        let (publisher, mut rx) = Publisher::channel(8);
+        {
+            let mut buffer = DelayBuffer::new("delayed", 1_024, Some(&directory));
+            for (release_at, key) in [(100, "a"), (200, "b"), (300, "c")] {
+                buffer.push(release_at, "t", key, key.to_string());
+            }
+            buffer.release(&publisher, 100);
+            assert_eq!(rx.try_recv().unwrap().key, "a");
+        }
+
+        let mut buffer = DelayBuffer::new("delayed", 1_024, Some(&directory));
+        assert_eq!(buffer.messages.len(), 2);
+        buffer.push(400, "t", "d", "d".to_string());
+        assert_eq!(buffer.release(&publisher, 1_000), 3);
+        let keys: Vec<String> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|message| message.key)
+            .collect();
+        assert_eq!(keys, vec!["b", "c", "d"]);
+
+        // Everything was released, so nothing comes back after another restart, and
+        // sequence numbers keep increasing so new messages are not mistaken for released
+        let mut buffer = DelayBuffer::new("delayed", 1_024, Some(&directory));
+        assert_eq!(buffer.messages.len(), 0);
+        buffer.push(500, "t", "e", "e".to_string());
+        assert_eq!(
+            DelayBuffer::new("delayed", 1_024, Some(&directory))
+                .messages
+                .len(),
+            1
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
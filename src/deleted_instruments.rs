@@ -0,0 +1,117 @@
+// src/deleted_instruments.rs
+use crate::helpers::EngineCommand;
+use crate::order_responses::{RejectReason, Rejection};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Instruments deleted with `instrument.delete` and not created again since
+///
+/// Orders are entered on books created on demand, so an order queued behind the
+/// delete of its instrument would bring the book back. Order commands on a
+/// deleted instrument are rejected instead, until `instrument.create` opens it
+/// again. Whether a command is rejected depends only on the commands before it,
+/// so replaying the write-ahead log, which carries deletes forward as settings,
+/// rejects the same ones.
+#[derive(Default)]
+pub struct DeletedInstruments {
+    deleted: HashSet<String>,
+}
+
+impl DeletedInstruments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks the instruments a command deletes and creates
+    pub fn record(&mut self, cmd: &EngineCommand) {
+        match cmd {
+            EngineCommand::InstrumentDelete(delete) => {
+                self.deleted.insert(delete.instrument_id.clone());
+            }
+            EngineCommand::InstrumentCreate(create)
+                if self.deleted.remove(&create.instrument_id) =>
+            {
+                info!("Instrument {} is open again", create.instrument_id);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_deleted(&self, instrument_id: &str) -> bool {
+        self.deleted.contains(instrument_id)
+    }
+
+    /// Rejects an order command on a deleted instrument
+    pub fn admit(&self, cmd: &EngineCommand) -> Result<(), Rejection> {
+        let (instrument_id, order_id) = match cmd {
+            EngineCommand::OrderCreate(order) => (&order.instrument_id, order.order_id),
+            EngineCommand::OrderModify(order) => (&order.instrument_id, order.order_id),
+            EngineCommand::OrderCancel(order) => (&order.instrument_id, order.order_id),
+            EngineCommand::OrderReplace(order) => (&order.instrument_id, order.order_id),
+            _ => return Ok(()),
+        };
+        if !self.is_deleted(instrument_id) {
+            return Ok(());
+        }
+        warn!(
+            "Rejecting order command for {} on {}: the instrument was deleted",
+            order_id, instrument_id
+        );
+        Err(Rejection::new(
+            RejectReason::UnknownInstrument,
+            format!("instrument {instrument_id} was deleted"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::types::OrderType;
+    use crate::helpers::{DeleteInstrumentPayload, InstrumentCreatePayload, OrderCreatePayload};
+    use crate::tags::OrderTags;
+    use pricelevel::{Side, TimeInForce};
+
+    fn order(instrument_id: &str) -> EngineCommand {
+        EngineCommand::OrderCreate(OrderCreatePayload {
+            order_id: 1,
+            instrument_id: instrument_id.to_string(),
+            quantity: 5,
+            price: 100,
+            side: Side::Buy,
+            time_in_force: TimeInForce::Gtc,
+            order_type: OrderType::LIMIT,
+            participant_id: None,
+            oms_id: None,
+            client_order_id: None,
+            tags: OrderTags::new(),
+            visible_quantity: None,
+            hidden_quantity: None,
+            liquidation: false,
+            min_quantity: None,
+            all_or_none: false,
+            sweep_limit: None,
+            sequence: None,
+        })
+    }
+
+    #[test]
+    fn test_orders_on_deleted_instruments_are_rejected_until_recreated() {
+        let mut deleted = DeletedInstruments::new();
+        assert!(deleted.admit(&order("BTC")).is_ok());
+
+        deleted.record(&EngineCommand::InstrumentDelete(DeleteInstrumentPayload {
+            instrument_id: "BTC".to_string(),
+            sequence: None,
+        }));
+        let rejection = deleted.admit(&order("BTC")).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::UnknownInstrument);
+        assert!(deleted.admit(&order("ETH")).is_ok());
+
+        let create: InstrumentCreatePayload =
+            serde_json::from_value(serde_json::json!({"instrument_id": "BTC"})).unwrap();
+        deleted.record(&EngineCommand::InstrumentCreate(create));
+        assert!(!deleted.is_deleted("BTC"));
+        assert!(deleted.admit(&order("BTC")).is_ok());
+    }
+}
@@ -0,0 +1,337 @@
+// src/depth_deltas.rs
+use crate::config::depth_deltas::DepthDeltaConfig;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::snapshot::OrderBookSnapshot;
+use crate::publisher::Publisher;
+use crate::sinks::LevelChange;
+use crate::tags::OrderTags;
+use pricelevel::{PriceLevelSnapshot, Side};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Whether an L2 update replaces the whole book or changes some of its levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L2UpdateKind {
+    Delta,
+    Snapshot,
+}
+
+/// Visible quantity at a price; in a delta, 0 means the level is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct L2Level {
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// A message of the L2 feed of an instrument
+#[derive(Debug, Serialize)]
+pub struct L2Update {
+    pub instrument_id: String,
+    /// One more than the previous message of the instrument; a consumer that
+    /// sees a gap waits for the next snapshot
+    pub sequence: u64,
+    pub kind: L2UpdateKind,
+    /// Millisecond the changes were made in, or the snapshot was taken at
+    pub timestamp: u64,
+    /// Best price first
+    pub bids: Vec<L2Level>,
+    /// Best price first
+    pub asks: Vec<L2Level>,
+}
+
+/// Level changes of an instrument within one millisecond, the last quantity
+/// of each level
+struct Batch {
+    timestamp: u64,
+    bids: BTreeMap<u64, u64>,
+    asks: BTreeMap<u64, u64>,
+}
+
+#[derive(Default)]
+struct InstrumentFeed {
+    /// Sequence of the last message published
+    sequence: u64,
+    pending: Option<Batch>,
+    /// When the last snapshot was published, if ever
+    snapshot_at: Option<u64>,
+}
+
+impl InstrumentFeed {
+    fn publish(
+        &mut self,
+        config: &DepthDeltaConfig,
+        publisher: &Publisher,
+        instrument_id: &str,
+        kind: L2UpdateKind,
+        timestamp: u64,
+        (bids, asks): (Vec<L2Level>, Vec<L2Level>),
+    ) {
+        self.sequence += 1;
+        let update = L2Update {
+            instrument_id: instrument_id.to_string(),
+            sequence: self.sequence,
+            kind,
+            timestamp,
+            bids,
+            asks,
+        };
+        publisher.publish(&config.topic(instrument_id), instrument_id, &update);
+    }
+
+    fn flush(&mut self, config: &DepthDeltaConfig, publisher: &Publisher, instrument_id: &str) {
+        let Some(batch) = self.pending.take() else {
+            return;
+        };
+        let levels = |levels: BTreeMap<u64, u64>| {
+            levels
+                .into_iter()
+                .map(|(price, quantity)| L2Level { price, quantity })
+        };
+        let bids = levels(batch.bids).rev().collect();
+        let asks = levels(batch.asks).collect();
+        self.publish(
+            config,
+            publisher,
+            instrument_id,
+            L2UpdateKind::Delta,
+            batch.timestamp,
+            (bids, asks),
+        );
+    }
+}
+
+/// Publishes an incremental L2 feed of every book, one topic per instrument
+///
+/// The level changes of each command are batched by the millisecond they were
+/// made in, keeping the last quantity of each level, and a batch is published
+/// once a later millisecond starts. Each instrument's messages are numbered in
+/// one sequence, snapshots included: a consumer joining late applies the
+/// deltas that follow the first snapshot it reads. A snapshot is published
+/// when a book starts out, every `snapshot_interval_ms` after, and, empty,
+/// when it is deleted.
+pub struct DepthDeltaPublisher {
+    config: DepthDeltaConfig,
+    instruments: HashMap<String, InstrumentFeed>,
+}
+
+impl DepthDeltaPublisher {
+    pub fn new(config: DepthDeltaConfig) -> Self {
+        Self {
+            config,
+            instruments: HashMap::new(),
+        }
+    }
+
+    /// Adds the level changes of a command to their instruments' batches
+    pub fn on_changes(&mut self, changes: &[LevelChange], publisher: &Publisher) {
+        if !self.config.enabled {
+            return;
+        }
+        for change in changes {
+            let feed = self
+                .instruments
+                .entry(change.instrument_id.clone())
+                .or_default();
+            if feed
+                .pending
+                .as_ref()
+                .is_some_and(|batch| batch.timestamp != change.timestamp)
+            {
+                feed.flush(&self.config, publisher, &change.instrument_id);
+            }
+            let batch = feed.pending.get_or_insert_with(|| Batch {
+                timestamp: change.timestamp,
+                bids: BTreeMap::new(),
+                asks: BTreeMap::new(),
+            });
+            let side = match change.side {
+                Side::Buy => &mut batch.bids,
+                Side::Sell => &mut batch.asks,
+            };
+            side.insert(change.price, change.quantity);
+        }
+    }
+
+    /// Publishes a full snapshot of a book, after the changes it includes
+    pub fn on_snapshot(&mut self, snapshot: &OrderBookSnapshot, publisher: &Publisher) {
+        if !self.config.enabled {
+            return;
+        }
+        let levels = |levels: &[PriceLevelSnapshot]| {
+            levels
+                .iter()
+                .filter(|level| level.visible_quantity > 0)
+                .map(|level| L2Level {
+                    price: level.price,
+                    quantity: level.visible_quantity,
+                })
+                .collect()
+        };
+        let feed = self.instruments.entry(snapshot.symbol.clone()).or_default();
+        feed.flush(&self.config, publisher, &snapshot.symbol);
+        feed.publish(
+            &self.config,
+            publisher,
+            &snapshot.symbol,
+            L2UpdateKind::Snapshot,
+            snapshot.timestamp,
+            (levels(&snapshot.bids), levels(&snapshot.asks)),
+        );
+        feed.snapshot_at = Some(snapshot.timestamp);
+    }
+
+    /// Publishes the batches of elapsed milliseconds and the snapshots that
+    /// are due
+    pub fn on_tick(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        publisher: &Publisher,
+        now: u64,
+    ) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut due = Vec::new();
+        for (instrument_id, feed) in &mut self.instruments {
+            if feed
+                .pending
+                .as_ref()
+                .is_some_and(|batch| batch.timestamp < now)
+            {
+                feed.flush(&self.config, publisher, instrument_id);
+            }
+            if feed
+                .snapshot_at
+                .is_none_or(|at| now.saturating_sub(at) >= self.config.snapshot_interval_ms)
+            {
+                due.push(instrument_id.clone());
+            }
+        }
+        for instrument_id in due {
+            // Books evicted to disk change no more until they are loaded again
+            if let Some(book) = manager.get_book(&instrument_id) {
+                let mut snapshot = book.create_snapshot(usize::MAX);
+                snapshot.timestamp = now;
+                self.on_snapshot(&snapshot, publisher);
+            }
+        }
+    }
+
+    /// Stops publishing a deleted instrument, whose last message is the empty
+    /// snapshot the engine hands over
+    pub fn forget(&mut self, instrument_id: &str) {
+        self.instruments.remove(instrument_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::publisher::OutboundMessage;
+    use pricelevel::{OrderId, TimeInForce};
+    use tokio::sync::mpsc::Receiver;
+
+    fn change(timestamp: u64, side: Side, price: u64, quantity: u64) -> LevelChange {
+        LevelChange {
+            instrument_id: "BTC".to_string(),
+            timestamp,
+            side,
+            price,
+            quantity,
+            replenished: None,
+        }
+    }
+
+    fn drain(rx: &mut Receiver<OutboundMessage>) -> Vec<serde_json::Value> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|message| {
+                assert_eq!(
+                    (message.topic.as_str(), message.key.as_str()),
+                    ("marketdata.l2.BTC", "BTC")
+                );
+                serde_json::from_str(&message.payload).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_changes_are_batched_per_millisecond_between_snapshots() {
+        let (publisher, mut rx) = Publisher::channel(16);
+        let mut deltas = DepthDeltaPublisher::new(DepthDeltaConfig {
+            enabled: true,
+            snapshot_interval_ms: 1_000,
+            ..DepthDeltaConfig::default()
+        });
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let book = manager.get_book("BTC").unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            99,
+            5,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        let mut snapshot = book.create_snapshot(usize::MAX);
+        snapshot.timestamp = 10;
+        deltas.on_snapshot(&snapshot, &publisher);
+
+        deltas.on_changes(
+            &[
+                change(10, Side::Buy, 99, 8),
+                change(10, Side::Sell, 101, 3),
+                change(10, Side::Buy, 98, 4),
+            ],
+            &publisher,
+        );
+        // The same level changing again in the millisecond keeps its last quantity
+        deltas.on_changes(&[change(10, Side::Buy, 99, 0)], &publisher);
+        let messages = drain(&mut rx);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["kind"], "snapshot");
+        assert_eq!(messages[0]["sequence"], 1);
+        assert_eq!(
+            messages[0]["bids"],
+            serde_json::json!([{ "price": 99, "quantity": 5 }])
+        );
+
+        // A later millisecond publishes the batch before it
+        deltas.on_changes(&[change(11, Side::Sell, 101, 0)], &publisher);
+        let messages = drain(&mut rx);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            (
+                &messages[0]["kind"],
+                &messages[0]["sequence"],
+                &messages[0]["timestamp"]
+            ),
+            (&"delta".into(), &2.into(), &10.into())
+        );
+        assert_eq!(
+            messages[0]["bids"],
+            serde_json::json!([{ "price": 99, "quantity": 0 }, { "price": 98, "quantity": 4 }])
+        );
+
+        // So does a tick once the millisecond is over, and a snapshot follows
+        // when it is due
+        deltas.on_tick(&manager, &publisher, 11);
+        assert!(drain(&mut rx).is_empty());
+        deltas.on_tick(&manager, &publisher, 1_010);
+        let messages = drain(&mut rx);
+        let kinds: Vec<_> = messages
+            .iter()
+            .map(|message| (message["kind"].clone(), message["sequence"].clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [("delta".into(), 3.into()), ("snapshot".into(), 4.into())]
+        );
+        assert_eq!(
+            messages[0]["asks"],
+            serde_json::json!([{ "price": 101, "quantity": 0 }])
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// src/diagnostics.rs
+use crate::alerts::{Alert, AlertKind, emit_alert};
+use crate::config::diagnostics::DiagnosticsConfig;
+use crate::orderbook::OrderBook;
+use crate::orderbook::flight_recorder::FlightRecord;
+use crate::orderbook::invariants::InvariantViolation;
+use crate::orderbook::snapshot::OrderBookSnapshotPackage;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// Everything captured about a book at the moment a violation was detected
+#[derive(Debug, Serialize)]
+pub struct BookStateDump<'a> {
+    pub symbol: &'a str,
+    pub timestamp: u64,
+    pub violations: &'a [InvariantViolation],
+    pub snapshot: Option<OrderBookSnapshotPackage>,
+    pub flight_events: Vec<FlightRecord>,
+}
+
+/// Runs invariant checks on books and dumps their state when one fails.
+///
+/// A book is dumped once when it enters a violating state; it is reported
+/// again only after it has passed a check in between.
+pub struct InvariantMonitor {
+    config: DiagnosticsConfig,
+    flagged: HashSet<String>,
+}
+
+impl InvariantMonitor {
+    pub fn new(config: DiagnosticsConfig) -> Self {
+        Self {
+            config,
+            flagged: HashSet::new(),
+        }
+    }
+
+    pub fn check(&mut self, book: &OrderBook<OrderTags>, publisher: &Publisher) {
+        if !self.config.enabled {
+            return;
+        }
+        let symbol = book.symbol();
+        let violations = book.check_invariants();
+        if violations.is_empty() {
+            if self.flagged.remove(symbol) {
+                info!("Book {} passes invariant checks again", symbol);
+            }
+            return;
+        }
+        if !self.flagged.insert(symbol.to_string()) {
+            return;
+        }
+
+        let summary = violations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        if self.config.log_ladder_depth > 0 {
+            warn!(
+                "Book {} fails invariant checks:\n{}",
+                symbol,
+                book.create_snapshot(self.config.log_ladder_depth)
+                    .ladder(self.config.log_ladder_depth)
+            );
+        }
+        let message = match self.dump(book, &violations) {
+            Ok(path) => format!("{} (state dumped to {})", summary, path.display()),
+            Err(e) => format!("{} (failed to dump state: {})", summary, e),
+        };
+        emit_alert(
+            publisher,
+            Alert::new(AlertKind::InvariantViolation, symbol, message).with_details(&violations),
+        );
+    }
+
+    /// Forgets a book, e.g. after it has been removed
+    pub fn forget(&mut self, symbol: &str) {
+        self.flagged.remove(symbol);
+    }
+
+    fn dump(
+        &self,
+        book: &OrderBook<OrderTags>,
+        violations: &[InvariantViolation],
+    ) -> std::io::Result<PathBuf> {
+        let timestamp = current_time_millis();
+        let dump = BookStateDump {
+            symbol: book.symbol(),
+            timestamp,
+            violations,
+            snapshot: book
+                .create_snapshot_package(self.config.snapshot_depth)
+                .ok(),
+            flight_events: book
+                .flight_recorder()
+                .map(|recorder| recorder.dump())
+                .unwrap_or_default(),
+        };
+        let json = serde_json::to_string_pretty(&dump).map_err(std::io::Error::other)?;
+
+        let directory = PathBuf::from(&self.config.directory);
+        std::fs::create_dir_all(&directory)?;
+        // Symbols such as "BTC/USD" are not valid file names as-is
+        let file_stem: String = book
+            .symbol()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = directory.join(format!("{file_stem}-{timestamp}.json"));
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,277 @@
+// src/dry_run.rs
+use crate::config::topics::CommandKind;
+use crate::helpers::orderbook_helpers::best_opposite;
+use crate::helpers::types::OrderType;
+use crate::helpers::{EngineCommand, OrderCreatePayload, cap_sweep, split_order_replace};
+use crate::order_responses::{RejectReason, Rejection};
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::{OrderBook, OrderBookError};
+use crate::tags::OrderTags;
+use pricelevel::{OrderId, TimeInForce};
+use serde::Serialize;
+
+/// What a command would have done had it been applied, published to the
+/// shadow topic in dry-run mode
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DryRunResult {
+    pub command: CommandKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instrument_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<u64>,
+    pub accepted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<RejectReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Quantity an order would fill against the book as it stands
+    pub would_fill: u64,
+    /// Quantity an order would leave resting in the book
+    pub would_rest: u64,
+    pub timestamp: u64,
+}
+
+impl DryRunResult {
+    /// The result of a command, given its kind, instrument and order before
+    /// the command was checked
+    pub fn new(
+        command: CommandKind,
+        instrument_id: Option<String>,
+        order_id: Option<u64>,
+        outcome: Result<(u64, u64), Rejection>,
+        timestamp: u64,
+    ) -> Self {
+        let (accepted, reason, message, (would_fill, would_rest)) = match outcome {
+            Ok(quantities) => (true, None, None, quantities),
+            Err(rejection) => (
+                false,
+                Some(rejection.reason),
+                Some(rejection.message),
+                (0, 0),
+            ),
+        };
+        Self {
+            command,
+            instrument_id,
+            order_id,
+            accepted,
+            reason,
+            message,
+            would_fill,
+            would_rest,
+            timestamp,
+        }
+    }
+}
+
+/// Checks a command the engine admitted against the books as they stand,
+/// returning what an order command would fill and leave resting
+///
+/// Nothing is changed: fills are previewed with `peek_match`. An order beyond
+/// its sweep limit is previewed as its first sweep. Commands other than order
+/// commands are accepted as they are.
+pub fn preview(
+    manager: &BookManagerStd<OrderTags>,
+    cmd: EngineCommand,
+) -> Result<(u64, u64), Rejection> {
+    match cmd {
+        EngineCommand::OrderCreate(mut order) => {
+            cap_sweep(manager, &mut order)?;
+            preview_order(manager.get_book(&order.instrument_id), &order)
+        }
+        EngineCommand::OrderReplace(replace) => {
+            let (_, mut order) = split_order_replace(manager, replace)?;
+            cap_sweep(manager, &mut order)?;
+            preview_order(manager.get_book(&order.instrument_id), &order)
+        }
+        EngineCommand::OrderModify(modify) => {
+            resting(manager, &modify.instrument_id, modify.order_id)?;
+            Ok((0, modify.quantity))
+        }
+        EngineCommand::OrderCancel(cancel) => {
+            resting(manager, &cancel.instrument_id, cancel.order_id)?;
+            Ok((0, 0))
+        }
+        _ => Ok((0, 0)),
+    }
+}
+
+/// Rejects a command naming an order that does not rest on its book
+fn resting(
+    manager: &BookManagerStd<OrderTags>,
+    instrument_id: &str,
+    order_id: u64,
+) -> Result<(), Rejection> {
+    let Some(book) = manager.get_book(instrument_id) else {
+        return Err(Rejection::new(
+            RejectReason::UnknownInstrument,
+            format!("no book for {instrument_id}"),
+        ));
+    };
+    if book.get_order(OrderId::from_u64(order_id)).is_none() {
+        return Err(Rejection::new(
+            RejectReason::UnknownOrder,
+            format!("no resting order {order_id} on {instrument_id}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Fill and resting quantity of a new order, checked as `handle_order_create`
+/// would; without a book, one would be created and the order rest in it
+fn preview_order(
+    book: Option<&OrderBook<OrderTags>>,
+    order: &OrderCreatePayload,
+) -> Result<(u64, u64), Rejection> {
+    let immediate = order.time_in_force.is_immediate();
+    if order.order_type == OrderType::ICEBERG {
+        order
+            .iceberg_quantities()
+            .map_err(|e| Rejection::new(RejectReason::InvalidOrder, e))?;
+    }
+    let min_execution = order
+        .min_execution()
+        .map_err(|e| Rejection::new(RejectReason::InvalidOrder, e))?;
+    let Some(book) = book else {
+        return match order.order_type {
+            OrderType::MARKET | OrderType::MARKET_TO_LIMIT => Err(nothing_to_match()),
+            _ if order.all_or_none => Err(nothing_to_match()),
+            _ if immediate => Ok((0, 0)),
+            _ => Ok((0, order.quantity)),
+        };
+    };
+    if book.is_halted() {
+        return Err(Rejection::new(
+            RejectReason::TradingHalted,
+            format!("trading is halted on {}", order.instrument_id),
+        ));
+    }
+    if book.is_in_auction() && (immediate || order.order_type.is_priced_by_book()) {
+        return Err(Rejection::new(
+            RejectReason::InvalidOrder,
+            "only orders that can rest are accepted during the auction",
+        ));
+    }
+    let price_limit = match order.order_type {
+        OrderType::MARKET => None,
+        OrderType::LIMIT | OrderType::ICEBERG => Some(order.price),
+        OrderType::MARKET_TO_LIMIT => {
+            Some(best_opposite(book, order.side).ok_or_else(nothing_to_match)?)
+        }
+    };
+    if let Some(min_quantity) = min_execution {
+        if book.is_in_auction() {
+            return Err(Rejection::new(
+                RejectReason::InvalidOrder,
+                "orders with a minimum execution are not accepted during the auction",
+            ));
+        }
+        if let Err(e) = book.ensure_min_execution(order.side, min_quantity, price_limit) {
+            let crosses = !matches!(
+                e,
+                OrderBookError::InsufficientLiquidity { available: 0, .. }
+            );
+            if crosses || order.all_or_none || order.order_type.is_priced_by_book() {
+                return Err(e.into());
+            }
+        }
+    }
+    let would_fill = if book.is_in_auction() {
+        0
+    } else {
+        book.peek_match(order.side, order.quantity, price_limit)
+    };
+    if order.order_type == OrderType::MARKET && would_fill == 0 {
+        return Err(nothing_to_match());
+    }
+    if order.time_in_force == TimeInForce::Fok && would_fill < order.quantity {
+        return Err(Rejection::new(
+            RejectReason::InsufficientLiquidity,
+            format!("{} of {} available", would_fill, order.quantity),
+        ));
+    }
+    let would_rest = if immediate || order.order_type == OrderType::MARKET {
+        0
+    } else {
+        order.quantity - would_fill
+    };
+    Ok((would_fill, would_rest))
+}
+
+fn nothing_to_match() -> Rejection {
+    Rejection::new(
+        RejectReason::InsufficientLiquidity,
+        "no opposite orders to match against",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::handle_order_create;
+    use pricelevel::Side;
+
+    fn order(order_id: u64, side: Side, price: u64, quantity: u64) -> OrderCreatePayload {
+        OrderCreatePayload {
+            order_id,
+            instrument_id: "BTC".to_string(),
+            quantity,
+            price,
+            side,
+            time_in_force: TimeInForce::Gtc,
+            order_type: OrderType::LIMIT,
+            participant_id: None,
+            oms_id: None,
+            client_order_id: None,
+            tags: OrderTags::new(),
+            visible_quantity: None,
+            hidden_quantity: None,
+            liquidation: false,
+            min_quantity: None,
+            all_or_none: false,
+            sweep_limit: None,
+            sequence: None,
+        }
+    }
+
+    #[test]
+    fn test_previews_leave_the_book_untouched() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        handle_order_create(&mut manager, order(1, Side::Sell, 100, 5)).unwrap();
+        handle_order_create(&mut manager, order(2, Side::Sell, 101, 5)).unwrap();
+
+        let crossing = EngineCommand::OrderCreate(order(3, Side::Buy, 101, 12));
+        assert_eq!(preview(&manager, crossing), Ok((10, 2)));
+        let fok = EngineCommand::OrderCreate(OrderCreatePayload {
+            time_in_force: TimeInForce::Fok,
+            ..order(4, Side::Buy, 100, 6)
+        });
+        assert_eq!(
+            preview(&manager, fok).unwrap_err().reason,
+            RejectReason::InsufficientLiquidity
+        );
+        let market = EngineCommand::OrderCreate(OrderCreatePayload {
+            order_type: OrderType::MARKET,
+            ..order(5, Side::Sell, 0, 1)
+        });
+        assert_eq!(
+            preview(&manager, market).unwrap_err().reason,
+            RejectReason::InsufficientLiquidity
+        );
+        let cancel = EngineCommand::OrderCancel(crate::helpers::OrderCancelPayload {
+            order_id: 9,
+            instrument_id: "BTC".to_string(),
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        });
+        assert_eq!(
+            preview(&manager, cancel).unwrap_err().reason,
+            RejectReason::UnknownOrder
+        );
+
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!((book.best_bid(), book.best_ask()), (None, Some(100)));
+        assert_eq!(book.get_all_orders().len(), 2);
+    }
+}
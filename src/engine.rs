@@ -1,34 +1,1940 @@
 // src/engine.rs
-use crate::helpers::EngineCommand;
+use crate::alerts::ALERTS_TOPIC;
+use crate::archive::{BookArchiver, EngineLoad};
+use crate::bbo_state::BboStatePublisher;
+use crate::clearing::ClearingLedger;
+use crate::client_orders::ClientOrderIds;
+use crate::config::analytics::AnalyticsConfig;
+use crate::config::archive::ArchiveConfig;
+use crate::config::bbo_state::BboStateConfig;
+use crate::config::clearing::ClearingConfig;
+use crate::config::dedup::DedupConfig;
+use crate::config::depth_deltas::DepthDeltaConfig;
+use crate::config::diagnostics::DiagnosticsConfig;
+use crate::config::dry_run::DryRunConfig;
+use crate::config::execution_quality::ExecutionQualityConfig;
+use crate::config::fair_value::FairValueConfig;
+use crate::config::features::FeatureFlagConfig;
+use crate::config::feeds::FeedConfig;
+use crate::config::funding::FundingConfig;
+use crate::config::indices::IndexConfig;
+use crate::config::instruments::InstrumentEventsConfig;
+use crate::config::liquidations::LiquidationConfig;
+use crate::config::liveness::LivenessConfig;
+use crate::config::mass_cancel::MassCancelConfig;
+use crate::config::mbo::MboConfig;
+use crate::config::order_expiry::OrderExpiryConfig;
+use crate::config::order_responses::OrderResponseConfig;
+use crate::config::order_to_trade::{OrderToTradeConfig, OtrAction};
+use crate::config::rfq::RfqConfig;
+use crate::config::risk::RiskConfig;
+use crate::config::sequencer::SequencerConfig;
+use crate::config::shutdown::ShutdownConfig;
+use crate::config::sinks::{EventKind, PipelineConfig, SinkTarget};
+use crate::config::supervisor::SupervisorConfig;
+use crate::config::tiering::TieringConfig;
+use crate::config::trades::TradeReportConfig;
+use crate::config::verification::VerificationConfig;
+use crate::config::wal::WalConfig;
+use crate::dedup::DuplicateWindow;
+use crate::deleted_instruments::DeletedInstruments;
+use crate::depth_deltas::DepthDeltaPublisher;
+use crate::diagnostics::InvariantMonitor;
+use crate::dry_run::{self, DryRunResult};
+use crate::execution_quality::ExecutionQualityMonitor;
+use crate::expiry::ExpiryManager;
+use crate::fair_value::FairValueMonitor;
+use crate::feature_flags::FeatureFlags;
+use crate::feeds::FeedPublisher;
+use crate::funding::FundingCalculator;
+use crate::helpers::types::KillSwitchAction;
 use crate::helpers::{
-    handle_instrument_create, handle_instrument_delete, handle_order_cancel, handle_order_create,
-    handle_order_modify,
+    AdminCommandPayload, EngineCommand, MassCancelPayload, OrderCancelPayload, OrderCreatePayload,
+    OrderReplacePayload, TradingHaltPayload,
 };
-use crate::orderbook::manager::BookManagerStd;
-use tokio::sync::mpsc::Receiver;
-use tracing::info;
+use crate::helpers::{
+    cap_sweep, handle_admin_command, handle_auction_start, handle_auction_uncross,
+    handle_block_trade, handle_halt, handle_instrument_adjust, handle_instrument_create,
+    handle_instrument_delete, handle_mass_cancel, handle_order_cancel, handle_order_create,
+    handle_order_modify, handle_resume, handle_rfq_execute, handle_rfq_quote, handle_rfq_request,
+    sample_correlations, split_order_replace, sweep_rfqs,
+};
+use crate::indices::IndexCalculator;
+use crate::kill_switch::KillSwitches;
+use crate::lanes::CommandLanes;
+use crate::liquidations::LiquidationMonitor;
+use crate::liveness::OmsLiveness;
+use crate::mbo::MboPublisher;
+use crate::order_responses::{OrderRequest, OrderResponder, RejectReason, Rejection};
+use crate::order_to_trade::OrderToTradeMonitor;
+use crate::orderbook::correlation::CorrelationTracker;
+use crate::orderbook::flight_recorder::FlightEvent;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::rfq::{RfqExecution, RfqManager};
+use crate::orderbook::snapshot::OrderBookSnapshot;
+use crate::price_bands::{BandReference, PriceBands};
+use crate::publisher::Publisher;
+use crate::risk::ParticipantRisk;
+use crate::sequencer::{Sequenced, Sequencer};
+use crate::shutdown::write_snapshots;
+use crate::sinks::{
+    EventSink, LevelTap, OrderAction, OrderEvent, SinkEvent, SinkPipeline, TradeRecord,
+};
+use crate::supervisor::Checkpoints;
+use crate::tags::{OrderTags, attach_taker_tags, is_liquidation, mark_liquidation, trade_tags};
+use crate::tiering::BookTiering;
+use crate::trade_producer::TradeProducer;
+use crate::utils::current_time_millis;
+use crate::verification::StateVerifier;
+use crate::wal::{self, Compaction, WriteAheadLog};
+use crate::watchdog::Progress;
+use pricelevel::{OrderId, Side};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+/// Settings for the engine task and the monitors it owns
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub diagnostics: DiagnosticsConfig,
+    pub analytics: AnalyticsConfig,
+    pub fair_value: FairValueConfig,
+    pub indices: IndexConfig,
+    pub rfq: RfqConfig,
+    pub trades: TradeReportConfig,
+    pub clearing: ClearingConfig,
+    pub instruments: InstrumentEventsConfig,
+    pub feeds: FeedConfig,
+    pub archive: ArchiveConfig,
+    pub sinks: PipelineConfig,
+    pub verification: VerificationConfig,
+    pub features: FeatureFlagConfig,
+    pub supervisor: SupervisorConfig,
+    pub order_to_trade: OrderToTradeConfig,
+    pub liveness: LivenessConfig,
+    pub execution_quality: ExecutionQualityConfig,
+    pub shutdown: ShutdownConfig,
+    pub wal: WalConfig,
+    pub order_expiry: OrderExpiryConfig,
+    pub order_responses: OrderResponseConfig,
+    pub mass_cancel: MassCancelConfig,
+    pub funding: FundingConfig,
+    pub liquidations: LiquidationConfig,
+    pub bbo_state: BboStateConfig,
+    pub tiering: TieringConfig,
+    pub dry_run: DryRunConfig,
+    pub risk: RiskConfig,
+    pub dedup: DedupConfig,
+    pub sequencer: SequencerConfig,
+    pub depth_deltas: DepthDeltaConfig,
+    pub mbo: MboConfig,
+}
+
+impl EngineConfig {
+    /// Every fixed topic the engine may publish to; the per-instrument depth
+    /// delta topics under `depth_deltas.topic_prefix` are not listed
+    pub fn output_topics(&self) -> Vec<&str> {
+        let mut topics = vec![
+            ALERTS_TOPIC,
+            self.fair_value.metrics_topic.as_str(),
+            self.indices.tick_topic.as_str(),
+            self.rfq.events_topic.as_str(),
+            self.trades.trades_topic.as_str(),
+            self.instruments.adjustments_topic.as_str(),
+            self.instruments.settlement_topic.as_str(),
+            self.mass_cancel.summary_topic.as_str(),
+        ];
+        for profile in &self.feeds.profiles {
+            topics.extend([profile.depth_topic.as_str(), profile.trades_topic.as_str()]);
+        }
+        for sink in &self.sinks.sinks {
+            if let SinkTarget::Kafka { topic } = &sink.target {
+                topics.push(topic);
+            }
+        }
+        if self.verification.enabled && self.verification.expected_path.is_none() {
+            topics.push(&self.verification.topic);
+        }
+        if self.dry_run.enabled {
+            topics.push(&self.dry_run.shadow_topic);
+        }
+        if self.order_to_trade.enabled {
+            topics.push(&self.order_to_trade.metrics_topic);
+            if self.order_to_trade.action == OtrAction::Throttle {
+                topics.push(&self.order_to_trade.throttle_topic);
+            }
+        }
+        if self.liveness.enabled {
+            topics.push(&self.liveness.heartbeat_topic);
+        }
+        if self.execution_quality.enabled {
+            topics.push(&self.execution_quality.metrics_topic);
+        }
+        if self.liquidations.enabled {
+            topics.push(&self.liquidations.metrics_topic);
+        }
+        if self.bbo_state.enabled {
+            topics.push(&self.bbo_state.topic);
+        }
+        if self.bbo_state.events_enabled {
+            topics.push(&self.bbo_state.events_topic);
+        }
+        if self.mbo.enabled {
+            topics.push(&self.mbo.topic);
+        }
+        if self.trades.publish_tape {
+            topics.push(&self.trades.tape_topic);
+        }
+        if self.funding.enabled {
+            topics.extend([
+                self.funding.premium_topic.as_str(),
+                self.funding.funding_topic.as_str(),
+            ]);
+        }
+        if self.order_responses.enabled {
+            topics.extend([
+                self.order_responses.ack_topic.as_str(),
+                self.order_responses.reject_topic.as_str(),
+            ]);
+        }
+        topics
+    }
+}
+
+/// State owned by the engine task
+struct Engine {
+    manager: BookManagerStd<OrderTags>,
+    publisher: Publisher,
+    invariants: InvariantMonitor,
+    fair_value: FairValueMonitor,
+    correlations: CorrelationTracker,
+    indices: IndexCalculator,
+    rfqs: RfqManager,
+    rfq_config: RfqConfig,
+    trade_config: TradeReportConfig,
+    trades: TradeProducer,
+    clearing: ClearingLedger,
+    instrument_config: InstrumentEventsConfig,
+    mass_cancel_config: MassCancelConfig,
+    expiries: ExpiryManager,
+    feeds: FeedPublisher,
+    archiver: BookArchiver,
+    level_tap: LevelTap,
+    sinks: SinkPipeline,
+    verifier: StateVerifier,
+    features: FeatureFlags,
+    order_to_trade: OrderToTradeMonitor,
+    liveness: OmsLiveness,
+    execution_quality: ExecutionQualityMonitor,
+    client_orders: ClientOrderIds,
+    order_responses: OrderResponder,
+    deleted_instruments: DeletedInstruments,
+    kill_switches: KillSwitches,
+    price_bands: PriceBands,
+    funding: FundingCalculator,
+    liquidations: LiquidationMonitor,
+    bbo_state: BboStatePublisher,
+    tiering: BookTiering,
+    /// Log every command is written to before it is applied
+    wal: Option<WriteAheadLog>,
+    /// Slowest command since the last archive tick, in microseconds
+    max_command_latency_us: u64,
+    /// Shadow topic of the would-be results when commands are only previewed
+    dry_run: Option<String>,
+    /// Remainders of orders beyond their sweep limit, entered again between
+    /// the commands received
+    continuations: VecDeque<EngineCommand>,
+    risk: ParticipantRisk,
+    /// Recent order commands, to drop the ones delivered again
+    duplicates: DuplicateWindow,
+    /// Commands held back until the ones before them in their instrument's
+    /// sequence arrive
+    sequencer: Sequencer,
+    depth_deltas: DepthDeltaPublisher,
+    mbo: MboPublisher,
+}
+
+pub async fn run_engine(mut rx: CommandLanes, publisher: Publisher, config: EngineConfig) {
+    run_engine_with(&mut rx, publisher, config, None, Progress::default()).await;
+}
+
+/// Runs the engine on a borrowed channel, so a supervisor can hand the same one
+/// to a restarted engine. With `checkpoints`, the engine starts from the books
+/// they hold and keeps them up to date. Every command applied is counted in
+/// `progress`.
+pub async fn run_engine_with(
+    rx: &mut CommandLanes,
+    publisher: Publisher,
+    mut config: EngineConfig,
+    checkpoints: Option<Checkpoints>,
+    progress: Progress,
+) {
+    let publisher = if config.dry_run.enabled {
+        // A dry run changes no book and writes no file, and nothing but its
+        // previews and alerts leaves it
+        config.order_expiry.sweep_interval_ms = 0;
+        config.tiering.enabled = false;
+        config.archive.enabled = false;
+        config.shutdown.snapshot_dir = None;
+        warn!(
+            "Dry run: commands are previewed to {} and not applied",
+            config.dry_run.shadow_topic
+        );
+        publisher.restricted_to([config.dry_run.shadow_topic.as_str(), ALERTS_TOPIC])
+    } else {
+        publisher
+    };
+    let mut engine = Engine::new(&config, publisher);
+    if let Some((restored, taken_at)) = checkpoints
+        .as_ref()
+        .and_then(|checkpoints| checkpoints.restore(&mut engine.manager))
+    {
+        warn!(
+            "Engine restored {} books from the checkpoint of {} ms ago",
+            restored,
+            current_time_millis().saturating_sub(taken_at)
+        );
+        engine.features.apply_all(&mut engine.manager);
+    } else if config.wal.enabled && config.wal.recover {
+        let recovered = recover(&config);
+        engine.manager = recovered.manager;
+        engine.client_orders = recovered.client_orders;
+        engine.risk = recovered.risk;
+        engine.tiering.adopt(recovered.creates);
+        engine.continuations = recovered.remainders;
+        engine.features.apply_all(&mut engine.manager);
+    }
+    engine.tiering.load(&mut engine.manager);
+    if config.wal.enabled && !config.dry_run.enabled {
+        match WriteAheadLog::open(config.wal.clone()) {
+            Ok(log) => engine.wal = Some(log),
+            Err(e) => error!(
+                "Commands are not logged, failed to open the write-ahead log in {}: {}",
+                config.wal.directory, e
+            ),
+        }
+    }
+    let mut analytics_tick = tokio::time::interval(Duration::from_millis(
+        config.analytics.sample_interval_ms.max(1),
+    ));
+    analytics_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut rfq_tick =
+        tokio::time::interval(Duration::from_millis(config.rfq.sweep_interval_ms.max(1)));
+    rfq_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut feed_tick =
+        tokio::time::interval(Duration::from_millis(config.feeds.flush_interval_ms.max(1)));
+    feed_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut checkpoint_tick = tokio::time::interval(Duration::from_millis(
+        config.supervisor.checkpoint_interval_ms.max(1),
+    ));
+    checkpoint_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut heartbeat_tick = tokio::time::interval(Duration::from_millis(
+        config.liveness.heartbeat_interval_ms.max(1),
+    ));
+    heartbeat_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut journal_tick = tokio::time::interval(Duration::from_millis(
+        config.wal.snapshot_interval_ms.max(1),
+    ));
+    journal_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let snapshots_journal = engine.wal.is_some() && config.wal.snapshot_interval_ms > 0;
+    let mut order_expiry_tick = tokio::time::interval(Duration::from_millis(
+        config.order_expiry.sweep_interval_ms.max(1),
+    ));
+    order_expiry_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut tiering_tick = tokio::time::interval(Duration::from_millis(
+        config.tiering.sweep_interval_ms.max(1),
+    ));
+    tiering_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut sequencer_tick = tokio::time::interval(Duration::from_millis(
+        config.sequencer.sweep_interval_ms.max(1),
+    ));
+    sequencer_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut depth_delta_tick = tokio::time::interval(Duration::from_millis(
+        config.depth_deltas.flush_interval_ms.max(1),
+    ));
+    depth_delta_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut mbo_tick = tokio::time::interval(Duration::from_millis(
+        config.mbo.snapshot_interval_ms.max(1),
+    ));
+    mbo_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
-pub async fn run_engine(mut rx: Receiver<EngineCommand>) {
-    let mut manager = BookManagerStd::<()>::new();
     info!("Engine started, waiting for commands...");
-    while let Some(cmd) = rx.recv().await {
+    loop {
+        tokio::select! {
+            cmd = rx.recv() => {
+                let Some(cmd) = cmd else { break };
+                // A redelivered command is neither logged nor applied again
+                if engine.drop_duplicate(&cmd) {
+                    progress.duplicate();
+                    continue;
+                }
+                match engine.sequencer.admit(cmd, &engine.publisher, current_time_millis()) {
+                    Sequenced::Ready(ready) => {
+                        for cmd in ready {
+                            engine.apply(cmd);
+                            progress.applied(current_time_millis());
+                        }
+                    }
+                    Sequenced::Held => {}
+                    // Already applied, or given up on when its gap was skipped
+                    Sequenced::Stale(cmd) => {
+                        progress.duplicate();
+                        engine.reject_dropped(
+                            &cmd,
+                            Rejection::new(
+                                RejectReason::OutOfSequence,
+                                "behind its instrument's sequence",
+                            ),
+                        );
+                    }
+                }
+            }
+            _ = std::future::ready(()), if !engine.continuations.is_empty() => {
+                let Some(cmd) = engine.continuations.pop_front() else { continue };
+                engine.apply(cmd);
+            }
+            _ = analytics_tick.tick() => {
+                sample_correlations(
+                    &engine.manager,
+                    &mut engine.correlations,
+                    config.analytics.price_source,
+                );
+                engine.indices.on_tick(&engine.manager, &engine.publisher);
+                let now = current_time_millis();
+                if !config.dry_run.enabled {
+                    engine.reprice_pegged_orders(now);
+                    engine.settle_expired(now);
+                }
+                engine.funding.on_tick(
+                    |instrument_id| engine.fair_value.price(instrument_id),
+                    |index_id| engine.indices.value(index_id),
+                    &engine.publisher,
+                    now,
+                );
+                engine.clearing.on_tick(&engine.manager, now);
+                engine.order_to_trade.on_tick(&engine.publisher, now);
+                engine.execution_quality.on_tick(&engine.publisher, now);
+                engine.liquidations.on_tick(&engine.publisher, now);
+                let load = EngineLoad {
+                    queue_fill: rx.fill(),
+                    command_latency_us: std::mem::take(&mut engine.max_command_latency_us),
+                };
+                engine.on_sink_tick(load, now);
+            }
+            _ = feed_tick.tick() => {
+                engine
+                    .feeds
+                    .on_tick(&engine.manager, &engine.publisher, current_time_millis());
+                progress.delayed_dropped(engine.feeds.take_delayed_dropped());
+            }
+            _ = rfq_tick.tick() => {
+                for execution in sweep_rfqs(&mut engine.rfqs, &engine.publisher, &engine.rfq_config) {
+                    engine.record_rfq_execution(&execution);
+                }
+            }
+            _ = heartbeat_tick.tick(), if config.liveness.enabled => {
+                let orphaned =
+                    engine
+                        .liveness
+                        .on_tick(&engine.manager, &engine.publisher, current_time_millis());
+                for (instrument_id, order_id) in orphaned {
+                    engine.cancel_resting(instrument_id, order_id);
+                }
+            }
+            _ = checkpoint_tick.tick(), if checkpoints.is_some() => {
+                if let Some(checkpoints) = &checkpoints {
+                    checkpoints.store(&engine.manager, current_time_millis());
+                }
+            }
+            _ = journal_tick.tick(), if snapshots_journal => {
+                engine.snapshot_journal(current_time_millis());
+            }
+            _ = order_expiry_tick.tick(), if config.order_expiry.sweep_interval_ms > 0 => {
+                engine.expire_orders(current_time_millis());
+            }
+            _ = tiering_tick.tick(), if config.tiering.enabled => {
+                engine.evict_idle_books(current_time_millis());
+            }
+            _ = depth_delta_tick.tick(), if config.depth_deltas.enabled => {
+                engine
+                    .depth_deltas
+                    .on_tick(&engine.manager, &engine.publisher, current_time_millis());
+            }
+            _ = mbo_tick.tick(), if config.mbo.enabled => {
+                engine.mbo.on_tick(&engine.manager, &engine.publisher, current_time_millis());
+            }
+            _ = sequencer_tick.tick(), if config.sequencer.enabled => {
+                for cmd in engine.sequencer.on_tick(&engine.publisher, current_time_millis()) {
+                    engine.apply(cmd);
+                    progress.applied(current_time_millis());
+                }
+            }
+        }
+    }
+    // Commands still waiting on a gap are applied rather than lost
+    for cmd in engine.sequencer.drain() {
+        engine.apply(cmd);
+        progress.applied(current_time_millis());
+    }
+    engine.apply_continuations();
+    engine.stop(&config.shutdown);
+    info!("Engine stopped (command channel closed)");
+}
+
+/// What carries over from the write-ahead log to the engine that goes on
+struct Recovered {
+    manager: BookManagerStd<OrderTags>,
+    client_orders: ClientOrderIds,
+    risk: ParticipantRisk,
+    /// Create commands books are evicted with
+    creates: HashMap<String, serde_json::Value>,
+    /// Remainders requeued by a sweep limit and not entered yet
+    remainders: VecDeque<EngineCommand>,
+}
+
+/// Rebuilds the books from the newest snapshot of the write-ahead log and the
+/// commands logged after it
+///
+/// The commands run through an engine of their own that publishes nothing and
+/// writes no files, so recovery does not repeat any output. As with a checkpoint,
+/// only the books carry over to the engine that goes on, along with the client
+/// order ids and participant limits of their orders and the create commands
+/// books are evicted with;
+/// a command that panics is skipped rather than failing every startup.
+///
+/// Remainders requeued by a sweep limit are logged when entered again. Those
+/// the log does not hold were still queued when the engine stopped, and are
+/// returned to be entered by the engine that goes on.
+fn recover(config: &EngineConfig) -> Recovered {
+    let mut quiet = config.clone();
+    quiet.diagnostics.enabled = false;
+    quiet.feeds.profiles.clear();
+    quiet.archive.enabled = false;
+    quiet.sinks.sinks.clear();
+    quiet.verification.enabled = false;
+    quiet.dry_run.enabled = false;
+    let (publisher, mut discarded) = Publisher::channel(1_024);
+    let mut engine = Engine::new(&quiet, publisher);
+    let started = Instant::now();
+    let mut apply = |engine: &mut Engine, cmd: EngineCommand| {
+        // A logged remainder stands in for the one its order requeued
+        if let EngineCommand::OrderCreate(order) = &cmd {
+            engine.continuations.retain(|queued| {
+                !matches!(queued, EngineCommand::OrderCreate(remainder)
+                    if remainder.instrument_id == order.instrument_id
+                        && remainder.order_id == order.order_id)
+            });
+        }
+        let applied = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            engine.process_command(cmd);
+        }));
+        if applied.is_err() {
+            error!("Skipping a logged command that panicked");
+        }
+        while discarded.try_recv().is_ok() {}
+    };
+    let directory = Path::new(&config.wal.directory);
+    let mut from_seq = 0;
+    match wal::latest_snapshot(directory) {
+        Ok(Some(snapshot)) => {
+            from_seq = snapshot.seq;
+            for setting in &snapshot.settings {
+                match setting.parse() {
+                    Ok(Some(cmd)) => apply(&mut engine, cmd),
+                    Ok(None) => {}
+                    Err(e) => warn!(
+                        "Skipping a snapshotted command that no longer parses: {}",
+                        e
+                    ),
+                }
+            }
+            for package in snapshot.books {
+                let symbol = package.snapshot.symbol.clone();
+                if !engine.manager.has_book(&symbol) {
+                    engine.manager.add_book(&symbol);
+                }
+                if let Some(book) = engine.manager.get_book(&symbol)
+                    && let Err(e) = book.restore_from_snapshot_package(package)
+                {
+                    error!(
+                        "Failed to restore {} from the journal snapshot: {}",
+                        symbol, e
+                    );
+                }
+            }
+            engine.client_orders.restore(snapshot.client_orders);
+            engine.risk.restore(snapshot.open_orders);
+        }
+        Ok(None) => {}
+        Err(e) => error!(
+            "Failed to read the snapshots of the write-ahead log in {}: {}",
+            config.wal.directory, e
+        ),
+    }
+    let replayed = wal::replay(directory, from_seq, |cmd| apply(&mut engine, cmd));
+    match replayed {
+        Ok(commands) => info!(
+            "Recovered {} books from {} logged commands in {} ms",
+            engine.manager.book_count(),
+            commands,
+            started.elapsed().as_millis()
+        ),
+        Err(e) => error!(
+            "Failed to read the write-ahead log in {}, recovery stopped: {}",
+            config.wal.directory, e
+        ),
+    }
+    if !engine.continuations.is_empty() {
+        warn!(
+            "{} remainders of orders beyond their sweep limit were not entered before the engine stopped",
+            engine.continuations.len()
+        );
+    }
+    Recovered {
+        creates: engine.tiering.take_creates(),
+        manager: engine.manager,
+        client_orders: engine.client_orders,
+        risk: engine.risk,
+        remainders: engine.continuations,
+    }
+}
+
+/// Rebuilds the books from the write-ahead log as startup would, for
+/// `--mode replay` to report them without consuming anything
+pub fn replay_journal(config: &EngineConfig) -> BookManagerStd<OrderTags> {
+    recover(config).manager
+}
+
+/// Snapshots the books recovered from the write-ahead log and deletes every
+/// segment the snapshot covers, for `journal compact` while the engine is
+/// stopped. Refuses a log that fails `wal::verify`, which could lose commands.
+pub fn compact_journal(config: &EngineConfig) -> io::Result<Compaction> {
+    let verification = wal::verify(Path::new(&config.wal.directory))?;
+    if !verification.problems.is_empty() {
+        return Err(io::Error::other(format!(
+            "the log fails verification: {}",
+            verification.problems.join("; ")
+        )));
+    }
+    let recovered = recover(config);
+    let mut config = config.wal.clone();
+    config.retention_ms = 0;
+    config.retention_bytes = 0;
+    let mut log = WriteAheadLog::open(config)?;
+    let compaction = log.snapshot(
+        &recovered.manager,
+        &recovered.client_orders,
+        &recovered.risk,
+        current_time_millis(),
+    )?;
+    // Remainders not entered yet would be lost with the orders they came from
+    for remainder in &recovered.remainders {
+        log.append(remainder)?;
+    }
+    log.flush()?;
+    Ok(compaction)
+}
+
+impl Engine {
+    fn new(config: &EngineConfig, publisher: Publisher) -> Self {
+        let mut correlations = CorrelationTracker::new(config.analytics.correlation_window);
+        for (instrument_a, instrument_b) in &config.analytics.correlation_pairs {
+            correlations.add_pair(instrument_a, instrument_b);
+        }
+        let sinks = SinkPipeline::new(&config.sinks, &publisher);
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.serialize_extra_fields();
+        Self {
+            manager,
+            publisher,
+            invariants: InvariantMonitor::new(config.diagnostics.clone()),
+            fair_value: FairValueMonitor::new(config.fair_value.clone()),
+            correlations,
+            indices: IndexCalculator::new(config.indices.clone()),
+            rfqs: RfqManager::new(),
+            rfq_config: config.rfq.clone(),
+            trades: TradeProducer::new(config.trades.clone()),
+            trade_config: config.trades.clone(),
+            clearing: ClearingLedger::new(config.clearing.clone(), current_time_millis()),
+            expiries: ExpiryManager::new(config.instruments.clone()),
+            instrument_config: config.instruments.clone(),
+            mass_cancel_config: config.mass_cancel.clone(),
+            feeds: FeedPublisher::new(config.feeds.clone()),
+            archiver: BookArchiver::new(config.archive.clone(), current_time_millis()),
+            level_tap: LevelTap::new(),
+            sinks,
+            verifier: StateVerifier::new(config.verification.clone()),
+            features: FeatureFlags::new(&config.features),
+            order_to_trade: OrderToTradeMonitor::new(
+                config.order_to_trade.clone(),
+                current_time_millis(),
+            ),
+            liveness: OmsLiveness::new(config.liveness.clone()),
+            execution_quality: ExecutionQualityMonitor::new(
+                config.execution_quality.clone(),
+                current_time_millis(),
+            ),
+            client_orders: ClientOrderIds::new(),
+            order_responses: OrderResponder::new(config.order_responses.clone()),
+            deleted_instruments: DeletedInstruments::new(),
+            kill_switches: KillSwitches::new(),
+            price_bands: PriceBands::new(),
+            funding: FundingCalculator::new(config.funding.clone(), current_time_millis()),
+            liquidations: LiquidationMonitor::new(
+                config.liquidations.clone(),
+                current_time_millis(),
+            ),
+            bbo_state: BboStatePublisher::new(config.bbo_state.clone()),
+            tiering: BookTiering::new(config.tiering.clone()),
+            wal: None,
+            max_command_latency_us: 0,
+            dry_run: config
+                .dry_run
+                .enabled
+                .then(|| config.dry_run.shadow_topic.clone()),
+            continuations: VecDeque::new(),
+            risk: ParticipantRisk::new(config.risk.clone()),
+            duplicates: DuplicateWindow::new(&config.dedup),
+            sequencer: Sequencer::new(config.sequencer.clone()),
+            depth_deltas: DepthDeltaPublisher::new(config.depth_deltas.clone()),
+            mbo: MboPublisher::new(config.mbo.clone()),
+        }
+    }
+
+    /// Whether a command was delivered before, rejecting it if so
+    fn drop_duplicate(&mut self, cmd: &EngineCommand) -> bool {
+        if !self.duplicates.is_duplicate(cmd) {
+            return false;
+        }
+        self.reject_dropped(
+            cmd,
+            Rejection::new(RejectReason::Duplicate, "delivered again"),
+        );
+        true
+    }
+
+    /// Answers an order command dropped before it was logged
+    fn reject_dropped(&self, cmd: &EngineCommand, rejection: Rejection) {
+        if let Some(request) = OrderRequest::of(cmd) {
+            self.order_responses.respond(
+                &self.publisher,
+                &request,
+                &Err(rejection),
+                current_time_millis(),
+            );
+        }
+    }
+
+    /// Logs and applies a command, keeping track of the slowest one
+    fn apply(&mut self, cmd: EngineCommand) {
+        let started = Instant::now();
+        self.log(&cmd);
+        self.process_command(cmd);
+        self.max_command_latency_us = self
+            .max_command_latency_us
+            .max(started.elapsed().as_micros() as u64);
+    }
+
+    /// Enters the remainders of orders beyond their sweep limit still queued,
+    /// and the remainders they leave in turn
+    fn apply_continuations(&mut self) {
+        while let Some(cmd) = self.continuations.pop_front() {
+            self.apply(cmd);
+        }
+    }
+
+    /// Writes a command to the write-ahead log, if any, ahead of applying it
+    fn log(&mut self, cmd: &EngineCommand) {
+        if let Some(wal) = &mut self.wal
+            && let Err(e) = wal.append(cmd)
+        {
+            error!("Failed to write command to the write-ahead log: {}", e);
+        }
+    }
+
+    /// Cancels a resting order the engine itself decided to, returning
+    /// whether it no longer rests
+    ///
+    /// The cancel goes through the command path like any other, so it is
+    /// logged for recovery and reported as a cancel event.
+    fn cancel_resting(&mut self, instrument_id: String, order_id: OrderId) -> bool {
+        let Some(id) = engine_order_id(order_id) else {
+            warn!(
+                "Order {} on {} has no engine id to cancel it by",
+                order_id, instrument_id
+            );
+            return false;
+        };
+        let cmd = EngineCommand::OrderCancel(OrderCancelPayload {
+            order_id: id,
+            instrument_id: instrument_id.clone(),
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        });
+        self.log(&cmd);
+        self.process_command(cmd);
+        self.manager
+            .get_book(&instrument_id)
+            .is_none_or(|book| book.get_order(order_id).is_none())
+    }
+
+    /// Cancels the resting orders whose time in force has run out
+    fn expire_orders(&mut self, now: u64) {
+        let mut expired = Vec::new();
+        for instrument_id in self.manager.symbols() {
+            let Some(book) = self.manager.get_book(&instrument_id) else {
+                continue;
+            };
+            for order_id in book.expired_orders(now) {
+                expired.push((instrument_id.clone(), order_id));
+            }
+        }
+        for (instrument_id, order_id) in expired {
+            info!("Order {} on {} expired", order_id, instrument_id);
+            self.cancel_resting(instrument_id, order_id);
+        }
+    }
+
+    /// Settles the contracts whose expiry has passed
+    ///
+    /// The halt and the cancels of the resting orders go through the command
+    /// path, so they are logged for recovery and reported like any other.
+    fn settle_expired(&mut self, now: u64) {
+        for (instrument_id, expiry) in self.expiries.due(now) {
+            let halt = EngineCommand::Halt(TradingHaltPayload {
+                instrument_id: instrument_id.clone(),
+                reason: Some("contract expired".to_string()),
+                sequence: None,
+            });
+            self.log(&halt);
+            self.process_command(halt);
+            let Some(settlement) =
+                self.expiries
+                    .begin_settlement(&self.manager, instrument_id, expiry, now)
+            else {
+                continue;
+            };
+            let instrument_id = settlement.instrument_id().to_string();
+            let mut cancelled = Vec::new();
+            for &order_id in &settlement.resting_orders {
+                if self.cancel_resting(instrument_id.clone(), order_id) {
+                    cancelled.push(order_id);
+                }
+            }
+            self.expiries
+                .finish_settlement(&self.manager, &self.publisher, settlement, cancelled);
+        }
+    }
+
+    /// Moves the pegged orders of every book whose reference moved since the
+    /// last command, such as an index value set on this tick
+    ///
+    /// The moves go through the command path, so they are logged for recovery
+    /// and replayed as made rather than after a reference recovery cannot see.
+    fn reprice_pegged_orders(&mut self, now: u64) {
+        for instrument_id in self.manager.symbols() {
+            let Some(book) = self.manager.get_book(&instrument_id) else {
+                continue;
+            };
+            let moves = book.due_peg_moves(now);
+            if moves.is_empty() {
+                continue;
+            }
+            let cmd = EngineCommand::Admin(AdminCommandPayload::MovePeggedOrders {
+                instrument_id,
+                moves,
+            });
+            self.log(&cmd);
+            self.process_command(cmd);
+        }
+    }
+
+    /// Brings back the cold books a command needs: the one it names, the one
+    /// holding the order of a client order id it reuses, or all of them for a
+    /// mass cancel or an engaged kill switch
+    fn hydrate_books(&mut self, cmd: &EngineCommand) {
+        let mut needed: Vec<String> = cmd
+            .instrument_id()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        match cmd {
+            EngineCommand::OrderCreate(order) => needed.extend(
+                order
+                    .client_order_id
+                    .as_deref()
+                    .and_then(|client_order_id| {
+                        self.client_orders
+                            .instrument_of(order.participant_id.as_deref(), client_order_id)
+                    })
+                    .map(str::to_string),
+            ),
+            EngineCommand::OrderReplace(replace) => needed.extend(
+                replace
+                    .new_client_order_id
+                    .as_deref()
+                    .and_then(|client_order_id| {
+                        self.client_orders
+                            .instrument_of(replace.participant_id.as_deref(), client_order_id)
+                    })
+                    .map(str::to_string),
+            ),
+            EngineCommand::MassCancel(cancel) if cancel.instrument_id.is_none() => {
+                needed.extend(self.tiering.cold_instruments())
+            }
+            EngineCommand::KillSwitch(kill_switch)
+                if kill_switch.action == KillSwitchAction::Engage =>
+            {
+                needed.extend(self.tiering.cold_instruments())
+            }
+            _ => {}
+        }
+        for instrument_id in needed {
+            if self.tiering.is_cold(&instrument_id) {
+                self.tiering
+                    .hydrate(&mut self.manager, &mut self.expiries, &instrument_id);
+            }
+        }
+    }
+
+    /// Evicts the books idle long enough that nothing else needs in memory
+    ///
+    /// Books stay hot while halted or in an auction, while their instrument
+    /// has an expiry to run, feeds an index or correlation, or while they hold
+    /// an order that may expire or be cancelled when its OMS or session goes.
+    fn evict_idle_books(&mut self, now: u64) {
+        let tracked: HashSet<String> = self
+            .indices
+            .instruments()
+            .map(str::to_string)
+            .chain(self.correlations.instruments())
+            .collect();
+        let evicted = self
+            .tiering
+            .evict_idle(&mut self.manager, now, |instrument_id, book| {
+                book.is_halted()
+                    || book.is_in_auction()
+                    || self.expiries.is_scheduled(instrument_id)
+                    || tracked.contains(instrument_id)
+                    || !book.expired_orders(u64::MAX).is_empty()
+                    || book
+                        .get_all_orders()
+                        .iter()
+                        .any(|order| self.liveness.oms_of(instrument_id, order.id()).is_some())
+            });
+        for instrument_id in evicted {
+            self.level_tap.detach(&instrument_id);
+            self.mbo.detach(&instrument_id);
+        }
+    }
+
+    /// Snapshots the books into the write-ahead log, compacting what it covers
+    fn snapshot_journal(&mut self, now: u64) {
+        // The snapshot would cover the orders of queued remainders, which
+        // recovery would then not requeue
+        if !self.continuations.is_empty() {
+            return;
+        }
+        let Some(wal) = &mut self.wal else {
+            return;
+        };
+        let cold_books = match self.tiering.cold_snapshots() {
+            Ok(cold_books) => cold_books,
+            Err(e) => {
+                error!(
+                    "Skipping a snapshot of the write-ahead log, failed to read the cold books: {}",
+                    e
+                );
+                return;
+            }
+        };
+        match wal.snapshot_with(
+            &self.manager,
+            cold_books,
+            &self.client_orders,
+            &self.risk,
+            now,
+        ) {
+            Ok(compaction) if compaction.segments > 0 => info!(
+                "Compacted {} segments ({} bytes) of the write-ahead log",
+                compaction.segments, compaction.bytes
+            ),
+            Ok(_) => {}
+            Err(e) => error!("Failed to snapshot the write-ahead log: {}", e),
+        }
+    }
+
+    /// Persists every book and hands the last batched events to the sinks, once
+    /// the command channel has closed and every queued command was applied
+    fn stop(&mut self, config: &ShutdownConfig) {
+        if let Some(wal) = &mut self.wal
+            && let Err(e) = wal.flush()
+        {
+            error!("Failed to sync the write-ahead log: {}", e);
+        }
+        let now = current_time_millis();
+        self.archiver.on_tick(now);
+        self.sinks.on_tick(now);
+        let Some(directory) = &config.snapshot_dir else {
+            return;
+        };
+        match write_snapshots(&self.manager, Path::new(directory)) {
+            Ok(books) => info!("Wrote snapshots of {} books to {}", books, directory),
+            Err(e) => error!("Failed to write book snapshots to {}: {}", directory, e),
+        }
+    }
+
+    /// Hands an event to the archive and every configured sink
+    fn emit(&mut self, event: &SinkEvent<'_>) {
+        self.archiver.accept(event);
+        self.sinks.emit(event);
+    }
+
+    fn on_sink_tick(&mut self, load: EngineLoad, now: u64) {
+        self.archiver.on_tick(now);
+        self.sinks.on_tick(now);
+        let wanted = self.archiver.enabled() || self.sinks.wants(EventKind::Snapshot);
+        if !wanted || !self.archiver.snapshot_due(load, now) {
+            return;
+        }
+        let instrument_ids: Vec<String> = self.level_tap.attached().cloned().collect();
+        for instrument_id in instrument_ids {
+            if let Some(book) = self.manager.get_book(&instrument_id) {
+                let mut snapshot = book.create_snapshot(usize::MAX);
+                snapshot.timestamp = now;
+                self.emit(&SinkEvent::Snapshot(&snapshot));
+            }
+        }
+    }
+
+    fn record_rfq_execution(&mut self, execution: &RfqExecution) {
+        self.clearing.record_rfq_execution(execution);
+        let (buyer_id, seller_id) = match execution.side {
+            Side::Buy => (&execution.requester_id, &execution.dealer_id),
+            Side::Sell => (&execution.dealer_id, &execution.requester_id),
+        };
+        self.expiries.record_fill(
+            &execution.instrument_id,
+            Some(buyer_id),
+            Some(seller_id),
+            execution.quantity,
+        );
+    }
+
+    /// Counts an order message toward its participant's order-to-trade ratio,
+    /// or rejects it if the participant is throttled from entering orders
+    fn admit_order_message(&mut self, cmd: &EngineCommand, now: u64) -> Result<(), Rejection> {
+        // Liquidations are the risk engine's, not the participant's, messages
+        if cmd.is_liquidation() {
+            return Ok(());
+        }
+        let (instrument_id, participant_id) = match cmd {
+            EngineCommand::OrderCreate(order) => {
+                (&order.instrument_id, order.participant_id.clone())
+            }
+            EngineCommand::OrderModify(order) => (
+                &order.instrument_id,
+                self.clearing
+                    .participant(&order.instrument_id, OrderId::from_u64(order.order_id)),
+            ),
+            EngineCommand::OrderCancel(order) => (
+                &order.instrument_id,
+                self.clearing
+                    .participant(&order.instrument_id, OrderId::from_u64(order.order_id)),
+            ),
+            EngineCommand::OrderReplace(order) => (
+                &order.instrument_id,
+                self.clearing
+                    .participant(&order.instrument_id, OrderId::from_u64(order.order_id)),
+            ),
+            _ => return Ok(()),
+        };
+        let Some(participant_id) = participant_id else {
+            return Ok(());
+        };
+        // Cancels, modifies and replaces stay allowed, so a throttled
+        // participant can still reduce its exposure
+        if let EngineCommand::OrderCreate(order) = cmd
+            && self
+                .order_to_trade
+                .throttled(&participant_id, instrument_id)
+        {
+            warn!(
+                "Rejecting order {} of {} on {}: order-to-trade limit exceeded",
+                order.order_id, participant_id, instrument_id
+            );
+            return Err(Rejection::new(
+                RejectReason::Throttled,
+                "order-to-trade limit exceeded",
+            ));
+        }
+        self.order_to_trade
+            .record_message(&participant_id, instrument_id, now);
+        Ok(())
+    }
+
+    /// Rejects an order command whose price or quantity the spec of its
+    /// instrument does not allow
+    fn admit_instrument_spec(&self, cmd: &EngineCommand) -> Result<(), Rejection> {
+        let (instrument_id, order_id, price, quantity) = match cmd {
+            EngineCommand::OrderCreate(order) => (
+                &order.instrument_id,
+                order.order_id,
+                (!order.order_type.is_priced_by_book()).then_some(order.price),
+                order.quantity,
+            ),
+            EngineCommand::OrderModify(order) => (
+                &order.instrument_id,
+                order.order_id,
+                Some(order.price),
+                order.quantity,
+            ),
+            EngineCommand::OrderReplace(order) => (
+                &order.instrument_id,
+                order.order_id,
+                Some(order.price),
+                order.quantity,
+            ),
+            _ => return Ok(()),
+        };
+        let Some(spec) = self.manager.instrument_spec(instrument_id) else {
+            return Ok(());
+        };
+        spec.validate(price, quantity).map_err(|e| {
+            warn!("Rejecting order {} on {}: {}", order_id, instrument_id, e);
+            Rejection::from(e)
+        })
+    }
+
+    /// Applies an order and records who owns it while it rests
+    fn enter_order(&mut self, mut order: OrderCreatePayload, now: u64) -> Result<(), Rejection> {
+        if let Some(remainder) = cap_sweep(&self.manager, &mut order)? {
+            self.continuations
+                .push_back(EngineCommand::OrderCreate(remainder));
+        }
+        if let Some(participant_id) = order.participant_id.clone() {
+            self.clearing.register_order(
+                &order.instrument_id,
+                OrderId::from_u64(order.order_id),
+                participant_id,
+            );
+        }
+        let (oms_id, participant_id, instrument_id, order_id) = (
+            order.oms_id.clone(),
+            order.participant_id.clone(),
+            order.instrument_id.clone(),
+            order.order_id,
+        );
+        let client_order_id = order
+            .client_order_id
+            .clone()
+            .map(|id| (order.participant_id.clone(), id));
+        let outcome = handle_order_create(&mut self.manager, order);
+        let rests = self
+            .manager
+            .get_book(&instrument_id)
+            .is_some_and(|book| book.get_order(OrderId::from_u64(order_id)).is_some());
+        if rests && let Some(oms_id) = oms_id {
+            self.liveness
+                .register_order(&oms_id, &instrument_id, OrderId::from_u64(order_id), now);
+        }
+        if rests && let Some(participant_id) = &participant_id {
+            self.risk
+                .register(&self.manager, participant_id, &instrument_id, order_id);
+        }
+        if rests && let Some((participant_id, client_order_id)) = client_order_id {
+            self.client_orders.register(
+                participant_id.as_deref(),
+                &client_order_id,
+                &instrument_id,
+                order_id,
+            );
+        }
+        outcome
+    }
+
+    /// Cancels the orders a mass cancel selects and publishes its summary
+    fn mass_cancel(&mut self, mass_cancel: MassCancelPayload, now: u64) {
+        let summary = handle_mass_cancel(
+            &self.manager,
+            mass_cancel,
+            |instrument_id, order_id| self.clearing.participant(instrument_id, order_id),
+            |instrument_id, order_id| {
+                self.liveness.order_done(instrument_id, order_id);
+                self.client_orders.order_done(instrument_id, order_id);
+                self.risk.order_done(instrument_id, order_id);
+            },
+            now,
+        );
+        let key = summary
+            .request
+            .instrument_id
+            .as_deref()
+            .or(summary.request.participant_id.as_deref())
+            .unwrap_or("engine");
+        self.publisher
+            .publish(&self.mass_cancel_config.summary_topic, key, &summary);
+    }
+
+    /// Cancels the order a replace names and enters its replacement, which
+    /// belongs to the same participant and OMS
+    ///
+    /// A replacement the book refuses, e.g. a FOK one short of liquidity, leaves
+    /// the replaced order cancelled.
+    fn replace_order(
+        &mut self,
+        replace: OrderReplacePayload,
+        taker_tags: &mut OrderTags,
+        now: u64,
+    ) -> Result<(), Rejection> {
+        let (cancel, mut order) = split_order_replace(&self.manager, replace)?;
+        let replaced = OrderId::from_u64(cancel.order_id);
+        order.participant_id = order
+            .participant_id
+            .or_else(|| self.clearing.participant(&cancel.instrument_id, replaced));
+        order.oms_id = self.liveness.oms_of(&cancel.instrument_id, replaced);
+        order.client_order_id = order.client_order_id.or_else(|| {
+            self.client_orders
+                .client_order_id(&cancel.instrument_id, replaced)
+        });
+        self.risk
+            .admit_order(&self.manager, &order, Some(cancel.order_id))?;
+        self.liveness.order_done(&cancel.instrument_id, replaced);
+        self.client_orders
+            .order_done(&cancel.instrument_id, replaced);
+        let instrument_id = cancel.instrument_id.clone();
+        handle_order_cancel(&mut self.manager, cancel)?;
+        self.risk.refresh(&self.manager, &instrument_id, replaced);
+        taker_tags.clone_from(&order.tags);
+        self.enter_order(order, now)
+    }
+
+    /// Current price of the reference of a price band
+    fn band_reference(&self, instrument_id: &str, reference: BandReference) -> Option<u64> {
+        let book = self.manager.get_book(instrument_id);
+        match reference {
+            BandReference::Last => book?.last_trade_price(),
+            BandReference::Index => book?.external_reference_price(),
+            BandReference::Mid => {
+                let book = book?;
+                book.mid_price()
+                    .map(|mid| mid.round() as u64)
+                    .or_else(|| book.last_trade_price())
+            }
+            BandReference::Mark => self
+                .fair_value
+                .price(instrument_id)
+                .map(|price| price.round() as u64),
+        }
+    }
+
+    fn process_command(&mut self, cmd: EngineCommand) {
+        let started = Instant::now();
+        let now = current_time_millis();
+        self.hydrate_books(&cmd);
+        // Cancels and modifies naming a client order id learn their order id
+        // once resolved, and are answered with it from then on
+        let mut request = OrderRequest::of(&cmd);
+        let previewed = self.dry_run.is_some().then(|| {
+            (
+                cmd.kind(),
+                cmd.instrument_id().map(str::to_string),
+                request.as_ref().map(|request| request.order_id),
+            )
+        });
+        let admitted = self
+            .deleted_instruments
+            .admit(&cmd)
+            .and_then(|()| self.client_orders.resolve(&self.manager, cmd))
+            .and_then(|cmd| {
+                request = OrderRequest::of(&cmd);
+                self.kill_switches.admit(&cmd, |instrument_id, order_id| {
+                    self.clearing.participant(instrument_id, order_id)
+                })?;
+                self.admit_order_message(&cmd, now)?;
+                self.risk.admit(&self.manager, &cmd)?;
+                self.admit_instrument_spec(&cmd)?;
+                self.price_bands
+                    .admit(&cmd, |instrument_id, reference| {
+                        self.band_reference(instrument_id, reference)
+                    })
+                    .map(|()| cmd)
+            });
+        if let (Some(shadow_topic), Some((kind, instrument_id, order_id))) =
+            (&self.dry_run, previewed)
+        {
+            let outcome = admitted.and_then(|cmd| dry_run::preview(&self.manager, cmd));
+            let result = DryRunResult::new(kind, instrument_id, order_id, outcome, now);
+            let key = result.instrument_id.as_deref().unwrap_or("engine");
+            self.publisher.publish(shadow_topic, key, &result);
+            return;
+        }
+        let cmd = match admitted {
+            Ok(cmd) => cmd,
+            Err(rejection) => {
+                if let Some(request) = &request {
+                    self.order_responses
+                        .respond(&self.publisher, request, &Err(rejection), now);
+                }
+                return;
+            }
+        };
+        self.deleted_instruments.record(&cmd);
+        self.kill_switches.record(&cmd);
+        self.price_bands.record(&cmd);
+        self.tiering.record(&cmd, &self.manager, now);
+        self.funding.record(&cmd);
+        if cmd.is_liquidation()
+            && let Some(instrument_id) = cmd.instrument_id()
+        {
+            self.liquidations.record_order(instrument_id);
+        }
+        if let Some(order) = order_event(&cmd, &self.clearing, &self.manager, now) {
+            self.emit(&SinkEvent::Order(&order));
+        }
+        let manager = &mut self.manager;
+        let instrument_id = cmd.instrument_id().map(str::to_string);
+        if let Some(book) = cmd.instrument_id().and_then(|id| manager.get_book(id)) {
+            book.record_flight_event(FlightEvent::Command {
+                command: format!("{cmd:?}"),
+            });
+        }
+        // The book only learns the tags of an order once it rests
+        let mut taker_tags = OrderTags::new();
+        // Whether an order command was applied
+        let mut outcome = Ok(());
         match cmd {
             EngineCommand::InstrumentCreate(instr) => {
-                handle_instrument_create(&mut manager, instr);
+                handle_instrument_create(manager, &mut self.expiries, instr);
             }
             EngineCommand::InstrumentDelete(delete_instr) => {
-                handle_instrument_delete(&mut manager, delete_instr);
+                handle_instrument_delete(manager, delete_instr);
             }
-            EngineCommand::OrderCreate(order) => {
-                handle_order_create(&mut manager, order);
+            EngineCommand::Halt(halt) => handle_halt(manager, halt),
+            EngineCommand::Resume(resume) => handle_resume(manager, resume),
+            EngineCommand::AuctionStart(auction) => handle_auction_start(manager, auction),
+            EngineCommand::AuctionUncross(auction) => {
+                let instrument_id = auction.instrument_id.clone();
+                let uncross = handle_auction_uncross(manager, auction);
+                // Buyers filled in full leave the book like takers would
+                for filled in uncross.matches.iter().filter(|fill| fill.is_complete) {
+                    self.liveness.order_done(&instrument_id, filled.order_id);
+                    self.client_orders
+                        .order_done(&instrument_id, filled.order_id);
+                }
+            }
+            EngineCommand::InstrumentAdjust(adjust) => {
+                handle_instrument_adjust(manager, &self.publisher, &self.instrument_config, adjust);
+            }
+            EngineCommand::OrderCreate(mut order) => {
+                mark_liquidation(&mut order.tags, order.liquidation);
+                taker_tags.clone_from(&order.tags);
+                outcome = self.enter_order(order, now);
             }
             EngineCommand::OrderModify(order) => {
-                handle_order_modify(&mut manager, order);
+                let (instrument_id, order_id) = (
+                    order.instrument_id.clone(),
+                    OrderId::from_u64(order.order_id),
+                );
+                outcome = handle_order_modify(manager, order);
+                self.risk.refresh(manager, &instrument_id, order_id);
+            }
+            EngineCommand::OrderReplace(replace) => {
+                outcome = self.replace_order(replace, &mut taker_tags, now);
+            }
+            EngineCommand::MassCancel(mass_cancel) => self.mass_cancel(mass_cancel, now),
+            // Once engaged, the participant's new orders are turned away on
+            // admission
+            EngineCommand::KillSwitch(kill_switch) => {
+                if kill_switch.action == KillSwitchAction::Engage {
+                    let mass_cancel = MassCancelPayload {
+                        instrument_id: None,
+                        side: None,
+                        participant_id: Some(kill_switch.participant_id),
+                        sequence: None,
+                    };
+                    self.mass_cancel(mass_cancel, now);
+                }
             }
             EngineCommand::OrderCancel(order) => {
-                handle_order_cancel(&mut manager, order);
+                self.liveness
+                    .order_done(&order.instrument_id, OrderId::from_u64(order.order_id));
+                self.client_orders
+                    .order_done(&order.instrument_id, OrderId::from_u64(order.order_id));
+                let (instrument_id, order_id) = (
+                    order.instrument_id.clone(),
+                    OrderId::from_u64(order.order_id),
+                );
+                outcome = handle_order_cancel(manager, order);
+                self.risk.refresh(manager, &instrument_id, order_id);
+            }
+            EngineCommand::Admin(admin) => {
+                handle_admin_command(
+                    manager,
+                    &mut self.correlations,
+                    &mut self.clearing,
+                    &mut self.archiver,
+                    &mut self.features,
+                    &mut self.sinks,
+                    &mut self.risk,
+                    admin,
+                );
+            }
+            EngineCommand::TheoreticalPrice(theo) => {
+                let Some(book) = manager.get_book(&theo.instrument_id) else {
+                    warn!(
+                        "No book found for {}, ignoring theoretical price",
+                        theo.instrument_id
+                    );
+                    return;
+                };
+                self.fair_value.update(book, theo, &self.publisher);
+            }
+            EngineCommand::IndexDefine(index) => {
+                self.indices.define(index);
+            }
+            EngineCommand::RfqRequest(request) => {
+                handle_rfq_request(
+                    manager,
+                    &mut self.rfqs,
+                    &self.publisher,
+                    &self.rfq_config,
+                    request,
+                );
+            }
+            EngineCommand::RfqQuote(quote) => {
+                handle_rfq_quote(&mut self.rfqs, quote);
+            }
+            EngineCommand::RfqExecute(execute) => {
+                if let Some(execution) =
+                    handle_rfq_execute(&mut self.rfqs, &self.publisher, &self.rfq_config, execute)
+                {
+                    self.record_rfq_execution(&execution);
+                }
+            }
+            EngineCommand::OmsHeartbeat(heartbeat) => {
+                let orphaned = self.liveness.on_heartbeat(
+                    &heartbeat.oms_id,
+                    heartbeat.status,
+                    manager,
+                    &self.publisher,
+                    now,
+                );
+                // Logged after the heartbeat, and found already cancelled
+                // when the heartbeat is replayed
+                for (instrument_id, order_id) in orphaned {
+                    self.cancel_resting(instrument_id, order_id);
+                }
+            }
+            EngineCommand::BlockTrade(trade) => {
+                let instrument_id = trade.instrument_id.clone();
+                if let Some(trade) =
+                    handle_block_trade(manager, &self.publisher, &self.trade_config, trade)
+                {
+                    self.clearing.record_block_trade(&instrument_id, &trade);
+                    self.expiries.record_fill(
+                        &instrument_id,
+                        Some(&trade.buyer_id),
+                        Some(&trade.seller_id),
+                        trade.quantity,
+                    );
+                }
+            }
+        }
+        if let Some(request) = &request {
+            self.order_responses
+                .respond(&self.publisher, request, &outcome, now);
+        }
+        for mut event in self.manager.drain_trade_events() {
+            attach_taker_tags(&mut event.trade_result, &taker_tags);
+            if let Some(book) = self.manager.get_book(&event.symbol) {
+                self.trades.on_trade_event(&event, book, &self.publisher);
+                self.feeds
+                    .on_trade_event(&event, book, &self.publisher, now);
+            }
+            for transaction in event.trade_result.match_result.transactions.as_vec() {
+                for order_id in [transaction.taker_order_id, transaction.maker_order_id] {
+                    self.risk.refresh(&self.manager, &event.symbol, order_id);
+                }
+                let taker = trade_tags(&event.trade_result, transaction.taker_order_id);
+                let maker = trade_tags(&event.trade_result, transaction.maker_order_id);
+                let liquidation = is_liquidation(&taker) || is_liquidation(&maker);
+                if liquidation {
+                    self.liquidations.record_trade(
+                        &event.symbol,
+                        transaction.price,
+                        transaction.quantity,
+                    );
+                }
+                self.emit(&SinkEvent::Trade(&TradeRecord {
+                    instrument_id: event.symbol.clone(),
+                    trade_id: transaction.transaction_id,
+                    price: transaction.price,
+                    quantity: transaction.quantity,
+                    aggressor_side: transaction.taker_side,
+                    taker_order_id: transaction.taker_order_id,
+                    maker_order_id: transaction.maker_order_id,
+                    timestamp: transaction.timestamp,
+                    taker_participant_id: self
+                        .clearing
+                        .participant(&event.symbol, transaction.taker_order_id),
+                    maker_participant_id: self
+                        .clearing
+                        .participant(&event.symbol, transaction.maker_order_id),
+                    book_context: event.trade_result.book_context,
+                    taker_tags: taker,
+                    maker_tags: maker,
+                    liquidation,
+                }));
+                for order_id in [transaction.taker_order_id, transaction.maker_order_id] {
+                    if let Some(participant_id) = self.clearing.participant(&event.symbol, order_id)
+                    {
+                        self.order_to_trade
+                            .record_trade(&participant_id, &event.symbol, now);
+                    }
+                }
+                if self
+                    .manager
+                    .get_book(&event.symbol)
+                    .is_some_and(|book| book.get_order(transaction.maker_order_id).is_none())
+                {
+                    self.liveness
+                        .order_done(&event.symbol, transaction.maker_order_id);
+                    self.client_orders
+                        .order_done(&event.symbol, transaction.maker_order_id);
+                }
+            }
+            let taker_participant_id = self
+                .clearing
+                .participant(&event.symbol, event.trade_result.match_result.order_id);
+            if let Some(execution) =
+                self.execution_quality
+                    .record(&event, taker_participant_id, started.elapsed())
+            {
+                self.emit(&SinkEvent::Execution(&execution));
+            }
+            let order_to_trade = &self.order_to_trade;
+            self.clearing
+                .record_trade_event(&event, |participant_id, instrument_id| {
+                    order_to_trade.surcharged(participant_id, instrument_id)
+                });
+            self.expiries
+                .record_trade_event(&event, |symbol, order_id| {
+                    self.clearing.participant(symbol, order_id)
+                });
+        }
+        let changes = self.level_tap.drain();
+        for change in &changes {
+            self.emit(&SinkEvent::Delta(change));
+        }
+        self.depth_deltas.on_changes(&changes, &self.publisher);
+        self.mbo.drain(&self.publisher);
+        // Pegged orders follow the prices this command moved
+        if let Some(id) = &instrument_id
+            && let Some(book) = self.manager.get_book(id)
+        {
+            book.reprice_pegged_orders(now);
+        }
+        // Books created by this command start out with every feature
+        if let Some(id) = &instrument_id
+            && let Some(book) = self.manager.get_book_mut(id)
+        {
+            self.features.apply(book);
+        }
+        // A new book starts its history with a snapshot, so levels of an earlier
+        // book of the same instrument do not carry over
+        if let Some(id) = &instrument_id
+            && let Some(book) = self.manager.get_book_mut(id)
+            && self.level_tap.attach(book)
+        {
+            let mut snapshot = book.create_snapshot(usize::MAX);
+            snapshot.timestamp = now;
+            self.emit(&SinkEvent::Snapshot(&snapshot));
+            self.depth_deltas.on_snapshot(&snapshot, &self.publisher);
+        }
+        if let Some(id) = &instrument_id
+            && let Some(book) = self.manager.get_book_mut(id)
+        {
+            self.mbo.attach(book, &self.publisher, now);
+        }
+        if let Some(id) = &instrument_id {
+            self.verifier
+                .on_command(id, self.manager.get_book(id), &self.publisher);
+        }
+        if let Some(id) = instrument_id {
+            match self.manager.get_book(&id) {
+                Some(book) => {
+                    self.invariants.check(book, &self.publisher);
+                    self.fair_value.check(book, &self.publisher);
+                    self.feeds.on_book_change(book, &self.publisher, now);
+                    self.bbo_state.on_book_change(book, &self.publisher, now);
+                }
+                // A book that failed to hydrate is still there, on disk
+                None if self.tiering.is_cold(&id) => {}
+                None => {
+                    self.clearing.forget(&id);
+                    self.risk.forget(&id);
+                    self.invariants.forget(&id);
+                    self.fair_value.forget(&id);
+                    self.expiries.forget(&id);
+                    self.feeds.forget(&id);
+                    self.bbo_state.forget(&id, &self.publisher, now);
+                    if self.level_tap.detach(&id) {
+                        let snapshot = OrderBookSnapshot {
+                            symbol: id.clone(),
+                            timestamp: now,
+                            bids: Vec::new(),
+                            asks: Vec::new(),
+                            extra_fields: Vec::new(),
+                        };
+                        self.emit(&SinkEvent::Snapshot(&snapshot));
+                        self.depth_deltas.on_snapshot(&snapshot, &self.publisher);
+                    }
+                    self.depth_deltas.forget(&id);
+                    self.mbo.forget(&id, &self.publisher, now);
+                }
             }
         }
     }
-    info!("Engine stopped (command channel closed)");
+}
+
+/// The `u64` an order id was created from with `OrderId::from_u64`, as every
+/// order entered through the engine's commands is
+fn engine_order_id(order_id: OrderId) -> Option<u64> {
+    let id = u64::from_be_bytes(order_id.as_bytes()[..8].try_into().ok()?);
+    (OrderId::from_u64(id) == order_id).then_some(id)
+}
+
+/// The order event for an order command, if it is one, taken before the
+/// command is applied
+fn order_event(
+    cmd: &EngineCommand,
+    clearing: &ClearingLedger,
+    manager: &BookManagerStd<OrderTags>,
+    now: u64,
+) -> Option<OrderEvent> {
+    let owner = |instrument_id: &str, order_id: u64| {
+        clearing.participant(instrument_id, OrderId::from_u64(order_id))
+    };
+    let tags = |instrument_id: &str, order_id: u64| {
+        manager
+            .get_book(instrument_id)
+            .and_then(|book| book.order_extra_fields(OrderId::from_u64(order_id)))
+            .unwrap_or_default()
+    };
+    let event = match cmd {
+        EngineCommand::OrderCreate(order) => OrderEvent {
+            instrument_id: order.instrument_id.clone(),
+            order_id: order.order_id,
+            action: OrderAction::Create,
+            side: Some(order.side),
+            price: Some(order.price),
+            quantity: Some(order.quantity),
+            timestamp: now,
+            participant_id: order.participant_id.clone(),
+            tags: order.tags.clone(),
+        },
+        EngineCommand::OrderModify(order) => OrderEvent {
+            instrument_id: order.instrument_id.clone(),
+            order_id: order.order_id,
+            action: OrderAction::Modify,
+            side: None,
+            price: Some(order.price),
+            quantity: Some(order.quantity),
+            timestamp: now,
+            participant_id: owner(&order.instrument_id, order.order_id),
+            tags: tags(&order.instrument_id, order.order_id),
+        },
+        EngineCommand::OrderReplace(order) => OrderEvent {
+            instrument_id: order.instrument_id.clone(),
+            order_id: order.order_id,
+            action: OrderAction::Replace,
+            side: order.side,
+            price: Some(order.price),
+            quantity: Some(order.quantity),
+            timestamp: now,
+            participant_id: owner(&order.instrument_id, order.order_id),
+            tags: tags(&order.instrument_id, order.order_id),
+        },
+        EngineCommand::OrderCancel(order) => OrderEvent {
+            instrument_id: order.instrument_id.clone(),
+            order_id: order.order_id,
+            action: OrderAction::Cancel,
+            side: None,
+            price: None,
+            quantity: None,
+            timestamp: now,
+            participant_id: owner(&order.instrument_id, order.order_id),
+            tags: tags(&order.instrument_id, order.order_id),
+        },
+        _ => return None,
+    };
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::topics::CommandKind;
+    use crate::config::wal::FsyncPolicy;
+    use crate::helpers::types::InstrumentExpiry;
+
+    fn order(payload: String) -> EngineCommand {
+        EngineCommand::parse(CommandKind::OrderCreate, &payload)
+            .unwrap()
+            .unwrap()
+    }
+
+    fn sell(order_id: u64, price: u64) -> EngineCommand {
+        order(format!(
+            r#"{{"order_id":{order_id},"instrument_id":"BTC","quantity":5,"price":{price},"side":"Sell","time_in_force":"Gtc","order_type":"LIMIT"}}"#
+        ))
+    }
+
+    fn buy(order_id: u64, price: u64) -> EngineCommand {
+        order(format!(
+            r#"{{"order_id":{order_id},"instrument_id":"BTC","quantity":5,"price":{price},"side":"Buy","time_in_force":"Ioc","order_type":"LIMIT"}}"#
+        ))
+    }
+
+    fn best_ask(manager: &BookManagerStd<OrderTags>) -> Option<u64> {
+        manager.get_book("BTC").unwrap().best_ask()
+    }
+
+    /// An engine logging to a write-ahead log of its own
+    fn logging_engine(mut config: EngineConfig) -> (EngineConfig, Engine) {
+        config.wal.enabled = true;
+        config.wal.fsync = FsyncPolicy::Always;
+        config.wal.directory = std::env::temp_dir()
+            .join(format!("engine-wal-{}", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        let (publisher, _) = Publisher::channel(1_024);
+        let mut engine = Engine::new(&config, publisher);
+        engine.wal = Some(WriteAheadLog::open(config.wal.clone()).unwrap());
+        (config, engine)
+    }
+
+    #[test]
+    fn test_remainders_queued_at_shutdown_are_entered_and_recovered() {
+        let (config, mut engine) = logging_engine(EngineConfig::default());
+        for (order_id, price) in [(1, 100), (2, 101), (3, 102)] {
+            engine.apply(sell(order_id, price));
+        }
+        // Takes one level per sweep
+        engine.apply(order(
+            r#"{"order_id":4,"instrument_id":"BTC","quantity":15,"price":102,"side":"Buy","time_in_force":"Gtc","order_type":"LIMIT","sweep_limit":{"max_levels":1,"remainder":"requeue"}}"#
+                .to_string(),
+        ));
+        assert_eq!(engine.continuations.len(), 1);
+        assert_eq!(best_ask(&engine.manager), Some(101));
+
+        // Stopped now, the remainder the log does not hold is handed back
+        let recovered = recover(&config);
+        assert_eq!(best_ask(&recovered.manager), Some(101));
+        assert_eq!(recovered.remainders.len(), 1);
+
+        engine.apply_continuations();
+        assert!(engine.continuations.is_empty());
+        assert_eq!(best_ask(&engine.manager), None);
+        let recovered = recover(&config);
+        assert_eq!(best_ask(&recovered.manager), None);
+        assert!(recovered.remainders.is_empty());
+        std::fs::remove_dir_all(&config.wal.directory).unwrap();
+    }
+
+    #[test]
+    fn test_expiry_settlement_is_logged_for_recovery() {
+        let (config, mut engine) = logging_engine(EngineConfig::default());
+        engine.apply(sell(1, 100));
+        engine.apply(sell(2, 101));
+        engine.expiries.schedule(
+            "BTC",
+            InstrumentExpiry {
+                expires_at: 1_000,
+                roll_to: None,
+            },
+        );
+        engine.settle_expired(1_000);
+        let book = engine.manager.get_book("BTC").unwrap();
+        assert!(book.is_halted());
+        assert!(book.get_all_orders().is_empty());
+
+        let recovered = recover(&config);
+        let book = recovered.manager.get_book("BTC").unwrap();
+        assert!(book.is_halted());
+        assert!(book.get_all_orders().is_empty());
+        std::fs::remove_dir_all(&config.wal.directory).unwrap();
+    }
+
+    #[test]
+    fn test_duplicates_are_rejected() {
+        let mut config = EngineConfig::default();
+        config.dedup.enabled = true;
+        config.order_responses.enabled = true;
+        let (publisher, mut outbound) = Publisher::channel(16);
+        let mut engine = Engine::new(&config, publisher);
+        assert!(!engine.drop_duplicate(&sell(1, 100)));
+        assert!(outbound.try_recv().is_err());
+        assert!(engine.drop_duplicate(&sell(1, 100)));
+        let reject = outbound.try_recv().unwrap();
+        assert_eq!(reject.topic, "order.reject");
+        let reject: serde_json::Value = serde_json::from_str(&reject.payload).unwrap();
+        assert_eq!(reject["order_id"], 1);
+        assert_eq!(reject["reason"], "duplicate");
+    }
+
+    #[test]
+    fn test_mid_price_bands_fall_back_to_the_last_trade() {
+        let (publisher, _) = Publisher::channel(1_024);
+        let mut engine = Engine::new(&EngineConfig::default(), publisher);
+        let create = r#"{"instrument_id":"BTC","price_band":{"reference":"mid","kind":"percent","percent":5}}"#;
+        engine.apply(
+            EngineCommand::parse(CommandKind::InstrumentCreate, create)
+                .unwrap()
+                .unwrap(),
+        );
+        let rests = |engine: &Engine, order_id: u64| {
+            engine
+                .manager
+                .get_book("BTC")
+                .unwrap()
+                .get_order(OrderId::from_u64(order_id))
+                .is_some()
+        };
+        engine.apply(sell(1, 104));
+        engine.apply(order(
+            r#"{"order_id":2,"instrument_id":"BTC","quantity":5,"price":96,"side":"Buy","time_in_force":"Gtc","order_type":"LIMIT"}"#
+                .to_string(),
+        ));
+        // Within 5% of the mid at 100, then past it
+        engine.apply(sell(3, 105));
+        engine.apply(sell(4, 106));
+        assert!(rests(&engine, 3));
+        assert!(!rests(&engine, 4));
+
+        // With the asks taken, the band follows the last trade at 105
+        engine.apply(buy(5, 105));
+        engine.apply(buy(6, 105));
+        assert_eq!(best_ask(&engine.manager), None);
+        engine.apply(sell(7, 111));
+        engine.apply(sell(8, 110));
+        assert!(!rests(&engine, 7));
+        assert!(rests(&engine, 8));
+    }
+
+    #[test]
+    fn test_kill_switch_cancels_the_orders_of_evicted_books() {
+        let mut config = EngineConfig::default();
+        config.tiering.enabled = true;
+        config.tiering.idle_ms = 0;
+        config.tiering.directory = std::env::temp_dir()
+            .join(format!("engine-cold-{}", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        let (publisher, _) = Publisher::channel(1_024);
+        let mut engine = Engine::new(&config, publisher);
+        let create = r#"{"instrument_id":"BTC"}"#;
+        engine.apply(
+            EngineCommand::parse(CommandKind::InstrumentCreate, create)
+                .unwrap()
+                .unwrap(),
+        );
+        engine.apply(order(
+            r#"{"order_id":1,"instrument_id":"BTC","quantity":5,"price":100,"side":"Sell","time_in_force":"Gtc","order_type":"LIMIT","participant_id":"desk-1"}"#
+                .to_string(),
+        ));
+        engine.evict_idle_books(current_time_millis() + 1);
+        assert!(!engine.manager.has_book("BTC"));
+
+        let engage = r#"{"participant_id":"desk-1"}"#;
+        engine.apply(
+            EngineCommand::parse(CommandKind::KillSwitch, engage)
+                .unwrap()
+                .unwrap(),
+        );
+        assert_eq!(best_ask(&engine.manager), None);
+        std::fs::remove_dir_all(&config.tiering.directory).unwrap();
+    }
+
+    #[test]
+    fn test_pegged_orders_moved_on_the_tick_are_logged_for_recovery() {
+        let (config, mut engine) = logging_engine(EngineConfig::default());
+        let create =
+            r#"{"instrument_id":"BTC","peg_repricing":{"min_interval_ms":0,"min_move":1}}"#;
+        engine.apply(
+            EngineCommand::parse(CommandKind::InstrumentCreate, create)
+                .unwrap()
+                .unwrap(),
+        );
+        let pegged = OrderId::from_u64(1);
+        let book = engine.manager.get_book("BTC").unwrap();
+        book.add_order(pricelevel::OrderType::PeggedOrder {
+            id: pegged,
+            price: 90,
+            quantity: 5,
+            side: Side::Buy,
+            timestamp: 0,
+            time_in_force: pricelevel::TimeInForce::Gtc,
+            reference_price_offset: -1,
+            reference_price_type: pricelevel::PegReferenceType::MidPrice,
+            extra_fields: OrderTags::new(),
+        })
+        .unwrap();
+        engine.snapshot_journal(current_time_millis());
+        // As an index pegging the book would on the tick, unseen by recovery
+        let book = engine.manager.get_book("BTC").unwrap();
+        book.set_external_reference_price(120);
+        engine.reprice_pegged_orders(current_time_millis());
+        let price = |manager: &BookManagerStd<OrderTags>| {
+            manager
+                .get_book("BTC")
+                .unwrap()
+                .get_order(pegged)
+                .unwrap()
+                .price()
+        };
+        assert_eq!(price(&engine.manager), 119);
+
+        let recovered = recover(&config);
+        assert_eq!(price(&recovered.manager), 119);
+        std::fs::remove_dir_all(&config.wal.directory).unwrap();
+    }
+
+    #[test]
+    fn test_disconnect_cancels_are_logged_for_recovery() {
+        let mut config = EngineConfig::default();
+        config.liveness.enabled = true;
+        let (config, mut engine) = logging_engine(config);
+        engine.apply(order(
+            r#"{"order_id":1,"instrument_id":"BTC","quantity":5,"price":100,"side":"Sell","time_in_force":"Gtc","order_type":"LIMIT","oms_id":"oms-a"}"#
+                .to_string(),
+        ));
+        engine.apply(sell(2, 101));
+        let disconnect = r#"{"oms_id":"oms-a","status":"disconnect"}"#;
+        engine.apply(
+            EngineCommand::parse(CommandKind::OmsHeartbeat, disconnect)
+                .unwrap()
+                .unwrap(),
+        );
+        assert_eq!(best_ask(&engine.manager), Some(101));
+
+        let mut cancelled = Vec::new();
+        wal::replay(Path::new(&config.wal.directory), 0, |cmd| {
+            if let EngineCommand::OrderCancel(cancel) = cmd {
+                cancelled.push(cancel.order_id);
+            }
+        })
+        .unwrap();
+        assert_eq!(cancelled, [1]);
+        let recovered = recover(&config);
+        assert_eq!(best_ask(&recovered.manager), Some(101));
+        std::fs::remove_dir_all(&config.wal.directory).unwrap();
+    }
 }
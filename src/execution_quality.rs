@@ -0,0 +1,333 @@
+// src/execution_quality.rs
+use crate::config::execution_quality::ExecutionQualityConfig;
+use crate::orderbook::trade::TradeEvent;
+use crate::publisher::Publisher;
+use pricelevel::{OrderId, Side};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+/// How one aggressive order executed, for the audit trail
+#[derive(Debug, Clone, Serialize)]
+pub struct AggressorExecution {
+    pub instrument_id: String,
+    pub order_id: OrderId,
+    pub side: Side,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub participant_id: Option<String>,
+    /// Microseconds from the engine taking the order to the end of its matching
+    pub lifetime_us: u64,
+    /// Distinct prices the order filled at
+    pub levels_swept: usize,
+    pub filled_quantity: u64,
+    /// Volume-weighted price of the fills, in raw book units
+    pub average_price: f64,
+    /// Midpoint of the touch when the order arrived, if both sides were quoted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arrival_mid: Option<f64>,
+    /// Cost of the fills against the arrival mid in basis points; positive is
+    /// worse than mid for the aggressor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slippage_bps: Option<f64>,
+    /// Whether the order filled completely rather than resting or expiring
+    pub fully_filled: bool,
+    pub timestamp: u64,
+}
+
+/// Summary of the values recorded since the last report
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    /// Nearest-rank percentiles of the values, `None` when there are none
+    pub fn of(values: &mut [f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let count = values.len();
+        let percentile = |p: f64| values[((p * count as f64).ceil() as usize).clamp(1, count) - 1];
+        Some(Self {
+            count,
+            mean: values.iter().sum::<f64>() / count as f64,
+            p50: percentile(0.50),
+            p90: percentile(0.90),
+            p99: percentile(0.99),
+            max: values[count - 1],
+        })
+    }
+}
+
+/// Execution quality of the aggressive orders on an instrument, as published
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExecutionQualityReport {
+    pub instrument_id: String,
+    /// Start of the period the distributions cover
+    pub since: u64,
+    pub timestamp: u64,
+    pub lifetime_us: Distribution,
+    pub levels_swept: Distribution,
+    /// Missing when no order arrived at a two-sided book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slippage_bps: Option<Distribution>,
+}
+
+#[derive(Default)]
+struct Samples {
+    lifetime_us: Vec<f64>,
+    levels_swept: Vec<f64>,
+    slippage_bps: Vec<f64>,
+}
+
+/// Measures how aggressive orders execute: how long they take, how many levels
+/// they sweep and how far their fills land from the mid they arrived at
+///
+/// Samples accumulate per instrument and are summarised and cleared on each
+/// report, so every report covers the period since the previous one.
+pub struct ExecutionQualityMonitor {
+    config: ExecutionQualityConfig,
+    samples: BTreeMap<String, Samples>,
+    since: u64,
+    next_report_at: u64,
+}
+
+impl ExecutionQualityMonitor {
+    pub fn new(config: ExecutionQualityConfig, now: u64) -> Self {
+        let next_report_at = now + config.report_interval_ms;
+        Self {
+            config,
+            samples: BTreeMap::new(),
+            since: now,
+            next_report_at,
+        }
+    }
+
+    /// Records the match of an aggressive order, returning its execution
+    /// record; `None` when disabled or nothing filled
+    pub fn record(
+        &mut self,
+        event: &TradeEvent,
+        participant_id: Option<String>,
+        lifetime: Duration,
+    ) -> Option<AggressorExecution> {
+        if !self.config.enabled {
+            return None;
+        }
+        let match_result = &event.trade_result.match_result;
+        let transactions = match_result.transactions.as_vec();
+        let side = transactions.first()?.taker_side;
+        let filled_quantity: u64 = transactions.iter().map(|fill| fill.quantity).sum();
+        if filled_quantity == 0 {
+            return None;
+        }
+        let notional: f64 = transactions
+            .iter()
+            .map(|fill| fill.price as f64 * fill.quantity as f64)
+            .sum();
+        let average_price = notional / filled_quantity as f64;
+        let levels_swept = transactions
+            .iter()
+            .map(|fill| fill.price)
+            .collect::<HashSet<_>>()
+            .len();
+        let arrival_mid = event
+            .trade_result
+            .book_context
+            .and_then(|context| Some((context.best_bid? as f64 + context.best_ask? as f64) / 2.0));
+        let slippage_bps = arrival_mid.filter(|&mid| mid > 0.0).map(|mid| {
+            let cost = match side {
+                Side::Buy => average_price - mid,
+                Side::Sell => mid - average_price,
+            };
+            cost / mid * 10_000.0
+        });
+        let execution = AggressorExecution {
+            instrument_id: event.symbol.clone(),
+            order_id: match_result.order_id,
+            side,
+            participant_id,
+            lifetime_us: lifetime.as_micros() as u64,
+            levels_swept,
+            filled_quantity,
+            average_price,
+            arrival_mid,
+            slippage_bps,
+            fully_filled: match_result.is_complete,
+            timestamp: event.timestamp,
+        };
+        let samples = self.samples.entry(event.symbol.clone()).or_default();
+        samples.lifetime_us.push(execution.lifetime_us as f64);
+        samples.levels_swept.push(levels_swept as f64);
+        samples.slippage_bps.extend(slippage_bps);
+        Some(execution)
+    }
+
+    /// Distributions of every instrument with aggressive orders since the last
+    /// report, restarting the samples
+    pub fn report(&mut self, now: u64) -> Vec<ExecutionQualityReport> {
+        let since = std::mem::replace(&mut self.since, now);
+        std::mem::take(&mut self.samples)
+            .into_iter()
+            .filter_map(|(instrument_id, mut samples)| {
+                Some(ExecutionQualityReport {
+                    instrument_id,
+                    since,
+                    timestamp: now,
+                    lifetime_us: Distribution::of(&mut samples.lifetime_us)?,
+                    levels_swept: Distribution::of(&mut samples.levels_swept)?,
+                    slippage_bps: Distribution::of(&mut samples.slippage_bps),
+                })
+            })
+            .collect()
+    }
+
+    /// Publishes the distributions once the report interval has passed
+    pub fn on_tick(&mut self, publisher: &Publisher, now: u64) {
+        if !self.config.enabled || now < self.next_report_at {
+            return;
+        }
+        self.next_report_at = now + self.config.report_interval_ms;
+        for report in self.report(now) {
+            publisher.publish(&self.config.metrics_topic, &report.instrument_id, &report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::OrderBook;
+    use crate::orderbook::trade::TradeResult;
+    use crate::tags::OrderTags;
+    use pricelevel::TimeInForce;
+
+    fn monitor() -> ExecutionQualityMonitor {
+        ExecutionQualityMonitor::new(
+            ExecutionQualityConfig {
+                enabled: true,
+                report_interval_ms: 1_000,
+                ..ExecutionQualityConfig::default()
+            },
+            0,
+        )
+    }
+
+    /// Bid at 98, asks of 5 at 102, 103 and 104
+    fn book() -> OrderBook<OrderTags> {
+        let book = OrderBook::new("BTC");
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            98,
+            5,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        for (id, price) in [(2, 102), (3, 103), (4, 104)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                5,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        book
+    }
+
+    fn take(book: &OrderBook<OrderTags>, id: u64, side: Side, quantity: u64) -> TradeEvent {
+        let book_context = book.book_context();
+        let match_result = book
+            .match_order(OrderId::from_u64(id), side, quantity, None)
+            .unwrap();
+        TradeEvent {
+            symbol: "BTC".to_string(),
+            trade_result: TradeResult::new("BTC".to_string(), match_result)
+                .with_book_context(Some(book_context)),
+            timestamp: 7,
+        }
+    }
+
+    #[test]
+    fn test_sweep_is_measured_against_arrival_mid() {
+        let book = book();
+        let mut monitor = monitor();
+        let event = take(&book, 10, Side::Buy, 10);
+        let execution = monitor
+            .record(
+                &event,
+                Some("desk-1".to_string()),
+                Duration::from_micros(40),
+            )
+            .unwrap();
+        assert_eq!(execution.levels_swept, 2);
+        assert_eq!(execution.filled_quantity, 10);
+        assert_eq!(execution.average_price, 102.5);
+        assert_eq!(execution.arrival_mid, Some(100.0));
+        assert_eq!(execution.slippage_bps, Some(250.0));
+        assert_eq!(execution.lifetime_us, 40);
+        assert!(execution.fully_filled);
+
+        // Selling into the bid below mid costs the seller as well
+        let execution = monitor
+            .record(&take(&book, 11, Side::Sell, 5), None, Duration::ZERO)
+            .unwrap();
+        assert_eq!(execution.arrival_mid, Some(101.0));
+        assert!(execution.slippage_bps.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_reports_cover_the_period_since_the_last() {
+        let book = book();
+        let mut monitor = monitor();
+        for (id, lifetime_us) in [(10, 10), (11, 30)] {
+            monitor.record(
+                &take(&book, id, Side::Buy, 5),
+                None,
+                Duration::from_micros(lifetime_us),
+            );
+        }
+        // An empty book has no mid to slip against
+        let empty = OrderBook::<OrderTags>::new("ETH");
+        empty
+            .add_limit_order(
+                OrderId::from_u64(1),
+                50,
+                5,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        let mut event = take(&empty, 2, Side::Buy, 5);
+        event.symbol = "ETH".to_string();
+        monitor.record(&event, None, Duration::ZERO);
+
+        let (publisher, mut outbound) = Publisher::channel(16);
+        monitor.on_tick(&publisher, 999);
+        assert!(outbound.try_recv().is_err());
+        monitor.on_tick(&publisher, 1_000);
+        let btc: serde_json::Value =
+            serde_json::from_str(&outbound.try_recv().unwrap().payload).unwrap();
+        assert_eq!(btc["instrument_id"], "BTC");
+        assert_eq!(btc["lifetime_us"]["count"], 2);
+        assert_eq!(btc["lifetime_us"]["mean"], 20.0);
+        assert_eq!(btc["lifetime_us"]["p50"], 10.0);
+        assert_eq!(btc["lifetime_us"]["max"], 30.0);
+        let eth: serde_json::Value =
+            serde_json::from_str(&outbound.try_recv().unwrap().payload).unwrap();
+        assert!(eth.get("slippage_bps").is_none());
+
+        assert!(monitor.report(2_000).is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+// src/expiry.rs
+use crate::config::instruments::InstrumentEventsConfig;
+use crate::helpers::types::InstrumentExpiry;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::scale::{ScaledDepth, ScaledValue};
+use crate::orderbook::trade::TradeEvent;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use pricelevel::{OrderId, Side};
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Net position of a participant in a contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpenInterest {
+    pub participant_id: String,
+    /// Positive for long, negative for short (in units)
+    pub net_quantity: i64,
+}
+
+/// Final state of a contract, published once at expiry
+#[derive(Debug, Serialize)]
+pub struct SettlementReport {
+    pub instrument_id: String,
+    pub expired_at: u64,
+    /// Last trade price, or the mid price if the contract never traded
+    pub settlement_price: Option<ScaledValue>,
+    /// Book as it stood when trading was halted, before orders were cancelled
+    pub snapshot: ScaledDepth,
+    pub cancelled_orders: Vec<OrderId>,
+    pub open_interest: Vec<OpenInterest>,
+    /// Contract the open interest attribution was moved to
+    pub rolled_to: Option<String>,
+}
+
+/// A halted contract being settled, with its book as it stood before its
+/// orders are cancelled
+pub struct Settlement {
+    instrument_id: String,
+    expiry: InstrumentExpiry,
+    expired_at: u64,
+    snapshot: ScaledDepth,
+    settlement_price: Option<u64>,
+    /// Orders resting on the book, to be cancelled
+    pub resting_orders: Vec<OrderId>,
+}
+
+impl Settlement {
+    pub fn instrument_id(&self) -> &str {
+        &self.instrument_id
+    }
+}
+
+/// Tracks contract expiries and open interest, settling books as they expire
+///
+/// The engine halts an expired book and cancels its orders through logged
+/// commands, between [`ExpiryManager::begin_settlement`] and
+/// [`ExpiryManager::finish_settlement`].
+pub struct ExpiryManager {
+    config: InstrumentEventsConfig,
+    expiries: HashMap<String, InstrumentExpiry>,
+    /// Net quantity per participant, keyed by instrument
+    open_interest: HashMap<String, HashMap<String, i64>>,
+}
+
+impl ExpiryManager {
+    pub fn new(config: InstrumentEventsConfig) -> Self {
+        Self {
+            config,
+            expiries: HashMap::new(),
+            open_interest: HashMap::new(),
+        }
+    }
+
+    pub fn schedule(&mut self, instrument_id: &str, expiry: InstrumentExpiry) {
+        info!(
+            "Scheduled expiry of {} at {}",
+            instrument_id, expiry.expires_at
+        );
+        self.expiries.insert(instrument_id.to_string(), expiry);
+    }
+
+    /// Whether the instrument has an expiry still to run
+    pub fn is_scheduled(&self, instrument_id: &str) -> bool {
+        self.expiries.contains_key(instrument_id)
+    }
+
+    pub fn forget(&mut self, instrument_id: &str) {
+        self.expiries.remove(instrument_id);
+        self.open_interest.remove(instrument_id);
+    }
+
+    /// Attributes a fill to the buyer and seller, when they are known
+    pub fn record_fill(
+        &mut self,
+        instrument_id: &str,
+        buyer_id: Option<&str>,
+        seller_id: Option<&str>,
+        quantity: u64,
+    ) {
+        let positions = self
+            .open_interest
+            .entry(instrument_id.to_string())
+            .or_default();
+        let quantity = quantity as i64;
+        if let Some(buyer_id) = buyer_id {
+            *positions.entry(buyer_id.to_string()).or_default() += quantity;
+        }
+        if let Some(seller_id) = seller_id {
+            *positions.entry(seller_id.to_string()).or_default() -= quantity;
+        }
+    }
+
+    /// Attributes the fills of a matched trade, resolving participants by order id
+    pub fn record_trade_event<F>(&mut self, event: &TradeEvent, participant: F)
+    where
+        F: Fn(&str, OrderId) -> Option<String>,
+    {
+        for transaction in event.trade_result.match_result.transactions.as_vec() {
+            let taker = participant(&event.symbol, transaction.taker_order_id);
+            let maker = participant(&event.symbol, transaction.maker_order_id);
+            let (buyer_id, seller_id) = match transaction.taker_side {
+                Side::Buy => (taker, maker),
+                Side::Sell => (maker, taker),
+            };
+            self.record_fill(
+                &event.symbol,
+                buyer_id.as_deref(),
+                seller_id.as_deref(),
+                transaction.quantity,
+            );
+        }
+    }
+
+    /// Takes the contracts whose expiry has passed off the schedule
+    pub fn due(&mut self, now: u64) -> Vec<(String, InstrumentExpiry)> {
+        let expired: Vec<String> = self
+            .expiries
+            .iter()
+            .filter(|(_, expiry)| expiry.expires_at <= now)
+            .map(|(instrument_id, _)| instrument_id.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|instrument_id| {
+                let expiry = self.expiries.remove(&instrument_id)?;
+                Some((instrument_id, expiry))
+            })
+            .collect()
+    }
+
+    /// Records the book of an expired contract, once halted and before its
+    /// orders are cancelled
+    pub fn begin_settlement(
+        &self,
+        manager: &BookManagerStd<OrderTags>,
+        instrument_id: String,
+        expiry: InstrumentExpiry,
+        now: u64,
+    ) -> Option<Settlement> {
+        let Some(book) = manager.get_book(&instrument_id) else {
+            warn!("No book found for expired contract {}", instrument_id);
+            return None;
+        };
+        Some(Settlement {
+            snapshot: book.scaled_depth(
+                self.config.settlement_snapshot_depth,
+                self.config.number_format,
+            ),
+            settlement_price: book
+                .last_trade_price()
+                .or_else(|| book.mid_price().map(|mid| mid.round() as u64)),
+            resting_orders: book
+                .get_all_orders()
+                .iter()
+                .map(|order| order.id())
+                .collect(),
+            instrument_id,
+            expiry,
+            expired_at: now,
+        })
+    }
+
+    /// Rolls the open interest of a settled contract and publishes its report
+    pub fn finish_settlement(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        publisher: &Publisher,
+        settlement: Settlement,
+        cancelled_orders: Vec<OrderId>,
+    ) {
+        let Settlement {
+            instrument_id,
+            expiry,
+            expired_at,
+            snapshot,
+            settlement_price,
+            ..
+        } = settlement;
+        let instrument_id = instrument_id.as_str();
+        let positions = self.open_interest.remove(instrument_id).unwrap_or_default();
+        let mut open_interest: Vec<OpenInterest> = positions
+            .iter()
+            .filter(|(_, net_quantity)| **net_quantity != 0)
+            .map(|(participant_id, net_quantity)| OpenInterest {
+                participant_id: participant_id.clone(),
+                net_quantity: *net_quantity,
+            })
+            .collect();
+        open_interest.sort_by(|a, b| a.participant_id.cmp(&b.participant_id));
+
+        let rolled_to = match expiry.roll_to {
+            Some(next) if manager.has_book(&next) => {
+                let next_positions = self.open_interest.entry(next.clone()).or_default();
+                for interest in &open_interest {
+                    *next_positions
+                        .entry(interest.participant_id.clone())
+                        .or_default() += interest.net_quantity;
+                }
+                Some(next)
+            }
+            Some(next) => {
+                warn!(
+                    "Cannot roll {} into unknown contract {}",
+                    instrument_id, next
+                );
+                None
+            }
+            None => None,
+        };
+
+        info!(
+            "Contract {} expired: settled at {:?}, cancelled {} orders, rolled to {:?}",
+            instrument_id,
+            settlement_price,
+            cancelled_orders.len(),
+            rolled_to
+        );
+        let scale = manager.get_book(instrument_id).map(|book| book.scale());
+        let report = SettlementReport {
+            instrument_id: instrument_id.to_string(),
+            expired_at,
+            settlement_price: settlement_price
+                .zip(scale)
+                .map(|(price, scale)| scale.price(price, self.config.number_format)),
+            snapshot,
+            cancelled_orders,
+            open_interest,
+            rolled_to,
+        };
+        publisher.publish(&self.config.settlement_topic, instrument_id, &report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::TimeInForce;
+
+    #[test]
+    fn test_expiry_settles_and_rolls_open_interest() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("FUT-MAR");
+        manager.add_book("FUT-JUN");
+        let book = manager.get_book("FUT-MAR").unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            104,
+            10,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        let (publisher, mut rx) = Publisher::channel(8);
+
+        let mut expiries = ExpiryManager::new(InstrumentEventsConfig::default());
+        expiries.schedule(
+            "FUT-MAR",
+            InstrumentExpiry {
+                expires_at: 1_000,
+                roll_to: Some("FUT-JUN".to_string()),
+            },
+        );
+        expiries.record_fill("FUT-MAR", Some("alice"), Some("bob"), 5);
+
+        assert!(expiries.due(999).is_empty());
+        let (instrument_id, expiry) = expiries.due(1_000).remove(0);
+        assert!(expiries.due(1_000).is_empty());
+
+        // The engine halts the book and cancels its orders in between
+        let book = manager.get_book("FUT-MAR").unwrap();
+        book.halt();
+        let settlement = expiries
+            .begin_settlement(&manager, instrument_id, expiry, 1_000)
+            .unwrap();
+        assert_eq!(settlement.resting_orders.len(), 2);
+        for order_id in &settlement.resting_orders {
+            book.cancel_order(*order_id).unwrap();
+        }
+        let cancelled = settlement.resting_orders.clone();
+        expiries.finish_settlement(&manager, &publisher, settlement, cancelled);
+
+        let message = rx.try_recv().unwrap();
+        assert_eq!(message.topic, "instrument.settlement");
+        let report: serde_json::Value = serde_json::from_str(&message.payload).unwrap();
+        assert_eq!(report["settlement_price"], 102);
+        assert_eq!(report["cancelled_orders"].as_array().unwrap().len(), 2);
+        assert_eq!(report["rolled_to"], "FUT-JUN");
+        assert_eq!(report["open_interest"].as_array().unwrap().len(), 2);
+        assert_eq!(expiries.open_interest["FUT-JUN"]["alice"], 5);
+        assert_eq!(expiries.open_interest["FUT-JUN"]["bob"], -5);
+    }
+}
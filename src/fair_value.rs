@@ -0,0 +1,131 @@
+// src/fair_value.rs
+use crate::alerts::{Alert, AlertKind, emit_alert};
+use crate::config::fair_value::FairValueConfig;
+use crate::helpers::TheoreticalPricePayload;
+use crate::orderbook::OrderBook;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::info;
+
+/// Deviation of a book's mid from its theoretical price
+#[derive(Debug, Clone, Serialize)]
+pub struct FairValueDeviation {
+    pub instrument_id: String,
+    pub theoretical_price: f64,
+    pub mid_price: f64,
+    pub deviation_bps: f64,
+    pub threshold_bps: f64,
+    pub timestamp: u64,
+}
+
+struct FairValue {
+    price: f64,
+    threshold_bps: f64,
+    alerted: bool,
+}
+
+/// Tracks upstream theoretical prices and alerts when book mids drift away from them.
+///
+/// An alert fires once when the deviation crosses the threshold and re-arms after
+/// the mid returns within it.
+pub struct FairValueMonitor {
+    config: FairValueConfig,
+    fair_values: HashMap<String, FairValue>,
+}
+
+impl FairValueMonitor {
+    pub fn new(config: FairValueConfig) -> Self {
+        Self {
+            config,
+            fair_values: HashMap::new(),
+        }
+    }
+
+    /// Stores a new theoretical price and publishes the resulting deviation metric
+    pub fn update(
+        &mut self,
+        book: &OrderBook<OrderTags>,
+        theo: TheoreticalPricePayload,
+        publisher: &Publisher,
+    ) {
+        if !theo.price.is_finite() || theo.price <= 0.0 {
+            info!(
+                "Ignoring invalid theoretical price {} for {}",
+                theo.price, theo.instrument_id
+            );
+            return;
+        }
+        let threshold_bps = theo
+            .alert_threshold_bps
+            .unwrap_or(self.config.alert_threshold_bps);
+        let fair_value = self
+            .fair_values
+            .entry(theo.instrument_id)
+            .or_insert(FairValue {
+                price: theo.price,
+                threshold_bps,
+                alerted: false,
+            });
+        fair_value.price = theo.price;
+        fair_value.threshold_bps = threshold_bps;
+
+        if let Some(deviation) = self.evaluate(book, publisher) {
+            publisher.publish(&self.config.metrics_topic, book.symbol(), &deviation);
+        }
+    }
+
+    /// Re-evaluates the deviation after the book has changed
+    pub fn check(&mut self, book: &OrderBook<OrderTags>, publisher: &Publisher) {
+        let _ = self.evaluate(book, publisher);
+    }
+
+    /// Latest theoretical price of an instrument
+    pub fn price(&self, symbol: &str) -> Option<f64> {
+        self.fair_values
+            .get(symbol)
+            .map(|fair_value| fair_value.price)
+    }
+
+    /// Forgets the theoretical price of a removed book
+    pub fn forget(&mut self, symbol: &str) {
+        self.fair_values.remove(symbol);
+    }
+
+    fn evaluate(
+        &mut self,
+        book: &OrderBook<OrderTags>,
+        publisher: &Publisher,
+    ) -> Option<FairValueDeviation> {
+        let symbol = book.symbol();
+        let fair_value = self.fair_values.get_mut(symbol)?;
+        let mid_price = book.mid_price()?;
+        let deviation = FairValueDeviation {
+            instrument_id: symbol.to_string(),
+            theoretical_price: fair_value.price,
+            mid_price,
+            deviation_bps: (mid_price - fair_value.price) / fair_value.price * 10_000.0,
+            threshold_bps: fair_value.threshold_bps,
+            timestamp: current_time_millis(),
+        };
+
+        let breached = deviation.deviation_bps.abs() > fair_value.threshold_bps;
+        if breached && !fair_value.alerted {
+            fair_value.alerted = true;
+            let message = format!(
+                "Mid {:.4} deviates {:.2} bps from theoretical {:.4} (threshold {:.2} bps)",
+                mid_price, deviation.deviation_bps, fair_value.price, fair_value.threshold_bps
+            );
+            emit_alert(
+                publisher,
+                Alert::new(AlertKind::FairValueDeviation, symbol, message).with_details(&deviation),
+            );
+        } else if !breached && fair_value.alerted {
+            fair_value.alerted = false;
+            info!("Mid of {} is back within fair value threshold", symbol);
+        }
+        Some(deviation)
+    }
+}
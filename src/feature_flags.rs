@@ -0,0 +1,144 @@
+// src/feature_flags.rs
+use crate::config::features::{Feature, FeatureFlagConfig};
+use crate::orderbook::OrderBook;
+use crate::orderbook::features::BookFeatures;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::tags::OrderTags;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tracing::info;
+
+/// Features currently in effect, as reported by the admin topic
+#[derive(Debug, Serialize)]
+pub struct FeatureSummary {
+    pub enabled: Vec<Feature>,
+    pub instruments: BTreeMap<String, BTreeMap<Feature, bool>>,
+}
+
+/// Per-instrument feature flags, seeded from config and changed at runtime
+/// through the admin topic
+///
+/// An instrument gets the default features, with its own overrides on top. A
+/// flag change takes effect on the next command, and removing an instrument's
+/// overrides rolls it back to the defaults.
+pub struct FeatureFlags {
+    defaults: BookFeatures,
+    overrides: HashMap<String, HashMap<Feature, bool>>,
+}
+
+impl FeatureFlags {
+    pub fn new(config: &FeatureFlagConfig) -> Self {
+        Self {
+            defaults: config
+                .enabled
+                .iter()
+                .fold(BookFeatures::empty(), |flags, feature| {
+                    flags | feature.flag()
+                }),
+            overrides: config.instruments.clone(),
+        }
+    }
+
+    pub fn for_instrument(&self, instrument_id: &str) -> BookFeatures {
+        let mut features = self.defaults;
+        for (feature, enabled) in self.overrides.get(instrument_id).into_iter().flatten() {
+            features.set(feature.flag(), *enabled);
+        }
+        features
+    }
+
+    /// Switches a feature for one instrument, or by default for all of them
+    pub fn set(&mut self, instrument_id: Option<&str>, feature: Feature, enabled: bool) {
+        match instrument_id {
+            Some(instrument_id) => {
+                self.overrides
+                    .entry(instrument_id.to_string())
+                    .or_default()
+                    .insert(feature, enabled);
+            }
+            None => self.defaults.set(feature.flag(), enabled),
+        }
+    }
+
+    /// Drops an instrument's overrides; returns `false` if it had none
+    pub fn reset(&mut self, instrument_id: &str) -> bool {
+        self.overrides.remove(instrument_id).is_some()
+    }
+
+    /// Brings a book's features in line with its flags, returning `true` if they changed
+    pub fn apply(&self, book: &mut OrderBook<OrderTags>) -> bool {
+        let features = self.for_instrument(book.symbol());
+        if book.features() == features {
+            return false;
+        }
+        info!(
+            "Features of {} changed from {:?} to {:?}",
+            book.symbol(),
+            book.features(),
+            features
+        );
+        book.set_features(features);
+        true
+    }
+
+    pub fn apply_all(&self, manager: &mut BookManagerStd<OrderTags>) {
+        for symbol in manager.symbols() {
+            if let Some(book) = manager.get_book_mut(&symbol) {
+                self.apply(book);
+            }
+        }
+    }
+
+    pub fn summary(&self) -> FeatureSummary {
+        FeatureSummary {
+            enabled: Feature::ALL
+                .into_iter()
+                .filter(|feature| self.defaults.contains(feature.flag()))
+                .collect(),
+            instruments: self
+                .overrides
+                .iter()
+                .map(|(instrument_id, overrides)| {
+                    (
+                        instrument_id.clone(),
+                        overrides.clone().into_iter().collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_apply_on_top_of_defaults() {
+        let mut flags = FeatureFlags::new(&FeatureFlagConfig {
+            enabled: vec![Feature::MarketProtection],
+            instruments: HashMap::from([(
+                "ETH".to_string(),
+                HashMap::from([(Feature::MinFillNotional, true)]),
+            )]),
+        });
+        assert_eq!(flags.for_instrument("BTC"), BookFeatures::MARKET_PROTECTION);
+        assert_eq!(flags.for_instrument("ETH"), BookFeatures::all());
+
+        flags.set(Some("BTC"), Feature::MarketProtection, false);
+        flags.set(None, Feature::MinFillNotional, true);
+        assert_eq!(flags.for_instrument("BTC"), BookFeatures::MIN_FILL_NOTIONAL);
+        assert_eq!(flags.for_instrument("SOL"), BookFeatures::all());
+
+        let mut book = OrderBook::new("BTC");
+        assert!(flags.apply(&mut book));
+        assert!(!flags.apply(&mut book));
+        assert_eq!(book.features(), BookFeatures::MIN_FILL_NOTIONAL);
+
+        // Rolling back to the defaults
+        assert!(flags.reset("BTC"));
+        assert!(!flags.reset("BTC"));
+        assert!(flags.apply(&mut book));
+        assert_eq!(book.features(), BookFeatures::all());
+    }
+}
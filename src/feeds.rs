@@ -0,0 +1,347 @@
+// src/feeds.rs
+use crate::config::feeds::{FeedConfig, FeedProfile};
+use crate::conflation::Conflator;
+use crate::delay_buffer::DelayBuffer;
+use crate::orderbook::OrderBook;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::scale::ScaledValue;
+use crate::orderbook::trade::TradeEvent;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use pricelevel::{OrderId, Side};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// A single fill as published on a feed's trade topic
+#[derive(Debug, Serialize)]
+pub struct TradePrint<'a> {
+    pub instrument_id: &'a str,
+    pub trade_id: Uuid,
+    pub price: ScaledValue,
+    pub quantity: ScaledValue,
+    pub aggressor_side: Side,
+    pub timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taker_order_id: Option<OrderId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maker_order_id: Option<OrderId>,
+}
+
+struct Feed {
+    profile: FeedProfile,
+    last_depth_at: HashMap<String, u64>,
+    /// Instruments whose depth changed while throttled
+    stale_depth: HashSet<String>,
+    /// Messages held back by the feed's delay
+    delayed: DelayBuffer,
+    /// Per-instrument depth intervals, when the profile conflates adaptively
+    conflator: Option<Conflator>,
+}
+
+impl Feed {
+    /// Sends a message now or after the feed's delay, returning its size in bytes
+    fn send<P: Serialize>(
+        &mut self,
+        publisher: &Publisher,
+        topic: &str,
+        key: &str,
+        payload: &P,
+        now: u64,
+    ) -> usize {
+        let payload = match serde_json::to_string(payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!(
+                    "Failed to serialize {} feed message for {}: {}",
+                    self.profile.name, topic, e
+                );
+                return 0;
+            }
+        };
+        let bytes = payload.len();
+        if self.profile.delay_ms == 0 {
+            publisher.publish_serialized(topic, key, payload);
+        } else {
+            self.delayed
+                .push(now + self.profile.delay_ms, topic, key, payload);
+        }
+        bytes
+    }
+
+    fn publish_depth(&mut self, book: &OrderBook<OrderTags>, publisher: &Publisher, now: u64) {
+        let depth = book.scaled_depth(self.profile.depth_levels, self.profile.number_format);
+        let topic = self.profile.depth_topic.clone();
+        let bytes = self.send(publisher, &topic, book.symbol(), &depth, now);
+        if let Some(conflator) = &mut self.conflator {
+            conflator.record_depth(book.symbol(), bytes);
+        }
+        self.last_depth_at.insert(book.symbol().to_string(), now);
+        self.stale_depth.remove(book.symbol());
+    }
+
+    fn depth_due(&self, instrument_id: &str, now: u64) -> bool {
+        let interval_ms = match &self.conflator {
+            Some(conflator) => conflator.interval_ms(instrument_id),
+            None => self.profile.min_interval_ms,
+        };
+        self.last_depth_at
+            .get(instrument_id)
+            .is_none_or(|last| now.saturating_sub(*last) >= interval_ms)
+    }
+}
+
+/// Publishes trades and depth to each configured feed profile
+///
+/// Each profile has its own topics, depth, throttling and delay, so a single engine
+/// can serve a full-resolution internal feed alongside a reduced public one.
+pub struct FeedPublisher {
+    feeds: Vec<Feed>,
+}
+
+impl FeedPublisher {
+    pub fn new(config: FeedConfig) -> Self {
+        for profile in &config.profiles {
+            info!(
+                "Feed {}: {} levels every {}ms, delayed {}ms, on {} / {}",
+                profile.name,
+                profile.depth_levels,
+                profile.min_interval_ms,
+                profile.delay_ms,
+                profile.depth_topic,
+                profile.trades_topic
+            );
+        }
+        let feeds = config
+            .profiles
+            .into_iter()
+            .map(|profile| Feed {
+                conflator: profile
+                    .adaptive_conflation
+                    .clone()
+                    .map(|config| Conflator::new(config, profile.min_interval_ms)),
+                delayed: DelayBuffer::new(
+                    &profile.name,
+                    profile.max_buffered_bytes,
+                    profile.journal_dir.as_deref(),
+                ),
+                profile,
+                last_depth_at: HashMap::new(),
+                stale_depth: HashSet::new(),
+            })
+            .collect();
+        Self { feeds }
+    }
+
+    /// Publishes the fills of a matched trade to every feed carrying the instrument
+    pub fn on_trade_event(
+        &mut self,
+        event: &TradeEvent,
+        book: &OrderBook<OrderTags>,
+        publisher: &Publisher,
+        now: u64,
+    ) {
+        let scale = book.scale();
+        for feed in &mut self.feeds {
+            if !feed.profile.carries(&event.symbol) {
+                continue;
+            }
+            let format = feed.profile.number_format;
+            let include_order_ids = feed.profile.include_order_ids;
+            let topic = feed.profile.trades_topic.clone();
+            for transaction in event.trade_result.match_result.transactions.as_vec() {
+                let print = TradePrint {
+                    instrument_id: &event.symbol,
+                    trade_id: transaction.transaction_id,
+                    price: scale.price(transaction.price, format),
+                    quantity: scale.quantity(transaction.quantity, format),
+                    aggressor_side: transaction.taker_side,
+                    timestamp: transaction.timestamp,
+                    taker_order_id: include_order_ids.then_some(transaction.taker_order_id),
+                    maker_order_id: include_order_ids.then_some(transaction.maker_order_id),
+                };
+                let bytes = feed.send(publisher, &topic, &event.symbol, &print, now);
+                if let Some(conflator) = &mut feed.conflator {
+                    conflator.record_trade(bytes);
+                }
+            }
+        }
+    }
+
+    /// Publishes depth after the book may have changed, subject to each feed's throttle
+    pub fn on_book_change(&mut self, book: &OrderBook<OrderTags>, publisher: &Publisher, now: u64) {
+        for feed in &mut self.feeds {
+            if !feed.profile.carries(book.symbol()) {
+                continue;
+            }
+            if let Some(conflator) = &mut feed.conflator {
+                conflator.on_book_change(book.symbol(), book.book_context());
+            }
+            if feed.depth_due(book.symbol(), now) {
+                feed.publish_depth(book, publisher, now);
+            } else {
+                feed.stale_depth.insert(book.symbol().to_string());
+            }
+        }
+    }
+
+    /// Publishes throttled depth whose interval has passed and releases delayed messages
+    pub fn on_tick(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        publisher: &Publisher,
+        now: u64,
+    ) {
+        for feed in &mut self.feeds {
+            if let Some(conflator) = &mut feed.conflator {
+                conflator.on_tick(now);
+            }
+            let due: Vec<String> = feed
+                .stale_depth
+                .iter()
+                .filter(|instrument_id| feed.depth_due(instrument_id, now))
+                .cloned()
+                .collect();
+            for instrument_id in due {
+                match manager.get_book(&instrument_id) {
+                    Some(book) => feed.publish_depth(book, publisher, now),
+                    None => {
+                        feed.stale_depth.remove(&instrument_id);
+                    }
+                }
+            }
+            feed.delayed.release(publisher, now);
+        }
+    }
+
+    /// Messages the delayed feeds dropped since the last call
+    pub fn take_delayed_dropped(&mut self) -> u64 {
+        self.feeds
+            .iter_mut()
+            .map(|feed| feed.delayed.take_dropped())
+            .sum()
+    }
+
+    pub fn forget(&mut self, instrument_id: &str) {
+        for feed in &mut self.feeds {
+            feed.last_depth_at.remove(instrument_id);
+            feed.stale_depth.remove(instrument_id);
+            if let Some(conflator) = &mut feed.conflator {
+                conflator.forget(instrument_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::scale::NumberFormat;
+    use crate::publisher::OutboundMessage;
+    use pricelevel::TimeInForce;
+    use tokio::sync::mpsc::Receiver;
+
+    fn profile(name: &str, min_interval_ms: u64, delay_ms: u64) -> FeedProfile {
+        FeedProfile {
+            name: name.to_string(),
+            depth_topic: format!("{name}.depth"),
+            trades_topic: format!("{name}.trades"),
+            depth_levels: 1,
+            min_interval_ms,
+            delay_ms,
+            include_order_ids: name == "internal",
+            instruments: Vec::new(),
+            number_format: NumberFormat::Raw,
+            max_buffered_bytes: 1_024 * 1_024,
+            journal_dir: None,
+            adaptive_conflation: None,
+        }
+    }
+
+    fn drain(rx: &mut Receiver<OutboundMessage>) -> Vec<OutboundMessage> {
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    fn book_with_levels() -> BookManagerStd<OrderTags> {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let book = manager.get_book("BTC").unwrap();
+        for (id, price) in [(1, 100), (2, 99)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                5,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        manager
+    }
+
+    #[test]
+    fn test_public_feed_is_throttled_delayed_and_truncated() {
+        let manager = book_with_levels();
+        let book = manager.get_book("BTC").unwrap();
+        let (publisher, mut rx) = Publisher::channel(16);
+        let mut feeds = FeedPublisher::new(FeedConfig {
+            profiles: vec![profile("internal", 0, 0), profile("public", 1_000, 200)],
+            flush_interval_ms: 50,
+        });
+
+        feeds.on_book_change(book, &publisher, 0);
+        feeds.on_book_change(book, &publisher, 100);
+        let messages = drain(&mut rx);
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|m| m.topic == "internal.depth"));
+        let depth: serde_json::Value = serde_json::from_str(&messages[0].payload).unwrap();
+        assert_eq!(depth["bids"].as_array().unwrap().len(), 1);
+
+        // The first public update is released once its delay has passed
+        feeds.on_tick(&manager, &publisher, 199);
+        assert!(drain(&mut rx).is_empty());
+        feeds.on_tick(&manager, &publisher, 200);
+        let messages = drain(&mut rx);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].topic, "public.depth");
+
+        // The change throttled at 100 is sent when the interval ends, then delayed
+        feeds.on_tick(&manager, &publisher, 1_000);
+        assert!(drain(&mut rx).is_empty());
+        feeds.on_tick(&manager, &publisher, 1_200);
+        assert_eq!(drain(&mut rx).len(), 1);
+    }
+
+    #[test]
+    fn test_public_trades_omit_order_ids() {
+        let manager = book_with_levels();
+        let book = manager.get_book("BTC").unwrap();
+        let match_result = book
+            .match_order(OrderId::from_u64(3), Side::Sell, 2, None)
+            .unwrap();
+        let event = TradeEvent {
+            symbol: "BTC".to_string(),
+            trade_result: crate::orderbook::trade::TradeResult::new(
+                "BTC".to_string(),
+                match_result,
+            ),
+            timestamp: 0,
+        };
+        let (publisher, mut rx) = Publisher::channel(16);
+        let mut feeds = FeedPublisher::new(FeedConfig {
+            profiles: vec![profile("internal", 0, 0), profile("public", 0, 0)],
+            flush_interval_ms: 50,
+        });
+
+        feeds.on_trade_event(&event, book, &publisher, 0);
+        let messages = drain(&mut rx);
+        assert_eq!(messages.len(), 2);
+        let internal: serde_json::Value = serde_json::from_str(&messages[0].payload).unwrap();
+        let public: serde_json::Value = serde_json::from_str(&messages[1].payload).unwrap();
+        assert!(internal.get("maker_order_id").is_some());
+        assert!(public.get("maker_order_id").is_none());
+        assert_eq!(public["price"], 100);
+    }
+}
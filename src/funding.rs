@@ -0,0 +1,281 @@
+// src/funding.rs
+use crate::config::funding::FundingConfig;
+use crate::helpers::EngineCommand;
+use crate::publisher::Publisher;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// Marks an instrument as a perpetual contract, funded against an index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Perpetual {
+    /// Index defined on `index.define` the mark price is compared with
+    pub index_id: String,
+}
+
+/// A sample of the premium of a perpetual, published on `funding.premium_topic`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PremiumSample {
+    pub instrument_id: String,
+    pub index_id: String,
+    pub mark_price: f64,
+    pub index_price: f64,
+    /// `(mark - index) / index`
+    pub premium: f64,
+    /// Average premium since the last funding
+    pub premium_index: f64,
+    /// Funding rate if the funding was now
+    pub estimated_funding_rate: f64,
+    pub next_funding_at: u64,
+    pub timestamp: u64,
+}
+
+/// Funding of a perpetual, published on `funding.funding_topic`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FundingRate {
+    pub instrument_id: String,
+    pub index_id: String,
+    /// Share of the position value longs pay shorts, or receive when negative
+    pub funding_rate: f64,
+    pub premium_index: f64,
+    pub interest_rate: f64,
+    /// Premium samples averaged into the premium index
+    pub samples: usize,
+    pub funding_at: u64,
+}
+
+struct PerpetualState {
+    perpetual: Perpetual,
+    premiums: Vec<f64>,
+}
+
+/// Computes the premium and funding rates of perpetual instruments
+///
+/// The premium of each perpetual is sampled every `premium_interval_ms` from
+/// its mark, the theoretical price posted on `price.theoretical`, and the value
+/// of its index. At each funding the samples are averaged into the premium
+/// index `P` and the funding rate is `P + clamp(interest - P)`, capped at
+/// `max_funding_rate`. No sample is taken while either price is unknown, and
+/// no funding is published for a perpetual without samples.
+pub struct FundingCalculator {
+    config: FundingConfig,
+    perpetuals: BTreeMap<String, PerpetualState>,
+    next_sample_at: u64,
+    next_funding_at: u64,
+}
+
+impl FundingCalculator {
+    pub fn new(config: FundingConfig, now: u64) -> Self {
+        let next_funding_at = next_funding(now, config.funding_interval_ms);
+        Self {
+            next_sample_at: now,
+            next_funding_at,
+            config,
+            perpetuals: BTreeMap::new(),
+        }
+    }
+
+    /// Tracks the instruments created as perpetuals, and forgets deleted ones
+    pub fn record(&mut self, cmd: &EngineCommand) {
+        match cmd {
+            EngineCommand::InstrumentCreate(create) => match &create.perpetual {
+                Some(perpetual) => {
+                    info!(
+                        "Funding {} against index {}",
+                        create.instrument_id, perpetual.index_id
+                    );
+                    self.perpetuals.insert(
+                        create.instrument_id.clone(),
+                        PerpetualState {
+                            perpetual: perpetual.clone(),
+                            premiums: Vec::new(),
+                        },
+                    );
+                }
+                None => {
+                    self.perpetuals.remove(&create.instrument_id);
+                }
+            },
+            EngineCommand::InstrumentDelete(delete) => {
+                self.perpetuals.remove(&delete.instrument_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Samples premiums and settles fundings that are due; `mark` gives the
+    /// mark price of an instrument and `index` the value of an index
+    pub fn on_tick(
+        &mut self,
+        mark: impl Fn(&str) -> Option<f64>,
+        index: impl Fn(&str) -> Option<f64>,
+        publisher: &Publisher,
+        now: u64,
+    ) {
+        if !self.config.enabled {
+            return;
+        }
+        if now >= self.next_funding_at {
+            self.settle(publisher, now);
+        }
+        if now < self.next_sample_at {
+            return;
+        }
+        self.next_sample_at = now + self.config.premium_interval_ms.max(1);
+        for (instrument_id, state) in &mut self.perpetuals {
+            let (Some(mark_price), Some(index_price)) =
+                (mark(instrument_id), index(&state.perpetual.index_id))
+            else {
+                continue;
+            };
+            if index_price <= 0.0 {
+                continue;
+            }
+            let premium = (mark_price - index_price) / index_price;
+            state.premiums.push(premium);
+            let premium_index = average(&state.premiums);
+            let sample = PremiumSample {
+                instrument_id: instrument_id.clone(),
+                index_id: state.perpetual.index_id.clone(),
+                mark_price,
+                index_price,
+                premium,
+                premium_index,
+                estimated_funding_rate: funding_rate(&self.config, premium_index),
+                next_funding_at: self.next_funding_at,
+                timestamp: now,
+            };
+            publisher.publish(&self.config.premium_topic, instrument_id, &sample);
+        }
+    }
+
+    /// Publishes the funding rate of every sampled perpetual and starts the
+    /// next funding interval
+    fn settle(&mut self, publisher: &Publisher, now: u64) {
+        let funding_at = self.next_funding_at;
+        self.next_funding_at = next_funding(now, self.config.funding_interval_ms);
+        for (instrument_id, state) in &mut self.perpetuals {
+            let premiums = std::mem::take(&mut state.premiums);
+            if premiums.is_empty() {
+                continue;
+            }
+            let premium_index = average(&premiums);
+            let funding = FundingRate {
+                instrument_id: instrument_id.clone(),
+                index_id: state.perpetual.index_id.clone(),
+                funding_rate: funding_rate(&self.config, premium_index),
+                premium_index,
+                interest_rate: self.config.interest_rate,
+                samples: premiums.len(),
+                funding_at,
+            };
+            info!(
+                "Funding rate of {} is {:.6}",
+                instrument_id, funding.funding_rate
+            );
+            publisher.publish(&self.config.funding_topic, instrument_id, &funding);
+        }
+    }
+}
+
+/// Funding rate implied by a premium index
+fn funding_rate(config: &FundingConfig, premium_index: f64) -> f64 {
+    let interest =
+        (config.interest_rate - premium_index).clamp(-config.interest_clamp, config.interest_clamp);
+    (premium_index + interest).clamp(-config.max_funding_rate, config.max_funding_rate)
+}
+
+fn average(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+/// First funding time after `now`
+fn next_funding(now: u64, interval_ms: u64) -> u64 {
+    let interval_ms = interval_ms.max(1);
+    (now / interval_ms + 1) * interval_ms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::InstrumentCreatePayload;
+    use crate::publisher::OutboundMessage;
+
+    fn calculator() -> FundingCalculator {
+        let mut calculator = FundingCalculator::new(
+            FundingConfig {
+                enabled: true,
+                premium_interval_ms: 10,
+                funding_interval_ms: 100,
+                ..FundingConfig::default()
+            },
+            5,
+        );
+        let create: InstrumentCreatePayload = serde_json::from_value(serde_json::json!({
+            "instrument_id": "BTC-PERP",
+            "perpetual": { "index_id": "BTC-IDX" },
+        }))
+        .unwrap();
+        calculator.record(&EngineCommand::InstrumentCreate(create));
+        calculator
+    }
+
+    fn payload(message: OutboundMessage) -> serde_json::Value {
+        serde_json::from_str(&message.payload).unwrap()
+    }
+
+    #[test]
+    fn test_funding_rates_follow_the_premium_within_bounds() {
+        let config = FundingConfig::default();
+        let assert_rate = |premium_index: f64, expected: f64| {
+            let rate = funding_rate(&config, premium_index);
+            assert!(
+                (rate - expected).abs() < 1e-12,
+                "{rate} for {premium_index}"
+            );
+        };
+        // The interest rate pulls a small premium up to it
+        assert_rate(0.0003, 0.0001);
+        assert_rate(0.002, 0.0015);
+        assert_rate(0.02, 0.0075);
+        assert_rate(-0.02, -0.0075);
+        assert_eq!(next_funding(5, 100), 100);
+        assert_eq!(next_funding(100, 100), 200);
+    }
+
+    #[test]
+    fn test_premiums_are_sampled_and_averaged_into_the_funding() {
+        let (publisher, mut outbound) = Publisher::channel(16);
+        let mut calculator = calculator();
+        let index = |index_id: &str| (index_id == "BTC-IDX").then_some(100.0);
+
+        calculator.on_tick(|_| Some(101.0), index, &publisher, 10);
+        // Sampled at most once per premium interval
+        calculator.on_tick(|_| Some(101.0), index, &publisher, 15);
+        calculator.on_tick(|_| Some(103.0), index, &publisher, 20);
+        // No mark, no sample
+        calculator.on_tick(|_| None, index, &publisher, 30);
+        let samples: Vec<_> = std::iter::from_fn(|| outbound.try_recv().ok()).collect();
+        assert_eq!(samples.len(), 2);
+        assert!(
+            samples
+                .iter()
+                .all(|sample| sample.topic == "funding.premium")
+        );
+        let last = payload(samples[1].clone());
+        assert!((last["premium_index"].as_f64().unwrap() - 0.02).abs() < 1e-12);
+        assert_eq!(last["next_funding_at"], 100);
+
+        calculator.on_tick(|_| Some(100.0), index, &publisher, 100);
+        let funding = outbound.try_recv().unwrap();
+        assert_eq!(funding.topic, "funding.rate");
+        let funding = payload(funding);
+        assert_eq!(funding["funding_rate"], 0.0075);
+        assert_eq!(funding["samples"], 2);
+        assert_eq!(funding["funding_at"], 100);
+        // The next interval starts without samples
+        let sample = payload(outbound.try_recv().unwrap());
+        assert_eq!(sample["premium_index"], 0.0);
+        assert_eq!(sample["next_funding_at"], 200);
+    }
+}
@@ -0,0 +1,241 @@
+use super::AdminCommandPayload;
+use crate::archive::BookArchiver;
+use crate::clearing::ClearingLedger;
+use crate::config::risk::ParticipantLimits;
+use crate::feature_flags::FeatureFlags;
+use crate::orderbook::correlation::CorrelationTracker;
+use crate::orderbook::manager::BookManager;
+use crate::orderbook::manager::BookManagerStd;
+use crate::risk::ParticipantRisk;
+use crate::sinks::SinkPipeline;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use tracing::{info, warn};
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_admin_command(
+    manager: &mut BookManagerStd<OrderTags>,
+    correlations: &mut CorrelationTracker,
+    clearing: &mut ClearingLedger,
+    archiver: &mut BookArchiver,
+    features: &mut FeatureFlags,
+    sinks: &mut SinkPipeline,
+    risk: &mut ParticipantRisk,
+    cmd: AdminCommandPayload,
+) {
+    match cmd {
+        AdminCommandPayload::EnableFlightRecorder {
+            instrument_id,
+            capacity,
+        } => {
+            let Some(book) = manager.get_book_mut(&instrument_id) else {
+                warn!(
+                    "No book found for {}, cannot enable flight recorder",
+                    instrument_id
+                );
+                return;
+            };
+            book.enable_flight_recorder(capacity);
+            info!(
+                "Enabled flight recorder on {} with capacity {}",
+                instrument_id, capacity
+            );
+        }
+        AdminCommandPayload::DisableFlightRecorder { instrument_id } => {
+            let Some(book) = manager.get_book_mut(&instrument_id) else {
+                warn!(
+                    "No book found for {}, cannot disable flight recorder",
+                    instrument_id
+                );
+                return;
+            };
+            book.disable_flight_recorder();
+            info!("Disabled flight recorder on {}", instrument_id);
+        }
+        AdminCommandPayload::DumpFlightEvents { instrument_id } => {
+            let Some(book) = manager.get_book(&instrument_id) else {
+                warn!(
+                    "No book found for {}, cannot dump flight recorder",
+                    instrument_id
+                );
+                return;
+            };
+            let Some(recorder) = book.flight_recorder() else {
+                warn!("Flight recorder is not enabled on {}", instrument_id);
+                return;
+            };
+            match serde_json::to_string(&recorder.dump()) {
+                Ok(json) => info!("Flight recorder dump for {}: {}", instrument_id, json),
+                Err(e) => warn!(
+                    "Failed to serialize flight recorder for {}: {}",
+                    instrument_id, e
+                ),
+            }
+        }
+        AdminCommandPayload::GetTrades {
+            instrument_id,
+            since_sequence,
+        } => {
+            let Some(book) = manager.get_book(&instrument_id) else {
+                warn!("No book found for {}, cannot get its trades", instrument_id);
+                return;
+            };
+            let Some(tape) = book.trade_tape() else {
+                warn!("Trade tape is not enabled on {}", instrument_id);
+                return;
+            };
+            let trades = match since_sequence {
+                Some(sequence) => tape.since_sequence(sequence),
+                None => tape.last().into_iter().collect(),
+            };
+            match serde_json::to_string(&trades) {
+                Ok(json) => info!("Trades on {}: {}", instrument_id, json),
+                Err(e) => warn!("Failed to serialize trades of {}: {}", instrument_id, e),
+            }
+        }
+        AdminCommandPayload::AddCorrelationPair {
+            instrument_a,
+            instrument_b,
+        } => {
+            if correlations.add_pair(&instrument_a, &instrument_b) {
+                info!(
+                    "Tracking return correlation of {} and {}",
+                    instrument_a, instrument_b
+                );
+            } else {
+                warn!(
+                    "Correlation of {} and {} is already tracked",
+                    instrument_a, instrument_b
+                );
+            }
+        }
+        AdminCommandPayload::RemoveCorrelationPair {
+            instrument_a,
+            instrument_b,
+        } => {
+            if correlations.remove_pair(&instrument_a, &instrument_b) {
+                info!(
+                    "Stopped tracking return correlation of {} and {}",
+                    instrument_a, instrument_b
+                );
+            } else {
+                warn!(
+                    "Correlation of {} and {} is not tracked",
+                    instrument_a, instrument_b
+                );
+            }
+        }
+        AdminCommandPayload::GetCorrelations => {
+            match serde_json::to_string(&correlations.correlations()) {
+                Ok(json) => info!("Instrument correlations: {}", json),
+                Err(e) => warn!("Failed to serialize correlations: {}", e),
+            }
+        }
+        AdminCommandPayload::RunClearingExport => {
+            clearing.export(manager, current_time_millis());
+        }
+        AdminCommandPayload::BookAsOf {
+            instrument_id,
+            timestamp,
+        } => {
+            let id = instrument_id.clone();
+            let queued =
+                archiver.query_book_as_of(&instrument_id, timestamp, move |result| match result {
+                    Ok(Some(snapshot)) => match serde_json::to_string(&snapshot) {
+                        Ok(json) => info!("Archived book of {} as of {}: {}", id, timestamp, json),
+                        Err(e) => warn!("Failed to serialize archived book of {}: {}", id, e),
+                    },
+                    Ok(None) => warn!("No archived book of {} as of {}", id, timestamp),
+                    Err(e) => warn!("Failed to read archived book of {}: {}", id, e),
+                });
+            if !queued {
+                warn!(
+                    "Archive writer is behind, cannot query book of {}",
+                    instrument_id
+                );
+            }
+        }
+        AdminCommandPayload::GetArchiveStats => match serde_json::to_string(&archiver.stats()) {
+            Ok(json) => info!("Archive snapshot stats: {}", json),
+            Err(e) => warn!("Failed to serialize archive stats: {}", e),
+        },
+        AdminCommandPayload::GetGlobalStats => {
+            let stats = manager.global_stats();
+            match serde_json::to_string(&stats) {
+                Ok(json) => info!("Global book stats: {}", json),
+                Err(e) => warn!("Failed to serialize global book stats: {}", e),
+            }
+        }
+        AdminCommandPayload::SetFeature {
+            feature,
+            enabled,
+            instrument_id,
+        } => {
+            features.set(instrument_id.as_deref(), feature, enabled);
+            info!(
+                "{} {:?} on {}",
+                if enabled { "Enabled" } else { "Disabled" },
+                feature,
+                instrument_id
+                    .as_deref()
+                    .unwrap_or("all instruments by default")
+            );
+            features.apply_all(manager);
+        }
+        AdminCommandPayload::ResetFeatures { instrument_id } => {
+            if !features.reset(&instrument_id) {
+                warn!("No feature overrides on {}", instrument_id);
+                return;
+            }
+            info!("Reset features of {} to the defaults", instrument_id);
+            features.apply_all(manager);
+        }
+        AdminCommandPayload::GetFeatures => match serde_json::to_string(&features.summary()) {
+            Ok(json) => info!("Feature flags: {}", json),
+            Err(e) => warn!("Failed to serialize feature flags: {}", e),
+        },
+        AdminCommandPayload::SetSinkFilter { sink, filter } => {
+            if sinks.set_filter(&sink, filter) {
+                info!("Replaced the filter of sink {}", sink);
+            } else {
+                warn!("No sink named {}, cannot set its filter", sink);
+            }
+        }
+        AdminCommandPayload::GetSinkFilters => match serde_json::to_string(&sinks.filters()) {
+            Ok(json) => info!("Sink filters: {}", json),
+            Err(e) => warn!("Failed to serialize sink filters: {}", e),
+        },
+        AdminCommandPayload::SetParticipantLimits {
+            participant_id,
+            max_open_orders,
+            max_open_notional,
+        } => risk.set_limits(
+            participant_id,
+            ParticipantLimits {
+                max_open_orders,
+                max_open_notional,
+            },
+        ),
+        AdminCommandPayload::MovePeggedOrders {
+            instrument_id,
+            moves,
+        } => {
+            let Some(book) = manager.get_book(&instrument_id) else {
+                warn!(
+                    "No book found for {}, cannot move its pegged orders",
+                    instrument_id
+                );
+                return;
+            };
+            book.move_pegged_orders(moves);
+        }
+        AdminCommandPayload::PauseConsumption { .. }
+        | AdminCommandPayload::ResumeConsumption { .. } => {
+            warn!("Pausing and resuming topics is up to the consumer, not the engine");
+        }
+        #[cfg(feature = "chaos")]
+        AdminCommandPayload::InjectFaults(plan) => crate::chaos::inject(plan),
+        #[cfg(feature = "chaos")]
+        AdminCommandPayload::ClearFaults => crate::chaos::clear(),
+    }
+}
@@ -0,0 +1,41 @@
+use crate::config::analytics::PriceSource;
+use crate::orderbook::OrderBook;
+use crate::orderbook::correlation::CorrelationTracker;
+use crate::orderbook::manager::BookManager;
+use crate::orderbook::manager::BookManagerStd;
+use crate::tags::OrderTags;
+use std::collections::HashMap;
+
+/// Current price of a book according to `source`
+pub fn book_price(book: &OrderBook<OrderTags>, source: PriceSource) -> Option<f64> {
+    match source {
+        PriceSource::Mid => book.mark_price().map(|mark| mark.price),
+        PriceSource::LastTrade => book.last_trade_price().map(|price| price as f64),
+    }
+}
+
+/// Collects the current price of each listed instrument that has one
+pub fn collect_prices<'a>(
+    manager: &BookManagerStd<OrderTags>,
+    instruments: impl IntoIterator<Item = &'a str>,
+    source: PriceSource,
+) -> HashMap<String, f64> {
+    instruments
+        .into_iter()
+        .filter_map(|symbol| {
+            let price = book_price(manager.get_book(symbol)?, source)?;
+            Some((symbol.to_string(), price))
+        })
+        .collect()
+}
+
+/// Samples the current price of every instrument in a tracked pair
+pub fn sample_correlations(
+    manager: &BookManagerStd<OrderTags>,
+    tracker: &mut CorrelationTracker,
+    source: PriceSource,
+) {
+    let instruments = tracker.instruments();
+    let prices = collect_prices(manager, instruments.iter().map(String::as_str), source);
+    tracker.sample(&prices);
+}
@@ -0,0 +1,71 @@
+use super::BlockTradePayload;
+use crate::config::trades::TradeReportConfig;
+use crate::orderbook::block_trade::BlockTrade;
+use crate::orderbook::manager::BookManager;
+use crate::orderbook::manager::BookManagerStd;
+use crate::orderbook::scale::ScaledValue;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// A block trade as published on the trade topic
+#[derive(Debug, Serialize)]
+pub struct BlockTradeReport<'a> {
+    pub instrument_id: &'a str,
+    pub trade_id: &'a str,
+    pub buyer_id: &'a str,
+    pub seller_id: &'a str,
+    pub price: ScaledValue,
+    pub quantity: ScaledValue,
+    pub timestamp: u64,
+    /// Always true: the trade was negotiated away from the central book
+    pub off_book: bool,
+}
+
+pub fn handle_block_trade(
+    manager: &BookManagerStd<OrderTags>,
+    publisher: &Publisher,
+    config: &TradeReportConfig,
+    payload: BlockTradePayload,
+) -> Option<BlockTrade> {
+    let Some(book) = manager.get_book(&payload.instrument_id) else {
+        warn!(
+            "No book found for {}, rejecting block trade {}",
+            payload.instrument_id, payload.trade_id
+        );
+        return None;
+    };
+    let trade = BlockTrade {
+        trade_id: payload.trade_id,
+        buyer_id: payload.buyer_id,
+        seller_id: payload.seller_id,
+        price: payload.price,
+        quantity: payload.quantity,
+        timestamp: current_time_millis(),
+    };
+    if let Err(e) = book.record_block_trade(&trade) {
+        warn!(
+            "Block trade {} on {} rejected: {}",
+            trade.trade_id, payload.instrument_id, e
+        );
+        return None;
+    }
+    info!(
+        "Recorded block trade {} on {}: {} @ {}",
+        trade.trade_id, payload.instrument_id, trade.quantity, trade.price
+    );
+    let report = BlockTradeReport {
+        instrument_id: &payload.instrument_id,
+        trade_id: &trade.trade_id,
+        buyer_id: &trade.buyer_id,
+        seller_id: &trade.seller_id,
+        price: book.scale().price(trade.price, config.number_format),
+        quantity: book.scale().quantity(trade.quantity, config.number_format),
+        timestamp: trade.timestamp,
+        off_book: true,
+    };
+    publisher.publish(&config.trades_topic, &payload.instrument_id, &report);
+    Some(trade)
+}
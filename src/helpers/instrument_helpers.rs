@@ -1,9 +1,36 @@
-use super::{DeleteInstrumentPayload, InstrumentCreatePayload};
+use super::{
+    AuctionPayload, DeleteInstrumentPayload, InstrumentAdjustPayload, InstrumentCreatePayload,
+    TradingHaltPayload,
+};
+use crate::config::instruments::InstrumentEventsConfig;
+use crate::expiry::ExpiryManager;
+use crate::orderbook::AuctionUncross;
+use crate::orderbook::corporate_action::OrderAdjustment;
 use crate::orderbook::manager::BookManager;
 use crate::orderbook::manager::BookManagerStd;
+use crate::orderbook::matching_policy::MatchingPolicy;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use serde::Serialize;
 use tracing::{info, warn};
 
-pub fn handle_instrument_create(manager: &mut BookManagerStd<()>, instr: InstrumentCreatePayload) {
+/// Published once per applied corporate action, listing every affected order
+#[derive(Debug, Serialize)]
+pub struct InstrumentAdjustmentEvent<'a> {
+    pub instrument_id: &'a str,
+    pub action_id: Option<&'a str>,
+    pub ratio: f64,
+    pub cash_adjustment: i64,
+    pub timestamp: u64,
+    pub adjustments: &'a [OrderAdjustment],
+}
+
+pub fn handle_instrument_create(
+    manager: &mut BookManagerStd<OrderTags>,
+    expiries: &mut ExpiryManager,
+    instr: InstrumentCreatePayload,
+) {
     let token = instr.instrument_id;
     println!("Handling instrument create for id: {}", token);
     if manager.get_book(&token).is_some() {
@@ -12,10 +39,98 @@ pub fn handle_instrument_create(manager: &mut BookManagerStd<()>, instr: Instrum
     }
     info!("Creating new order book for {}", token);
     manager.add_book(&token);
+    if let Some(capacity) = instr.flight_recorder_capacity
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        book.enable_flight_recorder(capacity);
+        info!(
+            "Enabled flight recorder on {} with capacity {}",
+            token, capacity
+        );
+    }
+    if let Some(capacity) = instr.trade_tape_capacity
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        book.enable_trade_tape(capacity);
+        info!("Enabled trade tape on {} with capacity {}", token, capacity);
+    }
+    if let Some(model) = instr.impact_model
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!("Configured {:?} impact model on {}", model.kind, token);
+        book.set_impact_model(Some(model));
+    }
+    if let Some(rules) = instr.block_trade_rules
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!("Configured block trade rules on {}: {:?}", token, rules);
+        book.set_block_trade_rules(rules);
+    }
+    if instr.matching_policy != MatchingPolicy::Fifo
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!(
+            "Matching {} with the {:?} policy",
+            token, instr.matching_policy
+        );
+        book.set_matching_policy(instr.matching_policy);
+    }
+    if let Some(pricing) = instr.mark_pricing
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!("Configured mark pricing on {}: {:?}", token, pricing);
+        book.set_mark_pricing(pricing);
+    }
+    if let Some(repricing) = instr.peg_repricing
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!(
+            "Configured pegged order repricing on {}: {:?}",
+            token, repricing
+        );
+        book.set_peg_repricing(Some(repricing));
+    }
+    if let Some(protection) = instr.market_protection
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!(
+            "Configured market order protection on {}: {} ticks of {}",
+            token, protection.ticks, protection.tick_size
+        );
+        book.set_market_protection(Some(protection));
+    }
+    if let Some(min_notional) = instr.min_fill_notional
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!(
+            "Configured minimum fill notional {} on {}",
+            min_notional, token
+        );
+        book.set_min_fill_notional(Some(min_notional));
+    }
+    if let Some(limit) = instr.sweep_limit
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!("Configured sweep limit on {}: {:?}", token, limit);
+        book.set_sweep_limit(Some(limit));
+    }
+    if let Some(scale) = instr.scale
+        && let Some(book) = manager.get_book_mut(&token)
+    {
+        info!("Configured scale on {}: {:?}", token, scale);
+        book.set_scale(scale);
+    }
+    if !instr.spec.is_empty() {
+        info!("Configured instrument spec on {}: {:?}", token, instr.spec);
+        manager.set_instrument_spec(&token, instr.spec);
+    }
+    if let Some(expiry) = instr.expiry {
+        expiries.schedule(&token, expiry);
+    }
 }
 
 pub fn handle_instrument_delete(
-    manager: &mut BookManagerStd<()>,
+    manager: &mut BookManagerStd<OrderTags>,
     delete_instr: DeleteInstrumentPayload,
 ) {
     let instrument_id = delete_instr.instrument_id;
@@ -26,3 +141,134 @@ pub fn handle_instrument_delete(
     info!("Deleting order book for {}", instrument_id);
     manager.remove_book(&instrument_id);
 }
+
+/// Halts trading on a book: new orders, modifies and replaces are rejected
+/// until it resumes, while cancels still empty it
+pub fn handle_halt(manager: &BookManagerStd<OrderTags>, halt: TradingHaltPayload) {
+    let Some(book) = manager.get_book(&halt.instrument_id) else {
+        warn!(
+            "Instrument {} does not exist, cannot halt",
+            halt.instrument_id
+        );
+        return;
+    };
+    book.halt();
+    info!(
+        "Halted trading on {} ({})",
+        halt.instrument_id,
+        halt.reason.as_deref().unwrap_or("no reason given")
+    );
+}
+
+pub fn handle_resume(manager: &BookManagerStd<OrderTags>, resume: TradingHaltPayload) {
+    let Some(book) = manager.get_book(&resume.instrument_id) else {
+        warn!(
+            "Instrument {} does not exist, cannot resume",
+            resume.instrument_id
+        );
+        return;
+    };
+    book.resume();
+    info!(
+        "Resumed trading on {} ({})",
+        resume.instrument_id,
+        resume.reason.as_deref().unwrap_or("no reason given")
+    );
+}
+
+/// Starts a call auction on a book: limit orders rest without matching, and
+/// orders that cannot rest are rejected, until the uncross
+pub fn handle_auction_start(manager: &BookManagerStd<OrderTags>, auction: AuctionPayload) {
+    let Some(book) = manager.get_book(&auction.instrument_id) else {
+        warn!(
+            "Instrument {} does not exist, cannot start an auction",
+            auction.instrument_id
+        );
+        return;
+    };
+    book.start_auction();
+    info!("Started an auction on {}", auction.instrument_id);
+}
+
+/// Uncrosses the auction on a book at its equilibrium price; the trades reach
+/// the book's trade listener like any other
+pub fn handle_auction_uncross(
+    manager: &BookManagerStd<OrderTags>,
+    auction: AuctionPayload,
+) -> AuctionUncross {
+    let Some(book) = manager.get_book(&auction.instrument_id) else {
+        warn!(
+            "Instrument {} does not exist, cannot uncross an auction",
+            auction.instrument_id
+        );
+        return AuctionUncross::default();
+    };
+    if !book.is_in_auction() {
+        warn!(
+            "Instrument {} is not in an auction, nothing to uncross",
+            auction.instrument_id
+        );
+        return AuctionUncross::default();
+    }
+    match book.uncross() {
+        Ok(uncross) => {
+            match &uncross.equilibrium {
+                Some(equilibrium) => info!(
+                    "Uncrossed the auction on {}: {} at {}, {} left over",
+                    auction.instrument_id,
+                    equilibrium.volume,
+                    equilibrium.price,
+                    equilibrium.surplus
+                ),
+                None => info!(
+                    "Ended the auction on {} without a trade",
+                    auction.instrument_id
+                ),
+            }
+            uncross
+        }
+        Err(e) => {
+            warn!(
+                "Could not uncross the auction on {}: {}",
+                auction.instrument_id, e
+            );
+            AuctionUncross::default()
+        }
+    }
+}
+
+pub fn handle_instrument_adjust(
+    manager: &BookManagerStd<OrderTags>,
+    publisher: &Publisher,
+    config: &InstrumentEventsConfig,
+    adjust: InstrumentAdjustPayload,
+) {
+    let Some(book) = manager.get_book(&adjust.instrument_id) else {
+        warn!(
+            "Instrument {} does not exist, cannot adjust",
+            adjust.instrument_id
+        );
+        return;
+    };
+    match book.apply_corporate_action(&adjust.action) {
+        Ok(adjustments) => {
+            info!(
+                "Adjusted {} resting orders on {} (ratio {}, cash {})",
+                adjustments.len(),
+                adjust.instrument_id,
+                adjust.action.ratio,
+                adjust.action.cash_adjustment
+            );
+            let event = InstrumentAdjustmentEvent {
+                instrument_id: &adjust.instrument_id,
+                action_id: adjust.action_id.as_deref(),
+                ratio: adjust.action.ratio,
+                cash_adjustment: adjust.action.cash_adjustment,
+                timestamp: current_time_millis(),
+                adjustments: &adjustments,
+            };
+            publisher.publish(&config.adjustments_topic, &adjust.instrument_id, &event);
+        }
+        Err(e) => warn!("Failed to adjust {}: {}", adjust.instrument_id, e),
+    }
+}
@@ -1,12 +1,29 @@
 // src/helpers/mod.rs
+pub mod admin_helpers;
+pub mod analytics_helpers;
+pub mod block_trade_helpers;
 pub mod instrument_helpers;
 pub mod orderbook_helpers;
+pub mod rfq_helpers;
+pub mod timestamp;
 pub mod types;
 
 pub use types::{
-    DeleteInstrumentPayload, EngineCommand, InstrumentCreatePayload, OrderCancelPayload,
-    OrderCreatePayload, OrderModifyPayload,
+    AdminCommandPayload, AuctionPayload, BlockTradePayload, DeleteInstrumentPayload, EngineCommand,
+    IndexDefinePayload, InstrumentAdjustPayload, InstrumentCreatePayload, MassCancelPayload,
+    KillSwitchPayload, MassCancelSummary, OrderCancelPayload, OrderCreatePayload, OrderModifyPayload, OrderReplacePayload, RfqExecutePayload,
+    RfqQuotePayload, RfqRequestPayload, TheoreticalPricePayload, TradingHaltPayload,
 };
 
-pub use instrument_helpers::{handle_instrument_create, handle_instrument_delete};
-pub use orderbook_helpers::{handle_order_cancel, handle_order_create, handle_order_modify};
+pub use admin_helpers::handle_admin_command;
+pub use analytics_helpers::sample_correlations;
+pub use block_trade_helpers::handle_block_trade;
+pub use instrument_helpers::{
+    handle_auction_start, handle_auction_uncross, handle_halt, handle_instrument_adjust, handle_instrument_create, handle_instrument_delete,
+    handle_resume,
+};
+pub use orderbook_helpers::{
+    cap_sweep, handle_mass_cancel, handle_order_cancel, handle_order_create, handle_order_modify,
+    split_order_replace,
+};
+pub use rfq_helpers::{handle_rfq_execute, handle_rfq_quote, handle_rfq_request, sweep_rfqs};
@@ -1,10 +1,23 @@
-use super::{OrderCancelPayload, OrderCreatePayload, OrderModifyPayload};
+use super::{
+    MassCancelPayload, MassCancelSummary, OrderCancelPayload, OrderCreatePayload,
+    OrderModifyPayload, OrderReplacePayload,
+};
 use crate::helpers::types::OrderType;
+use crate::order_responses::{RejectReason, Rejection};
 use crate::orderbook::manager::BookManager;
 use crate::orderbook::manager::BookManagerStd;
-use pricelevel::{OrderId, OrderUpdate, Side};
+use crate::orderbook::sweep_limit::{SweepLimit, SweepRemainder};
+use crate::orderbook::{OrderBook, OrderBookError};
+use crate::tags::{OrderTags, is_liquidation};
+use pricelevel::{OrderId, OrderType as BookOrder, OrderUpdate, Side, TimeInForce};
+use std::collections::BTreeMap;
 use tracing::{info, warn};
-pub fn handle_order_create(manager: &mut BookManagerStd<()>, order: OrderCreatePayload) {
+/// Applies an order, returning why it was refused if it was. An IOC order
+/// whose unfilled part is cancelled was still applied.
+pub fn handle_order_create(
+    manager: &mut BookManagerStd<OrderTags>,
+    order: OrderCreatePayload,
+) -> Result<(), Rejection> {
     let symbol = order.instrument_id.clone();
     if manager.get_book(&symbol).is_none() {
         info!("No book for {}, creating one on demand", symbol);
@@ -12,10 +25,112 @@ pub fn handle_order_create(manager: &mut BookManagerStd<()>, order: OrderCreateP
     }
     let Some(book) = manager.get_book(&symbol) else {
         warn!("Unable to get book for {} after add_book", symbol);
-        return;
+        return Err(Rejection::new(
+            RejectReason::UnknownInstrument,
+            format!("no book for {symbol}"),
+        ));
     };
+    ensure_not_halted(book, &symbol)?;
 
     let order_id = OrderId::from_u64(order.order_id);
+    // IOC and FOK orders never rest, whatever part of them does not fill is
+    // cancelled
+    let immediate = order.time_in_force.is_immediate();
+    // Displayed quantity of an iceberg order
+    let display = match order.order_type {
+        OrderType::ICEBERG => match order.iceberg_quantities() {
+            Ok((visible, _)) => Some(visible),
+            Err(e) => {
+                warn!("Rejected iceberg order {} on {}: {}", order_id, symbol, e);
+                return Err(Rejection::new(RejectReason::InvalidOrder, e));
+            }
+        },
+        OrderType::MARKET | OrderType::LIMIT | OrderType::MARKET_TO_LIMIT => None,
+    };
+    let min_execution = order.min_execution().map_err(|e| {
+        warn!("Rejected order {} on {}: {}", order_id, symbol, e);
+        Rejection::new(RejectReason::InvalidOrder, e)
+    })?;
+    // Worst price the order may match at
+    let limit = price_limit(book, &order);
+    let tags = Some(order.tags);
+    // Rests `quantity` of the order at `price`, an iceberg one showing no more
+    // than its displayed quantity
+    let rest = |price: u64, quantity: u64| match display {
+        Some(visible) => {
+            let visible = visible.min(quantity);
+            book.add_iceberg_order(
+                order_id,
+                price,
+                visible,
+                quantity - visible,
+                order.side,
+                order.time_in_force,
+                tags.clone(),
+            )
+        }
+        None => book.add_limit_order(
+            order_id,
+            price,
+            quantity,
+            order.side,
+            order.time_in_force,
+            tags.clone(),
+        ),
+    };
+    if immediate && book.is_in_auction() {
+        warn!(
+            "Rejected {:?} order {} on {}: the book is in auction",
+            order.time_in_force, order_id, symbol
+        );
+        return Err(Rejection::new(
+            RejectReason::InvalidOrder,
+            "only orders that can rest are accepted during the auction",
+        ));
+    }
+    if let Some(min_quantity) = min_execution {
+        if book.is_in_auction() {
+            warn!(
+                "Rejected order {} on {}: minimum execution in auction",
+                order_id, symbol
+            );
+            return Err(Rejection::new(
+                RejectReason::InvalidOrder,
+                "orders with a minimum execution are not accepted during the auction",
+            ));
+        }
+        // Checked before matching, so an order that cannot meet its minimum
+        // leaves the book untouched. A limit order that does not cross at all
+        // rests as an ordinary one, unless it is all-or-none.
+        if let Err(e) = book.ensure_min_execution(order.side, min_quantity, limit) {
+            let crosses = !matches!(
+                e,
+                OrderBookError::InsufficientLiquidity { available: 0, .. }
+            );
+            if crosses || order.all_or_none || order.order_type.is_priced_by_book() {
+                warn!(
+                    "Rejected order {} on {}: minimum execution {} not met: {}",
+                    order_id, symbol, min_quantity, e
+                );
+                return Err(e.into());
+            }
+        }
+    }
+    if order.time_in_force == TimeInForce::Fok {
+        // Checked before matching, so an order that cannot fill completely
+        // leaves the book untouched
+        let available = book.peek_match(order.side, order.quantity, limit);
+        if available < order.quantity {
+            warn!(
+                "Rejected FOK order {} on {}: {} of {} available",
+                order_id, symbol, available, order.quantity
+            );
+            return Err(Rejection::new(
+                RejectReason::InsufficientLiquidity,
+                format!("{} of {} available", available, order.quantity),
+            ));
+        }
+    }
 
     match order.order_type {
         // If it's a market order (you may use a flag in payload to distinguish); replace the check if different.
@@ -33,12 +148,14 @@ pub fn handle_order_create(manager: &mut BookManagerStd<()>, order: OrderCreateP
                 }
                 Err(e) => {
                     warn!("Market match failed for {} on {}: {}", order_id, symbol, e);
+                    return Err(e.into());
                 }
             }
         }
-        OrderType::LIMIT /* limit order */ => {
-            // Check whether order is aggressive (crosses book)
-            let should_attempt_match = match order.side {
+        OrderType::LIMIT | OrderType::ICEBERG /* limit order */ => {
+            // Check whether order is aggressive (crosses book); in an auction
+            // it rests until the uncross
+            let should_attempt_match = !book.is_in_auction() && match order.side {
                 Side::Buy => {
                     if let Some(best_ask) = book.best_ask() {
                         order.price >= best_ask
@@ -61,17 +178,13 @@ pub fn handle_order_create(manager: &mut BookManagerStd<()>, order: OrderCreateP
                     Ok(match_result) => {
                         // If there were transactions, trade_listener already invoked inside match_limit_order
                         info!("Limit order {} partially/fully matched: executed {} on {}", order_id, match_result.executed_quantity(), symbol);
-                        if match_result.remaining_quantity > 0 {
+                        if match_result.remaining_quantity > 0 && immediate {
+                            info!("Cancelled unfilled {} of {:?} order {} on {}", match_result.remaining_quantity, order.time_in_force, order_id, symbol);
+                        } else if match_result.remaining_quantity > 0 {
                             // Add remaining as a resting order
-                            if let Err(e) = book.add_limit_order(
-                                order_id,
-                                order.price,
-                                match_result.remaining_quantity,
-                                order.side,
-                                order.time_in_force,
-                                None,
-                            ) {
+                            if let Err(e) = rest(order.price, match_result.remaining_quantity) {
                                 warn!("Failed to add leftover resting order {} on {}: {}", order_id, symbol, e);
+                                return Err(e.into());
                             } else {
                                 info!("Added resting remainder {} qty for order {} on {}", match_result.remaining_quantity, order_id, symbol);
                             }
@@ -80,73 +193,894 @@ pub fn handle_order_create(manager: &mut BookManagerStd<()>, order: OrderCreateP
                     Err(e) => {
                         warn!("Matching failed for limit {} on {}: {}", order_id, symbol, e);
                         // Fallback: insert as resting order
-                        if let Err(e2) = book.add_limit_order(
-                            order_id,
-                            order.price,
-                            order.quantity,
-                            order.side,
-                            order.time_in_force,
-                            None,
-                        ) {
+                        if immediate {
+                            return Err(e.into());
+                        }
+                        if let Err(e2) = rest(order.price, order.quantity) {
                             warn!("Failed to add order {} after match failure: {}", order_id, e2);
+                            return Err(e2.into());
                         }
                     }
                 }
+            } else if immediate {
+                info!("Cancelled {:?} order {} on {}: it does not cross", order.time_in_force, order_id, symbol);
             } else {
                 // Not aggressive -> insert as resting order directly
-                if let Err(e) = book.add_limit_order(
-                    order_id,
-                    order.price,
-                    order.quantity,
-                    order.side,
-                    order.time_in_force,
-                    None,
-                ) {
+                if let Err(e) = rest(order.price, order.quantity) {
                     warn!("Failed to add order {} on {}: {}", order_id, symbol, e);
+                    return Err(e.into());
                 } else {
                     info!("Added order {} on {}", order_id, symbol);
                 }
             }
         }
+        OrderType::MARKET_TO_LIMIT => {
+            if book.is_in_auction() {
+                warn!("Rejected market-to-limit order {} on {}: the book is in auction", order_id, symbol);
+                return Err(Rejection::new(
+                    RejectReason::InvalidOrder,
+                    "market-to-limit orders are not accepted during the auction",
+                ));
+            }
+            // The best opposite price is the order's limit, so it fills there
+            // and nowhere worse
+            let Some(best) = best_opposite(book, order.side) else {
+                warn!("Rejected market-to-limit order {} on {}: nothing to match", order_id, symbol);
+                return Err(Rejection::new(
+                    RejectReason::InsufficientLiquidity,
+                    "no opposite orders to match against",
+                ));
+            };
+            let match_result = book
+                .match_limit_order(order_id, order.quantity, order.side, best)
+                .map_err(|e| {
+                    warn!("Matching failed for market-to-limit {} on {}: {}", order_id, symbol, e);
+                    Rejection::from(e)
+                })?;
+            let last_price = match_result
+                .transactions
+                .as_vec()
+                .last()
+                .map_or(best, |transaction| transaction.price);
+            info!("Market-to-limit order {} executed {} on {} at {}", order_id, match_result.executed_quantity(), symbol, last_price);
+            if match_result.remaining_quantity > 0 && immediate {
+                info!("Cancelled unfilled {} of {:?} order {} on {}", match_result.remaining_quantity, order.time_in_force, order_id, symbol);
+            } else if match_result.remaining_quantity > 0 {
+                if let Err(e) = rest(last_price, match_result.remaining_quantity) {
+                    warn!("Failed to rest market-to-limit order {} on {}: {}", order_id, symbol, e);
+                    return Err(e.into());
+                }
+                info!("Converted {} of market-to-limit order {} to a limit at {} on {}", match_result.remaining_quantity, order_id, last_price, symbol);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Cuts an order down to what it may execute in one sweep, under the tighter
+/// of its own sweep limit and its instrument's
+///
+/// An order that would execute beyond the cap becomes an IOC order for the
+/// capped quantity. The rest is returned as an order of its own to enter
+/// again when the limit requeues it, and is cancelled otherwise. An order that
+/// cannot be split, FOK or with a minimum execution beyond the cap, is
+/// rejected with the book untouched.
+pub fn cap_sweep(
+    manager: &BookManagerStd<OrderTags>,
+    order: &mut OrderCreatePayload,
+) -> Result<Option<OrderCreatePayload>, Rejection> {
+    let Some(book) = manager.get_book(&order.instrument_id) else {
+        return Ok(None);
+    };
+    let Some(limit) = SweepLimit::tighter(order.sweep_limit, book.sweep_limit()) else {
+        return Ok(None);
+    };
+    if book.is_in_auction() {
+        return Ok(None);
+    }
+    let limit_price = price_limit(book, order).or_else(|| book.market_protection_limit(order.side));
+    let executable = book.peek_match(order.side, order.quantity, limit_price);
+    let allowance = book.sweep_allowance(order.side, order.quantity, limit_price, &limit);
+    if allowance >= executable {
+        return Ok(None);
+    }
+    let unsplittable = order.time_in_force == TimeInForce::Fok
+        || order
+            .min_execution()
+            .is_ok_and(|min| min.is_some_and(|min| min > allowance));
+    if unsplittable {
+        warn!(
+            "Rejected order {} on {}: {} of it would execute, beyond its sweep limit of {}",
+            order.order_id, order.instrument_id, executable, allowance
+        );
+        return Err(Rejection::new(
+            RejectReason::InvalidOrder,
+            format!("{executable} would execute, beyond the sweep limit of {allowance}"),
+        ));
+    }
+    let mut remainder = order.clone();
+    remainder.quantity = order.quantity - allowance;
+    // The minimum was met by the first sweep
+    remainder.min_quantity = None;
+    remainder.visible_quantity = order
+        .visible_quantity
+        .map(|visible| visible.min(remainder.quantity));
+    remainder.hidden_quantity = None;
+    order.quantity = allowance;
+    order.time_in_force = TimeInForce::Ioc;
+    order.visible_quantity = order.visible_quantity.map(|visible| visible.min(allowance));
+    order.hidden_quantity = None;
+    match limit.remainder {
+        SweepRemainder::Requeue => {
+            info!(
+                "Requeued {} of order {} on {} beyond its sweep limit",
+                remainder.quantity, order.order_id, order.instrument_id
+            );
+            Ok(Some(remainder))
+        }
+        SweepRemainder::Cancel => {
+            info!(
+                "Cancelled {} of order {} on {} beyond its sweep limit",
+                remainder.quantity, order.order_id, order.instrument_id
+            );
+            Ok(None)
+        }
     }
 }
 
-pub fn handle_order_cancel(manager: &mut BookManagerStd<()>, order: OrderCancelPayload) {
+/// Worst price an order may match at, `None` for a market order
+pub fn price_limit(
+    book: &crate::orderbook::OrderBook<OrderTags>,
+    order: &OrderCreatePayload,
+) -> Option<u64> {
+    match order.order_type {
+        OrderType::MARKET => None,
+        OrderType::LIMIT | OrderType::ICEBERG => Some(order.price),
+        OrderType::MARKET_TO_LIMIT => best_opposite(book, order.side),
+    }
+}
+
+/// Best price an order on `side` would match against
+pub fn best_opposite<T>(book: &crate::orderbook::OrderBook<T>, side: Side) -> Option<u64>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    match side {
+        Side::Buy => book.best_ask(),
+        Side::Sell => book.best_bid(),
+    }
+}
+
+/// The rejection of a command naming an order that does not rest on the book
+fn unknown_order(order_id: OrderId, instrument_id: &str) -> Rejection {
+    Rejection::new(
+        RejectReason::UnknownOrder,
+        format!("no resting order {order_id} on {instrument_id}"),
+    )
+}
+
+fn unknown_instrument(instrument_id: &str) -> Rejection {
+    Rejection::new(
+        RejectReason::UnknownInstrument,
+        format!("no book for {instrument_id}"),
+    )
+}
+
+/// Refuses new orders, modifies and replaces while the instrument is halted
+fn ensure_not_halted(book: &OrderBook<OrderTags>, instrument_id: &str) -> Result<(), Rejection> {
+    if book.is_halted() {
+        warn!("Rejected an order on halted {}", instrument_id);
+        return Err(OrderBookError::InstrumentHalted {
+            instrument_id: instrument_id.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+pub fn handle_order_cancel(
+    manager: &mut BookManagerStd<OrderTags>,
+    order: OrderCancelPayload,
+) -> Result<(), Rejection> {
     let Some(book) = manager.get_book_mut(&order.instrument_id) else {
         warn!(
             "No book found for {}, cannot cancel order {}",
             order.instrument_id, order.order_id
         );
-        return;
+        return Err(unknown_instrument(&order.instrument_id));
     };
     let order_id = OrderId::from_u64(order.order_id);
-    if let Err(e) = book.cancel_order(order_id) {
-        warn!(
-            "Failed to remove order {} on {}: {}",
-            order_id, order.instrument_id, e
-        );
+    match book.cancel_order(order_id) {
+        Ok(Some(_)) => {
+            info!("Cancelled order {} on {}", order_id, order.instrument_id);
+            Ok(())
+        }
+        Ok(None) => {
+            warn!(
+                "No resting order {} on {} to cancel",
+                order_id, order.instrument_id
+            );
+            Err(unknown_order(order_id, &order.instrument_id))
+        }
+        Err(e) => {
+            warn!(
+                "Failed to remove order {} on {}: {}",
+                order_id, order.instrument_id, e
+            );
+            Err(e.into())
+        }
     }
-    info!("Cancelled order {} on {}", order_id, order.instrument_id);
 }
 
-pub fn handle_order_modify(manager: &mut BookManagerStd<()>, order: OrderModifyPayload) {
+/// Cancels every resting order a mass cancel selects, in one pass over each
+/// book it names. `owner` gives the participant of an order and
+/// `on_cancelled` learns each order cancelled.
+pub fn handle_mass_cancel(
+    manager: &BookManagerStd<OrderTags>,
+    mass_cancel: MassCancelPayload,
+    owner: impl Fn(&str, OrderId) -> Option<String>,
+    mut on_cancelled: impl FnMut(&str, OrderId),
+    now: u64,
+) -> MassCancelSummary {
+    let mut summary = MassCancelSummary {
+        request: mass_cancel,
+        orders_cancelled: 0,
+        quantity_cancelled: 0,
+        instruments: BTreeMap::new(),
+        timestamp: now,
+    };
+    let request = &summary.request;
+    if !request.is_targeted() {
+        warn!("Ignoring mass cancel naming neither an instrument nor a participant");
+        return summary;
+    }
+    let mut symbols = match &request.instrument_id {
+        Some(instrument_id) => vec![instrument_id.clone()],
+        None => manager.symbols(),
+    };
+    symbols.sort_unstable();
+    for symbol in symbols {
+        let Some(book) = manager.get_book(&symbol) else {
+            continue;
+        };
+        let cancelled = book.cancel_orders_where(|order_id, side| {
+            request.side.is_none_or(|wanted| wanted == side)
+                && request
+                    .participant_id
+                    .as_ref()
+                    .is_none_or(|wanted| owner(&symbol, order_id).as_ref() == Some(wanted))
+        });
+        if cancelled.is_empty() {
+            continue;
+        }
+        for order in &cancelled {
+            summary.quantity_cancelled += order.visible_quantity() + order.hidden_quantity();
+            on_cancelled(&symbol, order.id());
+        }
+        summary.orders_cancelled += cancelled.len();
+        summary.instruments.insert(symbol, cancelled.len());
+    }
+    info!(
+        "Mass cancel {:?} cancelled {} orders",
+        summary.request, summary.orders_cancelled
+    );
+    summary
+}
+
+pub fn handle_order_modify(
+    manager: &mut BookManagerStd<OrderTags>,
+    order: OrderModifyPayload,
+) -> Result<(), Rejection> {
     let Some(book) = manager.get_book_mut(&order.instrument_id) else {
         warn!(
             "No book found for {}, cannot modify order {}",
             order.instrument_id, order.order_id
         );
-        return;
+        return Err(unknown_instrument(&order.instrument_id));
     };
+    ensure_not_halted(book, &order.instrument_id)?;
     let order_id = OrderId::from_u64(order.order_id);
     let order_update = OrderUpdate::UpdatePriceAndQuantity {
         order_id,
         new_price: order.price,
         new_quantity: order.quantity,
     };
-    if let Err(e) = book.update_order(order_update) {
+    match book.update_order(order_update) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => {
+            warn!(
+                "No resting order {} on {} to modify",
+                order_id, order.instrument_id
+            );
+            Err(unknown_order(order_id, &order.instrument_id))
+        }
+        Err(e) => {
+            warn!(
+                "Failed to modify order {} on {}: {}",
+                order_id, order.instrument_id, e
+            );
+            Err(e.into())
+        }
+    }
+}
+
+/// The cancel of the order a replace names, and the order replacing it
+///
+/// The replacement takes the side, time in force and tags of the order it
+/// replaces unless the replace sets them, and replaces a liquidation order by
+/// another. An iceberg order is replaced by one
+/// displaying as much. The replacement is a new order to the book: it loses
+/// the time priority of the replaced one and matches if its price crosses.
+pub fn split_order_replace(
+    manager: &BookManagerStd<OrderTags>,
+    replace: OrderReplacePayload,
+) -> Result<(OrderCancelPayload, OrderCreatePayload), Rejection> {
+    let Some(book) = manager.get_book(&replace.instrument_id) else {
+        return Err(unknown_instrument(&replace.instrument_id));
+    };
+    // Checked up front, as the replacement would be refused once the order it
+    // replaces is cancelled
+    ensure_not_halted(book, &replace.instrument_id)?;
+    let order_id = OrderId::from_u64(replace.order_id);
+    let Some(original) = book.get_order(order_id) else {
         warn!(
-            "Failed to modify order {} on {}: {}",
-            order_id, order.instrument_id, e
+            "No resting order {} on {} to replace",
+            order_id, replace.instrument_id
+        );
+        return Err(unknown_order(order_id, &replace.instrument_id));
+    };
+    if replace.quantity == 0 {
+        return Err(Rejection::new(
+            RejectReason::InvalidOrder,
+            "a replacement needs a positive quantity",
+        ));
+    }
+    let (order_type, visible_quantity) = match *original {
+        BookOrder::IcebergOrder {
+            visible_quantity, ..
+        } => (
+            OrderType::ICEBERG,
+            Some(visible_quantity.min(replace.quantity)),
+        ),
+        _ => (OrderType::LIMIT, None),
+    };
+    let cancel = OrderCancelPayload {
+        order_id: replace.order_id,
+        instrument_id: replace.instrument_id.clone(),
+        client_order_id: None,
+        participant_id: replace.participant_id.clone(),
+        sequence: None,
+    };
+    let order = OrderCreatePayload {
+        order_id: replace.order_id,
+        instrument_id: replace.instrument_id,
+        quantity: replace.quantity,
+        price: replace.price,
+        side: replace.side.unwrap_or(original.side()),
+        time_in_force: replace.time_in_force.unwrap_or(original.time_in_force()),
+        order_type,
+        participant_id: replace.participant_id,
+        oms_id: None,
+        client_order_id: replace.new_client_order_id.or(replace.client_order_id),
+        tags: original.extra_fields().clone(),
+        visible_quantity,
+        hidden_quantity: None,
+        liquidation: is_liquidation(original.extra_fields()),
+        min_quantity: None,
+        all_or_none: false,
+        sweep_limit: None,
+        sequence: None,
+    };
+    Ok((cancel, order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::book_change_event::PriceLevelChangedEvent;
+    use std::sync::{Arc, Mutex};
+
+    fn order(
+        order_id: u64,
+        side: Side,
+        price: u64,
+        quantity: u64,
+        tif: TimeInForce,
+    ) -> OrderCreatePayload {
+        OrderCreatePayload {
+            order_id,
+            instrument_id: "BTC".to_string(),
+            quantity,
+            price,
+            side,
+            time_in_force: tif,
+            order_type: OrderType::LIMIT,
+            participant_id: None,
+            oms_id: None,
+            client_order_id: None,
+            tags: OrderTags::new(),
+            visible_quantity: None,
+            hidden_quantity: None,
+            liquidation: false,
+            min_quantity: None,
+            all_or_none: false,
+            sweep_limit: None,
+            sequence: None,
+        }
+    }
+
+    #[test]
+    fn test_mass_cancels_select_by_instrument_side_and_participant() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        for (order_id, side, price) in
+            [(1, Side::Buy, 99), (2, Side::Buy, 98), (3, Side::Sell, 101)]
+        {
+            handle_order_create(
+                &mut manager,
+                order(order_id, side, price, 5, TimeInForce::Gtc),
+            )
+            .unwrap();
+        }
+        let mut eth = order(4, Side::Buy, 99, 7, TimeInForce::Gtc);
+        eth.instrument_id = "ETH".to_string();
+        handle_order_create(&mut manager, eth).unwrap();
+        // Orders 2 and 4 belong to desk-b
+        let owner = |_: &str, order_id: OrderId| {
+            let desk = if [2, 4].map(OrderId::from_u64).contains(&order_id) {
+                "desk-b"
+            } else {
+                "desk-a"
+            };
+            Some(desk.to_string())
+        };
+        let mass_cancel =
+            |instrument_id: Option<&str>, side, participant_id: Option<&str>| MassCancelPayload {
+                instrument_id: instrument_id.map(str::to_string),
+                side,
+                participant_id: participant_id.map(str::to_string),
+                sequence: None,
+            };
+
+        let mut cancelled = Vec::new();
+        let summary = handle_mass_cancel(
+            &manager,
+            mass_cancel(None, Some(Side::Buy), Some("desk-b")),
+            owner,
+            |instrument_id, order_id| cancelled.push((instrument_id.to_string(), order_id)),
+            5,
+        );
+        assert_eq!(
+            (summary.orders_cancelled, summary.quantity_cancelled),
+            (2, 12)
+        );
+        assert_eq!(
+            summary.instruments,
+            BTreeMap::from([("BTC".to_string(), 1), ("ETH".to_string(), 1)])
+        );
+        assert_eq!(
+            cancelled,
+            [
+                ("BTC".to_string(), OrderId::from_u64(2)),
+                ("ETH".to_string(), OrderId::from_u64(4))
+            ]
+        );
+
+        // Naming neither an instrument nor a participant cancels nothing
+        let summary = handle_mass_cancel(
+            &manager,
+            mass_cancel(None, Some(Side::Sell), None),
+            owner,
+            |_, _| {},
+            5,
+        );
+        assert_eq!(summary.orders_cancelled, 0);
+
+        let summary = handle_mass_cancel(
+            &manager,
+            mass_cancel(Some("BTC"), None, None),
+            owner,
+            |_, _| {},
+            5,
+        );
+        assert_eq!(summary.orders_cancelled, 2);
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!((book.best_bid(), book.best_ask()), (None, None));
+        assert!(manager.get_book("ETH").unwrap().best_bid().is_none());
+    }
+
+    #[test]
+    fn test_halted_books_only_take_cancels() {
+        use crate::helpers::{TradingHaltPayload, handle_halt, handle_resume};
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        handle_order_create(&mut manager, order(1, Side::Buy, 99, 5, TimeInForce::Gtc)).unwrap();
+        handle_order_create(&mut manager, order(2, Side::Buy, 98, 5, TimeInForce::Gtc)).unwrap();
+        let halt = || TradingHaltPayload {
+            instrument_id: "BTC".to_string(),
+            reason: Some("news".to_string()),
+            sequence: None,
+        };
+        handle_halt(&manager, halt());
+
+        let halted = |outcome: Result<(), Rejection>| {
+            let rejection = outcome.unwrap_err();
+            assert_eq!(rejection.reason, RejectReason::TradingHalted);
+            assert_eq!(rejection.message, "Instrument BTC is halted");
+        };
+        halted(handle_order_create(
+            &mut manager,
+            order(3, Side::Buy, 97, 5, TimeInForce::Gtc),
+        ));
+        halted(handle_order_modify(
+            &mut manager,
+            OrderModifyPayload {
+                order_id: 1,
+                instrument_id: "BTC".to_string(),
+                price: 97,
+                quantity: 5,
+                client_order_id: None,
+                participant_id: None,
+                sequence: None,
+            },
+        ));
+        let replace: OrderReplacePayload = serde_json::from_value(serde_json::json!({
+            "instrument_id": "BTC", "order_id": 1, "price": 97, "quantity": 5,
+        }))
+        .unwrap();
+        halted(split_order_replace(&manager, replace).map(|_| ()));
+        let cancel = |order_id| OrderCancelPayload {
+            order_id,
+            instrument_id: "BTC".to_string(),
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        };
+        handle_order_cancel(&mut manager, cancel(2)).unwrap();
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!(book.best_bid(), Some(99));
+
+        handle_resume(&manager, halt());
+        handle_order_create(&mut manager, order(3, Side::Buy, 97, 5, TimeInForce::Gtc)).unwrap();
+    }
+
+    #[test]
+    fn test_auctions_collect_resting_orders_until_the_uncross() {
+        use crate::helpers::{AuctionPayload, handle_auction_start, handle_auction_uncross};
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        handle_order_create(&mut manager, order(1, Side::Sell, 100, 5, TimeInForce::Gtc)).unwrap();
+        let auction = || AuctionPayload {
+            instrument_id: "BTC".to_string(),
+            sequence: None,
+        };
+        handle_auction_start(&manager, auction());
+        handle_order_create(&mut manager, order(2, Side::Buy, 102, 8, TimeInForce::Gtc)).unwrap();
+        let rejection =
+            handle_order_create(&mut manager, order(3, Side::Buy, 102, 1, TimeInForce::Ioc))
+                .unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::InvalidOrder);
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(102), Some(100)));
+
+        let uncross = handle_auction_uncross(&manager, auction());
+        let equilibrium = uncross.equilibrium.unwrap();
+        assert_eq!((equilibrium.price, equilibrium.volume), (100, 5));
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(102), None));
+        // Nothing left to uncross
+        assert!(
+            handle_auction_uncross(&manager, auction())
+                .equilibrium
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_ioc_and_fok_orders_never_rest() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        handle_order_create(&mut manager, order(1, Side::Sell, 100, 5, TimeInForce::Gtc)).unwrap();
+        handle_order_create(&mut manager, order(2, Side::Sell, 101, 5, TimeInForce::Gtc)).unwrap();
+        let book = |manager: &BookManagerStd<OrderTags>| {
+            let book = manager.get_book("BTC").unwrap();
+            (
+                book.best_bid(),
+                book.best_ask(),
+                book.get_order(OrderId::from_u64(1))
+                    .map(|o| o.visible_quantity()),
+            )
+        };
+
+        // Short of liquidity within its limit, a FOK order leaves the book as it was
+        let rejection =
+            handle_order_create(&mut manager, order(3, Side::Buy, 101, 11, TimeInForce::Fok))
+                .unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::InsufficientLiquidity);
+        assert_eq!(book(&manager), (None, Some(100), Some(5)));
+
+        // An IOC order takes what it can and its remainder is cancelled
+        handle_order_create(&mut manager, order(4, Side::Buy, 100, 7, TimeInForce::Ioc)).unwrap();
+        assert_eq!(book(&manager), (None, Some(101), None));
+
+        // Neither rests when it does not cross
+        handle_order_create(&mut manager, order(5, Side::Buy, 99, 7, TimeInForce::Ioc)).unwrap();
+        handle_order_create(&mut manager, order(6, Side::Buy, 99, 7, TimeInForce::Fok))
+            .unwrap_err();
+        assert_eq!(book(&manager), (None, Some(101), None));
+
+        // A FOK order with enough liquidity fills completely
+        handle_order_create(&mut manager, order(7, Side::Buy, 101, 5, TimeInForce::Fok)).unwrap();
+        assert_eq!(book(&manager), (None, None, None));
+    }
+
+    #[test]
+    fn test_iceberg_orders_rest_and_replenish() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let sink = changes.clone();
+        manager
+            .get_book_mut("BTC")
+            .unwrap()
+            .set_price_level_listener(Arc::new(move |event: PriceLevelChangedEvent| {
+                sink.lock()
+                    .unwrap()
+                    .push((event.quantity, event.replenished));
+            }));
+        let iceberg = |order_id, quantity, visible_quantity, hidden_quantity| OrderCreatePayload {
+            order_type: OrderType::ICEBERG,
+            visible_quantity,
+            hidden_quantity,
+            ..order(order_id, Side::Sell, 100, quantity, TimeInForce::Gtc)
+        };
+
+        // Quantities that do not add up are rejected
+        for invalid in [
+            iceberg(1, 30, None, None),
+            iceberg(1, 30, Some(10), Some(10)),
+        ] {
+            let rejection = handle_order_create(&mut manager, invalid).unwrap_err();
+            assert_eq!(rejection.reason, RejectReason::InvalidOrder);
+        }
+        assert!(manager.get_book("BTC").unwrap().best_ask().is_none());
+
+        let resting = |manager: &BookManagerStd<OrderTags>| {
+            let order = manager
+                .get_book("BTC")
+                .unwrap()
+                .get_order(OrderId::from_u64(1))
+                .unwrap();
+            (order.visible_quantity(), order.hidden_quantity())
+        };
+        handle_order_create(&mut manager, iceberg(1, 30, Some(10), None)).unwrap();
+        assert_eq!(resting(&manager), (10, 20));
+
+        // Filling the displayed part shows the next slice of the reserve
+        handle_order_create(&mut manager, order(2, Side::Buy, 100, 10, TimeInForce::Gtc)).unwrap();
+        assert_eq!(changes.lock().unwrap().last(), Some(&(10, 10)));
+        assert_eq!(resting(&manager), (10, 10));
+    }
+
+    #[test]
+    fn test_market_to_limit_orders_rest_at_their_execution_price() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        let market_to_limit = |order_id, quantity, tif| OrderCreatePayload {
+            order_type: OrderType::MARKET_TO_LIMIT,
+            ..order(order_id, Side::Buy, 0, quantity, tif)
+        };
+        // Nothing to match, so nothing to price it at
+        let rejection =
+            handle_order_create(&mut manager, market_to_limit(1, 5, TimeInForce::Gtc)).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::InsufficientLiquidity);
+
+        handle_order_create(&mut manager, order(2, Side::Sell, 100, 5, TimeInForce::Gtc)).unwrap();
+        handle_order_create(&mut manager, order(3, Side::Sell, 101, 5, TimeInForce::Gtc)).unwrap();
+        // It fills at the best ask only and rests the rest there, rather than
+        // sweeping on to 101
+        handle_order_create(&mut manager, market_to_limit(4, 8, TimeInForce::Gtc)).unwrap();
+        let book = manager.get_book("BTC").unwrap();
+        let rested = book.get_order(OrderId::from_u64(4)).unwrap();
+        assert_eq!((rested.price(), rested.visible_quantity()), (100, 3));
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(100), Some(101)));
+
+        // An IOC one cancels what the best price does not fill
+        handle_order_create(&mut manager, market_to_limit(5, 8, TimeInForce::Ioc)).unwrap();
+        let book = manager.get_book("BTC").unwrap();
+        assert!(book.get_order(OrderId::from_u64(5)).is_none());
+        assert_eq!(book.best_ask(), None);
+    }
+
+    #[test]
+    fn test_minimum_executions_are_met_on_entry_or_not_at_all() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        handle_order_create(&mut manager, order(1, Side::Sell, 100, 5, TimeInForce::Gtc)).unwrap();
+        handle_order_create(&mut manager, order(2, Side::Sell, 102, 5, TimeInForce::Gtc)).unwrap();
+        let constrained = |order_id, price, min_quantity, all_or_none| OrderCreatePayload {
+            min_quantity,
+            all_or_none,
+            ..order(order_id, Side::Buy, price, 8, TimeInForce::Gtc)
+        };
+
+        for (invalid, reason) in [
+            (
+                constrained(3, 100, Some(0), false),
+                RejectReason::InvalidOrder,
+            ),
+            (
+                constrained(3, 100, Some(9), false),
+                RejectReason::InvalidOrder,
+            ),
+            (
+                constrained(3, 100, Some(4), true),
+                RejectReason::InvalidOrder,
+            ),
+            // Only 5 within the limit
+            (
+                constrained(3, 101, Some(6), false),
+                RejectReason::InsufficientLiquidity,
+            ),
+            (
+                constrained(3, 101, None, true),
+                RejectReason::InsufficientLiquidity,
+            ),
+        ] {
+            let rejection = handle_order_create(&mut manager, invalid).unwrap_err();
+            assert_eq!(rejection.reason, reason);
+        }
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!(book.get_all_orders().len(), 2);
+
+        // Not crossing at all, it rests as an ordinary order
+        handle_order_create(&mut manager, constrained(4, 99, Some(6), false)).unwrap();
+        // Meeting its minimum, it fills and rests the rest
+        handle_order_create(&mut manager, constrained(5, 101, Some(5), false)).unwrap();
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(101), Some(102)));
+        assert_eq!(
+            book.get_order(OrderId::from_u64(5))
+                .unwrap()
+                .visible_quantity(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_sweeps_beyond_the_limit_are_split() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        for (order_id, price) in [(1, 100), (2, 101), (3, 102)] {
+            handle_order_create(
+                &mut manager,
+                order(order_id, Side::Sell, price, 5, TimeInForce::Gtc),
+            )
+            .unwrap();
+        }
+        manager
+            .get_book_mut("BTC")
+            .unwrap()
+            .set_sweep_limit(Some(SweepLimit {
+                max_levels: Some(2),
+                max_quantity: None,
+                remainder: SweepRemainder::Requeue,
+            }));
+
+        // Within the limit, nothing changes
+        let mut small = order(4, Side::Buy, 102, 8, TimeInForce::Gtc);
+        assert!(cap_sweep(&manager, &mut small).unwrap().is_none());
+        assert_eq!((small.quantity, small.time_in_force), (8, TimeInForce::Gtc));
+
+        let fok = || order(5, Side::Buy, 102, 15, TimeInForce::Fok);
+        let rejection = cap_sweep(&manager, &mut fok()).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::InvalidOrder);
+
+        // Two levels now, the rest entered again later
+        let mut large = order(5, Side::Buy, 102, 18, TimeInForce::Gtc);
+        let remainder = cap_sweep(&manager, &mut large).unwrap().unwrap();
+        assert_eq!(
+            (large.quantity, large.time_in_force),
+            (10, TimeInForce::Ioc)
+        );
+        assert_eq!(
+            (remainder.quantity, remainder.time_in_force),
+            (8, TimeInForce::Gtc)
+        );
+        handle_order_create(&mut manager, large).unwrap();
+        assert_eq!(manager.get_book("BTC").unwrap().best_ask(), Some(102));
+        assert!(
+            cap_sweep(&manager, &mut remainder.clone())
+                .unwrap()
+                .is_none()
+        );
+        handle_order_create(&mut manager, remainder).unwrap();
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(102), None));
+
+        // A command's own limit tightens the instrument's, and cancels the rest
+        handle_order_create(&mut manager, order(6, Side::Buy, 101, 5, TimeInForce::Gtc)).unwrap();
+        let mut capped = OrderCreatePayload {
+            sweep_limit: Some(SweepLimit {
+                max_quantity: Some(4),
+                ..SweepLimit::default()
+            }),
+            ..order(7, Side::Sell, 101, 10, TimeInForce::Gtc)
+        };
+        assert!(cap_sweep(&manager, &mut capped).unwrap().is_none());
+        assert_eq!(capped.quantity, 4);
+    }
+
+    #[test]
+    fn test_replacements_keep_what_the_replace_does_not_set() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        let mut resting = order(1, Side::Buy, 99, 5, TimeInForce::Day);
+        resting.tags = OrderTags::from([("strategy".to_string(), "twap".to_string())]);
+        resting.client_order_id = Some("abc".to_string());
+        handle_order_create(&mut manager, resting).unwrap();
+        let replace = |side| OrderReplacePayload {
+            instrument_id: "BTC".to_string(),
+            order_id: 1,
+            client_order_id: None,
+            participant_id: None,
+            new_client_order_id: Some("abd".to_string()),
+            price: 101,
+            quantity: 7,
+            side,
+            time_in_force: None,
+            sequence: None,
+        };
+
+        let (cancel, order) = split_order_replace(&manager, replace(None)).unwrap();
+        assert_eq!(cancel.order_id, 1);
+        assert_eq!(
+            (order.side, order.time_in_force, order.price, order.quantity),
+            (Side::Buy, TimeInForce::Day, 101, 7)
+        );
+        assert_eq!(order.tags["strategy"], "twap");
+        assert_eq!(order.client_order_id.as_deref(), Some("abd"));
+        assert_eq!(order.order_type, OrderType::LIMIT);
+
+        let (_, order) = split_order_replace(&manager, replace(Some(Side::Sell))).unwrap();
+        assert_eq!(order.side, Side::Sell);
+        let unknown = OrderReplacePayload {
+            order_id: 2,
+            ..replace(None)
+        };
+        assert_eq!(
+            split_order_replace(&manager, unknown).unwrap_err().reason,
+            RejectReason::UnknownOrder
+        );
+    }
+
+    #[test]
+    fn test_cancels_and_modifies_of_unknown_orders_are_rejected() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        let cancel = |order_id| OrderCancelPayload {
+            order_id,
+            instrument_id: "BTC".to_string(),
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        };
+        let modify = |order_id| OrderModifyPayload {
+            instrument_id: "BTC".to_string(),
+            order_id,
+            price: 101,
+            quantity: 5,
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        };
+        let reason = |outcome: Result<(), Rejection>| outcome.unwrap_err().reason;
+        assert_eq!(
+            reason(handle_order_cancel(&mut manager, cancel(1))),
+            RejectReason::UnknownInstrument
+        );
+
+        handle_order_create(&mut manager, order(1, Side::Sell, 100, 5, TimeInForce::Gtc)).unwrap();
+        assert_eq!(
+            reason(handle_order_modify(&mut manager, modify(2))),
+            RejectReason::UnknownOrder
+        );
+        handle_order_modify(&mut manager, modify(1)).unwrap();
+        handle_order_cancel(&mut manager, cancel(1)).unwrap();
+        assert_eq!(
+            reason(handle_order_cancel(&mut manager, cancel(1))),
+            RejectReason::UnknownOrder
         );
     }
 }
@@ -0,0 +1,172 @@
+use super::{RfqExecutePayload, RfqQuotePayload, RfqRequestPayload};
+use crate::config::rfq::RfqConfig;
+use crate::orderbook::manager::BookManager;
+use crate::orderbook::manager::BookManagerStd;
+use crate::orderbook::rfq::{RfqExecution, RfqExpiry, RfqManager, RfqQuote, RfqRequest};
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Lifecycle events published for RFQs
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RfqEvent<'a> {
+    Opened(&'a RfqRequest),
+    Executed(&'a RfqExecution),
+    Expired(&'a RfqRequest),
+    Rejected { rfq_id: &'a str, reason: String },
+}
+
+fn publish_event(publisher: &Publisher, config: &RfqConfig, rfq_id: &str, event: &RfqEvent) {
+    publisher.publish(&config.events_topic, rfq_id, event);
+}
+
+fn reject(publisher: &Publisher, config: &RfqConfig, rfq_id: &str, reason: String) {
+    warn!("RFQ {} rejected: {}", rfq_id, reason);
+    publish_event(
+        publisher,
+        config,
+        rfq_id,
+        &RfqEvent::Rejected { rfq_id, reason },
+    );
+}
+
+pub fn handle_rfq_request(
+    manager: &BookManagerStd<OrderTags>,
+    rfqs: &mut RfqManager,
+    publisher: &Publisher,
+    config: &RfqConfig,
+    payload: RfqRequestPayload,
+) {
+    if !manager.has_book(&payload.instrument_id) {
+        let reason = format!("Unknown instrument {}", payload.instrument_id);
+        reject(publisher, config, &payload.rfq_id, reason);
+        return;
+    }
+    if payload.quantity == 0 {
+        reject(
+            publisher,
+            config,
+            &payload.rfq_id,
+            "Quantity must be positive".to_string(),
+        );
+        return;
+    }
+    let window_ms = payload
+        .window_ms
+        .unwrap_or(config.default_window_ms)
+        .min(config.max_window_ms);
+    let request = RfqRequest {
+        rfq_id: payload.rfq_id,
+        instrument_id: payload.instrument_id,
+        requester_id: payload.requester_id,
+        side: payload.side,
+        quantity: payload.quantity,
+        expires_at: current_time_millis() + window_ms,
+        auto_execute: payload.auto_execute,
+    };
+    let opened = request.clone();
+    match rfqs.open(request) {
+        Ok(()) => {
+            info!("Opened RFQ {} for {}", opened.rfq_id, opened.instrument_id);
+            publish_event(
+                publisher,
+                config,
+                &opened.rfq_id,
+                &RfqEvent::Opened(&opened),
+            );
+        }
+        Err(e) => reject(publisher, config, &opened.rfq_id, e.to_string()),
+    }
+}
+
+pub fn handle_rfq_quote(rfqs: &mut RfqManager, payload: RfqQuotePayload) {
+    let quote = RfqQuote {
+        rfq_id: payload.rfq_id,
+        quote_id: payload.quote_id,
+        dealer_id: payload.dealer_id,
+        price: payload.price,
+        quantity: payload.quantity,
+        received_at: current_time_millis(),
+    };
+    let (rfq_id, quote_id) = (quote.rfq_id.clone(), quote.quote_id.clone());
+    match rfqs.submit_quote(quote) {
+        Ok(()) => info!("Accepted quote {} for RFQ {}", quote_id, rfq_id),
+        // Rejected quotes are only logged; other dealers must not learn about them
+        Err(e) => warn!("Rejected quote {} for RFQ {}: {}", quote_id, rfq_id, e),
+    }
+}
+
+pub fn handle_rfq_execute(
+    rfqs: &mut RfqManager,
+    publisher: &Publisher,
+    config: &RfqConfig,
+    payload: RfqExecutePayload,
+) -> Option<RfqExecution> {
+    match rfqs.execute(
+        &payload.rfq_id,
+        &payload.requester_id,
+        payload.quote_id.as_deref(),
+        current_time_millis(),
+    ) {
+        Ok(execution) => {
+            info!(
+                "Executed RFQ {}: {} {} @ {} with {}",
+                execution.rfq_id,
+                execution.side,
+                execution.quantity,
+                execution.price,
+                execution.dealer_id
+            );
+            publish_event(
+                publisher,
+                config,
+                &execution.rfq_id,
+                &RfqEvent::Executed(&execution),
+            );
+            Some(execution)
+        }
+        Err(e) => {
+            reject(publisher, config, &payload.rfq_id, e.to_string());
+            None
+        }
+    }
+}
+
+/// Closes RFQs whose quoting window has ended, returning auto-executions
+pub fn sweep_rfqs(
+    rfqs: &mut RfqManager,
+    publisher: &Publisher,
+    config: &RfqConfig,
+) -> Vec<RfqExecution> {
+    let mut executions = Vec::new();
+    for expiry in rfqs.expire(current_time_millis()) {
+        match expiry {
+            RfqExpiry::Executed(execution) => {
+                info!(
+                    "Auto-executed RFQ {} at {}",
+                    execution.rfq_id, execution.price
+                );
+                publish_event(
+                    publisher,
+                    config,
+                    &execution.rfq_id,
+                    &RfqEvent::Executed(&execution),
+                );
+                executions.push(execution);
+            }
+            RfqExpiry::Expired(request) => {
+                info!("RFQ {} expired without execution", request.rfq_id);
+                publish_event(
+                    publisher,
+                    config,
+                    &request.rfq_id,
+                    &RfqEvent::Expired(&request),
+                );
+            }
+        }
+    }
+    executions
+}
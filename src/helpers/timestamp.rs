@@ -0,0 +1,267 @@
+// src/helpers/timestamp.rs
+//! Upstream timestamps normalized into Unix milliseconds
+//!
+//! Payload times may be ISO-8601 strings, epoch seconds or epoch milliseconds,
+//! the latter two as numbers or numeric strings. Integers are told apart by
+//! magnitude: below 10^11 they are seconds, since 10^11 milliseconds falls in
+//! 1973 and 10^11 seconds in the year 5138. Whatever the format, a time before
+//! 2000 or from 2200 on is rejected rather than misread, which catches zero,
+//! microseconds and nanoseconds.
+
+use pricelevel::TimeInForce;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+/// Integers below this are epoch seconds, others epoch milliseconds
+const SECONDS_BELOW: u64 = 100_000_000_000;
+/// 2000-01-01T00:00:00Z
+const EARLIEST_MILLIS: u64 = 946_684_800_000;
+/// 2200-01-01T00:00:00Z
+const LATEST_MILLIS: u64 = 7_258_118_400_000;
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Unix milliseconds of an epoch timestamp in seconds or milliseconds
+pub fn normalize(value: u64) -> Result<u64, String> {
+    let millis = if value < SECONDS_BELOW {
+        value * 1_000
+    } else {
+        value
+    };
+    within_range(millis).ok_or_else(|| format!("timestamp {value} is not between 2000 and 2200"))
+}
+
+/// Unix milliseconds of an ISO-8601 time, or of an epoch timestamp in
+/// seconds or milliseconds written as a string
+pub fn parse(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    if let Ok(value) = text.parse::<u64>() {
+        return normalize(value);
+    }
+    let millis = parse_iso8601(text)
+        .ok_or_else(|| format!("{text:?} is neither an ISO-8601 time nor an epoch timestamp"))?;
+    u64::try_from(millis)
+        .ok()
+        .and_then(within_range)
+        .ok_or_else(|| format!("{text} is not between 2000 and 2200"))
+}
+
+fn within_range(millis: u64) -> Option<u64> {
+    (EARLIEST_MILLIS..LATEST_MILLIS)
+        .contains(&millis)
+        .then_some(millis)
+}
+
+/// Milliseconds since the epoch of `YYYY-MM-DD`, taken as midnight UTC, or of
+/// `YYYY-MM-DDTHH:MM:SS[.fff]` followed by `Z` or an offset from UTC; a time
+/// without either is ambiguous and refused
+fn parse_iso8601(text: &str) -> Option<i64> {
+    let (date, time) = match text.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let mut fields = date.splitn(3, '-');
+    let year = digits(fields.next()?, 4)?;
+    let month = digits(fields.next()?, 2)?;
+    let day = digits(fields.next()?, 2)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let midnight = days_from_civil(year, month, day) * MILLIS_PER_DAY;
+    let Some(time) = time else {
+        return Some(midnight);
+    };
+
+    let (clock, offset_minutes) = match time.strip_suffix(['Z', 'z']) {
+        Some(clock) => (clock, 0),
+        None => {
+            let (clock, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let offset = &offset[1..];
+            let (hours, minutes) = offset
+                .split_once(':')
+                .or_else(|| offset.split_at_checked(2))?;
+            (clock, sign * (digits(hours, 2)? * 60 + digits(minutes, 2)?))
+        }
+    };
+    let (clock, fraction) = match clock.split_once('.') {
+        Some((clock, fraction))
+            if !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            (clock, fraction)
+        }
+        Some(_) => return None,
+        None => (clock, ""),
+    };
+    let mut fields = clock.splitn(3, ':');
+    let hour = digits(fields.next()?, 2)?;
+    let minute = digits(fields.next()?, 2)?;
+    let second = digits(fields.next()?, 2)?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let millis: i64 = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        .parse()
+        .ok()?;
+    Some(midnight + ((hour * 60 + minute) * 60 + second) * 1_000 + millis - offset_minutes * 60_000)
+}
+
+/// The value of exactly `len` ASCII digits
+fn digits(text: &str, len: usize) -> Option<i64> {
+    if text.len() != len || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// A timestamp as sent, a number or a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTimestamp {
+    Number(u64),
+    Text(String),
+}
+
+impl RawTimestamp {
+    fn millis(self) -> Result<u64, String> {
+        match self {
+            RawTimestamp::Number(value) => normalize(value),
+            RawTimestamp::Text(text) => parse(&text),
+        }
+    }
+}
+
+/// `TimeInForce` as sent, its GTD expiry in any accepted format
+#[derive(Deserialize)]
+enum RawTimeInForce {
+    #[serde(alias = "gtc", alias = "GTC")]
+    Gtc,
+    #[serde(alias = "ioc", alias = "IOC")]
+    Ioc,
+    #[serde(alias = "fok", alias = "FOK")]
+    Fok,
+    #[serde(alias = "gtd", alias = "GTD")]
+    Gtd(RawTimestamp),
+    #[serde(alias = "day", alias = "DAY")]
+    Day,
+}
+
+impl RawTimeInForce {
+    fn normalized(self) -> Result<TimeInForce, String> {
+        Ok(match self {
+            RawTimeInForce::Gtc => TimeInForce::Gtc,
+            RawTimeInForce::Ioc => TimeInForce::Ioc,
+            RawTimeInForce::Fok => TimeInForce::Fok,
+            RawTimeInForce::Gtd(expiry) => TimeInForce::Gtd(expiry.millis()?),
+            RawTimeInForce::Day => TimeInForce::Day,
+        })
+    }
+}
+
+/// Deserializes a timestamp in any accepted format into Unix milliseconds
+pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    RawTimestamp::deserialize(deserializer)?
+        .millis()
+        .map_err(D::Error::custom)
+}
+
+/// Deserializes a `TimeInForce` whose GTD expiry may be in any accepted format
+pub fn time_in_force<'de, D>(deserializer: D) -> Result<TimeInForce, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    RawTimeInForce::deserialize(deserializer)?
+        .normalized()
+        .map_err(D::Error::custom)
+}
+
+/// `time_in_force` for an optional field
+pub fn optional_time_in_force<'de, D>(deserializer: D) -> Result<Option<TimeInForce>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<RawTimeInForce>::deserialize(deserializer)?
+        .map(RawTimeInForce::normalized)
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_normalize_to_the_same_millis() {
+        let millis = 1_700_000_000_000;
+        for text in [
+            "1700000000",
+            "1700000000000",
+            "2023-11-14T22:13:20Z",
+            "2023-11-14T22:13:20.000Z",
+            "2023-11-15T00:13:20+02:00",
+            "2023-11-14 17:13:20-0500",
+        ] {
+            assert_eq!(parse(text), Ok(millis), "{text}");
+        }
+        assert_eq!(normalize(1_700_000_000), Ok(millis));
+        assert_eq!(normalize(millis), Ok(millis));
+        assert_eq!(parse("2024-02-29"), Ok(1_709_164_800_000));
+        assert_eq!(parse("2023-11-14T22:13:20.5Z"), Ok(millis + 500));
+
+        for absurd in [
+            "0",
+            "1700000000000000",
+            "1999-12-31T23:59:59Z",
+            "2200-01-01",
+            "2023-02-29",
+            "2023-11-14T22:13:20",
+            "2023-11-14T24:00:00Z",
+            "yesterday",
+        ] {
+            assert!(parse(absurd).is_err(), "{absurd}");
+        }
+
+        #[derive(Deserialize)]
+        struct Order {
+            #[serde(deserialize_with = "time_in_force")]
+            time_in_force: TimeInForce,
+        }
+        let gtd = |json: &str| {
+            serde_json::from_str::<Order>(json)
+                .map(|order| order.time_in_force)
+                .map_err(|_| ())
+        };
+        assert_eq!(
+            gtd(r#"{"time_in_force": {"GTD": "2023-11-14T22:13:20Z"}}"#),
+            Ok(TimeInForce::Gtd(millis))
+        );
+        assert_eq!(
+            gtd(r#"{"time_in_force": {"Gtd": 1700000000}}"#),
+            Ok(TimeInForce::Gtd(millis))
+        );
+        assert_eq!(gtd(r#"{"time_in_force": "IOC"}"#), Ok(TimeInForce::Ioc));
+        assert!(gtd(r#"{"time_in_force": {"GTD": 5}}"#).is_err());
+    }
+}
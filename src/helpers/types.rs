@@ -1,52 +1,803 @@
+use crate::config::features::Feature;
+use crate::config::sinks::SinkFilter;
+use crate::config::topics::CommandKind;
+use crate::funding::Perpetual;
+use crate::helpers::timestamp;
+use crate::orderbook::block_trade::BlockTradeRules;
+use crate::orderbook::corporate_action::CorporateAction;
+use crate::orderbook::index::IndexDefinition;
+use crate::orderbook::instrument_spec::InstrumentSpec;
+use crate::orderbook::mark::MarkPricing;
+use crate::orderbook::market_impact::ImpactModel;
+use crate::orderbook::matching_policy::MatchingPolicy;
+use crate::orderbook::pegging::{PegMove, PegRepricing};
+use crate::orderbook::protection::MarketProtection;
+use crate::orderbook::scale::InstrumentScale;
+use crate::orderbook::sweep_limit::SweepLimit;
+use crate::price_bands::PriceBand;
+use crate::tags::OrderTags;
 use pricelevel::Side;
 use pricelevel::TimeInForce;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Deserialize)]
 pub enum EngineCommand {
     InstrumentCreate(InstrumentCreatePayload),
     InstrumentDelete(DeleteInstrumentPayload),
+    InstrumentAdjust(InstrumentAdjustPayload),
     OrderCreate(OrderCreatePayload),
     OrderCancel(OrderCancelPayload),
     OrderModify(OrderModifyPayload),
+    OrderReplace(OrderReplacePayload),
+    MassCancel(MassCancelPayload),
+    KillSwitch(KillSwitchPayload),
+    Halt(TradingHaltPayload),
+    Resume(TradingHaltPayload),
+    AuctionStart(AuctionPayload),
+    AuctionUncross(AuctionPayload),
+    Admin(AdminCommandPayload),
+    TheoreticalPrice(TheoreticalPricePayload),
+    IndexDefine(IndexDefinePayload),
+    RfqRequest(RfqRequestPayload),
+    RfqQuote(RfqQuotePayload),
+    RfqExecute(RfqExecutePayload),
+    BlockTrade(BlockTradePayload),
+    OmsHeartbeat(OmsHeartbeatPayload),
 }
 
+impl EngineCommand {
+    /// Parses a message received on the topic of `kind`; alerts are not engine commands
+    pub fn parse(kind: CommandKind, payload: &str) -> Result<Option<Self>, serde_json::Error> {
+        if kind == CommandKind::Alert {
+            return Ok(None);
+        }
+        let mut deserializer = serde_json::Deserializer::from_str(payload);
+        let command = Self::deserialize_as(kind, &mut deserializer)?;
+        deserializer.end()?;
+        Ok(command)
+    }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+    /// Maps a message already decoded into JSON values, e.g. from Avro, the way
+    /// `parse` maps JSON text
+    pub fn from_value(
+        kind: CommandKind,
+        payload: serde_json::Value,
+    ) -> Result<Option<Self>, serde_json::Error> {
+        Self::deserialize_as(kind, payload)
+    }
+
+    fn deserialize_as<'de, D: Deserializer<'de>>(
+        kind: CommandKind,
+        deserializer: D,
+    ) -> Result<Option<Self>, D::Error> {
+        let command = match kind {
+            CommandKind::Alert => return Ok(None),
+            CommandKind::InstrumentCreate => {
+                EngineCommand::InstrumentCreate(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::InstrumentDelete => {
+                EngineCommand::InstrumentDelete(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::InstrumentAdjust => {
+                EngineCommand::InstrumentAdjust(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::OrderCreate => {
+                EngineCommand::OrderCreate(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::OrderCancel => {
+                EngineCommand::OrderCancel(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::OrderModify => {
+                EngineCommand::OrderModify(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::OrderReplace => {
+                EngineCommand::OrderReplace(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::MassCancel => {
+                EngineCommand::MassCancel(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::KillSwitch => {
+                EngineCommand::KillSwitch(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::Halt => EngineCommand::Halt(Deserialize::deserialize(deserializer)?),
+            CommandKind::Resume => EngineCommand::Resume(Deserialize::deserialize(deserializer)?),
+            CommandKind::AuctionStart => {
+                EngineCommand::AuctionStart(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::AuctionUncross => {
+                EngineCommand::AuctionUncross(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::Admin => EngineCommand::Admin(Deserialize::deserialize(deserializer)?),
+            CommandKind::TheoreticalPrice => {
+                EngineCommand::TheoreticalPrice(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::IndexDefine => {
+                EngineCommand::IndexDefine(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::RfqRequest => {
+                EngineCommand::RfqRequest(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::RfqQuote => {
+                EngineCommand::RfqQuote(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::RfqExecute => {
+                EngineCommand::RfqExecute(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::BlockTrade => {
+                EngineCommand::BlockTrade(Deserialize::deserialize(deserializer)?)
+            }
+            CommandKind::OmsHeartbeat => {
+                EngineCommand::OmsHeartbeat(Deserialize::deserialize(deserializer)?)
+            }
+        };
+        Ok(Some(command))
+    }
+
+    /// Topic kind this command arrives on
+    pub fn kind(&self) -> CommandKind {
+        match self {
+            EngineCommand::InstrumentCreate(_) => CommandKind::InstrumentCreate,
+            EngineCommand::InstrumentDelete(_) => CommandKind::InstrumentDelete,
+            EngineCommand::InstrumentAdjust(_) => CommandKind::InstrumentAdjust,
+            EngineCommand::OrderCreate(_) => CommandKind::OrderCreate,
+            EngineCommand::OrderCancel(_) => CommandKind::OrderCancel,
+            EngineCommand::OrderModify(_) => CommandKind::OrderModify,
+            EngineCommand::OrderReplace(_) => CommandKind::OrderReplace,
+            EngineCommand::MassCancel(_) => CommandKind::MassCancel,
+            EngineCommand::KillSwitch(_) => CommandKind::KillSwitch,
+            EngineCommand::Halt(_) => CommandKind::Halt,
+            EngineCommand::Resume(_) => CommandKind::Resume,
+            EngineCommand::AuctionStart(_) => CommandKind::AuctionStart,
+            EngineCommand::AuctionUncross(_) => CommandKind::AuctionUncross,
+            EngineCommand::Admin(_) => CommandKind::Admin,
+            EngineCommand::TheoreticalPrice(_) => CommandKind::TheoreticalPrice,
+            EngineCommand::IndexDefine(_) => CommandKind::IndexDefine,
+            EngineCommand::RfqRequest(_) => CommandKind::RfqRequest,
+            EngineCommand::RfqQuote(_) => CommandKind::RfqQuote,
+            EngineCommand::RfqExecute(_) => CommandKind::RfqExecute,
+            EngineCommand::BlockTrade(_) => CommandKind::BlockTrade,
+            EngineCommand::OmsHeartbeat(_) => CommandKind::OmsHeartbeat,
+        }
+    }
+
+    /// The payload as JSON values, which `from_value` with `kind` maps back
+    pub fn to_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        match self {
+            EngineCommand::InstrumentCreate(p) => serde_json::to_value(p),
+            EngineCommand::InstrumentDelete(p) => serde_json::to_value(p),
+            EngineCommand::InstrumentAdjust(p) => serde_json::to_value(p),
+            EngineCommand::OrderCreate(p) => serde_json::to_value(p),
+            EngineCommand::OrderCancel(p) => serde_json::to_value(p),
+            EngineCommand::OrderModify(p) => serde_json::to_value(p),
+            EngineCommand::OrderReplace(p) => serde_json::to_value(p),
+            EngineCommand::MassCancel(p) => serde_json::to_value(p),
+            EngineCommand::KillSwitch(p) => serde_json::to_value(p),
+            EngineCommand::Halt(p) | EngineCommand::Resume(p) => serde_json::to_value(p),
+            EngineCommand::AuctionStart(p) | EngineCommand::AuctionUncross(p) => {
+                serde_json::to_value(p)
+            }
+            EngineCommand::Admin(p) => serde_json::to_value(p),
+            EngineCommand::TheoreticalPrice(p) => serde_json::to_value(p),
+            EngineCommand::IndexDefine(p) => serde_json::to_value(p),
+            EngineCommand::RfqRequest(p) => serde_json::to_value(p),
+            EngineCommand::RfqQuote(p) => serde_json::to_value(p),
+            EngineCommand::RfqExecute(p) => serde_json::to_value(p),
+            EngineCommand::BlockTrade(p) => serde_json::to_value(p),
+            EngineCommand::OmsHeartbeat(p) => serde_json::to_value(p),
+        }
+    }
+
+    /// Instrument targeted by this command, if any
+    /// Position of the command in the sequence of its instrument, for the
+    /// commands that have one
+    pub fn sequence(&self) -> Option<u64> {
+        match self {
+            EngineCommand::InstrumentCreate(p) => p.sequence,
+            EngineCommand::InstrumentDelete(p) => p.sequence,
+            EngineCommand::InstrumentAdjust(p) => p.sequence,
+            EngineCommand::OrderCreate(p) => p.sequence,
+            EngineCommand::OrderCancel(p) => p.sequence,
+            EngineCommand::OrderModify(p) => p.sequence,
+            EngineCommand::OrderReplace(p) => p.sequence,
+            EngineCommand::MassCancel(p) => p.sequence,
+            EngineCommand::Halt(p) | EngineCommand::Resume(p) => p.sequence,
+            EngineCommand::AuctionStart(p) | EngineCommand::AuctionUncross(p) => p.sequence,
+            EngineCommand::TheoreticalPrice(p) => p.sequence,
+            EngineCommand::RfqRequest(p) => p.sequence,
+            EngineCommand::BlockTrade(p) => p.sequence,
+            EngineCommand::Admin(_)
+            | EngineCommand::IndexDefine(_)
+            | EngineCommand::RfqQuote(_)
+            | EngineCommand::RfqExecute(_)
+            | EngineCommand::KillSwitch(_)
+            | EngineCommand::OmsHeartbeat(_) => None,
+        }
+    }
+
+    pub fn instrument_id(&self) -> Option<&str> {
+        match self {
+            EngineCommand::InstrumentCreate(p) => Some(&p.instrument_id),
+            EngineCommand::InstrumentDelete(p) => Some(&p.instrument_id),
+            EngineCommand::InstrumentAdjust(p) => Some(&p.instrument_id),
+            EngineCommand::OrderCreate(p) => Some(&p.instrument_id),
+            EngineCommand::OrderCancel(p) => Some(&p.instrument_id),
+            EngineCommand::OrderModify(p) => Some(&p.instrument_id),
+            EngineCommand::OrderReplace(p) => Some(&p.instrument_id),
+            EngineCommand::MassCancel(p) => p.instrument_id.as_deref(),
+            EngineCommand::Halt(p) | EngineCommand::Resume(p) => Some(&p.instrument_id),
+            EngineCommand::AuctionStart(p) | EngineCommand::AuctionUncross(p) => {
+                Some(&p.instrument_id)
+            }
+            EngineCommand::Admin(p) => p.instrument_id(),
+            EngineCommand::TheoreticalPrice(p) => Some(&p.instrument_id),
+            EngineCommand::RfqRequest(p) => Some(&p.instrument_id),
+            EngineCommand::BlockTrade(p) => Some(&p.instrument_id),
+            EngineCommand::IndexDefine(_)
+            | EngineCommand::RfqQuote(_)
+            | EngineCommand::RfqExecute(_)
+            | EngineCommand::KillSwitch(_)
+            | EngineCommand::OmsHeartbeat(_) => None,
+        }
+    }
+
+    /// Whether this command enters a liquidation order, which takes the
+    /// priority lane to the engine
+    pub fn is_liquidation(&self) -> bool {
+        matches!(self, EngineCommand::OrderCreate(order) if order.liquidation)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderType {
     MARKET,
     LIMIT,
+    /// A limit order displaying `visible_quantity` at a time, refreshed from
+    /// its hidden reserve as the displayed part fills
+    ICEBERG,
+    /// Matches at the best opposite price only, resting what does not fill
+    /// there as a limit order at that price; the payload's price is ignored
+    #[allow(non_camel_case_types)]
+    MARKET_TO_LIMIT,
 }
-#[derive(Debug, Deserialize)]
+
+impl OrderType {
+    /// Whether the order takes its price from the book rather than the payload
+    pub fn is_priced_by_book(self) -> bool {
+        matches!(self, OrderType::MARKET | OrderType::MARKET_TO_LIMIT)
+    }
+}
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DeleteInstrumentPayload {
     pub instrument_id: String,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+/// Halts or resumes trading on an instrument
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TradingHaltPayload {
+    pub instrument_id: String,
+    /// Logged with the halt or resume, e.g. a news event
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+/// Starts a call auction on an instrument, or uncrosses it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuctionPayload {
+    pub instrument_id: String,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
 }
 
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct InstrumentCreatePayload {
     pub instrument_id: String,
+    /// Enables the book's flight recorder with this many events when set
+    #[serde(default)]
+    pub flight_recorder_capacity: Option<usize>,
+    /// Keeps this many recent trades on the book's trade tape when set
+    #[serde(default)]
+    pub trade_tape_capacity: Option<usize>,
+    /// Impact model reported alongside book-walk market impact for this instrument
+    #[serde(default)]
+    pub impact_model: Option<ImpactModel>,
+    /// Rules for off-book block trades reported on `trade.block`
+    #[serde(default)]
+    pub block_trade_rules: Option<BlockTradeRules>,
+    /// Sources and previous close the mark price falls back along when the
+    /// book has an empty side
+    #[serde(default)]
+    pub mark_pricing: Option<MarkPricing>,
+    /// How incoming orders are shared between the orders resting at a price
+    #[serde(default)]
+    pub matching_policy: MatchingPolicy,
+    /// Moves pegged orders after their reference when set; they otherwise
+    /// rest at the price they were entered at
+    #[serde(default)]
+    pub peg_repricing: Option<PegRepricing>,
+    /// Bounds how far market orders may sweep; unbounded when absent
+    #[serde(default)]
+    pub market_protection: Option<MarketProtection>,
+    /// Minimum notional (price times quantity) of a single fill
+    #[serde(default)]
+    pub min_fill_notional: Option<u64>,
+    /// Bounds how far a single order may sweep; unbounded when absent
+    #[serde(default)]
+    pub sweep_limit: Option<SweepLimit>,
+    /// Expiry of a dated contract; the book is settled and halted when it passes
+    #[serde(default)]
+    pub expiry: Option<InstrumentExpiry>,
+    /// Decimal places used to render prices and quantities in outbound messages
+    #[serde(default)]
+    pub scale: Option<InstrumentScale>,
+    /// Prices limit orders may be entered at; unbounded when absent
+    #[serde(default)]
+    pub price_band: Option<PriceBand>,
+    /// Makes the instrument a perpetual, funded against an index
+    #[serde(default)]
+    pub perpetual: Option<Perpetual>,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
+    /// Tick size, lot size and quantity limits orders are checked against
+    #[serde(flatten)]
+    pub spec: InstrumentSpec,
 }
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstrumentExpiry {
+    /// Unix timestamp in milliseconds at which the contract expires; sent as
+    /// an ISO-8601 time, epoch seconds or epoch millis
+    #[serde(deserialize_with = "timestamp::deserialize")]
+    pub expires_at: u64,
+    /// Next contract receiving the open interest attribution at expiry
+    #[serde(default)]
+    pub roll_to: Option<String>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TheoreticalPricePayload {
+    pub instrument_id: String,
+    /// Fair value posted by an upstream pricing model, in price units
+    pub price: f64,
+    /// Overrides the default deviation alert threshold for this instrument
+    #[serde(default)]
+    pub alert_threshold_bps: Option<f64>,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexDefinePayload {
+    #[serde(flatten)]
+    pub definition: IndexDefinition,
+    /// Instruments whose pegged orders track this index instead of their own mid
+    #[serde(default)]
+    pub pegged_instruments: Vec<String>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RfqRequestPayload {
+    pub rfq_id: String,
+    pub instrument_id: String,
+    pub requester_id: String,
+    pub side: Side,
+    pub quantity: u64,
+    /// Quoting window in milliseconds; the configured default applies when absent
+    #[serde(default)]
+    pub window_ms: Option<u64>,
+    /// Execute against the best quote when the window closes
+    #[serde(default)]
+    pub auto_execute: bool,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RfqQuotePayload {
+    pub rfq_id: String,
+    pub quote_id: String,
+    pub dealer_id: String,
+    pub price: u64,
+    pub quantity: u64,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RfqExecutePayload {
+    pub rfq_id: String,
+    pub requester_id: String,
+    /// Quote to execute against; the best quote is used when absent
+    #[serde(default)]
+    pub quote_id: Option<String>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstrumentAdjustPayload {
+    pub instrument_id: String,
+    /// Upstream identifier of the corporate action, echoed in adjustment events
+    #[serde(default)]
+    pub action_id: Option<String>,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
+    #[serde(flatten)]
+    pub action: CorporateAction,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockTradePayload {
+    pub trade_id: String,
+    pub instrument_id: String,
+    pub buyer_id: String,
+    pub seller_id: String,
+    pub price: u64,
+    pub quantity: u64,
+    /// Position of the command in its instrument's sequence
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderCreatePayload {
     pub order_id: u64,
     pub instrument_id: String,
     pub quantity: u64,
     pub price: u64,
     pub side: Side,
+    /// A GTD expiry may be an ISO-8601 time, epoch seconds or epoch millis
+    #[serde(deserialize_with = "timestamp::time_in_force")]
     pub time_in_force: TimeInForce,
     pub order_type: OrderType,
+    /// Participant owning the order, reported to clearing
+    #[serde(default)]
+    pub participant_id: Option<String>,
+    /// Upstream OMS that sent the order; its resting orders may be cancelled
+    /// when its heartbeats stop
+    #[serde(default)]
+    pub oms_id: Option<String>,
+    /// Id the participant's OMS knows the order by, unique among the
+    /// participant's resting orders
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    /// Metadata stored with the order and echoed in its order and trade events
+    #[serde(default)]
+    pub tags: OrderTags,
+    /// Quantity displayed at a time by an ICEBERG order, out of `quantity`
+    #[serde(default)]
+    pub visible_quantity: Option<u64>,
+    /// Reserve of an ICEBERG order, `quantity` less `visible_quantity` when
+    /// not given
+    #[serde(default)]
+    pub hidden_quantity: Option<u64>,
+    /// Order of the risk engine closing out a position: it is applied ahead of
+    /// regular order flow, exempt from the order-to-trade throttle and price
+    /// bands, and its trades are marked as liquidations
+    #[serde(default)]
+    pub liquidation: bool,
+    /// Least quantity that must fill on entry for the order to trade at all;
+    /// what rests afterwards is an ordinary order
+    #[serde(default)]
+    pub min_quantity: Option<u64>,
+    /// The order trades only if all of it fills on entry, and never rests
+    #[serde(default)]
+    pub all_or_none: bool,
+    /// Bounds how far the order may sweep, tightening its instrument's limit
+    #[serde(default)]
+    pub sweep_limit: Option<SweepLimit>,
+    /// Position of the command in its instrument's sequence, which also tells
+    /// it apart from a redelivery of an earlier one for the same order
+    #[serde(default)]
+    pub sequence: Option<u64>,
 }
-#[derive(Debug, Deserialize)]
+
+impl OrderCreatePayload {
+    /// Quantity that must fill on entry, if the order has a minimum, or why
+    /// its minimum is invalid
+    pub fn min_execution(&self) -> Result<Option<u64>, String> {
+        match (self.min_quantity, self.all_or_none) {
+            (Some(_), true) => {
+                Err("min_quantity and all_or_none cannot both be given".to_string())
+            }
+            (None, true) => Ok(Some(self.quantity)),
+            (Some(0), false) => Err("min_quantity must be positive".to_string()),
+            (Some(min), false) if min > self.quantity => Err(format!(
+                "min_quantity {} exceeds quantity {}",
+                min, self.quantity
+            )),
+            (min, false) => Ok(min),
+        }
+    }
+
+    /// Displayed and hidden quantities of an ICEBERG order, or why they do
+    /// not make up its quantity
+    pub fn iceberg_quantities(&self) -> Result<(u64, u64), String> {
+        let Some(visible) = self.visible_quantity.filter(|&visible| visible > 0) else {
+            return Err("an iceberg order needs a positive visible_quantity".to_string());
+        };
+        let Some(hidden) = self.quantity.checked_sub(visible) else {
+            return Err(format!(
+                "visible_quantity {} exceeds quantity {}",
+                visible, self.quantity
+            ));
+        };
+        match self.hidden_quantity {
+            Some(given) if given != hidden => Err(format!(
+                "visible_quantity {} and hidden_quantity {} do not add up to quantity {}",
+                visible, given, self.quantity
+            )),
+            _ => Ok((visible, hidden)),
+        }
+    }
+}
+/// What an OMS says about its session with the engine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OmsSessionStatus {
+    /// The OMS is up and its session stays open
+    #[default]
+    Alive,
+    /// The OMS is going away and leaves its orders resting
+    Logout,
+    /// The OMS is going away and its orders are cancelled at once, as they
+    /// would be once its heartbeats stop
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OmsHeartbeatPayload {
+    pub oms_id: String,
+    #[serde(default)]
+    pub status: OmsSessionStatus,
+}
+#[derive(Debug, Serialize, Deserialize)]
 pub struct OrderCancelPayload {
+    /// Ignored when `client_order_id` is given
+    #[serde(default)]
     pub order_id: u64,
     pub instrument_id: String,
+    /// Names the order by the id it was entered with, among the orders of
+    /// `participant_id`
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    #[serde(default)]
+    pub participant_id: Option<String>,
+    /// Position of the command in its instrument's sequence, which also tells
+    /// it apart from a redelivery of an earlier one for the same order
+    #[serde(default)]
+    pub sequence: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Cancels every resting order matching all the filters given, at least one
+/// of `instrument_id` and `participant_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MassCancelPayload {
+    /// Every instrument when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument_id: Option<String>,
+    /// Both sides when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+    /// Orders of every participant when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participant_id: Option<String>,
+    /// Position of the command in its instrument's sequence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+}
+
+impl MassCancelPayload {
+    /// Whether the mass cancel names an instrument or participant, rather
+    /// than every order of the engine
+    pub fn is_targeted(&self) -> bool {
+        self.instrument_id.is_some() || self.participant_id.is_some()
+    }
+}
+
+/// Whether a kill switch cuts a participant off or lets it trade again
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KillSwitchAction {
+    /// Cancel every resting order of the participant and reject its new ones
+    #[default]
+    Engage,
+    /// Accept the participant's orders again
+    Release,
+}
+
+/// Engages or releases the kill switch of a participant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillSwitchPayload {
+    pub participant_id: String,
+    #[serde(default)]
+    pub action: KillSwitchAction,
+}
+
+/// Published on `mass_cancel.summary_topic` once a mass cancel is applied
+#[derive(Debug, Serialize)]
+pub struct MassCancelSummary {
+    #[serde(flatten)]
+    pub request: MassCancelPayload,
+    pub orders_cancelled: usize,
+    pub quantity_cancelled: u64,
+    /// Orders cancelled on each instrument that had any
+    pub instruments: BTreeMap<String, usize>,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct OrderModifyPayload {
     pub instrument_id: String,
+    /// Ignored when `client_order_id` is given
+    #[serde(default)]
+    pub order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+    /// Names the order by the id it was entered with, among the orders of
+    /// `participant_id`
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    #[serde(default)]
+    pub participant_id: Option<String>,
+    /// Position of the command in its instrument's sequence, which also tells
+    /// it apart from a redelivery of an earlier one for the same order
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+
+/// Cancels a resting order and enters a new one in its place
+///
+/// Unlike a modify, the replacement may change side and time in force, and
+/// is matched again if its price crosses the book. Whatever it does not set is
+/// taken from the order it replaces.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderReplacePayload {
+    pub instrument_id: String,
+    /// Ignored when `client_order_id` is given; the replacement keeps it
+    #[serde(default)]
     pub order_id: u64,
+    /// Names the order to replace by the id it was entered with, among the
+    /// orders of `participant_id`
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    #[serde(default)]
+    pub participant_id: Option<String>,
+    /// Client order id of the replacement, which keeps the replaced order's
+    /// when not given
+    #[serde(default)]
+    pub new_client_order_id: Option<String>,
     pub price: u64,
     pub quantity: u64,
+    #[serde(default)]
+    pub side: Option<Side>,
+    #[serde(default, deserialize_with = "timestamp::optional_time_in_force")]
+    pub time_in_force: Option<TimeInForce>,
+    /// Position of the command in its instrument's sequence, which also tells
+    /// it apart from a redelivery of an earlier one for the same order
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+
+/// Operator commands received on the admin topic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum AdminCommandPayload {
+    EnableFlightRecorder {
+        instrument_id: String,
+        capacity: usize,
+    },
+    DisableFlightRecorder {
+        instrument_id: String,
+    },
+    DumpFlightEvents {
+        instrument_id: String,
+    },
+    /// Log the trades on an instrument's tape after a sequence number, or the
+    /// last trade when none is given
+    GetTrades {
+        instrument_id: String,
+        #[serde(default)]
+        since_sequence: Option<u64>,
+    },
+    AddCorrelationPair {
+        instrument_a: String,
+        instrument_b: String,
+    },
+    RemoveCorrelationPair {
+        instrument_a: String,
+        instrument_b: String,
+    },
+    GetCorrelations,
+    /// Write the clearing export now instead of waiting for end of day
+    RunClearingExport,
+    /// Reconstruct an instrument's depth at a past time from the book archive
+    BookAsOf {
+        instrument_id: String,
+        #[serde(deserialize_with = "timestamp::deserialize")]
+        timestamp: u64,
+    },
+    GetArchiveStats,
+    /// Log totals across every book of the engine, for a monitoring dashboard
+    GetGlobalStats,
+    /// Switch an experimental feature for one instrument, or by default for all
+    SetFeature {
+        feature: Feature,
+        enabled: bool,
+        instrument_id: Option<String>,
+    },
+    /// Drop an instrument's feature overrides, rolling it back to the defaults
+    ResetFeatures {
+        instrument_id: String,
+    },
+    GetFeatures,
+    /// Replace the filter of a configured event sink
+    SetSinkFilter {
+        sink: String,
+        filter: SinkFilter,
+    },
+    GetSinkFilters,
+    /// Replace the open order limits of a participant, or the default ones of
+    /// participants without limits of their own; a bound left out is lifted
+    SetParticipantLimits {
+        participant_id: Option<String>,
+        #[serde(default)]
+        max_open_orders: Option<usize>,
+        #[serde(default)]
+        max_open_notional: Option<u64>,
+    },
+    /// Stop consuming topics, or only the given partitions of them, until
+    /// resumed; applied by the consumer rather than the engine
+    PauseConsumption {
+        topics: Vec<String>,
+        #[serde(default)]
+        partitions: Vec<i32>,
+    },
+    /// Consume paused topics or partitions again
+    ResumeConsumption {
+        topics: Vec<String>,
+        #[serde(default)]
+        partitions: Vec<i32>,
+    },
+    /// Move pegged orders after their reference; logged by the engine when it
+    /// reprices on its own tick, so recovery makes the same moves
+    MovePeggedOrders {
+        instrument_id: String,
+        moves: Vec<PegMove>,
+    },
+    /// Start injecting faults, replacing any plan already in place
+    #[cfg(feature = "chaos")]
+    InjectFaults(crate::chaos::FaultPlan),
+    /// Stop injecting faults and restore the clock
+    #[cfg(feature = "chaos")]
+    ClearFaults,
+}
+
+impl AdminCommandPayload {
+    /// Instrument targeted by this admin command, if any
+    pub fn instrument_id(&self) -> Option<&str> {
+        match self {
+            AdminCommandPayload::EnableFlightRecorder { instrument_id, .. }
+            | AdminCommandPayload::DisableFlightRecorder { instrument_id }
+            | AdminCommandPayload::DumpFlightEvents { instrument_id }
+            | AdminCommandPayload::GetTrades { instrument_id, .. }
+            | AdminCommandPayload::ResetFeatures { instrument_id }
+            | AdminCommandPayload::MovePeggedOrders { instrument_id, .. } => Some(instrument_id),
+            AdminCommandPayload::SetFeature { instrument_id, .. } => instrument_id.as_deref(),
+            AdminCommandPayload::AddCorrelationPair { .. }
+            | AdminCommandPayload::RemoveCorrelationPair { .. }
+            | AdminCommandPayload::GetCorrelations
+            | AdminCommandPayload::RunClearingExport
+            | AdminCommandPayload::GetArchiveStats
+            | AdminCommandPayload::GetGlobalStats
+            | AdminCommandPayload::GetFeatures
+            | AdminCommandPayload::SetSinkFilter { .. }
+            | AdminCommandPayload::GetSinkFilters
+            | AdminCommandPayload::SetParticipantLimits { .. }
+            | AdminCommandPayload::PauseConsumption { .. }
+            | AdminCommandPayload::ResumeConsumption { .. }
+            // Reads the archive rather than the live book
+            | AdminCommandPayload::BookAsOf { .. } => None,
+            #[cfg(feature = "chaos")]
+            AdminCommandPayload::InjectFaults(_) | AdminCommandPayload::ClearFaults => None,
+        }
+    }
 }
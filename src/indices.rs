@@ -0,0 +1,116 @@
+// src/indices.rs
+use crate::config::analytics::PriceSource;
+use crate::config::indices::IndexConfig;
+use crate::helpers::IndexDefinePayload;
+use crate::helpers::analytics_helpers::collect_prices;
+use crate::orderbook::index::{IndexDefinition, IndexTick};
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+struct TrackedIndex {
+    definition: IndexDefinition,
+    /// Instruments whose pegged orders use this index as their mid reference
+    pegged_instruments: Vec<String>,
+    last_value: Option<f64>,
+}
+
+/// Computes index values from constituent books and publishes index ticks
+pub struct IndexCalculator {
+    tick_topic: String,
+    price_source: PriceSource,
+    indices: HashMap<String, TrackedIndex>,
+}
+
+impl IndexCalculator {
+    pub fn new(config: IndexConfig) -> Self {
+        let mut calculator = Self {
+            tick_topic: config.tick_topic,
+            price_source: config.price_source,
+            indices: HashMap::new(),
+        };
+        for definition in config.indices {
+            calculator.define(IndexDefinePayload {
+                definition,
+                pegged_instruments: Vec::new(),
+            });
+        }
+        calculator
+    }
+
+    /// Adds or replaces an index; an empty constituent list removes it
+    pub fn define(&mut self, payload: IndexDefinePayload) {
+        let index_id = payload.definition.index_id.clone();
+        if payload.definition.constituents.is_empty() {
+            if self.indices.remove(&index_id).is_some() {
+                info!("Removed index {}", index_id);
+            } else {
+                warn!("Index {} has no constituents, ignoring", index_id);
+            }
+            return;
+        }
+        info!(
+            "Defined index {} with {} constituents",
+            index_id,
+            payload.definition.constituents.len()
+        );
+        self.indices.insert(
+            index_id,
+            TrackedIndex {
+                definition: payload.definition,
+                pegged_instruments: payload.pegged_instruments,
+                last_value: None,
+            },
+        );
+    }
+
+    /// Latest value of an index, once its constituents were priced
+    pub fn value(&self, index_id: &str) -> Option<f64> {
+        self.indices.get(index_id)?.last_value
+    }
+
+    /// Constituents of every index and the instruments pegged to them
+    pub fn instruments(&self) -> impl Iterator<Item = &str> {
+        self.indices.values().flat_map(|index| {
+            index
+                .definition
+                .instruments()
+                .chain(index.pegged_instruments.iter().map(String::as_str))
+        })
+    }
+
+    /// Recomputes every index, publishing ticks for changed values and updating
+    /// the peg reference of linked instruments
+    pub fn on_tick(&mut self, manager: &BookManagerStd<OrderTags>, publisher: &Publisher) {
+        for index in self.indices.values_mut() {
+            let prices = collect_prices(manager, index.definition.instruments(), self.price_source);
+            let value = index.definition.compute(&prices);
+
+            for symbol in &index.pegged_instruments {
+                let Some(book) = manager.get_book(symbol) else {
+                    continue;
+                };
+                match value {
+                    Some(value) => book.set_external_reference_price(value.round() as u64),
+                    None => book.clear_external_reference_price(),
+                }
+            }
+
+            if value == index.last_value {
+                continue;
+            }
+            index.last_value = value;
+            if let Some(value) = value {
+                let tick = IndexTick {
+                    index_id: index.definition.index_id.clone(),
+                    value,
+                    timestamp: current_time_millis(),
+                };
+                publisher.publish(&self.tick_topic, &tick.index_id, &tick);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+// src/ingest.rs
+use crate::codec::{CodecError, Codecs, Decoder};
+use crate::config::ingest::IngestConfig;
+use crate::config::topics::{CommandKind, PayloadFormat};
+use crate::helpers::EngineCommand;
+use futures::FutureExt;
+use futures::StreamExt;
+use futures::future::{self, BoxFuture};
+use futures::stream::FuturesOrdered;
+use std::collections::{HashMap, VecDeque};
+
+/// An inbound message, copied out of the consumer's buffers
+pub struct Inbound {
+    pub topic: String,
+    pub key: Option<String>,
+    pub kind: CommandKind,
+    pub payload: Vec<u8>,
+}
+
+/// A message and what it decoded to
+pub struct Decoded {
+    pub message: Inbound,
+    pub result: Result<Option<EngineCommand>, CodecError>,
+}
+
+/// Decodes inbound messages several at a time on blocking threads, handing
+/// them back in the order they were taken
+///
+/// The order is kept across topics, so the commands of an instrument reach the
+/// engine in the order they were consumed whichever topics carried them, as
+/// when messages were decoded one at a time. Avro writer schemas are fetched as
+/// messages are taken, before they are decoded. A message of a topic at its
+/// `kafka.ingest.topics` limit is held back, with every message taken after it,
+/// until one of the topic's messages is handed back.
+pub struct DecodePool {
+    codecs: Codecs,
+    config: IngestConfig,
+    decoding: FuturesOrdered<BoxFuture<'static, Decoded>>,
+    /// Messages of each topic being decoded
+    in_flight: HashMap<String, usize>,
+    held: VecDeque<(Inbound, Decoder)>,
+}
+
+impl DecodePool {
+    pub fn new(codecs: Codecs, config: IngestConfig) -> Self {
+        Self {
+            codecs,
+            config,
+            decoding: FuturesOrdered::new(),
+            in_flight: HashMap::new(),
+            held: VecDeque::new(),
+        }
+    }
+
+    pub fn format(&self, kind: CommandKind) -> PayloadFormat {
+        self.codecs.format(kind)
+    }
+
+    /// Whether another message may be taken from the consumer
+    pub fn has_room(&self) -> bool {
+        self.held.is_empty() && self.decoding.len() < self.config.decode_workers
+    }
+
+    /// Starts decoding a message, or holds it back behind the others
+    pub async fn push(&mut self, message: Inbound) {
+        let decoder = self.codecs.decoder(message.kind, &message.payload).await;
+        if self.held.is_empty() && self.admits(&message.topic) {
+            self.start(message, decoder);
+        } else {
+            self.held.push_back((message, decoder));
+        }
+    }
+
+    /// The oldest message taken, once decoded; none when no message is
+    /// pending
+    ///
+    /// Cancel safe: a message is only removed once it is returned.
+    pub async fn next(&mut self) -> Option<Decoded> {
+        let decoded = self.decoding.next().await?;
+        if let Some(count) = self.in_flight.get_mut(&decoded.message.topic) {
+            *count -= 1;
+        }
+        while let Some((message, _)) = self.held.front()
+            && self.admits(&message.topic)
+        {
+            let (message, decoder) = self.held.pop_front().expect("front was checked");
+            self.start(message, decoder);
+        }
+        Some(decoded)
+    }
+
+    fn admits(&self, topic: &str) -> bool {
+        self.decoding.len() < self.config.decode_workers
+            && self.in_flight.get(topic).copied().unwrap_or_default()
+                < self.config.concurrency(topic)
+    }
+
+    fn start(&mut self, message: Inbound, decoder: Decoder) {
+        *self.in_flight.entry(message.topic.clone()).or_default() += 1;
+        let decoding = if self.config.decode_workers <= 1 {
+            let result = decoder.decode(message.kind, &message.payload);
+            future::ready(Decoded { message, result }).boxed()
+        } else {
+            tokio::task::spawn_blocking(move || {
+                let result = decoder.decode(message.kind, &message.payload);
+                Decoded { message, result }
+            })
+            .map(|decoded| decoded.expect("decoding a message panicked"))
+            .boxed()
+        };
+        self.decoding.push_back(decoding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::kafka::KafkaConfig;
+
+    fn cancel(topic: &str, order_id: u64) -> Inbound {
+        Inbound {
+            topic: topic.to_string(),
+            key: None,
+            kind: CommandKind::OrderCancel,
+            payload: format!(r#"{{"instrument_id":"BTC","order_id":{order_id}}}"#).into_bytes(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_messages_are_handed_back_in_the_order_taken() {
+        let mut config = IngestConfig {
+            decode_workers: 4,
+            ..IngestConfig::default()
+        };
+        config.topics.insert("order.cancelled".to_string(), 2);
+        let mut pool = DecodePool::new(Codecs::new(&KafkaConfig::default()), config);
+
+        let mut order_ids = Vec::new();
+        for order_id in 1..=3 {
+            assert!(pool.has_room());
+            pool.push(cancel("order.cancelled", order_id)).await;
+        }
+        // The third cancel waits for the first, and so does everything after it
+        assert!(!pool.has_room());
+        pool.push(cancel("order.create", 4)).await;
+        while let Some(decoded) = pool.next().await {
+            let Ok(Some(EngineCommand::OrderCancel(cancel))) = decoded.result else {
+                panic!("expected a cancel, got {:?}", decoded.result);
+            };
+            order_ids.push(cancel.order_id);
+        }
+        assert_eq!(order_ids, [1, 2, 3, 4]);
+        assert!(pool.has_room());
+    }
+}
@@ -0,0 +1,149 @@
+// src/kill_switch.rs
+use crate::helpers::EngineCommand;
+use crate::helpers::types::KillSwitchAction;
+use crate::order_responses::{RejectReason, Rejection};
+use pricelevel::OrderId;
+use std::collections::HashSet;
+use tracing::warn;
+
+/// Participants whose kill switch is engaged
+///
+/// Engaging the switch of a participant cancels its resting orders, which the
+/// engine does as it applies the command; from then on its new and replacement
+/// orders are rejected until `order.kill_switch` releases it. Cancels are still
+/// accepted. Like instrument deletes, kill switches are carried forward in the
+/// write-ahead log as settings, so replaying it rejects the same orders.
+#[derive(Default)]
+pub struct KillSwitches {
+    engaged: HashSet<String>,
+}
+
+impl KillSwitches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks the participants a command cuts off and lets trade again
+    pub fn record(&mut self, cmd: &EngineCommand) {
+        let EngineCommand::KillSwitch(kill_switch) = cmd else {
+            return;
+        };
+        let participant_id = &kill_switch.participant_id;
+        match kill_switch.action {
+            KillSwitchAction::Engage => {
+                warn!("Kill switch engaged for {}", participant_id);
+                self.engaged.insert(participant_id.clone());
+            }
+            KillSwitchAction::Release if self.engaged.remove(participant_id) => {
+                warn!("Kill switch released for {}", participant_id);
+            }
+            KillSwitchAction::Release => {
+                warn!("Kill switch of {} was not engaged", participant_id);
+            }
+        }
+    }
+
+    pub fn is_engaged(&self, participant_id: &str) -> bool {
+        self.engaged.contains(participant_id)
+    }
+
+    /// Rejects a new order of a participant that is cut off, and a replace of
+    /// one of its orders; `owner` gives the participant of a resting order
+    pub fn admit(
+        &self,
+        cmd: &EngineCommand,
+        owner: impl FnOnce(&str, OrderId) -> Option<String>,
+    ) -> Result<(), Rejection> {
+        let (participant_id, order_id) = match cmd {
+            EngineCommand::OrderCreate(order) => (order.participant_id.clone(), order.order_id),
+            EngineCommand::OrderReplace(replace) => (
+                replace
+                    .participant_id
+                    .clone()
+                    .or_else(|| owner(&replace.instrument_id, OrderId::from_u64(replace.order_id))),
+                replace.order_id,
+            ),
+            _ => return Ok(()),
+        };
+        let Some(participant_id) = participant_id.filter(|id| self.is_engaged(id)) else {
+            return Ok(());
+        };
+        warn!(
+            "Rejecting order {} of {}: its kill switch is engaged",
+            order_id, participant_id
+        );
+        Err(Rejection::new(
+            RejectReason::KillSwitchEngaged,
+            format!("the kill switch of {participant_id} is engaged"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::types::OrderType;
+    use crate::helpers::{KillSwitchPayload, OrderCreatePayload, OrderReplacePayload};
+    use crate::tags::OrderTags;
+    use pricelevel::{Side, TimeInForce};
+
+    fn order(participant_id: Option<&str>) -> EngineCommand {
+        EngineCommand::OrderCreate(OrderCreatePayload {
+            order_id: 1,
+            instrument_id: "BTC".to_string(),
+            quantity: 5,
+            price: 100,
+            side: Side::Buy,
+            time_in_force: TimeInForce::Gtc,
+            order_type: OrderType::LIMIT,
+            participant_id: participant_id.map(str::to_string),
+            oms_id: None,
+            client_order_id: None,
+            tags: OrderTags::new(),
+            visible_quantity: None,
+            hidden_quantity: None,
+            liquidation: false,
+            min_quantity: None,
+            all_or_none: false,
+            sweep_limit: None,
+            sequence: None,
+        })
+    }
+
+    fn kill_switch(action: KillSwitchAction) -> EngineCommand {
+        EngineCommand::KillSwitch(KillSwitchPayload {
+            participant_id: "desk-1".to_string(),
+            action,
+        })
+    }
+
+    #[test]
+    fn test_orders_are_rejected_while_the_kill_switch_is_engaged() {
+        let mut switches = KillSwitches::new();
+        let owner = |_: &str, _: OrderId| Some("desk-1".to_string());
+        assert!(switches.admit(&order(Some("desk-1")), owner).is_ok());
+
+        switches.record(&kill_switch(KillSwitchAction::Engage));
+        let rejection = switches.admit(&order(Some("desk-1")), owner).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::KillSwitchEngaged);
+        assert!(switches.admit(&order(Some("desk-2")), owner).is_ok());
+        assert!(switches.admit(&order(None), owner).is_ok());
+        // A replace naming no participant belongs to the owner of its order
+        let replace: OrderReplacePayload = serde_json::from_value(serde_json::json!({
+            "instrument_id": "BTC",
+            "order_id": 1,
+            "price": 100,
+            "quantity": 5,
+        }))
+        .unwrap();
+        assert!(
+            switches
+                .admit(&EngineCommand::OrderReplace(replace), owner)
+                .is_err()
+        );
+
+        switches.record(&kill_switch(KillSwitchAction::Release));
+        assert!(!switches.is_engaged("desk-1"));
+        assert!(switches.admit(&order(Some("desk-1")), owner).is_ok());
+    }
+}
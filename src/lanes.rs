@@ -0,0 +1,125 @@
+// src/lanes.rs
+use crate::helpers::EngineCommand;
+use tokio::sync::mpsc::error::SendError;
+#[cfg(test)]
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+/// Queues commands for an engine shard, each on its lane
+#[derive(Clone)]
+pub struct LaneSender {
+    priority: Sender<EngineCommand>,
+    regular: Sender<EngineCommand>,
+}
+
+/// Commands queued for an engine shard, liquidation orders on a priority lane
+/// of their own
+///
+/// The engine takes from the priority lane whenever it holds a command, so a
+/// liquidation is not held up behind a backlog of regular order flow. Commands
+/// keep their order within a lane, but a liquidation may be applied ahead of
+/// commands received before it; the write-ahead log records the order they
+/// were applied in.
+pub struct CommandLanes {
+    priority: Receiver<EngineCommand>,
+    regular: Receiver<EngineCommand>,
+}
+
+/// Lanes holding up to `capacity` regular commands and `priority_capacity`
+/// liquidation orders
+pub fn channel(capacity: usize, priority_capacity: usize) -> (LaneSender, CommandLanes) {
+    let (priority, priority_rx) = mpsc::channel(priority_capacity);
+    let (regular, regular_rx) = mpsc::channel(capacity);
+    (
+        LaneSender { priority, regular },
+        CommandLanes {
+            priority: priority_rx,
+            regular: regular_rx,
+        },
+    )
+}
+
+impl LaneSender {
+    pub async fn send(&self, cmd: EngineCommand) -> Result<(), SendError<EngineCommand>> {
+        if cmd.is_liquidation() {
+            self.priority.send(cmd).await
+        } else {
+            self.regular.send(cmd).await
+        }
+    }
+}
+
+impl CommandLanes {
+    /// The next command, from the priority lane if it holds one; `None` once
+    /// every sender is gone and both lanes are drained
+    pub async fn recv(&mut self) -> Option<EngineCommand> {
+        tokio::select! {
+            biased;
+            Some(cmd) = self.priority.recv() => Some(cmd),
+            cmd = self.regular.recv() => match cmd {
+                Some(cmd) => Some(cmd),
+                // A liquidation may have been queued since the priority lane
+                // was looked at
+                None => self.priority.recv().await,
+            },
+        }
+    }
+
+    /// The next queued command without waiting, priority lane first
+    #[cfg(test)]
+    pub fn try_recv(&mut self) -> Result<EngineCommand, TryRecvError> {
+        self.priority
+            .try_recv()
+            .or_else(|_| self.regular.try_recv())
+    }
+
+    /// Share of the regular lane's capacity in use
+    pub fn fill(&self) -> f64 {
+        self.regular.len() as f64 / self.regular.max_capacity() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::topics::CommandKind;
+
+    fn order(order_id: u64, liquidation: bool) -> EngineCommand {
+        let payload = format!(
+            r#"{{"order_id":{order_id},"instrument_id":"BTC","quantity":1,"price":100,"side":"Buy","time_in_force":"Ioc","order_type":"LIMIT","liquidation":{liquidation}}}"#
+        );
+        EngineCommand::parse(CommandKind::OrderCreate, &payload)
+            .unwrap()
+            .unwrap()
+    }
+
+    fn order_id(cmd: EngineCommand) -> u64 {
+        match cmd {
+            EngineCommand::OrderCreate(order) => order.order_id,
+            cmd => panic!("unexpected command {cmd:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_liquidations_overtake_regular_commands() {
+        let (sender, mut lanes) = channel(8, 8);
+        sender.send(order(1, false)).await.unwrap();
+        sender.send(order(2, false)).await.unwrap();
+        sender.send(order(3, true)).await.unwrap();
+        sender.send(order(4, true)).await.unwrap();
+        assert_eq!(lanes.fill(), 0.25);
+
+        let mut received = Vec::new();
+        while let Ok(cmd) = lanes.try_recv() {
+            received.push(order_id(cmd));
+        }
+        assert_eq!(received, [3, 4, 1, 2]);
+
+        sender.send(order(5, false)).await.unwrap();
+        sender.send(order(6, true)).await.unwrap();
+        drop(sender);
+        assert_eq!(lanes.recv().await.map(order_id), Some(6));
+        assert_eq!(lanes.recv().await.map(order_id), Some(5));
+        assert!(lanes.recv().await.is_none());
+    }
+}
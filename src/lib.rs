@@ -237,13 +237,19 @@ pub mod orderbook;
 pub mod prelude;
 mod utils;
 
+pub use orderbook::activity::ActivityStats;
+pub use orderbook::extra_fields::OrderExtraFields;
+pub use orderbook::global_stats::{GlobalStats, InstrumentLoad};
 pub use orderbook::iterators::LevelInfo;
 pub use orderbook::manager::{BookManager, BookManagerStd, BookManagerTokio};
-pub use orderbook::market_impact::{MarketImpact, OrderSimulation};
+pub use orderbook::market_impact::{
+    ImpactEstimate, ImpactModel, ImpactModelKind, MarketImpact, OrderSimulation,
+};
 pub use orderbook::snapshot::{EnrichedSnapshot, MetricFlags};
 pub use orderbook::statistics::{DepthStats, DistributionBin};
-pub use orderbook::trade::{TradeListener, TradeResult};
+pub use orderbook::trade::{BookContext, TradeListener, TradeResult};
 pub use orderbook::{OrderBook, OrderBookError, OrderBookSnapshot};
+pub use utils::chunking::{ChunkAssembler, ChunkError, ChunkHeader, split_payload};
 pub use utils::current_time_millis;
 
 /// Legacy type alias for `OrderBook<()>` to maintain backward compatibility.
@@ -0,0 +1,140 @@
+// src/liquidations.rs
+use crate::config::liquidations::LiquidationConfig;
+use crate::publisher::Publisher;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Liquidations on an instrument over one metrics interval, as published
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiquidationVolume {
+    pub instrument_id: String,
+    /// Liquidation orders entered
+    pub orders: u64,
+    /// Trades with a liquidation order on either side
+    pub trades: u64,
+    pub quantity: u64,
+    /// Sum of price times quantity of those trades
+    pub notional: u128,
+    pub interval_start: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Default)]
+struct Counts {
+    orders: u64,
+    trades: u64,
+    quantity: u64,
+    notional: u128,
+}
+
+/// Counts liquidation orders and the volume they trade per instrument,
+/// publishing and resetting the counts every `metrics_interval_ms`
+pub struct LiquidationMonitor {
+    config: LiquidationConfig,
+    counts: BTreeMap<String, Counts>,
+    interval_start: u64,
+}
+
+impl LiquidationMonitor {
+    pub fn new(config: LiquidationConfig, now: u64) -> Self {
+        Self {
+            config,
+            counts: BTreeMap::new(),
+            interval_start: now,
+        }
+    }
+
+    fn counts(&mut self, instrument_id: &str) -> &mut Counts {
+        self.counts.entry(instrument_id.to_string()).or_default()
+    }
+
+    /// Counts a liquidation order the engine admitted
+    pub fn record_order(&mut self, instrument_id: &str) {
+        if self.config.enabled {
+            self.counts(instrument_id).orders += 1;
+        }
+    }
+
+    /// Counts a trade with a liquidation order on either side
+    pub fn record_trade(&mut self, instrument_id: &str, price: u64, quantity: u64) {
+        if self.config.enabled {
+            let counts = self.counts(instrument_id);
+            counts.trades += 1;
+            counts.quantity += quantity;
+            counts.notional += u128::from(price) * u128::from(quantity);
+        }
+    }
+
+    /// Volumes of every instrument liquidated on since the last report, which
+    /// start over
+    pub fn report(&mut self, now: u64) -> Vec<LiquidationVolume> {
+        let interval_start = std::mem::replace(&mut self.interval_start, now);
+        std::mem::take(&mut self.counts)
+            .into_iter()
+            .map(|(instrument_id, counts)| LiquidationVolume {
+                instrument_id,
+                orders: counts.orders,
+                trades: counts.trades,
+                quantity: counts.quantity,
+                notional: counts.notional,
+                interval_start,
+                timestamp: now,
+            })
+            .collect()
+    }
+
+    /// Publishes the volumes once the metrics interval has passed
+    pub fn on_tick(&mut self, publisher: &Publisher, now: u64) {
+        if !self.config.enabled || now < self.interval_start + self.config.metrics_interval_ms {
+            return;
+        }
+        for volume in self.report(now) {
+            publisher.publish(&self.config.metrics_topic, &volume.instrument_id, &volume);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volumes_are_reported_per_interval() {
+        let (publisher, mut outbound) = Publisher::channel(8);
+        let mut monitor = LiquidationMonitor::new(
+            LiquidationConfig {
+                enabled: true,
+                metrics_interval_ms: 100,
+                ..LiquidationConfig::default()
+            },
+            0,
+        );
+        monitor.record_order("BTC");
+        monitor.record_trade("BTC", 100, 3);
+        monitor.record_trade("BTC", 99, 2);
+        monitor.record_trade("ETH", 10, 1);
+
+        monitor.on_tick(&publisher, 50);
+        assert!(outbound.try_recv().is_err());
+        monitor.on_tick(&publisher, 100);
+        let btc = outbound.try_recv().unwrap();
+        assert_eq!(btc.topic, "metrics.liquidations");
+        let btc: serde_json::Value = serde_json::from_str(&btc.payload).unwrap();
+        assert_eq!(btc["instrument_id"], "BTC");
+        assert_eq!(
+            (btc["orders"].as_u64(), btc["trades"].as_u64()),
+            (Some(1), Some(2))
+        );
+        assert_eq!(
+            (btc["quantity"].as_u64(), btc["notional"].as_u64()),
+            (Some(5), Some(498))
+        );
+        assert!(outbound.try_recv().is_ok());
+
+        // Quiet instruments are not reported
+        monitor.record_trade("ETH", 10, 1);
+        let volumes = monitor.report(200);
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].interval_start, 100);
+    }
+}
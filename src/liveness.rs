@@ -0,0 +1,349 @@
+// src/liveness.rs
+use crate::alerts::{Alert, AlertKind, emit_alert};
+use crate::config::liveness::LivenessConfig;
+use crate::helpers::types::OmsSessionStatus;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::publisher::Publisher;
+use crate::sessions::{CloseReason, SessionClosed, SessionId, SessionRegistry, resting_orders};
+use crate::tags::OrderTags;
+use pricelevel::OrderId;
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::info;
+
+/// Published on the heartbeat topic while the engine task is processing
+#[derive(Debug, Serialize)]
+pub struct EngineHeartbeat {
+    /// Increases by one per heartbeat, restarting at 1 with the engine
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub instruments: usize,
+    /// OMSes whose heartbeats are current
+    pub live_oms: Vec<String>,
+}
+
+/// Details of an OMS heartbeat loss alert
+#[derive(Debug, Serialize)]
+struct OmsHeartbeatLost<'a> {
+    oms_id: &'a str,
+    orders_cancelled: usize,
+}
+
+/// The liveness contract with upstream OMSes
+///
+/// Each OMS sending orders or heartbeats is tracked as a session of the
+/// [`SessionRegistry`], owning the orders it entered that still rest. When its
+/// heartbeats stop for longer than its timeout, or it announces a disconnect,
+/// the session is closed and, per its policy, its orders are cancelled so they
+/// are not left orphaned. An OMS logging out leaves its orders resting. The
+/// next heartbeat opens a new session.
+///
+/// The orders to cancel are returned to the engine, which cancels them
+/// through logged commands so recovery does not bring them back.
+pub struct OmsLiveness {
+    config: LivenessConfig,
+    sessions: SessionRegistry,
+    /// Open session of each OMS
+    oms: HashMap<String, SessionId>,
+    sequence: u64,
+}
+
+impl OmsLiveness {
+    pub fn new(config: LivenessConfig) -> Self {
+        Self {
+            sessions: SessionRegistry::new(config.oms.clone()),
+            config,
+            oms: HashMap::new(),
+            sequence: 0,
+        }
+    }
+
+    fn session(&mut self, oms_id: &str, now: u64) -> SessionId {
+        if let Some(&session_id) = self.oms.get(oms_id) {
+            return session_id;
+        }
+        let session_id = self.sessions.open(oms_id, now);
+        self.oms.insert(oms_id.to_string(), session_id);
+        session_id
+    }
+
+    /// Records a heartbeat, closing the session of an OMS that says it is
+    /// going away and returning the orders to cancel
+    pub fn on_heartbeat(
+        &mut self,
+        oms_id: &str,
+        status: OmsSessionStatus,
+        manager: &BookManagerStd<OrderTags>,
+        publisher: &Publisher,
+        now: u64,
+    ) -> Vec<(String, OrderId)> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+        let closed = match status {
+            OmsSessionStatus::Alive => {
+                let session_id = self.session(oms_id, now);
+                self.sessions.heartbeat(session_id, now);
+                return Vec::new();
+            }
+            OmsSessionStatus::Logout => self
+                .oms
+                .get(oms_id)
+                .and_then(|&session_id| self.sessions.logout(session_id)),
+            OmsSessionStatus::Disconnect => self
+                .oms
+                .get(oms_id)
+                .and_then(|&session_id| self.sessions.disconnected(session_id)),
+        };
+        match closed {
+            Some(closed) => self.on_closed(manager, publisher, &closed),
+            None => Vec::new(),
+        }
+    }
+
+    /// Records an order of the OMS left resting in the book
+    pub fn register_order(
+        &mut self,
+        oms_id: &str,
+        instrument_id: &str,
+        order_id: OrderId,
+        now: u64,
+    ) {
+        if !self.config.enabled {
+            return;
+        }
+        let session_id = self.session(oms_id, now);
+        self.sessions
+            .register_order(session_id, instrument_id, order_id, now);
+    }
+
+    /// The OMS that entered a resting order
+    pub fn oms_of(&self, instrument_id: &str, order_id: OrderId) -> Option<String> {
+        self.sessions
+            .owner(instrument_id, order_id)
+            .map(str::to_string)
+    }
+
+    /// Forgets an order that filled or was cancelled
+    pub fn order_done(&mut self, instrument_id: &str, order_id: OrderId) {
+        if self.config.enabled {
+            self.sessions.order_done(instrument_id, order_id);
+        }
+    }
+
+    /// Closes the session of every OMS whose heartbeats stopped, then
+    /// publishes the engine heartbeat, returning the orders to cancel
+    pub fn on_tick(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        publisher: &Publisher,
+        now: u64,
+    ) -> Vec<(String, OrderId)> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+        let mut orphaned = Vec::new();
+        for closed in self.sessions.expire(now) {
+            orphaned.extend(self.on_closed(manager, publisher, &closed));
+        }
+        self.sequence += 1;
+        let mut live_oms: Vec<String> = self.oms.keys().cloned().collect();
+        live_oms.sort_unstable();
+        publisher.publish(
+            &self.config.heartbeat_topic,
+            "engine",
+            &EngineHeartbeat {
+                sequence: self.sequence,
+                timestamp: now,
+                instruments: manager.symbols().len(),
+                live_oms,
+            },
+        );
+        orphaned
+    }
+
+    fn on_closed(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        publisher: &Publisher,
+        closed: &SessionClosed,
+    ) -> Vec<(String, OrderId)> {
+        self.oms.remove(&closed.participant_id);
+        let orphaned = resting_orders(manager, closed);
+        let orders_cancelled = orphaned.len();
+        if closed.reason != CloseReason::HeartbeatTimeout {
+            info!(
+                "OMS {} announced {:?}, cancelling {} resting orders",
+                closed.participant_id, closed.reason, orders_cancelled
+            );
+            return orphaned;
+        }
+        emit_alert(
+            publisher,
+            Alert::new(
+                AlertKind::OmsHeartbeatLost,
+                "engine",
+                format!(
+                    "Heartbeats of OMS {} stopped, cancelled {} resting orders",
+                    closed.participant_id, orders_cancelled
+                ),
+            )
+            .with_details(&OmsHeartbeatLost {
+                oms_id: &closed.participant_id,
+                orders_cancelled,
+            }),
+        );
+        orphaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::sessions::SessionPolicy;
+    use pricelevel::{Side, TimeInForce};
+
+    fn liveness() -> OmsLiveness {
+        let mut config = LivenessConfig {
+            enabled: true,
+            ..LivenessConfig::default()
+        };
+        config.oms.default.heartbeat_timeout_ms = 1_000;
+        config.oms.participants.insert(
+            "oms-keep".to_string(),
+            SessionPolicy {
+                cancel_on_disconnect: false,
+                heartbeat_timeout_ms: 1_000,
+            },
+        );
+        OmsLiveness::new(config)
+    }
+
+    fn rest(manager: &BookManagerStd<OrderTags>, id: u64) {
+        manager
+            .get_book("BTC")
+            .unwrap()
+            .add_limit_order(
+                OrderId::from_u64(id),
+                100 + id,
+                5,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_silent_oms_orders_are_cancelled() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let (publisher, mut outbound) = Publisher::channel(16);
+        let mut liveness = liveness();
+        for (id, oms_id) in [(1, "oms-a"), (2, "oms-a"), (3, "oms-b"), (4, "oms-keep")] {
+            rest(&manager, id);
+            liveness.register_order(oms_id, "BTC", OrderId::from_u64(id), 0);
+        }
+        assert_eq!(
+            liveness.oms_of("BTC", OrderId::from_u64(3)).as_deref(),
+            Some("oms-b")
+        );
+        liveness.on_heartbeat("oms-b", OmsSessionStatus::Alive, &manager, &publisher, 900);
+        liveness.on_heartbeat(
+            "oms-keep",
+            OmsSessionStatus::Alive,
+            &manager,
+            &publisher,
+            900,
+        );
+
+        let orphaned = liveness.on_tick(&manager, &publisher, 1_500);
+        assert_eq!(
+            orphaned,
+            [1, 2].map(|id| ("BTC".to_string(), OrderId::from_u64(id)))
+        );
+        let alert: serde_json::Value =
+            serde_json::from_str(&outbound.try_recv().unwrap().payload).unwrap();
+        assert_eq!(alert["kind"], "oms_heartbeat_lost");
+        assert_eq!(alert["details"]["oms_id"], "oms-a");
+        assert_eq!(alert["details"]["orders_cancelled"], 2);
+        let heartbeat = outbound.try_recv().unwrap();
+        assert_eq!(heartbeat.topic, "engine.heartbeat");
+        let heartbeat: serde_json::Value = serde_json::from_str(&heartbeat.payload).unwrap();
+        assert_eq!(heartbeat["sequence"], 1);
+        assert_eq!(
+            heartbeat["live_oms"],
+            serde_json::json!(["oms-b", "oms-keep"])
+        );
+
+        // Orders of an OMS that asked to keep them survive its heartbeat loss
+        liveness.on_heartbeat(
+            "oms-b",
+            OmsSessionStatus::Alive,
+            &manager,
+            &publisher,
+            2_000,
+        );
+        assert!(liveness.on_tick(&manager, &publisher, 2_500).is_empty());
+        let alert: serde_json::Value =
+            serde_json::from_str(&outbound.try_recv().unwrap().payload).unwrap();
+        assert_eq!(alert["details"]["oms_id"], "oms-keep");
+        assert_eq!(alert["details"]["orders_cancelled"], 0);
+    }
+
+    #[test]
+    fn test_heartbeat_after_loss_opens_a_new_session() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let (publisher, _outbound) = Publisher::channel(16);
+        let mut liveness = liveness();
+        liveness.on_heartbeat("oms-a", OmsSessionStatus::Alive, &manager, &publisher, 0);
+        liveness.on_tick(&manager, &publisher, 2_000);
+        assert!(liveness.oms.is_empty());
+
+        liveness.on_heartbeat(
+            "oms-a",
+            OmsSessionStatus::Alive,
+            &manager,
+            &publisher,
+            3_000,
+        );
+        rest(&manager, 1);
+        liveness.register_order("oms-a", "BTC", OrderId::from_u64(1), 3_000);
+        liveness.order_done("BTC", OrderId::from_u64(1));
+        // Forgotten orders are left alone
+        assert!(liveness.on_tick(&manager, &publisher, 5_000).is_empty());
+    }
+
+    #[test]
+    fn test_disconnect_cancels_the_oms_orders_and_logout_keeps_them() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let (publisher, mut outbound) = Publisher::channel(16);
+        let mut liveness = liveness();
+        for (id, oms_id) in [(1, "oms-a"), (2, "oms-a"), (3, "oms-b")] {
+            rest(&manager, id);
+            liveness.register_order(oms_id, "BTC", OrderId::from_u64(id), 0);
+        }
+
+        let orphaned = liveness.on_heartbeat(
+            "oms-a",
+            OmsSessionStatus::Disconnect,
+            &manager,
+            &publisher,
+            100,
+        );
+        assert_eq!(orphaned.len(), 2);
+        assert_eq!(liveness.oms_of("BTC", OrderId::from_u64(1)), None);
+
+        let orphaned =
+            liveness.on_heartbeat("oms-b", OmsSessionStatus::Logout, &manager, &publisher, 100);
+        assert!(orphaned.is_empty(), "a logout cancels nothing");
+        assert!(liveness.oms.is_empty());
+        // Announced closes are not alerted on, and leave no session to expire
+        liveness.on_tick(&manager, &publisher, 5_000);
+        let heartbeat = outbound.try_recv().unwrap();
+        assert_eq!(heartbeat.topic, "engine.heartbeat");
+        assert!(outbound.try_recv().is_err());
+    }
+}
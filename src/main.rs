@@ -1,153 +1,577 @@
+mod alerts;
+mod archive;
+mod bbo_state;
+mod bench;
+mod blocking_worker;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod clearing;
+mod cli;
+mod client_orders;
+mod codec;
+mod conflation;
 mod config;
+mod consumption;
+mod dedup;
+mod delay_buffer;
+mod deleted_instruments;
+mod depth_deltas;
+mod diagnostics;
+mod dry_run;
 mod engine;
+mod execution_quality;
+mod expiry;
+mod fair_value;
+mod feature_flags;
+mod feeds;
+mod funding;
 mod helpers;
+mod indices;
+mod ingest;
+mod kill_switch;
+mod lanes;
+mod liquidations;
+mod liveness;
+mod mbo;
+mod metrics;
+mod order_responses;
+mod order_to_trade;
 mod orderbook;
+mod partitioning;
+mod preflight;
+mod price_bands;
+mod publisher;
+mod rebalance;
+mod redaction;
+mod risk;
+mod schema;
+mod sequencer;
+mod sessions;
+mod sharding;
+mod shutdown;
+mod sinks;
+mod soak;
+mod supervisor;
+mod tags;
+mod tiering;
+mod trade_producer;
 mod utils;
-use crate::config::kafka::{KafkaConfig, create_consumer};
-use crate::helpers::{
-    DeleteInstrumentPayload, EngineCommand, InstrumentCreatePayload, OrderCancelPayload,
-    OrderCreatePayload, OrderModifyPayload,
-};
+mod verification;
+mod wal;
+mod watchdog;
+use crate::cli::{Cli, Command, JournalAction, Mode};
+use crate::config::app::{AppConfig, AppConfigError, LogFormat};
+use crate::config::bench::BenchConfig;
+use crate::config::kafka::{create_consumer, create_producer};
+use crate::config::soak::SoakConfig;
+use crate::codec::Codecs;
+use crate::consumption::ConsumptionControl;
+use crate::config::topics::{CommandKind, PayloadFormat};
+use crate::helpers::{AdminCommandPayload, EngineCommand};
+use crate::ingest::{DecodePool, Decoded, Inbound};
+use crate::orderbook::manager::BookManager;
+use crate::publisher::{DeadLetter, Publisher};
+use crate::rebalance::CommandConsumer;
+use crate::redaction::Redactor;
+use crate::sharding::{ShardRouter, shard_config};
+use crate::utils::current_time_millis;
+use crate::watchdog::Progress;
+use clap::Parser;
 use futures::StreamExt;
 use rdkafka::message::Message;
-use tokio::sync::mpsc;
-use tracing::{info, warn};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+use tracing::{error, info, warn};
+use tracing_subscriber::filter::Targets;
 
 #[tokio::main]
 
 async fn main() {
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
-    // 1) Engine command channel
-    let (tx, rx) = mpsc::channel::<EngineCommand>(1024);
-    // 2) Spawn engine task that owns BookManagerStd
-    tokio::spawn(async move {
-        engine::run_engine(rx).await;
-    });
-    // 3) Kafka consumer
-    let kafka_config = KafkaConfig {
-        brokers: "localhost:9092".to_string(),
-        group_id: "orderbook_group".to_string(),
-        topics: vec![
-            "instrument.create".to_string(),
-            "instrument.delete".to_string(),
-            "alert.create".to_string(),
-            "order.cancelled".to_string(),
-            "order.create".to_string(),
-            "order.modify".to_string(),
-        ],
+    let cli = Cli::parse();
+    // 0) Refuse to start on a misconfiguration rather than dropping messages later
+    let config = AppConfig::load(cli.config.as_deref(), &cli.overrides());
+    let log_format = config
+        .as_ref()
+        .map_or(LogFormat::default(), |config| config.log_format);
+    match cli.command {
+        // `schema` prints the JSON Schema of every message instead of running the engine
+        Some(Command::Schema) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema::document()).expect("schema is valid JSON")
+            );
+            return;
+        }
+        Some(Command::BenchBook {
+            commands,
+            resting_orders,
+        }) => bench_mode(commands, resting_orders, log_format),
+        Some(Command::Soak { seconds }) => soak_mode(config, seconds, log_format).await,
+        Some(Command::Journal { action }) => journal_mode(config, action, log_format),
+        None => {}
+    }
+    init_logging(
+        Targets::new().with_default(
+            config
+                .as_ref()
+                .map_or(tracing::Level::INFO, |config| config.log_level.as_level()),
+        ),
+        log_format,
+    );
+    let mut config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
     };
-    let consumer = create_consumer(&kafka_config).expect("Failed to create Kafka consumer");
+    if let Some(path) = &cli.config {
+        info!("Loaded configuration from {}", path);
+    }
+    match cli.mode {
+        Mode::Run => {}
+        Mode::Replay => replay_mode(&config),
+        Mode::Backfill => {
+            backfill(&mut config);
+            info!(
+                "Backfilling from the earliest offsets as consumer group {}",
+                config.kafka.group_id
+            );
+        }
+        Mode::DryRun => {
+            dry_run(&mut config);
+            info!(
+                "Previewing commands to {} as consumer group {}",
+                config.engine.dry_run.shadow_topic, config.kafka.group_id
+            );
+        }
+    }
+    let AppConfig {
+        kafka: kafka_config,
+        channels,
+        engine: engine_config,
+        watchdog: watchdog_config,
+        sharding,
+        redaction,
+        metrics_port,
+        ..
+    } = config;
+    let topics = kafka_config.topics.clone();
+    let codecs = Codecs::new(&kafka_config);
+    if kafka_config.preflight.enabled {
+        let requirements = preflight::requirements(&topics, &engine_config);
+        let issues = preflight::run(&kafka_config, &requirements).await;
+        if !preflight::report(&kafka_config.preflight, &issues) {
+            std::process::exit(1);
+        }
+    }
+    // 1) Outbound publisher task
+    let producer = create_producer(&kafka_config).expect("Failed to create Kafka producer");
+    let (publisher, outbound_rx) = Publisher::channel(channels.outbound);
+    let publisher = publisher.with_redaction(Redactor::new(&redaction));
+    let max_message_bytes = kafka_config.message_max_bytes;
+    let partitioning = kafka_config.partitioning.clone();
+    let publisher_task = tokio::spawn(async move {
+        publisher::run_publisher(outbound_rx, producer, max_message_bytes, partitioning).await;
+    });
+    // 2) Engine command channels, one per shard
+    let dead_letters = publisher.clone();
+    let progress = Progress::default();
+    if let Some(port) = metrics_port {
+        tokio::spawn(metrics::serve(port, progress.clone()));
+    }
+    let watchdog_alerts = publisher.clone();
+    let mut shards = Vec::with_capacity(sharding.shards);
+    let mut engines = Vec::with_capacity(sharding.shards);
+    // 3) Spawn an engine task per shard, each owning the BookManagerStd of its
+    // instruments, under a supervisor that acts on its panics per
+    // `engine_config.supervisor`
+    for shard in 0..sharding.shards {
+        let (tx, rx) = lanes::channel(channels.engine_commands, channels.liquidation_commands);
+        shards.push(tx);
+        engines.push(tokio::spawn(supervisor::supervise(
+            rx,
+            publisher.clone(),
+            shard_config(&engine_config, shard, sharding.shards),
+            progress.clone(),
+        )));
+    }
+    if sharding.shards > 1 {
+        info!("Running {} engine shards", sharding.shards);
+    }
+    let mut router = ShardRouter::new(shards);
+    // 4) Kafka consumer, whose topics operators may pause
+    let consumption = ConsumptionControl::new(&topics.admin, &topics.consumption);
+    let subscriptions = topics.subscriptions(&engine_config.liveness);
+    let mut consumer = Arc::new(
+        create_consumer(&kafka_config, &subscriptions, &progress, &consumption)
+            .expect("Failed to create Kafka consumer"),
+    );
     info!("[INFO] Kafka consumer created successfully");
-    info!("[INFO] Subscribed to topics: {:?}", kafka_config.topics);
+    info!("[INFO] Subscribed to topics: {:?}", subscriptions);
     info!("[INFO] Brokers: {}", kafka_config.brokers);
-    let mut message_stream = consumer.stream();
-    while let Some(message_result) = message_stream.next().await {
-        match message_result {
-            Ok(message) => {
-                let topic = message.topic();
-                let payload = message
-                    .payload()
-                    .and_then(|p| std::str::from_utf8(p).ok())
-                    .unwrap_or("");
-                match topic {
-                    "alert.create" => {
-                        info!(
-                            "[INFO] Received message on topic 'alert.create': {}",
-                            payload
-                        );
-                        // Currently ignoring alert messages
-                    }
-                    "instrument.delete" => {
-                        match serde_json::from_str::<DeleteInstrumentPayload>(payload) {
-                            Ok(delete_instr) => {
-                                let cmd = EngineCommand::InstrumentDelete(delete_instr);
-                                if let Err(e) = tx.send(cmd).await {
-                                    warn!("Failed to send InstrumentDelete to engine: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse instrument.delete payload: {}", e);
-                            }
-                        }
-                    }
-                    "instrument.create" => {
-                        info!(
-                            "[INFO] Received message on topic 'instrument.create': {}",
-                            payload
-                        );
-                        match serde_json::from_str::<InstrumentCreatePayload>(payload) {
-                            Ok(instr_msg) => {
-                                let cmd =
-                                    EngineCommand::InstrumentCreate(instr_msg);
-                                if let Err(e) = tx.send(cmd).await {
-                                    warn!("Failed to send InstrumentCreate to engine: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse instrument.create payload: {}", e);
-                            }
-                        }
-                    }
-                    "order.create" => {
-                        info!(
-                            "[INFO] Received message on topic 'order.created': {}",
-                            payload
-                        );
-                        match serde_json::from_str::<OrderCreatePayload>(payload) {
-                            Ok(create_payload) => {
-                                let cmd = EngineCommand::OrderCreate(create_payload);
-                                if let Err(e) = tx.send(cmd).await {
-                                    warn!("Failed to send OrderCreate to engine: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse order.create payload: {}", e);
-                            }
-                        }
-                    }
-                    "order.cancelled" => {
-                        info!(
-                            "[INFO] Received message on topic 'order.cancelled': {}",
-                            payload
-                        );
-                        match serde_json::from_str::<OrderCancelPayload>(payload) {
-                            Ok(delete_order) => {
-                                let cmd = EngineCommand::OrderCancel(delete_order);
-                                if let Err(e) = tx.send(cmd).await {
-                                    warn!("Failed to send OrderCancel to engine: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse order.cancelled payload: {}", e);
-                            }
+    // 5) Watchdog over the consumer-to-engine pipeline, which may ask for the
+    // consumer to be recreated
+    let (consumers, consumer_rx) = watch::channel(consumer.clone());
+    let restart = Arc::new(Notify::new());
+    let watchdog = watchdog_config.enabled.then(|| {
+        tokio::spawn(watchdog::run(
+            watchdog_config,
+            progress.clone(),
+            consumer_rx,
+            restart.clone(),
+            watchdog_alerts,
+        ))
+    });
+    let shutdown_signal = shutdown::signal();
+    tokio::pin!(shutdown_signal);
+    // Messages are decoded apart from the consumer, and handled in the order
+    // they were consumed
+    let mut decoding = DecodePool::new(codecs, kafka_config.ingest.clone());
+    loop {
+        let mut message_stream = consumer.stream();
+        let stopped = loop {
+            let message_result = tokio::select! {
+                Some(decoded) = decoding.next() => {
+                    dispatch(
+                        decoded,
+                        &consumer,
+                        &consumption,
+                        &publisher,
+                        &mut router,
+                        &progress,
+                        &dead_letters,
+                        &topics.dead_letter,
+                    )
+                    .await;
+                    continue;
+                }
+                message = message_stream.next(), if decoding.has_room() => match message {
+                    Some(message) => message,
+                    None => break true,
+                },
+                _ = restart.notified() => break false,
+                signal = &mut shutdown_signal => {
+                    info!("Received {}, shutting down", signal);
+                    break true;
+                }
+            };
+            match message_result {
+                Ok(message) => {
+                    let topic = message.topic();
+                    let payload = message.payload().unwrap_or_default();
+                    let Some(kind) = topics.kind_of(topic) else {
+                        warn!("[WARN] Received message on unknown topic: {}", topic);
+                        continue;
+                    };
+                    // Theoretical prices arrive too often to log each one
+                    if kind != CommandKind::TheoreticalPrice {
+                        match decoding.format(kind) {
+                            PayloadFormat::Json => info!(
+                                "[INFO] Received message on topic '{}': {}",
+                                topic,
+                                String::from_utf8_lossy(payload)
+                            ),
+                            PayloadFormat::Protobuf => info!(
+                                "[INFO] Received {} byte protobuf message on topic '{}'",
+                                payload.len(),
+                                topic
+                            ),
+                            PayloadFormat::Avro => info!(
+                                "[INFO] Received {} byte Avro message on topic '{}'",
+                                payload.len(),
+                                topic
+                            ),
                         }
                     }
-                    "order.modify" => {
-                        info!(
-                            "[INFO] Received message on topic 'order.modify': {}",
-                            payload
-                        );
-                        match serde_json::from_str::<OrderModifyPayload>(payload) {
-                            Ok(modify_msg) => {
-                                let cmd = EngineCommand::OrderModify(modify_msg);
-                                if let Err(e) = tx.send(cmd).await {
-                                    warn!("Failed to send OrderModify to engine: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse order.modify payload: {}", e);
-                            }
+                    #[cfg(feature = "chaos")]
+                    let copies = match chaos::global().inbound() {
+                        chaos::Delivery::Delay(delay) => {
+                            tokio::time::sleep(delay).await;
+                            1
                         }
-                    }
-                    other => {
-                        warn!("[WARN] Received message on unknown topic: {}", other);
+                        delivery => delivery.copies(),
+                    };
+                    #[cfg(not(feature = "chaos"))]
+                    let copies = 1;
+                    // A dropped message is never parsed, a duplicated one is handled twice
+                    for _ in 0..copies {
+                        decoding
+                            .push(Inbound {
+                                topic: topic.to_string(),
+                                key: message
+                                    .key()
+                                    .and_then(|k| std::str::from_utf8(k).ok())
+                                    .map(str::to_string),
+                                kind,
+                                payload: payload.to_vec(),
+                            })
+                            .await;
                     }
                 }
+                Err(e) => eprintln!("Kafka error: {}", e),
             }
-            Err(e) => eprintln!("Kafka error: {}", e),
+        };
+        // Commands consumed before the stream stopped are still handled
+        while let Some(decoded) = decoding.next().await {
+            dispatch(
+                decoded,
+                &consumer,
+                &consumption,
+                &publisher,
+                &mut router,
+                &progress,
+                &dead_letters,
+                &topics.dead_letter,
+            )
+            .await;
+        }
+        if stopped {
+            break;
         }
+        // Resumes from the committed offsets, so commands consumed but not yet
+        // committed may be handled twice
+        drop(message_stream);
+        warn!("Recreating the Kafka consumer after a processing stall");
+        consumer = Arc::new(
+            create_consumer(&kafka_config, &subscriptions, &progress, &consumption)
+                .expect("Failed to create Kafka consumer"),
+        );
+        consumers.send_replace(consumer.clone());
     }
     info!("[INFO] Stream ended or consumer disconnected");
+    // Closing the command channels lets every engine apply what is queued,
+    // persist its books and stop; the publisher stops once the last publisher
+    // handle is gone
+    drop(router);
+    if let Some(watchdog) = watchdog {
+        watchdog.abort();
+    }
+    drop((publisher, dead_letters));
+    shutdown::drain(
+        engines,
+        publisher_task,
+        Duration::from_millis(engine_config.shutdown.drain_timeout_ms),
+    )
+    .await;
+}
+
+/// Hands a decoded message on: consumption commands to the consumer, other
+/// commands to the engine, and what failed to decode to the dead-letter topic
+#[allow(clippy::too_many_arguments)]
+async fn dispatch(
+    decoded: Decoded,
+    consumer: &CommandConsumer,
+    consumption: &ConsumptionControl,
+    publisher: &Publisher,
+    router: &mut ShardRouter,
+    progress: &Progress,
+    dead_letters: &Publisher,
+    dead_letter_topic: &str,
+) {
+    let Decoded { message, result } = decoded;
+    match result {
+        Ok(Some(EngineCommand::Admin(AdminCommandPayload::PauseConsumption {
+            topics,
+            partitions,
+        }))) => consumption.pause(consumer, publisher, &topics, &partitions),
+        Ok(Some(EngineCommand::Admin(AdminCommandPayload::ResumeConsumption {
+            topics,
+            partitions,
+        }))) => consumption.resume(consumer, publisher, &topics, &partitions),
+        Ok(Some(cmd)) => router.send(cmd, progress).await,
+        // Currently ignoring alert messages
+        Ok(None) => {}
+        Err(e) => {
+            warn!("Failed to parse {} payload: {}", message.topic, e);
+            dead_letters.publish(
+                dead_letter_topic,
+                message.key.as_deref().unwrap_or(&message.topic),
+                &DeadLetter::new(
+                    &message.topic,
+                    e.to_string(),
+                    &message.payload,
+                    current_time_millis(),
+                ),
+            );
+        }
+    }
+}
+
+/// Writes the logs `filter` lets through to stderr, as text or JSON lines
+fn init_logging(filter: Targets, format: LogFormat) {
+    use tracing_subscriber::prelude::*;
+    let layer = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+    };
+    tracing_subscriber::registry().with(layer).with(filter).init();
+}
+
+/// Consumes from the earliest offsets under a consumer group of its own, so the
+/// offsets of the live group are left alone, into books that start empty
+fn backfill(config: &mut AppConfig) {
+    config.kafka.group_id = format!("{}-backfill", config.kafka.group_id);
+    config.kafka.group_instance_id = None;
+    // The write-ahead log and evicted books belong to the live engine
+    config.engine.wal.enabled = false;
+    config.engine.tiering.enabled = false;
+}
+
+/// Previews commands under a consumer group of its own, so the offsets of the
+/// live group are left alone, against the books recovered from the
+/// write-ahead log, which it does not write to
+fn dry_run(config: &mut AppConfig) {
+    config.kafka.group_id = format!("{}-dry-run", config.kafka.group_id);
+    config.kafka.group_instance_id = None;
+    config.engine.dry_run.enabled = true;
+}
+
+/// `--mode replay` rebuilds the books of every shard from its write-ahead log
+/// and prints them, without connecting to Kafka
+fn replay_mode(config: &AppConfig) -> ! {
+    let shards = config.sharding.shards;
+    for shard in 0..shards {
+        let engine_config = shard_config(&config.engine, shard, shards);
+        let manager = engine::replay_journal(&engine_config);
+        println!(
+            "{}: {} books",
+            engine_config.wal.directory,
+            manager.book_count()
+        );
+        let mut symbols = manager.symbols();
+        symbols.sort();
+        for symbol in symbols {
+            let Some(book) = manager.get_book(&symbol) else {
+                continue;
+            };
+            let price = |price: Option<u64>| price.map_or("-".to_string(), |p| p.to_string());
+            println!(
+                "  {}: {} resting orders, bid {}, ask {}",
+                symbol,
+                book.get_all_orders().len(),
+                price(book.best_bid()),
+                price(book.best_ask())
+            );
+        }
+    }
+    std::process::exit(0);
+}
+
+/// The `soak [seconds]` subcommand drives the engine with generated load
+/// instead of Kafka, exiting with 1 if the run fails. The engine settings of
+/// `--config` apply.
+async fn soak_mode(
+    config: Result<AppConfig, AppConfigError>,
+    seconds: Option<u64>,
+    format: LogFormat,
+) -> ! {
+    // Per-command logs would drown the progress reports
+    init_logging(
+        Targets::new()
+            .with_default(tracing::Level::ERROR)
+            .with_target("orderbook_rust::soak", tracing::Level::INFO),
+        format,
+    );
+    let engine_config = match config {
+        Ok(config) => config.engine,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let mut config = SoakConfig::default();
+    if let Some(seconds) = seconds {
+        config.duration_secs = seconds;
+        // Leave most of a short run for measuring memory after warmup
+        config.warmup_secs = config.warmup_secs.min(seconds / 4);
+    }
+    let report = soak::run(config.clone(), engine_config).await;
+    std::process::exit(if soak::publish_report(&config, &report) {
+        0
+    } else {
+        1
+    });
+}
+
+/// `bench-book [commands] [resting orders]` measures a single book under
+/// generated load and prints the sustainable rate and latencies as JSON
+fn bench_mode(commands: Option<u64>, resting_orders: Option<usize>, format: LogFormat) -> ! {
+    init_logging(Targets::new().with_default(tracing::Level::ERROR), format);
+    let mut config = BenchConfig::default();
+    if let Some(commands) = commands {
+        config.commands = commands;
+    }
+    if let Some(resting_orders) = resting_orders {
+        config.resting_orders = resting_orders;
+    }
+    let report = bench::run(&config);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("report is valid JSON")
+    );
+    std::process::exit(0);
+}
+
+/// `journal verify` checks the write-ahead log of every shard and `journal
+/// compact` snapshots it and deletes the segments the snapshot covers, which
+/// needs the engine stopped. Exits with 1 if any log fails.
+fn journal_mode(
+    config: Result<AppConfig, AppConfigError>,
+    action: JournalAction,
+    format: LogFormat,
+) -> ! {
+    init_logging(Targets::new().with_default(tracing::Level::WARN), format);
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let shards = config.sharding.shards;
+    let mut failed = false;
+    for shard in 0..shards {
+        let engine_config = shard_config(&config.engine, shard, shards);
+        let directory = &engine_config.wal.directory;
+        if action == JournalAction::Compact {
+            match engine::compact_journal(&engine_config) {
+                Ok(compaction) => println!(
+                    "{}: removed {} segments ({} bytes)",
+                    directory, compaction.segments, compaction.bytes
+                ),
+                Err(e) => {
+                    println!("{}: not compacted, {}", directory, e);
+                    failed = true;
+                }
+            }
+            continue;
+        }
+        match wal::verify(Path::new(directory)) {
+            Ok(verification) => {
+                println!(
+                    "{}: {} segments, {} bytes, {} commands, snapshot {}, {} torn writes",
+                    directory,
+                    verification.segments,
+                    verification.bytes,
+                    verification.entries,
+                    verification
+                        .snapshot_seq
+                        .map_or("none".to_string(), |seq| format!("at command {seq}")),
+                    verification.torn_writes
+                );
+                for problem in &verification.problems {
+                    println!("  {}", problem);
+                }
+                failed |= !verification.problems.is_empty();
+            }
+            Err(e) => {
+                println!("{}: unreadable, {}", directory, e);
+                failed = true;
+            }
+        }
+    }
+    std::process::exit(i32::from(failed));
 }
@@ -0,0 +1,323 @@
+// src/mbo.rs
+use crate::config::mbo::MboConfig;
+use crate::orderbook::OrderBook;
+use crate::orderbook::book_change_event::{OrderChange, OrderChangedEvent};
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use pricelevel::{OrderId, Side};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// A change of a resting order, as published on the MBO topic
+#[derive(Debug, Serialize)]
+pub struct MboEvent {
+    pub instrument_id: String,
+    /// One more than the previous message of the instrument; a consumer that
+    /// sees a gap waits for the next snapshot
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub action: OrderChange,
+    pub order_id: OrderId,
+    pub side: Side,
+    pub price: u64,
+    /// Displayed quantity of the order once changed, 0 once it left the book
+    pub quantity: u64,
+    /// Quantity traded, for executions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executed: Option<u64>,
+    /// Time priority of the order at its price level: lower fills first, and
+    /// orders of equal priority in the order they were added; 0 for an
+    /// execution that filled the order
+    pub priority: u64,
+}
+
+/// A resting order in an MBO snapshot
+#[derive(Debug, Serialize)]
+pub struct MboOrder {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub price: u64,
+    pub quantity: u64,
+    pub priority: u64,
+}
+
+/// Every resting order of a book, replacing all seen before
+#[derive(Debug, Serialize)]
+pub struct MboSnapshot {
+    pub instrument_id: String,
+    pub sequence: u64,
+    pub timestamp: u64,
+    /// Best price first on each side, then in queue order
+    pub orders: Vec<MboOrder>,
+}
+
+/// A message of the MBO feed
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MboMessage {
+    Order(MboEvent),
+    Snapshot(MboSnapshot),
+}
+
+struct Change {
+    instrument_id: String,
+    timestamp: u64,
+    event: OrderChangedEvent,
+}
+
+/// Publishes a market-by-order feed of every book
+///
+/// Books report the orders they add, modify, delete and execute through their
+/// order listener while a command is processed; the engine drains them once
+/// the command is done, as it does the level changes. Each instrument's
+/// messages are numbered in one sequence, snapshots included: a consumer
+/// joining late rebuilds the queues from the first snapshot it reads and
+/// applies the events that follow. A snapshot is published when a book is
+/// attached, every `snapshot_interval_ms` after, and, empty, when it is
+/// deleted.
+pub struct MboPublisher {
+    config: MboConfig,
+    sender: Sender<Change>,
+    receiver: Receiver<Change>,
+    attached: HashSet<String>,
+    /// Sequence of the last message of each instrument, kept while its book
+    /// is evicted
+    sequences: HashMap<String, u64>,
+    snapshot_at: HashMap<String, u64>,
+}
+
+impl MboPublisher {
+    pub fn new(config: MboConfig) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        Self {
+            config,
+            sender,
+            receiver,
+            attached: HashSet::new(),
+            sequences: HashMap::new(),
+            snapshot_at: HashMap::new(),
+        }
+    }
+
+    /// Installs the order listener on a book not attached yet and publishes
+    /// its resting orders
+    pub fn attach(&mut self, book: &mut OrderBook<OrderTags>, publisher: &Publisher, now: u64) {
+        if !self.config.enabled || self.attached.contains(book.symbol()) {
+            return;
+        }
+        let sender = self.sender.clone();
+        let instrument_id = book.symbol().to_string();
+        book.set_order_listener(Arc::new(move |event: OrderChangedEvent| {
+            let _ = sender.send(Change {
+                instrument_id: instrument_id.clone(),
+                timestamp: current_time_millis(),
+                event,
+            });
+        }));
+        self.attached.insert(book.symbol().to_string());
+        self.publish_snapshot(book, publisher, now);
+    }
+
+    /// Forgets a book evicted to disk, which is attached again once loaded
+    pub fn detach(&mut self, instrument_id: &str) {
+        self.attached.remove(instrument_id);
+    }
+
+    /// Publishes the order changes reported since the last drain
+    pub fn drain(&mut self, publisher: &Publisher) {
+        for Change {
+            instrument_id,
+            timestamp,
+            event,
+        } in self.receiver.try_iter()
+        {
+            let sequence = self.sequences.entry(instrument_id.clone()).or_default();
+            *sequence += 1;
+            let message = MboMessage::Order(MboEvent {
+                instrument_id: instrument_id.clone(),
+                sequence: *sequence,
+                timestamp,
+                action: event.change,
+                order_id: event.order_id,
+                side: event.side,
+                price: event.price,
+                quantity: event.quantity,
+                executed: (event.change == OrderChange::Executed).then_some(event.executed),
+                priority: event.priority,
+            });
+            publisher.publish(&self.config.topic, &instrument_id, &message);
+        }
+    }
+
+    /// Publishes the snapshots that are due
+    pub fn on_tick(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        publisher: &Publisher,
+        now: u64,
+    ) {
+        let due: Vec<String> = self
+            .attached
+            .iter()
+            .filter(|instrument_id| {
+                self.snapshot_at
+                    .get(*instrument_id)
+                    .is_none_or(|at| now.saturating_sub(*at) >= self.config.snapshot_interval_ms)
+            })
+            .cloned()
+            .collect();
+        for instrument_id in due {
+            if let Some(book) = manager.get_book(&instrument_id) {
+                self.publish_snapshot(book, publisher, now);
+            }
+        }
+    }
+
+    /// Publishes an empty snapshot for a deleted instrument, after which it
+    /// is no longer carried
+    pub fn forget(&mut self, instrument_id: &str, publisher: &Publisher, now: u64) {
+        if self.attached.remove(instrument_id) {
+            self.publish(instrument_id, Vec::new(), publisher, now);
+        }
+        self.sequences.remove(instrument_id);
+        self.snapshot_at.remove(instrument_id);
+    }
+
+    fn publish_snapshot(&mut self, book: &OrderBook<OrderTags>, publisher: &Publisher, now: u64) {
+        let mut orders: Vec<MboOrder> = book
+            .get_all_orders()
+            .iter()
+            .map(|order| MboOrder {
+                order_id: order.id(),
+                side: order.side(),
+                price: order.price(),
+                quantity: order.visible_quantity(),
+                priority: order.timestamp(),
+            })
+            .collect();
+        orders.sort_by_key(|order| {
+            let rank = match order.side {
+                Side::Buy => u64::MAX - order.price,
+                Side::Sell => order.price,
+            };
+            (order.side == Side::Sell, rank, order.priority)
+        });
+        self.publish(book.symbol(), orders, publisher, now);
+    }
+
+    fn publish(
+        &mut self,
+        instrument_id: &str,
+        orders: Vec<MboOrder>,
+        publisher: &Publisher,
+        now: u64,
+    ) {
+        // Changes made before the snapshot go out ahead of it
+        self.drain(publisher);
+        let sequence = self.sequences.entry(instrument_id.to_string()).or_default();
+        *sequence += 1;
+        let snapshot = MboMessage::Snapshot(MboSnapshot {
+            instrument_id: instrument_id.to_string(),
+            sequence: *sequence,
+            timestamp: now,
+            orders,
+        });
+        publisher.publish(&self.config.topic, instrument_id, &snapshot);
+        self.snapshot_at.insert(instrument_id.to_string(), now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::publisher::OutboundMessage;
+    use pricelevel::TimeInForce;
+    use tokio::sync::mpsc;
+
+    fn drain(rx: &mut mpsc::Receiver<OutboundMessage>) -> Vec<serde_json::Value> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|message| {
+                assert_eq!(
+                    (message.topic.as_str(), message.key.as_str()),
+                    ("marketdata.mbo", "BTC")
+                );
+                serde_json::from_str(&message.payload).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_order_changes_are_published_from_the_book() {
+        let (publisher, mut rx) = Publisher::channel(16);
+        let mut mbo = MboPublisher::new(MboConfig {
+            enabled: true,
+            ..MboConfig::default()
+        });
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let book = manager.get_book_mut("BTC").unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            101,
+            5,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        mbo.attach(book, &publisher, 1);
+        let messages = drain(&mut rx);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            (&messages[0]["type"], &messages[0]["sequence"]),
+            (&"snapshot".into(), &1.into())
+        );
+        assert_eq!(messages[0]["orders"][0]["quantity"], 5);
+
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            101,
+            4,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.match_market_order(OrderId::from_u64(3), 7, Side::Buy)
+            .unwrap();
+        book.cancel_order(OrderId::from_u64(2)).unwrap();
+        mbo.drain(&publisher);
+        let events: Vec<_> = drain(&mut rx)
+            .into_iter()
+            .map(|event| {
+                assert_eq!(event["type"], "order");
+                (
+                    event["sequence"].as_u64().unwrap(),
+                    event["action"].as_str().unwrap().to_string(),
+                    event["order_id"].clone(),
+                    event["quantity"].as_u64().unwrap(),
+                    event["executed"].as_u64(),
+                )
+            })
+            .collect();
+        let id = |id: u64| serde_json::to_value(OrderId::from_u64(id)).unwrap();
+        assert_eq!(
+            events,
+            [
+                (2, "added".to_string(), id(2), 4, None),
+                (3, "executed".to_string(), id(1), 0, Some(5)),
+                (4, "executed".to_string(), id(2), 2, Some(2)),
+                (5, "deleted".to_string(), id(2), 0, None),
+            ]
+        );
+
+        mbo.forget("BTC", &publisher, 2);
+        let messages = drain(&mut rx);
+        assert_eq!(messages[0]["sequence"], 6);
+        assert_eq!(messages[0]["orders"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,125 @@
+// src/metrics.rs
+use crate::utils::current_time_millis;
+use crate::watchdog::Progress;
+use std::fmt::Write;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+/// Serves the pipeline counters in the Prometheus text format on `/metrics`
+/// until the process exits
+pub async fn serve(port: u16, progress: Progress) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Failed to serve metrics on port {}: {}", port, e);
+            return;
+        }
+    };
+    info!("Serving metrics on port {}", port);
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let progress = progress.clone();
+        tokio::spawn(async move {
+            let mut request = [0u8; 1_024];
+            let Ok(read) = stream.read(&mut request).await else {
+                return;
+            };
+            let response = if request[..read].starts_with(b"GET /metrics ") {
+                let body = render(&progress, current_time_millis());
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string()
+            };
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// The counters as Prometheus text
+fn render(progress: &Progress, now: u64) -> String {
+    let last_applied_at = progress.last_applied_at();
+    let idle_seconds = if last_applied_at == 0 {
+        0.0
+    } else {
+        now.saturating_sub(last_applied_at) as f64 / 1_000.0
+    };
+    let mut text = String::new();
+    for (name, kind, help, value) in [
+        (
+            "orderbook_commands_received_total",
+            "counter",
+            "Commands handed to the engine",
+            progress.commands_received() as f64,
+        ),
+        (
+            "orderbook_commands_applied_total",
+            "counter",
+            "Commands the engine finished applying",
+            progress.commands_applied() as f64,
+        ),
+        (
+            "orderbook_duplicate_commands_total",
+            "counter",
+            "Commands the engine dropped as redeliveries of earlier ones",
+            progress.duplicates_dropped() as f64,
+        ),
+        (
+            "orderbook_delayed_messages_dropped_total",
+            "counter",
+            "Messages delayed feeds dropped because their delay buffer was full",
+            progress.delayed_messages_dropped() as f64,
+        ),
+        (
+            "orderbook_commands_queued",
+            "gauge",
+            "Commands handed to the engine and not applied yet",
+            progress.queued() as f64,
+        ),
+        (
+            "orderbook_seconds_since_last_applied",
+            "gauge",
+            "Time since the engine last applied a command, 0 before the first",
+            idle_seconds,
+        ),
+    ] {
+        let _ = writeln!(
+            text,
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_reports_the_pipeline_counters() {
+        let progress = Progress::default();
+        assert!(render(&progress, 5_000).contains("\norderbook_seconds_since_last_applied 0\n"));
+        progress.received();
+        progress.received();
+        progress.applied(1_000);
+        progress.received();
+        progress.duplicate();
+        progress.delayed_dropped(4);
+
+        let text = render(&progress, 3_500);
+        assert!(text.contains("# TYPE orderbook_commands_received_total counter\n"));
+        assert!(text.contains("\norderbook_commands_received_total 3\n"));
+        assert!(text.contains("\norderbook_commands_applied_total 2\n"));
+        assert!(text.contains("\norderbook_duplicate_commands_total 1\n"));
+        assert!(text.contains("\norderbook_delayed_messages_dropped_total 4\n"));
+        assert!(text.contains("\norderbook_commands_queued 1\n"));
+        assert!(text.contains("\norderbook_seconds_since_last_applied 2.5\n"));
+    }
+}
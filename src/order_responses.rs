@@ -0,0 +1,272 @@
+// src/order_responses.rs
+use crate::config::order_responses::OrderResponseConfig;
+use crate::helpers::EngineCommand;
+use crate::orderbook::OrderBookError;
+use crate::publisher::Publisher;
+use crate::sinks::OrderAction;
+use serde::Serialize;
+
+/// Why an order command was not applied
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    UnknownInstrument,
+    /// No resting order with the order id or client order id given
+    UnknownOrder,
+    /// The client order id is in use by another resting order of the participant
+    DuplicateClientOrderId,
+    InvalidOrder,
+    /// A fill-or-kill or market order the book cannot fill
+    InsufficientLiquidity,
+    TradingHalted,
+    BelowMinimumNotional,
+    PriceCrossing,
+    /// A limit price outside the price band of the instrument
+    OutsidePriceBand,
+    /// A limit price that is not a multiple of the instrument's tick size
+    InvalidTickSize,
+    /// A quantity that is not a multiple of the instrument's lot size
+    InvalidLotSize,
+    /// A quantity outside the instrument's minimum and maximum order quantity
+    QuantityOutOfRange,
+    /// The participant is over its order-to-trade limit
+    Throttled,
+    /// The order would take its participant past its open order or open
+    /// notional limit
+    RiskLimitExceeded,
+    /// The kill switch of the participant is engaged
+    KillSwitchEngaged,
+    /// A redelivery of a command received before, which was answered then
+    Duplicate,
+    /// A command behind its instrument's sequence: a redelivery, or one given
+    /// up on when the gap before it was skipped
+    OutOfSequence,
+    Internal,
+}
+
+impl From<&OrderBookError> for RejectReason {
+    fn from(error: &OrderBookError) -> Self {
+        match error {
+            OrderBookError::OrderNotFound(_) => RejectReason::UnknownOrder,
+            OrderBookError::PriceCrossing { .. } => RejectReason::PriceCrossing,
+            OrderBookError::InsufficientLiquidity { .. } => RejectReason::InsufficientLiquidity,
+            OrderBookError::BelowMinimumNotional { .. } => RejectReason::BelowMinimumNotional,
+            OrderBookError::InstrumentHalted { .. } => RejectReason::TradingHalted,
+            OrderBookError::PriceOffTick { .. } => RejectReason::InvalidTickSize,
+            OrderBookError::QuantityOffLot { .. } => RejectReason::InvalidLotSize,
+            OrderBookError::BelowMinimumQuantity { .. }
+            | OrderBookError::AboveMaximumQuantity { .. } => RejectReason::QuantityOutOfRange,
+            OrderBookError::PriceLevelError(_)
+            | OrderBookError::InvalidPriceLevel(_)
+            | OrderBookError::InvalidOperation { .. }
+            | OrderBookError::BlockTradeRejected { .. } => RejectReason::InvalidOrder,
+            OrderBookError::SerializationError { .. }
+            | OrderBookError::DeserializationError { .. }
+            | OrderBookError::ChecksumMismatch { .. } => RejectReason::Internal,
+        }
+    }
+}
+
+/// An order command the engine refused, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub reason: RejectReason,
+    pub message: String,
+}
+
+impl Rejection {
+    pub fn new(reason: RejectReason, message: impl Into<String>) -> Self {
+        Self {
+            reason,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<OrderBookError> for Rejection {
+    fn from(error: OrderBookError) -> Self {
+        Self::new(RejectReason::from(&error), error.to_string())
+    }
+}
+
+/// The order command an ack or reject answers
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderRequest {
+    pub instrument_id: String,
+    pub order_id: u64,
+    pub action: OrderAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub participant_id: Option<String>,
+}
+
+impl OrderRequest {
+    /// The request a command makes, if it is an order command
+    pub fn of(cmd: &EngineCommand) -> Option<Self> {
+        let (instrument_id, order_id, action, client_order_id, participant_id) = match cmd {
+            // Answered under the client order id of the replacement
+            EngineCommand::OrderReplace(replace) => (
+                &replace.instrument_id,
+                replace.order_id,
+                OrderAction::Replace,
+                match &replace.new_client_order_id {
+                    Some(_) => &replace.new_client_order_id,
+                    None => &replace.client_order_id,
+                },
+                &replace.participant_id,
+            ),
+            EngineCommand::OrderCreate(order) => (
+                &order.instrument_id,
+                order.order_id,
+                OrderAction::Create,
+                &order.client_order_id,
+                &order.participant_id,
+            ),
+            EngineCommand::OrderModify(order) => (
+                &order.instrument_id,
+                order.order_id,
+                OrderAction::Modify,
+                &order.client_order_id,
+                &order.participant_id,
+            ),
+            EngineCommand::OrderCancel(order) => (
+                &order.instrument_id,
+                order.order_id,
+                OrderAction::Cancel,
+                &order.client_order_id,
+                &order.participant_id,
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            instrument_id: instrument_id.clone(),
+            order_id,
+            action,
+            client_order_id: client_order_id.clone(),
+            participant_id: participant_id.clone(),
+        })
+    }
+}
+
+/// Published on `order_responses.ack_topic` once an order command is applied
+#[derive(Debug, Serialize)]
+pub struct OrderAck<'a> {
+    #[serde(flatten)]
+    pub request: &'a OrderRequest,
+    pub timestamp: u64,
+}
+
+/// Published on `order_responses.reject_topic` for an order command that was
+/// not applied
+#[derive(Debug, Serialize)]
+pub struct OrderReject<'a> {
+    #[serde(flatten)]
+    pub request: &'a OrderRequest,
+    pub reason: RejectReason,
+    pub message: &'a str,
+    pub timestamp: u64,
+}
+
+/// Tells upstream OMSes whether each of their order commands was applied
+///
+/// Creates, modifies, cancels and replaces are answered with an ack once applied, or a
+/// reject carrying the reason they were not. An IOC order whose unfilled part
+/// is cancelled was still applied, and is acked. Responses are keyed by
+/// instrument, as the events of the orders they answer are.
+pub struct OrderResponder {
+    config: OrderResponseConfig,
+}
+
+impl OrderResponder {
+    pub fn new(config: OrderResponseConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn respond(
+        &self,
+        publisher: &Publisher,
+        request: &OrderRequest,
+        outcome: &Result<(), Rejection>,
+        now: u64,
+    ) {
+        if !self.config.enabled {
+            return;
+        }
+        match outcome {
+            Ok(()) => publisher.publish(
+                &self.config.ack_topic,
+                &request.instrument_id,
+                &OrderAck {
+                    request,
+                    timestamp: now,
+                },
+            ),
+            Err(rejection) => publisher.publish(
+                &self.config.reject_topic,
+                &request.instrument_id,
+                &OrderReject {
+                    request,
+                    reason: rejection.reason,
+                    message: &rejection.message,
+                    timestamp: now,
+                },
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::OrderCancelPayload;
+
+    #[test]
+    fn test_rejects_carry_the_request_and_reason() {
+        let (publisher, mut outbound) = Publisher::channel(8);
+        let responder = OrderResponder::new(OrderResponseConfig {
+            enabled: true,
+            ..OrderResponseConfig::default()
+        });
+        let request = OrderRequest::of(&EngineCommand::OrderCancel(OrderCancelPayload {
+            order_id: 0,
+            instrument_id: "BTC".to_string(),
+            client_order_id: Some("abc".to_string()),
+            participant_id: Some("desk-a".to_string()),
+            sequence: None,
+        }))
+        .unwrap();
+
+        let rejection = Rejection::from(OrderBookError::OrderNotFound("7".to_string()));
+        responder.respond(&publisher, &request, &Err(rejection), 5);
+        let message = outbound.try_recv().unwrap();
+        assert_eq!(
+            (message.topic.as_str(), message.key.as_str()),
+            ("order.reject", "BTC")
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&message.payload).unwrap(),
+            serde_json::json!({
+                "instrument_id": "BTC",
+                "order_id": 0,
+                "action": "cancel",
+                "client_order_id": "abc",
+                "participant_id": "desk-a",
+                "reason": "unknown_order",
+                "message": "Order not found: 7",
+                "timestamp": 5,
+            })
+        );
+
+        responder.respond(&publisher, &request, &Ok(()), 6);
+        assert_eq!(outbound.try_recv().unwrap().topic, "order.ack");
+
+        OrderResponder::new(OrderResponseConfig::default()).respond(
+            &publisher,
+            &request,
+            &Ok(()),
+            7,
+        );
+        assert!(outbound.try_recv().is_err());
+    }
+}
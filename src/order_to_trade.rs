@@ -0,0 +1,379 @@
+// src/order_to_trade.rs
+use crate::config::order_to_trade::{OrderToTradeConfig, OtrAction};
+use crate::publisher::Publisher;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::{info, warn};
+
+/// Order-to-trade ratio of a participant on an instrument, as published
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrderToTradeRatio {
+    pub participant_id: String,
+    pub instrument_id: String,
+    pub window_secs: u64,
+    /// Creates, modifies and cancels within the window
+    pub order_messages: u64,
+    /// Fills the participant took part in within the window, either side
+    pub trades: u64,
+    /// Order messages per trade, or the message count when nothing traded
+    pub ratio: f64,
+    pub breached: bool,
+    pub timestamp: u64,
+}
+
+/// Why a participant's new orders on an instrument are refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleReason {
+    /// The participant is over its order-to-trade limit
+    OrderToTrade,
+}
+
+/// Throttle state of a participant on an instrument, published when the
+/// throttle starts, on each evaluation it holds, and when it ends, so gateways
+/// can hold orders rather than send them to be rejected
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThrottleState {
+    pub participant_id: String,
+    pub instrument_id: String,
+    pub throttled: bool,
+    /// Earliest time the throttle may lift, when it is next evaluated; the
+    /// time it lifted once it has
+    pub until_ts: u64,
+    pub reason: ThrottleReason,
+    pub timestamp: u64,
+}
+
+#[derive(Default)]
+struct Bucket {
+    start: u64,
+    messages: u64,
+    trades: u64,
+}
+
+/// Counts of one participant on one instrument, a bucket per `bucket_secs`
+#[derive(Default)]
+struct Window {
+    buckets: VecDeque<Bucket>,
+}
+
+impl Window {
+    fn bucket(&mut self, start: u64) -> &mut Bucket {
+        if self
+            .buckets
+            .back()
+            .is_none_or(|bucket| bucket.start < start)
+        {
+            self.buckets.push_back(Bucket {
+                start,
+                ..Bucket::default()
+            });
+        }
+        // Counts arriving out of order land in the latest bucket
+        self.buckets.back_mut().expect("bucket was just pushed")
+    }
+
+    fn expire(&mut self, oldest: u64) {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| bucket.start < oldest)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn totals(&self) -> (u64, u64) {
+        self.buckets
+            .iter()
+            .fold((0, 0), |(messages, trades), bucket| {
+                (messages + bucket.messages, trades + bucket.trades)
+            })
+    }
+}
+
+/// Tracks order-to-trade ratios over a rolling window and enforces the limit
+///
+/// Ratios are evaluated on each tick rather than on each message, so a breach
+/// starts and ends at tick boundaries.
+pub struct OrderToTradeMonitor {
+    config: OrderToTradeConfig,
+    /// Keyed by participant and instrument
+    windows: HashMap<(String, String), Window>,
+    breached: HashSet<(String, String)>,
+    next_evaluation_at: u64,
+}
+
+impl OrderToTradeMonitor {
+    pub fn new(config: OrderToTradeConfig, now: u64) -> Self {
+        let next_evaluation_at = now + config.evaluation_interval_ms;
+        Self {
+            config,
+            windows: HashMap::new(),
+            breached: HashSet::new(),
+            next_evaluation_at,
+        }
+    }
+
+    fn bucket_start(&self, now: u64) -> u64 {
+        let bucket_ms = self.config.bucket_secs.max(1) * 1_000;
+        now / bucket_ms * bucket_ms
+    }
+
+    fn bucket(&mut self, participant_id: &str, instrument_id: &str, now: u64) -> &mut Bucket {
+        let start = self.bucket_start(now);
+        self.windows
+            .entry((participant_id.to_string(), instrument_id.to_string()))
+            .or_default()
+            .bucket(start)
+    }
+
+    /// Counts an order create, modify, cancel or replace
+    pub fn record_message(&mut self, participant_id: &str, instrument_id: &str, now: u64) {
+        if self.config.enabled {
+            self.bucket(participant_id, instrument_id, now).messages += 1;
+        }
+    }
+
+    /// Counts a fill the participant was a side of
+    pub fn record_trade(&mut self, participant_id: &str, instrument_id: &str, now: u64) {
+        if self.config.enabled {
+            self.bucket(participant_id, instrument_id, now).trades += 1;
+        }
+    }
+
+    fn is_breached(&self, participant_id: &str, instrument_id: &str) -> bool {
+        self.breached
+            .contains(&(participant_id.to_string(), instrument_id.to_string()))
+    }
+
+    /// Whether new orders of the participant on the instrument are rejected
+    pub fn throttled(&self, participant_id: &str, instrument_id: &str) -> bool {
+        self.config.action == OtrAction::Throttle && self.is_breached(participant_id, instrument_id)
+    }
+
+    /// Whether trades of the participant on the instrument carry a surcharge
+    pub fn surcharged(&self, participant_id: &str, instrument_id: &str) -> bool {
+        self.config.action == OtrAction::Surcharge
+            && self.is_breached(participant_id, instrument_id)
+    }
+
+    /// Ratios of every participant active within the window, updating breaches
+    pub fn evaluate(&mut self, now: u64) -> Vec<OrderToTradeRatio> {
+        let window_ms = self.config.window_secs * 1_000;
+        let oldest = self.bucket_start(now.saturating_sub(window_ms));
+        let mut ratios = Vec::new();
+        let mut breached = HashSet::new();
+        self.windows
+            .retain(|(participant_id, instrument_id), window| {
+                window.expire(oldest);
+                let (order_messages, trades) = window.totals();
+                if order_messages == 0 && trades == 0 {
+                    return false;
+                }
+                let ratio = order_messages as f64 / trades.max(1) as f64;
+                let is_breached =
+                    order_messages >= self.config.min_messages && ratio > self.config.max_ratio;
+                if is_breached {
+                    breached.insert((participant_id.clone(), instrument_id.clone()));
+                }
+                ratios.push(OrderToTradeRatio {
+                    participant_id: participant_id.clone(),
+                    instrument_id: instrument_id.clone(),
+                    window_secs: self.config.window_secs,
+                    order_messages,
+                    trades,
+                    ratio,
+                    breached: is_breached,
+                    timestamp: now,
+                });
+                true
+            });
+        for (participant_id, instrument_id) in breached.difference(&self.breached) {
+            warn!(
+                "{} exceeds the order-to-trade limit of {} on {}, applying {:?}",
+                participant_id, self.config.max_ratio, instrument_id, self.config.action
+            );
+        }
+        for (participant_id, instrument_id) in self.breached.difference(&breached) {
+            info!(
+                "{} is back within the order-to-trade limit on {}",
+                participant_id, instrument_id
+            );
+        }
+        self.breached = breached;
+        ratios.sort_by(|a, b| {
+            (&a.participant_id, &a.instrument_id).cmp(&(&b.participant_id, &b.instrument_id))
+        });
+        ratios
+    }
+
+    /// Throttle states after an evaluation: every pair throttled now, and every
+    /// pair of `previously` breached that no longer is
+    fn throttle_states(
+        &self,
+        previously: &HashSet<(String, String)>,
+        now: u64,
+    ) -> Vec<ThrottleState> {
+        if self.config.action != OtrAction::Throttle {
+            return Vec::new();
+        }
+        let state = |(participant_id, instrument_id): &(String, String), throttled, until_ts| {
+            ThrottleState {
+                participant_id: participant_id.clone(),
+                instrument_id: instrument_id.clone(),
+                throttled,
+                until_ts,
+                reason: ThrottleReason::OrderToTrade,
+                timestamp: now,
+            }
+        };
+        let mut states: Vec<ThrottleState> = self
+            .breached
+            .iter()
+            .map(|pair| state(pair, true, self.next_evaluation_at))
+            .chain(
+                previously
+                    .difference(&self.breached)
+                    .map(|pair| state(pair, false, now)),
+            )
+            .collect();
+        states.sort_by(|a, b| {
+            (&a.participant_id, &a.instrument_id).cmp(&(&b.participant_id, &b.instrument_id))
+        });
+        states
+    }
+
+    /// Evaluates and publishes the ratios once the evaluation interval has
+    /// passed, along with the throttle states they lead to
+    pub fn on_tick(&mut self, publisher: &Publisher, now: u64) {
+        if !self.config.enabled || now < self.next_evaluation_at {
+            return;
+        }
+        self.next_evaluation_at = now + self.config.evaluation_interval_ms;
+        let previously = self.breached.clone();
+        for ratio in self.evaluate(now) {
+            publisher.publish(&self.config.metrics_topic, &ratio.participant_id, &ratio);
+        }
+        for state in self.throttle_states(&previously, now) {
+            publisher.publish(&self.config.throttle_topic, &state.participant_id, &state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(action: OtrAction) -> OrderToTradeMonitor {
+        OrderToTradeMonitor::new(
+            OrderToTradeConfig {
+                enabled: true,
+                window_secs: 60,
+                bucket_secs: 10,
+                max_ratio: 5.0,
+                min_messages: 10,
+                action,
+                ..OrderToTradeConfig::default()
+            },
+            0,
+        )
+    }
+
+    #[test]
+    fn test_ratio_per_participant_and_instrument() {
+        let mut otr = monitor(OtrAction::Monitor);
+        for _ in 0..12 {
+            otr.record_message("mm-1", "BTC", 1_000);
+        }
+        otr.record_trade("mm-1", "BTC", 2_000);
+        otr.record_trade("mm-1", "BTC", 2_000);
+        // Below the minimum, never in breach however high the ratio
+        for _ in 0..9 {
+            otr.record_message("mm-1", "ETH", 1_000);
+        }
+
+        let ratios = otr.evaluate(5_000);
+        assert_eq!(ratios.len(), 2);
+        assert_eq!(ratios[0].instrument_id, "BTC");
+        assert_eq!(ratios[0].order_messages, 12);
+        assert_eq!(ratios[0].trades, 2);
+        assert_eq!(ratios[0].ratio, 6.0);
+        assert!(ratios[0].breached);
+        assert_eq!(ratios[1].ratio, 9.0);
+        assert!(!ratios[1].breached);
+        // Monitoring only reports
+        assert!(!otr.throttled("mm-1", "BTC"));
+        assert!(!otr.surcharged("mm-1", "BTC"));
+    }
+
+    #[test]
+    fn test_window_rolls_off_old_counts() {
+        let mut otr = monitor(OtrAction::Throttle);
+        for _ in 0..20 {
+            otr.record_message("mm-1", "BTC", 5_000);
+        }
+        otr.evaluate(6_000);
+        assert!(otr.throttled("mm-1", "BTC"));
+        assert!(!otr.throttled("mm-2", "BTC"));
+
+        otr.record_trade("mm-1", "BTC", 30_000);
+        // The bucket holding the messages leaves the window after 60 s
+        let ratios = otr.evaluate(70_000);
+        assert_eq!(ratios[0].order_messages, 0);
+        assert_eq!(ratios[0].trades, 1);
+        assert!(!otr.throttled("mm-1", "BTC"));
+        assert!(otr.evaluate(100_000).is_empty());
+    }
+
+    #[test]
+    fn test_breach_surcharges_and_publishes() {
+        let mut otr = monitor(OtrAction::Surcharge);
+        let (publisher, mut outbound) = Publisher::channel(8);
+        for _ in 0..10 {
+            otr.record_message("mm-1", "BTC", 1_000);
+        }
+        otr.on_tick(&publisher, 1_000);
+        assert!(outbound.try_recv().is_err());
+
+        otr.on_tick(&publisher, 5_000);
+        let message = outbound.try_recv().unwrap();
+        assert_eq!(message.topic, "metrics.order_to_trade");
+        assert_eq!(message.key, "mm-1");
+        assert!(otr.surcharged("mm-1", "BTC"));
+        assert!(!otr.throttled("mm-1", "BTC"));
+        // Surcharged participants may keep sending orders
+        assert!(outbound.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_throttle_states_are_published_until_the_throttle_lifts() {
+        let mut otr = monitor(OtrAction::Throttle);
+        let (publisher, mut outbound) = Publisher::channel(8);
+        let mut throttle_states = |otr: &mut OrderToTradeMonitor, now| {
+            otr.on_tick(&publisher, now);
+            std::iter::from_fn(|| outbound.try_recv().ok())
+                .filter(|message| message.topic == "order.throttle")
+                .map(|message| serde_json::from_str::<serde_json::Value>(&message.payload).unwrap())
+                .map(|state| {
+                    (
+                        state["throttled"].as_bool().unwrap(),
+                        state["until_ts"].as_u64().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        for _ in 0..10 {
+            otr.record_message("mm-1", "BTC", 1_000);
+        }
+        // Held until the next evaluation, then for another interval while it lasts
+        assert_eq!(throttle_states(&mut otr, 5_000), [(true, 10_000)]);
+        assert_eq!(throttle_states(&mut otr, 10_000), [(true, 15_000)]);
+
+        for _ in 0..2 {
+            otr.record_trade("mm-1", "BTC", 12_000);
+        }
+        assert_eq!(throttle_states(&mut otr, 15_000), [(false, 15_000)]);
+        assert!(throttle_states(&mut otr, 20_000).is_empty());
+    }
+}
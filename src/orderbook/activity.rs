@@ -0,0 +1,171 @@
+//! Counters of the operations applied to an order book
+//!
+//! They are embedded in enriched snapshots, so a snapshot tells how active the book
+//! has been and how recently it last changed without any other context.
+
+use super::OrderBook;
+use crate::utils::current_time_millis;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Processing statistics of a book since it was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityStats {
+    /// Orders accepted by the book, whether they rested or filled on arrival
+    pub orders_added: u64,
+
+    /// Resting orders cancelled
+    pub orders_cancelled: u64,
+
+    /// Resting orders whose price or quantity was updated
+    pub orders_modified: u64,
+
+    /// Fills against resting orders
+    pub trades: u64,
+
+    /// Unix timestamp in milliseconds of the last operation counted, 0 before any
+    pub last_command_timestamp: u64,
+
+    /// Sequence number of the last operation counted; each add, cancel, modify or
+    /// standalone match takes the next one, starting at 1
+    pub last_sequence: u64,
+}
+
+/// Live counters behind [`ActivityStats`]
+#[derive(Debug, Default)]
+pub(super) struct BookActivity {
+    orders_added: AtomicU64,
+    orders_cancelled: AtomicU64,
+    orders_modified: AtomicU64,
+    trades: AtomicU64,
+    last_command_timestamp: AtomicU64,
+    sequence: AtomicU64,
+}
+
+impl BookActivity {
+    fn counted(&self, counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.sequence.fetch_add(1, Ordering::Relaxed);
+        self.last_command_timestamp
+            .store(current_time_millis(), Ordering::Relaxed);
+    }
+
+    pub(super) fn order_added(&self) {
+        self.counted(&self.orders_added);
+    }
+
+    pub(super) fn order_cancelled(&self) {
+        self.counted(&self.orders_cancelled);
+    }
+
+    pub(super) fn order_modified(&self) {
+        self.counted(&self.orders_modified);
+    }
+
+    /// Counts fills; they belong to the add or match that caused them, which
+    /// takes the sequence number
+    pub(super) fn traded(&self, fills: u64) {
+        self.trades.fetch_add(fills, Ordering::Relaxed);
+    }
+
+    /// Counts a match submitted on its own, e.g. a market order
+    pub(super) fn matched(&self) {
+        self.sequence.fetch_add(1, Ordering::Relaxed);
+        self.last_command_timestamp
+            .store(current_time_millis(), Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> ActivityStats {
+        ActivityStats {
+            orders_added: self.orders_added.load(Ordering::Relaxed),
+            orders_cancelled: self.orders_cancelled.load(Ordering::Relaxed),
+            orders_modified: self.orders_modified.load(Ordering::Relaxed),
+            trades: self.trades.load(Ordering::Relaxed),
+            last_command_timestamp: self.last_command_timestamp.load(Ordering::Relaxed),
+            last_sequence: self.sequence.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Get the processing statistics of this book
+    pub fn activity_stats(&self) -> ActivityStats {
+        self.activity.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, OrderUpdate, Side, TimeInForce};
+
+    #[test]
+    fn test_activity_counts_each_operation_once() {
+        let book = OrderBook::<()>::new("BTC");
+        assert_eq!(book.activity_stats(), ActivityStats::default());
+
+        for (id, price, side) in [
+            (1, 100, Side::Buy),
+            (2, 101, Side::Sell),
+            (3, 99, Side::Buy),
+        ] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                10,
+                side,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        // A price update replaces the order internally, but is one modification
+        book.update_order(OrderUpdate::UpdatePrice {
+            order_id: OrderId::from_u64(3),
+            new_price: 98,
+        })
+        .unwrap();
+        book.cancel_order(OrderId::from_u64(3)).unwrap();
+        // Crosses order 2 and rests the remainder
+        book.add_limit_order(
+            OrderId::from_u64(4),
+            101,
+            15,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.submit_market_order(OrderId::from_u64(5), 5, Side::Sell)
+            .unwrap();
+
+        let stats = book.activity_stats();
+        assert_eq!(
+            stats,
+            ActivityStats {
+                orders_added: 4,
+                orders_cancelled: 1,
+                orders_modified: 1,
+                trades: 2,
+                last_command_timestamp: stats.last_command_timestamp,
+                last_sequence: 7,
+            }
+        );
+        assert!(stats.last_command_timestamp > 0);
+        assert_eq!(book.enriched_snapshot(10).activity, stats);
+    }
+
+    #[test]
+    fn test_rejected_operations_are_not_counted() {
+        let book = OrderBook::<()>::new("BTC");
+        assert!(book.cancel_order(OrderId::from_u64(1)).unwrap().is_none());
+        assert!(
+            book.submit_market_order(OrderId::from_u64(2), 5, Side::Buy)
+                .is_err()
+        );
+        assert_eq!(book.activity_stats(), ActivityStats::default());
+    }
+}
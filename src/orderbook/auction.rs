@@ -0,0 +1,292 @@
+//! Call auctions for opening, closing and resuming from halts
+//!
+//! While a book is in auction, limit orders rest without matching, so the book
+//! may cross. The equilibrium price is the one executing the most volume; ties
+//! go to the smallest surplus left unexecuted, then to the price closest to the
+//! last trade, then to the lowest price. The uncross executes every crossing
+//! order at that single price, buys in price-time priority against sells, and
+//! returns the book to continuous matching.
+
+use super::OrderBook;
+use super::error::OrderBookError;
+use super::trade::TradeResult;
+use pricelevel::{MatchResult, OrderId, OrderUpdate, Side};
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use tracing::trace;
+
+/// Price an auction would uncross at, and what it would leave unexecuted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AuctionPrice {
+    pub price: u64,
+    /// Quantity executed at the price
+    pub volume: u64,
+    /// Quantity of the larger side left unexecuted at the price
+    pub surplus: u64,
+    /// Side of the surplus, `None` when both sides execute in full
+    pub surplus_side: Option<Side>,
+}
+
+/// Outcome of an uncross
+#[derive(Debug, Clone, Default)]
+pub struct AuctionUncross {
+    /// Equilibrium the auction uncrossed at, `None` when the book was not crossed
+    pub equilibrium: Option<AuctionPrice>,
+    /// One match per buy order that executed, all at the equilibrium price
+    pub matches: Vec<MatchResult>,
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Starts an auction: orders rest without matching until the uncross
+    pub fn start_auction(&self) {
+        self.in_auction.store(true, Ordering::SeqCst);
+        trace!("Order book {}: Auction started", self.symbol);
+    }
+
+    /// Whether the book is accumulating orders for an auction
+    pub fn is_in_auction(&self) -> bool {
+        self.in_auction.load(Ordering::SeqCst)
+    }
+
+    /// Rejects orders that cannot rest while the book is in auction
+    pub(super) fn ensure_can_rest(&self, immediate: bool) -> Result<(), OrderBookError> {
+        if immediate && self.is_in_auction() {
+            return Err(OrderBookError::InvalidOperation {
+                message: format!(
+                    "Only orders that can rest are accepted during the auction on {}",
+                    self.symbol
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Price the book would uncross at now, `None` unless it is crossed
+    pub fn equilibrium_price(&self) -> Option<AuctionPrice> {
+        let best_bid = *self.bids.back()?.key();
+        let best_ask = *self.asks.front()?.key();
+        if best_bid < best_ask {
+            return None;
+        }
+        let demand = |price: u64| -> u64 {
+            self.bids
+                .range(price..)
+                .map(|entry| entry.value().total_quantity())
+                .sum()
+        };
+        let supply = |price: u64| -> u64 {
+            self.asks
+                .range(..=price)
+                .map(|entry| entry.value().total_quantity())
+                .sum()
+        };
+        let reference = self.last_trade_price();
+        let candidates = self
+            .bids
+            .range(best_ask..=best_bid)
+            .chain(self.asks.range(best_ask..=best_bid))
+            .map(|entry| *entry.key());
+        candidates
+            .map(|price| {
+                let (demand, supply) = (demand(price), supply(price));
+                AuctionPrice {
+                    price,
+                    volume: demand.min(supply),
+                    surplus: demand.abs_diff(supply),
+                    surplus_side: match demand.cmp(&supply) {
+                        std::cmp::Ordering::Greater => Some(Side::Buy),
+                        std::cmp::Ordering::Less => Some(Side::Sell),
+                        std::cmp::Ordering::Equal => None,
+                    },
+                }
+            })
+            .min_by_key(|candidate| {
+                (
+                    std::cmp::Reverse(candidate.volume),
+                    candidate.surplus,
+                    reference.map(|reference| candidate.price.abs_diff(reference)),
+                    candidate.price,
+                )
+            })
+    }
+
+    /// Ends the auction, executing every crossing order at the equilibrium price
+    ///
+    /// Each buy order that executes is reported to the trade listener as the
+    /// taker of its fills. A buy order left partly filled keeps resting with
+    /// its remainder, behind the orders already at its price.
+    pub fn uncross(&self) -> Result<AuctionUncross, OrderBookError> {
+        self.ensure_not_halted()?;
+        let equilibrium = self.equilibrium_price();
+        self.in_auction.store(false, Ordering::SeqCst);
+        let Some(auction) = equilibrium.filter(|auction| auction.volume > 0) else {
+            trace!("Order book {}: Auction ended uncrossed", self.symbol);
+            return Ok(AuctionUncross::default());
+        };
+        let buyers: Vec<(OrderId, u64)> = self
+            .bids
+            .range(auction.price..)
+            .rev()
+            .flat_map(|entry| {
+                entry
+                    .value()
+                    .iter_orders()
+                    .into_iter()
+                    .map(|order| {
+                        (
+                            order.id(),
+                            order.visible_quantity() + order.hidden_quantity(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let mut unexecuted = auction.volume;
+        let mut matches = Vec::new();
+        for (order_id, quantity) in buyers {
+            if unexecuted == 0 {
+                break;
+            }
+            let taker_fields = self.extra_fields.get(order_id);
+            let mut match_result = self.match_order(
+                order_id,
+                Side::Buy,
+                quantity.min(unexecuted),
+                Some(auction.price),
+            )?;
+            let executed = match_result.executed_quantity();
+            if executed == 0 {
+                // Blocked by the minimum fill notional
+                break;
+            }
+            unexecuted -= executed;
+            for transaction in &mut match_result.transactions.transactions {
+                transaction.price = auction.price;
+            }
+            match_result.remaining_quantity = quantity - executed;
+            match_result.is_complete = executed == quantity;
+            if match_result.is_complete {
+                self.remove_order(order_id)?;
+            } else {
+                self.apply_update(OrderUpdate::UpdateQuantity {
+                    order_id,
+                    new_quantity: quantity - executed,
+                })?;
+            }
+            self.record_tape_trades(&match_result);
+            if let Some(ref listener) = self.trade_listener {
+                let trade_result = TradeResult::new(self.symbol.clone(), match_result.clone())
+                    .with_extra_fields(self.trade_extra_fields(&match_result, Some(&taker_fields)));
+                listener(&trade_result);
+            }
+            matches.push(match_result);
+        }
+        self.last_trade_price
+            .store(auction.price, Ordering::Relaxed);
+        self.activity.matched();
+        trace!(
+            "Order book {}: Auction uncrossed {} at {}",
+            self.symbol, auction.volume, auction.price
+        );
+        Ok(AuctionUncross {
+            equilibrium: Some(auction),
+            matches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::TimeInForce;
+    use std::sync::{Arc, Mutex};
+
+    fn rest(book: &OrderBook<()>, id: u64, side: Side, price: u64, quantity: u64) {
+        book.add_limit_order(
+            OrderId::from_u64(id),
+            price,
+            quantity,
+            side,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_orders_accumulate_and_uncross_at_one_price() {
+        let trades = Arc::new(Mutex::new(Vec::new()));
+        let sink = trades.clone();
+        let book = OrderBook::<()>::with_trade_listener(
+            "TEST",
+            Arc::new(move |trade: &TradeResult| sink.lock().unwrap().push(trade.clone())),
+        );
+        book.start_auction();
+        rest(&book, 1, Side::Buy, 102, 10);
+        rest(&book, 2, Side::Buy, 100, 10);
+        rest(&book, 3, Side::Sell, 99, 5);
+        rest(&book, 4, Side::Sell, 101, 10);
+        rest(&book, 5, Side::Sell, 103, 10);
+        assert_eq!(book.best_bid(), Some(102));
+        assert_eq!(book.best_ask(), Some(99));
+        assert!(book.check_invariants().is_empty());
+        assert!(
+            book.submit_market_order(OrderId::from_u64(6), 5, Side::Buy)
+                .is_err()
+        );
+
+        // 10 trade at 101 or 102, leaving 5 to sell; without a last trade the
+        // lower price wins
+        let expected = AuctionPrice {
+            price: 101,
+            volume: 10,
+            surplus: 5,
+            surplus_side: Some(Side::Sell),
+        };
+        assert_eq!(book.equilibrium_price(), Some(expected));
+        let uncross = book.uncross().unwrap();
+        assert_eq!(uncross.equilibrium, Some(expected));
+        assert!(!book.is_in_auction());
+        let trades = trades.lock().unwrap();
+        assert_eq!(trades.len(), 1);
+        let transactions = trades[0].match_result.transactions.as_vec();
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions.iter().all(|fill| fill.price == 101));
+        assert_eq!(book.last_trade_price(), Some(101));
+        assert!(book.get_order(OrderId::from_u64(1)).is_none());
+        assert_eq!(book.best_bid(), Some(100));
+        assert_eq!(
+            book.get_order(OrderId::from_u64(4))
+                .unwrap()
+                .visible_quantity(),
+            5
+        );
+        assert!(book.check_invariants().is_empty());
+    }
+
+    #[test]
+    fn test_partly_filled_buyers_keep_resting() {
+        let book = OrderBook::<()>::new("TEST");
+        book.start_auction();
+        rest(&book, 1, Side::Buy, 100, 10);
+        rest(&book, 2, Side::Sell, 100, 4);
+        let uncross = book.uncross().unwrap();
+        assert_eq!(uncross.matches.len(), 1);
+        assert_eq!(uncross.matches[0].remaining_quantity, 6);
+        assert_eq!(
+            book.get_order(OrderId::from_u64(1))
+                .unwrap()
+                .visible_quantity(),
+            6
+        );
+        assert_eq!(book.best_ask(), None);
+
+        // Continuous matching resumes
+        rest(&book, 3, Side::Sell, 100, 6);
+        assert_eq!(book.best_bid(), None);
+        assert!(book.uncross().unwrap().equilibrium.is_none());
+    }
+}
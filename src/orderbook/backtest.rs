@@ -0,0 +1,520 @@
+//! Execution of simulated strategy orders against historical book states
+//!
+//! A [`Backtester`] steps through states reconstructed by
+//! [`BookHistory`](super::history::BookHistory) and decides when the strategy's
+//! orders would have filled. A [`FillModel`] sets how realistic those fills are:
+//! orders reach the book only after a latency, passive orders wait behind the
+//! quantity already queued at their price, and fill opportunities may only
+//! partly fill an order.
+//!
+//! Archived states carry visible depth per level only, so every decrease of a
+//! level's quantity is treated as trading through its queue in time priority,
+//! and simulated orders never change the historical book.
+
+use super::snapshot::OrderBookSnapshot;
+use pricelevel::{PriceLevelSnapshot, Side};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Where a passive order joins the queue at its price level
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePosition {
+    /// Ahead of all quantity already at the level; the optimistic assumption
+    Front,
+    /// Behind all quantity already at the level
+    Back,
+    /// Behind this fraction (0.0 to 1.0) of the quantity already at the level
+    Fraction(f64),
+}
+
+/// How simulated orders are executed against historical depth
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillModel {
+    /// Queue position assumed when a passive order reaches the book
+    #[serde(default = "default_queue_position")]
+    pub queue_position: QueuePosition,
+
+    /// Delay between submitting an order and it reaching the book (in milliseconds)
+    #[serde(default)]
+    pub latency_ms: u64,
+
+    /// Probability (0.0 to 1.0) that a fill opportunity only partly fills the order
+    #[serde(default)]
+    pub partial_fill_probability: f64,
+
+    /// Share of the available quantity filled when a fill is partial
+    #[serde(default = "default_partial_fill_ratio")]
+    pub partial_fill_ratio: f64,
+
+    /// Fee on passive fills, in basis points of notional; negative for a rebate
+    #[serde(default)]
+    pub maker_fee_bps: f64,
+
+    /// Fee on fills taking liquidity, in basis points of notional
+    #[serde(default)]
+    pub taker_fee_bps: f64,
+
+    /// Seed of the partial fill draws, so runs are reproducible
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_queue_position() -> QueuePosition {
+    QueuePosition::Back
+}
+
+fn default_partial_fill_ratio() -> f64 {
+    0.5
+}
+
+impl Default for FillModel {
+    fn default() -> Self {
+        Self {
+            queue_position: default_queue_position(),
+            latency_ms: 0,
+            partial_fill_probability: 0.0,
+            partial_fill_ratio: default_partial_fill_ratio(),
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// A limit order placed by the strategy under test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatedOrder {
+    pub id: u64,
+    pub side: Side,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Liquidity {
+    /// The order rested on the book and was filled by others
+    Maker,
+    /// The order crossed the book on arrival
+    Taker,
+}
+
+/// A simulated execution of a strategy order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedFill {
+    pub order_id: u64,
+    /// Time of the book state in which the fill happened
+    pub timestamp: u64,
+    pub price: u64,
+    pub quantity: u64,
+    pub liquidity: Liquidity,
+    /// Fee charged for the fill (in price units times quantity units)
+    pub fee: f64,
+}
+
+struct WorkingOrder {
+    order: SimulatedOrder,
+    /// Time the order reaches the book, after latency
+    live_at: u64,
+    remaining: u64,
+    /// Quantity queued ahead of the order; `None` until it reaches the book
+    queue_ahead: Option<u64>,
+    /// Visible quantity at the order's level in the previous state
+    level_quantity: u64,
+}
+
+/// Replays strategy orders against a sequence of historical book states
+pub struct Backtester {
+    model: FillModel,
+    orders: Vec<WorkingOrder>,
+    /// State of the xorshift generator behind partial fill draws
+    rng: u64,
+}
+
+impl Backtester {
+    pub fn new(model: FillModel) -> Self {
+        // xorshift never leaves an all-zero state
+        let rng = model.seed.max(1);
+        Self {
+            model,
+            orders: Vec::new(),
+            rng,
+        }
+    }
+
+    /// Places an order at `timestamp`; it reaches the book after the model's latency
+    pub fn submit(&mut self, order: SimulatedOrder, timestamp: u64) {
+        self.orders.push(WorkingOrder {
+            order,
+            live_at: timestamp.saturating_add(self.model.latency_ms),
+            remaining: order.quantity,
+            queue_ahead: None,
+            level_quantity: 0,
+        });
+    }
+
+    /// Cancels an order, returning false if it is not open
+    pub fn cancel(&mut self, order_id: u64) -> bool {
+        let before = self.orders.len();
+        self.orders.retain(|working| working.order.id != order_id);
+        self.orders.len() != before
+    }
+
+    /// Open orders with their unfilled quantity
+    pub fn open_orders(&self) -> impl Iterator<Item = (&SimulatedOrder, u64)> {
+        self.orders
+            .iter()
+            .map(|working| (&working.order, working.remaining))
+    }
+
+    /// Advances to the next book state, returning the fills it produced
+    pub fn on_state(&mut self, state: &OrderBookSnapshot) -> Vec<SimulatedFill> {
+        let mut fills = Vec::new();
+        let mut orders = std::mem::take(&mut self.orders);
+        for working in &mut orders {
+            if working.live_at > state.timestamp {
+                continue;
+            }
+            match working.queue_ahead {
+                None => self.arrive(working, state, &mut fills),
+                Some(_) => self.rest(working, state, &mut fills),
+            }
+        }
+        orders.retain(|working| working.remaining > 0);
+        self.orders = orders;
+        fills
+    }
+
+    /// Takes the liquidity a newly arrived order crosses, then queues the rest
+    fn arrive(
+        &mut self,
+        working: &mut WorkingOrder,
+        state: &OrderBookSnapshot,
+        fills: &mut Vec<SimulatedFill>,
+    ) {
+        let order = working.order;
+        let mut crossed: Vec<&PriceLevelSnapshot> = opposite(state, order.side)
+            .iter()
+            .filter(|level| crosses(order.side, order.price, level.price))
+            .collect();
+        // Best prices first
+        crossed.sort_by_key(|level| match order.side {
+            Side::Buy => level.price,
+            Side::Sell => u64::MAX - level.price,
+        });
+        let available: u64 = crossed.iter().map(|level| level.visible_quantity).sum();
+        let mut to_take = self.draw(available.min(working.remaining));
+        for level in crossed {
+            if to_take == 0 {
+                break;
+            }
+            let quantity = to_take.min(level.visible_quantity);
+            if quantity > 0 {
+                to_take -= quantity;
+                self.fill(working, state.timestamp, level.price, quantity, fills);
+            }
+        }
+
+        let level_quantity = level_quantity(state, order.side, order.price);
+        working.level_quantity = level_quantity;
+        working.queue_ahead = Some(match self.model.queue_position {
+            QueuePosition::Front => 0,
+            QueuePosition::Back => level_quantity,
+            QueuePosition::Fraction(fraction) => {
+                (level_quantity as f64 * fraction.clamp(0.0, 1.0)).round() as u64
+            }
+        });
+    }
+
+    /// Fills a resting order from the quantity traded at its level, or all of it
+    /// once the opposite side trades through its price
+    fn rest(
+        &mut self,
+        working: &mut WorkingOrder,
+        state: &OrderBookSnapshot,
+        fills: &mut Vec<SimulatedFill>,
+    ) {
+        let order = working.order;
+        let level_quantity = level_quantity(state, order.side, order.price);
+        let traded_through = opposite(state, order.side)
+            .iter()
+            .any(|level| crosses(order.side, order.price, level.price));
+        let queue_ahead = working.queue_ahead.unwrap_or(0);
+        let fillable = if traded_through {
+            working.queue_ahead = Some(0);
+            working.remaining
+        } else {
+            let traded = working.level_quantity.saturating_sub(level_quantity);
+            let consumed = traded.min(queue_ahead);
+            working.queue_ahead = Some(queue_ahead - consumed);
+            traded - consumed
+        };
+        working.level_quantity = level_quantity;
+        let quantity = self.draw(fillable.min(working.remaining));
+        if quantity > 0 {
+            self.fill(working, state.timestamp, order.price, quantity, fills);
+        }
+    }
+
+    fn fill(
+        &self,
+        working: &mut WorkingOrder,
+        timestamp: u64,
+        price: u64,
+        quantity: u64,
+        fills: &mut Vec<SimulatedFill>,
+    ) {
+        let liquidity = if working.queue_ahead.is_none() {
+            Liquidity::Taker
+        } else {
+            Liquidity::Maker
+        };
+        let fee_bps = match liquidity {
+            Liquidity::Maker => self.model.maker_fee_bps,
+            Liquidity::Taker => self.model.taker_fee_bps,
+        };
+        working.remaining -= quantity;
+        fills.push(SimulatedFill {
+            order_id: working.order.id,
+            timestamp,
+            price,
+            quantity,
+            liquidity,
+            fee: price as f64 * quantity as f64 * fee_bps / 10_000.0,
+        });
+    }
+
+    /// Quantity actually filled out of a fill opportunity of `quantity`
+    fn draw(&mut self, quantity: u64) -> u64 {
+        if quantity == 0 || self.model.partial_fill_probability <= 0.0 {
+            return quantity;
+        }
+        if self.next_unit() < self.model.partial_fill_probability {
+            let partial = (quantity as f64 * self.model.partial_fill_ratio.clamp(0.0, 1.0)) as u64;
+            partial.clamp(1, quantity)
+        } else {
+            quantity
+        }
+    }
+
+    /// Uniform draw in [0, 1)
+    fn next_unit(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Steps `strategy` through `states`, returning every fill
+///
+/// The strategy sees each state with the fills it produced and may submit or
+/// cancel orders; new orders are first considered on the next state.
+pub fn run<I, F>(model: FillModel, states: I, mut strategy: F) -> io::Result<Vec<SimulatedFill>>
+where
+    I: IntoIterator<Item = io::Result<OrderBookSnapshot>>,
+    F: FnMut(&OrderBookSnapshot, &[SimulatedFill], &mut Backtester),
+{
+    let mut backtester = Backtester::new(model);
+    let mut all_fills = Vec::new();
+    for state in states {
+        let state = state?;
+        let fills = backtester.on_state(&state);
+        strategy(&state, &fills, &mut backtester);
+        all_fills.extend(fills);
+    }
+    Ok(all_fills)
+}
+
+fn opposite(state: &OrderBookSnapshot, side: Side) -> &[PriceLevelSnapshot] {
+    match side {
+        Side::Buy => &state.asks,
+        Side::Sell => &state.bids,
+    }
+}
+
+/// Whether an order at `price` would match an opposite level at `level_price`
+fn crosses(side: Side, price: u64, level_price: u64) -> bool {
+    match side {
+        Side::Buy => level_price <= price,
+        Side::Sell => level_price >= price,
+    }
+}
+
+fn level_quantity(state: &OrderBookSnapshot, side: Side, price: u64) -> u64 {
+    let levels = match side {
+        Side::Buy => &state.bids,
+        Side::Sell => &state.asks,
+    };
+    levels
+        .iter()
+        .find(|level| level.price == price)
+        .map_or(0, |level| level.visible_quantity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::history::{BookArchive, BookHistory};
+
+    fn state(timestamp: u64, bids: &[(u64, u64)], asks: &[(u64, u64)]) -> OrderBookSnapshot {
+        let levels = |levels: &[(u64, u64)]| {
+            levels
+                .iter()
+                .map(|(price, quantity)| {
+                    let mut level = PriceLevelSnapshot::new(*price);
+                    level.visible_quantity = *quantity;
+                    level
+                })
+                .collect()
+        };
+        OrderBookSnapshot {
+            symbol: "BTC".to_string(),
+            timestamp,
+            bids: levels(bids),
+            asks: levels(asks),
+            extra_fields: Vec::new(),
+        }
+    }
+
+    fn bid(id: u64, price: u64, quantity: u64) -> SimulatedOrder {
+        SimulatedOrder {
+            id,
+            side: Side::Buy,
+            price,
+            quantity,
+        }
+    }
+
+    fn filled(fills: &[SimulatedFill]) -> Vec<(u64, u64, Liquidity)> {
+        fills
+            .iter()
+            .map(|fill| (fill.price, fill.quantity, fill.liquidity))
+            .collect()
+    }
+
+    #[test]
+    fn test_marketable_order_takes_depth_after_latency() {
+        let mut backtester = Backtester::new(FillModel {
+            latency_ms: 10,
+            taker_fee_bps: 10.0,
+            ..FillModel::default()
+        });
+        backtester.submit(bid(1, 102, 8), 0);
+        let book = |timestamp| state(timestamp, &[(99, 5)], &[(101, 3), (102, 4), (103, 9)]);
+
+        assert!(backtester.on_state(&book(5)).is_empty());
+        let fills = backtester.on_state(&book(10));
+        assert_eq!(
+            filled(&fills),
+            vec![(101, 3, Liquidity::Taker), (102, 4, Liquidity::Taker)]
+        );
+        assert!((fills[0].fee - 101.0 * 3.0 * 0.001).abs() < 1e-9);
+        // The unfilled quantity rests at 102
+        assert_eq!(
+            backtester
+                .open_orders()
+                .map(|(order, remaining)| (order.id, remaining))
+                .collect::<Vec<_>>(),
+            vec![(1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_passive_fills_wait_for_the_queue_ahead() {
+        let run = |queue_position| {
+            let mut backtester = Backtester::new(FillModel {
+                queue_position,
+                ..FillModel::default()
+            });
+            backtester.submit(bid(1, 100, 5), 0);
+            [
+                state(0, &[(100, 10)], &[(101, 5)]),
+                state(1, &[(100, 4)], &[(101, 5)]),
+                state(2, &[(100, 1)], &[(101, 5)]),
+                // Asks trade through the bid
+                state(3, &[(99, 3)], &[(100, 2)]),
+            ]
+            .iter()
+            .map(|state| filled(&backtester.on_state(state)))
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            run(QueuePosition::Front),
+            vec![vec![], vec![(100, 5, Liquidity::Maker)], vec![], vec![]]
+        );
+        assert_eq!(
+            run(QueuePosition::Fraction(0.5)),
+            vec![
+                vec![],
+                vec![(100, 1, Liquidity::Maker)],
+                vec![(100, 3, Liquidity::Maker)],
+                vec![(100, 1, Liquidity::Maker)]
+            ]
+        );
+        assert_eq!(
+            run(QueuePosition::Back),
+            vec![vec![], vec![], vec![], vec![(100, 5, Liquidity::Maker)]]
+        );
+    }
+
+    #[test]
+    fn test_partial_fills_leave_the_rest_working() {
+        let mut backtester = Backtester::new(FillModel {
+            partial_fill_probability: 1.0,
+            partial_fill_ratio: 0.5,
+            ..FillModel::default()
+        });
+        backtester.submit(bid(1, 101, 8), 0);
+        let book = state(0, &[], &[(101, 20)]);
+        assert_eq!(
+            filled(&backtester.on_state(&book)),
+            vec![(101, 4, Liquidity::Taker)]
+        );
+        let book = state(1, &[], &[(101, 20)]);
+        assert_eq!(
+            filled(&backtester.on_state(&book)),
+            vec![(101, 2, Liquidity::Maker)]
+        );
+        assert!(backtester.cancel(1));
+        assert!(!backtester.cancel(1));
+    }
+
+    #[test]
+    fn test_run_replays_archived_states() {
+        let directory = std::env::temp_dir().join(format!("backtest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut archive = BookArchive::new(&directory);
+        archive
+            .append_snapshot(&state(0, &[(100, 10)], &[(101, 5)]))
+            .unwrap();
+        archive.append_delta("BTC", 20, Side::Buy, 100, 2).unwrap();
+
+        let states = BookHistory::new(&directory)
+            .iter_states("BTC", 0, 30, 10)
+            .unwrap();
+        let fills = run(
+            FillModel {
+                queue_position: QueuePosition::Front,
+                ..FillModel::default()
+            },
+            states,
+            |state, _, backtester| {
+                if state.timestamp == 0 {
+                    backtester.submit(bid(1, 100, 3), state.timestamp);
+                }
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            fills
+                .iter()
+                .map(|fill| (fill.timestamp, fill.quantity))
+                .collect::<Vec<_>>(),
+            vec![(20, 3)]
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
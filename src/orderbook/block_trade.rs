@@ -0,0 +1,141 @@
+//! Off-book block trades reported against an order book
+//!
+//! Block trades are negotiated bilaterally and only reported to the book. They are
+//! validated against the instrument's block trade rules and counted in the book's
+//! statistics, but never match or modify resting orders.
+
+use super::OrderBook;
+use super::error::OrderBookError;
+use super::flight_recorder::FlightEvent;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+
+/// Basis points per unit of relative price change
+const BASIS_POINTS_MULTIPLIER: f64 = 10_000.0;
+
+/// A pre-negotiated trade reported for an instrument
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTrade {
+    /// Identifier of the reported trade
+    pub trade_id: String,
+
+    /// Participant buying
+    pub buyer_id: String,
+
+    /// Participant selling
+    pub seller_id: String,
+
+    /// Negotiated price (in price units)
+    pub price: u64,
+
+    /// Negotiated quantity (in units)
+    pub quantity: u64,
+
+    /// Unix timestamp in milliseconds when the trade was reported
+    pub timestamp: u64,
+}
+
+/// Validation rules for block trades on an instrument
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTradeRules {
+    /// Minimum quantity for a trade to qualify as a block (in units)
+    #[serde(default)]
+    pub min_quantity: u64,
+
+    /// Maximum deviation of the block price from the book's reference price,
+    /// in basis points; no band is enforced when absent
+    #[serde(default)]
+    pub max_deviation_bps: Option<f64>,
+}
+
+impl Default for BlockTradeRules {
+    fn default() -> Self {
+        Self {
+            min_quantity: 1,
+            max_deviation_bps: None,
+        }
+    }
+}
+
+/// Off-book block trade statistics of a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockTradeStats {
+    /// Number of block trades recorded
+    pub count: u64,
+
+    /// Total quantity of block trades recorded (in units)
+    pub volume: u64,
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set the rules applied to block trades reported for this book
+    pub fn set_block_trade_rules(&mut self, rules: BlockTradeRules) {
+        self.block_trade_rules = rules;
+    }
+
+    /// Get the rules applied to block trades reported for this book
+    pub fn block_trade_rules(&self) -> &BlockTradeRules {
+        &self.block_trade_rules
+    }
+
+    /// Validates and records an off-book block trade
+    ///
+    /// The price band is measured from the mid price, or the last trade price when
+    /// one side of the book is empty. If neither exists the band is not enforced.
+    /// Resting orders, the last trade price and the trade listener are untouched.
+    ///
+    /// # Errors
+    /// Returns `OrderBookError::BlockTradeRejected` if the trade is below the
+    /// minimum block size or its price is outside the band, and
+    /// `OrderBookError::InstrumentHalted` if the book is halted.
+    pub fn record_block_trade(&self, trade: &BlockTrade) -> Result<(), OrderBookError> {
+        self.ensure_not_halted()?;
+        let rules = &self.block_trade_rules;
+        if trade.quantity == 0 || trade.quantity < rules.min_quantity {
+            return Err(OrderBookError::BlockTradeRejected {
+                reason: format!(
+                    "quantity {} is below minimum block size {}",
+                    trade.quantity, rules.min_quantity
+                ),
+            });
+        }
+
+        let reference_price = self.mark_price().map(|mark| mark.price);
+        if let (Some(max_deviation_bps), Some(reference_price)) =
+            (rules.max_deviation_bps, reference_price)
+            && reference_price > 0.0
+        {
+            let deviation_bps = (trade.price as f64 - reference_price).abs() / reference_price
+                * BASIS_POINTS_MULTIPLIER;
+            if deviation_bps > max_deviation_bps {
+                return Err(OrderBookError::BlockTradeRejected {
+                    reason: format!(
+                        "price {} deviates {deviation_bps:.2} bps from reference {reference_price}, limit {max_deviation_bps} bps",
+                        trade.price
+                    ),
+                });
+            }
+        }
+
+        self.block_trade_count.fetch_add(1, Ordering::Relaxed);
+        self.block_trade_volume
+            .fetch_add(trade.quantity, Ordering::Relaxed);
+        self.record_flight_event(FlightEvent::BlockTrade {
+            trade_id: trade.trade_id.clone(),
+            price: trade.price,
+            quantity: trade.quantity,
+        });
+        Ok(())
+    }
+
+    /// Get the off-book block trade statistics of this book
+    pub fn block_trade_stats(&self) -> BlockTradeStats {
+        BlockTradeStats {
+            count: self.block_trade_count.load(Ordering::Relaxed),
+            volume: self.block_trade_volume.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,22 +1,38 @@
 //! Core OrderBook implementation for managing price levels and orders
 
+use super::activity::BookActivity;
+use super::block_trade::BlockTradeRules;
 use super::cache::PriceLevelCache;
 use super::error::OrderBookError;
+use super::extra_fields::ExtraFieldStore;
+use super::features::BookFeatures;
 use super::iterators::{LevelInfo, LevelsInRange, LevelsUntilDepth, LevelsWithCumulativeDepth};
-use super::market_impact::{MarketImpact, OrderSimulation};
+use super::mark::MarkPricing;
+use super::market_impact::{ImpactModel, MarketImpact, OrderSimulation};
+use super::matching_policy::MatchingPolicy;
+use super::pegging::{PegRepricing, PegState};
+use super::protection::MarketProtection;
+use super::scale::InstrumentScale;
 use super::snapshot::{EnrichedSnapshot, MetricFlags, OrderBookSnapshot, OrderBookSnapshotPackage};
 use super::statistics::{DepthStats, DistributionBin};
-use crate::orderbook::book_change_event::PriceLevelChangedListener;
-use crate::orderbook::trade::{TradeListener, TradeResult};
+use super::sweep_limit::SweepLimit;
+use crate::orderbook::book_change_event::{
+    OrderChange, OrderChangedEvent, OrderChangedListener, PriceLevelChangedListener,
+};
+use crate::orderbook::flight_recorder::{FlightEvent, FlightRecorder};
+use crate::orderbook::trade::tape::TradeTape;
+use crate::orderbook::trade::{BookContext, TradeListener, TradeResult};
 use crate::utils::time::current_time_millis;
 use crossbeam_skiplist::SkipMap;
 use dashmap::DashMap;
-use pricelevel::{MatchResult, OrderId, OrderType, PriceLevel, Side, UuidGenerator};
+use pricelevel::{
+    MatchResult, OrderId, OrderType, PegReferenceType, PriceLevel, Side, UuidGenerator,
+};
 use serde::Serialize;
 use std::collections::{BTreeMap, HashMap};
 use std::marker::PhantomData;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tracing::trace;
 use uuid::Uuid;
 
@@ -55,12 +71,24 @@ pub struct OrderBook<T = ()> {
     /// Flag indicating if there was a trade
     pub(super) has_traded: AtomicBool,
 
+    /// Externally supplied reference price, e.g. an index value, for pegged orders
+    pub(super) external_reference_price: AtomicU64,
+
+    /// Flag indicating if an external reference price is set
+    pub(super) has_external_reference: AtomicBool,
+
     /// The timestamp of market close, if applicable (for DAY orders)
     pub(super) market_close_timestamp: AtomicU64,
 
     /// Flag indicating if market close is set
     pub(super) has_market_close: AtomicBool,
 
+    /// Flag indicating that trading is halted and new orders are rejected
+    pub(super) halted: AtomicBool,
+
+    /// Flag indicating that orders rest without matching until an auction uncross
+    pub(super) in_auction: AtomicBool,
+
     /// A cache for storing best bid/ask prices to avoid recalculation
     pub(super) cache: PriceLevelCache,
 
@@ -70,8 +98,65 @@ pub struct OrderBook<T = ()> {
     /// Phantom data to maintain generic type parameter
     _phantom: PhantomData<T>,
 
+    /// Extra fields of resting orders, which price levels do not hold
+    pub(super) extra_fields: ExtraFieldStore<T>,
+
     /// listens to order book changes. This provides a point to update a corresponding external order book e.g. in the UI
     pub price_level_changed_listener: Option<PriceLevelChangedListener>,
+
+    /// listens to changes of single resting orders, to keep a market-by-order view
+    pub order_changed_listener: Option<OrderChangedListener>,
+
+    /// Optional ring buffer of recent book events, used when investigating anomalies
+    pub(super) flight_recorder: Option<Arc<FlightRecorder>>,
+
+    /// Optional ring buffer of recent trades, queried instead of the trade topic
+    pub(super) trade_tape: Option<Arc<TradeTape>>,
+
+    /// Optional parametric impact model reported alongside book-walk market impact
+    pub(super) impact_model: Option<ImpactModel>,
+
+    /// Rules applied to off-book block trades reported for this instrument
+    pub(super) block_trade_rules: BlockTradeRules,
+
+    /// Number of off-book block trades recorded
+    pub(super) block_trade_count: AtomicU64,
+
+    /// Total quantity of off-book block trades recorded
+    pub(super) block_trade_volume: AtomicU64,
+
+    /// Optional price protection bounding how far market orders may sweep
+    pub(super) market_protection: Option<MarketProtection>,
+
+    /// Sources the mark price falls back along when a side is empty
+    pub(super) mark_pricing: MarkPricing,
+
+    /// How incoming orders are shared between the orders resting at a price
+    pub(super) matching_policy: MatchingPolicy,
+
+    /// How pegged orders follow their reference, if they do
+    pub(super) peg_repricing: Option<PegRepricing>,
+
+    /// When pegged orders were last repriced, and against which references
+    pub(super) peg_state: Mutex<PegState>,
+
+    /// Flag indicating that pegged orders were added since the last repricing
+    pub(super) pegged_orders_added: AtomicBool,
+
+    /// Optional minimum notional of a single fill
+    pub(super) min_fill_notional: Option<u64>,
+
+    /// Optional cap on how far a single order may sweep
+    pub(super) sweep_limit: Option<SweepLimit>,
+
+    /// Decimal places of prices and quantities, used to render outbound messages
+    pub(super) scale: InstrumentScale,
+
+    /// Behaviors switched on for this book
+    pub(super) features: BookFeatures,
+
+    /// Counters of the operations applied, reported in enriched snapshots
+    pub(super) activity: BookActivity,
 }
 
 impl<T> Serialize for OrderBook<T>
@@ -164,7 +249,7 @@ where
                 side: *side,
                 timestamp: *timestamp,
                 time_in_force: *time_in_force,
-                extra_fields: T::default(),
+                extra_fields: self.extra_fields.get(*id),
             },
             OrderType::IcebergOrder {
                 id,
@@ -183,7 +268,7 @@ where
                 side: *side,
                 timestamp: *timestamp,
                 time_in_force: *time_in_force,
-                extra_fields: T::default(),
+                extra_fields: self.extra_fields.get(*id),
             },
             OrderType::PostOnly {
                 id,
@@ -200,7 +285,7 @@ where
                 side: *side,
                 timestamp: *timestamp,
                 time_in_force: *time_in_force,
-                extra_fields: T::default(),
+                extra_fields: self.extra_fields.get(*id),
             },
             OrderType::TrailingStop {
                 id,
@@ -221,7 +306,7 @@ where
                 time_in_force: *time_in_force,
                 trail_amount: *trail_amount,
                 last_reference_price: *last_reference_price,
-                extra_fields: T::default(),
+                extra_fields: self.extra_fields.get(*id),
             },
             OrderType::PeggedOrder {
                 id,
@@ -242,7 +327,7 @@ where
                 time_in_force: *time_in_force,
                 reference_price_offset: *reference_price_offset,
                 reference_price_type: *reference_price_type,
-                extra_fields: T::default(),
+                extra_fields: self.extra_fields.get(*id),
             },
             OrderType::MarketToLimit {
                 id,
@@ -259,7 +344,7 @@ where
                 side: *side,
                 timestamp: *timestamp,
                 time_in_force: *time_in_force,
-                extra_fields: T::default(),
+                extra_fields: self.extra_fields.get(*id),
             },
             OrderType::ReserveOrder {
                 id,
@@ -284,7 +369,7 @@ where
                 replenish_threshold: *replenish_threshold,
                 replenish_amount: *replenish_amount,
                 auto_replenish: *auto_replenish,
-                extra_fields: T::default(),
+                extra_fields: self.extra_fields.get(*id),
             },
         }
     }
@@ -301,12 +386,35 @@ where
             transaction_id_generator: UuidGenerator::new(namespace),
             last_trade_price: AtomicU64::new(0),
             has_traded: AtomicBool::new(false),
+            external_reference_price: AtomicU64::new(0),
+            has_external_reference: AtomicBool::new(false),
             market_close_timestamp: AtomicU64::new(0),
             has_market_close: AtomicBool::new(false),
+            halted: AtomicBool::new(false),
+            in_auction: AtomicBool::new(false),
             cache: PriceLevelCache::new(),
             trade_listener: None,
             _phantom: PhantomData,
+            extra_fields: ExtraFieldStore::new(),
             price_level_changed_listener: None,
+            order_changed_listener: None,
+            flight_recorder: None,
+            trade_tape: None,
+            impact_model: None,
+            block_trade_rules: BlockTradeRules::default(),
+            block_trade_count: AtomicU64::new(0),
+            block_trade_volume: AtomicU64::new(0),
+            market_protection: None,
+            mark_pricing: MarkPricing::default(),
+            matching_policy: MatchingPolicy::default(),
+            peg_repricing: None,
+            peg_state: Mutex::new(PegState::default()),
+            pegged_orders_added: AtomicBool::new(false),
+            min_fill_notional: None,
+            sweep_limit: None,
+            scale: InstrumentScale::default(),
+            features: BookFeatures::default(),
+            activity: BookActivity::default(),
         }
     }
 
@@ -322,12 +430,35 @@ where
             transaction_id_generator: UuidGenerator::new(namespace),
             last_trade_price: AtomicU64::new(0),
             has_traded: AtomicBool::new(false),
+            external_reference_price: AtomicU64::new(0),
+            has_external_reference: AtomicBool::new(false),
             market_close_timestamp: AtomicU64::new(0),
             has_market_close: AtomicBool::new(false),
+            halted: AtomicBool::new(false),
+            in_auction: AtomicBool::new(false),
             cache: PriceLevelCache::new(),
             trade_listener: Some(trade_listener),
             _phantom: PhantomData,
+            extra_fields: ExtraFieldStore::new(),
             price_level_changed_listener: None,
+            order_changed_listener: None,
+            flight_recorder: None,
+            trade_tape: None,
+            impact_model: None,
+            block_trade_rules: BlockTradeRules::default(),
+            block_trade_count: AtomicU64::new(0),
+            block_trade_volume: AtomicU64::new(0),
+            market_protection: None,
+            mark_pricing: MarkPricing::default(),
+            matching_policy: MatchingPolicy::default(),
+            peg_repricing: None,
+            peg_state: Mutex::new(PegState::default()),
+            pegged_orders_added: AtomicBool::new(false),
+            min_fill_notional: None,
+            sweep_limit: None,
+            scale: InstrumentScale::default(),
+            features: BookFeatures::default(),
+            activity: BookActivity::default(),
         }
     }
 
@@ -346,12 +477,35 @@ where
             transaction_id_generator: UuidGenerator::new(namespace),
             last_trade_price: AtomicU64::new(0),
             has_traded: AtomicBool::new(false),
+            external_reference_price: AtomicU64::new(0),
+            has_external_reference: AtomicBool::new(false),
             market_close_timestamp: AtomicU64::new(0),
             has_market_close: AtomicBool::new(false),
+            halted: AtomicBool::new(false),
+            in_auction: AtomicBool::new(false),
             cache: PriceLevelCache::new(),
             trade_listener: Some(trade_listener),
             _phantom: PhantomData,
+            extra_fields: ExtraFieldStore::new(),
             price_level_changed_listener: Some(book_changed_listener),
+            order_changed_listener: None,
+            flight_recorder: None,
+            trade_tape: None,
+            impact_model: None,
+            block_trade_rules: BlockTradeRules::default(),
+            block_trade_count: AtomicU64::new(0),
+            block_trade_volume: AtomicU64::new(0),
+            market_protection: None,
+            mark_pricing: MarkPricing::default(),
+            matching_policy: MatchingPolicy::default(),
+            peg_repricing: None,
+            peg_state: Mutex::new(PegState::default()),
+            pegged_orders_added: AtomicBool::new(false),
+            min_fill_notional: None,
+            sweep_limit: None,
+            scale: InstrumentScale::default(),
+            features: BookFeatures::default(),
+            activity: BookActivity::default(),
         }
     }
 
@@ -375,6 +529,86 @@ where
         self.price_level_changed_listener = None;
     }
 
+    /// set order listener for this order book
+    pub fn set_order_listener(&mut self, listener: OrderChangedListener) {
+        self.order_changed_listener = Some(listener);
+    }
+
+    /// remove order listener for this order book
+    pub fn remove_order_listener(&mut self) {
+        self.order_changed_listener = None;
+    }
+
+    /// Reports a change of a resting order to the order listener, if any
+    pub(super) fn notify_order_change(
+        &self,
+        order: &OrderType<()>,
+        change: OrderChange,
+        executed: u64,
+    ) {
+        if let Some(ref listener) = self.order_changed_listener {
+            let resting = !matches!(change, OrderChange::Deleted);
+            listener(OrderChangedEvent {
+                order_id: order.id(),
+                change,
+                side: order.side(),
+                price: order.price(),
+                quantity: if resting { order.visible_quantity() } else { 0 },
+                executed,
+                priority: order.timestamp(),
+            });
+        }
+    }
+
+    /// Enable the flight recorder, keeping the last `capacity` book events
+    pub fn enable_flight_recorder(&mut self, capacity: usize) {
+        self.flight_recorder = Some(Arc::new(FlightRecorder::new(capacity)));
+    }
+
+    /// Disable the flight recorder, discarding any recorded events
+    pub fn disable_flight_recorder(&mut self) {
+        self.flight_recorder = None;
+    }
+
+    /// Get the flight recorder for this order book, if enabled
+    pub fn flight_recorder(&self) -> Option<&Arc<FlightRecorder>> {
+        self.flight_recorder.as_ref()
+    }
+
+    /// Record an event in the flight recorder, if enabled
+    pub fn record_flight_event(&self, event: FlightEvent) {
+        if let Some(ref recorder) = self.flight_recorder {
+            recorder.record(event);
+        }
+    }
+
+    /// Enable the trade tape, keeping the last `capacity` trades
+    pub fn enable_trade_tape(&mut self, capacity: usize) {
+        self.trade_tape = Some(Arc::new(TradeTape::new(capacity)));
+    }
+
+    /// Get the trade tape for this order book, if enabled
+    pub fn trade_tape(&self) -> Option<&Arc<TradeTape>> {
+        self.trade_tape.as_ref()
+    }
+
+    /// Record the fills of a match on the trade tape, if enabled
+    pub(super) fn record_tape_trades(&self, match_result: &MatchResult) {
+        if let Some(ref tape) = self.trade_tape {
+            tape.record(match_result);
+        }
+    }
+
+    /// Set the impact model used for model-based estimates in `market_impact`
+    pub fn set_impact_model(&mut self, model: Option<ImpactModel>) {
+        self.impact_model = model;
+    }
+
+    /// Get the impact model for this order book, if configured
+    pub fn impact_model(&self) -> Option<&ImpactModel> {
+        self.impact_model.as_ref()
+    }
+
     /// Get the symbol of this order book
     pub fn symbol(&self) -> &str {
         &self.symbol
@@ -396,6 +630,34 @@ where
         self.has_market_close.store(false, Ordering::SeqCst);
     }
 
+    /// Halt trading: new orders, modifications and block trades are rejected,
+    /// while cancellations are still accepted
+    pub fn halt(&self) {
+        self.halted.store(true, Ordering::SeqCst);
+        trace!("Order book {}: Trading halted", self.symbol);
+    }
+
+    /// Resume trading after a halt
+    pub fn resume(&self) {
+        self.halted.store(false, Ordering::SeqCst);
+        trace!("Order book {}: Trading resumed", self.symbol);
+    }
+
+    /// Check whether trading is halted
+    pub fn is_halted(&self) -> bool {
+        self.halted.load(Ordering::SeqCst)
+    }
+
+    /// Returns `OrderBookError::InstrumentHalted` if trading is halted
+    pub(super) fn ensure_not_halted(&self) -> Result<(), OrderBookError> {
+        if self.is_halted() {
+            return Err(OrderBookError::InstrumentHalted {
+                instrument_id: self.symbol.clone(),
+            });
+        }
+        Ok(())
+    }
+
     /// Get the best bid price, if any
     ///
     /// # Performance
@@ -413,6 +675,26 @@ where
         best_price
     }
 
+    /// Best prices and the quantity resting at each, as seen by an incoming order
+    pub fn book_context(&self) -> BookContext {
+        let best_bid = self.best_bid();
+        let best_ask = self.best_ask();
+        let depth = |levels: &SkipMap<u64, Arc<PriceLevel>>, price: Option<u64>| {
+            price
+                .and_then(|price| levels.get(&price))
+                .map_or(0, |entry| entry.value().total_quantity())
+        };
+        BookContext {
+            best_bid,
+            best_ask,
+            spread: best_bid
+                .zip(best_ask)
+                .map(|(bid, ask)| ask.saturating_sub(bid)),
+            bid_depth_at_touch: depth(&self.bids, best_bid),
+            ask_depth_at_touch: depth(&self.asks, best_ask),
+        }
+    }
+
     /// Get the best ask price, if any
     ///
     /// # Performance
@@ -450,6 +732,49 @@ where
         }
     }
 
+    /// Set an external reference price, such as an index value, for pegged orders
+    pub fn set_external_reference_price(&self, price: u64) {
+        self.external_reference_price
+            .store(price, Ordering::Relaxed);
+        self.has_external_reference.store(true, Ordering::Relaxed);
+    }
+
+    /// Clear the external reference price
+    pub fn clear_external_reference_price(&self) {
+        self.has_external_reference.store(false, Ordering::Relaxed);
+    }
+
+    /// Get the external reference price, if any
+    pub fn external_reference_price(&self) -> Option<u64> {
+        if self.has_external_reference.load(Ordering::Relaxed) {
+            Some(self.external_reference_price.load(Ordering::Relaxed))
+        } else {
+            None
+        }
+    }
+
+    /// Resolve the price a pegged order should track
+    ///
+    /// When an external reference price is set it replaces the book's own mid
+    /// for `MidPrice` pegs, so instruments tracking an index peg to the index.
+    /// Pegged orders are left out of the best bid and ask they track.
+    pub fn peg_reference_price(&self, reference: PegReferenceType) -> Option<u64> {
+        match reference {
+            PegReferenceType::BestBid => self.unpegged_best(Side::Buy),
+            PegReferenceType::BestAsk => self.unpegged_best(Side::Sell),
+            PegReferenceType::MidPrice => self.external_reference_price().or_else(|| {
+                match (
+                    self.unpegged_best(Side::Buy),
+                    self.unpegged_best(Side::Sell),
+                ) {
+                    (Some(bid), Some(ask)) => Some(((bid + ask) as f64 / 2.0).round() as u64),
+                    _ => None,
+                }
+            }),
+            PegReferenceType::LastTrade => self.last_trade_price(),
+        }
+    }
+
     /// Get the spread (best ask - best bid)
     pub fn spread(&self) -> Option<u64> {
         match (
@@ -906,6 +1231,8 @@ where
     /// - `slippage_bps`: Slippage in basis points
     /// - `levels_consumed`: Number of price levels used
     /// - `total_quantity_available`: Total liquidity available
+    /// - `model_estimate`: Expected impact from the book's impact model, measured
+    ///   from the mid price, if a model is configured
     ///
     /// # Performance
     /// O(M log N) where M is the number of levels needed.
@@ -1008,6 +1335,10 @@ where
             slippage_bps,
             levels_consumed,
             total_quantity_available: total_filled,
+            model_estimate: self.impact_model.as_ref().map(|model| {
+                let reference_price = self.mid_price().unwrap_or(best_price as f64);
+                model.estimate(quantity, side, reference_price)
+            }),
         }
     }
 
@@ -1685,6 +2016,9 @@ where
     }
 
     /// Match a market order against the book
+    ///
+    /// With market protection configured, the order only sweeps up to the protection
+    /// price and any unfilled remainder is cancelled, as for an IOC limit order.
     pub fn match_market_order(
         &self,
         order_id: OrderId,
@@ -1695,13 +2029,22 @@ where
             "Order book {}: Matching market order {} for {} at side {:?}",
             self.symbol, order_id, quantity, side
         );
-        let match_result = OrderBook::<T>::match_order(self, order_id, side, quantity, None)?;
+        self.ensure_not_halted()?;
+        self.ensure_can_rest(true)?;
+        let limit_price = self.market_protection_limit(side);
+        let book_context = self.trade_listener.is_some().then(|| self.book_context());
+        let match_result =
+            OrderBook::<T>::match_order(self, order_id, side, quantity, limit_price)?;
+        self.activity.matched();
+        self.record_tape_trades(&match_result);
 
         // Trigger trade listener if there are transactions
         if !match_result.transactions.transactions.is_empty()
             && let Some(ref listener) = self.trade_listener
         {
-            let trade_result = TradeResult::new(self.symbol.clone(), match_result.clone());
+            let trade_result = TradeResult::new(self.symbol.clone(), match_result.clone())
+                .with_book_context(book_context)
+                .with_extra_fields(self.trade_extra_fields(&match_result, None));
             listener(&trade_result);
         }
 
@@ -1750,14 +2093,20 @@ where
             "Order book {}: Matching limit order {} for {} at side {:?} with limit price {}",
             self.symbol, order_id, quantity, side, limit_price
         );
+        self.ensure_can_rest(true)?;
+        let book_context = self.trade_listener.is_some().then(|| self.book_context());
         let match_result =
             OrderBook::<T>::match_order(self, order_id, side, quantity, Some(limit_price))?;
+        self.activity.matched();
+        self.record_tape_trades(&match_result);
 
         // Trigger trade listener if there are transactions
         if !match_result.transactions.transactions.is_empty()
             && let Some(ref listener) = self.trade_listener
         {
-            let trade_result = TradeResult::new(self.symbol.clone(), match_result.clone());
+            let trade_result = TradeResult::new(self.symbol.clone(), match_result.clone())
+                .with_book_context(book_context)
+                .with_extra_fields(self.trade_extra_fields(&match_result, None));
             listener(&trade_result);
         }
 
@@ -1793,11 +2142,13 @@ where
             }
         }
 
+        let extra_fields = self.snapshot_extra_fields(bid_levels.iter().chain(&ask_levels));
         OrderBookSnapshot {
             symbol: self.symbol.clone(),
             timestamp: current_time_millis(),
             bids: bid_levels,
             asks: ask_levels,
+            extra_fields,
         }
     }
 
@@ -1841,6 +2192,7 @@ where
         }
 
         self.cache.invalidate();
+        self.restore_extra_fields(snapshot.extra_fields)?;
 
         // Clear all existing data
         while let Some(entry) = self.bids.pop_front() {
@@ -1854,6 +2206,7 @@ where
         self.last_trade_price.store(0, Ordering::Relaxed);
         self.has_market_close.store(false, Ordering::Relaxed);
         self.market_close_timestamp.store(0, Ordering::Relaxed);
+        self.pegged_orders_added.store(true, Ordering::Relaxed);
 
         for level_snapshot in snapshot.bids {
             let price = level_snapshot.price;
@@ -1996,7 +2349,7 @@ where
         }
 
         // Create enriched snapshot with pre-calculated metrics
-        EnrichedSnapshot::with_metrics(
+        let mut snapshot = EnrichedSnapshot::with_metrics(
             self.symbol.clone(),
             current_time_millis(),
             bid_levels,
@@ -2004,7 +2357,9 @@ where
             depth, // Use depth for VWAP calculation
             depth, // Use depth for imbalance calculation
             flags,
-        )
+        );
+        snapshot.activity = self.activity_stats();
+        snapshot
     }
 
     /// Get the total volume at each price level
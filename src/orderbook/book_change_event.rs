@@ -1,4 +1,5 @@
-use pricelevel::Side;
+use pricelevel::{OrderId, Side};
+use serde::Serialize;
 use std::sync::Arc;
 
 /// Event data for orderbook price level changes.
@@ -16,6 +17,46 @@ pub struct PriceLevelChangedEvent {
 
     /// latest visible quantity of the order book at this price level
     pub quantity: u64,
+
+    /// quantity refreshed from the hidden reserve of iceberg and reserve
+    /// orders into `quantity` by this change, 0 when none was
+    pub replenished: u64,
 }
 
 pub type PriceLevelChangedListener = Arc<dyn Fn(PriceLevelChangedEvent) + Send + Sync>;
+
+/// What happened to a resting order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderChange {
+    /// The order joined the queue of its price level
+    Added,
+    /// The quantity of the order changed in place
+    Modified,
+    /// The order left the book without trading, e.g. cancelled or moved to
+    /// another price, where it is added again
+    Deleted,
+    /// The order traded against an incoming one
+    Executed,
+}
+
+/// Event data for changes of single resting orders, the market-by-order
+/// counterpart of [`PriceLevelChangedEvent`].
+/// Like it, it leaves out the symbol of the book.
+#[derive(Debug)]
+pub struct OrderChangedEvent {
+    pub order_id: OrderId,
+    pub change: OrderChange,
+    pub side: Side,
+    pub price: u64,
+    /// displayed quantity of the order once changed, 0 once it left the book
+    pub quantity: u64,
+    /// quantity traded, for executions
+    pub executed: u64,
+    /// time priority of the order in the queue of its price level: orders
+    /// with a lower priority fill first, and orders of equal priority in the
+    /// order they were added
+    pub priority: u64,
+}
+
+pub type OrderChangedListener = Arc<dyn Fn(OrderChangedEvent) + Send + Sync>;
@@ -0,0 +1,461 @@
+//! Corporate action adjustments applied to resting orders
+//!
+//! A corporate action such as a split or a cash dividend changes the meaning of the
+//! prices and quantities already resting in a book. Instead of having upstream cancel
+//! and resubmit every order, the book can rescale all resting orders in one step.
+
+use super::OrderBook;
+use super::error::OrderBookError;
+use super::flight_recorder::FlightEvent;
+use pricelevel::{OrderId, OrderType, PriceLevel, PriceLevelSnapshot, Side};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+/// Price and quantity adjustment applied to every resting order of a book
+///
+/// Prices become `(price - cash_adjustment) / ratio` and quantities `quantity * ratio`,
+/// so a 2-for-1 split is `ratio = 2.0` and a cash dividend of 5 is `cash_adjustment = 5`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CorporateAction {
+    /// Number of new units per existing unit
+    #[serde(default = "default_ratio")]
+    pub ratio: f64,
+
+    /// Amount subtracted from every price before applying the ratio (in price units)
+    #[serde(default)]
+    pub cash_adjustment: i64,
+}
+
+fn default_ratio() -> f64 {
+    1.0
+}
+
+/// The effect of a corporate action on a single resting order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderAdjustment {
+    /// Identifier of the adjusted order
+    pub order_id: OrderId,
+
+    /// Side of the order
+    pub side: Side,
+
+    /// Price before the adjustment
+    pub old_price: u64,
+
+    /// Price after the adjustment
+    pub new_price: u64,
+
+    /// Total quantity (visible and hidden) before the adjustment
+    pub old_quantity: u64,
+
+    /// Total quantity after the adjustment; zero when the order was removed
+    pub new_quantity: u64,
+}
+
+impl CorporateAction {
+    fn validate(&self) -> Result<(), OrderBookError> {
+        if !self.ratio.is_finite() || self.ratio <= 0.0 {
+            return Err(OrderBookError::InvalidOperation {
+                message: format!("Adjustment ratio must be positive, got {}", self.ratio),
+            });
+        }
+        Ok(())
+    }
+
+    /// Adjusted price, rounded away from the opposite side so the book cannot cross
+    fn adjust_price(&self, price: u64, side: Side) -> Option<u64> {
+        let adjusted = (price as f64 - self.cash_adjustment as f64) / self.ratio;
+        let rounded = match side {
+            Side::Buy => adjusted.floor(),
+            Side::Sell => adjusted.ceil(),
+        };
+        (rounded >= 1.0 && rounded <= u64::MAX as f64).then_some(rounded as u64)
+    }
+
+    fn adjust_quantity(&self, quantity: u64) -> u64 {
+        (quantity as f64 * self.ratio).floor() as u64
+    }
+
+    /// Offsets and distances scale with the price but are not shifted by the cash amount
+    fn adjust_distance(&self, distance: u64) -> u64 {
+        (distance as f64 / self.ratio).round() as u64
+    }
+
+    /// Rescales a resting order, returning `None` if its visible quantity rounds to zero
+    fn adjust_order(&self, order: &OrderType<()>, new_price: u64) -> Option<OrderType<()>> {
+        let adjusted = match *order {
+            OrderType::Standard {
+                id,
+                quantity,
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+                ..
+            } => OrderType::Standard {
+                id,
+                price: new_price,
+                quantity: self.adjust_quantity(quantity),
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+            },
+            OrderType::IcebergOrder {
+                id,
+                visible_quantity,
+                hidden_quantity,
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+                ..
+            } => OrderType::IcebergOrder {
+                id,
+                price: new_price,
+                visible_quantity: self.adjust_quantity(visible_quantity),
+                hidden_quantity: self.adjust_quantity(hidden_quantity),
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+            },
+            OrderType::PostOnly {
+                id,
+                quantity,
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+                ..
+            } => OrderType::PostOnly {
+                id,
+                price: new_price,
+                quantity: self.adjust_quantity(quantity),
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+            },
+            OrderType::TrailingStop {
+                id,
+                quantity,
+                side,
+                timestamp,
+                time_in_force,
+                trail_amount,
+                last_reference_price,
+                extra_fields,
+                ..
+            } => OrderType::TrailingStop {
+                id,
+                price: new_price,
+                quantity: self.adjust_quantity(quantity),
+                side,
+                timestamp,
+                time_in_force,
+                trail_amount: self.adjust_distance(trail_amount),
+                last_reference_price: self
+                    .adjust_price(last_reference_price, side)
+                    .unwrap_or(new_price),
+                extra_fields,
+            },
+            OrderType::PeggedOrder {
+                id,
+                quantity,
+                side,
+                timestamp,
+                time_in_force,
+                reference_price_offset,
+                reference_price_type,
+                extra_fields,
+                ..
+            } => OrderType::PeggedOrder {
+                id,
+                price: new_price,
+                quantity: self.adjust_quantity(quantity),
+                side,
+                timestamp,
+                time_in_force,
+                reference_price_offset: (reference_price_offset as f64 / self.ratio).round() as i64,
+                reference_price_type,
+                extra_fields,
+            },
+            OrderType::MarketToLimit {
+                id,
+                quantity,
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+                ..
+            } => OrderType::MarketToLimit {
+                id,
+                price: new_price,
+                quantity: self.adjust_quantity(quantity),
+                side,
+                timestamp,
+                time_in_force,
+                extra_fields,
+            },
+            OrderType::ReserveOrder {
+                id,
+                visible_quantity,
+                hidden_quantity,
+                side,
+                timestamp,
+                time_in_force,
+                replenish_threshold,
+                replenish_amount,
+                auto_replenish,
+                extra_fields,
+                ..
+            } => OrderType::ReserveOrder {
+                id,
+                price: new_price,
+                visible_quantity: self.adjust_quantity(visible_quantity),
+                hidden_quantity: self.adjust_quantity(hidden_quantity),
+                side,
+                timestamp,
+                time_in_force,
+                replenish_threshold: self.adjust_quantity(replenish_threshold),
+                replenish_amount: replenish_amount
+                    .map(|amount| self.adjust_quantity(amount).max(1)),
+                auto_replenish,
+                extra_fields,
+            },
+        };
+        (adjusted.visible_quantity() > 0).then_some(adjusted)
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Rescales the prices and quantities of all resting orders
+    ///
+    /// All adjusted orders are computed before the book is touched, so either every
+    /// order is adjusted or none is. Bid prices are rounded down and ask prices up,
+    /// which keeps an uncrossed book uncrossed. Orders whose visible quantity rounds to
+    /// zero are removed and reported with a new quantity of zero. Orders keep their
+    /// timestamps, so time priority survives levels being merged by rounding. The last
+    /// trade price is adjusted as well.
+    ///
+    /// # Errors
+    /// Returns `OrderBookError::InvalidOperation` if the ratio is not positive or any
+    /// adjusted price would fall below one price unit.
+    pub fn apply_corporate_action(
+        &self,
+        action: &CorporateAction,
+    ) -> Result<Vec<OrderAdjustment>, OrderBookError> {
+        action.validate()?;
+        let snapshot = self.create_snapshot(usize::MAX);
+
+        let mut adjustments = Vec::new();
+        let mut new_bids: BTreeMap<u64, Vec<Arc<OrderType<()>>>> = BTreeMap::new();
+        let mut new_asks: BTreeMap<u64, Vec<Arc<OrderType<()>>>> = BTreeMap::new();
+        for (side, levels, new_levels) in [
+            (Side::Buy, &snapshot.bids, &mut new_bids),
+            (Side::Sell, &snapshot.asks, &mut new_asks),
+        ] {
+            for level in levels {
+                let new_price = action.adjust_price(level.price, side).ok_or_else(|| {
+                    OrderBookError::InvalidOperation {
+                        message: format!(
+                            "Adjusted price of level {} on {} would not be positive",
+                            level.price, self.symbol
+                        ),
+                    }
+                })?;
+                for order in &level.orders {
+                    let adjusted = action.adjust_order(order, new_price);
+                    adjustments.push(OrderAdjustment {
+                        order_id: order.id(),
+                        side,
+                        old_price: level.price,
+                        new_price,
+                        old_quantity: order.visible_quantity() + order.hidden_quantity(),
+                        new_quantity: adjusted.map_or(0, |adjusted| {
+                            adjusted.visible_quantity() + adjusted.hidden_quantity()
+                        }),
+                    });
+                    if let Some(adjusted) = adjusted {
+                        new_levels
+                            .entry(new_price)
+                            .or_default()
+                            .push(Arc::new(adjusted));
+                    }
+                }
+            }
+        }
+
+        self.cache.invalidate();
+        while self.bids.pop_front().is_some() {}
+        while self.asks.pop_front().is_some() {}
+        self.order_locations.clear();
+        for (side, new_levels, book_side) in [
+            (Side::Buy, new_bids, &self.bids),
+            (Side::Sell, new_asks, &self.asks),
+        ] {
+            for (price, orders) in new_levels {
+                for order in &orders {
+                    self.order_locations.insert(order.id(), (price, side));
+                }
+                let mut level_snapshot = PriceLevelSnapshot::new(price);
+                level_snapshot.orders = orders;
+                book_side.insert(price, Arc::new(PriceLevel::from(&level_snapshot)));
+            }
+        }
+
+        if self.has_traded.load(Ordering::Relaxed) {
+            let last_price = self.last_trade_price.load(Ordering::Relaxed);
+            if let Some(adjusted) = action.adjust_price(last_price, Side::Buy) {
+                self.last_trade_price.store(adjusted, Ordering::Relaxed);
+            }
+        }
+
+        self.record_flight_event(FlightEvent::CorporateAction {
+            ratio: action.ratio,
+            cash_adjustment: action.cash_adjustment,
+            orders_adjusted: adjustments.len(),
+        });
+        Ok(adjustments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::TimeInForce;
+
+    fn split(ratio: f64) -> CorporateAction {
+        CorporateAction {
+            ratio,
+            cash_adjustment: 0,
+        }
+    }
+
+    #[test]
+    fn test_split_rescales_resting_orders() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        let bid = OrderId::from_u64(1);
+        let ask = OrderId::from_u64(2);
+        book.add_limit_order(bid, 100, 10, Side::Buy, TimeInForce::Gtc, None)
+            .unwrap();
+        book.add_limit_order(ask, 102, 5, Side::Sell, TimeInForce::Gtc, None)
+            .unwrap();
+
+        let adjustments = book.apply_corporate_action(&split(2.0)).unwrap();
+
+        assert_eq!(adjustments.len(), 2);
+        assert_eq!(book.best_bid(), Some(50));
+        assert_eq!(book.best_ask(), Some(51));
+        assert_eq!(book.get_order(bid).unwrap().visible_quantity(), 20);
+        assert_eq!(book.get_order(ask).unwrap().visible_quantity(), 10);
+        assert!(book.check_invariants().is_empty());
+    }
+
+    #[test]
+    fn test_rounding_never_crosses_and_merges_levels() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            101,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            100,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(3),
+            102,
+            10,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        book.apply_corporate_action(&split(4.0)).unwrap();
+
+        assert_eq!(book.best_bid(), Some(25));
+        assert_eq!(book.best_ask(), Some(26));
+        assert_eq!(book.get_orders_at_price(25, Side::Buy).len(), 2);
+        assert!(book.check_invariants().is_empty());
+    }
+
+    #[test]
+    fn test_cash_adjustment_and_removed_orders() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            1,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            100,
+            20,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        let action = CorporateAction {
+            ratio: 0.5,
+            cash_adjustment: 10,
+        };
+        let adjustments = book.apply_corporate_action(&action).unwrap();
+
+        assert_eq!(book.best_bid(), Some(180));
+        assert!(book.get_order(OrderId::from_u64(1)).is_none());
+        let new_quantity = |id| {
+            adjustments
+                .iter()
+                .find(|adjustment| adjustment.order_id == OrderId::from_u64(id))
+                .map(|adjustment| adjustment.new_quantity)
+        };
+        assert_eq!(new_quantity(1), Some(0));
+        assert_eq!(new_quantity(2), Some(10));
+    }
+
+    #[test]
+    fn test_invalid_action_leaves_book_untouched() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        let action = CorporateAction {
+            ratio: 1.0,
+            cash_adjustment: 100,
+        };
+        assert!(book.apply_corporate_action(&action).is_err());
+        assert!(book.apply_corporate_action(&split(0.0)).is_err());
+        assert_eq!(book.best_bid(), Some(100));
+    }
+}
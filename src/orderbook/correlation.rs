@@ -0,0 +1,255 @@
+//! Rolling return correlations between instruments
+//!
+//! Prices are sampled for all tracked instruments at the same instants, converted
+//! to log returns, and fed into a fixed-size window per instrument pair. The
+//! resulting Pearson correlations are intended for pairs-trading and risk consumers.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Pearson correlation over a rolling window of paired returns
+#[derive(Debug, Clone)]
+pub struct RollingCorrelation {
+    window: usize,
+    samples: VecDeque<(f64, f64)>,
+}
+
+impl RollingCorrelation {
+    /// Creates a correlation over the last `window` paired samples (minimum 2)
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        let window = window.max(2);
+        Self {
+            window,
+            samples: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Adds a pair of returns, evicting the oldest pair when the window is full
+    pub fn push(&mut self, return_a: f64, return_b: f64) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((return_a, return_b));
+    }
+
+    /// Number of paired samples in the window
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns true if no samples have been added
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the Pearson correlation of the window
+    ///
+    /// # Returns
+    /// `None` if fewer than two samples exist or either series has zero variance.
+    #[must_use]
+    pub fn correlation(&self) -> Option<f64> {
+        let n = self.samples.len();
+        if n < 2 {
+            return None;
+        }
+
+        let (sum_a, sum_b) = self
+            .samples
+            .iter()
+            .fold((0.0, 0.0), |(sa, sb), (a, b)| (sa + a, sb + b));
+        let mean_a = sum_a / n as f64;
+        let mean_b = sum_b / n as f64;
+
+        let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+        for (a, b) in &self.samples {
+            let da = a - mean_a;
+            let db = b - mean_b;
+            covariance += da * db;
+            variance_a += da * da;
+            variance_b += db * db;
+        }
+
+        if variance_a <= f64::EPSILON || variance_b <= f64::EPSILON {
+            return None;
+        }
+        Some((covariance / (variance_a.sqrt() * variance_b.sqrt())).clamp(-1.0, 1.0))
+    }
+}
+
+/// Current correlation of one instrument pair
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairCorrelation {
+    /// First instrument of the pair
+    pub instrument_a: String,
+
+    /// Second instrument of the pair
+    pub instrument_b: String,
+
+    /// Pearson correlation of returns, if enough samples exist
+    pub correlation: Option<f64>,
+
+    /// Number of paired samples in the window
+    pub samples: usize,
+}
+
+/// Tracks rolling return correlations for a set of instrument pairs
+#[derive(Debug, Clone)]
+pub struct CorrelationTracker {
+    window: usize,
+    last_prices: HashMap<String, f64>,
+    pairs: Vec<(String, String, RollingCorrelation)>,
+}
+
+impl CorrelationTracker {
+    /// Creates a tracker using `window` paired samples per correlation
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            last_prices: HashMap::new(),
+            pairs: Vec::new(),
+        }
+    }
+
+    /// Starts tracking a pair; returns false if the pair is already tracked
+    pub fn add_pair(&mut self, instrument_a: &str, instrument_b: &str) -> bool {
+        if self.position(instrument_a, instrument_b).is_some() {
+            return false;
+        }
+        self.pairs.push((
+            instrument_a.to_string(),
+            instrument_b.to_string(),
+            RollingCorrelation::new(self.window),
+        ));
+        true
+    }
+
+    /// Stops tracking a pair; returns false if the pair was not tracked
+    pub fn remove_pair(&mut self, instrument_a: &str, instrument_b: &str) -> bool {
+        let Some(index) = self.position(instrument_a, instrument_b) else {
+            return false;
+        };
+        self.pairs.remove(index);
+        self.last_prices.retain(|symbol, _| {
+            self.pairs
+                .iter()
+                .any(|(a, b, _)| a == symbol || b == symbol)
+        });
+        true
+    }
+
+    /// Instruments that appear in at least one tracked pair
+    #[must_use]
+    pub fn instruments(&self) -> Vec<String> {
+        let mut instruments: Vec<String> = self
+            .pairs
+            .iter()
+            .flat_map(|(a, b, _)| [a.clone(), b.clone()])
+            .collect();
+        instruments.sort();
+        instruments.dedup();
+        instruments
+    }
+
+    /// Records a synchronized sample of prices
+    ///
+    /// Returns are computed against the previous sample of each instrument. A pair
+    /// is updated only when both of its instruments have a return for this sample;
+    /// instruments missing from `prices` keep their previous price.
+    pub fn sample(&mut self, prices: &HashMap<String, f64>) {
+        let mut returns = HashMap::new();
+        for (symbol, &price) in prices {
+            if price <= 0.0 {
+                continue;
+            }
+            if let Some(previous) = self.last_prices.insert(symbol.clone(), price) {
+                returns.insert(symbol.as_str(), (price / previous).ln());
+            }
+        }
+
+        for (a, b, rolling) in &mut self.pairs {
+            if let (Some(&return_a), Some(&return_b)) =
+                (returns.get(a.as_str()), returns.get(b.as_str()))
+            {
+                rolling.push(return_a, return_b);
+            }
+        }
+    }
+
+    /// Returns the current correlation of every tracked pair
+    #[must_use]
+    pub fn correlations(&self) -> Vec<PairCorrelation> {
+        self.pairs
+            .iter()
+            .map(|(a, b, rolling)| PairCorrelation {
+                instrument_a: a.clone(),
+                instrument_b: b.clone(),
+                correlation: rolling.correlation(),
+                samples: rolling.len(),
+            })
+            .collect()
+    }
+
+    fn position(&self, instrument_a: &str, instrument_b: &str) -> Option<usize> {
+        self.pairs.iter().position(|(a, b, _)| {
+            (a == instrument_a && b == instrument_b) || (a == instrument_b && b == instrument_a)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(a: f64, b: f64) -> HashMap<String, f64> {
+        HashMap::from([("A".to_string(), a), ("B".to_string(), b)])
+    }
+
+    #[test]
+    fn test_rolling_correlation_perfect() {
+        let mut rolling = RollingCorrelation::new(10);
+        assert_eq!(rolling.correlation(), None);
+
+        for (a, b) in [(0.01, 0.02), (-0.02, -0.04), (0.03, 0.06)] {
+            rolling.push(a, b);
+        }
+        assert!((rolling.correlation().unwrap() - 1.0).abs() < 1e-9);
+
+        rolling.push(0.01, -0.02);
+        assert!(rolling.correlation().unwrap() < 1.0);
+    }
+
+    #[test]
+    fn test_rolling_correlation_window_and_zero_variance() {
+        let mut rolling = RollingCorrelation::new(2);
+        rolling.push(0.01, 0.01);
+        rolling.push(0.01, 0.02);
+        rolling.push(0.01, 0.03);
+        assert_eq!(rolling.len(), 2);
+        assert_eq!(rolling.correlation(), None);
+    }
+
+    #[test]
+    fn test_correlation_tracker_pairs() {
+        let mut tracker = CorrelationTracker::new(10);
+        assert!(tracker.add_pair("A", "B"));
+        assert!(!tracker.add_pair("B", "A"));
+
+        tracker.sample(&prices(100.0, 50.0));
+        tracker.sample(&prices(101.0, 49.0));
+        tracker.sample(&prices(100.0, 50.0));
+        tracker.sample(&prices(102.0, 48.0));
+
+        let correlations = tracker.correlations();
+        assert_eq!(correlations.len(), 1);
+        assert_eq!(correlations[0].samples, 3);
+        assert!(correlations[0].correlation.unwrap() < -0.9);
+
+        assert!(tracker.remove_pair("B", "A"));
+        assert!(tracker.correlations().is_empty());
+        assert!(tracker.instruments().is_empty());
+    }
+}
@@ -43,6 +43,52 @@ pub enum OrderBookError {
         /// Underlying error message
         message: String,
     },
+    /// Off-book block trade failed the instrument's block trade rules
+    BlockTradeRejected {
+        /// Reason the trade was rejected
+        reason: String,
+    },
+    /// Order notional is below the book's minimum fill notional
+    BelowMinimumNotional {
+        /// Notional of the order (price times quantity)
+        notional: u128,
+        /// Minimum notional of a single fill
+        minimum: u64,
+    },
+    /// Limit price is not a multiple of the instrument's tick size
+    PriceOffTick {
+        /// Price of the order
+        price: u64,
+        /// Tick size of the instrument
+        tick_size: u64,
+    },
+    /// Quantity is not a multiple of the instrument's lot size
+    QuantityOffLot {
+        /// Quantity of the order
+        quantity: u64,
+        /// Lot size of the instrument
+        lot_size: u64,
+    },
+    /// Quantity is below the instrument's minimum order quantity
+    BelowMinimumQuantity {
+        /// Quantity of the order
+        quantity: u64,
+        /// Minimum order quantity
+        minimum: u64,
+    },
+    /// Quantity is above the instrument's maximum order quantity
+    AboveMaximumQuantity {
+        /// Quantity of the order
+        quantity: u64,
+        /// Maximum order quantity
+        maximum: u64,
+    },
+    /// The instrument is halted, by an `instrument.halt` command or by its
+    /// contract expiring
+    InstrumentHalted {
+        /// Id of the halted instrument
+        instrument_id: String,
+    },
     /// Snapshot integrity check failed
     ChecksumMismatch {
         /// Expected checksum value
@@ -86,6 +132,42 @@ impl fmt::Display for OrderBookError {
             OrderBookError::DeserializationError { message } => {
                 write!(f, "Deserialization error: {message}")
             }
+            OrderBookError::BlockTradeRejected { reason } => {
+                write!(f, "Block trade rejected: {reason}")
+            }
+            OrderBookError::BelowMinimumNotional { notional, minimum } => {
+                write!(
+                    f,
+                    "Notional {notional} is below the minimum fill notional {minimum}"
+                )
+            }
+            OrderBookError::PriceOffTick { price, tick_size } => {
+                write!(
+                    f,
+                    "Price {price} is not a multiple of the tick size {tick_size}"
+                )
+            }
+            OrderBookError::QuantityOffLot { quantity, lot_size } => {
+                write!(
+                    f,
+                    "Quantity {quantity} is not a multiple of the lot size {lot_size}"
+                )
+            }
+            OrderBookError::BelowMinimumQuantity { quantity, minimum } => {
+                write!(
+                    f,
+                    "Quantity {quantity} is below the minimum order quantity {minimum}"
+                )
+            }
+            OrderBookError::AboveMaximumQuantity { quantity, maximum } => {
+                write!(
+                    f,
+                    "Quantity {quantity} is above the maximum order quantity {maximum}"
+                )
+            }
+            OrderBookError::InstrumentHalted { instrument_id } => {
+                write!(f, "Instrument {instrument_id} is halted")
+            }
             OrderBookError::ChecksumMismatch { expected, actual } => {
                 write!(
                     f,
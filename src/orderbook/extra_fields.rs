@@ -0,0 +1,364 @@
+//! Extra fields of resting orders
+//!
+//! Price levels only hold `OrderType<()>`, so the `extra_fields` of an order
+//! added as `OrderType<T>` are kept here by order id and put back whenever the
+//! book hands the order out again. Books whose `T` serializes can also carry
+//! them into snapshots and trade results once
+//! [`OrderBook::serialize_extra_fields`] is called, so that client order ids,
+//! accounts or tags round-trip through both.
+
+use super::OrderBook;
+use super::error::OrderBookError;
+use dashmap::DashMap;
+use pricelevel::{MatchResult, OrderId, PriceLevelSnapshot};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Mutex;
+
+/// Extra fields of one order, as rendered into snapshots and trade results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderExtraFields {
+    pub order_id: OrderId,
+    pub fields: Value,
+}
+
+/// Conversions of `T` to and from JSON, only available where `T` serializes
+struct ExtraFieldCodec<T> {
+    encode: fn(&T) -> serde_json::Result<Value>,
+    decode: fn(Value) -> serde_json::Result<T>,
+}
+
+fn encode<T: Serialize>(fields: &T) -> serde_json::Result<Value> {
+    serde_json::to_value(fields)
+}
+
+fn decode<T: DeserializeOwned>(fields: Value) -> serde_json::Result<T> {
+    serde_json::from_value(fields)
+}
+
+/// Extra fields of the orders resting in a book
+pub(super) struct ExtraFieldStore<T> {
+    resting: DashMap<OrderId, T>,
+    /// Fields of the makers filled completely by the current match, which have
+    /// left the book but still belong in its trade result
+    filled: Mutex<Vec<(OrderId, T)>>,
+    codec: Option<ExtraFieldCodec<T>>,
+}
+
+impl<T: Clone> ExtraFieldStore<T> {
+    pub(super) fn new() -> Self {
+        Self {
+            resting: DashMap::new(),
+            filled: Mutex::new(Vec::new()),
+            codec: None,
+        }
+    }
+
+    /// `()` and other zero-sized types carry nothing, so books using them
+    /// never touch the maps
+    fn carries_data() -> bool {
+        size_of::<T>() != 0
+    }
+
+    pub(super) fn insert(&self, order_id: OrderId, fields: &T) {
+        if Self::carries_data() {
+            self.resting.insert(order_id, fields.clone());
+        }
+    }
+
+    /// Fields of a resting order, or the default for orders added without any
+    pub(super) fn get(&self, order_id: OrderId) -> T
+    where
+        T: Default,
+    {
+        if !Self::carries_data() {
+            return T::default();
+        }
+        self.resting
+            .get(&order_id)
+            .map(|fields| fields.value().clone())
+            .unwrap_or_default()
+    }
+
+    pub(super) fn remove(&self, order_id: &OrderId) {
+        if Self::carries_data() {
+            self.resting.remove(order_id);
+        }
+    }
+
+    pub(super) fn clear(&self) {
+        self.resting.clear();
+        self.clear_filled();
+    }
+
+    fn clear_filled(&self) {
+        self.filled
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Forgets the makers filled by the previous match, as a new one begins
+    pub(super) fn start_match(&self) {
+        if Self::carries_data() {
+            self.clear_filled();
+        }
+    }
+
+    /// Moves the fields of makers filled completely aside until the next match
+    pub(super) fn retire(&self, order_ids: &[OrderId]) {
+        if !Self::carries_data() {
+            return;
+        }
+        let mut filled = self.filled.lock().unwrap_or_else(|e| e.into_inner());
+        filled.extend(order_ids.iter().filter_map(|id| self.resting.remove(id)));
+    }
+
+    /// Fields of a maker of the current match, resting or filled
+    fn maker(&self, order_id: OrderId) -> Option<T> {
+        if let Some(fields) = self.resting.get(&order_id) {
+            return Some(fields.value().clone());
+        }
+        let filled = self.filled.lock().unwrap_or_else(|e| e.into_inner());
+        filled
+            .iter()
+            .find(|(id, _)| *id == order_id)
+            .map(|(_, fields)| fields.clone())
+    }
+
+    fn render(&self, order_id: OrderId, fields: &T) -> Option<OrderExtraFields> {
+        let codec = self.codec.as_ref()?;
+        match (codec.encode)(fields) {
+            Ok(fields) => Some(OrderExtraFields { order_id, fields }),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to serialize extra fields of order {}: {}",
+                    order_id,
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Extra fields of a resting order, as given when it was added
+    pub fn order_extra_fields(&self, order_id: OrderId) -> Option<T> {
+        self.order_locations
+            .contains_key(&order_id)
+            .then(|| self.extra_fields.get(order_id))
+    }
+
+    /// Extra fields of the orders in the given levels, in book order
+    pub(super) fn snapshot_extra_fields<'a>(
+        &self,
+        levels: impl Iterator<Item = &'a PriceLevelSnapshot>,
+    ) -> Vec<OrderExtraFields> {
+        if self.extra_fields.codec.is_none() {
+            return Vec::new();
+        }
+        levels
+            .flat_map(|level| level.orders.iter())
+            .filter_map(|order| {
+                let fields = self.extra_fields.resting.get(&order.id())?;
+                self.extra_fields.render(order.id(), fields.value())
+            })
+            .collect()
+    }
+
+    /// Puts back the extra fields of a restored snapshot
+    pub(super) fn restore_extra_fields(
+        &self,
+        entries: Vec<OrderExtraFields>,
+    ) -> Result<(), OrderBookError> {
+        let decoded = match &self.extra_fields.codec {
+            Some(codec) => entries
+                .into_iter()
+                .map(|entry| {
+                    (codec.decode)(entry.fields)
+                        .map(|fields| (entry.order_id, fields))
+                        .map_err(|e| OrderBookError::DeserializationError {
+                            message: format!("extra fields of order {}: {}", entry.order_id, e),
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        self.extra_fields.clear();
+        for (order_id, fields) in decoded {
+            self.extra_fields.insert(order_id, &fields);
+        }
+        Ok(())
+    }
+
+    /// Extra fields of the taker, when given, and of every maker of a match
+    pub(super) fn trade_extra_fields(
+        &self,
+        match_result: &MatchResult,
+        taker: Option<&T>,
+    ) -> Vec<OrderExtraFields> {
+        if self.extra_fields.codec.is_none() {
+            return Vec::new();
+        }
+        let mut rendered: Vec<OrderExtraFields> = taker
+            .and_then(|fields| self.extra_fields.render(match_result.order_id, fields))
+            .into_iter()
+            .collect();
+        for transaction in match_result.transactions.as_vec() {
+            let maker = transaction.maker_order_id;
+            if rendered.iter().any(|entry| entry.order_id == maker) {
+                continue;
+            }
+            if let Some(fields) = self.extra_fields.maker(maker)
+                && let Some(entry) = self.extra_fields.render(maker, &fields)
+            {
+                rendered.push(entry);
+            }
+        }
+        rendered
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + Default + 'static,
+{
+    /// Carries the extra fields of orders into snapshots and trade results
+    pub fn serialize_extra_fields(&mut self) {
+        self.extra_fields.codec = Some(ExtraFieldCodec {
+            encode: encode::<T>,
+            decode: decode::<T>,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::trade::TradeResult;
+    use pricelevel::{OrderType, Side, TimeInForce};
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct ClientFields {
+        client_order_id: String,
+        account: String,
+        tags: Vec<String>,
+    }
+
+    fn fields(client_order_id: &str, account: &str) -> ClientFields {
+        ClientFields {
+            client_order_id: client_order_id.to_string(),
+            account: account.to_string(),
+            tags: vec!["algo".to_string()],
+        }
+    }
+
+    fn order(
+        id: u64,
+        price: u64,
+        quantity: u64,
+        side: Side,
+        extra: ClientFields,
+    ) -> OrderType<ClientFields> {
+        OrderType::Standard {
+            id: OrderId::from_u64(id),
+            price,
+            quantity,
+            side,
+            timestamp: 0,
+            time_in_force: TimeInForce::Gtc,
+            extra_fields: extra,
+        }
+    }
+
+    fn client_book() -> (OrderBook<ClientFields>, Arc<Mutex<Vec<TradeResult>>>) {
+        let trades = Arc::new(Mutex::new(Vec::new()));
+        let sink = trades.clone();
+        let mut book = OrderBook::with_trade_listener(
+            "BTC",
+            Arc::new(move |trade: &TradeResult| sink.lock().unwrap().push(trade.clone())),
+        );
+        book.serialize_extra_fields();
+        (book, trades)
+    }
+
+    #[test]
+    fn test_extra_fields_survive_resting_and_snapshots() {
+        let (book, _) = client_book();
+        book.add_order(order(1, 100, 10, Side::Buy, fields("c-1", "acct-a")))
+            .unwrap();
+        book.add_order(order(2, 101, 5, Side::Sell, fields("c-2", "acct-b")))
+            .unwrap();
+
+        let resting = book.get_order(OrderId::from_u64(1)).unwrap();
+        assert_eq!(*resting.extra_fields(), fields("c-1", "acct-a"));
+
+        let snapshot = book.create_snapshot(usize::MAX);
+        assert_eq!(snapshot.extra_fields.len(), 2);
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        let (restored, _) = client_book();
+        restored
+            .restore_from_snapshot(serde_json::from_str(&json).unwrap())
+            .unwrap();
+        assert_eq!(
+            restored.order_extra_fields(OrderId::from_u64(2)),
+            Some(fields("c-2", "acct-b"))
+        );
+
+        book.cancel_order(OrderId::from_u64(1)).unwrap();
+        assert_eq!(book.order_extra_fields(OrderId::from_u64(1)), None);
+        assert!(
+            book.extra_fields
+                .resting
+                .get(&OrderId::from_u64(1))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_trade_results_carry_taker_and_maker_fields() {
+        let (book, trades) = client_book();
+        book.add_order(order(1, 100, 5, Side::Sell, fields("maker-1", "acct-a")))
+            .unwrap();
+        book.add_order(order(2, 100, 5, Side::Sell, fields("maker-2", "acct-b")))
+            .unwrap();
+        book.add_order(order(3, 100, 7, Side::Buy, fields("taker", "acct-c")))
+            .unwrap();
+
+        let trades = trades.lock().unwrap();
+        let rendered: Vec<(OrderId, String)> = trades[0]
+            .extra_fields
+            .iter()
+            .map(|entry| {
+                let fields: ClientFields = serde_json::from_value(entry.fields.clone()).unwrap();
+                (entry.order_id, fields.client_order_id)
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                (OrderId::from_u64(3), "taker".to_string()),
+                (OrderId::from_u64(1), "maker-1".to_string()),
+                (OrderId::from_u64(2), "maker-2".to_string()),
+            ]
+        );
+        // The filled maker is gone, the partly filled one still rests
+        assert!(
+            book.extra_fields
+                .resting
+                .get(&OrderId::from_u64(1))
+                .is_none()
+        );
+        assert_eq!(
+            book.order_extra_fields(OrderId::from_u64(2)),
+            Some(fields("maker-2", "acct-b"))
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Per-book switches for behaviors that are still being rolled out
+//!
+//! A behavior configured on a book only takes effect while its feature is
+//! enabled, so it can be turned on instrument by instrument and switched off
+//! again without losing its settings.
+
+use super::OrderBook;
+use bitflags::bitflags;
+
+bitflags! {
+    /// Behaviors that can be switched on and off per book
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct BookFeatures: u32 {
+        /// Bound how far market orders sweep, see `MarketProtection`
+        const MARKET_PROTECTION = 1 << 0;
+        /// Enforce the minimum fill notional
+        const MIN_FILL_NOTIONAL = 1 << 1;
+    }
+}
+
+impl Default for BookFeatures {
+    /// Every feature, so configuring a behavior is enough to use it
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set which features are enabled on this book
+    pub fn set_features(&mut self, features: BookFeatures) {
+        self.features = features;
+    }
+
+    /// Features enabled on this book
+    pub fn features(&self) -> BookFeatures {
+        self.features
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::protection::MarketProtection;
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    #[test]
+    fn test_disabled_features_keep_their_settings() {
+        let mut book: OrderBook<()> = OrderBook::new("TEST");
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            5,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.set_market_protection(Some(MarketProtection {
+            ticks: 2,
+            tick_size: 1,
+        }));
+        book.set_min_fill_notional(Some(1_000));
+        assert_eq!(book.market_protection_limit(Side::Buy), Some(102));
+        assert_eq!(book.min_fill_notional(), Some(1_000));
+
+        book.set_features(BookFeatures::MIN_FILL_NOTIONAL);
+        assert_eq!(book.market_protection_limit(Side::Buy), None);
+        assert!(book.market_protection().is_some());
+
+        book.set_features(BookFeatures::empty());
+        assert_eq!(book.min_fill_notional(), None);
+        assert!(book.meets_min_fill_notional(100, 1));
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            99,
+            1,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        // Rolling back restores the configured behavior
+        book.set_features(BookFeatures::default());
+        assert_eq!(book.min_fill_notional(), Some(1_000));
+        assert!(!book.meets_min_fill_notional(100, 1));
+    }
+}
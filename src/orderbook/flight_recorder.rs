@@ -0,0 +1,218 @@
+//! Per-book flight recorder for debugging book anomalies.
+//!
+//! The recorder keeps the last N commands and book events in a bounded ring buffer,
+//! so an operator can inspect recent activity on a single book without enabling
+//! full audit logging. Recording is optional and disabled by default.
+
+use pricelevel::{OrderId, Side};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::utils::current_time_millis;
+
+/// An event captured by the flight recorder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FlightEvent {
+    /// A command received by the engine for this book
+    Command {
+        /// Debug representation of the command
+        command: String,
+    },
+    /// An order was added to the book as a resting order
+    OrderAdded {
+        /// Identifier of the added order
+        order_id: OrderId,
+        /// Price of the resting order
+        price: u64,
+        /// Resting quantity after any immediate matching
+        quantity: u64,
+        /// Side of the order
+        side: Side,
+    },
+    /// An order was cancelled and removed from the book
+    OrderCancelled {
+        /// Identifier of the cancelled order
+        order_id: OrderId,
+    },
+    /// An order was updated in place or re-queued
+    OrderUpdated {
+        /// Debug representation of the applied update
+        update: String,
+    },
+    /// A trade was executed against a resting order
+    Trade {
+        /// Identifier of the aggressive order
+        taker_order_id: OrderId,
+        /// Identifier of the resting order
+        maker_order_id: OrderId,
+        /// Execution price
+        price: u64,
+        /// Executed quantity
+        quantity: u64,
+    },
+    /// An off-book block trade was recorded without touching resting orders
+    BlockTrade {
+        /// Identifier of the reported trade
+        trade_id: String,
+        /// Execution price
+        price: u64,
+        /// Executed quantity
+        quantity: u64,
+    },
+    /// All resting orders were rescaled by a corporate action
+    CorporateAction {
+        /// Number of new units per existing unit
+        ratio: f64,
+        /// Amount subtracted from every price before applying the ratio
+        cash_adjustment: i64,
+        /// Number of resting orders adjusted or removed
+        orders_adjusted: usize,
+    },
+}
+
+/// A single entry in the flight recorder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlightRecord {
+    /// Monotonic sequence number assigned by the recorder
+    pub sequence: u64,
+    /// Unix timestamp in milliseconds when the event was recorded
+    pub timestamp: u64,
+    /// The recorded event
+    pub event: FlightEvent,
+}
+
+/// Bounded ring buffer of recent book events
+#[derive(Debug)]
+pub struct FlightRecorder {
+    capacity: usize,
+    next_sequence: AtomicU64,
+    records: Mutex<VecDeque<FlightRecord>>,
+}
+
+impl FlightRecorder {
+    /// Creates a recorder keeping at most `capacity` records (minimum 1)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            next_sequence: AtomicU64::new(0),
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Maximum number of records kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Records an event, evicting the oldest record when the buffer is full
+    pub fn record(&self, event: FlightEvent) {
+        let record = FlightRecord {
+            sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+            timestamp: current_time_millis(),
+            event,
+        };
+
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Returns a copy of the recorded events, oldest first
+    pub fn dump(&self) -> Vec<FlightRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().cloned().collect()
+    }
+
+    /// Number of records currently held
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns true if no records are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all records, keeping the sequence counter
+    pub fn clear(&self) {
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flight_recorder_evicts_oldest() {
+        let recorder = FlightRecorder::new(2);
+        for i in 0..3 {
+            recorder.record(FlightEvent::OrderCancelled {
+                order_id: OrderId::from_u64(i),
+            });
+        }
+
+        let records = recorder.dump();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].sequence, 1);
+        assert_eq!(records[1].sequence, 2);
+    }
+
+    #[test]
+    fn test_flight_recorder_minimum_capacity() {
+        let recorder = FlightRecorder::new(0);
+        assert_eq!(recorder.capacity(), 1);
+        assert!(recorder.is_empty());
+    }
+
+    #[test]
+    fn test_flight_recorder_records_book_activity() {
+        use crate::orderbook::OrderBook;
+        use pricelevel::TimeInForce;
+
+        let mut book: OrderBook<()> = OrderBook::new("TEST");
+        book.enable_flight_recorder(16);
+
+        let maker = OrderId::from_u64(1);
+        let taker = OrderId::from_u64(2);
+        let _ = book.add_limit_order(maker, 100, 10, Side::Sell, TimeInForce::Gtc, None);
+        let _ = book.match_market_order(taker, 4, Side::Buy);
+        let _ = book.cancel_order(maker);
+
+        let events: Vec<FlightEvent> = book
+            .flight_recorder()
+            .unwrap()
+            .dump()
+            .into_iter()
+            .map(|record| record.event)
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                FlightEvent::OrderAdded {
+                    order_id: maker,
+                    price: 100,
+                    quantity: 10,
+                    side: Side::Sell,
+                },
+                FlightEvent::Trade {
+                    taker_order_id: taker,
+                    maker_order_id: maker,
+                    price: 100,
+                    quantity: 4,
+                },
+                FlightEvent::OrderCancelled { order_id: maker },
+            ]
+        );
+    }
+}
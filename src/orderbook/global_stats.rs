@@ -0,0 +1,220 @@
+//! Statistics aggregated across every book of a manager
+//!
+//! [`GlobalStats`] is a single JSON-serializable document meant for a monitoring
+//! dashboard: what rests in the books, how much has traded and which instruments
+//! see the most activity.
+
+use super::OrderBook;
+use crate::utils::current_time_millis;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Instruments listed in [`GlobalStats::busiest`]
+pub const BUSIEST_INSTRUMENTS: usize = 10;
+
+/// Activity of one instrument, as ranked among the busiest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentLoad {
+    pub instrument_id: String,
+
+    /// Adds, cancels, modifies and standalone matches applied to the book
+    pub operations: u64,
+
+    /// Fills against resting orders
+    pub trades: u64,
+
+    /// Orders resting in the book
+    pub resting_orders: usize,
+}
+
+/// Aggregate of every book in a manager at one point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlobalStats {
+    /// Unix timestamp in milliseconds when the statistics were gathered
+    pub timestamp: u64,
+
+    pub book_count: usize,
+
+    /// Orders resting across all books
+    pub total_orders: usize,
+
+    /// Sum of price times quantity, hidden quantity included, of every resting
+    /// order, in raw book units
+    pub total_notional: u128,
+
+    /// Fills across all books since they were created
+    pub total_trades: u64,
+
+    /// Fills per second since the previous call, or since the manager was
+    /// created on the first one
+    pub trades_per_sec: f64,
+
+    /// Instruments with the most operations, busiest first, at most
+    /// [`BUSIEST_INSTRUMENTS`] of them
+    pub busiest: Vec<InstrumentLoad>,
+}
+
+/// Trade count seen at the last sample, to turn the running total into a rate
+#[derive(Debug)]
+pub(super) struct TradeRateSampler {
+    /// (timestamp, total trades) of the last sample
+    last: Mutex<(u64, u64)>,
+}
+
+impl TradeRateSampler {
+    pub(super) fn new() -> Self {
+        Self {
+            last: Mutex::new((current_time_millis(), 0)),
+        }
+    }
+
+    /// Trades per second since the last sample; removed books can make the
+    /// total go down, which counts as no trades
+    fn sample(&self, now: u64, total_trades: u64) -> f64 {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let (since, trades) = std::mem::replace(&mut *last, (now, total_trades));
+        let elapsed_ms = now.saturating_sub(since);
+        if elapsed_ms == 0 {
+            return 0.0;
+        }
+        total_trades.saturating_sub(trades) as f64 * 1_000.0 / elapsed_ms as f64
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Number of orders resting on both sides
+    pub fn resting_order_count(&self) -> usize {
+        self.bids
+            .iter()
+            .chain(self.asks.iter())
+            .map(|entry| entry.value().order_count())
+            .sum()
+    }
+
+    /// Sum of price times total quantity over both sides, in raw book units
+    pub fn resting_notional(&self) -> u128 {
+        self.bids
+            .iter()
+            .chain(self.asks.iter())
+            .map(|entry| *entry.key() as u128 * entry.value().total_quantity() as u128)
+            .sum()
+    }
+}
+
+impl GlobalStats {
+    pub(super) fn gather<'a, T>(
+        books: impl Iterator<Item = &'a OrderBook<T>>,
+        sampler: &TradeRateSampler,
+    ) -> Self
+    where
+        T: Clone + Send + Sync + Default + 'static,
+    {
+        let mut stats = Self {
+            timestamp: current_time_millis(),
+            book_count: 0,
+            total_orders: 0,
+            total_notional: 0,
+            total_trades: 0,
+            trades_per_sec: 0.0,
+            busiest: Vec::new(),
+        };
+        for book in books {
+            let activity = book.activity_stats();
+            let resting_orders = book.resting_order_count();
+            stats.book_count += 1;
+            stats.total_orders += resting_orders;
+            stats.total_notional += book.resting_notional();
+            stats.total_trades += activity.trades;
+            stats.busiest.push(InstrumentLoad {
+                instrument_id: book.symbol().to_string(),
+                operations: activity.last_sequence,
+                trades: activity.trades,
+                resting_orders,
+            });
+        }
+        stats.trades_per_sec = sampler.sample(stats.timestamp, stats.total_trades);
+        stats.busiest.sort_by(|a, b| {
+            b.operations
+                .cmp(&a.operations)
+                .then_with(|| a.instrument_id.cmp(&b.instrument_id))
+        });
+        stats.busiest.truncate(BUSIEST_INSTRUMENTS);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::manager::{BookManager, BookManagerStd};
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    #[test]
+    fn test_stats_aggregate_every_book() {
+        let mut manager = BookManagerStd::<()>::new();
+        manager.add_book("BTC");
+        manager.add_book("ETH");
+        manager.add_book("IDLE");
+        let btc = manager.get_book("BTC").unwrap();
+        for (id, price, side) in [
+            (1, 100, Side::Buy),
+            (2, 101, Side::Sell),
+            (3, 102, Side::Sell),
+        ] {
+            btc.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                10,
+                side,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        btc.match_market_order(OrderId::from_u64(4), 4, Side::Buy)
+            .unwrap();
+        manager
+            .get_book("ETH")
+            .unwrap()
+            .add_limit_order(
+                OrderId::from_u64(1),
+                50,
+                2,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+
+        let stats = manager.global_stats();
+        assert_eq!(stats.book_count, 3);
+        assert_eq!(stats.total_orders, 4);
+        assert_eq!(stats.total_notional, 100 * 10 + 101 * 6 + 102 * 10 + 50 * 2);
+        assert_eq!(stats.total_trades, 1);
+        let busiest: Vec<_> = stats
+            .busiest
+            .iter()
+            .map(|load| load.instrument_id.as_str())
+            .collect();
+        assert_eq!(busiest, ["BTC", "ETH", "IDLE"]);
+        assert_eq!(stats.busiest[0].operations, 4);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["busiest"][0]["resting_orders"], 3);
+    }
+
+    #[test]
+    fn test_trade_rate_covers_the_period_since_the_last_sample() {
+        let sampler = TradeRateSampler {
+            last: Mutex::new((1_000, 0)),
+        };
+        assert_eq!(sampler.sample(3_000, 10), 5.0);
+        assert_eq!(sampler.sample(3_500, 12), 4.0);
+        // A removed book takes its trades out of the total
+        assert_eq!(sampler.sample(4_500, 2), 0.0);
+        assert_eq!(sampler.sample(4_500, 3), 0.0);
+    }
+}
@@ -0,0 +1,528 @@
+//! Archive of depth snapshots and journaled level deltas, for reconstructing a
+//! book as it was at a past point in time.
+//!
+//! Each instrument has its own directory holding two append-only JSON lines
+//! files: `snapshots.jsonl` with periodic full-depth snapshots and `deltas.jsonl`
+//! with every price level change in between. Deltas are numbered, and each
+//! snapshot records the number of the first delta it does not include, so a
+//! book can be rebuilt from any snapshot without relying on timestamps to order
+//! a snapshot against deltas taken in the same millisecond.
+//!
+//! [`BookHistory`] reads the archive back, either at a single point in time or as
+//! a sequence of states at fixed intervals.
+//!
+//! The archive keeps visible quantity per level only, so reconstructed books are
+//! price-level depth: levels carry no orders and no hidden quantity.
+
+use super::snapshot::OrderBookSnapshot;
+use pricelevel::{PriceLevelSnapshot, Side};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const SNAPSHOTS_FILE: &str = "snapshots.jsonl";
+const DELTAS_FILE: &str = "deltas.jsonl";
+
+/// A change of the visible quantity at one price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelDelta {
+    /// Position in the instrument's delta journal, starting at 0
+    pub seq: u64,
+    /// Milliseconds since epoch
+    pub timestamp: u64,
+    pub side: Side,
+    pub price: u64,
+    /// New visible quantity; 0 removes the level
+    pub quantity: u64,
+}
+
+/// Depth snapshot as stored in the archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivedSnapshot {
+    timestamp: u64,
+    /// Sequence of the first delta taken after this snapshot
+    next_delta_seq: u64,
+    /// (price, visible quantity), best first
+    bids: Vec<(u64, u64)>,
+    asks: Vec<(u64, u64)>,
+}
+
+/// Visible quantity per price level, as rebuilt from the archive
+#[derive(Debug, Clone, Default)]
+struct DepthState {
+    bids: BTreeMap<u64, u64>,
+    asks: BTreeMap<u64, u64>,
+}
+
+impl DepthState {
+    fn from_archived(snapshot: &ArchivedSnapshot) -> Self {
+        Self {
+            bids: snapshot.bids.iter().copied().collect(),
+            asks: snapshot.asks.iter().copied().collect(),
+        }
+    }
+
+    fn apply(&mut self, delta: &LevelDelta) {
+        let levels = match delta.side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        if delta.quantity == 0 {
+            levels.remove(&delta.price);
+        } else {
+            levels.insert(delta.price, delta.quantity);
+        }
+    }
+
+    fn to_snapshot(&self, symbol: &str, timestamp: u64) -> OrderBookSnapshot {
+        let level = |(price, quantity): (&u64, &u64)| {
+            let mut level = PriceLevelSnapshot::new(*price);
+            level.visible_quantity = *quantity;
+            level
+        };
+        OrderBookSnapshot {
+            symbol: symbol.to_string(),
+            timestamp,
+            bids: self.bids.iter().rev().map(level).collect(),
+            asks: self.asks.iter().map(level).collect(),
+            extra_fields: Vec::new(),
+        }
+    }
+}
+
+/// Delta journal of one instrument, opened for appending
+struct DeltaJournal {
+    file: File,
+    next_seq: u64,
+}
+
+/// Per-instrument store of depth snapshots and level deltas
+pub struct BookArchive {
+    directory: PathBuf,
+    journals: HashMap<String, DeltaJournal>,
+}
+
+impl BookArchive {
+    /// Creates an archive rooted at `directory`; files are created on first write
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            journals: HashMap::new(),
+        }
+    }
+
+    fn journal(&mut self, instrument_id: &str) -> io::Result<&mut DeltaJournal> {
+        if !self.journals.contains_key(instrument_id) {
+            let directory = instrument_dir(&self.directory, instrument_id);
+            std::fs::create_dir_all(&directory)?;
+            let path = directory.join(DELTAS_FILE);
+            // Continue numbering after whatever a previous run journaled
+            let next_seq = read_lines::<LevelDelta>(&path)?
+                .last()
+                .map_or(0, |delta| delta.seq + 1);
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            self.journals
+                .insert(instrument_id.to_string(), DeltaJournal { file, next_seq });
+        }
+        Ok(self
+            .journals
+            .get_mut(instrument_id)
+            .expect("journal was just opened"))
+    }
+
+    /// Journals a level change, returning its sequence number
+    pub fn append_delta(
+        &mut self,
+        instrument_id: &str,
+        timestamp: u64,
+        side: Side,
+        price: u64,
+        quantity: u64,
+    ) -> io::Result<u64> {
+        let journal = self.journal(instrument_id)?;
+        let delta = LevelDelta {
+            seq: journal.next_seq,
+            timestamp,
+            side,
+            price,
+            quantity,
+        };
+        write_line(&mut journal.file, &delta)?;
+        journal.next_seq += 1;
+        Ok(delta.seq)
+    }
+
+    /// Archives the depth of a snapshot, as of every delta journaled so far
+    ///
+    /// Only price and visible quantity of each level are kept.
+    pub fn append_snapshot(&mut self, snapshot: &OrderBookSnapshot) -> io::Result<()> {
+        let next_delta_seq = self.journal(&snapshot.symbol)?.next_seq;
+        let levels = |levels: &[PriceLevelSnapshot]| {
+            levels
+                .iter()
+                .filter(|level| level.visible_quantity > 0)
+                .map(|level| (level.price, level.visible_quantity))
+                .collect()
+        };
+        let archived = ArchivedSnapshot {
+            timestamp: snapshot.timestamp,
+            next_delta_seq,
+            bids: levels(&snapshot.bids),
+            asks: levels(&snapshot.asks),
+        };
+        let path = instrument_dir(&self.directory, &snapshot.symbol).join(SNAPSHOTS_FILE);
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        write_line(&mut file, &archived)
+    }
+
+    /// Reconstructs an instrument's depth as it was at `timestamp`
+    ///
+    /// See [`BookHistory::book_as_of`].
+    pub fn book_as_of(
+        &self,
+        instrument_id: &str,
+        timestamp: u64,
+    ) -> io::Result<Option<OrderBookSnapshot>> {
+        BookHistory::new(&self.directory).book_as_of(instrument_id, timestamp)
+    }
+}
+
+/// Read-only access to the book states recorded in a [`BookArchive`] directory
+pub struct BookHistory {
+    directory: PathBuf,
+}
+
+impl BookHistory {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Reconstructs an instrument's depth as it was at `timestamp`
+    ///
+    /// Starts from the latest archived snapshot taken at or before `timestamp` and
+    /// replays the deltas journaled after it, up to `timestamp`. Returns `None` when
+    /// the archive holds nothing for the instrument from before `timestamp`.
+    pub fn book_as_of(
+        &self,
+        instrument_id: &str,
+        timestamp: u64,
+    ) -> io::Result<Option<OrderBookSnapshot>> {
+        let mut replay = Replay::open(&self.directory, instrument_id)?;
+        Ok(if replay.advance_to(timestamp)? {
+            Some(replay.state.to_snapshot(instrument_id, timestamp))
+        } else {
+            None
+        })
+    }
+
+    /// Iterates an instrument's depth at `from`, `from + step`, ... up to `to`
+    ///
+    /// The archive is read once, front to back, so iterating a range costs about as
+    /// much as a single [`book_as_of`](Self::book_as_of) at its end. Times before the
+    /// instrument's first archived snapshot are skipped.
+    pub fn iter_states(
+        &self,
+        instrument_id: &str,
+        from: u64,
+        to: u64,
+        step: u64,
+    ) -> io::Result<BookStates> {
+        Ok(BookStates {
+            replay: Replay::open(&self.directory, instrument_id)?,
+            instrument_id: instrument_id.to_string(),
+            next: Some(from),
+            to,
+            step: step.max(1),
+        })
+    }
+}
+
+/// Book states at fixed intervals, from [`BookHistory::iter_states`]
+pub struct BookStates {
+    replay: Replay,
+    instrument_id: String,
+    /// Time of the next state, `None` once past the end of the range
+    next: Option<u64>,
+    to: u64,
+    step: u64,
+}
+
+impl Iterator for BookStates {
+    type Item = io::Result<OrderBookSnapshot>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(timestamp) = self.next.filter(|timestamp| *timestamp <= self.to) {
+            self.next = timestamp.checked_add(self.step);
+            match self.replay.advance_to(timestamp) {
+                Ok(true) => {
+                    return Some(Ok(self
+                        .replay
+                        .state
+                        .to_snapshot(&self.instrument_id, timestamp)));
+                }
+                Ok(false) => continue,
+                Err(e) => {
+                    self.next = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Forward-only reconstruction of one instrument's depth
+struct Replay {
+    snapshots: Vec<ArchivedSnapshot>,
+    /// Snapshot the current state was rebuilt from
+    base: Option<usize>,
+    state: DepthState,
+    deltas: DeltaReader,
+    /// Delta read past the last requested time, applied on a later advance
+    pending: Option<LevelDelta>,
+}
+
+impl Replay {
+    fn open(directory: &Path, instrument_id: &str) -> io::Result<Self> {
+        let directory = instrument_dir(directory, instrument_id);
+        Ok(Self {
+            snapshots: read_lines(&directory.join(SNAPSHOTS_FILE))?,
+            base: None,
+            state: DepthState::default(),
+            deltas: DeltaReader::open(&directory.join(DELTAS_FILE))?,
+            pending: None,
+        })
+    }
+
+    /// Brings the state up to `timestamp`, which must not be earlier than the last
+    /// one; returns false if no snapshot was taken by then
+    fn advance_to(&mut self, timestamp: u64) -> io::Result<bool> {
+        // A later snapshot supersedes every delta before it, including those of an
+        // earlier book of the same instrument
+        let unused = self.base.map_or(0, |base| base + 1);
+        let latest = self.snapshots[unused..]
+            .iter()
+            .rposition(|snapshot| snapshot.timestamp <= timestamp)
+            .map(|offset| unused + offset);
+        if let Some(latest) = latest {
+            self.state = DepthState::from_archived(&self.snapshots[latest]);
+            self.base = Some(latest);
+        }
+        let Some(base) = self.base else {
+            return Ok(false);
+        };
+        let first_seq = self.snapshots[base].next_delta_seq;
+
+        loop {
+            let delta = match self.pending.take() {
+                Some(delta) => delta,
+                None => match self.deltas.next_delta()? {
+                    Some(delta) => delta,
+                    None => break,
+                },
+            };
+            if delta.seq < first_seq {
+                continue;
+            }
+            if delta.timestamp > timestamp {
+                self.pending = Some(delta);
+                break;
+            }
+            self.state.apply(&delta);
+        }
+        Ok(true)
+    }
+}
+
+/// Streams the deltas of a journal in order, skipping unreadable lines
+struct DeltaReader {
+    lines: Option<std::io::Lines<BufReader<File>>>,
+}
+
+impl DeltaReader {
+    /// Opens a journal; a missing file reads as empty
+    fn open(path: &Path) -> io::Result<Self> {
+        let lines = match File::open(path) {
+            Ok(file) => Some(BufReader::new(file).lines()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(Self { lines })
+    }
+
+    fn next_delta(&mut self) -> io::Result<Option<LevelDelta>> {
+        let Some(lines) = &mut self.lines else {
+            return Ok(None);
+        };
+        for line in lines.by_ref() {
+            // A torn final write after a crash; everything before it is intact
+            if let Ok(delta) = serde_json::from_str(&line?) {
+                return Ok(Some(delta));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn instrument_dir(directory: &Path, instrument_id: &str) -> PathBuf {
+    // Keep instrument ids from escaping the archive directory
+    directory.join(instrument_id.replace(['/', '\\'], "_").replace("..", "_"))
+}
+
+fn write_line<V: Serialize>(file: &mut File, value: &V) -> io::Result<()> {
+    let mut line = serde_json::to_string(value).map_err(io::Error::other)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+}
+
+/// Reads every parseable record of a JSON lines file; a missing file reads as empty
+fn read_lines<V: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<Vec<V>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut values = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(value) = serde_json::from_str(&line?) {
+            values.push(value);
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_archive(name: &str) -> (BookArchive, PathBuf) {
+        let directory =
+            std::env::temp_dir().join(format!("book-archive-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        (BookArchive::new(&directory), directory)
+    }
+
+    fn snapshot_at(timestamp: u64, bids: &[(u64, u64)], asks: &[(u64, u64)]) -> OrderBookSnapshot {
+        let levels = |levels: &[(u64, u64)]| {
+            levels
+                .iter()
+                .map(|(price, quantity)| {
+                    let mut level = PriceLevelSnapshot::new(*price);
+                    level.visible_quantity = *quantity;
+                    level
+                })
+                .collect()
+        };
+        OrderBookSnapshot {
+            symbol: "BTC".to_string(),
+            timestamp,
+            bids: levels(bids),
+            asks: levels(asks),
+            extra_fields: Vec::new(),
+        }
+    }
+
+    /// (price, visible quantity) of each level
+    type Levels = Vec<(u64, u64)>;
+
+    fn depth(snapshot: &OrderBookSnapshot) -> (Levels, Levels) {
+        let levels = |levels: &[PriceLevelSnapshot]| {
+            levels
+                .iter()
+                .map(|level| (level.price, level.visible_quantity))
+                .collect()
+        };
+        (levels(&snapshot.bids), levels(&snapshot.asks))
+    }
+
+    #[test]
+    fn test_book_as_of_replays_deltas_after_nearest_snapshot() {
+        let (mut archive, directory) = temp_archive("as-of");
+        archive
+            .append_snapshot(&snapshot_at(100, &[(99, 5)], &[(101, 5)]))
+            .unwrap();
+        archive.append_delta("BTC", 110, Side::Buy, 98, 3).unwrap();
+        archive
+            .append_delta("BTC", 120, Side::Sell, 101, 0)
+            .unwrap();
+        // Taken in the same millisecond as the next delta, which it does not include
+        archive
+            .append_snapshot(&snapshot_at(130, &[(99, 5), (98, 3)], &[]))
+            .unwrap();
+        archive
+            .append_delta("BTC", 130, Side::Sell, 102, 7)
+            .unwrap();
+
+        assert!(archive.book_as_of("BTC", 99).unwrap().is_none());
+        assert!(archive.book_as_of("ETH", 200).unwrap().is_none());
+
+        let book = archive.book_as_of("BTC", 115).unwrap().unwrap();
+        assert_eq!(book.timestamp, 115);
+        assert_eq!(depth(&book), (vec![(99, 5), (98, 3)], vec![(101, 5)]));
+
+        let book = archive.book_as_of("BTC", 120).unwrap().unwrap();
+        assert_eq!(depth(&book), (vec![(99, 5), (98, 3)], vec![]));
+
+        let book = archive.book_as_of("BTC", 130).unwrap().unwrap();
+        assert_eq!(depth(&book), (vec![(99, 5), (98, 3)], vec![(102, 7)]));
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_iter_states_steps_through_snapshots_and_deltas() {
+        let (mut archive, directory) = temp_archive("iter");
+        archive
+            .append_snapshot(&snapshot_at(100, &[(99, 5)], &[]))
+            .unwrap();
+        archive.append_delta("BTC", 105, Side::Buy, 99, 2).unwrap();
+        archive
+            .append_delta("BTC", 125, Side::Sell, 101, 4)
+            .unwrap();
+        // The book was deleted and created again
+        archive
+            .append_snapshot(&snapshot_at(130, &[], &[]))
+            .unwrap();
+        archive.append_delta("BTC", 135, Side::Buy, 97, 1).unwrap();
+
+        let history = BookHistory::new(&directory);
+        let states: Vec<_> = history
+            .iter_states("BTC", 90, 140, 10)
+            .unwrap()
+            .map(|state| {
+                let state = state.unwrap();
+                (state.timestamp, depth(&state))
+            })
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                (100, (vec![(99, 5)], vec![])),
+                (110, (vec![(99, 2)], vec![])),
+                (120, (vec![(99, 2)], vec![])),
+                (130, (vec![], vec![])),
+                (140, (vec![(97, 1)], vec![])),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_delta_numbering_continues_after_reopen() {
+        let (mut archive, directory) = temp_archive("reopen");
+        assert_eq!(archive.append_delta("BTC", 1, Side::Buy, 99, 1).unwrap(), 0);
+        assert_eq!(archive.append_delta("BTC", 2, Side::Buy, 99, 2).unwrap(), 1);
+
+        let mut archive = BookArchive::new(&directory);
+        archive.append_snapshot(&snapshot_at(3, &[], &[])).unwrap();
+        assert_eq!(archive.append_delta("BTC", 4, Side::Buy, 99, 3).unwrap(), 2);
+
+        // The snapshot resets the book, so earlier deltas are not replayed onto it
+        let book = archive.book_as_of("BTC", 3).unwrap().unwrap();
+        assert!(book.bids.is_empty());
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,166 @@
+//! Weighted index calculation from constituent instrument prices
+//!
+//! An index value is the weighted sum of its constituents' prices divided by a
+//! divisor. The value is only defined once every constituent has a price, so a
+//! constituent without liquidity never silently drags the index towards zero.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A single instrument contributing to an index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexConstituent {
+    /// Instrument identifier of the constituent
+    pub instrument_id: String,
+
+    /// Weight applied to the constituent's price
+    pub weight: f64,
+}
+
+/// Definition of a weighted index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexDefinition {
+    /// Identifier of the index
+    pub index_id: String,
+
+    /// Constituents and their weights
+    pub constituents: Vec<IndexConstituent>,
+
+    /// Divisor applied to the weighted sum (defaults to 1.0)
+    #[serde(default = "default_divisor")]
+    pub divisor: f64,
+}
+
+fn default_divisor() -> f64 {
+    1.0
+}
+
+/// A computed index value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexTick {
+    /// Identifier of the index
+    pub index_id: String,
+
+    /// Computed index value (in price units)
+    pub value: f64,
+
+    /// Unix timestamp in milliseconds when the value was computed
+    pub timestamp: u64,
+}
+
+impl IndexDefinition {
+    /// Creates an index definition with a divisor of 1.0
+    #[must_use]
+    pub fn new(index_id: &str, constituents: Vec<IndexConstituent>) -> Self {
+        Self {
+            index_id: index_id.to_string(),
+            constituents,
+            divisor: default_divisor(),
+        }
+    }
+
+    /// Instruments referenced by this index
+    pub fn instruments(&self) -> impl Iterator<Item = &str> {
+        self.constituents.iter().map(|c| c.instrument_id.as_str())
+    }
+
+    /// Computes the index value from constituent prices
+    ///
+    /// # Returns
+    /// `None` if the index has no constituents, the divisor is zero, or any
+    /// constituent is missing from `prices`.
+    #[must_use]
+    pub fn compute(&self, prices: &HashMap<String, f64>) -> Option<f64> {
+        if self.constituents.is_empty() || self.divisor == 0.0 {
+            return None;
+        }
+        let mut weighted_sum = 0.0;
+        for constituent in &self.constituents {
+            let price = prices.get(&constituent.instrument_id)?;
+            weighted_sum += constituent.weight * price;
+        }
+        Some(weighted_sum / self.divisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constituent(instrument_id: &str, weight: f64) -> IndexConstituent {
+        IndexConstituent {
+            instrument_id: instrument_id.to_string(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_index_compute_weighted_value() {
+        let mut index =
+            IndexDefinition::new("IDX", vec![constituent("A", 2.0), constituent("B", 1.0)]);
+        index.divisor = 4.0;
+        let prices = HashMap::from([("A".to_string(), 100.0), ("B".to_string(), 40.0)]);
+
+        // (2 * 100 + 1 * 40) / 4 = 60
+        assert_eq!(index.compute(&prices), Some(60.0));
+    }
+
+    #[test]
+    fn test_index_compute_requires_all_constituents() {
+        let index = IndexDefinition::new("IDX", vec![constituent("A", 1.0), constituent("B", 1.0)]);
+        let prices = HashMap::from([("A".to_string(), 100.0)]);
+        assert_eq!(index.compute(&prices), None);
+
+        let empty = IndexDefinition::new("EMPTY", Vec::new());
+        assert_eq!(empty.compute(&prices), None);
+    }
+
+    #[test]
+    fn test_index_definition_default_divisor() {
+        let json = r#"{"index_id":"IDX","constituents":[{"instrument_id":"A","weight":0.5}]}"#;
+        let index: IndexDefinition = serde_json::from_str(json).unwrap();
+        assert_eq!(index.divisor, 1.0);
+        assert_eq!(index.instruments().collect::<Vec<_>>(), vec!["A"]);
+    }
+
+    #[test]
+    fn test_index_as_peg_reference() {
+        use crate::orderbook::OrderBook;
+        use pricelevel::{OrderId, PegReferenceType, Side, TimeInForce};
+
+        let book: OrderBook<()> = OrderBook::new("TRACKER");
+        let _ = book.add_limit_order(
+            OrderId::from_u64(1),
+            98,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        );
+        let _ = book.add_limit_order(
+            OrderId::from_u64(2),
+            102,
+            10,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        );
+        assert_eq!(
+            book.peg_reference_price(PegReferenceType::MidPrice),
+            Some(100)
+        );
+
+        book.set_external_reference_price(110);
+        assert_eq!(
+            book.peg_reference_price(PegReferenceType::MidPrice),
+            Some(110)
+        );
+        assert_eq!(
+            book.peg_reference_price(PegReferenceType::BestBid),
+            Some(98)
+        );
+
+        book.clear_external_reference_price();
+        assert_eq!(book.external_reference_price(), None);
+    }
+}
@@ -0,0 +1,107 @@
+//! Tick and lot sizes and quantity limits of an instrument
+//!
+//! A spec is kept by the book manager beside the instrument's book and checked
+//! before an order reaches it, so orders that could never be entered on the
+//! instrument are rejected with the rule they break.
+
+use super::error::OrderBookError;
+use serde::{Deserialize, Serialize};
+
+/// Prices and quantities orders on an instrument may be entered with; each
+/// rule is unenforced when unset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentSpec {
+    /// Prices must be a multiple of the tick size
+    #[serde(default)]
+    pub tick_size: Option<u64>,
+    /// Quantities must be a multiple of the lot size
+    #[serde(default)]
+    pub lot_size: Option<u64>,
+    #[serde(default)]
+    pub min_quantity: Option<u64>,
+    #[serde(default)]
+    pub max_quantity: Option<u64>,
+}
+
+impl InstrumentSpec {
+    /// Whether the spec sets no rule at all
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks a limit price against the tick size
+    pub fn validate_price(&self, price: u64) -> Result<(), OrderBookError> {
+        match self.tick_size {
+            Some(tick_size) if tick_size > 0 && !price.is_multiple_of(tick_size) => {
+                Err(OrderBookError::PriceOffTick { price, tick_size })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks an order quantity against the lot size and quantity limits
+    pub fn validate_quantity(&self, quantity: u64) -> Result<(), OrderBookError> {
+        if let Some(lot_size) = self.lot_size
+            && lot_size > 0
+            && !quantity.is_multiple_of(lot_size)
+        {
+            return Err(OrderBookError::QuantityOffLot { quantity, lot_size });
+        }
+        if let Some(minimum) = self.min_quantity
+            && quantity < minimum
+        {
+            return Err(OrderBookError::BelowMinimumQuantity { quantity, minimum });
+        }
+        if let Some(maximum) = self.max_quantity
+            && quantity > maximum
+        {
+            return Err(OrderBookError::AboveMaximumQuantity { quantity, maximum });
+        }
+        Ok(())
+    }
+
+    /// Checks an order, `price` being `None` for market orders
+    pub fn validate(&self, price: Option<u64>, quantity: u64) -> Result<(), OrderBookError> {
+        if let Some(price) = price {
+            self.validate_price(price)?;
+        }
+        self.validate_quantity(quantity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orders_must_fit_ticks_lots_and_limits() {
+        let spec = InstrumentSpec {
+            tick_size: Some(5),
+            lot_size: Some(10),
+            min_quantity: Some(20),
+            max_quantity: Some(1_000),
+        };
+        assert!(spec.validate(Some(105), 50).is_ok());
+        assert!(spec.validate(None, 1_000).is_ok());
+        assert!(matches!(
+            spec.validate(Some(103), 50),
+            Err(OrderBookError::PriceOffTick {
+                price: 103,
+                tick_size: 5
+            })
+        ));
+        assert!(matches!(
+            spec.validate(Some(105), 55),
+            Err(OrderBookError::QuantityOffLot { .. })
+        ));
+        assert!(matches!(
+            spec.validate(Some(105), 10),
+            Err(OrderBookError::BelowMinimumQuantity { minimum: 20, .. })
+        ));
+        assert!(matches!(
+            spec.validate(None, 1_010),
+            Err(OrderBookError::AboveMaximumQuantity { maximum: 1_000, .. })
+        ));
+        assert!(InstrumentSpec::default().validate(Some(103), 7).is_ok());
+    }
+}
@@ -0,0 +1,177 @@
+//! Structural invariant checks for detecting inconsistent book state.
+
+use super::OrderBook;
+use pricelevel::Side;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A violated structural invariant of the order book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InvariantViolation {
+    /// Best bid is at or above best ask while both rest in the book
+    CrossedBook {
+        /// Highest resting bid price
+        best_bid: u64,
+        /// Lowest resting ask price
+        best_ask: u64,
+    },
+    /// A price level without orders was left in the book
+    EmptyPriceLevel {
+        /// Side of the empty level
+        side: Side,
+        /// Price of the empty level
+        price: u64,
+    },
+    /// The order location index disagrees with the orders resting in price levels
+    OrderCountMismatch {
+        /// Number of entries in the order location index
+        indexed: usize,
+        /// Number of orders found in price levels
+        resting: usize,
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::CrossedBook { best_bid, best_ask } => {
+                write!(f, "Crossed book at rest: bid {best_bid} >= ask {best_ask}")
+            }
+            InvariantViolation::EmptyPriceLevel { side, price } => {
+                write!(f, "Empty {side} price level left at {price}")
+            }
+            InvariantViolation::OrderCountMismatch { indexed, resting } => {
+                write!(
+                    f,
+                    "Order index mismatch: {indexed} indexed, {resting} resting"
+                )
+            }
+        }
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Checks the structural invariants of the book
+    ///
+    /// Reads the price levels directly rather than through the best price cache,
+    /// so a stale cache cannot hide an inconsistency.
+    ///
+    /// # Returns
+    /// All detected violations, or an empty vector if the book is consistent.
+    ///
+    /// # Performance
+    /// O(N) where N is the number of price levels.
+    #[must_use]
+    pub fn check_invariants(&self) -> Vec<InvariantViolation> {
+        let mut violations = Vec::new();
+
+        let best_bid = self.bids.iter().next_back().map(|entry| *entry.key());
+        let best_ask = self.asks.iter().next().map(|entry| *entry.key());
+        // Orders waiting for an auction's uncross may cross
+        if let (Some(best_bid), Some(best_ask)) = (best_bid, best_ask)
+            && best_bid >= best_ask
+            && !self.is_in_auction()
+        {
+            violations.push(InvariantViolation::CrossedBook { best_bid, best_ask });
+        }
+
+        let mut resting = 0usize;
+        for (side, levels) in [(Side::Buy, &self.bids), (Side::Sell, &self.asks)] {
+            for entry in levels.iter() {
+                let order_count = entry.value().order_count();
+                if order_count == 0 {
+                    violations.push(InvariantViolation::EmptyPriceLevel {
+                        side,
+                        price: *entry.key(),
+                    });
+                }
+                resting += order_count;
+            }
+        }
+
+        let indexed = self.order_locations.len();
+        if indexed != resting {
+            violations.push(InvariantViolation::OrderCountMismatch { indexed, resting });
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::current_time_millis;
+    use pricelevel::{OrderId, OrderType, TimeInForce};
+    use std::sync::Arc;
+
+    fn resting_order(id: u64, price: u64, side: Side) -> Arc<OrderType<()>> {
+        Arc::new(OrderType::Standard {
+            id: OrderId::from_u64(id),
+            price,
+            quantity: 10,
+            side,
+            timestamp: current_time_millis(),
+            time_in_force: TimeInForce::Gtc,
+            extra_fields: (),
+        })
+    }
+
+    #[test]
+    fn test_check_invariants_consistent_book() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        let _ = book.add_limit_order(
+            OrderId::from_u64(1),
+            99,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        );
+        let _ = book.add_limit_order(
+            OrderId::from_u64(2),
+            101,
+            10,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        );
+
+        assert!(book.check_invariants().is_empty());
+    }
+
+    #[test]
+    fn test_check_invariants_detects_crossed_book() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        // place_order_in_book bypasses matching, allowing a crossed state
+        let _ = book.place_order_in_book(resting_order(1, 105, Side::Buy));
+        let _ = book.place_order_in_book(resting_order(2, 100, Side::Sell));
+
+        assert_eq!(
+            book.check_invariants(),
+            vec![InvariantViolation::CrossedBook {
+                best_bid: 105,
+                best_ask: 100,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_invariants_detects_index_mismatch() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        let _ = book.place_order_in_book(resting_order(1, 100, Side::Buy));
+        book.order_locations.remove(&OrderId::from_u64(1));
+
+        assert_eq!(
+            book.check_invariants(),
+            vec![InvariantViolation::OrderCountMismatch {
+                indexed: 0,
+                resting: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Plain-text rendering of book depth as a price ladder, and of the changes
+//! between two snapshots, for logs and terminal tools.
+
+use super::snapshot::OrderBookSnapshot;
+use pricelevel::PriceLevelSnapshot;
+use std::fmt;
+
+/// Top levels of a snapshot rendered as an aligned ladder
+///
+/// Asks are listed above bids, both from the highest price down, so the best
+/// prices meet at the separator line:
+///
+/// ```text
+/// BTC @ 1000
+/// BID  PRICE  ASK
+///        102    4
+///        101    3
+/// ---------------
+///   5    100
+///   2     99
+/// ```
+pub struct Ladder<'a> {
+    snapshot: &'a OrderBookSnapshot,
+    depth: usize,
+}
+
+/// Levels whose visible quantity differs between two snapshots of a book
+///
+/// ```text
+/// BTC 1000 -> 2000
+/// SIDE  PRICE  BEFORE  AFTER  CHANGE
+///  ask    101       3      0      -3
+///  bid    100       5      7      +2
+/// ```
+pub struct LadderDiff<'a> {
+    before: &'a OrderBookSnapshot,
+    after: &'a OrderBookSnapshot,
+    depth: usize,
+}
+
+impl OrderBookSnapshot {
+    /// Renders the top `depth` levels of each side as a ladder
+    pub fn ladder(&self, depth: usize) -> Ladder<'_> {
+        Ladder {
+            snapshot: self,
+            depth,
+        }
+    }
+
+    /// Renders the changes from `self` to `after` within the top `depth` levels
+    pub fn ladder_diff<'a>(&'a self, after: &'a OrderBookSnapshot, depth: usize) -> LadderDiff<'a> {
+        LadderDiff {
+            before: self,
+            after,
+            depth,
+        }
+    }
+}
+
+/// (price, visible quantity) of the best `depth` levels, highest price first
+fn top_levels(levels: &[PriceLevelSnapshot], depth: usize, ascending: bool) -> Vec<(u64, u64)> {
+    let mut levels: Vec<(u64, u64)> = levels
+        .iter()
+        .map(|level| (level.price, level.visible_quantity))
+        .collect();
+    // Best first: lowest ask, highest bid
+    if ascending {
+        levels.sort_by_key(|(price, _)| *price);
+    } else {
+        levels.sort_by_key(|(price, _)| std::cmp::Reverse(*price));
+    }
+    levels.truncate(depth);
+    levels.sort_by_key(|(price, _)| std::cmp::Reverse(*price));
+    levels
+}
+
+/// Writes rows with every column right-aligned to its widest cell
+fn write_table(
+    f: &mut fmt::Formatter<'_>,
+    rows: &[Vec<String>],
+    rule_after: Option<usize>,
+) -> fmt::Result {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(String::len)
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let total = widths.iter().sum::<usize>() + 2 * columns.saturating_sub(1);
+    for (index, row) in rows.iter().enumerate() {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:>width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(f, "{}", line.trim_end())?;
+        if rule_after == Some(index) {
+            writeln!(f, "{}", "-".repeat(total))?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Ladder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} @ {}", self.snapshot.symbol, self.snapshot.timestamp)?;
+        let asks = top_levels(&self.snapshot.asks, self.depth, true);
+        let bids = top_levels(&self.snapshot.bids, self.depth, false);
+        let mut rows = vec![vec![
+            "BID".to_string(),
+            "PRICE".to_string(),
+            "ASK".to_string(),
+        ]];
+        rows.extend(
+            asks.iter().map(|(price, quantity)| {
+                vec![String::new(), price.to_string(), quantity.to_string()]
+            }),
+        );
+        let rule_after = rows.len() - 1;
+        rows.extend(
+            bids.iter().map(|(price, quantity)| {
+                vec![quantity.to_string(), price.to_string(), String::new()]
+            }),
+        );
+        write_table(f, &rows, Some(rule_after))
+    }
+}
+
+impl fmt::Display for LadderDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}",
+            self.after.symbol, self.before.timestamp, self.after.timestamp
+        )?;
+        let sides = [
+            ("ask", &self.before.asks, &self.after.asks, true),
+            ("bid", &self.before.bids, &self.after.bids, false),
+        ];
+        let mut rows = vec![
+            ["SIDE", "PRICE", "BEFORE", "AFTER", "CHANGE"]
+                .map(str::to_string)
+                .to_vec(),
+        ];
+        for (name, before, after, ascending) in sides {
+            let before = top_levels(before, self.depth, ascending);
+            let after = top_levels(after, self.depth, ascending);
+            let mut prices: Vec<u64> = before
+                .iter()
+                .chain(&after)
+                .map(|(price, _)| *price)
+                .collect();
+            prices.sort_by_key(|price| std::cmp::Reverse(*price));
+            prices.dedup();
+            let quantity_at = |levels: &[(u64, u64)], price: u64| {
+                levels
+                    .iter()
+                    .find(|(level_price, _)| *level_price == price)
+                    .map_or(0, |(_, quantity)| *quantity)
+            };
+            for price in prices {
+                let (old, new) = (quantity_at(&before, price), quantity_at(&after, price));
+                if old != new {
+                    rows.push(vec![
+                        name.to_string(),
+                        price.to_string(),
+                        old.to_string(),
+                        new.to_string(),
+                        format!("{:+}", new as i128 - old as i128),
+                    ]);
+                }
+            }
+        }
+        if rows.len() == 1 {
+            return writeln!(f, ": no changes");
+        }
+        writeln!(f)?;
+        write_table(f, &rows, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(timestamp: u64, bids: &[(u64, u64)], asks: &[(u64, u64)]) -> OrderBookSnapshot {
+        let levels = |levels: &[(u64, u64)]| {
+            levels
+                .iter()
+                .map(|(price, quantity)| {
+                    let mut level = PriceLevelSnapshot::new(*price);
+                    level.visible_quantity = *quantity;
+                    level
+                })
+                .collect()
+        };
+        OrderBookSnapshot {
+            symbol: "BTC".to_string(),
+            timestamp,
+            bids: levels(bids),
+            asks: levels(asks),
+            extra_fields: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ladder_lists_top_levels_around_the_spread() {
+        // Levels out of order, as reconstructed depth may be
+        let book = snapshot(
+            1000,
+            &[(99, 2), (100, 5), (98, 40)],
+            &[(103, 9), (101, 3), (102, 4)],
+        );
+        assert_eq!(
+            book.ladder(2).to_string(),
+            "BTC @ 1000\n\
+             BID  PRICE  ASK\n\
+             \x20      102    4\n\
+             \x20      101    3\n\
+             ---------------\n\
+             \x20 5    100\n\
+             \x20 2     99\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_shows_changed_levels_only() {
+        let before = snapshot(1000, &[(100, 5), (99, 2)], &[(101, 3), (102, 4)]);
+        let after = snapshot(2000, &[(100, 7), (99, 2)], &[(102, 4), (103, 12)]);
+        assert_eq!(
+            before.ladder_diff(&after, 10).to_string(),
+            "BTC 1000 -> 2000\n\
+             SIDE  PRICE  BEFORE  AFTER  CHANGE\n\
+             \x20ask    103       0     12     +12\n\
+             \x20ask    101       3      0      -3\n\
+             \x20bid    100       5      7      +2\n"
+        );
+        assert_eq!(
+            before.ladder_diff(&before, 10).to_string(),
+            "BTC 1000 -> 1000: no changes\n"
+        );
+    }
+}
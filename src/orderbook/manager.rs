@@ -4,7 +4,11 @@
 //! for both standard library (`BookManagerStd`) and Tokio (`BookManagerTokio`) channels.
 
 use crate::orderbook::OrderBook;
+use crate::orderbook::global_stats::{GlobalStats, TradeRateSampler};
+use crate::orderbook::instrument_spec::InstrumentSpec;
 use crate::orderbook::trade::{TradeEvent, TradeListener, TradeResult};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{error, info};
@@ -37,6 +41,17 @@ where
 
     /// Get the number of order books in this manager.
     fn book_count(&self) -> usize;
+
+    /// Aggregate statistics across all books, with the trade rate measured
+    /// since the previous call.
+    fn global_stats(&self) -> GlobalStats;
+
+    /// Set the tick and lot sizes and quantity limits of a symbol, kept until
+    /// its book is removed.
+    fn set_instrument_spec(&mut self, symbol: &str, spec: InstrumentSpec);
+
+    /// Get the spec orders on a symbol are checked against, if one was set.
+    fn instrument_spec(&self, symbol: &str) -> Option<&InstrumentSpec>;
 }
 
 /// BookManager implementation using standard library mpsc channels.
@@ -46,10 +61,16 @@ where
 {
     /// Collection of order books indexed by symbol
     books: HashMap<String, OrderBook<T>>,
+    /// Specs of the symbols created with one
+    specs: HashMap<String, InstrumentSpec>,
     /// Sender for trade events
     trade_sender: std::sync::mpsc::Sender<TradeEvent>,
     /// Receiver for trade events (taken when processor starts)
     trade_receiver: Option<std::sync::mpsc::Receiver<TradeEvent>>,
+    /// Trade total at the last call to `global_stats`
+    trade_rate: TradeRateSampler,
+    /// Applied to every book added, once extra fields are serialized
+    configure_book: Option<fn(&mut OrderBook<T>)>,
 }
 
 impl<T> BookManagerStd<T>
@@ -62,8 +83,11 @@ where
 
         Self {
             books: HashMap::new(),
+            specs: HashMap::new(),
             trade_sender: sender,
             trade_receiver: Some(receiver),
+            trade_rate: TradeRateSampler::new(),
+            configure_book: None,
         }
     }
 
@@ -85,6 +109,16 @@ where
         })
     }
 
+    /// Drain pending trade events without starting a processor thread.
+    ///
+    /// Returns an empty vector once the trade processor has been started.
+    pub fn drain_trade_events(&self) -> Vec<TradeEvent> {
+        self.trade_receiver
+            .as_ref()
+            .map(|receiver| receiver.try_iter().collect())
+            .unwrap_or_default()
+    }
+
     /// Process a single trade event.
     fn process_trade_event(event: TradeEvent) {
         info!(
@@ -108,6 +142,20 @@ where
     }
 }
 
+impl<T> BookManagerStd<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + Default + 'static,
+{
+    /// Carries the extra fields of orders into snapshots and trade results in
+    /// every book, including those added later
+    pub fn serialize_extra_fields(&mut self) {
+        self.configure_book = Some(OrderBook::serialize_extra_fields);
+        for book in self.books.values_mut() {
+            book.serialize_extra_fields();
+        }
+    }
+}
+
 impl<T> BookManager<T> for BookManagerStd<T>
 where
     T: Clone + Send + Sync + Default + 'static,
@@ -131,7 +179,10 @@ where
             }
         });
 
-        let book = OrderBook::with_trade_listener(symbol, trade_listener);
+        let mut book = OrderBook::with_trade_listener(symbol, trade_listener);
+        if let Some(configure) = self.configure_book {
+            configure(&mut book);
+        }
         self.books.insert(symbol.to_string(), book);
         info!("Added order book for symbol: {}", symbol);
     }
@@ -149,6 +200,7 @@ where
 
     fn remove_book(&mut self, symbol: &str) -> Option<OrderBook<T>> {
         let result = self.books.remove(symbol);
+        self.specs.remove(symbol);
         if result.is_some() {
             info!("Removed order book for symbol: {}", symbol);
         }
@@ -162,6 +214,18 @@ where
     fn book_count(&self) -> usize {
         self.books.len()
     }
+
+    fn global_stats(&self) -> GlobalStats {
+        GlobalStats::gather(self.books.values(), &self.trade_rate)
+    }
+
+    fn set_instrument_spec(&mut self, symbol: &str, spec: InstrumentSpec) {
+        self.specs.insert(symbol.to_string(), spec);
+    }
+
+    fn instrument_spec(&self, symbol: &str) -> Option<&InstrumentSpec> {
+        self.specs.get(symbol)
+    }
 }
 
 impl<T> Default for BookManagerStd<T>
@@ -180,10 +244,16 @@ where
 {
     /// Collection of order books indexed by symbol
     books: HashMap<String, OrderBook<T>>,
+    /// Specs of the symbols created with one
+    specs: HashMap<String, InstrumentSpec>,
     /// Sender for trade events
     trade_sender: tokio::sync::mpsc::UnboundedSender<TradeEvent>,
     /// Receiver for trade events (taken when processor starts)
     trade_receiver: Option<tokio::sync::mpsc::UnboundedReceiver<TradeEvent>>,
+    /// Trade total at the last call to `global_stats`
+    trade_rate: TradeRateSampler,
+    /// Applied to every book added, once extra fields are serialized
+    configure_book: Option<fn(&mut OrderBook<T>)>,
 }
 
 impl<T> BookManagerTokio<T>
@@ -196,8 +266,11 @@ where
 
         Self {
             books: HashMap::new(),
+            specs: HashMap::new(),
             trade_sender: sender,
             trade_receiver: Some(receiver),
+            trade_rate: TradeRateSampler::new(),
+            configure_book: None,
         }
     }
 
@@ -244,6 +317,20 @@ where
     }
 }
 
+impl<T> BookManagerTokio<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + Default + 'static,
+{
+    /// Carries the extra fields of orders into snapshots and trade results in
+    /// every book, including those added later
+    pub fn serialize_extra_fields(&mut self) {
+        self.configure_book = Some(OrderBook::serialize_extra_fields);
+        for book in self.books.values_mut() {
+            book.serialize_extra_fields();
+        }
+    }
+}
+
 impl<T> BookManager<T> for BookManagerTokio<T>
 where
     T: Clone + Send + Sync + Default + 'static,
@@ -267,7 +354,10 @@ where
             }
         });
 
-        let book = OrderBook::with_trade_listener(symbol, trade_listener);
+        let mut book = OrderBook::with_trade_listener(symbol, trade_listener);
+        if let Some(configure) = self.configure_book {
+            configure(&mut book);
+        }
         self.books.insert(symbol.to_string(), book);
         info!("Added order book for symbol: {}", symbol);
     }
@@ -286,6 +376,7 @@ where
 
     fn remove_book(&mut self, symbol: &str) -> Option<OrderBook<T>> {
         let result = self.books.remove(symbol);
+        self.specs.remove(symbol);
         if result.is_some() {
             info!("Removed order book for symbol: {}", symbol);
         }
@@ -299,6 +390,18 @@ where
     fn book_count(&self) -> usize {
         self.books.len()
     }
+
+    fn global_stats(&self) -> GlobalStats {
+        GlobalStats::gather(self.books.values(), &self.trade_rate)
+    }
+
+    fn set_instrument_spec(&mut self, symbol: &str, spec: InstrumentSpec) {
+        self.specs.insert(symbol.to_string(), spec);
+    }
+
+    fn instrument_spec(&self, symbol: &str) -> Option<&InstrumentSpec> {
+        self.specs.get(symbol)
+    }
 }
 
 impl<T> Default for BookManagerTokio<T>
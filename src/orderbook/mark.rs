@@ -0,0 +1,165 @@
+//! Mark prices of books with an empty side
+//!
+//! The mid price is only defined while both sides of a book are quoted. The mark
+//! price falls back along a configured list of sources instead, and reports the
+//! source it was taken from, so consumers can tell a quoted mid from a stale last
+//! trade rather than being handed no price, or a zero, on a one-sided book.
+
+use super::OrderBook;
+use serde::{Deserialize, Serialize};
+
+/// Where a mark price was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkSource {
+    /// Average of the best bid and best ask
+    Mid,
+    /// Last trade price of the book
+    LastTrade,
+    /// Close of the previous session, from the instrument's metadata
+    PreviousClose,
+    /// External reference price, such as the value of an index
+    ExternalReference,
+}
+
+/// A mark price and the source it was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MarkPrice {
+    pub price: f64,
+    pub source: MarkSource,
+}
+
+/// Sources a book's mark price is taken from, in order of preference
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarkPricing {
+    /// Sources tried in order, the first with a price winning
+    #[serde(default = "MarkPricing::default_sources")]
+    pub sources: Vec<MarkSource>,
+    /// Close of the previous session
+    #[serde(default)]
+    pub previous_close: Option<u64>,
+}
+
+impl MarkPricing {
+    fn default_sources() -> Vec<MarkSource> {
+        vec![
+            MarkSource::Mid,
+            MarkSource::LastTrade,
+            MarkSource::PreviousClose,
+            MarkSource::ExternalReference,
+        ]
+    }
+}
+
+impl Default for MarkPricing {
+    fn default() -> Self {
+        Self {
+            sources: Self::default_sources(),
+            previous_close: None,
+        }
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set the sources the mark price is taken from
+    pub fn set_mark_pricing(&mut self, pricing: MarkPricing) {
+        self.mark_pricing = pricing;
+    }
+
+    /// Get the sources the mark price is taken from
+    pub fn mark_pricing(&self) -> &MarkPricing {
+        &self.mark_pricing
+    }
+
+    /// Price of the book according to one source, if that source has one
+    pub fn price_from(&self, source: MarkSource) -> Option<f64> {
+        match source {
+            MarkSource::Mid => self.mid_price(),
+            MarkSource::LastTrade => self.last_trade_price().map(|price| price as f64),
+            MarkSource::PreviousClose => self.mark_pricing.previous_close.map(|price| price as f64),
+            MarkSource::ExternalReference => {
+                self.external_reference_price().map(|price| price as f64)
+            }
+        }
+    }
+
+    /// Price of the first configured source that has one, `None` when none has
+    pub fn mark_price(&self) -> Option<MarkPrice> {
+        self.mark_pricing.sources.iter().find_map(|&source| {
+            self.price_from(source)
+                .map(|price| MarkPrice { price, source })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    #[test]
+    fn test_mark_price_falls_back_when_a_side_is_empty() {
+        let mut book = OrderBook::<()>::new("TEST");
+        assert_eq!(book.mark_price(), None);
+        book.set_external_reference_price(95);
+        assert_eq!(
+            book.mark_price(),
+            Some(MarkPrice {
+                price: 95.0,
+                source: MarkSource::ExternalReference,
+            })
+        );
+        book.set_mark_pricing(MarkPricing {
+            previous_close: Some(98),
+            ..MarkPricing::default()
+        });
+        assert_eq!(
+            book.mark_price().map(|mark| mark.source),
+            Some(MarkSource::PreviousClose)
+        );
+
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            5,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.submit_market_order(OrderId::from_u64(2), 2, Side::Buy)
+            .unwrap();
+        // The ask alone is no mid
+        assert_eq!(
+            book.mark_price(),
+            Some(MarkPrice {
+                price: 100.0,
+                source: MarkSource::LastTrade,
+            })
+        );
+        book.add_limit_order(
+            OrderId::from_u64(3),
+            96,
+            5,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            book.mark_price().map(|mark| mark.source),
+            Some(MarkSource::Mid)
+        );
+        assert_eq!(book.mark_price().map(|mark| mark.price), Some(98.0));
+
+        // The previous close wins over the last trade when preferred
+        book.set_mark_pricing(MarkPricing {
+            sources: vec![MarkSource::PreviousClose, MarkSource::Mid],
+            previous_close: Some(97),
+        });
+        assert_eq!(book.mark_price().map(|mark| mark.price), Some(97.0));
+    }
+}
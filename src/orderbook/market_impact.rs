@@ -6,9 +6,14 @@
 //! - Expected slippage
 //! - Number of price levels consumed
 //! - Available liquidity in price ranges
+//! - Model-based expected impact (linear or square-root) with latency slippage
 
+use pricelevel::Side;
 use serde::{Deserialize, Serialize};
 
+/// Basis points per unit of relative price change
+const BASIS_POINTS_MULTIPLIER: f64 = 10_000.0;
+
 /// Represents the market impact analysis of an order
 ///
 /// Provides detailed metrics about how an order would affect the market,
@@ -32,6 +37,71 @@ pub struct MarketImpact {
 
     /// Total quantity available to fill the order (in units)
     pub total_quantity_available: u64,
+
+    /// Model-based expected impact, present when the book has an impact model configured
+    #[serde(default)]
+    pub model_estimate: Option<ImpactEstimate>,
+}
+
+/// Functional form of a model-based impact estimate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactModelKind {
+    /// Impact grows linearly with participation
+    Linear,
+    /// Impact grows with the square root of participation
+    SquareRoot,
+}
+
+/// Parametric market impact model for a single instrument
+///
+/// Participation is the order quantity divided by `reference_volume`. The temporary
+/// component applies only to the order itself, while the permanent component is the
+/// expected lasting shift of the price after execution. Latency slippage models the
+/// adverse drift of the price between decision and arrival at the book.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImpactModel {
+    /// Functional form applied to participation
+    pub kind: ImpactModelKind,
+
+    /// Temporary impact coefficient (in basis points at full participation)
+    pub temporary_coefficient: f64,
+
+    /// Permanent impact coefficient (in basis points at full participation)
+    pub permanent_coefficient: f64,
+
+    /// Volume used to normalize order size, e.g. average daily volume (in units)
+    pub reference_volume: u64,
+
+    /// Expected order latency (in milliseconds)
+    #[serde(default)]
+    pub latency_ms: u64,
+
+    /// Price volatility (in basis points per square root of a second)
+    #[serde(default)]
+    pub volatility_bps: f64,
+}
+
+/// Expected impact of an order as predicted by an `ImpactModel`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImpactEstimate {
+    /// Order quantity relative to the model's reference volume
+    pub participation: f64,
+
+    /// Temporary impact (in basis points)
+    pub temporary_bps: f64,
+
+    /// Permanent impact (in basis points)
+    pub permanent_bps: f64,
+
+    /// Expected slippage from latency (in basis points)
+    pub latency_bps: f64,
+
+    /// Sum of all components (in basis points)
+    pub total_bps: f64,
+
+    /// Expected average execution price (in price units)
+    pub expected_price: f64,
 }
 
 /// Represents a simulated order execution
@@ -67,6 +137,7 @@ impl MarketImpact {
             slippage_bps: 0.0,
             levels_consumed: 0,
             total_quantity_available: 0,
+            model_estimate: None,
         }
     }
 
@@ -98,6 +169,97 @@ impl MarketImpact {
     }
 }
 
+impl ImpactModel {
+    /// Creates a linear impact model without latency slippage
+    #[must_use]
+    pub fn linear(
+        temporary_coefficient: f64,
+        permanent_coefficient: f64,
+        reference_volume: u64,
+    ) -> Self {
+        Self {
+            kind: ImpactModelKind::Linear,
+            temporary_coefficient,
+            permanent_coefficient,
+            reference_volume,
+            latency_ms: 0,
+            volatility_bps: 0.0,
+        }
+    }
+
+    /// Creates a square-root impact model without latency slippage
+    #[must_use]
+    pub fn square_root(
+        temporary_coefficient: f64,
+        permanent_coefficient: f64,
+        reference_volume: u64,
+    ) -> Self {
+        Self {
+            kind: ImpactModelKind::SquareRoot,
+            ..Self::linear(
+                temporary_coefficient,
+                permanent_coefficient,
+                reference_volume,
+            )
+        }
+    }
+
+    /// Adds latency slippage to the model
+    ///
+    /// # Arguments
+    /// - `latency_ms`: Expected order latency (in milliseconds)
+    /// - `volatility_bps`: Price volatility (in basis points per square root of a second)
+    #[must_use]
+    pub fn with_latency(mut self, latency_ms: u64, volatility_bps: f64) -> Self {
+        self.latency_ms = latency_ms;
+        self.volatility_bps = volatility_bps;
+        self
+    }
+
+    /// Estimates the impact of an order
+    ///
+    /// # Arguments
+    /// - `quantity`: The order quantity (in units)
+    /// - `side`: The side of the order
+    /// - `reference_price`: Price the impact is measured from, usually the mid (in price units)
+    ///
+    /// # Returns
+    /// An `ImpactEstimate` whose expected price is above the reference for buys
+    /// and below it for sells.
+    #[must_use]
+    pub fn estimate(&self, quantity: u64, side: Side, reference_price: f64) -> ImpactEstimate {
+        let participation = if self.reference_volume == 0 {
+            0.0
+        } else {
+            quantity as f64 / self.reference_volume as f64
+        };
+        let scale = match self.kind {
+            ImpactModelKind::Linear => participation,
+            ImpactModelKind::SquareRoot => participation.sqrt(),
+        };
+        let temporary_bps = self.temporary_coefficient * scale;
+        let permanent_bps = self.permanent_coefficient * scale;
+        let latency_bps = self.volatility_bps * (self.latency_ms as f64 / 1000.0).sqrt();
+        let total_bps = temporary_bps + permanent_bps + latency_bps;
+
+        let direction = match side {
+            Side::Buy => 1.0,
+            Side::Sell => -1.0,
+        };
+        let expected_price =
+            reference_price * (1.0 + direction * total_bps / BASIS_POINTS_MULTIPLIER);
+
+        ImpactEstimate {
+            participation,
+            temporary_bps,
+            permanent_bps,
+            latency_bps,
+            total_bps,
+            expected_price,
+        }
+    }
+}
+
 impl OrderSimulation {
     /// Creates a new OrderSimulation with empty fills
     ///
@@ -165,6 +327,7 @@ mod tests {
             slippage_bps: 50.0,
             levels_consumed: 3,
             total_quantity_available: 100,
+            model_estimate: None,
         };
 
         assert!(impact.can_fill(100));
@@ -181,6 +344,7 @@ mod tests {
             slippage_bps: 50.0,
             levels_consumed: 3,
             total_quantity_available: 75,
+            model_estimate: None,
         };
 
         assert_eq!(impact.fill_ratio(100), 0.75);
@@ -237,4 +401,71 @@ mod tests {
         // (100 * 10) + (105 * 10) = 1000 + 1050 = 2050
         assert_eq!(sim.total_cost(), 2050);
     }
+
+    #[test]
+    fn test_impact_model_linear_vs_square_root() {
+        let linear = ImpactModel::linear(100.0, 20.0, 10_000);
+        let sqrt = ImpactModel::square_root(100.0, 20.0, 10_000);
+
+        let linear_estimate = linear.estimate(2_500, Side::Buy, 100.0);
+        assert_eq!(linear_estimate.participation, 0.25);
+        assert_eq!(linear_estimate.temporary_bps, 25.0);
+        assert_eq!(linear_estimate.permanent_bps, 5.0);
+        assert_eq!(linear_estimate.total_bps, 30.0);
+        assert!((linear_estimate.expected_price - 100.3).abs() < 1e-9);
+
+        let sqrt_estimate = sqrt.estimate(2_500, Side::Sell, 100.0);
+        assert_eq!(sqrt_estimate.temporary_bps, 50.0);
+        assert_eq!(sqrt_estimate.permanent_bps, 10.0);
+        assert!((sqrt_estimate.expected_price - 99.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_impact_model_latency_component() {
+        let model = ImpactModel::linear(0.0, 0.0, 1_000).with_latency(4_000, 5.0);
+        let estimate = model.estimate(100, Side::Buy, 1_000.0);
+
+        // 5 bps * sqrt(4s) = 10 bps
+        assert_eq!(estimate.latency_bps, 10.0);
+        assert_eq!(estimate.total_bps, 10.0);
+    }
+
+    #[test]
+    fn test_impact_model_zero_reference_volume() {
+        let model = ImpactModel::square_root(100.0, 20.0, 0);
+        let estimate = model.estimate(100, Side::Buy, 50.0);
+        assert_eq!(estimate.participation, 0.0);
+        assert_eq!(estimate.expected_price, 50.0);
+    }
+
+    #[test]
+    fn test_book_market_impact_includes_model_estimate() {
+        use crate::orderbook::OrderBook;
+        use pricelevel::{OrderId, TimeInForce};
+
+        let mut book: OrderBook<()> = OrderBook::new("TEST");
+        let _ = book.add_limit_order(
+            OrderId::from_u64(1),
+            99,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        );
+        let _ = book.add_limit_order(
+            OrderId::from_u64(2),
+            101,
+            10,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        );
+        assert!(book.market_impact(5, Side::Buy).model_estimate.is_none());
+
+        book.set_impact_model(Some(ImpactModel::linear(100.0, 0.0, 100)));
+        let estimate = book.market_impact(5, Side::Buy).model_estimate.unwrap();
+        // Measured from the mid (100), 5% participation -> 5 bps
+        assert_eq!(estimate.total_bps, 5.0);
+        assert!((estimate.expected_price - 100.05).abs() < 1e-9);
+    }
 }
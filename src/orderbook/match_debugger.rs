@@ -0,0 +1,287 @@
+//! Step-through execution of order matching, for debugging and tests
+//!
+//! `OrderBook::debug_match` returns a [`MatchDebugger`] that performs the same
+//! matching as `match_order`, but one price level at a time. After each step the
+//! book is left consistent (emptied levels and filled orders are removed), so it can
+//! be inspected between steps, either by the caller or through a registered hook.
+//!
+//! Only available with the `match-debugger` feature.
+
+use super::OrderBook;
+use super::error::OrderBookError;
+use pricelevel::{MatchResult, OrderId, Side, Transaction};
+
+/// What happened at one price level during a stepped match
+#[derive(Debug, Clone)]
+pub struct MatchStep {
+    /// Zero-based index of this step
+    pub index: usize,
+
+    /// Price of the level that was matched
+    pub price: u64,
+
+    /// Quantity of the incoming order still unfilled before this step
+    pub quantity_before: u64,
+
+    /// Quantity of the incoming order still unfilled after this step
+    pub quantity_after: u64,
+
+    /// Transactions produced at this level
+    pub transactions: Vec<Transaction>,
+
+    /// Displayed quantity left at the level after this step
+    pub level_visible_quantity: u64,
+
+    /// Hidden quantity left at the level after this step
+    pub level_hidden_quantity: u64,
+
+    /// Whether matching stopped at this level because the next fill would be dust
+    pub blocked: bool,
+}
+
+type StepHook<'a, T> = Box<dyn FnMut(&MatchStep, &OrderBook<T>) + 'a>;
+
+/// Executes a match against a book one price level at a time
+pub struct MatchDebugger<'a, T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    book: &'a OrderBook<T>,
+    order_id: OrderId,
+    side: Side,
+    quantity: u64,
+    limit_price: Option<u64>,
+    remaining_quantity: u64,
+    match_result: MatchResult,
+    maker_order_ids: Vec<OrderId>,
+    steps: Vec<MatchStep>,
+    hooks: Vec<StepHook<'a, T>>,
+    finished: bool,
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Starts a stepped match of an incoming order; no level is touched until the
+    /// first call to [`MatchDebugger::step`]
+    pub fn debug_match(
+        &self,
+        order_id: OrderId,
+        side: Side,
+        quantity: u64,
+        limit_price: Option<u64>,
+    ) -> MatchDebugger<'_, T> {
+        MatchDebugger {
+            book: self,
+            order_id,
+            side,
+            quantity,
+            limit_price,
+            remaining_quantity: quantity,
+            match_result: MatchResult::new(order_id, quantity),
+            maker_order_ids: Vec::new(),
+            steps: Vec::new(),
+            hooks: Vec::new(),
+            finished: quantity == 0,
+        }
+    }
+}
+
+impl<'a, T> MatchDebugger<'a, T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Registers a hook called with each step and the book right after the step
+    pub fn with_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&MatchStep, &OrderBook<T>) + 'a,
+    {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Price of the level the next step would match, if matching can continue
+    pub fn next_price(&self) -> Option<u64> {
+        if self.finished {
+            return None;
+        }
+        let price = match self.side {
+            Side::Buy => *self.book.asks.front()?.key(),
+            Side::Sell => *self.book.bids.back()?.key(),
+        };
+        match (self.side, self.limit_price) {
+            (Side::Buy, Some(limit)) if price > limit => None,
+            (Side::Sell, Some(limit)) if price < limit => None,
+            _ => Some(price),
+        }
+    }
+
+    /// Matches the next price level, returning `None` once matching is complete
+    pub fn step(&mut self) -> Option<&MatchStep> {
+        let Some(price) = self.next_price() else {
+            self.finished = true;
+            return None;
+        };
+        let book = self.book;
+        let levels = match self.side {
+            Side::Buy => &book.asks,
+            Side::Sell => &book.bids,
+        };
+        let entry = levels.get(&price)?;
+        let price_level = entry.value();
+
+        book.cache.invalidate();
+        let transactions_before = self.match_result.transactions.as_vec().len();
+        let mut filled_orders = Vec::new();
+        let quantity_before = self.remaining_quantity;
+        let (remaining, blocked) = book.match_price_level(
+            self.order_id,
+            self.side,
+            price,
+            price_level,
+            quantity_before,
+            &mut self.match_result,
+            &mut filled_orders,
+            &mut self.maker_order_ids,
+        );
+        self.remaining_quantity = remaining;
+
+        // Leave the book consistent so it can be inspected between steps
+        for order_id in &filled_orders {
+            book.order_locations.remove(order_id);
+        }
+        book.extra_fields.retire(&filled_orders);
+        let step = MatchStep {
+            index: self.steps.len(),
+            price,
+            quantity_before,
+            quantity_after: remaining,
+            transactions: self.match_result.transactions.as_vec()[transactions_before..].to_vec(),
+            level_visible_quantity: price_level.visible_quantity(),
+            level_hidden_quantity: price_level.hidden_quantity(),
+            blocked,
+        };
+        if price_level.order_count() == 0 {
+            levels.remove(&price);
+        }
+        drop(entry);
+
+        // A level that produced nothing would be matched again forever
+        if remaining == 0 || blocked || remaining == quantity_before {
+            self.finished = true;
+        }
+        for hook in &mut self.hooks {
+            hook(&step, book);
+        }
+        self.steps.push(step);
+        self.steps.last()
+    }
+
+    /// Steps taken so far
+    pub fn steps(&self) -> &[MatchStep] {
+        &self.steps
+    }
+
+    /// Quantity of the incoming order still unfilled
+    pub fn remaining_quantity(&self) -> u64 {
+        self.remaining_quantity
+    }
+
+    /// Whether no further step will match anything
+    pub fn is_finished(&self) -> bool {
+        self.finished || self.next_price().is_none()
+    }
+
+    /// Runs the remaining steps and returns the overall result
+    ///
+    /// The result is the same `match_order` would have produced, including the
+    /// `InsufficientLiquidity` error for market orders that fill nothing. As with
+    /// `match_order`, the trade listener is not invoked.
+    pub fn finish(mut self) -> Result<MatchResult, OrderBookError> {
+        while self.step().is_some() {}
+        self.book.cancel_dust_remainders(&self.maker_order_ids);
+
+        if self.limit_price.is_none() && self.remaining_quantity == self.quantity {
+            return Err(OrderBookError::InsufficientLiquidity {
+                side: self.side,
+                requested: self.quantity,
+                available: 0,
+            });
+        }
+        let mut match_result = self.match_result;
+        match_result.remaining_quantity = self.remaining_quantity;
+        match_result.is_complete = self.remaining_quantity == 0;
+        Ok(match_result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::TimeInForce;
+    use std::cell::RefCell;
+
+    fn three_level_book() -> OrderBook<()> {
+        let book = OrderBook::new("TEST");
+        for (id, price) in [(1, 100), (2, 101), (3, 102)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                10,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_steps_one_level_at_a_time() {
+        let book = three_level_book();
+        let mut debugger = book.debug_match(OrderId::from_u64(10), Side::Buy, 25, None);
+
+        assert_eq!(debugger.next_price(), Some(100));
+        let step = debugger.step().unwrap();
+        assert_eq!(step.price, 100);
+        assert_eq!(step.quantity_after, 15);
+        assert_eq!(step.transactions.len(), 1);
+        // The first level is gone before the second step runs
+        assert_eq!(book.best_ask(), Some(101));
+
+        let result = debugger.finish().unwrap();
+        assert_eq!(result.executed_quantity(), 25);
+        assert_eq!(book.best_ask(), Some(102));
+        assert_eq!(
+            book.get_order(OrderId::from_u64(3))
+                .unwrap()
+                .visible_quantity(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_hooks_see_each_step_and_limit_stops_matching() {
+        let book = three_level_book();
+        let seen = RefCell::new(Vec::new());
+        let debugger = book
+            .debug_match(OrderId::from_u64(10), Side::Buy, 30, Some(101))
+            .with_hook(|step, book| seen.borrow_mut().push((step.price, book.best_ask())));
+
+        let result = debugger.finish().unwrap();
+        assert_eq!(result.remaining_quantity, 10);
+        assert_eq!(seen.into_inner(), vec![(100, Some(101)), (101, Some(102))]);
+    }
+
+    #[test]
+    fn test_market_order_on_empty_side_fails_like_match_order() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        let debugger = book.debug_match(OrderId::from_u64(10), Side::Sell, 5, None);
+        assert!(matches!(
+            debugger.finish(),
+            Err(OrderBookError::InsufficientLiquidity { .. })
+        ));
+    }
+}
@@ -1,10 +1,13 @@
 //! Contains the core matching engine logic for the order book.
 
 use super::OrderBook;
-use crate::orderbook::book_change_event::PriceLevelChangedEvent;
+use crate::orderbook::book_change_event::{OrderChange, OrderChangedEvent, PriceLevelChangedEvent};
 use crate::orderbook::error::OrderBookError;
+use crate::orderbook::flight_recorder::FlightEvent;
 use crate::orderbook::pool::MatchingPool;
-use pricelevel::{MatchResult, OrderId, Side};
+use crate::orderbook::priority::PriorityTier;
+use pricelevel::{MatchResult, OrderId, PriceLevel, Side};
+use std::collections::HashMap;
 use std::sync::atomic::Ordering;
 
 impl<T> OrderBook<T>
@@ -29,8 +32,10 @@ where
         limit_price: Option<u64>,
     ) -> Result<MatchResult, OrderBookError> {
         self.cache.invalidate();
+        self.extra_fields.start_match();
         let mut match_result = MatchResult::new(order_id, quantity);
         let mut remaining_quantity = quantity;
+        let mut maker_order_ids = Vec::new();
 
         // Choose the appropriate side for matching
         let match_side = match side {
@@ -86,50 +91,25 @@ where
             // Get price level value from the entry
             let price_level = entry.value();
 
-            // Perform the match at this price level
-            let price_level_match = price_level.match_order(
-                remaining_quantity,
+            let (remaining, blocked) = self.match_price_level(
                 order_id,
-                &self.transaction_id_generator,
+                side,
+                price,
+                price_level,
+                remaining_quantity,
+                &mut match_result,
+                &mut filled_orders,
+                &mut maker_order_ids,
             );
-
-            // Process transactions if any occurred
-            if !price_level_match.transactions.as_vec().is_empty() {
-                // Update last trade price atomically
-                self.last_trade_price.store(price, Ordering::Relaxed);
-                self.has_traded.store(true, Ordering::Relaxed);
-
-                // Add transactions to result
-                for transaction in price_level_match.transactions.as_vec() {
-                    match_result.add_transaction(*transaction);
-                }
-
-                // notify price level changes
-                if let Some(ref listener) = self.price_level_changed_listener {
-                    listener(PriceLevelChangedEvent {
-                        side: side.opposite(),
-                        price: price_level.price(),
-                        quantity: price_level.visible_quantity(),
-                    });
-                }
-            }
-
-            // Collect filled orders for batch removal
-            for &filled_order_id in &price_level_match.filled_order_ids {
-                match_result.add_filled_order_id(filled_order_id);
-                filled_orders.push(filled_order_id);
-            }
-
-            // Update remaining quantity
-            remaining_quantity = price_level_match.remaining_quantity;
+            remaining_quantity = remaining;
 
             // Check if price level is empty and mark for removal
             if price_level.order_count() == 0 {
                 empty_price_levels.push(price);
             }
 
-            // Early exit if order is fully matched
-            if remaining_quantity == 0 {
+            // Early exit if order is fully matched or matching is blocked by dust
+            if remaining_quantity == 0 || blocked {
                 break;
             }
         }
@@ -143,6 +123,7 @@ where
         for order_id in &filled_orders {
             self.order_locations.remove(order_id);
         }
+        self.extra_fields.retire(&filled_orders);
 
         // Return vectors to pool for reuse
         MATCHING_POOL.with(|pool| {
@@ -150,6 +131,8 @@ where
             pool.return_price_vec(empty_price_levels);
         });
 
+        self.cancel_dust_remainders(&maker_order_ids);
+
         // Check for insufficient liquidity in market orders
         if limit_price.is_none() && remaining_quantity == quantity {
             return Err(OrderBookError::InsufficientLiquidity {
@@ -166,6 +149,138 @@ where
         Ok(match_result)
     }
 
+    /// Matches an incoming order against a single price level, in priority tiers
+    ///
+    /// Returns the quantity still unfilled and whether matching is blocked because
+    /// the next fill at this level would be below the minimum fill notional.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn match_price_level(
+        &self,
+        order_id: OrderId,
+        side: Side,
+        price: u64,
+        price_level: &PriceLevel,
+        mut remaining_quantity: u64,
+        match_result: &mut MatchResult,
+        filled_orders: &mut Vec<OrderId>,
+        maker_order_ids: &mut Vec<OrderId>,
+    ) -> (u64, bool) {
+        // Displayed quantity fills before hidden reserve at the same price
+        let mut blocked = false;
+        for tier in PriorityTier::MATCHING_ORDER {
+            if price_level.order_count() == 0 {
+                break;
+            }
+            let tier_quantity = tier.match_limit(price_level, remaining_quantity);
+            if tier_quantity == 0 {
+                continue;
+            }
+
+            // Only match what can be filled without producing dust fills, shared
+            // out by the book's policy in the displayed tier
+            let allocations = match tier {
+                PriorityTier::Displayed => {
+                    self.allocate_displayed(price_level, price, tier_quantity)
+                }
+                PriorityTier::NonDisplayed => None,
+            };
+            let match_quantity = match &allocations {
+                Some(allocations) => allocations.iter().map(|&(_, fill)| fill).sum(),
+                None => self.dust_free_quantity(price_level, price, tier_quantity),
+            };
+            if match_quantity == 0 {
+                blocked = true;
+                break;
+            }
+
+            // Perform the match at this price level
+            let visible_quantity = price_level.visible_quantity();
+            let price_level_match = match allocations {
+                Some(allocations) => self.fill_allocations(price_level, &allocations, order_id),
+                None => price_level.match_order(
+                    match_quantity,
+                    order_id,
+                    &self.transaction_id_generator,
+                ),
+            };
+
+            // Process transactions if any occurred
+            if !price_level_match.transactions.as_vec().is_empty() {
+                // Update last trade price atomically
+                self.last_trade_price.store(price, Ordering::Relaxed);
+                self.has_traded.store(true, Ordering::Relaxed);
+
+                self.activity
+                    .traded(price_level_match.transactions.as_vec().len() as u64);
+                // Add transactions to result
+                for transaction in price_level_match.transactions.as_vec() {
+                    match_result.add_transaction(*transaction);
+                    maker_order_ids.push(transaction.maker_order_id);
+                    self.record_flight_event(FlightEvent::Trade {
+                        taker_order_id: transaction.taker_order_id,
+                        maker_order_id: transaction.maker_order_id,
+                        price: transaction.price,
+                        quantity: transaction.quantity,
+                    });
+                }
+
+                // notify price level changes
+                let executed = match_quantity - price_level_match.remaining_quantity;
+                if let Some(ref listener) = self.price_level_changed_listener {
+                    listener(PriceLevelChangedEvent {
+                        side: side.opposite(),
+                        price: price_level.price(),
+                        quantity: price_level.visible_quantity(),
+                        // Displayed quantity only grows by refreshing reserve
+                        replenished: (price_level.visible_quantity() + executed)
+                            .saturating_sub(visible_quantity),
+                    });
+                }
+                if let Some(ref listener) = self.order_changed_listener {
+                    // Makers still resting show what is left of them; filled
+                    // ones are gone
+                    let resting: HashMap<OrderId, _> = price_level
+                        .iter_orders()
+                        .into_iter()
+                        .map(|order| (order.id(), order))
+                        .collect();
+                    for transaction in price_level_match.transactions.as_vec() {
+                        let maker = resting.get(&transaction.maker_order_id);
+                        listener(OrderChangedEvent {
+                            order_id: transaction.maker_order_id,
+                            change: OrderChange::Executed,
+                            side: side.opposite(),
+                            price: price_level.price(),
+                            quantity: maker.map_or(0, |order| order.visible_quantity()),
+                            executed: transaction.quantity,
+                            priority: maker.map_or(0, |order| order.timestamp()),
+                        });
+                    }
+                }
+            }
+
+            // Collect filled orders for batch removal
+            for &filled_order_id in &price_level_match.filled_order_ids {
+                match_result.add_filled_order_id(filled_order_id);
+                filled_orders.push(filled_order_id);
+            }
+
+            // Update remaining quantity
+            remaining_quantity -= match_quantity - price_level_match.remaining_quantity;
+
+            // The next fill at this level would be dust; later orders and worse
+            // levels must not be filled ahead of it
+            if match_quantity < tier_quantity {
+                blocked = true;
+                break;
+            }
+            if remaining_quantity == 0 {
+                break;
+            }
+        }
+        (remaining_quantity, blocked)
+    }
+
     /// Optimized peek match without memory pooling or sorting
     ///
     /// # Performance Optimization
@@ -212,7 +327,11 @@ where
             let available_quantity = price_level.total_quantity();
             let needed_quantity = quantity.saturating_sub(matched_quantity);
             let quantity_to_match = needed_quantity.min(available_quantity);
-            matched_quantity = matched_quantity.saturating_add(quantity_to_match);
+            let dust_free_quantity = self.dust_free_quantity(price_level, price, quantity_to_match);
+            matched_quantity = matched_quantity.saturating_add(dust_free_quantity);
+            if dust_free_quantity < quantity_to_match {
+                break;
+            }
         }
 
         matched_quantity
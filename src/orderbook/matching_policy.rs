@@ -0,0 +1,275 @@
+//! Allocation of fills between the orders resting at a price
+//!
+//! Books match in price-time priority by default. Futures-style markets often
+//! allocate an incoming order between the displayed orders at a price instead:
+//! pro rata to their size, or to the largest first. The policy only changes how
+//! the displayed tier is shared out; reserve quantity still fills in time
+//! priority once the displayed tier is exhausted. Orders keep their place in
+//! the queue when partly filled, and the minimum fill notional applies to each
+//! allocation.
+
+use super::OrderBook;
+use pricelevel::{MatchResult, OrderId, OrderUpdate, PriceLevel};
+use serde::{Deserialize, Serialize};
+
+/// How a book shares an incoming order between the orders resting at a price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchingPolicy {
+    /// Orders fill in the order they arrived
+    #[default]
+    Fifo,
+    /// Each order gets a share proportional to its displayed quantity, rounded
+    /// down; what rounding leaves over fills in time priority
+    ProRata,
+    /// The largest displayed orders fill first, ties in time priority
+    SizeTime,
+}
+
+impl MatchingPolicy {
+    /// Shares `quantity` between resting orders, given as their id and displayed
+    /// quantity in time priority, skipping fills below `min_fill`
+    ///
+    /// Returns the quantity allocated to each order, in the order to fill them.
+    pub fn allocate(
+        self,
+        orders: &[(OrderId, u64)],
+        quantity: u64,
+        min_fill: u64,
+    ) -> Vec<(OrderId, u64)> {
+        match self {
+            MatchingPolicy::Fifo => fill_in_order(orders.iter().copied(), quantity, min_fill),
+            MatchingPolicy::SizeTime => {
+                let mut by_size = orders.to_vec();
+                // Stable, so equal sizes keep their time priority
+                by_size.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+                fill_in_order(by_size, quantity, min_fill)
+            }
+            MatchingPolicy::ProRata => {
+                let total: u64 = orders.iter().map(|&(_, size)| size).sum();
+                if total == 0 {
+                    return Vec::new();
+                }
+                let mut shares: Vec<u64> = orders
+                    .iter()
+                    .map(|&(_, size)| {
+                        let share = (u128::from(quantity.min(total)) * u128::from(size)
+                            / u128::from(total)) as u64;
+                        if share < min_fill { 0 } else { share }
+                    })
+                    .collect();
+                let mut left = quantity - shares.iter().sum::<u64>();
+                for (share, &(_, size)) in shares.iter_mut().zip(orders) {
+                    if left == 0 {
+                        break;
+                    }
+                    let top_up = left.min(size - *share);
+                    if *share + top_up >= min_fill {
+                        *share += top_up;
+                        left -= top_up;
+                    }
+                }
+                orders
+                    .iter()
+                    .zip(shares)
+                    .filter(|&(_, share)| share > 0)
+                    .map(|(&(order_id, _), share)| (order_id, share))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Fills orders one after another, stopping at the first fill below `min_fill`
+/// since later orders cannot be filled ahead of it
+fn fill_in_order(
+    orders: impl IntoIterator<Item = (OrderId, u64)>,
+    mut quantity: u64,
+    min_fill: u64,
+) -> Vec<(OrderId, u64)> {
+    let mut allocations = Vec::new();
+    for (order_id, size) in orders {
+        if quantity == 0 {
+            break;
+        }
+        let fill = quantity.min(size);
+        if fill < min_fill {
+            break;
+        }
+        allocations.push((order_id, fill));
+        quantity -= fill;
+    }
+    allocations
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set how incoming orders are shared between the orders resting at a price
+    pub fn set_matching_policy(&mut self, policy: MatchingPolicy) {
+        self.matching_policy = policy;
+    }
+
+    /// Get how incoming orders are shared between the orders resting at a price
+    pub fn matching_policy(&self) -> MatchingPolicy {
+        self.matching_policy
+    }
+
+    /// Allocation of up to `quantity` between the displayed orders of a level,
+    /// `None` when the level simply fills in time priority
+    pub(super) fn allocate_displayed(
+        &self,
+        level: &PriceLevel,
+        price: u64,
+        quantity: u64,
+    ) -> Option<Vec<(OrderId, u64)>> {
+        if self.matching_policy == MatchingPolicy::Fifo {
+            return None;
+        }
+        let orders: Vec<(OrderId, u64)> = level
+            .iter_orders()
+            .iter()
+            .map(|order| (order.id(), order.visible_quantity()))
+            .collect();
+        Some(
+            self.matching_policy
+                .allocate(&orders, quantity, self.min_fill_quantity(price)),
+        )
+    }
+
+    /// Fills each allocated order of a level by its allocation
+    ///
+    /// Each order is matched on a level of its own, so icebergs refresh their
+    /// display as they do in time priority, and put back with what is left.
+    pub(super) fn fill_allocations(
+        &self,
+        level: &PriceLevel,
+        allocations: &[(OrderId, u64)],
+        taker_order_id: OrderId,
+    ) -> MatchResult {
+        let quantity = allocations.iter().map(|&(_, fill)| fill).sum();
+        let mut result = MatchResult::new(taker_order_id, quantity);
+        let mut remaining = quantity;
+        for &(maker_order_id, fill) in allocations {
+            let Ok(Some(order)) = level.update_order(OrderUpdate::Cancel {
+                order_id: maker_order_id,
+            }) else {
+                continue;
+            };
+            let single = PriceLevel::new(level.price());
+            single.add_order(*order);
+            let matched = single.match_order(fill, taker_order_id, &self.transaction_id_generator);
+            for transaction in matched.transactions.as_vec() {
+                result.add_transaction(*transaction);
+            }
+            remaining -= fill - matched.remaining_quantity;
+            // The order is back at its place: the level's queue still holds its id
+            match single.iter_orders().first() {
+                Some(rest) => {
+                    level.add_order(**rest);
+                }
+                None => result.add_filled_order_id(maker_order_id),
+            }
+        }
+        result.remaining_quantity = remaining;
+        result.is_complete = remaining == 0;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderType, Side, TimeInForce};
+
+    /// Rests a sell order timestamped with its id, so time priority is deterministic
+    fn rest(book: &OrderBook<()>, id: u64, quantity: u64) {
+        book.add_order(OrderType::Standard {
+            id: OrderId::from_u64(id),
+            price: 100,
+            quantity,
+            side: Side::Sell,
+            timestamp: id,
+            time_in_force: TimeInForce::Gtc,
+            extra_fields: (),
+        })
+        .unwrap();
+    }
+
+    /// Makers and quantities of the fills of a market buy, in the order filled
+    fn fills(book: &OrderBook<()>, id: u64, quantity: u64) -> Vec<(OrderId, u64)> {
+        let result = book
+            .submit_market_order(OrderId::from_u64(id), quantity, Side::Buy)
+            .unwrap();
+        result
+            .transactions
+            .as_vec()
+            .iter()
+            .map(|fill| (fill.maker_order_id, fill.quantity))
+            .collect()
+    }
+
+    fn made_by(fills: &[(u64, u64)]) -> Vec<(OrderId, u64)> {
+        fills
+            .iter()
+            .map(|&(id, quantity)| (OrderId::from_u64(id), quantity))
+            .collect()
+    }
+
+    #[test]
+    fn test_pro_rata_shares_by_size_and_rounds_in_time_priority() {
+        let orders = [
+            (OrderId::from_u64(1), 10),
+            (OrderId::from_u64(2), 30),
+            (OrderId::from_u64(3), 60),
+        ];
+        // 5, 15 and 30, exactly
+        let shares = MatchingPolicy::ProRata.allocate(&orders, 50, 0);
+        assert_eq!(
+            shares.iter().map(|&(_, fill)| fill).collect::<Vec<_>>(),
+            [5, 15, 30]
+        );
+        // 1.1, 3.3 and 6.6 round down to 1, 3 and 6; the lot left over goes to
+        // the oldest order
+        let shares = MatchingPolicy::ProRata.allocate(&orders, 11, 0);
+        assert_eq!(
+            shares.iter().map(|&(_, fill)| fill).collect::<Vec<_>>(),
+            [2, 3, 6]
+        );
+        // Shares below the minimum fill are skipped
+        let shares = MatchingPolicy::ProRata.allocate(&orders, 11, 3);
+        assert_eq!(
+            shares.iter().map(|&(_, fill)| fill).collect::<Vec<_>>(),
+            [5, 6]
+        );
+    }
+
+    #[test]
+    fn test_books_match_by_their_policy() {
+        let mut book = OrderBook::<()>::new("TEST");
+        book.set_matching_policy(MatchingPolicy::SizeTime);
+        rest(&book, 1, 10);
+        rest(&book, 2, 30);
+        rest(&book, 3, 30);
+        assert_eq!(fills(&book, 4, 40), made_by(&[(2, 30), (3, 10)]));
+        assert_eq!(book.best_ask(), Some(100));
+        assert!(book.check_invariants().is_empty());
+
+        let mut book = OrderBook::<()>::new("TEST");
+        book.set_matching_policy(MatchingPolicy::ProRata);
+        rest(&book, 1, 10);
+        rest(&book, 2, 30);
+        assert_eq!(fills(&book, 3, 20), made_by(&[(1, 5), (2, 15)]));
+        assert_eq!(
+            book.get_order(OrderId::from_u64(2))
+                .unwrap()
+                .visible_quantity(),
+            15
+        );
+        // The order whose share filled it in full leaves the book
+        assert_eq!(fills(&book, 4, 20), made_by(&[(1, 5), (2, 15)]));
+        assert_eq!(book.best_ask(), None);
+        assert!(book.check_invariants().is_empty());
+    }
+}
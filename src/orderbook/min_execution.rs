@@ -0,0 +1,75 @@
+//! Minimum execution quantity and all-or-none checks
+//!
+//! An order carrying a minimum execution quantity trades only if at least that
+//! much of it fills on entry; an all-or-none order is the case where the
+//! minimum is its whole quantity. The book is checked with `peek_match` before
+//! matching, so an order that cannot meet its minimum leaves the book
+//! untouched. The constraint applies on entry only: a resting order cannot
+//! skip incoming orders too small for it without the book crossing, so
+//! whatever rests after the first execution is an ordinary order.
+
+use super::OrderBook;
+use super::error::OrderBookError;
+use pricelevel::Side;
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Checks that at least `min_quantity` would fill on `side` at
+    /// `limit_price` or better, or within the market protection limit for a
+    /// market order
+    pub fn ensure_min_execution(
+        &self,
+        side: Side,
+        min_quantity: u64,
+        limit_price: Option<u64>,
+    ) -> Result<(), OrderBookError> {
+        let limit_price = limit_price.or_else(|| self.market_protection_limit(side));
+        let available = self.peek_match(side, min_quantity, limit_price);
+        if available < min_quantity {
+            return Err(OrderBookError::InsufficientLiquidity {
+                side,
+                requested: min_quantity,
+                available,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, TimeInForce};
+
+    #[test]
+    fn test_min_execution_counts_liquidity_within_the_limit() {
+        let book = OrderBook::<()>::new("TEST");
+        for (id, price) in [(1, 100), (2, 101)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                5,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+
+        assert!(book.ensure_min_execution(Side::Buy, 5, Some(100)).is_ok());
+        assert!(matches!(
+            book.ensure_min_execution(Side::Buy, 8, Some(100)),
+            Err(OrderBookError::InsufficientLiquidity {
+                requested: 8,
+                available: 5,
+                ..
+            })
+        ));
+        assert!(book.ensure_min_execution(Side::Buy, 10, None).is_ok());
+        assert!(book.ensure_min_execution(Side::Sell, 1, None).is_err());
+        // Nothing was taken
+        assert_eq!(book.get_all_orders().len(), 2);
+    }
+}
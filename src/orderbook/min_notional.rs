@@ -0,0 +1,240 @@
+//! Minimum fill notional enforcement
+//!
+//! Venues commonly refuse to print fills whose notional (price times quantity) is
+//! below a minimum. With a minimum configured, matching stops before it would create
+//! such a dust fill rather than skipping ahead in the queue, incoming orders below
+//! the minimum are rejected, and remainders too small to fill again are not left
+//! resting in the book.
+
+use super::OrderBook;
+use super::error::OrderBookError;
+use super::features::BookFeatures;
+use pricelevel::{OrderId, PriceLevel};
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set the minimum notional of a single fill (in price units times quantity);
+    /// `None` disables the check
+    pub fn set_min_fill_notional(&mut self, min_notional: Option<u64>) {
+        self.min_fill_notional = min_notional;
+    }
+
+    /// Get the minimum notional of a single fill, if any and the feature is enabled
+    pub fn min_fill_notional(&self) -> Option<u64> {
+        self.min_fill_notional
+            .filter(|_| self.features.contains(BookFeatures::MIN_FILL_NOTIONAL))
+    }
+
+    /// Smallest quantity that may fill at `price` without producing dust
+    pub fn min_fill_quantity(&self, price: u64) -> u64 {
+        match self.min_fill_notional() {
+            Some(min_notional) if price > 0 => min_notional.div_ceil(price),
+            Some(_) => u64::MAX,
+            None => 0,
+        }
+    }
+
+    /// Returns `true` if `quantity` at `price` meets the minimum fill notional
+    pub fn meets_min_fill_notional(&self, price: u64, quantity: u64) -> bool {
+        quantity >= self.min_fill_quantity(price)
+    }
+
+    /// Rejects an order whose total notional is below the minimum fill notional
+    pub(super) fn ensure_min_fill_notional(
+        &self,
+        price: u64,
+        quantity: u64,
+    ) -> Result<(), OrderBookError> {
+        if self.meets_min_fill_notional(price, quantity) {
+            return Ok(());
+        }
+        Err(OrderBookError::BelowMinimumNotional {
+            notional: u128::from(price) * u128::from(quantity),
+            minimum: self.min_fill_notional().unwrap_or_default(),
+        })
+    }
+
+    /// Quantity that can be matched at a level without producing a dust fill
+    ///
+    /// Resting orders are walked in time priority. Matching stops at the first order
+    /// whose fill would be below the minimum, since later orders cannot be filled
+    /// ahead of it.
+    pub(super) fn dust_free_quantity(&self, level: &PriceLevel, price: u64, quantity: u64) -> u64 {
+        if self.min_fill_notional().is_none() {
+            return quantity;
+        }
+        let min_quantity = self.min_fill_quantity(price);
+        let mut planned = 0u64;
+        for order in level.iter_orders() {
+            let available = quantity - planned;
+            if available == 0 {
+                break;
+            }
+            let fill = available.min(order.visible_quantity());
+            if fill < min_quantity {
+                break;
+            }
+            planned += fill;
+        }
+        planned
+    }
+
+    /// Cancels partially filled resting orders whose remainder can no longer fill
+    pub(super) fn cancel_dust_remainders(&self, maker_order_ids: &[OrderId]) {
+        if self.min_fill_notional().is_none() {
+            return;
+        }
+        for &order_id in maker_order_ids {
+            let Some(order) = self.get_order(order_id) else {
+                continue;
+            };
+            let remaining = order.visible_quantity() + order.hidden_quantity();
+            if !self.meets_min_fill_notional(order.price(), remaining) {
+                let _ = self.cancel_order(order_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderType, Side, TimeInForce};
+
+    fn book_with_min(min_notional: u64) -> OrderBook<()> {
+        let mut book = OrderBook::new("TEST");
+        book.set_min_fill_notional(Some(min_notional));
+        book
+    }
+
+    /// Rests a sell order with an explicit timestamp so queue priority is deterministic
+    fn rest_sell(book: &OrderBook<()>, id: u64, quantity: u64, timestamp: u64) {
+        book.add_order(OrderType::Standard {
+            id: OrderId::from_u64(id),
+            price: 100,
+            quantity,
+            side: Side::Sell,
+            timestamp,
+            time_in_force: TimeInForce::Gtc,
+            extra_fields: (),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_small_orders_are_rejected() {
+        let book = book_with_min(1_000);
+        let result = book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            9,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(OrderBookError::BelowMinimumNotional { .. })
+        ));
+        assert!(
+            book.add_limit_order(
+                OrderId::from_u64(2),
+                100,
+                10,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_matching_stops_before_dust_fill() {
+        let book = book_with_min(1_000);
+        rest_sell(&book, 1, 15, 1);
+        rest_sell(&book, 2, 30, 2);
+
+        // The second fill would only be 5, below the minimum quantity of 10
+        let result = book
+            .submit_market_order(OrderId::from_u64(3), 20, Side::Buy)
+            .unwrap();
+        assert_eq!(result.executed_quantity(), 15);
+        assert_eq!(result.remaining_quantity, 5);
+        assert_eq!(
+            book.get_order(OrderId::from_u64(2))
+                .unwrap()
+                .visible_quantity(),
+            30
+        );
+    }
+
+    #[test]
+    fn test_dust_maker_remainder_is_cancelled() {
+        let book = book_with_min(1_000);
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            25,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        // Filling 20 would leave 5 resting, which can never fill again
+        let result = book
+            .submit_market_order(OrderId::from_u64(2), 20, Side::Buy)
+            .unwrap();
+        assert_eq!(result.executed_quantity(), 20);
+        assert!(book.get_order(OrderId::from_u64(1)).is_none());
+        assert_eq!(book.best_ask(), None);
+    }
+
+    #[test]
+    fn test_remainder_below_minimum_does_not_rest() {
+        let book = book_with_min(1_000);
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            15,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        // 15 fill, leaving 5 which is too small to rest
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            100,
+            20,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        assert!(book.get_order(OrderId::from_u64(2)).is_none());
+        assert_eq!(book.best_bid(), None);
+    }
+
+    #[test]
+    fn test_dust_free_quantity_respects_queue() {
+        let book = book_with_min(1_000);
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            10,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        let level = book.asks.get(&100).unwrap().value().clone();
+
+        assert_eq!(book.dust_free_quantity(&level, 100, 15), 10);
+        assert_eq!(book.dust_free_quantity(&level, 100, 5), 0);
+    }
+}
@@ -1,14 +1,54 @@
 //! OrderBook implementation for managing multiple price levels and order matching.
 
+/// Per-book counters of the operations applied.
+pub mod activity;
+/// Call auctions uncrossing at a single equilibrium price.
+pub mod auction;
+/// Simulated execution of strategy orders against archived book states.
+pub mod backtest;
+pub mod block_trade;
 pub mod book;
+/// Corporate action adjustments to resting orders.
+pub mod corporate_action;
+/// Rolling return correlations between instruments.
+pub mod correlation;
 pub mod error;
+/// Extra fields of resting orders, kept beside the price levels.
+pub mod extra_fields;
+/// Per-book switches for behaviors being rolled out.
+pub mod features;
+/// Per-book ring buffer of recent events for debugging.
+pub mod flight_recorder;
+/// Statistics aggregated across every book of a manager, for monitoring.
+pub mod global_stats;
+/// Archived snapshots and level deltas for reconstructing past depth.
+pub mod history;
+/// Weighted index calculation from constituent instrument prices.
+pub mod index;
+/// Tick and lot sizes and quantity limits of instruments.
+pub mod instrument_spec;
+/// Structural invariant checks for detecting inconsistent book state.
+pub mod invariants;
 /// Functional-style iterators for order book analysis.
 pub mod iterators;
+/// Text ladders of book depth and of changes between snapshots.
+pub mod ladder;
 /// Multi-book management with centralized trade event routing.
 pub mod manager;
+/// Mark prices falling back along configured sources.
+pub mod mark;
 /// Market impact simulation and liquidity analysis.
 pub mod market_impact;
+/// Minimum execution quantity and all-or-none checks.
+pub mod min_execution;
+/// Minimum fill notional enforcement.
+pub mod min_notional;
+/// Step-through execution of matching for debugging.
+#[cfg(feature = "match-debugger")]
+pub mod match_debugger;
 pub mod matching;
+/// Pro-rata and size priority allocation of fills at a price.
+pub mod matching_policy;
 /// Aggregate statistics for order book analysis.
 pub mod statistics;
 
@@ -17,19 +57,60 @@ mod cache;
 /// Contains the core logic for modifying the order book state, such as adding, canceling, or updating orders.
 pub mod modifications;
 pub mod operations;
+/// Repricing of pegged orders after their reference.
+pub mod pegging;
 mod pool;
 mod private;
+/// Displayed and non-displayed priority tiers within a price level.
+pub mod priority;
+/// Price protection for market orders.
+pub mod protection;
+/// Request-for-quote workflow for block-size flow.
+pub mod rfq;
+/// Decimal rendering of prices and quantities for outbound messages.
+pub mod scale;
 pub mod snapshot;
+/// Caps on how far a single aggressive order may sweep.
+pub mod sweep_limit;
 /// Trade-related types including TradeResult and TradeListener for monitoring order executions.
 pub mod trade;
 
+pub use activity::ActivityStats;
+pub use auction::{AuctionPrice, AuctionUncross};
+pub use backtest::{
+    Backtester, FillModel, Liquidity, QueuePosition, SimulatedFill, SimulatedOrder,
+};
+pub use block_trade::{BlockTrade, BlockTradeRules, BlockTradeStats};
 pub use book::OrderBook;
+pub use corporate_action::{CorporateAction, OrderAdjustment};
+pub use correlation::{CorrelationTracker, PairCorrelation, RollingCorrelation};
 pub use error::OrderBookError;
+pub use extra_fields::OrderExtraFields;
+pub use features::BookFeatures;
+pub use flight_recorder::{FlightEvent, FlightRecord, FlightRecorder};
+pub use global_stats::{GlobalStats, InstrumentLoad};
+pub use history::{BookArchive, BookHistory, BookStates, LevelDelta};
+pub use index::{IndexConstituent, IndexDefinition, IndexTick};
+pub use instrument_spec::InstrumentSpec;
+pub use invariants::InvariantViolation;
 pub use iterators::LevelInfo;
+pub use ladder::{Ladder, LadderDiff};
+#[cfg(feature = "match-debugger")]
+pub use match_debugger::{MatchDebugger, MatchStep};
 pub use manager::{BookManager, BookManagerStd};
-pub use market_impact::{MarketImpact, OrderSimulation};
+pub use mark::{MarkPrice, MarkPricing, MarkSource};
+pub use matching_policy::MatchingPolicy;
+pub use pegging::{PegMove, PegRepricing};
+pub use market_impact::{
+    ImpactEstimate, ImpactModel, ImpactModelKind, MarketImpact, OrderSimulation,
+};
+pub use priority::PriorityTier;
+pub use protection::MarketProtection;
+pub use rfq::{RfqError, RfqExecution, RfqExpiry, RfqManager, RfqQuote, RfqRequest};
+pub use scale::{InstrumentScale, NumberFormat, ScaledDepth, ScaledLevel, ScaledValue};
 pub use snapshot::{
     EnrichedSnapshot, MetricFlags, ORDERBOOK_SNAPSHOT_FORMAT_VERSION, OrderBookSnapshot,
     OrderBookSnapshotPackage,
 };
 pub use statistics::{DepthStats, DistributionBin};
+pub use trade::tape::{TapeTrade, TradeTape};
@@ -1,9 +1,11 @@
 use crate::orderbook::book::OrderBook;
-use crate::orderbook::book_change_event::PriceLevelChangedEvent;
+use crate::orderbook::book_change_event::{OrderChange, PriceLevelChangedEvent};
 use crate::orderbook::error::OrderBookError;
+use crate::orderbook::flight_recorder::FlightEvent;
 use crate::orderbook::trade::TradeResult;
-use pricelevel::{OrderId, OrderType, OrderUpdate, PriceLevel, Side};
+use pricelevel::{MatchResult, OrderId, OrderType, OrderUpdate, PriceLevel, Side};
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use tracing::trace;
 
 /// A trait to abstract quantity access and modification for different order types.
@@ -107,8 +109,33 @@ where
         &self,
         update: OrderUpdate,
     ) -> Result<Option<Arc<OrderType<T>>>, OrderBookError> {
+        let is_cancel = matches!(update, OrderUpdate::Cancel { .. });
+        let result = self.apply_update(update);
+        if let Ok(Some(_)) = &result {
+            if is_cancel {
+                self.activity.order_cancelled();
+            } else {
+                self.activity.order_modified();
+            }
+        }
+        result
+    }
+
+    /// Applies an update without counting it, or the cancel and add replacing
+    /// the order, in the book's activity
+    pub(super) fn apply_update(
+        &self,
+        update: OrderUpdate,
+    ) -> Result<Option<Arc<OrderType<T>>>, OrderBookError> {
+        // Cancellations are allowed while halted so books can be emptied
+        if !matches!(update, OrderUpdate::Cancel { .. }) {
+            self.ensure_not_halted()?;
+        }
         self.cache.invalidate();
         trace!("Order book {}: Updating order {:?}", self.symbol, update);
+        self.record_flight_event(FlightEvent::OrderUpdated {
+            update: format!("{update:?}"),
+        });
         match update {
             OrderUpdate::UpdatePrice {
                 order_id,
@@ -134,7 +161,7 @@ where
                     };
 
                     // Cancel the original order
-                    self.cancel_order(order_id)?;
+                    self.remove_order(order_id)?;
 
                     // Create a new order with the updated price
                     let mut new_order = original_order;
@@ -151,7 +178,7 @@ where
                     }
 
                     // Add the updated order
-                    let result = self.add_order(new_order)?;
+                    let result = self.insert_order(new_order)?;
                     Ok(Some(result))
                 } else {
                     Ok(None) // Order not found
@@ -193,8 +220,10 @@ where
                                     side,
                                     price: price_level.price(),
                                     quantity: price_level.visible_quantity(),
+                                    replenished: 0,
                                 })
                             }
+                            self.notify_order_change(&order, OrderChange::Modified, 0);
                             result = Some(Arc::new(self.convert_from_unit_type(&order)));
                         }
 
@@ -232,7 +261,7 @@ where
                     };
 
                     // Cancel the original order
-                    self.cancel_order(order_id)?;
+                    self.remove_order(order_id)?;
 
                     // Create a new order with the updated price and quantity
                     let mut new_order = original_order;
@@ -252,7 +281,7 @@ where
                     new_order.set_quantity(new_quantity);
 
                     // Add the updated order
-                    let result = self.add_order(new_order)?;
+                    let result = self.insert_order(new_order)?;
                     Ok(Some(result))
                 } else {
                     Ok(None) // Order not found
@@ -285,20 +314,25 @@ where
                             let result = price_level.update_order(cancel_update);
                             // notify price level changes
                             if let Some(ref listener) = self.price_level_changed_listener
-                                && let Ok(updated_order) = result
+                                && let Ok(updated_order) = &result
                                 && updated_order.is_some()
                             {
                                 listener(PriceLevelChangedEvent {
                                     side,
                                     price: price_level.price(),
                                     quantity: price_level.visible_quantity(),
+                                    replenished: 0,
                                 })
                             }
+                            if let Ok(Some(cancelled)) = &result {
+                                self.notify_order_change(cancelled, OrderChange::Deleted, 0);
+                            }
                             is_empty = price_level.order_count() == 0;
                         }
 
                         // Remove from order locations tracking
                         self.order_locations.remove(&order_id);
+                        self.extra_fields.remove(&order_id);
                     }
 
                     // If price level is empty, remove it
@@ -414,10 +448,10 @@ where
                     }
 
                     // Cancel the original order
-                    self.cancel_order(order_id)?;
+                    self.remove_order(order_id)?;
 
                     // Add the new order
-                    let result = self.add_order(new_order)?;
+                    let result = self.insert_order(new_order)?;
                     Ok(Some(result))
                 } else {
                     Ok(None) // Original order not found
@@ -430,6 +464,18 @@ where
     pub fn cancel_order(
         &self,
         order_id: OrderId,
+    ) -> Result<Option<Arc<OrderType<T>>>, OrderBookError> {
+        let result = self.remove_order(order_id);
+        if let Ok(Some(_)) = &result {
+            self.activity.order_cancelled();
+        }
+        result
+    }
+
+    /// Removes a resting order without counting a cancel in the book's activity
+    pub(super) fn remove_order(
+        &self,
+        order_id: OrderId,
     ) -> Result<Option<Arc<OrderType<T>>>, OrderBookError> {
         self.cache.invalidate();
         // First, we find the order's location (price and side) without locking
@@ -463,8 +509,12 @@ where
                             side,
                             price: price_level.price(),
                             quantity: price_level.visible_quantity(),
+                            replenished: 0,
                         })
                     }
+                    if let Some(cancelled) = &result {
+                        self.notify_order_change(cancelled, OrderChange::Deleted, 0);
+                    }
 
                     // Check if the level became empty
                     empty_level = price_level.order_count() == 0;
@@ -474,8 +524,11 @@ where
             self.cache.invalidate();
             // If we got a result and the order was canceled
             if result.is_some() {
+                self.record_flight_event(FlightEvent::OrderCancelled { order_id });
+
                 // Remove the order from the locations map
                 self.order_locations.remove(&order_id);
+                self.extra_fields.remove(&order_id);
 
                 // If the level became empty, remove it
                 if empty_level {
@@ -489,8 +542,42 @@ where
         }
     }
 
+    /// Cancel the resting orders `selected` picks by id and side, returning the
+    /// cancelled orders
+    pub fn cancel_orders_where(
+        &self,
+        selected: impl Fn(OrderId, Side) -> bool,
+    ) -> Vec<Arc<OrderType<T>>> {
+        let order_ids: Vec<OrderId> = self
+            .order_locations
+            .iter()
+            .filter(|entry| selected(*entry.key(), entry.value().1))
+            .map(|entry| *entry.key())
+            .collect();
+        order_ids
+            .into_iter()
+            .filter_map(|order_id| self.cancel_order(order_id).ok().flatten())
+            .collect()
+    }
+
     /// Add a new order to the book, automatically matching it if it's aggressive.
-    pub fn add_order(&self, mut order: OrderType<T>) -> Result<Arc<OrderType<T>>, OrderBookError> {
+    pub fn add_order(&self, order: OrderType<T>) -> Result<Arc<OrderType<T>>, OrderBookError> {
+        let pegged = matches!(order, OrderType::PeggedOrder { .. });
+        let result = self.insert_order(order);
+        if result.is_ok() {
+            self.activity.order_added();
+            if pegged {
+                self.pegged_orders_added.store(true, Ordering::Relaxed);
+            }
+        }
+        result
+    }
+
+    /// Adds an order without counting it in the book's activity
+    pub(super) fn insert_order(
+        &self,
+        mut order: OrderType<T>,
+    ) -> Result<Arc<OrderType<T>>, OrderBookError> {
         self.cache.invalidate();
 
         trace!(
@@ -500,6 +587,10 @@ where
             order.price()
         );
 
+        self.ensure_not_halted()?;
+        self.ensure_can_rest(order.is_immediate())?;
+        self.ensure_min_fill_notional(order.price(), order.total_quantity())?;
+
         if self.has_expired(&order) {
             return Err(OrderBookError::InvalidOperation {
                 message: "Order has already expired".to_string(),
@@ -532,18 +623,29 @@ where
         }
 
         self.cache.invalidate();
-        // Attempt to match the order immediately
-        let match_result = self.match_order(
-            order.id(),
-            order.side(),
-            order.total_quantity(), // Use total quantity for matching
-            Some(order.price()),
-        )?;
+        let book_context = self.trade_listener.is_some().then(|| self.book_context());
+        // Attempt to match the order immediately, unless it waits for the
+        // auction's uncross
+        let match_result = if self.is_in_auction() {
+            MatchResult::new(order.id(), order.total_quantity())
+        } else {
+            self.match_order(
+                order.id(),
+                order.side(),
+                order.total_quantity(), // Use total quantity for matching
+                Some(order.price()),
+            )?
+        };
+        self.record_tape_trades(&match_result);
 
         if !match_result.transactions.transactions.is_empty()
             && let Some(ref listener) = self.trade_listener
         {
-            let trade_result = TradeResult::new(self.symbol.clone(), match_result.clone());
+            let trade_result = TradeResult::new(self.symbol.clone(), match_result.clone())
+                .with_book_context(book_context)
+                .with_extra_fields(
+                    self.trade_extra_fields(&match_result, Some(order.extra_fields())),
+                );
             listener(&trade_result) // emit trade events to listener
         }
 
@@ -562,6 +664,17 @@ where
                 });
             }
 
+            // A remainder below the minimum fill notional could never fill, so it is dropped
+            if !self.meets_min_fill_notional(order.price(), match_result.remaining_quantity) {
+                trace!(
+                    "Order book {}: Dropping remainder {} of order {} below minimum notional",
+                    self.symbol,
+                    match_result.remaining_quantity,
+                    order.id()
+                );
+                return Ok(Arc::new(order));
+            }
+
             // Update the order with the remaining quantity
             // For iceberg orders, only update if there was actual matching (remaining < total)
             if match_result.remaining_quantity < order.total_quantity() {
@@ -580,6 +693,7 @@ where
             let level = price_level.value();
 
             // Convert to unit type for PriceLevel compatibility
+            self.extra_fields.insert(order.id(), order.extra_fields());
             let unit_order = self.convert_to_unit_type(&order);
             let unit_order_arc = price_level.value().add_order(unit_order);
             // notify price level changes
@@ -588,10 +702,18 @@ where
                     side,
                     price: level.price(),
                     quantity: level.visible_quantity(),
+                    replenished: 0,
                 })
             }
+            self.notify_order_change(&unit_order_arc, OrderChange::Added, 0);
             self.order_locations
                 .insert(unit_order_arc.id(), (price, side));
+            self.record_flight_event(FlightEvent::OrderAdded {
+                order_id: unit_order_arc.id(),
+                price,
+                quantity: unit_order_arc.visible_quantity() + unit_order_arc.hidden_quantity(),
+                side,
+            });
 
             // Convert back to generic type for return
             let generic_order = self.convert_from_unit_type(&unit_order_arc);
@@ -0,0 +1,302 @@
+//! Repricing of pegged orders as the price they track moves
+//!
+//! Pegged orders rest at the price they were entered at until the book is told
+//! to reprice them. A book with repricing configured moves each of them to its
+//! reference plus its offset whenever the reference has moved, at most once per
+//! `min_interval_ms`, so a reference flickering within the interval costs one
+//! batch of moves rather than one per change. A move cancels the order and adds
+//! it back at its new price, so it loses time priority, trades if the new price
+//! crosses, and emits level changes for both prices like any other cancel and
+//! add.
+
+use super::OrderBook;
+use pricelevel::{OrderId, OrderType, PegReferenceType, Side};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use tracing::warn;
+
+/// How a book moves its pegged orders after their reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PegRepricing {
+    /// Least time between two batches of moves
+    pub min_interval_ms: u64,
+    /// Smallest move made, so an order is not requeued for a tick of noise
+    pub min_move: u64,
+}
+
+impl Default for PegRepricing {
+    fn default() -> Self {
+        Self {
+            min_interval_ms: 0,
+            min_move: 1,
+        }
+    }
+}
+
+/// A pegged order moved after its reference
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PegMove {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub from: u64,
+    pub to: u64,
+}
+
+/// References of the last batch, to skip batches nothing has moved for
+#[derive(Debug, Default)]
+pub(super) struct PegState {
+    repriced_at: Option<u64>,
+    references: [Option<u64>; 4],
+}
+
+const REFERENCES: [PegReferenceType; 4] = [
+    PegReferenceType::BestBid,
+    PegReferenceType::BestAsk,
+    PegReferenceType::MidPrice,
+    PegReferenceType::LastTrade,
+];
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set how pegged orders follow their reference; `None` leaves them at
+    /// the price they were entered at
+    pub fn set_peg_repricing(&mut self, repricing: Option<PegRepricing>) {
+        self.peg_repricing = repricing;
+    }
+
+    /// Get how pegged orders follow their reference, if they do
+    pub fn peg_repricing(&self) -> Option<PegRepricing> {
+        self.peg_repricing
+    }
+
+    /// Moves every pegged order whose reference has moved to its new price,
+    /// once `min_interval_ms` has passed since the last batch
+    ///
+    /// Nothing moves while the book is halted or in an auction. Returns the
+    /// moves made.
+    pub fn reprice_pegged_orders(&self, now: u64) -> Vec<PegMove> {
+        self.move_pegged_orders(self.due_peg_moves(now))
+    }
+
+    /// The moves `reprice_pegged_orders` would make now, without making them
+    ///
+    /// The batch counts as made, so the next one waits out `min_interval_ms`.
+    pub fn due_peg_moves(&self, now: u64) -> Vec<PegMove> {
+        let Some(repricing) = self.peg_repricing else {
+            return Vec::new();
+        };
+        if self.is_halted() || self.is_in_auction() {
+            return Vec::new();
+        }
+        let mut state = self
+            .peg_state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state
+            .repriced_at
+            .is_some_and(|at| now < at.saturating_add(repricing.min_interval_ms))
+        {
+            return Vec::new();
+        }
+        let references = REFERENCES.map(|reference| self.peg_reference_price(reference));
+        let added = self.pegged_orders_added.swap(false, Ordering::Relaxed);
+        if !added && state.repriced_at.is_some() && references == state.references {
+            return Vec::new();
+        }
+        state.repriced_at = Some(now);
+        state.references = references;
+
+        let mut moves = Vec::new();
+        for level in self.bids.iter().chain(self.asks.iter()) {
+            for order in level.value().iter_orders() {
+                let OrderType::PeggedOrder {
+                    id,
+                    price,
+                    side,
+                    reference_price_offset,
+                    reference_price_type,
+                    ..
+                } = *order
+                else {
+                    continue;
+                };
+                let Some(to) = self
+                    .peg_reference_price(reference_price_type)
+                    .and_then(|reference| reference.checked_add_signed(reference_price_offset))
+                    .filter(|&to| to > 0)
+                else {
+                    continue;
+                };
+                if to.abs_diff(price) >= repricing.min_move.max(1) {
+                    moves.push(PegMove {
+                        order_id: id,
+                        side,
+                        from: price,
+                        to,
+                    });
+                }
+            }
+        }
+        moves
+    }
+
+    /// Makes the moves of a batch, returning those made
+    ///
+    /// A move is skipped once its order no longer rests at the price it moves
+    /// from, so a batch made again leaves the book as it was.
+    pub fn move_pegged_orders(&self, mut moves: Vec<PegMove>) -> Vec<PegMove> {
+        moves.retain(|peg_move| self.move_pegged_order(peg_move));
+        moves
+    }
+
+    /// Requeues a pegged order at its new price, putting it back where it was
+    /// if the book refuses it there
+    fn move_pegged_order(&self, peg_move: &PegMove) -> bool {
+        let rests_at_from = self
+            .get_order(peg_move.order_id)
+            .is_some_and(|order| order.price() == peg_move.from);
+        if !rests_at_from {
+            return false;
+        }
+        let Ok(Some(order)) = self.remove_order(peg_move.order_id) else {
+            return false;
+        };
+        let original = (*order).clone();
+        let mut moved = original.clone();
+        if let OrderType::PeggedOrder { price, .. } = &mut moved {
+            *price = peg_move.to;
+        }
+        match self.insert_order(moved) {
+            Ok(_) => true,
+            Err(e) => {
+                warn!(
+                    "Order book {}: pegged order {} stays at {}, it cannot move to {}: {}",
+                    self.symbol, peg_move.order_id, peg_move.from, peg_move.to, e
+                );
+                if let Err(e) = self.insert_order(original) {
+                    warn!(
+                        "Order book {}: pegged order {} was lost putting it back: {}",
+                        self.symbol, peg_move.order_id, e
+                    );
+                }
+                false
+            }
+        }
+    }
+
+    /// Best price of a side among orders that are not pegged, which pegged
+    /// orders track so they do not follow themselves
+    pub(super) fn unpegged_best(&self, side: Side) -> Option<u64> {
+        let has_unpegged = |level: &pricelevel::PriceLevel| {
+            level
+                .iter_orders()
+                .iter()
+                .any(|order| !matches!(**order, OrderType::PeggedOrder { .. }))
+        };
+        match side {
+            Side::Buy => self
+                .bids
+                .iter()
+                .rev()
+                .find(|level| has_unpegged(level.value())),
+            Side::Sell => self.asks.iter().find(|level| has_unpegged(level.value())),
+        }
+        .map(|level| *level.key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::TimeInForce;
+    use std::sync::{Arc, Mutex};
+
+    fn pegged(id: u64, price: u64, offset: i64) -> OrderType<()> {
+        OrderType::PeggedOrder {
+            id: OrderId::from_u64(id),
+            price,
+            quantity: 5,
+            side: Side::Buy,
+            timestamp: id,
+            time_in_force: TimeInForce::Gtc,
+            reference_price_offset: offset,
+            reference_price_type: PegReferenceType::BestBid,
+            extra_fields: (),
+        }
+    }
+
+    #[test]
+    fn test_pegged_orders_follow_the_best_bid_in_batches() {
+        let mut book = OrderBook::<()>::new("TEST");
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = changes.clone();
+        book.price_level_changed_listener = Some(Arc::new(move |event| {
+            seen.lock().unwrap().push((event.price, event.quantity));
+        }));
+        book.set_peg_repricing(Some(PegRepricing {
+            min_interval_ms: 100,
+            min_move: 1,
+        }));
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_order(pegged(2, 90, -1)).unwrap();
+        changes.lock().unwrap().clear();
+
+        assert_eq!(
+            book.reprice_pegged_orders(0),
+            [PegMove {
+                order_id: OrderId::from_u64(2),
+                side: Side::Buy,
+                from: 90,
+                to: 99,
+            }]
+        );
+        assert_eq!(*changes.lock().unwrap(), [(90, 0), (99, 5)]);
+
+        // The best bid moves twice within the interval; the order catches up
+        // with the last move only
+        book.add_limit_order(
+            OrderId::from_u64(3),
+            101,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        assert!(book.reprice_pegged_orders(50).is_empty());
+        book.add_limit_order(
+            OrderId::from_u64(4),
+            102,
+            10,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        let moves = book.reprice_pegged_orders(100);
+        assert_eq!((moves[0].from, moves[0].to), (99, 101));
+        // Nothing moved since, nothing to do
+        assert!(book.reprice_pegged_orders(300).is_empty());
+
+        // A pegged order does not track itself: with a zero offset it joins the
+        // best bid, and follows it down when it leaves
+        book.add_order(pegged(5, 50, 0)).unwrap();
+        book.reprice_pegged_orders(400);
+        assert_eq!(book.get_order(OrderId::from_u64(5)).unwrap().price(), 102);
+        book.cancel_order(OrderId::from_u64(4)).unwrap();
+        book.reprice_pegged_orders(500);
+        assert_eq!(book.get_order(OrderId::from_u64(5)).unwrap().price(), 101);
+        assert_eq!(book.get_order(OrderId::from_u64(2)).unwrap().price(), 100);
+    }
+}
@@ -0,0 +1,109 @@
+//! Priority tiers within a price level
+//!
+//! At a single price, displayed quantity always fills before non-displayed reserve
+//! (the hidden part of iceberg and reserve orders), regardless of when the orders
+//! arrived. Within a tier, orders fill in time priority. Reserve quantity that is
+//! refreshed into display joins the back of the displayed queue.
+
+use pricelevel::PriceLevel;
+use serde::{Deserialize, Serialize};
+
+/// Priority tier of resting quantity at a price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityTier {
+    /// Quantity visible in the book
+    Displayed,
+    /// Hidden reserve of iceberg and reserve orders
+    NonDisplayed,
+}
+
+impl PriorityTier {
+    /// Tiers in the order they are matched
+    pub const MATCHING_ORDER: [PriorityTier; 2] =
+        [PriorityTier::Displayed, PriorityTier::NonDisplayed];
+
+    /// Resting quantity of this tier at a price level
+    pub fn quantity_at(self, level: &PriceLevel) -> u64 {
+        match self {
+            PriorityTier::Displayed => level.visible_quantity(),
+            PriorityTier::NonDisplayed => level.hidden_quantity(),
+        }
+    }
+
+    /// Largest quantity an incoming order may match in this tier
+    ///
+    /// The displayed tier is capped at the displayed quantity resting before the
+    /// match. Reserve is only reached once it is refreshed into display, so the
+    /// non-displayed tier takes whatever the displayed tier left over.
+    pub(super) fn match_limit(self, level: &PriceLevel, remaining: u64) -> u64 {
+        match self {
+            PriorityTier::Displayed => remaining.min(level.visible_quantity()),
+            PriorityTier::NonDisplayed => remaining,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::OrderBook;
+    use pricelevel::{OrderId, OrderType, Side, TimeInForce};
+
+    fn rest(book: &OrderBook<()>, order: OrderType<()>) {
+        book.add_order(order).unwrap();
+    }
+
+    #[test]
+    fn test_displayed_quantity_fills_before_reserve() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        rest(
+            &book,
+            OrderType::IcebergOrder {
+                id: OrderId::from_u64(1),
+                price: 100,
+                visible_quantity: 10,
+                hidden_quantity: 90,
+                side: Side::Sell,
+                timestamp: 1,
+                time_in_force: TimeInForce::Gtc,
+                extra_fields: (),
+            },
+        );
+        rest(
+            &book,
+            OrderType::Standard {
+                id: OrderId::from_u64(2),
+                price: 100,
+                quantity: 20,
+                side: Side::Sell,
+                timestamp: 2,
+                time_in_force: TimeInForce::Gtc,
+                extra_fields: (),
+            },
+        );
+        let level = book.asks.get(&100).unwrap().value().clone();
+        assert_eq!(PriorityTier::Displayed.quantity_at(&level), 30);
+        assert_eq!(PriorityTier::NonDisplayed.quantity_at(&level), 90);
+
+        // The later displayed order fills before the iceberg's hidden reserve
+        let result = book
+            .submit_market_order(OrderId::from_u64(3), 35, Side::Buy)
+            .unwrap();
+        let fills: Vec<(OrderId, u64)> = result
+            .transactions
+            .as_vec()
+            .iter()
+            .map(|transaction| (transaction.maker_order_id, transaction.quantity))
+            .collect();
+        assert_eq!(
+            fills,
+            vec![
+                (OrderId::from_u64(1), 10),
+                (OrderId::from_u64(2), 20),
+                (OrderId::from_u64(1), 5),
+            ]
+        );
+        assert!(book.get_order(OrderId::from_u64(2)).is_none());
+    }
+}
@@ -1,8 +1,8 @@
 use super::OrderBook;
-use crate::orderbook::book_change_event::PriceLevelChangedEvent;
+use crate::orderbook::book_change_event::{OrderChange, PriceLevelChangedEvent};
 use crate::orderbook::error::OrderBookError;
 use crate::utils::current_time_millis;
-use pricelevel::{OrderType, PriceLevel, Side};
+use pricelevel::{OrderId, OrderType, PriceLevel, Side};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
@@ -14,15 +14,27 @@ where
     pub fn has_expired(&self, order: &OrderType<T>) -> bool {
         let time_in_force = order.time_in_force();
         let current_time = current_time_millis();
+        time_in_force.is_expired(current_time, self.market_close())
+    }
 
-        // Only check market close timestamp if we have one set
-        let market_close = if self.has_market_close.load(Ordering::Relaxed) {
-            Some(self.market_close_timestamp.load(Ordering::Relaxed))
-        } else {
-            None
-        };
+    /// Market close timestamp, if one is set
+    fn market_close(&self) -> Option<u64> {
+        self.has_market_close
+            .load(Ordering::Relaxed)
+            .then(|| self.market_close_timestamp.load(Ordering::Relaxed))
+    }
 
-        time_in_force.is_expired(current_time, market_close)
+    /// Resting orders expired at `now`: GTD orders past their expiry, and DAY
+    /// orders once the market close is set and has passed
+    pub fn expired_orders(&self, now: u64) -> Vec<OrderId> {
+        let market_close = self.market_close();
+        self.bids
+            .iter()
+            .chain(self.asks.iter())
+            .flat_map(|entry| entry.value().iter_orders())
+            .filter(|order| order.time_in_force().is_expired(now, market_close))
+            .map(|order| order.id())
+            .collect()
     }
 
     /// Check if there would be a price crossing
@@ -53,8 +65,9 @@ where
             .clone();
 
         // Convert OrderType<T> to OrderType<()> for compatibility with current PriceLevel API
+        self.extra_fields.insert(order_id, order.extra_fields());
         let unit_order = self.convert_to_unit_type(&*order);
-        let _added_order = price_level.add_order(unit_order);
+        let added_order = price_level.add_order(unit_order);
 
         // notify price level changes
         if let Some(ref listener) = self.price_level_changed_listener {
@@ -62,8 +75,10 @@ where
                 side,
                 price: price_level.price(),
                 quantity: price_level.visible_quantity(),
+                replenished: 0,
             })
         }
+        self.notify_order_change(&added_order, OrderChange::Added, 0);
         // The location is stored as (price, side) for efficient retrieval in cancel_order
         self.order_locations.insert(order_id, (price, side));
 
@@ -282,6 +297,27 @@ mod tests {
         assert!(book.has_expired(&order));
     }
 
+    #[test]
+    fn test_expired_orders_lists_gtd_and_day_orders_past_expiry() {
+        let book: OrderBook<()> = OrderBook::new("TEST");
+        let now = current_time_millis();
+        let gtd = OrderId::from_u64(1);
+        let day = OrderId::from_u64(2);
+        let gtc = OrderId::from_u64(3);
+        book.add_limit_order(gtd, 100, 10, Side::Buy, TimeInForce::Gtd(now + 1_000), None)
+            .unwrap();
+        book.add_limit_order(day, 101, 10, Side::Sell, TimeInForce::Day, None)
+            .unwrap();
+        book.add_limit_order(gtc, 102, 10, Side::Sell, TimeInForce::Gtc, None)
+            .unwrap();
+
+        assert!(book.expired_orders(now).is_empty());
+        // DAY orders only expire once a market close is known
+        assert_eq!(book.expired_orders(now + 1_000), [gtd]);
+        book.set_market_close_timestamp(now + 2_000);
+        assert_eq!(book.expired_orders(now + 2_000), [gtd, day]);
+    }
+
     #[test]
     fn test_will_cross_market_sell_no_bid() {
         let book: OrderBook<()> = OrderBook::new("TEST");
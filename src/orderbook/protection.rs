@@ -0,0 +1,142 @@
+//! Price protection for market orders
+//!
+//! Without protection a market order sweeps the opposite side until it is filled,
+//! which on a thin book can execute far away from the touch. A protection limit
+//! turns the market order into an immediate-or-cancel limit order priced a fixed
+//! number of ticks through the best opposite price.
+
+use super::OrderBook;
+use super::features::BookFeatures;
+use pricelevel::Side;
+use serde::{Deserialize, Serialize};
+
+/// How far through the best opposite price a market order may execute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketProtection {
+    /// Number of ticks beyond the best opposite price
+    pub ticks: u64,
+
+    /// Size of one tick (in price units)
+    pub tick_size: u64,
+}
+
+impl MarketProtection {
+    /// Worst price a market order on `side` may execute at, given the best opposite price
+    pub fn limit_price(&self, side: Side, best_opposite: u64) -> u64 {
+        let distance = self.ticks.saturating_mul(self.tick_size);
+        match side {
+            Side::Buy => best_opposite.saturating_add(distance),
+            Side::Sell => best_opposite.saturating_sub(distance),
+        }
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set the price protection applied to market orders; `None` disables it
+    pub fn set_market_protection(&mut self, protection: Option<MarketProtection>) {
+        self.market_protection = protection;
+    }
+
+    /// Get the price protection applied to market orders, if any
+    pub fn market_protection(&self) -> Option<&MarketProtection> {
+        self.market_protection.as_ref()
+    }
+
+    /// Protection price for a market order on `side`, if protection is configured
+    /// and enabled, and the opposite side has liquidity
+    pub fn market_protection_limit(&self, side: Side) -> Option<u64> {
+        if !self.features.contains(BookFeatures::MARKET_PROTECTION) {
+            return None;
+        }
+        let protection = self.market_protection.as_ref()?;
+        let best_opposite = match side {
+            Side::Buy => self.best_ask()?,
+            Side::Sell => self.best_bid()?,
+        };
+        Some(protection.limit_price(side, best_opposite))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, TimeInForce};
+
+    fn thin_book() -> OrderBook<()> {
+        let mut book = OrderBook::new("TEST");
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            5,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            101,
+            5,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(3),
+            500,
+            5,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.set_market_protection(Some(MarketProtection {
+            ticks: 2,
+            tick_size: 1,
+        }));
+        book
+    }
+
+    #[test]
+    fn test_protected_market_order_stops_at_protection_price() {
+        let book = thin_book();
+        assert_eq!(book.market_protection_limit(Side::Buy), Some(102));
+
+        let result = book
+            .submit_market_order(OrderId::from_u64(10), 15, Side::Buy)
+            .unwrap();
+
+        assert_eq!(result.executed_quantity(), 10);
+        assert_eq!(result.remaining_quantity, 5);
+        assert!(!result.is_complete);
+        assert_eq!(book.best_ask(), Some(500));
+        assert!(book.get_order(OrderId::from_u64(10)).is_none());
+    }
+
+    #[test]
+    fn test_unprotected_market_order_sweeps() {
+        let mut book = thin_book();
+        book.set_market_protection(None);
+
+        let result = book
+            .submit_market_order(OrderId::from_u64(10), 15, Side::Buy)
+            .unwrap();
+
+        assert_eq!(result.executed_quantity(), 15);
+        assert_eq!(book.best_ask(), None);
+    }
+
+    #[test]
+    fn test_sell_protection_saturates_at_zero() {
+        let protection = MarketProtection {
+            ticks: 10,
+            tick_size: 5,
+        };
+        assert_eq!(protection.limit_price(Side::Sell, 30), 0);
+        assert_eq!(protection.limit_price(Side::Sell, 100), 50);
+    }
+}
@@ -0,0 +1,398 @@
+//! Request-for-quote workflow for block-size flow
+//!
+//! A requester opens an RFQ for a side and size, dealers answer with firm quotes
+//! for at least that size until the quoting window closes, and the requester
+//! executes against the best (or a chosen) quote. RFQ executions are bilateral and
+//! never touch resting orders in the central book.
+
+use pricelevel::Side;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// An open request for quotes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RfqRequest {
+    /// Identifier of the RFQ
+    pub rfq_id: String,
+
+    /// Instrument the quotes are requested for
+    pub instrument_id: String,
+
+    /// Participant requesting quotes
+    pub requester_id: String,
+
+    /// Side of the requester (Buy = requester buys from the dealer)
+    pub side: Side,
+
+    /// Requested quantity (in units)
+    pub quantity: u64,
+
+    /// Unix timestamp in milliseconds when the quoting window closes
+    pub expires_at: u64,
+
+    /// Execute against the best quote automatically when the window closes
+    pub auto_execute: bool,
+}
+
+/// A dealer's firm quote in response to an RFQ
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RfqQuote {
+    /// Identifier of the RFQ being answered
+    pub rfq_id: String,
+
+    /// Identifier of the quote
+    pub quote_id: String,
+
+    /// Dealer providing the quote
+    pub dealer_id: String,
+
+    /// Quoted price (in price units)
+    pub price: u64,
+
+    /// Quoted quantity, at least the requested quantity (in units)
+    pub quantity: u64,
+
+    /// Unix timestamp in milliseconds when the quote was received
+    pub received_at: u64,
+}
+
+/// A completed RFQ execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RfqExecution {
+    /// Identifier of the executed RFQ
+    pub rfq_id: String,
+
+    /// Identifier of the quote executed against
+    pub quote_id: String,
+
+    /// Instrument traded
+    pub instrument_id: String,
+
+    /// Participant that requested quotes
+    pub requester_id: String,
+
+    /// Dealer whose quote was executed
+    pub dealer_id: String,
+
+    /// Side of the requester
+    pub side: Side,
+
+    /// Execution price (in price units)
+    pub price: u64,
+
+    /// Executed quantity (in units)
+    pub quantity: u64,
+
+    /// Unix timestamp in milliseconds of the execution
+    pub timestamp: u64,
+}
+
+/// Outcome of an RFQ whose quoting window closed
+#[derive(Debug, Clone, PartialEq)]
+pub enum RfqExpiry {
+    /// The RFQ was executed automatically against its best quote
+    Executed(RfqExecution),
+    /// The RFQ expired without execution
+    Expired(RfqRequest),
+}
+
+/// Errors that can occur in the RFQ workflow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RfqError {
+    /// An RFQ with this identifier is already open
+    DuplicateRfq(String),
+    /// No open RFQ with this identifier
+    UnknownRfq(String),
+    /// The RFQ's quoting window has closed
+    Expired(String),
+    /// Quote for the same RFQ with this identifier already exists
+    DuplicateQuote(String),
+    /// The quote does not cover the requested quantity
+    InsufficientQuantity {
+        /// Quantity requested in the RFQ
+        requested: u64,
+        /// Quantity offered by the quote
+        quoted: u64,
+    },
+    /// Only the requester may execute an RFQ
+    NotRequester(String),
+    /// The chosen quote does not exist for this RFQ
+    UnknownQuote(String),
+    /// No quotes were received for this RFQ
+    NoQuotes(String),
+}
+
+impl fmt::Display for RfqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RfqError::DuplicateRfq(id) => write!(f, "RFQ already open: {id}"),
+            RfqError::UnknownRfq(id) => write!(f, "RFQ not found: {id}"),
+            RfqError::Expired(id) => write!(f, "RFQ quoting window closed: {id}"),
+            RfqError::DuplicateQuote(id) => write!(f, "Quote already received: {id}"),
+            RfqError::InsufficientQuantity { requested, quoted } => {
+                write!(
+                    f,
+                    "Quote for {quoted} does not cover requested quantity {requested}"
+                )
+            }
+            RfqError::NotRequester(id) => write!(f, "Participant {id} is not the requester"),
+            RfqError::UnknownQuote(id) => write!(f, "Quote not found: {id}"),
+            RfqError::NoQuotes(id) => write!(f, "No quotes received for RFQ: {id}"),
+        }
+    }
+}
+
+impl std::error::Error for RfqError {}
+
+#[derive(Debug, Clone)]
+struct OpenRfq {
+    request: RfqRequest,
+    quotes: Vec<RfqQuote>,
+}
+
+impl OpenRfq {
+    /// Best quote for the requester: lowest price for buys, highest for sells,
+    /// earliest received on ties
+    fn best_quote(&self) -> Option<&RfqQuote> {
+        self.quotes.iter().reduce(|best, quote| {
+            let better = match self.request.side {
+                Side::Buy => quote.price < best.price,
+                Side::Sell => quote.price > best.price,
+            };
+            if better { quote } else { best }
+        })
+    }
+
+    fn execute(&self, quote: &RfqQuote, timestamp: u64) -> RfqExecution {
+        RfqExecution {
+            rfq_id: self.request.rfq_id.clone(),
+            quote_id: quote.quote_id.clone(),
+            instrument_id: self.request.instrument_id.clone(),
+            requester_id: self.request.requester_id.clone(),
+            dealer_id: quote.dealer_id.clone(),
+            side: self.request.side,
+            price: quote.price,
+            quantity: self.request.quantity,
+            timestamp,
+        }
+    }
+}
+
+/// Tracks open RFQs and their quotes
+#[derive(Debug, Default)]
+pub struct RfqManager {
+    open: HashMap<String, OpenRfq>,
+}
+
+impl RfqManager {
+    /// Creates an empty RFQ manager
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of open RFQs
+    #[must_use]
+    pub fn open_count(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Opens a new RFQ
+    pub fn open(&mut self, request: RfqRequest) -> Result<(), RfqError> {
+        if self.open.contains_key(&request.rfq_id) {
+            return Err(RfqError::DuplicateRfq(request.rfq_id));
+        }
+        self.open.insert(
+            request.rfq_id.clone(),
+            OpenRfq {
+                request,
+                quotes: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Records a dealer quote; a dealer re-quoting replaces its previous quote
+    pub fn submit_quote(&mut self, quote: RfqQuote) -> Result<(), RfqError> {
+        let rfq = self
+            .open
+            .get_mut(&quote.rfq_id)
+            .ok_or_else(|| RfqError::UnknownRfq(quote.rfq_id.clone()))?;
+        if quote.received_at >= rfq.request.expires_at {
+            return Err(RfqError::Expired(quote.rfq_id));
+        }
+        if quote.quantity < rfq.request.quantity {
+            return Err(RfqError::InsufficientQuantity {
+                requested: rfq.request.quantity,
+                quoted: quote.quantity,
+            });
+        }
+        if rfq.quotes.iter().any(|q| q.quote_id == quote.quote_id) {
+            return Err(RfqError::DuplicateQuote(quote.quote_id));
+        }
+        rfq.quotes.retain(|q| q.dealer_id != quote.dealer_id);
+        rfq.quotes.push(quote);
+        Ok(())
+    }
+
+    /// Returns the best quote received so far for an RFQ
+    #[must_use]
+    pub fn best_quote(&self, rfq_id: &str) -> Option<&RfqQuote> {
+        self.open.get(rfq_id)?.best_quote()
+    }
+
+    /// Executes an RFQ against a chosen quote, or the best quote if none is given
+    ///
+    /// The RFQ is closed on success. Executions are accepted until the quoting
+    /// window closes.
+    pub fn execute(
+        &mut self,
+        rfq_id: &str,
+        requester_id: &str,
+        quote_id: Option<&str>,
+        now: u64,
+    ) -> Result<RfqExecution, RfqError> {
+        let rfq = self
+            .open
+            .get(rfq_id)
+            .ok_or_else(|| RfqError::UnknownRfq(rfq_id.to_string()))?;
+        if rfq.request.requester_id != requester_id {
+            return Err(RfqError::NotRequester(requester_id.to_string()));
+        }
+        if now >= rfq.request.expires_at {
+            return Err(RfqError::Expired(rfq_id.to_string()));
+        }
+        let quote = match quote_id {
+            Some(quote_id) => rfq
+                .quotes
+                .iter()
+                .find(|q| q.quote_id == quote_id)
+                .ok_or_else(|| RfqError::UnknownQuote(quote_id.to_string()))?,
+            None => rfq
+                .best_quote()
+                .ok_or_else(|| RfqError::NoQuotes(rfq_id.to_string()))?,
+        };
+        let execution = rfq.execute(quote, now);
+        self.open.remove(rfq_id);
+        Ok(execution)
+    }
+
+    /// Closes every RFQ whose quoting window has ended
+    ///
+    /// RFQs flagged for auto-execution are executed against their best quote.
+    pub fn expire(&mut self, now: u64) -> Vec<RfqExpiry> {
+        let expired: Vec<String> = self
+            .open
+            .iter()
+            .filter(|(_, rfq)| now >= rfq.request.expires_at)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|id| self.open.remove(&id))
+            .map(|rfq| match rfq.best_quote() {
+                Some(quote) if rfq.request.auto_execute => {
+                    RfqExpiry::Executed(rfq.execute(quote, now))
+                }
+                _ => RfqExpiry::Expired(rfq.request),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(side: Side, auto_execute: bool) -> RfqRequest {
+        RfqRequest {
+            rfq_id: "rfq-1".to_string(),
+            instrument_id: "BTC/USD".to_string(),
+            requester_id: "client".to_string(),
+            side,
+            quantity: 100,
+            expires_at: 1_000,
+            auto_execute,
+        }
+    }
+
+    fn quote(quote_id: &str, dealer_id: &str, price: u64) -> RfqQuote {
+        RfqQuote {
+            rfq_id: "rfq-1".to_string(),
+            quote_id: quote_id.to_string(),
+            dealer_id: dealer_id.to_string(),
+            price,
+            quantity: 100,
+            received_at: 10,
+        }
+    }
+
+    #[test]
+    fn test_rfq_execute_best_quote() {
+        let mut manager = RfqManager::new();
+        manager.open(request(Side::Buy, false)).unwrap();
+        manager.submit_quote(quote("q1", "dealer-a", 105)).unwrap();
+        manager.submit_quote(quote("q2", "dealer-b", 103)).unwrap();
+        manager.submit_quote(quote("q3", "dealer-c", 103)).unwrap();
+
+        assert_eq!(
+            manager.execute("rfq-1", "dealer-a", None, 500),
+            Err(RfqError::NotRequester("dealer-a".to_string()))
+        );
+
+        let execution = manager.execute("rfq-1", "client", None, 500).unwrap();
+        assert_eq!(execution.quote_id, "q2");
+        assert_eq!(execution.price, 103);
+        assert_eq!(execution.quantity, 100);
+        assert_eq!(manager.open_count(), 0);
+    }
+
+    #[test]
+    fn test_rfq_quote_validation() {
+        let mut manager = RfqManager::new();
+        manager.open(request(Side::Sell, false)).unwrap();
+
+        let mut small = quote("q1", "dealer-a", 100);
+        small.quantity = 50;
+        assert!(matches!(
+            manager.submit_quote(small),
+            Err(RfqError::InsufficientQuantity { .. })
+        ));
+
+        let mut late = quote("q2", "dealer-a", 100);
+        late.received_at = 1_000;
+        assert!(matches!(
+            manager.submit_quote(late),
+            Err(RfqError::Expired(_))
+        ));
+
+        // A dealer re-quoting replaces its previous quote
+        manager.submit_quote(quote("q3", "dealer-a", 99)).unwrap();
+        manager.submit_quote(quote("q4", "dealer-a", 97)).unwrap();
+        assert_eq!(manager.best_quote("rfq-1").unwrap().quote_id, "q4");
+    }
+
+    #[test]
+    fn test_rfq_expiry() {
+        let mut manager = RfqManager::new();
+        manager.open(request(Side::Sell, true)).unwrap();
+        manager.submit_quote(quote("q1", "dealer-a", 99)).unwrap();
+        manager.submit_quote(quote("q2", "dealer-b", 101)).unwrap();
+        assert!(manager.expire(999).is_empty());
+
+        match manager.expire(1_000).as_slice() {
+            [RfqExpiry::Executed(execution)] => assert_eq!(execution.price, 101),
+            other => panic!("unexpected expiry result: {other:?}"),
+        }
+
+        let mut no_auto = request(Side::Buy, false);
+        no_auto.rfq_id = "rfq-2".to_string();
+        manager.open(no_auto).unwrap();
+        assert!(matches!(
+            manager.expire(2_000).as_slice(),
+            [RfqExpiry::Expired(_)]
+        ));
+    }
+}
@@ -0,0 +1,213 @@
+//! Decimal rendering of prices and quantities for outbound messages
+//!
+//! Prices and quantities are integers internally, in units of the instrument's
+//! smallest increment. Outbound trades and depth can render them as decimal strings
+//! using the instrument's scale, so consumers don't need to know the scaling, or
+//! keep the raw integers.
+
+use super::OrderBook;
+use super::snapshot::OrderBookSnapshot;
+use pricelevel::PriceLevelSnapshot;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+/// Largest number of decimal places that can be rendered from a `u64`
+const MAX_DECIMALS: u32 = 19;
+
+/// Decimal places of an instrument's prices and quantities
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentScale {
+    /// A raw price of 12345 with 2 price decimals is 123.45
+    #[serde(default)]
+    pub price_decimals: u32,
+
+    /// A raw quantity of 1500 with 3 quantity decimals is 1.500
+    #[serde(default)]
+    pub quantity_decimals: u32,
+}
+
+/// How prices and quantities are written in outbound messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberFormat {
+    /// Raw integers, as held by the engine
+    #[default]
+    Raw,
+    /// Decimal strings using the instrument scale
+    Decimal,
+}
+
+/// A price or quantity serialized according to a [`NumberFormat`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaledValue {
+    value: u64,
+    decimals: Option<u32>,
+}
+
+impl ScaledValue {
+    /// The raw integer value
+    pub fn raw(&self) -> u64 {
+        self.value
+    }
+}
+
+impl Serialize for ScaledValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.decimals {
+            Some(decimals) => serializer.serialize_str(&format_decimal(self.value, decimals)),
+            None => serializer.serialize_u64(self.value),
+        }
+    }
+}
+
+impl fmt::Display for ScaledValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.decimals {
+            Some(decimals) => f.write_str(&format_decimal(self.value, decimals)),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+/// Renders a raw integer with `decimals` implied decimal places, e.g. 12345 with 2
+/// decimals is "123.45" and 5 with 3 decimals is "0.005"
+pub fn format_decimal(value: u64, decimals: u32) -> String {
+    let decimals = decimals.min(MAX_DECIMALS) as usize;
+    if decimals == 0 {
+        return value.to_string();
+    }
+    let digits = format!("{:0>width$}", value, width = decimals + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    format!("{integer}.{fraction}")
+}
+
+impl InstrumentScale {
+    /// Wraps a raw price for serialization in `format`
+    pub fn price(&self, value: u64, format: NumberFormat) -> ScaledValue {
+        Self::scaled(value, self.price_decimals, format)
+    }
+
+    /// Wraps a raw quantity for serialization in `format`
+    pub fn quantity(&self, value: u64, format: NumberFormat) -> ScaledValue {
+        Self::scaled(value, self.quantity_decimals, format)
+    }
+
+    fn scaled(value: u64, decimals: u32, format: NumberFormat) -> ScaledValue {
+        let decimals = match format {
+            NumberFormat::Raw => None,
+            NumberFormat::Decimal => Some(decimals),
+        };
+        ScaledValue { value, decimals }
+    }
+}
+
+/// One price level of outbound depth
+#[derive(Debug, Clone, Serialize)]
+pub struct ScaledLevel {
+    pub price: ScaledValue,
+    /// Displayed quantity only; hidden reserve is never published
+    pub quantity: ScaledValue,
+    pub order_count: usize,
+}
+
+/// Outbound depth of a book, rendered from a snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct ScaledDepth {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub bids: Vec<ScaledLevel>,
+    pub asks: Vec<ScaledLevel>,
+}
+
+impl ScaledDepth {
+    /// Renders a snapshot's levels with the given scale and format
+    pub fn from_snapshot(
+        snapshot: &OrderBookSnapshot,
+        scale: InstrumentScale,
+        format: NumberFormat,
+    ) -> Self {
+        let level = |level: &PriceLevelSnapshot| ScaledLevel {
+            price: scale.price(level.price, format),
+            quantity: scale.quantity(level.visible_quantity, format),
+            order_count: level.order_count,
+        };
+        Self {
+            symbol: snapshot.symbol.clone(),
+            timestamp: snapshot.timestamp,
+            bids: snapshot.bids.iter().map(level).collect(),
+            asks: snapshot.asks.iter().map(level).collect(),
+        }
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set the decimal places used to render this book's prices and quantities
+    pub fn set_scale(&mut self, scale: InstrumentScale) {
+        self.scale = scale;
+    }
+
+    /// Get the decimal places used to render this book's prices and quantities
+    pub fn scale(&self) -> InstrumentScale {
+        self.scale
+    }
+
+    /// Creates outbound depth of up to `depth` levels per side
+    pub fn scaled_depth(&self, depth: usize, format: NumberFormat) -> ScaledDepth {
+        ScaledDepth::from_snapshot(&self.create_snapshot(depth), self.scale, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    #[test]
+    fn test_format_decimal() {
+        assert_eq!(format_decimal(12345, 2), "123.45");
+        assert_eq!(format_decimal(5, 3), "0.005");
+        assert_eq!(format_decimal(100, 0), "100");
+        assert_eq!(format_decimal(0, 2), "0.00");
+        assert_eq!(format_decimal(u64::MAX, 40), "1.8446744073709551615");
+    }
+
+    #[test]
+    fn test_scaled_values_serialize_by_format() {
+        let scale = InstrumentScale {
+            price_decimals: 2,
+            quantity_decimals: 1,
+        };
+        let raw = serde_json::to_string(&scale.price(12345, NumberFormat::Raw)).unwrap();
+        let decimal = serde_json::to_string(&scale.price(12345, NumberFormat::Decimal)).unwrap();
+        assert_eq!(raw, "12345");
+        assert_eq!(decimal, "\"123.45\"");
+        let quantity = serde_json::to_string(&scale.quantity(15, NumberFormat::Decimal)).unwrap();
+        assert_eq!(quantity, "\"1.5\"");
+    }
+
+    #[test]
+    fn test_scaled_depth_uses_book_scale() {
+        let mut book: OrderBook<()> = OrderBook::new("TEST");
+        book.set_scale(InstrumentScale {
+            price_decimals: 2,
+            quantity_decimals: 0,
+        });
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            10050,
+            7,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        let depth = serde_json::to_value(book.scaled_depth(10, NumberFormat::Decimal)).unwrap();
+        assert_eq!(depth["bids"][0]["price"], "100.50");
+        assert_eq!(depth["bids"][0]["quantity"], "7");
+        assert!(depth["asks"].as_array().unwrap().is_empty());
+    }
+}
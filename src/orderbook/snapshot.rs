@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tracing::trace;
 
+use super::activity::ActivityStats;
 use super::error::OrderBookError;
+use super::extra_fields::OrderExtraFields;
 
 /// A snapshot of the order book state at a specific point in time
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +24,11 @@ pub struct OrderBookSnapshot {
 
     /// Snapshot of ask price levels
     pub asks: Vec<PriceLevelSnapshot>,
+
+    /// Extra fields of the orders in the levels, in book order, for books that
+    /// serialize them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_fields: Vec<OrderExtraFields>,
 }
 
 impl OrderBookSnapshot {
@@ -36,6 +43,32 @@ impl OrderBookSnapshot {
         }
     }
 
+    /// Hex-encoded hash of the levels and resting orders, in the order they appear
+    ///
+    /// Unlike the package checksum it leaves out the snapshot and order
+    /// timestamps, so two books built from the same commands hash the same even
+    /// when they were built at different times.
+    pub fn state_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (side, levels) in [(b'B', &self.bids), (b'A', &self.asks)] {
+            hasher.update([side]);
+            for level in levels {
+                hasher.update(level.price.to_le_bytes());
+                hasher.update(level.visible_quantity.to_le_bytes());
+                hasher.update(level.hidden_quantity.to_le_bytes());
+                hasher.update((level.orders.len() as u64).to_le_bytes());
+                for order in &level.orders {
+                    hasher.update(order.id().to_string());
+                    hasher.update(order.price().to_le_bytes());
+                    hasher.update(order.visible_quantity().to_le_bytes());
+                    hasher.update(order.hidden_quantity().to_le_bytes());
+                    hasher.update(format!("{:?}", order.time_in_force()));
+                }
+            }
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
     /// Get the best bid price and quantity
     pub fn best_bid(&self) -> Option<(u64, u64)> {
         let bids = self
@@ -305,6 +338,11 @@ pub struct EnrichedSnapshot {
 
     /// VWAP for top N ask levels
     pub vwap_ask: Option<f64>,
+
+    /// Processing statistics of the book when the snapshot was taken; all zero
+    /// when the snapshot was not taken from a book
+    #[serde(default)]
+    pub activity: ActivityStats,
 }
 
 impl EnrichedSnapshot {
@@ -408,6 +446,7 @@ impl EnrichedSnapshot {
             order_book_imbalance,
             vwap_bid,
             vwap_ask,
+            activity: ActivityStats::default(),
         }
     }
 
@@ -0,0 +1,157 @@
+//! Caps on how far a single aggressive order may sweep
+//!
+//! An order crossing many levels executes them all within one command, so one
+//! giant order can hold up everything queued behind it on the shard. A sweep
+//! limit bounds the levels or the quantity an order may take in one go; what
+//! lies beyond the cap is cancelled, or entered again as an order of its own
+//! after the commands already waiting.
+
+use super::OrderBook;
+use pricelevel::Side;
+use serde::{Deserialize, Serialize};
+
+/// What becomes of the part of an order beyond its sweep limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepRemainder {
+    /// Cancelled, as for an IOC order
+    #[default]
+    Cancel,
+    /// Entered again behind the commands already queued, where it may sweep
+    /// another batch or rest
+    Requeue,
+}
+
+/// How much a single order may execute in one sweep
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SweepLimit {
+    /// Most opposite price levels taken from
+    pub max_levels: Option<usize>,
+    /// Most quantity executed
+    pub max_quantity: Option<u64>,
+    pub remainder: SweepRemainder,
+}
+
+impl SweepLimit {
+    /// The tighter of two limits on each bound, handling the remainder as
+    /// `limit` does when both are given
+    pub fn tighter(limit: Option<Self>, other: Option<Self>) -> Option<Self> {
+        match (limit, other) {
+            (Some(limit), Some(other)) => Some(Self {
+                max_levels: tighter_bound(limit.max_levels, other.max_levels),
+                max_quantity: tighter_bound(limit.max_quantity, other.max_quantity),
+                remainder: limit.remainder,
+            }),
+            (limit, other) => limit.or(other),
+        }
+    }
+}
+
+fn tighter_bound<B: Ord>(bound: Option<B>, other: Option<B>) -> Option<B> {
+    match (bound, other) {
+        (Some(bound), Some(other)) => Some(bound.min(other)),
+        (bound, other) => bound.or(other),
+    }
+}
+
+impl<T> OrderBook<T>
+where
+    T: Clone + Send + Sync + Default + 'static,
+{
+    /// Set how far a single order may sweep this book; `None` leaves it
+    /// unbounded
+    pub fn set_sweep_limit(&mut self, limit: Option<SweepLimit>) {
+        self.sweep_limit = limit;
+    }
+
+    /// Get how far a single order may sweep this book, if bounded
+    pub fn sweep_limit(&self) -> Option<SweepLimit> {
+        self.sweep_limit
+    }
+
+    /// Quantity an order on `side` for `quantity` may execute in one sweep,
+    /// within `limit_price` and `limit`
+    ///
+    /// Bounds of zero count as one, so an order always makes progress.
+    pub fn sweep_allowance(
+        &self,
+        side: Side,
+        quantity: u64,
+        limit_price: Option<u64>,
+        limit: &SweepLimit,
+    ) -> u64 {
+        let quantity = limit
+            .max_quantity
+            .map_or(quantity, |max| quantity.min(max.max(1)));
+        // Price of the last level the order may reach
+        let last_level = limit.max_levels.and_then(|levels| {
+            let skipped = levels.max(1) - 1;
+            match side {
+                Side::Buy => self.asks.iter().nth(skipped),
+                Side::Sell => self.bids.iter().rev().nth(skipped),
+            }
+            .map(|level| *level.key())
+        });
+        let limit_price = match (limit_price, last_level) {
+            (Some(limit_price), Some(last_level)) => Some(match side {
+                Side::Buy => limit_price.min(last_level),
+                Side::Sell => limit_price.max(last_level),
+            }),
+            (limit_price, last_level) => limit_price.or(last_level),
+        };
+        self.peek_match(side, quantity, limit_price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, TimeInForce};
+
+    #[test]
+    fn test_sweep_allowance_stops_at_the_tighter_bound() {
+        let mut book = OrderBook::<()>::new("TEST");
+        for (id, price) in [(1, 100), (2, 101), (3, 102)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                5,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        let levels = SweepLimit {
+            max_levels: Some(2),
+            ..SweepLimit::default()
+        };
+        assert_eq!(book.sweep_allowance(Side::Buy, 20, None, &levels), 10);
+        assert_eq!(book.sweep_allowance(Side::Buy, 20, Some(100), &levels), 5);
+        let quantity = SweepLimit {
+            max_quantity: Some(7),
+            ..SweepLimit::default()
+        };
+        assert_eq!(book.sweep_allowance(Side::Buy, 20, None, &quantity), 7);
+
+        // The instrument's limit tightens the command's without changing how
+        // its remainder is handled
+        book.set_sweep_limit(Some(SweepLimit {
+            max_levels: Some(1),
+            max_quantity: Some(100),
+            remainder: SweepRemainder::Requeue,
+        }));
+        let limit = SweepLimit::tighter(Some(quantity), book.sweep_limit()).unwrap();
+        assert_eq!(
+            limit,
+            SweepLimit {
+                max_levels: Some(1),
+                max_quantity: Some(7),
+                remainder: SweepRemainder::Cancel,
+            }
+        );
+        assert_eq!(book.sweep_allowance(Side::Buy, 20, None, &limit), 5);
+        assert_eq!(SweepLimit::tighter(None, None), None);
+    }
+}
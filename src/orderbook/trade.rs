@@ -3,9 +3,29 @@
    Email: jb@taunais.com
    Date: 2/10/25
 ******************************************************************************/
+pub mod tape;
+
+use super::extra_fields::OrderExtraFields;
 use pricelevel::MatchResult;
+use serde::Serialize;
 use std::sync::Arc;
 
+/// The touch of a book just before an order matched against it
+///
+/// Lets fills be compared with the prices available when the order arrived,
+/// e.g. to measure price improvement, without joining a separate market data feed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BookContext {
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+    /// Best ask minus best bid, when both sides are quoted
+    pub spread: Option<u64>,
+    /// Quantity resting at the best bid, hidden quantity included
+    pub bid_depth_at_touch: u64,
+    /// Quantity resting at the best ask, hidden quantity included
+    pub ask_depth_at_touch: u64,
+}
+
 /// Enhanced trade result that includes symbol information
 #[derive(Debug, Clone)]
 pub struct TradeResult {
@@ -13,6 +33,10 @@ pub struct TradeResult {
     pub symbol: String,
     /// The underlying match result from the pricelevel crate
     pub match_result: MatchResult,
+    /// The book's touch before matching, when captured
+    pub book_context: Option<BookContext>,
+    /// Extra fields of the taker and makers, for books that serialize them
+    pub extra_fields: Vec<OrderExtraFields>,
 }
 
 impl TradeResult {
@@ -21,8 +45,22 @@ impl TradeResult {
         Self {
             symbol,
             match_result,
+            book_context: None,
+            extra_fields: Vec::new(),
         }
     }
+
+    /// Attaches the state of the book's touch before matching
+    pub fn with_book_context(mut self, book_context: Option<BookContext>) -> Self {
+        self.book_context = book_context;
+        self
+    }
+
+    /// Attaches the extra fields of the orders involved
+    pub fn with_extra_fields(mut self, extra_fields: Vec<OrderExtraFields>) -> Self {
+        self.extra_fields = extra_fields;
+        self
+    }
 }
 
 /// Trade listener specification using Arc for shared ownership
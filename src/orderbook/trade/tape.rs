@@ -0,0 +1,187 @@
+//! Per-book tape of recent trades.
+//!
+//! The tape keeps the last N trades of a book in a bounded ring buffer, so the
+//! latest trade and the trades since a sequence number or a time can be served
+//! from the engine instead of consumers having to tail the trade topic.
+//! Keeping a tape is optional and disabled by default.
+
+use pricelevel::{MatchResult, OrderId, Side};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use uuid::Uuid;
+
+/// A trade held on the tape
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TapeTrade {
+    /// Monotonic sequence number assigned by the tape, starting at 1
+    pub sequence: u64,
+    pub trade_id: Uuid,
+    pub price: u64,
+    pub quantity: u64,
+    /// Side of the taker
+    pub aggressor_side: Side,
+    pub taker_order_id: OrderId,
+    pub maker_order_id: OrderId,
+    /// Unix timestamp in milliseconds of the execution
+    pub timestamp: u64,
+}
+
+/// Bounded ring buffer of the recent trades of a book
+#[derive(Debug)]
+pub struct TradeTape {
+    capacity: usize,
+    last_sequence: AtomicU64,
+    trades: Mutex<VecDeque<TapeTrade>>,
+}
+
+impl TradeTape {
+    /// Creates a tape keeping at most `capacity` trades (minimum 1)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            last_sequence: AtomicU64::new(0),
+            trades: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Maximum number of trades kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Records every fill of a match, evicting the oldest trades when full
+    pub fn record(&self, match_result: &MatchResult) {
+        let mut trades = self.trades.lock().unwrap_or_else(|e| e.into_inner());
+        for transaction in match_result.transactions.as_vec() {
+            if trades.len() == self.capacity {
+                trades.pop_front();
+            }
+            trades.push_back(TapeTrade {
+                sequence: self.last_sequence.fetch_add(1, Ordering::Relaxed) + 1,
+                trade_id: transaction.transaction_id,
+                price: transaction.price,
+                quantity: transaction.quantity,
+                aggressor_side: transaction.taker_side,
+                taker_order_id: transaction.taker_order_id,
+                maker_order_id: transaction.maker_order_id,
+                timestamp: transaction.timestamp,
+            });
+        }
+    }
+
+    /// The most recent trade, if any is held
+    pub fn last(&self) -> Option<TapeTrade> {
+        self.trades
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .back()
+            .cloned()
+    }
+
+    /// The trade held with the given id, searched from the most recent
+    pub fn find(&self, trade_id: Uuid) -> Option<TapeTrade> {
+        self.trades
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .rev()
+            .find(|trade| trade.trade_id == trade_id)
+            .cloned()
+    }
+
+    /// Sequence number of the most recent trade ever recorded, 0 before the first
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence.load(Ordering::Relaxed)
+    }
+
+    /// Trades held with a sequence number after `sequence`, oldest first
+    ///
+    /// Trades evicted since are missing; a gap between `sequence` and the first
+    /// trade returned tells the caller it fell behind the tape.
+    pub fn since_sequence(&self, sequence: u64) -> Vec<TapeTrade> {
+        let trades = self.trades.lock().unwrap_or_else(|e| e.into_inner());
+        let start = trades.partition_point(|trade| trade.sequence <= sequence);
+        trades.range(start..).cloned().collect()
+    }
+
+    /// Trades held executed at or after `timestamp`, oldest first
+    pub fn since_timestamp(&self, timestamp: u64) -> Vec<TapeTrade> {
+        let trades = self.trades.lock().unwrap_or_else(|e| e.into_inner());
+        trades
+            .iter()
+            .filter(|trade| trade.timestamp >= timestamp)
+            .cloned()
+            .collect()
+    }
+
+    /// Up to `count` of the most recent trades, oldest first
+    pub fn recent(&self, count: usize) -> Vec<TapeTrade> {
+        let trades = self.trades.lock().unwrap_or_else(|e| e.into_inner());
+        trades
+            .range(trades.len().saturating_sub(count)..)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of trades currently held
+    pub fn len(&self) -> usize {
+        self.trades.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns true if no trades are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::OrderBook;
+    use pricelevel::TimeInForce;
+
+    #[test]
+    fn test_trade_tape_keeps_the_latest_trades() {
+        let mut book: OrderBook<()> = OrderBook::new("TEST");
+        book.enable_trade_tape(3);
+        for (id, price) in [(1, 100), (2, 101), (3, 102), (4, 103)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                1,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        // Four fills, the first of which is evicted
+        book.match_market_order(OrderId::from_u64(5), 3, Side::Buy)
+            .unwrap();
+        book.match_market_order(OrderId::from_u64(6), 1, Side::Buy)
+            .unwrap();
+        let tape = book.trade_tape().unwrap();
+        assert_eq!(tape.len(), 3);
+        assert_eq!(tape.last_sequence(), 4);
+        let last = tape.last().unwrap();
+        assert_eq!(
+            (last.sequence, last.price, last.taker_order_id),
+            (4, 103, OrderId::from_u64(6))
+        );
+        assert_eq!(last.aggressor_side, Side::Buy);
+
+        let since: Vec<u64> = tape
+            .since_sequence(2)
+            .iter()
+            .map(|trade| trade.sequence)
+            .collect();
+        assert_eq!(since, [3, 4]);
+        assert_eq!(tape.since_sequence(0)[0].sequence, 2);
+        assert_eq!(tape.recent(1), std::slice::from_ref(&last));
+        assert_eq!(tape.since_timestamp(last.timestamp + 1), []);
+        assert_eq!(tape.since_timestamp(0).len(), 3);
+    }
+}
@@ -0,0 +1,178 @@
+// src/partitioning.rs
+use crate::config::partitioning::{MessageKey, Partitioner, PartitioningConfig};
+use crate::publisher::OutboundMessage;
+use std::collections::HashMap;
+
+/// Where a message goes among the partitions of its topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Wherever librdkafka's partitioner puts its key
+    ByKey,
+    /// The partition of a hash of its key
+    Hash(u32),
+    /// The partition after the one of the topic's previous message, counting
+    /// messages from zero
+    Next(u64),
+}
+
+impl Placement {
+    /// Partition of the message among `partitions`, unless left to librdkafka
+    pub fn partition(self, partitions: i32) -> Option<i32> {
+        let partitions = u64::try_from(partitions).ok().filter(|&n| n > 0)?;
+        let slot = match self {
+            Placement::ByKey => return None,
+            Placement::Hash(hash) => u64::from(hash) % partitions,
+            Placement::Next(sequence) => sequence % partitions,
+        };
+        i32::try_from(slot).ok()
+    }
+}
+
+/// Keys and places outbound messages per `PartitioningConfig`
+pub struct OutboundRouter {
+    config: PartitioningConfig,
+    /// Messages placed in turn on each round-robin topic
+    sequences: HashMap<String, u64>,
+}
+
+impl OutboundRouter {
+    pub fn new(config: PartitioningConfig) -> Self {
+        Self {
+            config,
+            sequences: HashMap::new(),
+        }
+    }
+
+    /// Key a message is produced under and where it goes
+    pub fn route(&mut self, message: &OutboundMessage) -> (String, Placement) {
+        let partitioning = self.config.for_topic(&message.topic);
+        let field = match partitioning.key {
+            MessageKey::Published => None,
+            MessageKey::Instrument => Some("instrument_id"),
+            MessageKey::Participant => Some("participant_id"),
+            MessageKey::RoundRobin => {
+                let sequence = self.sequences.entry(message.topic.clone()).or_default();
+                let placement = Placement::Next(*sequence);
+                *sequence += 1;
+                return (message.key.clone(), placement);
+            }
+        };
+        let key = field
+            .and_then(|field| payload_field(&message.payload, field))
+            .unwrap_or_else(|| message.key.clone());
+        let placement = match partitioning.partitioner {
+            None => Placement::ByKey,
+            Some(Partitioner::Murmur2) => Placement::Hash(murmur2(key.as_bytes()) & 0x7fff_ffff),
+            Some(Partitioner::Fnv1a) => Placement::Hash(fnv1a(key.as_bytes())),
+        };
+        (key, placement)
+    }
+}
+
+/// A top-level string field of a JSON message
+fn payload_field(payload: &str, field: &str) -> Option<String> {
+    match serde_json::from_str::<serde_json::Value>(payload)
+        .ok()?
+        .get(field)?
+    {
+        serde_json::Value::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// The murmur2 hash of the Java client's default partitioner
+fn murmur2(data: &[u8]) -> u32 {
+    const SEED: u32 = 0x9747_b28c;
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+    let mut hash = SEED ^ data.len() as u32;
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        hash = hash.wrapping_mul(M) ^ k;
+    }
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            hash ^= u32::from(*byte) << (8 * i);
+        }
+        hash = hash.wrapping_mul(M);
+    }
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(M);
+    hash ^ (hash >> 15)
+}
+
+fn fnv1a(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::partitioning::TopicPartitioning;
+
+    fn message(topic: &str, payload: &str) -> OutboundMessage {
+        OutboundMessage {
+            topic: topic.to_string(),
+            key: "BTC".to_string(),
+            payload: payload.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_messages_are_keyed_and_placed_per_topic() {
+        // Values of the Java client's own tests
+        for (key, hash) in [
+            ("21", -973_932_308),
+            ("foobar", -790_332_482),
+            ("a-little-bit-long-string", -985_981_536),
+            ("a-little-bit-longer-string", -1_486_304_829),
+            ("abc", 479_470_107),
+        ] {
+            assert_eq!(murmur2(key.as_bytes()) as i32, hash, "{key}");
+        }
+        assert_eq!(fnv1a(b"foobar"), 0xbf9c_f968);
+
+        let mut config = PartitioningConfig::default();
+        config.topics.insert(
+            "order.acks".to_string(),
+            TopicPartitioning {
+                key: MessageKey::Participant,
+                partitioner: Some(Partitioner::Murmur2),
+            },
+        );
+        config.topics.insert(
+            "md.trades".to_string(),
+            TopicPartitioning {
+                key: MessageKey::RoundRobin,
+                partitioner: None,
+            },
+        );
+        let mut router = OutboundRouter::new(config);
+
+        let ack = message("order.acks", r#"{"participant_id":"21","order_id":7}"#);
+        let (key, placement) = router.route(&ack);
+        assert_eq!(key, "21");
+        // -973932308 & 0x7fffffff is 1173551340
+        assert_eq!(placement.partition(12), Some(0));
+        // Without the field, the published key is kept
+        let (key, _) = router.route(&message("order.acks", r#"{"order_id":7}"#));
+        assert_eq!(key, "BTC");
+
+        let partitions: Vec<_> = (0..4)
+            .map(|_| router.route(&message("md.trades", "{}")).1.partition(3))
+            .collect();
+        assert_eq!(partitions, [Some(0), Some(1), Some(2), Some(0)]);
+        assert_eq!(
+            router.route(&message("md.depth", "{}")),
+            ("BTC".to_string(), Placement::ByKey)
+        );
+        assert_eq!(Placement::Next(1).partition(0), None);
+    }
+}
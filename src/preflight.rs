@@ -0,0 +1,560 @@
+// src/preflight.rs
+use crate::config::kafka::KafkaConfig;
+use crate::config::preflight::PreflightConfig;
+use crate::config::topics::TopicMap;
+use crate::engine::EngineConfig;
+use rdkafka::ClientConfig;
+use rdkafka::admin::{AdminClient, AdminOptions, ResourceSpecifier};
+use rdkafka::client::DefaultClientContext;
+use rdkafka::error::RDKafkaErrorCode;
+use rdkafka::metadata::Metadata;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Rough size of one serialized depth level, used to estimate snapshot messages
+const LEVEL_BYTES_ESTIMATE: usize = 96;
+/// Rough size of a snapshot message without its levels
+const SNAPSHOT_ENVELOPE_BYTES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicRole {
+    Consume,
+    Produce,
+}
+
+/// A topic the engine uses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicRequirement {
+    pub role: TopicRole,
+    /// Estimated largest message published to the topic, when it carries snapshots
+    pub largest_message_bytes: Option<usize>,
+}
+
+/// How a topic looked in the cluster metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopicStatus {
+    Ok,
+    Missing,
+    NotAuthorized,
+    Error(String),
+}
+
+/// What the cluster reported about one topic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicView {
+    pub status: TopicStatus,
+    pub partitions: usize,
+    /// Partitions without a leader, which can neither be produced to nor consumed
+    pub leaderless: Vec<i32>,
+    /// The topic's `max.message.bytes`, or why it could not be read
+    pub max_message_bytes: Result<usize, String>,
+}
+
+/// What the cluster reported about the topics the engine uses
+#[derive(Debug, Clone, Default)]
+pub struct ClusterView {
+    pub brokers: usize,
+    pub topics: HashMap<String, TopicView>,
+}
+
+/// A problem found by the preflight checks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreflightIssue {
+    /// The cluster did not answer the metadata request
+    Unreachable(String),
+    NoBrokers,
+    Missing(String),
+    /// The principal may not describe the topic, so it cannot use it either
+    NotAuthorized(String),
+    TopicError {
+        topic: String,
+        error: String,
+    },
+    TooFewPartitions {
+        topic: String,
+        partitions: usize,
+        required: usize,
+    },
+    Leaderless {
+        topic: String,
+        partitions: Vec<i32>,
+    },
+    /// Messages (or chunks) the producer sends would be rejected by the topic
+    MessageTooLarge {
+        topic: String,
+        producer_max_bytes: usize,
+        topic_max_bytes: usize,
+    },
+    /// The topic's config could not be read, e.g. without `DescribeConfigs` access
+    ConfigUnreadable {
+        topic: String,
+        error: String,
+    },
+    /// Snapshots on the topic exceed the message size and will be sent as chunks
+    SnapshotChunked {
+        topic: String,
+        estimated_bytes: usize,
+        max_bytes: usize,
+    },
+}
+
+impl PreflightIssue {
+    /// Whether the engine cannot run correctly with this issue
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            PreflightIssue::ConfigUnreadable { .. } | PreflightIssue::SnapshotChunked { .. }
+        )
+    }
+}
+
+impl fmt::Display for PreflightIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreflightIssue::Unreachable(e) => {
+                write!(
+                    f,
+                    "Brokers did not answer a metadata request ({e}); check `brokers` and the network"
+                )
+            }
+            PreflightIssue::NoBrokers => write!(f, "The cluster reported no brokers"),
+            PreflightIssue::Missing(topic) => {
+                write!(
+                    f,
+                    "Topic {topic} does not exist; create it or fix its name in the topic configuration"
+                )
+            }
+            PreflightIssue::NotAuthorized(topic) => {
+                write!(
+                    f,
+                    "Not authorized to access topic {topic}; grant this client's principal access to it"
+                )
+            }
+            PreflightIssue::TopicError { topic, error } => {
+                write!(f, "Topic {topic} is unavailable: {error}")
+            }
+            PreflightIssue::TooFewPartitions {
+                topic,
+                partitions,
+                required,
+            } => write!(
+                f,
+                "Topic {topic} has {partitions} partitions, at least {required} are required"
+            ),
+            PreflightIssue::Leaderless { topic, partitions } => {
+                write!(
+                    f,
+                    "Partitions {partitions:?} of topic {topic} have no leader"
+                )
+            }
+            PreflightIssue::MessageTooLarge {
+                topic,
+                producer_max_bytes,
+                topic_max_bytes,
+            } => write!(
+                f,
+                "Topic {topic} accepts messages up to {topic_max_bytes} bytes but the producer sends up to {producer_max_bytes}; lower `message_max_bytes` or raise the topic's max.message.bytes"
+            ),
+            PreflightIssue::ConfigUnreadable { topic, error } => write!(
+                f,
+                "Could not read the config of topic {topic} ({error}); its message size limit is unchecked"
+            ),
+            PreflightIssue::SnapshotChunked {
+                topic,
+                estimated_bytes,
+                max_bytes,
+            } => write!(
+                f,
+                "Snapshots on {topic} may reach {estimated_bytes} bytes, over the {max_bytes} byte message limit; they will be sent in chunks that consumers must reassemble"
+            ),
+        }
+    }
+}
+
+/// Estimated size of a snapshot message with `levels` levels per side
+fn snapshot_bytes(levels: usize) -> usize {
+    SNAPSHOT_ENVELOPE_BYTES + 2 * levels * LEVEL_BYTES_ESTIMATE
+}
+
+/// Every topic the engine consumes or produces to, by name. The depth delta
+/// topics are named per instrument and so are not checked here
+pub fn requirements(
+    topics: &TopicMap,
+    engine: &EngineConfig,
+) -> BTreeMap<String, TopicRequirement> {
+    let mut requirements = BTreeMap::new();
+    let mut require = |topic: &str, role, largest_message_bytes: Option<usize>| {
+        let requirement = requirements
+            .entry(topic.to_string())
+            .or_insert(TopicRequirement {
+                role,
+                largest_message_bytes: None,
+            });
+        requirement.largest_message_bytes =
+            requirement.largest_message_bytes.max(largest_message_bytes);
+    };
+    for topic in topics.subscriptions(&engine.liveness) {
+        require(topic, TopicRole::Consume, None);
+    }
+    require(&topics.dead_letter, TopicRole::Produce, None);
+    require(&topics.consumption, TopicRole::Produce, None);
+    for topic in engine.output_topics() {
+        require(topic, TopicRole::Produce, None);
+    }
+    for profile in &engine.feeds.profiles {
+        require(
+            &profile.depth_topic,
+            TopicRole::Produce,
+            Some(snapshot_bytes(profile.depth_levels)),
+        );
+    }
+    require(
+        &engine.instruments.settlement_topic,
+        TopicRole::Produce,
+        Some(snapshot_bytes(engine.instruments.settlement_snapshot_depth)),
+    );
+    requirements
+}
+
+/// Checks what the cluster reported against what the engine needs
+pub fn check(
+    config: &PreflightConfig,
+    producer_max_bytes: usize,
+    requirements: &BTreeMap<String, TopicRequirement>,
+    cluster: &ClusterView,
+) -> Vec<PreflightIssue> {
+    if cluster.brokers == 0 {
+        return vec![PreflightIssue::NoBrokers];
+    }
+    let mut issues = Vec::new();
+    for (topic, requirement) in requirements {
+        let Some(view) = cluster.topics.get(topic) else {
+            issues.push(PreflightIssue::Missing(topic.clone()));
+            continue;
+        };
+        match &view.status {
+            TopicStatus::Ok => {}
+            TopicStatus::Missing => {
+                issues.push(PreflightIssue::Missing(topic.clone()));
+                continue;
+            }
+            TopicStatus::NotAuthorized => {
+                issues.push(PreflightIssue::NotAuthorized(topic.clone()));
+                continue;
+            }
+            TopicStatus::Error(error) => {
+                issues.push(PreflightIssue::TopicError {
+                    topic: topic.clone(),
+                    error: error.clone(),
+                });
+                continue;
+            }
+        }
+        if view.partitions < config.min_partitions {
+            issues.push(PreflightIssue::TooFewPartitions {
+                topic: topic.clone(),
+                partitions: view.partitions,
+                required: config.min_partitions,
+            });
+        }
+        if !view.leaderless.is_empty() {
+            issues.push(PreflightIssue::Leaderless {
+                topic: topic.clone(),
+                partitions: view.leaderless.clone(),
+            });
+        }
+        if requirement.role == TopicRole::Consume {
+            continue;
+        }
+        match &view.max_message_bytes {
+            Ok(topic_max_bytes) => {
+                if producer_max_bytes > *topic_max_bytes {
+                    issues.push(PreflightIssue::MessageTooLarge {
+                        topic: topic.clone(),
+                        producer_max_bytes,
+                        topic_max_bytes: *topic_max_bytes,
+                    });
+                }
+                let max_bytes = producer_max_bytes.min(*topic_max_bytes);
+                if let Some(estimated_bytes) = requirement.largest_message_bytes
+                    && estimated_bytes > max_bytes
+                {
+                    issues.push(PreflightIssue::SnapshotChunked {
+                        topic: topic.clone(),
+                        estimated_bytes,
+                        max_bytes,
+                    });
+                }
+            }
+            Err(error) => issues.push(PreflightIssue::ConfigUnreadable {
+                topic: topic.clone(),
+                error: error.clone(),
+            }),
+        }
+    }
+    issues
+}
+
+/// Asks the cluster about the required topics and checks them
+pub async fn run(
+    kafka: &KafkaConfig,
+    requirements: &BTreeMap<String, TopicRequirement>,
+) -> Vec<PreflightIssue> {
+    let timeout = Duration::from_millis(kafka.preflight.timeout_ms);
+    let admin: AdminClient<DefaultClientContext> = match ClientConfig::new()
+        .set("bootstrap.servers", &kafka.brokers)
+        .create()
+    {
+        Ok(admin) => admin,
+        Err(e) => return vec![PreflightIssue::Unreachable(e.to_string())],
+    };
+    let metadata = match admin.inner().fetch_metadata(None, timeout) {
+        Ok(metadata) => metadata,
+        Err(e) => return vec![PreflightIssue::Unreachable(e.to_string())],
+    };
+    let mut cluster = cluster_view(&metadata, requirements);
+
+    let produced: Vec<&str> = requirements
+        .iter()
+        .filter(|(topic, requirement)| {
+            requirement.role == TopicRole::Produce
+                && cluster
+                    .topics
+                    .get(*topic)
+                    .is_some_and(|view| view.status == TopicStatus::Ok)
+        })
+        .map(|(topic, _)| topic.as_str())
+        .collect();
+    let specifiers: Vec<ResourceSpecifier> = produced
+        .iter()
+        .map(|topic| ResourceSpecifier::Topic(topic))
+        .collect();
+    let options = AdminOptions::new().request_timeout(Some(timeout));
+    match admin.describe_configs(&specifiers, &options).await {
+        Ok(results) => {
+            for (topic, result) in produced.iter().zip(results) {
+                let max_message_bytes =
+                    result
+                        .map_err(|code| code.to_string())
+                        .and_then(|resource| {
+                            resource
+                                .get("max.message.bytes")
+                                .and_then(|entry| entry.value.as_deref())
+                                .and_then(|value| value.parse().ok())
+                                .ok_or_else(|| "max.message.bytes not reported".to_string())
+                        });
+                if let Some(view) = cluster.topics.get_mut(*topic) {
+                    view.max_message_bytes = max_message_bytes;
+                }
+            }
+        }
+        Err(e) => {
+            for topic in &produced {
+                if let Some(view) = cluster.topics.get_mut(*topic) {
+                    view.max_message_bytes = Err(e.to_string());
+                }
+            }
+        }
+    }
+    check(
+        &kafka.preflight,
+        kafka.message_max_bytes,
+        requirements,
+        &cluster,
+    )
+}
+
+fn cluster_view(
+    metadata: &Metadata,
+    requirements: &BTreeMap<String, TopicRequirement>,
+) -> ClusterView {
+    let topics = metadata
+        .topics()
+        .iter()
+        .filter(|topic| requirements.contains_key(topic.name()))
+        .map(|topic| {
+            let status = match topic.error().map(RDKafkaErrorCode::from) {
+                None => TopicStatus::Ok,
+                Some(RDKafkaErrorCode::UnknownTopicOrPartition) => TopicStatus::Missing,
+                Some(RDKafkaErrorCode::TopicAuthorizationFailed) => TopicStatus::NotAuthorized,
+                Some(code) => TopicStatus::Error(code.to_string()),
+            };
+            let view = TopicView {
+                status,
+                partitions: topic.partitions().len(),
+                leaderless: topic
+                    .partitions()
+                    .iter()
+                    .filter(|partition| partition.leader() < 0)
+                    .map(|partition| partition.id())
+                    .collect(),
+                max_message_bytes: Err("not described".to_string()),
+            };
+            (topic.name().to_string(), view)
+        })
+        .collect();
+    ClusterView {
+        brokers: metadata.brokers().len(),
+        topics,
+    }
+}
+
+/// Logs the issues, returning whether the engine may start
+pub fn report(config: &PreflightConfig, issues: &[PreflightIssue]) -> bool {
+    for issue in issues {
+        if issue.is_error() {
+            error!("Preflight: {}", issue);
+        } else {
+            warn!("Preflight: {}", issue);
+        }
+    }
+    let errors = issues.iter().filter(|issue| issue.is_error()).count();
+    let warnings = issues.len() - errors;
+    if issues.is_empty() {
+        info!("Preflight checks passed");
+    } else {
+        info!(
+            "Preflight checks found {} errors and {} warnings",
+            errors, warnings
+        );
+    }
+    errors == 0 && (warnings == 0 || !config.fail_on_warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy(partitions: usize) -> TopicView {
+        TopicView {
+            status: TopicStatus::Ok,
+            partitions,
+            leaderless: Vec::new(),
+            max_message_bytes: Ok(1_000_000),
+        }
+    }
+
+    fn healthy_cluster(requirements: &BTreeMap<String, TopicRequirement>) -> ClusterView {
+        ClusterView {
+            brokers: 3,
+            topics: requirements
+                .keys()
+                .map(|topic| (topic.clone(), healthy(3)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_requirements_cover_commands_outputs_and_snapshots() {
+        let engine = EngineConfig::default();
+        let requirements = requirements(&TopicMap::default(), &engine);
+        assert_eq!(requirements["order.create"].role, TopicRole::Consume);
+        assert!(!requirements.contains_key("oms.heartbeat"));
+        assert_eq!(requirements["engine.dlq"].role, TopicRole::Produce);
+        assert_eq!(requirements["trade.executed"].role, TopicRole::Produce);
+        let depth = &engine.feeds.profiles[0];
+        assert_eq!(
+            requirements[&depth.depth_topic].largest_message_bytes,
+            Some(snapshot_bytes(depth.depth_levels))
+        );
+
+        // OMS heartbeats are only consumed under the liveness contract
+        let mut engine = engine;
+        engine.liveness.enabled = true;
+        let with_liveness = super::requirements(&TopicMap::default(), &engine);
+        assert_eq!(with_liveness["oms.heartbeat"].role, TopicRole::Consume);
+    }
+
+    #[test]
+    fn test_healthy_cluster_passes() {
+        let config = PreflightConfig::default();
+        let requirements = requirements(&TopicMap::default(), &EngineConfig::default());
+        let cluster = healthy_cluster(&requirements);
+        assert_eq!(check(&config, 1_000_000, &requirements, &cluster), vec![]);
+        assert!(report(&config, &[]));
+    }
+
+    #[test]
+    fn test_cluster_problems_are_reported() {
+        let config = PreflightConfig {
+            min_partitions: 2,
+            ..PreflightConfig::default()
+        };
+        let requirements = requirements(&TopicMap::default(), &EngineConfig::default());
+        let mut cluster = healthy_cluster(&requirements);
+        cluster.topics.remove("order.create");
+        cluster.topics.get_mut("engine.admin").unwrap().status = TopicStatus::NotAuthorized;
+        *cluster.topics.get_mut("rfq.quote").unwrap() = TopicView {
+            leaderless: vec![0],
+            ..healthy(1)
+        };
+        cluster
+            .topics
+            .get_mut("engine.dlq")
+            .unwrap()
+            .max_message_bytes = Ok(500_000);
+        cluster
+            .topics
+            .get_mut("rfq.events")
+            .unwrap()
+            .max_message_bytes = Err("ClusterAuthorizationFailed".to_string());
+
+        let issues = check(&config, 1_000_000, &requirements, &cluster);
+        assert_eq!(
+            issues,
+            vec![
+                PreflightIssue::NotAuthorized("engine.admin".to_string()),
+                PreflightIssue::MessageTooLarge {
+                    topic: "engine.dlq".to_string(),
+                    producer_max_bytes: 1_000_000,
+                    topic_max_bytes: 500_000,
+                },
+                PreflightIssue::Missing("order.create".to_string()),
+                PreflightIssue::ConfigUnreadable {
+                    topic: "rfq.events".to_string(),
+                    error: "ClusterAuthorizationFailed".to_string(),
+                },
+                PreflightIssue::TooFewPartitions {
+                    topic: "rfq.quote".to_string(),
+                    partitions: 1,
+                    required: 2,
+                },
+                PreflightIssue::Leaderless {
+                    topic: "rfq.quote".to_string(),
+                    partitions: vec![0],
+                },
+            ]
+        );
+        assert!(!report(&config, &issues));
+
+        let unreachable = ClusterView::default();
+        assert_eq!(
+            check(&config, 1_000_000, &requirements, &unreachable),
+            vec![PreflightIssue::NoBrokers]
+        );
+    }
+
+    #[test]
+    fn test_oversized_snapshots_warn() {
+        let config = PreflightConfig::default();
+        let requirements = requirements(&TopicMap::default(), &EngineConfig::default());
+        let cluster = healthy_cluster(&requirements);
+        let issues = check(&config, 4_096, &requirements, &cluster);
+        assert!(!issues.is_empty());
+        assert!(issues.iter().all(|issue| matches!(
+            issue,
+            PreflightIssue::SnapshotChunked {
+                max_bytes: 4_096,
+                ..
+            }
+        )));
+        assert!(report(&config, &issues));
+        let strict = PreflightConfig {
+            fail_on_warnings: true,
+            ..config
+        };
+        assert!(!report(&strict, &issues));
+    }
+}
@@ -7,12 +7,15 @@ pub use crate::orderbook::manager::{BookManager, BookManagerStd, BookManagerToki
 pub use crate::orderbook::iterators::LevelInfo;
 
 // Market impact and simulation types
-pub use crate::orderbook::market_impact::{MarketImpact, OrderSimulation};
+pub use crate::orderbook::market_impact::{
+    ImpactEstimate, ImpactModel, ImpactModelKind, MarketImpact, OrderSimulation,
+};
 
 // Snapshot types
 pub use crate::orderbook::snapshot::{EnrichedSnapshot, MetricFlags, OrderBookSnapshot};
 
 // Statistics types
+pub use crate::orderbook::activity::ActivityStats;
 pub use crate::orderbook::statistics::{DepthStats, DistributionBin};
 
 // Trade-related types
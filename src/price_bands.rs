@@ -0,0 +1,219 @@
+// src/price_bands.rs
+use crate::helpers::EngineCommand;
+use crate::order_responses::{RejectReason, Rejection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// Price a band is centred on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BandReference {
+    /// Last trade price of the book
+    #[default]
+    Last,
+    /// Value of the index the instrument's pegged orders track
+    Index,
+    /// Theoretical price posted on `price.theoretical`
+    Mark,
+    /// Midpoint of the best bid and ask, or the last trade price while a side
+    /// of the book is empty, so fat-fingered orders are caught on books that
+    /// rarely trade
+    Mid,
+}
+
+/// How far from its reference a band reaches on either side
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BandWidth {
+    /// A fixed number of ticks, whatever the price
+    Ticks { ticks: u64, tick_size: u64 },
+    /// A share of the reference, e.g. `2.5` for 2.5%, so the band follows the
+    /// price of volatile instruments
+    Percent { percent: f64 },
+}
+
+/// Prices limit orders may be entered at, around a moving reference
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceBand {
+    #[serde(default)]
+    pub reference: BandReference,
+    #[serde(flatten)]
+    pub width: BandWidth,
+}
+
+impl PriceBand {
+    /// Lowest and highest prices within the band around `reference`
+    pub fn bounds(&self, reference: u64) -> (u64, u64) {
+        let distance = match self.width {
+            BandWidth::Ticks { ticks, tick_size } => ticks.saturating_mul(tick_size),
+            BandWidth::Percent { percent } => (reference as f64 * percent / 100.0) as u64,
+        };
+        (
+            reference.saturating_sub(distance),
+            reference.saturating_add(distance),
+        )
+    }
+}
+
+/// Price bands of the instruments created with one
+///
+/// Limit orders, modifies and replaces priced outside the band of their
+/// instrument are rejected. Market orders are bounded by market protection
+/// instead, and liquidation orders must be able to close out at any price. No
+/// band is enforced while its reference is unknown, e.g. before the first
+/// trade. Last, mid and mark references move with the commands the engine
+/// processes; index values are computed on a timer, so orders near the edge of
+/// an index band may be admitted differently when the log is replayed.
+#[derive(Default)]
+pub struct PriceBands {
+    bands: HashMap<String, PriceBand>,
+}
+
+impl PriceBands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks the bands instruments are created and deleted with
+    pub fn record(&mut self, cmd: &EngineCommand) {
+        match cmd {
+            EngineCommand::InstrumentCreate(create) => match create.price_band {
+                Some(band) => {
+                    info!(
+                        "Configured price band on {}: {:?}",
+                        create.instrument_id, band
+                    );
+                    self.bands.insert(create.instrument_id.clone(), band);
+                }
+                None => {
+                    self.bands.remove(&create.instrument_id);
+                }
+            },
+            EngineCommand::InstrumentDelete(delete) => {
+                self.bands.remove(&delete.instrument_id);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn band(&self, instrument_id: &str) -> Option<&PriceBand> {
+        self.bands.get(instrument_id)
+    }
+
+    /// Rejects a priced order command outside the band of its instrument,
+    /// `reference` giving the current price of a band's reference
+    pub fn admit(
+        &self,
+        cmd: &EngineCommand,
+        reference: impl FnOnce(&str, BandReference) -> Option<u64>,
+    ) -> Result<(), Rejection> {
+        let (instrument_id, order_id, price) = match cmd {
+            EngineCommand::OrderCreate(order)
+                if !order.order_type.is_priced_by_book() && !order.liquidation =>
+            {
+                (&order.instrument_id, order.order_id, order.price)
+            }
+            EngineCommand::OrderModify(order) => {
+                (&order.instrument_id, order.order_id, order.price)
+            }
+            EngineCommand::OrderReplace(order) => {
+                (&order.instrument_id, order.order_id, order.price)
+            }
+            _ => return Ok(()),
+        };
+        let Some(band) = self.band(instrument_id) else {
+            return Ok(());
+        };
+        let Some(reference_price) = reference(instrument_id, band.reference) else {
+            return Ok(());
+        };
+        let (low, high) = band.bounds(reference_price);
+        if (low..=high).contains(&price) {
+            return Ok(());
+        }
+        warn!(
+            "Rejecting order {} on {}: price {} is outside the band [{}, {}]",
+            order_id, instrument_id, price, low, high
+        );
+        Err(Rejection::new(
+            RejectReason::OutsidePriceBand,
+            format!(
+                "price {price} is outside the band [{low}, {high}] around {:?} price {reference_price}",
+                band.reference
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{DeleteInstrumentPayload, InstrumentCreatePayload, OrderModifyPayload};
+
+    fn modify(price: u64) -> EngineCommand {
+        EngineCommand::OrderModify(OrderModifyPayload {
+            order_id: 1,
+            instrument_id: "BTC".to_string(),
+            price,
+            quantity: 5,
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        })
+    }
+
+    #[test]
+    fn test_bands_reach_ticks_or_a_share_of_the_reference() {
+        let ticks = PriceBand {
+            reference: BandReference::Last,
+            width: BandWidth::Ticks {
+                ticks: 3,
+                tick_size: 10,
+            },
+        };
+        assert_eq!(ticks.bounds(1_000), (970, 1_030));
+        assert_eq!(ticks.bounds(20), (0, 50));
+        let percent: PriceBand = serde_json::from_value(serde_json::json!({
+            "reference": "index",
+            "kind": "percent",
+            "percent": 2.5,
+        }))
+        .unwrap();
+        assert_eq!(percent.reference, BandReference::Index);
+        assert_eq!(percent.bounds(40_000), (39_000, 41_000));
+        assert_eq!(percent.bounds(80_000), (78_000, 82_000));
+        let mid: PriceBand = serde_json::from_value(serde_json::json!({
+            "reference": "mid",
+            "kind": "percent",
+            "percent": 5,
+        }))
+        .unwrap();
+        assert_eq!(mid.reference, BandReference::Mid);
+        assert_eq!(mid.bounds(101), (96, 106));
+    }
+
+    #[test]
+    fn test_orders_outside_the_band_are_rejected() {
+        let mut bands = PriceBands::new();
+        let create: InstrumentCreatePayload = serde_json::from_value(serde_json::json!({
+            "instrument_id": "BTC",
+            "price_band": { "reference": "mark", "kind": "percent", "percent": 10 },
+        }))
+        .unwrap();
+        bands.record(&EngineCommand::InstrumentCreate(create));
+
+        let mark = |_: &str, reference| (reference == BandReference::Mark).then_some(100);
+        assert!(bands.admit(&modify(110), mark).is_ok());
+        let rejection = bands.admit(&modify(111), mark).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::OutsidePriceBand);
+        // Unenforced until the reference is known
+        assert!(bands.admit(&modify(111), |_, _| None).is_ok());
+
+        bands.record(&EngineCommand::InstrumentDelete(DeleteInstrumentPayload {
+            instrument_id: "BTC".to_string(),
+            sequence: None,
+        }));
+        assert!(bands.admit(&modify(111), mark).is_ok());
+    }
+}
@@ -0,0 +1,322 @@
+// src/publisher.rs
+use crate::config::partitioning::PartitioningConfig;
+use crate::partitioning::{OutboundRouter, Placement};
+use crate::redaction::Redactor;
+use crate::utils::chunking::{ChunkHeader, split_payload};
+use crate::utils::current_time_millis;
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tracing::{info, warn};
+
+/// A message waiting to be produced to Kafka
+#[derive(Debug, Clone)]
+pub struct OutboundMessage {
+    pub topic: String,
+    pub key: String,
+    pub payload: String,
+}
+
+/// A consumed message that could not be processed, forwarded to the dead letter topic
+#[derive(Debug, Serialize)]
+pub struct DeadLetter<'a> {
+    /// Topic the message was consumed from
+    pub topic: &'a str,
+    pub error: String,
+    /// The message as received, in hex digits when it is not UTF-8
+    pub payload: Cow<'a, str>,
+    /// `hex` for a message that is not UTF-8, such as a protobuf one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_encoding: Option<&'static str>,
+    pub timestamp: u64,
+}
+
+impl<'a> DeadLetter<'a> {
+    pub fn new(topic: &'a str, error: String, payload: &'a [u8], timestamp: u64) -> Self {
+        let (payload, payload_encoding) = match std::str::from_utf8(payload) {
+            Ok(text) => (Cow::Borrowed(text), None),
+            Err(_) => (
+                Cow::Owned(payload.iter().map(|byte| format!("{byte:02x}")).collect()),
+                Some("hex"),
+            ),
+        };
+        Self {
+            topic,
+            error,
+            payload,
+            payload_encoding,
+            timestamp,
+        }
+    }
+}
+
+/// Handle used by the engine to queue outbound messages without blocking.
+///
+/// Messages are serialized immediately and handed to the publisher task; if the
+/// queue is full the message is dropped with a warning rather than stalling matching.
+/// Messages for topics with a redaction rule are redacted on the way.
+#[derive(Debug, Clone)]
+pub struct Publisher {
+    tx: Sender<OutboundMessage>,
+    redactor: Option<Arc<Redactor>>,
+    /// Topics messages may be queued for, every topic when unset
+    allowed: Option<Arc<HashSet<String>>>,
+}
+
+impl Publisher {
+    /// Creates a publisher and the receiving end to hand to `run_publisher`
+    pub fn channel(capacity: usize) -> (Self, Receiver<OutboundMessage>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        (
+            Self {
+                tx,
+                redactor: None,
+                allowed: None,
+            },
+            rx,
+        )
+    }
+
+    /// Silently drops what this publisher and its clones queue for any topic
+    /// but `topics`
+    pub fn restricted_to<'a>(mut self, topics: impl IntoIterator<Item = &'a str>) -> Self {
+        self.allowed = Some(Arc::new(topics.into_iter().map(str::to_string).collect()));
+        self
+    }
+
+    /// Redacts what this publisher and its clones queue for the topics of the
+    /// redactor's rules
+    pub fn with_redaction(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(Arc::new(redactor));
+        self
+    }
+
+    pub fn publish<P: Serialize>(&self, topic: &str, key: &str, payload: &P) {
+        let payload = match self.redacting(topic) {
+            Some(redactor) => serde_json::to_value(payload).and_then(|mut value| {
+                redactor.redact(topic, &mut value);
+                serde_json::to_string(&value)
+            }),
+            None => serde_json::to_string(payload),
+        };
+        match payload {
+            Ok(json) => self.queue(topic, key, json),
+            Err(e) => warn!("Failed to serialize message for {}: {}", topic, e),
+        }
+    }
+
+    /// Queues a payload that has already been serialized to JSON
+    pub fn publish_serialized(&self, topic: &str, key: &str, payload: String) {
+        let Some(redactor) = self.redacting(topic) else {
+            self.queue(topic, key, payload);
+            return;
+        };
+        // Rather drop a message than publish what may not be redacted
+        let mut value = match serde_json::from_str(&payload) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Dropping unredactable message for {}: {}", topic, e);
+                return;
+            }
+        };
+        redactor.redact(topic, &mut value);
+        self.queue(topic, key, value.to_string());
+    }
+
+    fn redacting(&self, topic: &str) -> Option<&Redactor> {
+        self.redactor
+            .as_deref()
+            .filter(|redactor| redactor.redacts(topic))
+    }
+
+    fn queue(&self, topic: &str, key: &str, payload: String) {
+        if self
+            .allowed
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(topic))
+        {
+            return;
+        }
+        let message = OutboundMessage {
+            topic: topic.to_string(),
+            key: key.to_string(),
+            payload,
+        };
+        if let Err(e) = self.tx.try_send(message) {
+            warn!("Dropping outbound message for {}: {}", topic, e);
+        }
+    }
+}
+
+/// Room left in each chunk for the key and chunk headers
+const CHUNK_OVERHEAD_BYTES: usize = 512;
+const METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Partitions of each topic messages were placed on, looked up once per topic
+#[derive(Default)]
+struct PartitionCounts {
+    counts: HashMap<String, Option<i32>>,
+}
+
+impl PartitionCounts {
+    /// Partition of a message on `topic`, or `None` to leave it to librdkafka,
+    /// as when the partitions of the topic could not be looked up
+    async fn partition(
+        &mut self,
+        producer: &FutureProducer,
+        topic: &str,
+        placement: Placement,
+    ) -> Option<i32> {
+        if placement == Placement::ByKey {
+            return None;
+        }
+        if !self.counts.contains_key(topic) {
+            let count = partition_count(producer.clone(), topic.to_string()).await;
+            if count.is_none() {
+                warn!(
+                    "Partitions of {} unknown, leaving its messages to the default partitioner",
+                    topic
+                );
+            }
+            self.counts.insert(topic.to_string(), count);
+        }
+        self.counts[topic].and_then(|count| placement.partition(count))
+    }
+}
+
+async fn partition_count(producer: FutureProducer, topic: String) -> Option<i32> {
+    tokio::task::spawn_blocking(move || {
+        let metadata = producer
+            .client()
+            .fetch_metadata(Some(&topic), METADATA_TIMEOUT)
+            .map_err(|e| warn!("Failed to fetch the metadata of {}: {}", topic, e))
+            .ok()?;
+        let partitions = metadata.topics().first()?.partitions().len();
+        i32::try_from(partitions).ok().filter(|&count| count > 0)
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Drains the outbound queue into Kafka until every `Publisher` is dropped
+///
+/// Messages larger than `max_message_bytes` would be rejected by the producer, so
+/// they are published as a package of chunks under the same key instead, for
+/// consumers to reassemble with the library's `ChunkAssembler`. Each
+/// message is keyed and placed as `partitioning` sets for its topic, every
+/// chunk of a package on the same partition.
+pub async fn run_publisher(
+    mut rx: Receiver<OutboundMessage>,
+    producer: FutureProducer,
+    max_message_bytes: usize,
+    partitioning: PartitioningConfig,
+) {
+    info!("Publisher started");
+    let mut router = OutboundRouter::new(partitioning);
+    let mut partition_counts = PartitionCounts::default();
+    let mut packages_sent = 0u64;
+    while let Some(mut message) = rx.recv().await {
+        let (key, placement) = router.route(&message);
+        message.key = key;
+        let partition = partition_counts
+            .partition(&producer, &message.topic, placement)
+            .await;
+        if message.payload.len() <= max_message_bytes {
+            let mut record = FutureRecord::to(&message.topic)
+                .key(&message.key)
+                .payload(&message.payload);
+            record.partition = partition;
+            if let Err((e, _)) = producer.send(record, Duration::from_secs(5)).await {
+                warn!("Failed to publish to {}: {}", message.topic, e);
+            }
+            continue;
+        }
+
+        packages_sent += 1;
+        let package_id = format!(
+            "{}-{}-{}",
+            message.key,
+            current_time_millis(),
+            packages_sent
+        );
+        let max_chunk_bytes = max_message_bytes.saturating_sub(CHUNK_OVERHEAD_BYTES);
+        let chunks = split_payload(message.payload.as_bytes(), max_chunk_bytes, &package_id);
+        info!(
+            "Publishing {} byte message for {} as {} chunks (package {})",
+            message.payload.len(),
+            message.topic,
+            chunks.len(),
+            package_id
+        );
+        for (header, bytes) in chunks {
+            let mut record = FutureRecord::to(&message.topic)
+                .key(&message.key)
+                .payload(bytes)
+                .headers(chunk_headers(&header));
+            record.partition = partition;
+            if let Err((e, _)) = producer.send(record, Duration::from_secs(5)).await {
+                // Consumers cannot complete the package, so don't send the rest
+                warn!(
+                    "Failed to publish chunk {} of {} to {}: {}",
+                    header.part + 1,
+                    header.parts,
+                    message.topic,
+                    e
+                );
+                break;
+            }
+        }
+    }
+    info!("Publisher stopped (outbound channel closed)");
+}
+
+/// Kafka headers carrying the reassembly metadata of a chunk
+fn chunk_headers(header: &ChunkHeader) -> OwnedHeaders {
+    header
+        .to_headers()
+        .into_iter()
+        .fold(OwnedHeaders::new(), |headers, (name, value)| {
+            headers.insert(Header {
+                key: name,
+                value: Some(&value),
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chunking::ChunkAssembler;
+    use rdkafka::message::Headers;
+
+    #[test]
+    fn test_chunks_reassemble_from_their_kafka_headers() {
+        let payload: String = (0..1_000)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect();
+        let chunks = split_payload(payload.as_bytes(), 300, "BTC-1-1");
+        assert_eq!(chunks.len(), 4);
+
+        // As a consumer reads them, whatever order they arrive in
+        let mut assembler = ChunkAssembler::new();
+        let mut reassembled = None;
+        for (header, bytes) in chunks.iter().rev() {
+            let headers = chunk_headers(header);
+            let read = ChunkHeader::from_headers(
+                headers
+                    .iter()
+                    .filter_map(|header| Some((header.key, header.value?))),
+            )
+            .unwrap();
+            assert_eq!(&read, header);
+            reassembled = assembler.push(&read, bytes, 0).unwrap();
+        }
+        assert_eq!(reassembled.as_deref(), Some(payload.as_bytes()));
+    }
+}
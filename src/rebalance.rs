@@ -0,0 +1,132 @@
+// src/rebalance.rs
+use crate::consumption::ConsumptionControl;
+use crate::watchdog::Progress;
+use rdkafka::client::ClientContext;
+use rdkafka::consumer::{
+    BaseConsumer, CommitMode, Consumer, ConsumerContext, Rebalance, StreamConsumer,
+};
+use rdkafka::error::KafkaError;
+use rdkafka::types::RDKafkaErrorCode;
+use std::time::{Duration, Instant};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tracing::{info, warn};
+
+/// The consumer of inbound commands
+pub type CommandConsumer = StreamConsumer<RebalanceContext>;
+
+/// Keeps rebalances from splitting a partition's history between two engines
+///
+/// The callbacks run inside the consumer's poll, so no command is consumed
+/// while they do. Before partitions are revoked, the engine is given up to
+/// `drain_timeout` to apply every command already handed to it, and the
+/// offsets consumed so far are committed: the next owner of a partition then
+/// resumes after the last command applied here, rather than replaying part of
+/// what this process already applied or skipping what it had not. Newly
+/// assigned partitions are paused again if operators paused them.
+pub struct RebalanceContext {
+    progress: Progress,
+    consumption: ConsumptionControl,
+    drain_timeout: Duration,
+}
+
+impl RebalanceContext {
+    pub fn new(
+        progress: Progress,
+        consumption: ConsumptionControl,
+        drain_timeout: Duration,
+    ) -> Self {
+        Self {
+            progress,
+            consumption,
+            drain_timeout,
+        }
+    }
+
+    pub fn consumption(&self) -> &ConsumptionControl {
+        &self.consumption
+    }
+}
+
+impl ClientContext for RebalanceContext {}
+
+impl ConsumerContext for RebalanceContext {
+    fn pre_rebalance(&self, consumer: &BaseConsumer<Self>, rebalance: &Rebalance<'_>) {
+        let Rebalance::Revoke(partitions) = rebalance else {
+            return;
+        };
+        info!(
+            "Partitions revoked: {:?}, pausing intake",
+            partitions.elements()
+        );
+        // Blocking here must not hold up an engine task scheduled on this
+        // worker thread
+        let drained = match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| drain(&self.progress, self.drain_timeout))
+            }
+            _ => drain(&self.progress, self.drain_timeout),
+        };
+        if !drained {
+            warn!(
+                "{} commands still queued for the engine after {:?}, the next owner of the revoked partitions may apply them again",
+                self.progress.queued(),
+                self.drain_timeout
+            );
+        }
+        match consumer.commit_consumer_state(CommitMode::Sync) {
+            Ok(()) | Err(KafkaError::ConsumerCommit(RDKafkaErrorCode::NoOffset)) => {}
+            Err(e) => warn!("Failed to commit offsets of revoked partitions: {}", e),
+        }
+    }
+
+    fn post_rebalance(&self, consumer: &BaseConsumer<Self>, rebalance: &Rebalance<'_>) {
+        match rebalance {
+            Rebalance::Assign(partitions) => {
+                info!(
+                    "Partitions assigned: {:?}, resuming intake",
+                    partitions.elements()
+                );
+                self.consumption.apply(consumer);
+            }
+            Rebalance::Revoke(_) => {}
+            Rebalance::Error(e) => warn!("Rebalance failed: {}", e),
+        }
+    }
+}
+
+/// Waits until the engine has applied every command handed to it, returning
+/// false if it has not within `timeout`
+fn drain(progress: &Progress, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while progress.queued() > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_waits_for_queued_commands() {
+        let progress = Progress::default();
+        assert!(drain(&progress, Duration::ZERO));
+
+        progress.received();
+        progress.received();
+        assert!(!drain(&progress, Duration::from_millis(20)));
+
+        let engine = progress.clone();
+        let applying = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            engine.applied(1);
+            engine.applied(2);
+        });
+        assert!(drain(&progress, Duration::from_secs(5)));
+        applying.join().unwrap();
+    }
+}
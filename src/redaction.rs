@@ -0,0 +1,181 @@
+// src/redaction.rs
+use crate::config::redaction::RedactionConfig;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// What happens to a field of a redacted message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldAction {
+    Strip,
+    Hash,
+}
+
+/// Strips and hashes the configured fields of messages bound for redacted topics
+#[derive(Debug, Default)]
+pub struct Redactor {
+    salt: String,
+    /// Field actions per topic; a field both stripped and hashed is stripped
+    topics: HashMap<String, Arc<HashMap<String, FieldAction>>>,
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> Self {
+        let mut topics: HashMap<String, HashMap<String, FieldAction>> = HashMap::new();
+        for rule in &config.rules {
+            for topic in &rule.topics {
+                let fields = topics.entry(topic.clone()).or_default();
+                for field in &rule.hash {
+                    fields.entry(field.clone()).or_insert(FieldAction::Hash);
+                }
+                for field in &rule.strip {
+                    fields.insert(field.clone(), FieldAction::Strip);
+                }
+            }
+        }
+        Self {
+            salt: config.salt.clone(),
+            topics: topics
+                .into_iter()
+                .map(|(topic, fields)| (topic, Arc::new(fields)))
+                .collect(),
+        }
+    }
+
+    pub fn redacts(&self, topic: &str) -> bool {
+        self.topics.contains_key(topic)
+    }
+
+    /// Redacts a message bound for `topic` in place, at any depth
+    pub fn redact(&self, topic: &str, message: &mut Value) {
+        if let Some(fields) = self.topics.get(topic) {
+            self.redact_value(fields, message);
+        }
+    }
+
+    fn redact_value(&self, fields: &HashMap<String, FieldAction>, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.retain(|name, _| fields.get(name) != Some(&FieldAction::Strip));
+                for (name, value) in object.iter_mut() {
+                    if fields.get(name) == Some(&FieldAction::Hash) {
+                        self.hash(value);
+                    } else {
+                        self.redact_value(fields, value);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.redact_value(fields, item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces a value with the hex of its salted SHA-256, truncated to 128
+    /// bits; strings are hashed by their contents, other values by their JSON
+    fn hash(&self, value: &mut Value) {
+        let text = match &*value {
+            Value::Null => return,
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update(text.as_bytes());
+        let digest = hasher.finalize();
+        *value = Value::String(
+            digest[..16]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::redaction::RedactionRule;
+    use crate::publisher::Publisher;
+    use serde_json::json;
+
+    fn redactor() -> Redactor {
+        Redactor::new(&RedactionConfig {
+            salt: "secret".to_string(),
+            rules: vec![RedactionRule {
+                topics: vec!["public".to_string()],
+                strip: vec!["client_order_id".to_string()],
+                hash: vec!["participant_id".to_string()],
+            }],
+        })
+    }
+
+    #[test]
+    fn test_fields_are_stripped_and_hashed_at_any_depth() {
+        let redactor = redactor();
+        let mut message = json!({
+            "order_id": 1,
+            "participant_id": "desk-1",
+            "client_order_id": "abc",
+            "fills": [{ "participant_id": "desk-1", "client_order_id": "def", "price": 100 }],
+        });
+        let untouched = message.clone();
+        redactor.redact("internal", &mut message);
+        assert_eq!(message, untouched);
+
+        redactor.redact("public", &mut message);
+        let pseudonym = message["participant_id"].as_str().unwrap().to_string();
+        assert_eq!(pseudonym.len(), 32);
+        assert_ne!(pseudonym, "desk-1");
+        assert_eq!(
+            message,
+            json!({
+                "order_id": 1,
+                "participant_id": pseudonym,
+                "fills": [{ "participant_id": pseudonym, "price": 100 }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_hashes_depend_on_the_salt() {
+        let mut salted = json!({ "participant_id": "desk-1" });
+        redactor().redact("public", &mut salted);
+        let mut config = RedactionConfig {
+            salt: "other".to_string(),
+            ..RedactionConfig::default()
+        };
+        config.rules = vec![RedactionRule {
+            topics: vec!["public".to_string()],
+            strip: Vec::new(),
+            hash: vec!["participant_id".to_string()],
+        }];
+        let mut resalted = json!({ "participant_id": "desk-1" });
+        Redactor::new(&config).redact("public", &mut resalted);
+        assert_ne!(salted, resalted);
+    }
+
+    #[test]
+    fn test_publisher_redacts_only_configured_topics() {
+        let (publisher, mut outbound) = Publisher::channel(8);
+        let publisher = publisher.with_redaction(Redactor::new(&RedactionConfig::default()));
+        let trade = json!({ "price": 100, "taker_participant_id": "desk-1" });
+        publisher.publish("md.public.trades", "BTC", &trade);
+        publisher.publish("md.internal.trades", "BTC", &trade);
+        publisher.publish_serialized("md.public.trades", "BTC", trade.to_string());
+        // Payloads that cannot be redacted are not published
+        publisher.publish_serialized("md.public.trades", "BTC", "not json".to_string());
+
+        let payloads: Vec<Value> = std::iter::from_fn(|| outbound.try_recv().ok())
+            .map(|message| serde_json::from_str(&message.payload).unwrap())
+            .collect();
+        assert_eq!(
+            payloads,
+            [json!({ "price": 100 }), trade, json!({ "price": 100 })]
+        );
+    }
+}
@@ -0,0 +1,408 @@
+// src/risk.rs
+use crate::config::risk::{ParticipantLimits, RiskConfig};
+use crate::helpers::orderbook_helpers::price_limit;
+use crate::helpers::types::OrderType;
+use crate::helpers::{EngineCommand, OrderCreatePayload};
+use crate::order_responses::{RejectReason, Rejection};
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::tags::OrderTags;
+use pricelevel::OrderId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// A resting order and the participant owning it, as kept in journal snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOrder {
+    pub participant_id: String,
+    pub instrument_id: String,
+    pub order_id: u64,
+    /// Price times the quantity left when last counted
+    pub notional: u64,
+}
+
+/// Resting orders of a participant and what they are worth
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Exposure {
+    pub open_orders: usize,
+    pub open_notional: u64,
+}
+
+/// A registered order and the notional it was last counted with
+struct Tracked {
+    participant_id: String,
+    order_id: u64,
+    notional: u64,
+}
+
+/// Open order count and notional of each participant, checked against its
+/// limits before it enters an order that may rest
+///
+/// Orders are registered as they come to rest, and counted in their
+/// participant's totals from then on. The engine refreshes an order whenever
+/// it fills, is modified or cancelled, moving the totals by the difference, so
+/// admitting an order reads the totals without looking at the books. Orders of
+/// books evicted to disk keep counting as last seen. Orders that never rest,
+/// IOC, FOK and market ones, and liquidations are not limited.
+pub struct ParticipantRisk {
+    config: RiskConfig,
+    orders: HashMap<(String, OrderId), Tracked>,
+    exposures: HashMap<String, Exposure>,
+}
+
+/// Price times the quantity left of a resting order
+fn notional(order: &pricelevel::OrderType<OrderTags>) -> u64 {
+    let quantity = order.visible_quantity() + order.hidden_quantity();
+    order.price().saturating_mul(quantity)
+}
+
+impl ParticipantRisk {
+    pub fn new(config: RiskConfig) -> Self {
+        Self {
+            config,
+            orders: HashMap::new(),
+            exposures: HashMap::new(),
+        }
+    }
+
+    /// Records an order of a participant that rests in the book
+    pub fn register(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        participant_id: &str,
+        instrument_id: &str,
+        order_id: u64,
+    ) {
+        let Some(order) = manager
+            .get_book(instrument_id)
+            .and_then(|book| book.get_order(OrderId::from_u64(order_id)))
+        else {
+            return;
+        };
+        self.track(participant_id, instrument_id, order_id, notional(&order));
+    }
+
+    fn track(&mut self, participant_id: &str, instrument_id: &str, order_id: u64, notional: u64) {
+        let key = (instrument_id.to_string(), OrderId::from_u64(order_id));
+        self.order_done(instrument_id, key.1);
+        let exposure = self
+            .exposures
+            .entry(participant_id.to_string())
+            .or_default();
+        exposure.open_orders += 1;
+        exposure.open_notional = exposure.open_notional.saturating_add(notional);
+        self.orders.insert(
+            key,
+            Tracked {
+                participant_id: participant_id.to_string(),
+                order_id,
+                notional,
+            },
+        );
+    }
+
+    /// Counts an order again as it now rests, after it filled or was
+    /// modified or cancelled; an order no longer resting is forgotten
+    pub fn refresh(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        instrument_id: &str,
+        order_id: OrderId,
+    ) {
+        let Some(book) = manager.get_book(instrument_id) else {
+            return;
+        };
+        let Some(tracked) = self.orders.get_mut(&(instrument_id.to_string(), order_id)) else {
+            return;
+        };
+        let Some(order) = book.get_order(order_id) else {
+            self.order_done(instrument_id, order_id);
+            return;
+        };
+        let notional = notional(&order);
+        let exposure = self
+            .exposures
+            .entry(tracked.participant_id.clone())
+            .or_default();
+        exposure.open_notional = exposure
+            .open_notional
+            .saturating_sub(tracked.notional)
+            .saturating_add(notional);
+        tracked.notional = notional;
+    }
+
+    /// Forgets an order that left the book
+    pub fn order_done(&mut self, instrument_id: &str, order_id: OrderId) {
+        let Some(tracked) = self.orders.remove(&(instrument_id.to_string(), order_id)) else {
+            return;
+        };
+        if let Some(exposure) = self.exposures.get_mut(&tracked.participant_id) {
+            exposure.open_orders -= 1;
+            exposure.open_notional = exposure.open_notional.saturating_sub(tracked.notional);
+            if exposure.open_orders == 0 {
+                self.exposures.remove(&tracked.participant_id);
+            }
+        }
+    }
+
+    /// Forgets the orders of a deleted instrument
+    pub fn forget(&mut self, instrument_id: &str) {
+        let gone: Vec<OrderId> = self
+            .orders
+            .keys()
+            .filter(|(instrument, _)| instrument == instrument_id)
+            .map(|(_, order_id)| *order_id)
+            .collect();
+        for order_id in gone {
+            self.order_done(instrument_id, order_id);
+        }
+    }
+
+    /// Replaces the limits of a participant, or the default ones without one
+    pub fn set_limits(&mut self, participant_id: Option<String>, limits: ParticipantLimits) {
+        info!(
+            "Open order limits of {} set to {:?}",
+            participant_id
+                .as_deref()
+                .unwrap_or("every other participant"),
+            limits
+        );
+        match participant_id {
+            Some(participant_id) => {
+                self.config.participants.insert(participant_id, limits);
+            }
+            None => self.config.default = limits,
+        }
+    }
+
+    /// Orders of a participant still resting and their notional
+    pub fn exposure(&self, participant_id: &str) -> Exposure {
+        self.exposures
+            .get(participant_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Rejects a new order that would take its participant past its limits
+    pub fn admit(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        cmd: &EngineCommand,
+    ) -> Result<(), Rejection> {
+        match cmd {
+            EngineCommand::OrderCreate(order) => self.admit_order(manager, order, None),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects an order that would take its participant past its limits, not
+    /// counting the resting order it `replaces` on the same instrument
+    pub fn admit_order(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        order: &OrderCreatePayload,
+        replaces: Option<u64>,
+    ) -> Result<(), Rejection> {
+        let Some(participant_id) = &order.participant_id else {
+            return Ok(());
+        };
+        if order.liquidation
+            || order.time_in_force.is_immediate()
+            || order.order_type == OrderType::MARKET
+        {
+            return Ok(());
+        }
+        let limits = self.config.limits(participant_id);
+        if limits == ParticipantLimits::default() {
+            return Ok(());
+        }
+        let mut exposure = self.exposure(participant_id);
+        if let Some(replaced) = replaces.and_then(|order_id| {
+            self.orders
+                .get(&(order.instrument_id.clone(), OrderId::from_u64(order_id)))
+                .filter(|tracked| tracked.participant_id == *participant_id)
+        }) {
+            exposure.open_orders -= 1;
+            exposure.open_notional = exposure.open_notional.saturating_sub(replaced.notional);
+        }
+        if limits
+            .max_open_orders
+            .is_some_and(|max| exposure.open_orders >= max)
+        {
+            warn!(
+                "Rejecting order {} of {}: {} orders open already",
+                order.order_id, participant_id, exposure.open_orders
+            );
+            return Err(Rejection::new(
+                RejectReason::RiskLimitExceeded,
+                format!("{} orders open already", exposure.open_orders),
+            ));
+        }
+        let price = manager
+            .get_book(&order.instrument_id)
+            .and_then(|book| price_limit(book, order))
+            .unwrap_or(order.price);
+        let notional = exposure
+            .open_notional
+            .saturating_add(price.saturating_mul(order.quantity));
+        if let Some(max) = limits.max_open_notional
+            && notional > max
+        {
+            warn!(
+                "Rejecting order {} of {}: open notional would be {}, over {}",
+                order.order_id, participant_id, notional, max
+            );
+            return Err(Rejection::new(
+                RejectReason::RiskLimitExceeded,
+                format!("open notional would be {notional}, over {max}"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Every order registered, for a journal snapshot
+    pub fn entries(&self) -> Vec<OpenOrder> {
+        self.orders
+            .iter()
+            .map(|((instrument_id, _), tracked)| OpenOrder {
+                participant_id: tracked.participant_id.clone(),
+                instrument_id: instrument_id.clone(),
+                order_id: tracked.order_id,
+                notional: tracked.notional,
+            })
+            .collect()
+    }
+
+    /// Registers the orders of a journal snapshot
+    pub fn restore(&mut self, entries: Vec<OpenOrder>) {
+        for entry in entries {
+            self.track(
+                &entry.participant_id,
+                &entry.instrument_id,
+                entry.order_id,
+                entry.notional,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{
+        OrderCancelPayload, OrderModifyPayload, handle_order_cancel, handle_order_create,
+        handle_order_modify,
+    };
+    use pricelevel::{Side, TimeInForce};
+
+    fn order(order_id: u64, price: u64, quantity: u64) -> OrderCreatePayload {
+        OrderCreatePayload {
+            order_id,
+            instrument_id: "BTC".to_string(),
+            quantity,
+            price,
+            side: Side::Buy,
+            time_in_force: TimeInForce::Gtc,
+            order_type: OrderType::LIMIT,
+            participant_id: Some("desk-1".to_string()),
+            oms_id: None,
+            client_order_id: None,
+            tags: OrderTags::new(),
+            visible_quantity: None,
+            hidden_quantity: None,
+            liquidation: false,
+            min_quantity: None,
+            all_or_none: false,
+            sweep_limit: None,
+            sequence: None,
+        }
+    }
+
+    #[test]
+    fn test_orders_past_the_open_limits_are_rejected() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        let mut risk = ParticipantRisk::new(RiskConfig::default());
+        risk.set_limits(
+            Some("desk-1".to_string()),
+            ParticipantLimits {
+                max_open_orders: Some(2),
+                max_open_notional: Some(2_000),
+            },
+        );
+        let mut enter = |risk: &mut ParticipantRisk, order: OrderCreatePayload| {
+            risk.admit(&manager, &EngineCommand::OrderCreate(order.clone()))?;
+            let order_id = order.order_id;
+            handle_order_create(&mut manager, order)?;
+            risk.register(&manager, "desk-1", "BTC", order_id);
+            Ok::<_, Rejection>(())
+        };
+
+        enter(&mut risk, order(1, 100, 10)).unwrap();
+        let rejection = enter(&mut risk, order(2, 100, 11)).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::RiskLimitExceeded);
+        enter(&mut risk, order(2, 100, 5)).unwrap();
+        let rejection = enter(&mut risk, order(3, 1, 1)).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::RiskLimitExceeded);
+        // Orders that do not rest are not limited
+        let ioc = OrderCreatePayload {
+            time_in_force: TimeInForce::Ioc,
+            ..order(3, 1, 1)
+        };
+        enter(&mut risk, ioc).unwrap();
+
+        // Remaining quantity counts, and an order that left the book no longer
+        handle_order_modify(
+            &mut manager,
+            OrderModifyPayload {
+                instrument_id: "BTC".to_string(),
+                order_id: 1,
+                price: 100,
+                quantity: 1,
+                client_order_id: None,
+                participant_id: None,
+                sequence: None,
+            },
+        )
+        .unwrap();
+        risk.refresh(&manager, "BTC", OrderId::from_u64(1));
+        handle_order_cancel(
+            &mut manager,
+            OrderCancelPayload {
+                order_id: 2,
+                instrument_id: "BTC".to_string(),
+                client_order_id: None,
+                participant_id: None,
+                sequence: None,
+            },
+        )
+        .unwrap();
+        risk.refresh(&manager, "BTC", OrderId::from_u64(2));
+        assert_eq!(
+            risk.exposure("desk-1"),
+            Exposure {
+                open_orders: 1,
+                open_notional: 100,
+            }
+        );
+        assert_eq!(risk.entries().len(), 1);
+        // Orders of a book evicted to disk keep counting as last seen
+        manager.remove_book("BTC").unwrap();
+        risk.refresh(&manager, "BTC", OrderId::from_u64(1));
+        assert_eq!(risk.exposure("desk-1").open_orders, 1);
+        // A replacement does not count the order it replaces
+        let replacement = order(5, 100, 20);
+        assert!(risk.admit_order(&manager, &replacement, None).is_err());
+        risk.admit_order(&manager, &replacement, Some(1)).unwrap();
+        risk.set_limits(None, ParticipantLimits::default());
+        assert!(
+            risk.admit(
+                &manager,
+                &EngineCommand::OrderCreate(OrderCreatePayload {
+                    participant_id: Some("desk-2".to_string()),
+                    ..order(4, 100, 1_000)
+                })
+            )
+            .is_ok()
+        );
+    }
+}
@@ -0,0 +1,1835 @@
+// src/schema.rs
+use crate::alerts::ALERTS_TOPIC;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+/// Whether the engine consumes or produces a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// JSON Schema of one message the engine consumes or produces
+#[derive(Debug, Serialize)]
+pub struct MessageSchema {
+    /// Rust type the message is read into or written from
+    pub name: &'static str,
+    /// Topic the message travels on; topics set in configuration name the setting
+    pub topic: &'static str,
+    pub direction: Direction,
+    pub schema: Value,
+}
+
+/// Schema document printed by the `schema` subcommand
+///
+/// Schemas are written by hand next to the types they describe; the tests check
+/// them against the serde shapes of those types, so a change to a payload or
+/// event that is not reflected here fails the build.
+pub fn document() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "orderbook-rust messages",
+        "version": env!("CARGO_PKG_VERSION"),
+        "messages": messages(),
+    })
+}
+
+pub fn messages() -> Vec<MessageSchema> {
+    let mut messages = inbound();
+    messages.extend(outbound());
+    messages
+}
+
+fn inbound() -> Vec<MessageSchema> {
+    let message = |topic, name, schema| MessageSchema {
+        name,
+        topic,
+        direction: Direction::Inbound,
+        schema,
+    };
+    vec![
+        message(
+            "instrument.create",
+            "InstrumentCreatePayload",
+            object(
+                &[("instrument_id", string())],
+                &[
+                    ("flight_recorder_capacity", uint()),
+                    ("trade_tape_capacity", uint()),
+                    ("impact_model", impact_model()),
+                    ("block_trade_rules", block_trade_rules()),
+                    ("mark_pricing", mark_pricing()),
+                    (
+                        "matching_policy",
+                        string_enum(&["fifo", "pro_rata", "size_time"]),
+                    ),
+                    (
+                        "peg_repricing",
+                        object(&[], &[("min_interval_ms", uint()), ("min_move", uint())]),
+                    ),
+                    ("market_protection", market_protection()),
+                    ("min_fill_notional", uint()),
+                    ("sweep_limit", sweep_limit()),
+                    ("expiry", instrument_expiry()),
+                    ("scale", instrument_scale()),
+                    ("price_band", price_band()),
+                    ("perpetual", object(&[("index_id", string())], &[])),
+                    ("tick_size", uint()),
+                    ("lot_size", uint()),
+                    ("min_quantity", uint()),
+                    ("max_quantity", uint()),
+                    ("sequence", uint()),
+                ],
+            ),
+        ),
+        message(
+            "instrument.delete",
+            "DeleteInstrumentPayload",
+            object(&[("instrument_id", string())], &[("sequence", uint())]),
+        ),
+        message("instrument.halt", "TradingHaltPayload", trading_halt()),
+        message("instrument.resume", "TradingHaltPayload", trading_halt()),
+        message(
+            "instrument.auction_start",
+            "AuctionPayload",
+            object(&[("instrument_id", string())], &[("sequence", uint())]),
+        ),
+        message(
+            "instrument.auction_uncross",
+            "AuctionPayload",
+            object(&[("instrument_id", string())], &[("sequence", uint())]),
+        ),
+        message(
+            "instrument.adjust",
+            "InstrumentAdjustPayload",
+            object(
+                &[("instrument_id", string())],
+                &[
+                    ("action_id", string()),
+                    ("ratio", number()),
+                    ("cash_adjustment", int()),
+                    ("sequence", uint()),
+                ],
+            ),
+        ),
+        message(
+            "order.create",
+            "OrderCreatePayload",
+            object(
+                &[
+                    ("order_id", uint()),
+                    ("instrument_id", string()),
+                    ("quantity", uint()),
+                    ("price", uint()),
+                    ("side", side()),
+                    ("time_in_force", time_in_force()),
+                    (
+                        "order_type",
+                        string_enum(&["MARKET", "LIMIT", "ICEBERG", "MARKET_TO_LIMIT"]),
+                    ),
+                ],
+                &[
+                    ("participant_id", string()),
+                    ("oms_id", string()),
+                    ("client_order_id", string()),
+                    ("tags", tags()),
+                    ("visible_quantity", uint()),
+                    ("hidden_quantity", uint()),
+                    ("liquidation", boolean()),
+                    ("min_quantity", uint()),
+                    ("all_or_none", boolean()),
+                    ("sweep_limit", sweep_limit()),
+                    ("sequence", uint()),
+                ],
+            ),
+        ),
+        message(
+            "order.cancelled",
+            "OrderCancelPayload",
+            object(
+                &[("instrument_id", string())],
+                &[
+                    ("order_id", uint()),
+                    ("client_order_id", string()),
+                    ("participant_id", string()),
+                    ("sequence", uint()),
+                ],
+            ),
+        ),
+        message(
+            "order.modify",
+            "OrderModifyPayload",
+            object(
+                &[
+                    ("instrument_id", string()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                ],
+                &[
+                    ("order_id", uint()),
+                    ("client_order_id", string()),
+                    ("participant_id", string()),
+                    ("sequence", uint()),
+                ],
+            ),
+        ),
+        message(
+            "order.replace",
+            "OrderReplacePayload",
+            object(
+                &[
+                    ("instrument_id", string()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                ],
+                &[
+                    ("order_id", uint()),
+                    ("client_order_id", string()),
+                    ("participant_id", string()),
+                    ("new_client_order_id", string()),
+                    ("side", side()),
+                    ("time_in_force", time_in_force()),
+                    ("sequence", uint()),
+                ],
+            ),
+        ),
+        message(
+            "order.mass_cancel",
+            "MassCancelPayload",
+            mass_cancel_filters(&[]),
+        ),
+        message(
+            "order.kill_switch",
+            "KillSwitchPayload",
+            object(
+                &[("participant_id", string())],
+                &[("action", string_enum(&["engage", "release"]))],
+            ),
+        ),
+        message("engine.admin", "AdminCommandPayload", admin_command()),
+        message(
+            "price.theoretical",
+            "TheoreticalPricePayload",
+            object(
+                &[("instrument_id", string()), ("price", number())],
+                &[("alert_threshold_bps", number()), ("sequence", uint())],
+            ),
+        ),
+        message(
+            "index.define",
+            "IndexDefinePayload",
+            object(
+                &[
+                    ("index_id", string()),
+                    (
+                        "constituents",
+                        array(object(
+                            &[("instrument_id", string()), ("weight", number())],
+                            &[],
+                        )),
+                    ),
+                ],
+                &[
+                    ("divisor", number()),
+                    ("pegged_instruments", array(string())),
+                ],
+            ),
+        ),
+        message(
+            "rfq.request",
+            "RfqRequestPayload",
+            object(
+                &[
+                    ("rfq_id", string()),
+                    ("instrument_id", string()),
+                    ("requester_id", string()),
+                    ("side", side()),
+                    ("quantity", uint()),
+                ],
+                &[
+                    ("window_ms", uint()),
+                    ("auto_execute", boolean()),
+                    ("sequence", uint()),
+                ],
+            ),
+        ),
+        message(
+            "rfq.quote",
+            "RfqQuotePayload",
+            object(
+                &[
+                    ("rfq_id", string()),
+                    ("quote_id", string()),
+                    ("dealer_id", string()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                ],
+                &[],
+            ),
+        ),
+        message(
+            "rfq.execute",
+            "RfqExecutePayload",
+            object(
+                &[("rfq_id", string()), ("requester_id", string())],
+                &[("quote_id", string())],
+            ),
+        ),
+        message(
+            "trade.block",
+            "BlockTradePayload",
+            object(
+                &[
+                    ("trade_id", string()),
+                    ("instrument_id", string()),
+                    ("buyer_id", string()),
+                    ("seller_id", string()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                ],
+                &[("sequence", uint())],
+            ),
+        ),
+        message(
+            "oms.heartbeat",
+            "OmsHeartbeatPayload",
+            object(
+                &[("oms_id", string())],
+                &[("status", string_enum(&["alive", "logout", "disconnect"]))],
+            ),
+        ),
+    ]
+}
+
+fn outbound() -> Vec<MessageSchema> {
+    let message = |topic, name, schema| MessageSchema {
+        name,
+        topic,
+        direction: Direction::Outbound,
+        schema,
+    };
+    vec![
+        message("feeds[].depth_topic", "ScaledDepth", scaled_depth()),
+        message("feeds[].trades_topic", "TradePrint", trade_print()),
+        message("trades.trades_topic", "TradeExecution", trade_execution()),
+        message("trades.tape_topic", "TapePrint", tape_print()),
+        message("sinks[].topic", "SinkEvent", sink_event()),
+        message(
+            "kafka.topics.dead_letter",
+            "DeadLetter",
+            closed(object(
+                &[
+                    ("topic", string()),
+                    ("error", string()),
+                    ("payload", string()),
+                    ("timestamp", uint()),
+                ],
+                &[("payload_encoding", string_enum(&["hex"]))],
+            )),
+        ),
+        message(
+            "kafka.topics.consumption",
+            "ConsumptionState",
+            closed(object(
+                &[
+                    ("timestamp", uint()),
+                    (
+                        "paused",
+                        array(closed(object(
+                            &[("topic", string()), ("partitions", array(int()))],
+                            &[],
+                        ))),
+                    ),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            "funding.premium_topic",
+            "PremiumSample",
+            closed(object(
+                &[
+                    ("instrument_id", string()),
+                    ("index_id", string()),
+                    ("mark_price", number()),
+                    ("index_price", number()),
+                    ("premium", number()),
+                    ("premium_index", number()),
+                    ("estimated_funding_rate", number()),
+                    ("next_funding_at", uint()),
+                    ("timestamp", uint()),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            "funding.funding_topic",
+            "FundingRate",
+            closed(object(
+                &[
+                    ("instrument_id", string()),
+                    ("index_id", string()),
+                    ("funding_rate", number()),
+                    ("premium_index", number()),
+                    ("interest_rate", number()),
+                    ("samples", uint()),
+                    ("funding_at", uint()),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            "depth_deltas.topic_prefix + instrument_id",
+            "L2Update",
+            closed(object(
+                &[
+                    ("instrument_id", string()),
+                    ("sequence", uint()),
+                    ("kind", string_enum(&["delta", "snapshot"])),
+                    ("timestamp", uint()),
+                    ("bids", array(l2_level())),
+                    ("asks", array(l2_level())),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            "bbo_state.topic",
+            "BboState",
+            closed(object(
+                &[
+                    ("instrument_id", string()),
+                    ("best_bid", nullable(uint())),
+                    ("bid_quantity", uint()),
+                    ("best_ask", nullable(uint())),
+                    ("ask_quantity", uint()),
+                    ("mid_price", nullable(number())),
+                    ("spread_bps", nullable(number())),
+                    ("last_trade_price", nullable(uint())),
+                    ("mark_price", nullable(number())),
+                    ("mark_source", nullable(mark_source())),
+                    ("timestamp", uint()),
+                ],
+                &[],
+            )),
+        ),
+        message("mbo.topic", "MboMessage", mbo_message()),
+        message(
+            "bbo_state.events_topic",
+            "BboEvent",
+            closed(object(
+                &[
+                    ("instrument_id", string()),
+                    ("best_bid", nullable(uint())),
+                    ("bid_quantity", uint()),
+                    ("best_ask", nullable(uint())),
+                    ("ask_quantity", uint()),
+                    ("timestamp", uint()),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            "mass_cancel.summary_topic",
+            "MassCancelSummary",
+            closed(mass_cancel_filters(&[
+                ("orders_cancelled", uint()),
+                ("quantity_cancelled", uint()),
+                (
+                    "instruments",
+                    json!({ "type": "object", "additionalProperties": uint() }),
+                ),
+                ("timestamp", uint()),
+            ])),
+        ),
+        message(
+            "order_responses.ack_topic",
+            "OrderAck",
+            closed(object(&order_request(&[]), &order_request_optional())),
+        ),
+        message(
+            "order_responses.reject_topic",
+            "OrderReject",
+            closed(object(
+                &order_request(&[("reason", reject_reason()), ("message", string())]),
+                &order_request_optional(),
+            )),
+        ),
+        message(
+            "liveness.heartbeat_topic",
+            "EngineHeartbeat",
+            closed(object(
+                &[
+                    ("sequence", uint()),
+                    ("timestamp", uint()),
+                    ("instruments", uint()),
+                    ("live_oms", array(string())),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            "dry_run.shadow_topic",
+            "DryRunResult",
+            closed(object(
+                &[
+                    ("command", string()),
+                    ("accepted", boolean()),
+                    ("would_fill", uint()),
+                    ("would_rest", uint()),
+                    ("timestamp", uint()),
+                ],
+                &[
+                    ("instrument_id", string()),
+                    ("order_id", uint()),
+                    ("reason", reject_reason()),
+                    ("message", string()),
+                ],
+            )),
+        ),
+        message(
+            "order_to_trade.throttle_topic",
+            "ThrottleState",
+            closed(object(
+                &[
+                    ("participant_id", string()),
+                    ("instrument_id", string()),
+                    ("throttled", boolean()),
+                    ("until_ts", uint()),
+                    ("reason", string_enum(&["order_to_trade"])),
+                    ("timestamp", uint()),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            "execution_quality.metrics_topic",
+            "ExecutionQualityReport",
+            execution_quality_report(),
+        ),
+        message(
+            "verification.topic",
+            "StateHash",
+            closed(object(
+                &[
+                    ("instrument_id", string()),
+                    ("command_index", uint()),
+                    ("hash", string()),
+                    ("timestamp", uint()),
+                ],
+                &[],
+            )),
+        ),
+        message(
+            ALERTS_TOPIC,
+            "Alert",
+            closed(object(
+                &[
+                    (
+                        "kind",
+                        string_enum(&[
+                            "invariant_violation",
+                            "fair_value_deviation",
+                            "replay_divergence",
+                            "engine_panic",
+                            "processing_stall",
+                            "oms_heartbeat_lost",
+                            "sequence_gap",
+                        ]),
+                    ),
+                    ("instrument_id", string()),
+                    ("message", string()),
+                    ("timestamp", uint()),
+                    ("details", json!({})),
+                ],
+                &[],
+            )),
+        ),
+    ]
+}
+
+/// Required properties of an ack or reject, followed by `extra`
+fn order_request(extra: &[(&'static str, Value)]) -> Vec<(&'static str, Value)> {
+    let mut properties = vec![
+        ("instrument_id", string()),
+        ("order_id", uint()),
+        (
+            "action",
+            string_enum(&["create", "modify", "cancel", "replace"]),
+        ),
+        ("timestamp", uint()),
+    ];
+    properties.extend(extra.iter().cloned());
+    properties
+}
+
+/// What a mass cancel selects, besides the `required` fields
+fn mass_cancel_filters(required: &[(&str, Value)]) -> Value {
+    object(
+        required,
+        &[
+            ("instrument_id", string()),
+            ("side", side()),
+            ("participant_id", string()),
+            ("sequence", uint()),
+        ],
+    )
+}
+
+fn order_request_optional() -> [(&'static str, Value); 2] {
+    [("client_order_id", string()), ("participant_id", string())]
+}
+
+fn admin_command() -> Value {
+    let command = |name: &str, fields: &[(&str, Value)]| {
+        let mut required = vec![("command", json!({ "const": name }))];
+        required.extend(fields.iter().cloned());
+        object(&required, &[])
+    };
+    let pair = [("instrument_a", string()), ("instrument_b", string())];
+    let commands = vec![
+        command(
+            "enable_flight_recorder",
+            &[("instrument_id", string()), ("capacity", uint())],
+        ),
+        command("disable_flight_recorder", &[("instrument_id", string())]),
+        command("dump_flight_events", &[("instrument_id", string())]),
+        object(
+            &[
+                ("command", json!({ "const": "get_trades" })),
+                ("instrument_id", string()),
+            ],
+            &[("since_sequence", uint())],
+        ),
+        command("add_correlation_pair", &pair),
+        command("remove_correlation_pair", &pair),
+        command("get_correlations", &[]),
+        command("run_clearing_export", &[]),
+        command(
+            "book_as_of",
+            &[("instrument_id", string()), ("timestamp", timestamp())],
+        ),
+        command("get_archive_stats", &[]),
+        command("get_global_stats", &[]),
+        object(
+            &[
+                ("command", json!({ "const": "set_feature" })),
+                (
+                    "feature",
+                    string_enum(&["market_protection", "min_fill_notional"]),
+                ),
+                ("enabled", boolean()),
+            ],
+            &[("instrument_id", string())],
+        ),
+        command("reset_features", &[("instrument_id", string())]),
+        command("get_features", &[]),
+        command(
+            "set_sink_filter",
+            &[
+                ("sink", string()),
+                (
+                    "filter",
+                    object(
+                        &[],
+                        &[
+                            (
+                                "events",
+                                array(string_enum(&["trade", "delta", "snapshot", "order"])),
+                            ),
+                            ("instruments", array(string())),
+                            ("instrument_groups", array(string())),
+                            ("participants", array(string())),
+                        ],
+                    ),
+                ),
+            ],
+        ),
+        command("get_sink_filters", &[]),
+        object(
+            &[("command", json!({ "const": "set_participant_limits" }))],
+            &[
+                ("participant_id", string()),
+                ("max_open_orders", uint()),
+                ("max_open_notional", uint()),
+            ],
+        ),
+        command(
+            "move_pegged_orders",
+            &[
+                ("instrument_id", string()),
+                (
+                    "moves",
+                    array(object(
+                        &[
+                            ("order_id", uuid()),
+                            ("side", side()),
+                            ("from", uint()),
+                            ("to", uint()),
+                        ],
+                        &[],
+                    )),
+                ),
+            ],
+        ),
+    ];
+    let consumption = |name: &str| {
+        object(
+            &[
+                ("command", json!({ "const": name })),
+                ("topics", array(string())),
+            ],
+            &[("partitions", array(int()))],
+        )
+    };
+    let commands = [
+        commands,
+        vec![
+            consumption("pause_consumption"),
+            consumption("resume_consumption"),
+        ],
+    ]
+    .concat();
+    // Fault injection is only accepted by chaos builds
+    #[cfg(feature = "chaos")]
+    let commands = [
+        commands,
+        vec![
+            object(
+                &[("command", json!({ "const": "inject_faults" }))],
+                &[
+                    ("drop_probability", number()),
+                    ("duplicate_probability", number()),
+                    ("delay_probability", number()),
+                    ("delay_ms", uint()),
+                    ("sink_failure_probability", number()),
+                    ("clock_skew_ms", int()),
+                    ("seed", uint()),
+                ],
+            ),
+            command("clear_faults", &[]),
+        ],
+    ]
+    .concat();
+    json!({ "oneOf": commands })
+}
+
+fn mbo_message() -> Value {
+    let order = closed(object(
+        &[
+            ("order_id", string()),
+            ("side", side()),
+            ("price", uint()),
+            ("quantity", uint()),
+            ("priority", uint()),
+        ],
+        &[],
+    ));
+    json!({
+        "oneOf": [
+            closed(object(
+                &[
+                    ("type", json!({ "const": "order" })),
+                    ("instrument_id", string()),
+                    ("sequence", uint()),
+                    ("timestamp", uint()),
+                    (
+                        "action",
+                        string_enum(&["added", "modified", "deleted", "executed"]),
+                    ),
+                    ("order_id", string()),
+                    ("side", side()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                    ("priority", uint()),
+                ],
+                &[("executed", uint())],
+            )),
+            closed(object(
+                &[
+                    ("type", json!({ "const": "snapshot" })),
+                    ("instrument_id", string()),
+                    ("sequence", uint()),
+                    ("timestamp", uint()),
+                    ("orders", array(order)),
+                ],
+                &[],
+            )),
+        ]
+    })
+}
+
+fn sink_event() -> Value {
+    let event = |name: &str, fields: &[(&str, Value)], optional: &[(&str, Value)]| {
+        let mut required = vec![("event", json!({ "const": name }))];
+        required.extend(fields.iter().cloned());
+        closed(object(&required, optional))
+    };
+    json!({
+        "oneOf": [
+            event(
+                "trade",
+                &[
+                    ("instrument_id", string()),
+                    ("trade_id", uuid()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                    ("aggressor_side", side()),
+                    ("taker_order_id", string()),
+                    ("maker_order_id", string()),
+                    ("timestamp", uint()),
+                    ("liquidation", boolean()),
+                ],
+                &[
+                    ("taker_participant_id", string()),
+                    ("maker_participant_id", string()),
+                    ("book_context", book_context(uint(), uint())),
+                    ("taker_tags", tags()),
+                    ("maker_tags", tags()),
+                ],
+            ),
+            event(
+                "delta",
+                &[
+                    ("instrument_id", string()),
+                    ("timestamp", uint()),
+                    ("side", side()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                ],
+                &[("replenished", uint())],
+            ),
+            event(
+                "snapshot",
+                &[
+                    ("symbol", string()),
+                    ("timestamp", uint()),
+                    ("bids", array(price_level())),
+                    ("asks", array(price_level())),
+                ],
+                &[(
+                    "extra_fields",
+                    array(closed(object(
+                        &[("order_id", string()), ("fields", tags())],
+                        &[],
+                    ))),
+                )],
+            ),
+            event(
+                "order",
+                &[
+                    ("instrument_id", string()),
+                    ("order_id", uint()),
+                    ("action", string_enum(&["create", "modify", "cancel", "replace"])),
+                    ("timestamp", uint()),
+                ],
+                &[
+                    ("side", side()),
+                    ("price", uint()),
+                    ("quantity", uint()),
+                    ("participant_id", string()),
+                    ("tags", tags()),
+                ],
+            ),
+            event(
+                "execution",
+                &[
+                    ("instrument_id", string()),
+                    ("order_id", string()),
+                    ("side", side()),
+                    ("lifetime_us", uint()),
+                    ("levels_swept", uint()),
+                    ("filled_quantity", uint()),
+                    ("average_price", number()),
+                    ("fully_filled", boolean()),
+                    ("timestamp", uint()),
+                ],
+                &[
+                    ("participant_id", string()),
+                    ("arrival_mid", number()),
+                    ("slippage_bps", number()),
+                ],
+            ),
+        ]
+    })
+}
+
+fn execution_quality_report() -> Value {
+    let distribution = closed(object(
+        &[
+            ("count", uint()),
+            ("mean", number()),
+            ("p50", number()),
+            ("p90", number()),
+            ("p99", number()),
+            ("max", number()),
+        ],
+        &[],
+    ));
+    closed(object(
+        &[
+            ("instrument_id", string()),
+            ("since", uint()),
+            ("timestamp", uint()),
+            ("lifetime_us", distribution.clone()),
+            ("levels_swept", distribution.clone()),
+        ],
+        &[("slippage_bps", distribution)],
+    ))
+}
+
+fn scaled_depth() -> Value {
+    let level = closed(object(
+        &[
+            ("price", scaled_value()),
+            ("quantity", scaled_value()),
+            ("order_count", uint()),
+        ],
+        &[],
+    ));
+    closed(object(
+        &[
+            ("symbol", string()),
+            ("timestamp", uint()),
+            ("bids", array(level.clone())),
+            ("asks", array(level)),
+        ],
+        &[],
+    ))
+}
+
+fn trade_print() -> Value {
+    closed(object(
+        &[
+            ("instrument_id", string()),
+            ("trade_id", uuid()),
+            ("price", scaled_value()),
+            ("quantity", scaled_value()),
+            ("aggressor_side", side()),
+            ("timestamp", uint()),
+        ],
+        &[("taker_order_id", string()), ("maker_order_id", string())],
+    ))
+}
+
+/// A fill on a book as published on the trade tape
+fn tape_print() -> Value {
+    closed(object(
+        &[
+            ("instrument_id", string()),
+            ("sequence", uint()),
+            ("trade_id", uuid()),
+            ("price", uint()),
+            ("quantity", uint()),
+            ("aggressor_side", side()),
+            ("taker_order_id", string()),
+            ("maker_order_id", string()),
+            ("timestamp", uint()),
+        ],
+        &[],
+    ))
+}
+
+/// A match on a book, published with the JSON trade serializer
+fn trade_execution() -> Value {
+    let fill = closed(object(
+        &[
+            ("trade_id", uuid()),
+            ("price", scaled_value()),
+            ("quantity", scaled_value()),
+            ("aggressor_side", side()),
+            ("maker_order_id", string()),
+            ("timestamp", uint()),
+        ],
+        &[("maker_tags", tags())],
+    ));
+    closed(object(
+        &[
+            ("instrument_id", string()),
+            ("order_id", string()),
+            ("executed_quantity", scaled_value()),
+            ("remaining_quantity", scaled_value()),
+            ("is_complete", boolean()),
+            ("fills", array(fill)),
+            ("timestamp", uint()),
+            ("off_book", json!({ "const": false })),
+            ("liquidation", boolean()),
+        ],
+        &[
+            ("book_context", book_context(scaled_value(), scaled_value())),
+            ("tags", tags()),
+        ],
+    ))
+}
+
+/// The touch of a book before a match, in raw or scaled units
+fn book_context(price: Value, quantity: Value) -> Value {
+    closed(object(
+        &[
+            ("best_bid", nullable(price.clone())),
+            ("best_ask", nullable(price.clone())),
+            ("spread", nullable(price)),
+            ("bid_depth_at_touch", quantity.clone()),
+            ("ask_depth_at_touch", quantity),
+        ],
+        &[],
+    ))
+}
+
+/// A level of a full snapshot; resting orders are listed as written by pricelevel
+fn price_level() -> Value {
+    closed(object(
+        &[
+            ("price", uint()),
+            ("visible_quantity", uint()),
+            ("hidden_quantity", uint()),
+            ("order_count", uint()),
+            ("orders", array(json!({ "type": "object" }))),
+        ],
+        &[],
+    ))
+}
+
+fn impact_model() -> Value {
+    object(
+        &[
+            ("kind", string_enum(&["linear", "square_root"])),
+            ("temporary_coefficient", number()),
+            ("permanent_coefficient", number()),
+            ("reference_volume", uint()),
+        ],
+        &[("latency_ms", uint()), ("volatility_bps", number())],
+    )
+}
+
+fn block_trade_rules() -> Value {
+    object(
+        &[],
+        &[("min_quantity", uint()), ("max_deviation_bps", number())],
+    )
+}
+
+fn mark_source() -> Value {
+    string_enum(&["mid", "last_trade", "previous_close", "external_reference"])
+}
+
+fn mark_pricing() -> Value {
+    object(
+        &[],
+        &[
+            ("sources", array(mark_source())),
+            ("previous_close", uint()),
+        ],
+    )
+}
+
+fn market_protection() -> Value {
+    object(&[("ticks", uint()), ("tick_size", uint())], &[])
+}
+
+fn sweep_limit() -> Value {
+    object(
+        &[],
+        &[
+            ("max_levels", uint()),
+            ("max_quantity", uint()),
+            ("remainder", string_enum(&["cancel", "requeue"])),
+        ],
+    )
+}
+
+fn price_band() -> Value {
+    let reference = || ("reference", string_enum(&["last", "index", "mark", "mid"]));
+    json!({
+        "oneOf": [
+            object(
+                &[
+                    ("kind", string_enum(&["ticks"])),
+                    ("ticks", uint()),
+                    ("tick_size", uint()),
+                ],
+                &[reference()],
+            ),
+            object(
+                &[("kind", string_enum(&["percent"])), ("percent", number())],
+                &[reference()],
+            ),
+        ]
+    })
+}
+
+fn trading_halt() -> Value {
+    object(
+        &[("instrument_id", string())],
+        &[("reason", string()), ("sequence", uint())],
+    )
+}
+
+fn instrument_expiry() -> Value {
+    object(&[("expires_at", timestamp())], &[("roll_to", string())])
+}
+
+fn instrument_scale() -> Value {
+    object(
+        &[],
+        &[("price_decimals", uint()), ("quantity_decimals", uint())],
+    )
+}
+
+fn reject_reason() -> Value {
+    string_enum(&[
+        "unknown_instrument",
+        "unknown_order",
+        "duplicate_client_order_id",
+        "invalid_order",
+        "insufficient_liquidity",
+        "trading_halted",
+        "below_minimum_notional",
+        "price_crossing",
+        "outside_price_band",
+        "invalid_tick_size",
+        "invalid_lot_size",
+        "quantity_out_of_range",
+        "throttled",
+        "risk_limit_exceeded",
+        "kill_switch_engaged",
+        "duplicate",
+        "out_of_sequence",
+        "internal",
+    ])
+}
+
+fn side() -> Value {
+    string_enum(&["BUY", "SELL"])
+}
+
+fn time_in_force() -> Value {
+    json!({
+        "oneOf": [
+            string_enum(&["GTC", "IOC", "FOK", "DAY"]),
+            object(&[("GTD", timestamp())], &[]),
+        ]
+    })
+}
+
+/// An inbound time: epoch seconds or milliseconds from 2000 on, or ISO-8601
+fn timestamp() -> Value {
+    json!({
+        "oneOf": [
+            { "type": "integer", "minimum": 946_684_800 },
+            { "type": "string", "format": "date-time" },
+        ]
+    })
+}
+
+fn l2_level() -> Value {
+    closed(object(&[("price", uint()), ("quantity", uint())], &[]))
+}
+
+/// A price or quantity: a raw integer, or a decimal string on decimal feeds
+fn scaled_value() -> Value {
+    json!({ "oneOf": [uint(), string()] })
+}
+
+fn object(required: &[(&str, Value)], optional: &[(&str, Value)]) -> Value {
+    let properties: Map<String, Value> = required
+        .iter()
+        .chain(optional)
+        .map(|(name, schema)| (name.to_string(), schema.clone()))
+        .collect();
+    let required: Vec<&str> = required.iter().map(|(name, _)| *name).collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// Marks an outbound object as having no other properties
+///
+/// Inbound objects are left open because unknown fields are ignored on decode.
+fn closed(mut schema: Value) -> Value {
+    schema["additionalProperties"] = Value::Bool(false);
+    schema
+}
+
+/// Free-form string key/value pairs
+fn tags() -> Value {
+    json!({ "type": "object", "additionalProperties": string() })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn string_enum(values: &[&str]) -> Value {
+    json!({ "type": "string", "enum": values })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn uuid() -> Value {
+    json!({ "type": "string", "format": "uuid" })
+}
+
+fn uint() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn int() -> Value {
+    json!({ "type": "integer" })
+}
+
+fn number() -> Value {
+    json!({ "type": "number" })
+}
+
+fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "oneOf": [schema, { "type": "null" }] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::{Alert, AlertKind};
+    use crate::bbo_state::{BboEvent, BboState};
+    use crate::consumption::{ConsumptionState, PausedTopic};
+    use crate::depth_deltas::{L2Level, L2Update, L2UpdateKind};
+    use crate::dry_run::DryRunResult;
+    use crate::execution_quality::{AggressorExecution, Distribution, ExecutionQualityReport};
+    use crate::feeds::TradePrint;
+    use crate::funding::{FundingRate, PremiumSample};
+    use crate::helpers::types::OmsHeartbeatPayload;
+    use crate::helpers::{
+        AdminCommandPayload, AuctionPayload, BlockTradePayload, DeleteInstrumentPayload,
+        IndexDefinePayload, InstrumentAdjustPayload, InstrumentCreatePayload, KillSwitchPayload,
+        MassCancelPayload, MassCancelSummary, OrderCancelPayload, OrderCreatePayload,
+        OrderModifyPayload, OrderReplacePayload, RfqExecutePayload, RfqQuotePayload,
+        RfqRequestPayload, TheoreticalPricePayload, TradingHaltPayload,
+    };
+    use crate::liveness::EngineHeartbeat;
+    use crate::mbo::{MboEvent, MboMessage, MboOrder, MboSnapshot};
+    use crate::order_responses::{OrderAck, OrderReject, OrderRequest, RejectReason, Rejection};
+    use crate::order_to_trade::{ThrottleReason, ThrottleState};
+    use crate::orderbook::book_change_event::OrderChange;
+    use crate::orderbook::snapshot::OrderBookSnapshot;
+    use crate::orderbook::trade::BookContext;
+    use crate::orderbook::{
+        InstrumentScale, MarkSource, NumberFormat, OrderExtraFields, ScaledDepth, TapeTrade,
+    };
+    use crate::publisher::DeadLetter;
+    use crate::sinks::{LevelChange, OrderAction, OrderEvent, SinkEvent, TradeRecord};
+    use crate::tags::OrderTags;
+    use crate::trade_producer::{ExecutionFill, ScaledBookContext, TapePrint, TradeExecution};
+    use crate::verification::StateHash;
+    use pricelevel::{OrderId, PriceLevelSnapshot, Side};
+    use serde::de::DeserializeOwned;
+    use uuid::Uuid;
+
+    fn decode<T: DeserializeOwned>(value: Value) -> Result<(), serde_json::Error> {
+        serde_json::from_value::<T>(value).map(|_| ())
+    }
+
+    fn parse(name: &str, value: Value) -> Result<(), serde_json::Error> {
+        match name {
+            "InstrumentCreatePayload" => decode::<InstrumentCreatePayload>(value),
+            "DeleteInstrumentPayload" => decode::<DeleteInstrumentPayload>(value),
+            "InstrumentAdjustPayload" => decode::<InstrumentAdjustPayload>(value),
+            "OrderCreatePayload" => decode::<OrderCreatePayload>(value),
+            "OrderCancelPayload" => decode::<OrderCancelPayload>(value),
+            "OrderModifyPayload" => decode::<OrderModifyPayload>(value),
+            "OrderReplacePayload" => decode::<OrderReplacePayload>(value),
+            "MassCancelPayload" => decode::<MassCancelPayload>(value),
+            "KillSwitchPayload" => decode::<KillSwitchPayload>(value),
+            "TradingHaltPayload" => decode::<TradingHaltPayload>(value),
+            "AuctionPayload" => decode::<AuctionPayload>(value),
+            "AdminCommandPayload" => decode::<AdminCommandPayload>(value),
+            "TheoreticalPricePayload" => decode::<TheoreticalPricePayload>(value),
+            "IndexDefinePayload" => decode::<IndexDefinePayload>(value),
+            "RfqRequestPayload" => decode::<RfqRequestPayload>(value),
+            "RfqQuotePayload" => decode::<RfqQuotePayload>(value),
+            "RfqExecutePayload" => decode::<RfqExecutePayload>(value),
+            "BlockTradePayload" => decode::<BlockTradePayload>(value),
+            "OmsHeartbeatPayload" => decode::<OmsHeartbeatPayload>(value),
+            _ => panic!("no decoder for {name}"),
+        }
+    }
+
+    /// Builds a value matching `schema`, with every optional property when `full`
+    fn sample(schema: &Value, full: bool) -> Value {
+        if let Some(value) = schema.get("const") {
+            return value.clone();
+        }
+        if let Some(values) = schema.get("enum") {
+            return values[0].clone();
+        }
+        if let Some(alternatives) = schema.get("oneOf") {
+            return sample(&alternatives[0], full);
+        }
+        match schema["type"].as_str() {
+            Some("object") => {
+                let required = required(schema);
+                let properties = schema["properties"]
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default();
+                Value::Object(
+                    properties
+                        .iter()
+                        .filter(|(name, _)| full || required.contains(name))
+                        .map(|(name, property)| (name.clone(), sample(property, full)))
+                        .collect(),
+                )
+            }
+            Some("array") => json!([sample(&schema["items"], full)]),
+            Some("string") if schema["format"] == "uuid" => json!(uuid::Uuid::nil()),
+            Some("string") => json!("x"),
+            Some("integer") => json!(schema["minimum"].as_u64().unwrap_or(0).max(1)),
+            Some("number") => json!(1.5),
+            Some("boolean") => json!(true),
+            _ => Value::Null,
+        }
+    }
+
+    fn required(schema: &Value) -> Vec<String> {
+        schema["required"]
+            .as_array()
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| name.as_str().unwrap().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Checks the subset of JSON Schema used above
+    fn validate(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        if let Some(expected) = schema.get("const") {
+            return (expected == value)
+                .then_some(())
+                .ok_or(format!("{path}: expected {expected}, got {value}"));
+        }
+        if let Some(alternatives) = schema.get("oneOf") {
+            let matching = alternatives
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|alternative| validate(alternative, value, path).is_ok())
+                .count();
+            return (matching == 1)
+                .then_some(())
+                .ok_or(format!("{path}: {matching} alternatives match {value}"));
+        }
+        if let Some(values) = schema.get("enum")
+            && !values.as_array().unwrap().contains(value)
+        {
+            return Err(format!("{path}: {value} is not one of {values}"));
+        }
+        let type_matches = match schema.get("type").and_then(Value::as_str) {
+            None => true,
+            Some("object") => value.is_object(),
+            Some("array") => value.is_array(),
+            Some("string") => value.is_string(),
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("number") => value.is_number(),
+            Some("boolean") => value.is_boolean(),
+            Some("null") => value.is_null(),
+            Some(other) => return Err(format!("{path}: unsupported type {other}")),
+        };
+        if !type_matches {
+            return Err(format!("{path}: {value} is not a {}", schema["type"]));
+        }
+        if schema.get("minimum").is_some() && value.as_i64().is_some_and(|n| n < 0) {
+            return Err(format!("{path}: {value} is negative"));
+        }
+        if let Some(items) = value.as_array() {
+            for (index, item) in items.iter().enumerate() {
+                validate(&schema["items"], item, &format!("{path}[{index}]"))?;
+            }
+        }
+        if let Some(fields) = value.as_object()
+            && let Some(properties) = schema.get("properties")
+        {
+            for name in required(schema) {
+                if !fields.contains_key(&name) {
+                    return Err(format!("{path}: missing {name}"));
+                }
+            }
+            for (name, field) in fields {
+                match properties.get(name) {
+                    Some(property) => validate(property, field, &format!("{path}.{name}"))?,
+                    None if schema["additionalProperties"] == json!(false) => {
+                        return Err(format!("{path}: unexpected {name}"));
+                    }
+                    None => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn schema_of(name: &str) -> Value {
+        messages()
+            .into_iter()
+            .find(|message| message.name == name)
+            .unwrap_or_else(|| panic!("no schema for {name}"))
+            .schema
+    }
+
+    fn alternatives(schema: &Value) -> Vec<Value> {
+        match schema.get("oneOf") {
+            Some(alternatives) => alternatives.as_array().unwrap().clone(),
+            None => vec![schema.clone()],
+        }
+    }
+
+    #[test]
+    fn test_inbound_schemas_match_payload_decoding() {
+        for message in messages()
+            .into_iter()
+            .filter(|message| message.direction == Direction::Inbound)
+        {
+            for schema in alternatives(&message.schema) {
+                for full in [true, false] {
+                    let value = sample(&schema, full);
+                    validate(&message.schema, &value, message.name).unwrap();
+                    if let Err(e) = parse(message.name, value.clone()) {
+                        panic!("{} rejects {}: {}", message.name, value, e);
+                    }
+                }
+                // Every field the schema calls required really is
+                let value = sample(&schema, false);
+                for name in required(&schema) {
+                    let mut missing = value.clone();
+                    missing.as_object_mut().unwrap().remove(&name);
+                    assert!(
+                        parse(message.name, missing).is_err(),
+                        "{} accepts a message without {}",
+                        message.name,
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_time_in_force_schema_covers_every_variant() {
+        for time_in_force in [
+            json!("GTC"),
+            json!("IOC"),
+            json!("FOK"),
+            json!("DAY"),
+            json!({"GTD": 1_700_000_000}),
+            json!({"GTD": "2023-11-14T22:13:20Z"}),
+        ] {
+            let mut order = sample(&schema_of("OrderCreatePayload"), false);
+            order["time_in_force"] = time_in_force;
+            validate(&schema_of("OrderCreatePayload"), &order, "order").unwrap();
+            parse("OrderCreatePayload", order).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_outbound_messages_match_their_schemas() {
+        let tags = OrderTags::from([("strategy".to_string(), "twap".to_string())]);
+        let mut level = PriceLevelSnapshot::new(100);
+        level.visible_quantity = 5;
+        level.order_count = 1;
+        let snapshot = OrderBookSnapshot {
+            symbol: "BTC".to_string(),
+            timestamp: 1,
+            bids: vec![level],
+            asks: Vec::new(),
+            extra_fields: vec![OrderExtraFields {
+                order_id: OrderId::from_u64(1),
+                fields: serde_json::to_value(&tags).unwrap(),
+            }],
+        };
+        let trade = TradeRecord {
+            instrument_id: "BTC".to_string(),
+            trade_id: Uuid::new_v4(),
+            price: 100,
+            quantity: 5,
+            aggressor_side: Side::Sell,
+            taker_order_id: OrderId::from_u64(2),
+            maker_order_id: OrderId::from_u64(1),
+            timestamp: 1,
+            taker_participant_id: Some("desk-1".to_string()),
+            maker_participant_id: None,
+            book_context: Some(BookContext {
+                best_bid: Some(100),
+                best_ask: Some(101),
+                spread: Some(1),
+                bid_depth_at_touch: 5,
+                ask_depth_at_touch: 7,
+            }),
+            taker_tags: tags.clone(),
+            maker_tags: OrderTags::new(),
+            liquidation: true,
+        };
+        let change = LevelChange {
+            instrument_id: "BTC".to_string(),
+            timestamp: 1,
+            side: Side::Buy,
+            price: 100,
+            quantity: 0,
+            replenished: None,
+        };
+        let order = |side: Option<Side>| OrderEvent {
+            instrument_id: "BTC".to_string(),
+            order_id: 1,
+            action: OrderAction::Create,
+            side,
+            price: side.map(|_| 100),
+            quantity: side.map(|_| 5),
+            timestamp: 1,
+            participant_id: side.map(|_| "desk-1".to_string()),
+            tags: side.map(|_| tags.clone()).unwrap_or_default(),
+        };
+        let (with_details, without_details) = (order(Some(Side::Buy)), order(None));
+        let execution = AggressorExecution {
+            instrument_id: "BTC".to_string(),
+            order_id: OrderId::from_u64(2),
+            side: Side::Sell,
+            participant_id: Some("desk-1".to_string()),
+            lifetime_us: 40,
+            levels_swept: 1,
+            filled_quantity: 5,
+            average_price: 100.0,
+            arrival_mid: Some(100.5),
+            slippage_bps: Some(49.75),
+            fully_filled: true,
+            timestamp: 1,
+        };
+        let events = [
+            SinkEvent::Trade(&trade),
+            SinkEvent::Delta(&change),
+            SinkEvent::Snapshot(&snapshot),
+            SinkEvent::Order(&with_details),
+            SinkEvent::Order(&without_details),
+            SinkEvent::Execution(&execution),
+        ];
+        for event in &events {
+            let value = serde_json::to_value(event).unwrap();
+            validate(&schema_of("SinkEvent"), &value, "event").unwrap();
+        }
+
+        let scale = InstrumentScale {
+            price_decimals: 2,
+            quantity_decimals: 0,
+        };
+        for format in [NumberFormat::Raw, NumberFormat::Decimal] {
+            let depth = ScaledDepth::from_snapshot(&snapshot, scale, format);
+            let value = serde_json::to_value(&depth).unwrap();
+            validate(&schema_of("ScaledDepth"), &value, "depth").unwrap();
+
+            let print = TradePrint {
+                instrument_id: "BTC",
+                trade_id: trade.trade_id,
+                price: scale.price(100, format),
+                quantity: scale.quantity(5, format),
+                aggressor_side: Side::Buy,
+                timestamp: 1,
+                taker_order_id: (format == NumberFormat::Raw).then(|| OrderId::from_u64(2)),
+                maker_order_id: None,
+            };
+            let value = serde_json::to_value(&print).unwrap();
+            validate(&schema_of("TradePrint"), &value, "trade").unwrap();
+
+            let execution = TradeExecution {
+                instrument_id: "BTC",
+                order_id: OrderId::from_u64(2),
+                executed_quantity: scale.quantity(5, format),
+                remaining_quantity: scale.quantity(0, format),
+                is_complete: true,
+                fills: vec![ExecutionFill {
+                    trade_id: trade.trade_id,
+                    price: scale.price(100, format),
+                    quantity: scale.quantity(5, format),
+                    aggressor_side: Side::Sell,
+                    maker_order_id: OrderId::from_u64(1),
+                    timestamp: 1,
+                    maker_tags: tags.clone(),
+                }],
+                timestamp: 1,
+                off_book: false,
+                book_context: Some(ScaledBookContext::new(
+                    &BookContext {
+                        best_bid: None,
+                        best_ask: Some(100),
+                        spread: None,
+                        bid_depth_at_touch: 0,
+                        ask_depth_at_touch: 5,
+                    },
+                    scale,
+                    format,
+                )),
+                tags: OrderTags::new(),
+                liquidation: false,
+            };
+            let value = serde_json::to_value(&execution).unwrap();
+            validate(&schema_of("TradeExecution"), &value, "execution").unwrap();
+
+            let tape = TapePrint {
+                instrument_id: "BTC",
+                trade: TapeTrade {
+                    sequence: 1,
+                    trade_id: trade.trade_id,
+                    price: 100,
+                    quantity: 5,
+                    aggressor_side: Side::Sell,
+                    taker_order_id: OrderId::from_u64(2),
+                    maker_order_id: OrderId::from_u64(1),
+                    timestamp: 1,
+                },
+            };
+            let value = serde_json::to_value(&tape).unwrap();
+            validate(&schema_of("TapePrint"), &value, "tape").unwrap();
+        }
+
+        let alert = Alert::new(AlertKind::FairValueDeviation, "BTC", "off".to_string())
+            .with_details(&json!({"deviation_bps": 12.5}));
+        let value = serde_json::to_value(&alert).unwrap();
+        validate(&schema_of("Alert"), &value, "alert").unwrap();
+
+        let dead_letter = DeadLetter::new(
+            "order.create",
+            "expected value at line 1 column 1".to_string(),
+            b"not json",
+            1,
+        );
+        let value = serde_json::to_value(&dead_letter).unwrap();
+        validate(&schema_of("DeadLetter"), &value, "dead_letter").unwrap();
+        let binary = DeadLetter::new("order.create", "truncated".to_string(), &[0x12, 0xff], 1);
+        let value = serde_json::to_value(&binary).unwrap();
+        assert_eq!(value["payload"], "12ff");
+        validate(&schema_of("DeadLetter"), &value, "dead_letter").unwrap();
+
+        let consumption = ConsumptionState {
+            timestamp: 1,
+            paused: vec![PausedTopic {
+                topic: "order.create".to_string(),
+                partitions: vec![0, 2],
+            }],
+        };
+        let value = serde_json::to_value(&consumption).unwrap();
+        validate(&schema_of("ConsumptionState"), &value, "consumption").unwrap();
+
+        let request = OrderRequest {
+            instrument_id: "BTC".to_string(),
+            order_id: 1,
+            action: OrderAction::Cancel,
+            client_order_id: Some("abc".to_string()),
+            participant_id: Some("desk-1".to_string()),
+        };
+        let ack = OrderAck {
+            request: &request,
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&ack).unwrap();
+        validate(&schema_of("OrderAck"), &value, "ack").unwrap();
+        let reject = OrderReject {
+            request: &request,
+            reason: RejectReason::DuplicateClientOrderId,
+            message: "client order id abc is in use by order 2",
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&reject).unwrap();
+        validate(&schema_of("OrderReject"), &value, "reject").unwrap();
+
+        let sample = PremiumSample {
+            instrument_id: "BTC-PERP".to_string(),
+            index_id: "BTC-IDX".to_string(),
+            mark_price: 101.0,
+            index_price: 100.0,
+            premium: 0.01,
+            premium_index: 0.005,
+            estimated_funding_rate: 0.0055,
+            next_funding_at: 2,
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&sample).unwrap();
+        validate(&schema_of("PremiumSample"), &value, "premium").unwrap();
+        let funding = FundingRate {
+            instrument_id: "BTC-PERP".to_string(),
+            index_id: "BTC-IDX".to_string(),
+            funding_rate: 0.0055,
+            premium_index: 0.005,
+            interest_rate: 0.0001,
+            samples: 2,
+            funding_at: 2,
+        };
+        let value = serde_json::to_value(&funding).unwrap();
+        validate(&schema_of("FundingRate"), &value, "funding").unwrap();
+
+        let bbo = BboState {
+            instrument_id: "BTC".to_string(),
+            best_bid: Some(99),
+            bid_quantity: 5,
+            best_ask: None,
+            ask_quantity: 0,
+            mid_price: None,
+            spread_bps: None,
+            last_trade_price: Some(100),
+            mark_price: Some(100.0),
+            mark_source: Some(MarkSource::LastTrade),
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&bbo).unwrap();
+        validate(&schema_of("BboState"), &value, "bbo_state").unwrap();
+
+        let event = BboEvent {
+            instrument_id: "BTC".to_string(),
+            best_bid: Some(99),
+            bid_quantity: 5,
+            best_ask: None,
+            ask_quantity: 0,
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        validate(&schema_of("BboEvent"), &value, "bbo_event").unwrap();
+
+        let execution = MboMessage::Order(MboEvent {
+            instrument_id: "BTC".to_string(),
+            sequence: 2,
+            timestamp: 1,
+            action: OrderChange::Executed,
+            order_id: OrderId::from_u64(1),
+            side: Side::Sell,
+            price: 101,
+            quantity: 3,
+            executed: Some(2),
+            priority: 1,
+        });
+        let snapshot = MboMessage::Snapshot(MboSnapshot {
+            instrument_id: "BTC".to_string(),
+            sequence: 3,
+            timestamp: 1,
+            orders: vec![MboOrder {
+                order_id: OrderId::from_u64(1),
+                side: Side::Sell,
+                price: 101,
+                quantity: 3,
+                priority: 1,
+            }],
+        });
+        for message in [execution, snapshot] {
+            let value = serde_json::to_value(&message).unwrap();
+            validate(&schema_of("MboMessage"), &value, "mbo").unwrap();
+        }
+
+        let update = L2Update {
+            instrument_id: "BTC".to_string(),
+            sequence: 2,
+            kind: L2UpdateKind::Delta,
+            timestamp: 1,
+            bids: vec![L2Level {
+                price: 99,
+                quantity: 0,
+            }],
+            asks: Vec::new(),
+        };
+        let value = serde_json::to_value(&update).unwrap();
+        validate(&schema_of("L2Update"), &value, "l2_update").unwrap();
+
+        let summary = MassCancelSummary {
+            request: MassCancelPayload {
+                instrument_id: None,
+                side: Some(Side::Sell),
+                participant_id: Some("desk-1".to_string()),
+                sequence: None,
+            },
+            orders_cancelled: 3,
+            quantity_cancelled: 30,
+            instruments: [("BTC".to_string(), 2), ("ETH".to_string(), 1)].into(),
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&summary).unwrap();
+        validate(&schema_of("MassCancelSummary"), &value, "mass_cancel").unwrap();
+
+        let state_hash = StateHash {
+            instrument_id: "BTC".to_string(),
+            command_index: 1000,
+            hash: "ab12".to_string(),
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&state_hash).unwrap();
+        validate(&schema_of("StateHash"), &value, "state_hash").unwrap();
+
+        let heartbeat = EngineHeartbeat {
+            sequence: 1,
+            timestamp: 1,
+            instruments: 2,
+            live_oms: vec!["oms-a".to_string()],
+        };
+        let value = serde_json::to_value(&heartbeat).unwrap();
+        validate(&schema_of("EngineHeartbeat"), &value, "heartbeat").unwrap();
+
+        let throttle = ThrottleState {
+            participant_id: "mm-1".to_string(),
+            instrument_id: "BTC".to_string(),
+            throttled: true,
+            until_ts: 5_000,
+            reason: ThrottleReason::OrderToTrade,
+            timestamp: 1,
+        };
+        let value = serde_json::to_value(&throttle).unwrap();
+        validate(&schema_of("ThrottleState"), &value, "throttle").unwrap();
+
+        let preview = DryRunResult::new(
+            crate::config::topics::CommandKind::OrderCreate,
+            Some("BTC".to_string()),
+            Some(1),
+            Err(Rejection::new(RejectReason::TradingHalted, "halted")),
+            1,
+        );
+        let value = serde_json::to_value(&preview).unwrap();
+        validate(&schema_of("DryRunResult"), &value, "dry_run").unwrap();
+
+        let distribution = Distribution {
+            count: 2,
+            mean: 20.0,
+            p50: 10.0,
+            p90: 30.0,
+            p99: 30.0,
+            max: 30.0,
+        };
+        let report = ExecutionQualityReport {
+            instrument_id: "BTC".to_string(),
+            since: 0,
+            timestamp: 1,
+            lifetime_us: distribution.clone(),
+            levels_swept: distribution,
+            slippage_bps: None,
+        };
+        let value = serde_json::to_value(&report).unwrap();
+        validate(&schema_of("ExecutionQualityReport"), &value, "report").unwrap();
+    }
+
+    #[test]
+    fn test_validator_rejects_mismatched_messages() {
+        let schema = schema_of("SinkEvent");
+        let delta = json!({
+            "event": "delta", "instrument_id": "BTC", "timestamp": 1,
+            "side": "BUY", "price": 100, "quantity": 5
+        });
+        validate(&schema, &delta, "event").unwrap();
+
+        let mut extra = delta.clone();
+        extra["sequence"] = json!(1);
+        assert!(validate(&schema, &extra, "event").is_err());
+        let mut negative = delta;
+        negative["price"] = json!(-1);
+        assert!(validate(&schema, &negative, "event").is_err());
+    }
+}
@@ -0,0 +1,291 @@
+// src/sequencer.rs
+use crate::alerts::{Alert, AlertKind, emit_alert};
+use crate::config::sequencer::SequencerConfig;
+use crate::helpers::EngineCommand;
+use crate::publisher::Publisher;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+/// Where a command stands in the sequence of its instrument
+#[derive(Debug)]
+pub enum Sequenced {
+    /// Commands to apply now, in order: the command and any it was holding up
+    Ready(Vec<EngineCommand>),
+    /// Held back until the commands before it arrive
+    Held,
+    /// Behind the sequence, a redelivery or a command whose gap was skipped;
+    /// it is dropped, and handed back to be answered
+    Stale(Box<EngineCommand>),
+}
+
+/// Details of a sequence gap alert
+#[derive(Debug, Serialize)]
+struct SequenceGap {
+    /// First sequence number that never arrived
+    expected: u64,
+    /// Sequence number the instrument resumed at
+    resumed_at: u64,
+    missing: u64,
+    held: usize,
+    waited_ms: u64,
+}
+
+struct InstrumentSequence {
+    /// Sequence number of the next command to apply
+    next: u64,
+    /// Commands ahead of `next`, by sequence number
+    held: BTreeMap<u64, EngineCommand>,
+    /// When commands were first held back behind the current gap
+    gap_since: Option<u64>,
+}
+
+impl InstrumentSequence {
+    /// Moves the held commands that follow on from `next` to `ready`
+    fn release(&mut self, ready: &mut Vec<EngineCommand>) {
+        while let Some(cmd) = self.held.remove(&self.next) {
+            ready.push(cmd);
+            self.next += 1;
+        }
+        if self.held.is_empty() {
+            self.gap_since = None;
+        }
+    }
+
+    /// Gives up on the commands missing before the first one held, alerting
+    /// on them, and releases what follows
+    fn skip_gap(
+        &mut self,
+        instrument_id: &str,
+        publisher: &Publisher,
+        now: u64,
+        ready: &mut Vec<EngineCommand>,
+    ) {
+        let Some(&resumed_at) = self.held.keys().next() else {
+            return;
+        };
+        let gap = SequenceGap {
+            expected: self.next,
+            resumed_at,
+            missing: resumed_at - self.next,
+            held: self.held.len(),
+            waited_ms: now.saturating_sub(self.gap_since.unwrap_or(now)),
+        };
+        emit_alert(
+            publisher,
+            Alert::new(
+                AlertKind::SequenceGap,
+                instrument_id,
+                format!(
+                    "Commands {} to {} never arrived, resuming at {} after {} ms",
+                    gap.expected,
+                    resumed_at - 1,
+                    resumed_at,
+                    gap.waited_ms
+                ),
+            )
+            .with_details(&gap),
+        );
+        self.next = resumed_at;
+        self.gap_since = Some(now);
+        self.release(ready);
+    }
+}
+
+/// Applies the commands of each instrument in the order of their `sequence`
+///
+/// The first command seen for an instrument sets where its sequence starts.
+/// Sequences are not persisted, so they start over with the engine: after a
+/// restart, the first command seen for an instrument sets its sequence again,
+/// whatever was applied before. A command ahead of the next one expected is held back until the commands
+/// before it arrive; once it has waited `gap_timeout_ms`, or more than
+/// `max_held` commands are held, the gap is alerted on and skipped. Commands
+/// without a sequence or an instrument are applied as they come, ahead of any
+/// held back.
+pub struct Sequencer {
+    config: SequencerConfig,
+    instruments: HashMap<String, InstrumentSequence>,
+}
+
+impl Sequencer {
+    pub fn new(config: SequencerConfig) -> Self {
+        Self {
+            config,
+            instruments: HashMap::new(),
+        }
+    }
+
+    pub fn admit(&mut self, cmd: EngineCommand, publisher: &Publisher, now: u64) -> Sequenced {
+        if !self.config.enabled {
+            return Sequenced::Ready(vec![cmd]);
+        }
+        let (Some(instrument_id), Some(sequence)) = (cmd.instrument_id(), cmd.sequence()) else {
+            return Sequenced::Ready(vec![cmd]);
+        };
+        let Some(state) = self.instruments.get_mut(instrument_id) else {
+            self.instruments.insert(
+                instrument_id.to_string(),
+                InstrumentSequence {
+                    next: sequence + 1,
+                    held: BTreeMap::new(),
+                    gap_since: None,
+                },
+            );
+            return Sequenced::Ready(vec![cmd]);
+        };
+        match sequence.cmp(&state.next) {
+            Ordering::Less => {
+                warn!(
+                    "Dropping command {} on {}, behind its sequence at {}",
+                    sequence, instrument_id, state.next
+                );
+                Sequenced::Stale(Box::new(cmd))
+            }
+            Ordering::Equal => {
+                state.next += 1;
+                let mut ready = vec![cmd];
+                state.release(&mut ready);
+                Sequenced::Ready(ready)
+            }
+            Ordering::Greater => {
+                let instrument_id = instrument_id.to_string();
+                if state.held.contains_key(&sequence) {
+                    return Sequenced::Stale(Box::new(cmd));
+                }
+                warn!(
+                    "Holding back command {} on {} until {} arrives",
+                    sequence, instrument_id, state.next
+                );
+                state.held.insert(sequence, cmd);
+                state.gap_since.get_or_insert(now);
+                if state.held.len() <= self.config.max_held {
+                    return Sequenced::Held;
+                }
+                let mut ready = Vec::new();
+                state.skip_gap(&instrument_id, publisher, now, &mut ready);
+                Sequenced::Ready(ready)
+            }
+        }
+    }
+
+    /// Skips the gaps that have been open longer than the timeout, returning
+    /// the commands they held up
+    pub fn on_tick(&mut self, publisher: &Publisher, now: u64) -> Vec<EngineCommand> {
+        let mut ready = Vec::new();
+        for (instrument_id, state) in &mut self.instruments {
+            if state
+                .gap_since
+                .is_some_and(|since| now.saturating_sub(since) >= self.config.gap_timeout_ms)
+            {
+                state.skip_gap(instrument_id, publisher, now, &mut ready);
+            }
+        }
+        ready
+    }
+
+    /// Every command still held back, in sequence order per instrument, for
+    /// the engine to apply before it stops
+    pub fn drain(&mut self) -> Vec<EngineCommand> {
+        let mut ready = Vec::new();
+        for (instrument_id, state) in &mut self.instruments {
+            if !state.held.is_empty() {
+                warn!(
+                    "Applying {} commands on {} held back behind sequence {}",
+                    state.held.len(),
+                    instrument_id,
+                    state.next
+                );
+            }
+            ready.extend(std::mem::take(&mut state.held).into_values());
+            state.gap_since = None;
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::TradingHaltPayload;
+
+    fn halt(instrument_id: &str, sequence: u64) -> EngineCommand {
+        EngineCommand::Halt(TradingHaltPayload {
+            instrument_id: instrument_id.to_string(),
+            reason: None,
+            sequence: Some(sequence),
+        })
+    }
+
+    fn sequences(sequenced: Sequenced) -> Vec<u64> {
+        match sequenced {
+            Sequenced::Ready(ready) => ready.iter().filter_map(EngineCommand::sequence).collect(),
+            other => panic!("expected commands to apply, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_commands_are_applied_in_sequence_per_instrument() {
+        let (publisher, mut outbound) = Publisher::channel(16);
+        let mut sequencer = Sequencer::new(SequencerConfig {
+            enabled: true,
+            gap_timeout_ms: 1_000,
+            max_held: 2,
+            ..SequencerConfig::default()
+        });
+        assert_eq!(
+            sequences(sequencer.admit(halt("BTC", 1), &publisher, 0)),
+            [1]
+        );
+        assert!(matches!(
+            sequencer.admit(halt("BTC", 3), &publisher, 0),
+            Sequenced::Held
+        ));
+        // Other instruments are not held up
+        assert_eq!(
+            sequences(sequencer.admit(halt("ETH", 7), &publisher, 0)),
+            [7]
+        );
+        assert_eq!(
+            sequences(sequencer.admit(halt("BTC", 2), &publisher, 10)),
+            [2, 3]
+        );
+        // A redelivery is handed back to be answered
+        assert!(matches!(
+            sequencer.admit(halt("BTC", 2), &publisher, 10),
+            Sequenced::Stale(stale) if stale.sequence() == Some(2)
+        ));
+
+        // A gap open past the timeout is alerted on and skipped
+        assert!(matches!(
+            sequencer.admit(halt("BTC", 6), &publisher, 100),
+            Sequenced::Held
+        ));
+        assert!(sequencer.on_tick(&publisher, 1_099).is_empty());
+        assert!(outbound.try_recv().is_err());
+        let ready = sequencer.on_tick(&publisher, 1_100);
+        assert_eq!(
+            ready
+                .iter()
+                .filter_map(EngineCommand::sequence)
+                .collect::<Vec<_>>(),
+            [6]
+        );
+        let alert: serde_json::Value =
+            serde_json::from_str(&outbound.try_recv().unwrap().payload).unwrap();
+        assert_eq!(alert["kind"], "sequence_gap");
+        assert_eq!(alert["instrument_id"], "BTC");
+        assert_eq!(alert["details"]["missing"], 2);
+        assert_eq!(alert["details"]["waited_ms"], 1_000);
+
+        // So is one holding back more than allowed
+        for sequence in [9, 10] {
+            sequencer.admit(halt("BTC", sequence), &publisher, 2_000);
+        }
+        assert_eq!(
+            sequences(sequencer.admit(halt("BTC", 12), &publisher, 2_000)),
+            [9, 10]
+        );
+        assert_eq!(sequencer.drain().len(), 1);
+    }
+}
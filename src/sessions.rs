@@ -0,0 +1,331 @@
+// src/sessions.rs
+use crate::config::sessions::{SessionConfig, SessionPolicy};
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::tags::OrderTags;
+use pricelevel::OrderId;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+
+/// Identifies an order-entry session, unique for the life of the registry
+pub type SessionId = u64;
+
+/// Why a session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseReason {
+    /// The participant logged out; its orders stay in the book
+    Logout,
+    /// The connection dropped
+    Disconnected,
+    /// No heartbeat arrived within the participant's timeout
+    HeartbeatTimeout,
+}
+
+/// A session that ended, with the orders to cancel on its behalf
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionClosed {
+    pub session_id: SessionId,
+    pub participant_id: String,
+    pub reason: CloseReason,
+    /// Orders still owned by the session, as (instrument, order); empty when the
+    /// policy keeps them resting
+    pub orders_to_cancel: Vec<(String, OrderId)>,
+}
+
+struct Session {
+    participant_id: String,
+    policy: SessionPolicy,
+    last_heartbeat: u64,
+    orders: HashSet<(String, OrderId)>,
+}
+
+/// Orders owned by each live order-entry session, for cancel-on-disconnect
+///
+/// Transport-agnostic: whichever endpoint accepts direct order entry opens a
+/// session per connection, registers the orders it enters, forwards heartbeats,
+/// and reports the connection dropping. Sessions that drop, or fall silent for
+/// longer than their participant's heartbeat timeout, give back the orders to
+/// mass-cancel. Orders that fill or are cancelled must be forgotten so they are
+/// not cancelled again.
+pub struct SessionRegistry {
+    config: SessionConfig,
+    sessions: HashMap<SessionId, Session>,
+    owners: HashMap<(String, OrderId), SessionId>,
+    next_id: SessionId,
+}
+
+impl SessionRegistry {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            config,
+            sessions: HashMap::new(),
+            owners: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Opens a session for a participant, under the participant's policy
+    pub fn open(&mut self, participant_id: &str, now: u64) -> SessionId {
+        let session_id = self.next_id;
+        self.next_id += 1;
+        self.sessions.insert(
+            session_id,
+            Session {
+                participant_id: participant_id.to_string(),
+                policy: self.config.policy(participant_id),
+                last_heartbeat: now,
+                orders: HashSet::new(),
+            },
+        );
+        info!("Opened session {} for {}", session_id, participant_id);
+        session_id
+    }
+
+    /// Records a sign of life; false if the session is not open
+    pub fn heartbeat(&mut self, session_id: SessionId, now: u64) -> bool {
+        match self.sessions.get_mut(&session_id) {
+            Some(session) => {
+                session.last_heartbeat = session.last_heartbeat.max(now);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Records that an order entered through the session rests in the book.
+    /// Any message on the session also counts as a heartbeat.
+    pub fn register_order(
+        &mut self,
+        session_id: SessionId,
+        instrument_id: &str,
+        order_id: OrderId,
+        now: u64,
+    ) -> bool {
+        let Some(session) = self.sessions.get_mut(&session_id) else {
+            warn!(
+                "Order {} on {} entered through unknown session {}",
+                order_id, instrument_id, session_id
+            );
+            return false;
+        };
+        session.last_heartbeat = session.last_heartbeat.max(now);
+        let key = (instrument_id.to_string(), order_id);
+        session.orders.insert(key.clone());
+        // An order id reused on the instrument now belongs to this session only
+        if let Some(previous) = self.owners.insert(key.clone(), session_id)
+            && previous != session_id
+            && let Some(previous) = self.sessions.get_mut(&previous)
+        {
+            previous.orders.remove(&key);
+        }
+        true
+    }
+
+    /// Forgets an order that filled or was cancelled
+    pub fn order_done(&mut self, instrument_id: &str, order_id: OrderId) {
+        let key = (instrument_id.to_string(), order_id);
+        if let Some(session_id) = self.owners.remove(&key)
+            && let Some(session) = self.sessions.get_mut(&session_id)
+        {
+            session.orders.remove(&key);
+        }
+    }
+
+    /// Participant of the session that owns an order
+    pub fn owner(&self, instrument_id: &str, order_id: OrderId) -> Option<&str> {
+        let session_id = self.owners.get(&(instrument_id.to_string(), order_id))?;
+        self.sessions
+            .get(session_id)
+            .map(|session| session.participant_id.as_str())
+    }
+
+    /// Ends a session on logout; its orders keep resting
+    pub fn logout(&mut self, session_id: SessionId) -> Option<SessionClosed> {
+        self.close(session_id, CloseReason::Logout)
+    }
+
+    /// Ends a session whose connection dropped
+    pub fn disconnected(&mut self, session_id: SessionId) -> Option<SessionClosed> {
+        self.close(session_id, CloseReason::Disconnected)
+    }
+
+    /// Ends every session that missed its heartbeat deadline
+    pub fn expire(&mut self, now: u64) -> Vec<SessionClosed> {
+        let mut expired: Vec<SessionId> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| {
+                session.policy.heartbeat_timeout_ms > 0
+                    && now.saturating_sub(session.last_heartbeat)
+                        > session.policy.heartbeat_timeout_ms
+            })
+            .map(|(session_id, _)| *session_id)
+            .collect();
+        expired.sort_unstable();
+        expired
+            .into_iter()
+            .filter_map(|session_id| self.close(session_id, CloseReason::HeartbeatTimeout))
+            .collect()
+    }
+
+    fn close(&mut self, session_id: SessionId, reason: CloseReason) -> Option<SessionClosed> {
+        let session = self.sessions.remove(&session_id)?;
+        for key in &session.orders {
+            self.owners.remove(key);
+        }
+        let cancel = reason != CloseReason::Logout && session.policy.cancel_on_disconnect;
+        let mut orders_to_cancel: Vec<(String, OrderId)> = if cancel {
+            session.orders.into_iter().collect()
+        } else {
+            Vec::new()
+        };
+        orders_to_cancel.sort_by_cached_key(|(instrument_id, order_id)| {
+            (instrument_id.clone(), order_id.to_string())
+        });
+        info!(
+            "Closed session {} of {} ({:?}), cancelling {} orders",
+            session_id,
+            session.participant_id,
+            reason,
+            orders_to_cancel.len()
+        );
+        Some(SessionClosed {
+            session_id,
+            participant_id: session.participant_id,
+            reason,
+            orders_to_cancel,
+        })
+    }
+}
+
+/// The orders a closed session left behind that may still rest, for the
+/// caller to cancel. Orders of books not in memory are kept, as their books
+/// are loaded when the cancel is applied.
+pub fn resting_orders(
+    manager: &BookManagerStd<OrderTags>,
+    closed: &SessionClosed,
+) -> Vec<(String, OrderId)> {
+    closed
+        .orders_to_cancel
+        .iter()
+        .filter(|(instrument_id, order_id)| {
+            manager
+                .get_book(instrument_id)
+                .is_none_or(|book| book.get_order(*order_id).is_some())
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{Side, TimeInForce};
+
+    fn registry() -> SessionRegistry {
+        let mut config = SessionConfig::default();
+        config.participants.insert(
+            "keeper".to_string(),
+            SessionPolicy {
+                cancel_on_disconnect: false,
+                heartbeat_timeout_ms: 0,
+            },
+        );
+        SessionRegistry::new(config)
+    }
+
+    #[test]
+    fn test_disconnect_cancels_owned_orders() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let book = manager.get_book("BTC").unwrap();
+        let mut sessions = registry();
+        let session = sessions.open("firm-a", 0);
+        for id in 1..=3 {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                100,
+                5,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+            assert!(sessions.register_order(session, "BTC", OrderId::from_u64(id), 0));
+        }
+        // Filled meanwhile, so no longer the session's to cancel
+        book.cancel_order(OrderId::from_u64(2)).unwrap();
+        sessions.order_done("BTC", OrderId::from_u64(2));
+        // Cancelled without the session learning of it
+        book.cancel_order(OrderId::from_u64(3)).unwrap();
+
+        let closed = sessions.disconnected(session).unwrap();
+        assert_eq!(closed.reason, CloseReason::Disconnected);
+        assert_eq!(closed.orders_to_cancel.len(), 2);
+        assert_eq!(
+            resting_orders(&manager, &closed),
+            [("BTC".to_string(), OrderId::from_u64(1))]
+        );
+        assert!(sessions.disconnected(session).is_none());
+    }
+
+    #[test]
+    fn test_logout_and_policy_keep_orders() {
+        let mut sessions = registry();
+        let session = sessions.open("firm-a", 0);
+        sessions.register_order(session, "BTC", OrderId::from_u64(1), 0);
+        assert!(
+            sessions
+                .logout(session)
+                .unwrap()
+                .orders_to_cancel
+                .is_empty()
+        );
+
+        let session = sessions.open("keeper", 0);
+        sessions.register_order(session, "BTC", OrderId::from_u64(2), 0);
+        assert!(
+            sessions
+                .disconnected(session)
+                .unwrap()
+                .orders_to_cancel
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_missed_heartbeats_expire_sessions() {
+        let mut sessions = registry();
+        let quiet = sessions.open("firm-a", 0);
+        let alive = sessions.open("firm-b", 0);
+        let exempt = sessions.open("keeper", 0);
+        sessions.register_order(quiet, "BTC", OrderId::from_u64(1), 1_000);
+        assert!(sessions.heartbeat(alive, 9_000));
+
+        assert!(sessions.expire(11_000).is_empty());
+        let expired = sessions.expire(11_001);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].session_id, quiet);
+        assert_eq!(expired[0].reason, CloseReason::HeartbeatTimeout);
+        assert_eq!(
+            expired[0].orders_to_cancel,
+            vec![("BTC".to_string(), OrderId::from_u64(1))]
+        );
+        assert!(!sessions.heartbeat(quiet, 11_002));
+        assert_eq!(sessions.expire(1_000_000)[0].session_id, alive);
+        assert!(sessions.heartbeat(exempt, 1_000_000));
+    }
+
+    #[test]
+    fn test_reused_order_id_moves_to_the_new_session() {
+        let mut sessions = registry();
+        let first = sessions.open("firm-a", 0);
+        let second = sessions.open("firm-a", 0);
+        sessions.register_order(first, "BTC", OrderId::from_u64(1), 0);
+        sessions.register_order(second, "BTC", OrderId::from_u64(1), 0);
+        let orders = |closed: Option<SessionClosed>| closed.unwrap().orders_to_cancel.len();
+        assert_eq!(orders(sessions.disconnected(first)), 0);
+        assert_eq!(orders(sessions.disconnected(second)), 1);
+    }
+}
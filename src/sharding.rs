@@ -0,0 +1,307 @@
+// src/sharding.rs
+use crate::config::sinks::SinkTarget;
+use crate::engine::EngineConfig;
+use crate::helpers::{AdminCommandPayload, EngineCommand};
+use crate::lanes::LaneSender;
+use crate::watchdog::Progress;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use tracing::warn;
+
+/// RFQs remembered for routing their quotes and executions; the oldest are
+/// forgotten first, by which time they have long expired
+const RFQ_ROUTES_CAPACITY: usize = 65_536;
+
+/// Shard owning the books of an instrument
+///
+/// Hashed with FNV-1a rather than the std hasher so an instrument stays on the
+/// same shard across restarts and builds.
+pub fn shard_of(instrument_id: &str, shards: usize) -> usize {
+    if shards <= 1 {
+        return 0;
+    }
+    let hash = instrument_id
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    (hash % shards as u64) as usize
+}
+
+/// Engine settings of one shard. The files each engine writes on its own move
+/// to a `shard-<n>` subdirectory, so shards do not write over each other.
+pub fn shard_config(config: &EngineConfig, shard: usize, shards: usize) -> EngineConfig {
+    let mut config = config.clone();
+    if shards <= 1 {
+        return config;
+    }
+    let subdirectory = |directory: &str| {
+        Path::new(directory)
+            .join(format!("shard-{}", shard))
+            .to_string_lossy()
+            .into_owned()
+    };
+    config.clearing.archive_dir = subdirectory(&config.clearing.archive_dir);
+    if let Some(directory) = &mut config.shutdown.snapshot_dir {
+        *directory = subdirectory(directory);
+    }
+    config.wal.directory = subdirectory(&config.wal.directory);
+    for profile in &mut config.feeds.profiles {
+        if let Some(directory) = &mut profile.journal_dir {
+            *directory = subdirectory(directory);
+        }
+    }
+    for sink in &mut config.sinks.sinks {
+        if let SinkTarget::Jsonl { directory, .. } = &mut sink.target {
+            *directory = subdirectory(directory);
+        }
+    }
+    config
+}
+
+/// Sends each command to the engine shard owning its instrument
+///
+/// Commands without an instrument go where their state lives: RFQ quotes and
+/// executions follow their request, an index is computed on the shard of its
+/// first constituent, and OMS heartbeats and engine-wide admin commands reach
+/// every shard.
+pub struct ShardRouter {
+    shards: Vec<LaneSender>,
+    rfqs: HashMap<String, usize>,
+    rfq_order: VecDeque<String>,
+}
+
+impl ShardRouter {
+    pub fn new(shards: Vec<LaneSender>) -> Self {
+        assert!(!shards.is_empty(), "at least one engine shard is needed");
+        Self {
+            shards,
+            rfqs: HashMap::new(),
+            rfq_order: VecDeque::new(),
+        }
+    }
+
+    fn shard_of(&self, instrument_id: &str) -> usize {
+        shard_of(instrument_id, self.shards.len())
+    }
+
+    fn remember_rfq(&mut self, rfq_id: &str, shard: usize) {
+        if self.rfqs.insert(rfq_id.to_string(), shard).is_none() {
+            self.rfq_order.push_back(rfq_id.to_string());
+        }
+        while self.rfq_order.len() > RFQ_ROUTES_CAPACITY {
+            if let Some(oldest) = self.rfq_order.pop_front() {
+                self.rfqs.remove(&oldest);
+            }
+        }
+    }
+
+    fn rfq_shard(&self, rfq_id: &str) -> usize {
+        self.rfqs.get(rfq_id).copied().unwrap_or_else(|| {
+            // Left for the first shard to reject as unknown
+            warn!("No shard known for RFQ {}", rfq_id);
+            0
+        })
+    }
+
+    /// A copy of a command for every shard
+    fn everywhere(&self, copy: impl Fn() -> EngineCommand) -> Vec<(usize, EngineCommand)> {
+        (0..self.shards.len())
+            .map(|shard| (shard, copy()))
+            .collect()
+    }
+
+    /// The shards a command is queued on, with the command for each
+    fn route(&mut self, cmd: EngineCommand) -> Vec<(usize, EngineCommand)> {
+        if self.shards.len() == 1 {
+            return vec![(0, cmd)];
+        }
+        if let Some(instrument_id) = cmd.instrument_id() {
+            let shard = self.shard_of(instrument_id);
+            if let EngineCommand::RfqRequest(request) = &cmd {
+                self.remember_rfq(&request.rfq_id, shard);
+            }
+            return vec![(shard, cmd)];
+        }
+        let shard = match &cmd {
+            EngineCommand::RfqQuote(quote) => self.rfq_shard(&quote.rfq_id),
+            EngineCommand::RfqExecute(execute) => self.rfq_shard(&execute.rfq_id),
+            EngineCommand::IndexDefine(payload) => {
+                let mut shards = payload
+                    .definition
+                    .instruments()
+                    .map(|instrument_id| self.shard_of(instrument_id));
+                let Some(shard) = shards.next() else {
+                    // Removals reach wherever the index was defined
+                    return self.everywhere(|| EngineCommand::IndexDefine(payload.clone()));
+                };
+                if shards.any(|other| other != shard) {
+                    warn!(
+                        "Constituents of index {} span shards, it is only computed once all of them are priced on shard {}",
+                        payload.definition.index_id, shard
+                    );
+                }
+                shard
+            }
+            EngineCommand::OmsHeartbeat(heartbeat) => {
+                return self.everywhere(|| EngineCommand::OmsHeartbeat(heartbeat.clone()));
+            }
+            // Each shard cancels on its own books and reports its own counts
+            EngineCommand::MassCancel(mass_cancel) => {
+                return self.everywhere(|| EngineCommand::MassCancel(mass_cancel.clone()));
+            }
+            // A participant may trade on every shard
+            EngineCommand::KillSwitch(kill_switch) => {
+                return self.everywhere(|| EngineCommand::KillSwitch(kill_switch.clone()));
+            }
+            EngineCommand::Admin(admin) => match admin {
+                AdminCommandPayload::AddCorrelationPair { instrument_a, .. }
+                | AdminCommandPayload::RemoveCorrelationPair { instrument_a, .. } => {
+                    self.shard_of(instrument_a)
+                }
+                AdminCommandPayload::BookAsOf { instrument_id, .. }
+                | AdminCommandPayload::MovePeggedOrders { instrument_id, .. } => {
+                    self.shard_of(instrument_id)
+                }
+                // Process-wide, applied once
+                #[cfg(feature = "chaos")]
+                AdminCommandPayload::InjectFaults(_) | AdminCommandPayload::ClearFaults => 0,
+                _ => return self.everywhere(|| EngineCommand::Admin(admin.clone())),
+            },
+            // Targeted commands were routed by their instrument
+            _ => 0,
+        };
+        vec![(shard, cmd)]
+    }
+
+    /// Queues a command on its shard, or a copy on every shard, counting each
+    /// copy queued as received
+    pub async fn send(&mut self, cmd: EngineCommand, progress: &Progress) {
+        for (shard, cmd) in self.route(cmd) {
+            match self.shards[shard].send(cmd).await {
+                Ok(()) => progress.received(),
+                Err(e) => warn!("Failed to send command to engine shard {}: {}", shard, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::topics::CommandKind;
+    use crate::lanes;
+
+    fn command(kind: CommandKind, payload: &str) -> EngineCommand {
+        EngineCommand::parse(kind, payload).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_instruments_spread_over_stable_shards() {
+        assert_eq!(shard_of("BTC", 1), 0);
+        let shards: Vec<usize> = ["BTC", "ETH", "SOL", "XRP", "ADA", "DOT", "AVAX", "LINK"]
+            .iter()
+            .map(|instrument_id| shard_of(instrument_id, 4))
+            .collect();
+        assert!(shards.iter().all(|&shard| shard < 4));
+        assert!(shards.iter().any(|&shard| shard != shards[0]));
+        // The FNV-1a offset basis, whatever the build
+        assert_eq!(shard_of("", 4), (0xcbf2_9ce4_8422_2325_u64 % 4) as usize);
+    }
+
+    #[tokio::test]
+    async fn test_commands_follow_their_instrument() {
+        let (senders, mut receivers): (Vec<_>, Vec<_>) =
+            (0..4).map(|_| lanes::channel(8, 8)).unzip();
+        let mut router = ShardRouter::new(senders);
+        let progress = Progress::default();
+        let btc = shard_of("BTC", 4);
+
+        router
+            .send(
+                command(
+                    CommandKind::OrderCancel,
+                    r#"{"order_id":1,"instrument_id":"BTC"}"#,
+                ),
+                &progress,
+            )
+            .await;
+        router
+            .send(
+                command(
+                    CommandKind::RfqRequest,
+                    r#"{"rfq_id":"r-1","instrument_id":"BTC","requester_id":"c-1","side":"BUY","quantity":5}"#,
+                ),
+                &progress,
+            )
+            .await;
+        router
+            .send(
+                command(
+                    CommandKind::RfqExecute,
+                    r#"{"rfq_id":"r-1","requester_id":"c-1"}"#,
+                ),
+                &progress,
+            )
+            .await;
+        for (shard, receiver) in receivers.iter_mut().enumerate() {
+            let received = std::iter::from_fn(|| receiver.try_recv().ok()).count();
+            assert_eq!(received, if shard == btc { 3 } else { 0 });
+        }
+
+        // Heartbeats concern the orders of the OMS on every shard
+        router
+            .send(
+                command(CommandKind::OmsHeartbeat, r#"{"oms_id":"oms-1"}"#),
+                &progress,
+            )
+            .await;
+        for receiver in &mut receivers {
+            assert!(matches!(
+                receiver.try_recv(),
+                Ok(EngineCommand::OmsHeartbeat(_))
+            ));
+        }
+        // So are a participant's orders, unless the mass cancel names an instrument
+        router
+            .send(
+                command(CommandKind::MassCancel, r#"{"participant_id":"desk-a"}"#),
+                &progress,
+            )
+            .await;
+        router
+            .send(
+                command(CommandKind::MassCancel, r#"{"instrument_id":"BTC"}"#),
+                &progress,
+            )
+            .await;
+        for (shard, receiver) in receivers.iter_mut().enumerate() {
+            let received = std::iter::from_fn(|| receiver.try_recv().ok()).count();
+            assert_eq!(received, if shard == btc { 2 } else { 1 });
+        }
+        assert_eq!(progress.queued(), 12);
+    }
+
+    #[test]
+    fn test_shards_write_to_their_own_directories() {
+        let mut config = EngineConfig::default();
+        config.sinks.sinks.push(
+            serde_json::from_str(
+                r#"{"name":"drop","kind":"jsonl","directory":"out","queue_capacity":8}"#,
+            )
+            .unwrap(),
+        );
+        let single = shard_config(&config, 0, 1);
+        assert_eq!(single.clearing.archive_dir, config.clearing.archive_dir);
+
+        let shard = shard_config(&config, 2, 4);
+        assert_eq!(
+            Path::new(&shard.clearing.archive_dir),
+            Path::new(&config.clearing.archive_dir).join("shard-2")
+        );
+        let SinkTarget::Jsonl { directory, .. } = &shard.sinks.sinks[0].target else {
+            panic!("expected a JSONL sink");
+        };
+        assert_eq!(Path::new(directory), Path::new("out/shard-2"));
+    }
+}
@@ -0,0 +1,179 @@
+// src/shutdown.rs
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::tags::OrderTags;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Extension of the snapshot files; files being written carry `.partial` after it
+const SNAPSHOT_EXTENSION: &str = "json";
+
+/// Resolves on the first SIGINT or SIGTERM, naming the signal
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                () = interrupt() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                warn!("Cannot listen for SIGTERM: {}", e);
+                interrupt().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        interrupt().await;
+        "Ctrl+C"
+    }
+}
+
+/// Resolves on Ctrl+C, or never if it cannot be listened for
+async fn interrupt() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Cannot listen for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Waits for the engine tasks to apply their queued commands and persist their
+/// books, then for the publisher task to send what they published
+///
+/// The command channels must be closed and every other `Publisher` dropped
+/// first, or neither ever finishes. Gives up after `timeout`, or when a second
+/// signal asks to exit at once.
+pub async fn drain(engines: Vec<JoinHandle<()>>, publisher: JoinHandle<()>, timeout: Duration) {
+    let drained = async {
+        futures::future::join_all(engines).await;
+        let _ = publisher.await;
+    };
+    tokio::select! {
+        () = drained => info!("Shutdown complete"),
+        () = tokio::time::sleep(timeout) => warn!(
+            "Shutdown did not complete within {} ms, exiting anyway",
+            timeout.as_millis()
+        ),
+        signal = signal() => warn!("Received {} again, exiting without draining", signal),
+    }
+}
+
+/// File name of an instrument's snapshot; characters that may not be safe in a
+/// file name are percent-encoded, so distinct instruments never share a file
+fn file_name(instrument_id: &str) -> String {
+    let mut name = String::with_capacity(instrument_id.len() + 5);
+    for byte in instrument_id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    name.push('.');
+    name.push_str(SNAPSHOT_EXTENSION);
+    name
+}
+
+/// Writes an `OrderBookSnapshotPackage` of every book to its own file in
+/// `directory`, returning how many were written
+///
+/// Each file is written aside and renamed into place, so a snapshot is never
+/// left half written. Snapshots of books that no longer exist are removed, so
+/// the directory holds exactly the books of the engine.
+pub fn write_snapshots(manager: &BookManagerStd<OrderTags>, directory: &Path) -> io::Result<usize> {
+    std::fs::create_dir_all(directory)?;
+    let mut written = HashSet::new();
+    for symbol in manager.symbols() {
+        let Some(book) = manager.get_book(&symbol) else {
+            continue;
+        };
+        let json = match book.snapshot_to_json(usize::MAX) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to snapshot {}: {}", symbol, e);
+                continue;
+            }
+        };
+        let name = file_name(&symbol);
+        let path = directory.join(&name);
+        let partial = directory.join(format!("{name}.partial"));
+        match std::fs::write(&partial, json).and_then(|()| std::fs::rename(&partial, &path)) {
+            Ok(()) => {
+                written.insert(name);
+            }
+            Err(e) => warn!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let stale = path
+            .extension()
+            .is_some_and(|extension| extension == SNAPSHOT_EXTENSION)
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| !written.contains(name));
+        if stale {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(written.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::snapshot::OrderBookSnapshotPackage;
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    #[test]
+    fn test_instrument_names_are_safe_file_names() {
+        assert_eq!(file_name("BTC-PERP"), "BTC-PERP.json");
+        assert_eq!(file_name("BTC/USD"), "BTC%2FUSD.json");
+        assert_eq!(file_name(".."), "%2E%2E.json");
+        assert_ne!(file_name("BTC/USD"), file_name("BTC_USD"));
+    }
+
+    #[test]
+    fn test_every_book_is_persisted() {
+        let directory = std::env::temp_dir().join(format!("shutdown-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("DELISTED.json"), "{}").unwrap();
+        std::fs::write(directory.join("notes.txt"), "kept").unwrap();
+
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        for symbol in ["BTC", "ETH/USD"] {
+            manager.add_book(symbol);
+        }
+        manager
+            .get_book("BTC")
+            .unwrap()
+            .add_limit_order(
+                OrderId::from_u64(1),
+                100,
+                5,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(write_snapshots(&manager, &directory).unwrap(), 2);
+        let package = OrderBookSnapshotPackage::from_json(
+            &std::fs::read_to_string(directory.join("BTC.json")).unwrap(),
+        )
+        .unwrap();
+        let snapshot = package.into_snapshot().unwrap();
+        assert_eq!(snapshot.bids.len(), 1);
+        assert!(directory.join("ETH%2FUSD.json").exists());
+        assert!(!directory.join("DELISTED.json").exists());
+        assert!(directory.join("notes.txt").exists());
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,526 @@
+// src/sinks.rs
+use crate::blocking_worker::BlockingWorker;
+use crate::config::sinks::{EventKind, PipelineConfig, SinkConfig, SinkFilter, SinkTarget};
+use crate::execution_quality::AggressorExecution;
+use crate::orderbook::OrderBook;
+use crate::orderbook::book_change_event::PriceLevelChangedEvent;
+use crate::orderbook::snapshot::OrderBookSnapshot;
+use crate::orderbook::trade::BookContext;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use pricelevel::{OrderId, Side};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// A change of the visible quantity at one price level
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelChange {
+    pub instrument_id: String,
+    pub timestamp: u64,
+    pub side: Side,
+    pub price: u64,
+    /// New visible quantity; 0 means the level is gone
+    pub quantity: u64,
+    /// Part of `quantity` refreshed from the hidden reserve of iceberg orders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replenished: Option<u64>,
+}
+
+/// A single fill, in raw book units
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeRecord {
+    pub instrument_id: String,
+    pub trade_id: Uuid,
+    pub price: u64,
+    pub quantity: u64,
+    pub aggressor_side: Side,
+    pub taker_order_id: OrderId,
+    pub maker_order_id: OrderId,
+    pub timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taker_participant_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maker_participant_id: Option<String>,
+    /// The book's touch when the taker arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book_context: Option<BookContext>,
+    #[serde(skip_serializing_if = "OrderTags::is_empty")]
+    pub taker_tags: OrderTags,
+    #[serde(skip_serializing_if = "OrderTags::is_empty")]
+    pub maker_tags: OrderTags,
+    /// Whether a liquidation order was on either side
+    pub liquidation: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderAction {
+    Create,
+    Modify,
+    Cancel,
+    Replace,
+}
+
+/// An order command received by the engine
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderEvent {
+    pub instrument_id: String,
+    pub order_id: u64,
+    pub action: OrderAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u64>,
+    pub timestamp: u64,
+    /// Participant owning the order, when it was given on creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub participant_id: Option<String>,
+    /// Tags given on creation
+    #[serde(skip_serializing_if = "OrderTags::is_empty")]
+    pub tags: OrderTags,
+}
+
+/// An engine event offered to sinks
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SinkEvent<'a> {
+    Trade(&'a TradeRecord),
+    Delta(&'a LevelChange),
+    /// Full depth of a book; it replaces every level seen before
+    Snapshot(&'a OrderBookSnapshot),
+    Order(&'a OrderEvent),
+    /// How an aggressive order executed
+    Execution(&'a AggressorExecution),
+}
+
+impl SinkEvent<'_> {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            SinkEvent::Trade(_) => EventKind::Trade,
+            SinkEvent::Delta(_) => EventKind::Delta,
+            SinkEvent::Snapshot(_) => EventKind::Snapshot,
+            SinkEvent::Order(_) => EventKind::Order,
+            SinkEvent::Execution(_) => EventKind::Execution,
+        }
+    }
+
+    pub fn instrument_id(&self) -> &str {
+        match self {
+            SinkEvent::Trade(trade) => &trade.instrument_id,
+            SinkEvent::Delta(change) => &change.instrument_id,
+            SinkEvent::Snapshot(snapshot) => &snapshot.symbol,
+            SinkEvent::Order(order) => &order.instrument_id,
+            SinkEvent::Execution(execution) => &execution.instrument_id,
+        }
+    }
+
+    /// Participants on the sides of a trade or owning an order, as far as
+    /// known; `None` for events that belong to no participant
+    pub fn participants(&self) -> Option<Vec<&str>> {
+        match self {
+            SinkEvent::Trade(trade) => Some(
+                [&trade.taker_participant_id, &trade.maker_participant_id]
+                    .into_iter()
+                    .filter_map(|participant| participant.as_deref())
+                    .collect(),
+            ),
+            SinkEvent::Order(order) => Some(order.participant_id.as_deref().into_iter().collect()),
+            SinkEvent::Execution(execution) => {
+                Some(execution.participant_id.as_deref().into_iter().collect())
+            }
+            SinkEvent::Delta(_) | SinkEvent::Snapshot(_) => None,
+        }
+    }
+}
+
+/// An output for engine events
+///
+/// Sinks run on the engine task, so `accept` must not block; sinks that write to
+/// slow storage batch events and hand them to a [`BlockingWorker`].
+pub trait EventSink: Send {
+    fn name(&self) -> &str;
+
+    fn accept(&mut self, event: &SinkEvent<'_>);
+
+    /// Called on every engine tick, e.g. to hand batched events to a writer
+    fn on_tick(&mut self, _now: u64) {}
+}
+
+/// Collects price level changes from every attached book
+///
+/// Books report changes through their listener while a command is processed; the
+/// engine drains them once the command is done.
+pub struct LevelTap {
+    sender: Sender<LevelChange>,
+    receiver: Receiver<LevelChange>,
+    attached: HashSet<String>,
+}
+
+impl LevelTap {
+    pub fn new() -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            attached: HashSet::new(),
+        }
+    }
+
+    /// Installs the level listener on a book, returning false if it already has it
+    pub fn attach(&mut self, book: &mut OrderBook<OrderTags>) -> bool {
+        if self.attached.contains(book.symbol()) {
+            return false;
+        }
+        let sender = self.sender.clone();
+        let instrument_id = book.symbol().to_string();
+        book.set_price_level_listener(Arc::new(move |event: PriceLevelChangedEvent| {
+            let _ = sender.send(LevelChange {
+                instrument_id: instrument_id.clone(),
+                timestamp: current_time_millis(),
+                side: event.side,
+                price: event.price,
+                quantity: event.quantity,
+                replenished: (event.replenished > 0).then_some(event.replenished),
+            });
+        }));
+        self.attached.insert(book.symbol().to_string());
+        true
+    }
+
+    /// Forgets a deleted book, returning false if it was not attached
+    pub fn detach(&mut self, instrument_id: &str) -> bool {
+        self.attached.remove(instrument_id)
+    }
+
+    pub fn attached(&self) -> impl Iterator<Item = &String> {
+        self.attached.iter()
+    }
+
+    pub fn drain(&self) -> Vec<LevelChange> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// Publishes each event to a Kafka topic, keyed by instrument
+pub struct KafkaSink {
+    name: String,
+    topic: String,
+    publisher: Publisher,
+}
+
+impl EventSink for KafkaSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn accept(&mut self, event: &SinkEvent<'_>) {
+        self.publisher
+            .publish(&self.topic, event.instrument_id(), event);
+    }
+}
+
+/// Appends each event as a JSON line to a file, written from a dedicated thread
+pub struct JsonlSink {
+    name: String,
+    path: PathBuf,
+    pending: String,
+    worker: BlockingWorker<()>,
+}
+
+impl JsonlSink {
+    pub fn new(name: &str, directory: &str, queue_capacity: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            path: PathBuf::from(directory).join(format!("{name}.jsonl")),
+            pending: String::new(),
+            worker: BlockingWorker::spawn(&format!("sink-{name}"), (), queue_capacity),
+        }
+    }
+}
+
+impl EventSink for JsonlSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn accept(&mut self, event: &SinkEvent<'_>) {
+        match serde_json::to_string(event) {
+            Ok(line) => {
+                self.pending.push_str(&line);
+                self.pending.push('\n');
+            }
+            Err(e) => warn!("Failed to serialize event for sink {}: {}", self.name, e),
+        }
+    }
+
+    fn on_tick(&mut self, _now: u64) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let lines = std::mem::take(&mut self.pending);
+        let path = self.path.clone();
+        let name = self.name.clone();
+        let queued = self.worker.submit(move |_| {
+            let result = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| OpenOptions::new().create(true).append(true).open(&path))
+                .and_then(|mut file| file.write_all(lines.as_bytes()));
+            if let Err(e) = result {
+                warn!("Sink {} failed to write {}: {}", name, path.display(), e);
+            }
+        });
+        if !queued {
+            warn!("Sink {} is behind, dropped a batch of events", self.name);
+        }
+    }
+}
+
+/// Sinks built from configuration, each behind its own filter
+///
+/// Filters give each subscriber its own view, e.g. a risk desk receiving only
+/// its accounts' executions while surveillance receives everything. They can
+/// be replaced at runtime through the admin topic.
+pub struct SinkPipeline {
+    sinks: Vec<(SinkFilter, Box<dyn EventSink>)>,
+    instrument_groups: HashMap<String, Vec<String>>,
+}
+
+impl SinkPipeline {
+    pub fn new(config: &PipelineConfig, publisher: &Publisher) -> Self {
+        let sinks = config
+            .sinks
+            .iter()
+            .map(|sink| (sink.filter.clone(), build_sink(sink, publisher)))
+            .collect::<Vec<_>>();
+        #[cfg(feature = "chaos")]
+        let sinks = sinks
+            .into_iter()
+            .map(|(filter, sink)| {
+                let sink: Box<dyn EventSink> =
+                    Box::new(crate::chaos::FaultySink::new(sink, crate::chaos::global()));
+                (filter, sink)
+            })
+            .collect::<Vec<_>>();
+        for (filter, sink) in &sinks {
+            info!("Event sink {} is enabled", sink.name());
+            for group in &filter.instrument_groups {
+                if !config.instrument_groups.contains_key(group) {
+                    warn!(
+                        "Sink {} filters on unknown instrument group {}",
+                        sink.name(),
+                        group
+                    );
+                }
+            }
+        }
+        Self {
+            sinks,
+            instrument_groups: config.instrument_groups.clone(),
+        }
+    }
+
+    /// Replaces the filter of the named sink, returning false if there is none
+    pub fn set_filter(&mut self, name: &str, filter: SinkFilter) -> bool {
+        match self.sinks.iter_mut().find(|(_, sink)| sink.name() == name) {
+            Some((current, _)) => {
+                *current = filter;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Filter of every sink, by sink name
+    pub fn filters(&self) -> BTreeMap<&str, &SinkFilter> {
+        self.sinks
+            .iter()
+            .map(|(filter, sink)| (sink.name(), filter))
+            .collect()
+    }
+
+    /// Whether any sink takes events of this kind, to skip building unwanted events
+    pub fn wants(&self, kind: EventKind) -> bool {
+        self.sinks
+            .iter()
+            .any(|(filter, _)| filter.events.is_empty() || filter.events.contains(&kind))
+    }
+
+    pub fn emit(&mut self, event: &SinkEvent<'_>) {
+        let kind = event.kind();
+        let participants = event.participants();
+        for (filter, sink) in &mut self.sinks {
+            if filter.accepts(
+                kind,
+                event.instrument_id(),
+                participants.as_deref(),
+                &self.instrument_groups,
+            ) {
+                sink.accept(event);
+            }
+        }
+    }
+
+    pub fn on_tick(&mut self, now: u64) {
+        for (_, sink) in &mut self.sinks {
+            sink.on_tick(now);
+        }
+    }
+}
+
+fn build_sink(config: &SinkConfig, publisher: &Publisher) -> Box<dyn EventSink> {
+    match &config.target {
+        SinkTarget::Kafka { topic } => Box::new(KafkaSink {
+            name: config.name.clone(),
+            topic: topic.clone(),
+            publisher: publisher.clone(),
+        }),
+        SinkTarget::Jsonl {
+            directory,
+            queue_capacity,
+        } => Box::new(JsonlSink::new(&config.name, directory, *queue_capacity)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(instrument_id: &str) -> LevelChange {
+        LevelChange {
+            instrument_id: instrument_id.to_string(),
+            timestamp: 1,
+            side: Side::Buy,
+            price: 100,
+            quantity: 5,
+            replenished: None,
+        }
+    }
+
+    #[test]
+    fn test_pipeline_applies_per_sink_filters() {
+        let (publisher, mut rx) = Publisher::channel(8);
+        let config: PipelineConfig = serde_json::from_str(
+            r#"{"sinks": [
+                {"name": "deltas", "kind": "kafka", "topic": "engine.deltas",
+                 "filter": {"events": ["delta"], "instruments": ["BTC"]}},
+                {"name": "all", "kind": "kafka", "topic": "engine.all"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline = SinkPipeline::new(&config, &publisher);
+        assert!(pipeline.wants(EventKind::Order));
+
+        pipeline.emit(&SinkEvent::Delta(&change("BTC")));
+        pipeline.emit(&SinkEvent::Delta(&change("ETH")));
+        let topics: Vec<(String, String)> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|message| (message.topic, message.key))
+            .collect();
+        assert_eq!(
+            topics,
+            vec![
+                ("engine.deltas".to_string(), "BTC".to_string()),
+                ("engine.all".to_string(), "BTC".to_string()),
+                ("engine.all".to_string(), "ETH".to_string()),
+            ]
+        );
+    }
+
+    fn trade(instrument_id: &str, taker: Option<&str>, maker: Option<&str>) -> TradeRecord {
+        TradeRecord {
+            instrument_id: instrument_id.to_string(),
+            trade_id: Uuid::new_v4(),
+            price: 100,
+            quantity: 5,
+            aggressor_side: Side::Buy,
+            taker_order_id: OrderId::from_u64(2),
+            maker_order_id: OrderId::from_u64(1),
+            timestamp: 1,
+            taker_participant_id: taker.map(str::to_string),
+            maker_participant_id: maker.map(str::to_string),
+            book_context: None,
+            taker_tags: OrderTags::new(),
+            maker_tags: OrderTags::new(),
+            liquidation: false,
+        }
+    }
+
+    #[test]
+    fn test_drop_copy_views_by_participant_and_group() {
+        let (publisher, mut rx) = Publisher::channel(16);
+        let config: PipelineConfig = serde_json::from_str(
+            r#"{"instrument_groups": {"majors": ["BTC", "ETH"]},
+                "sinks": [
+                {"name": "risk", "kind": "kafka", "topic": "dropcopy.risk",
+                 "filter": {"participants": ["acct-1"], "instrument_groups": ["majors"]}},
+                {"name": "surveillance", "kind": "kafka", "topic": "dropcopy.all"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline = SinkPipeline::new(&config, &publisher);
+        let mut topics = |pipeline: &mut SinkPipeline, event: &SinkEvent<'_>| {
+            pipeline.emit(event);
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|message| message.topic)
+                .collect::<Vec<_>>()
+        };
+        let both = vec!["dropcopy.risk".to_string(), "dropcopy.all".to_string()];
+        let surveillance = vec!["dropcopy.all".to_string()];
+
+        let mine = trade("ETH", Some("acct-2"), Some("acct-1"));
+        assert_eq!(topics(&mut pipeline, &SinkEvent::Trade(&mine)), both);
+        let theirs = trade("BTC", Some("acct-2"), None);
+        assert_eq!(
+            topics(&mut pipeline, &SinkEvent::Trade(&theirs)),
+            surveillance
+        );
+        let outside_group = trade("SOL", Some("acct-1"), None);
+        assert_eq!(
+            topics(&mut pipeline, &SinkEvent::Trade(&outside_group)),
+            surveillance
+        );
+        // Market data belongs to no participant
+        assert_eq!(
+            topics(&mut pipeline, &SinkEvent::Delta(&change("BTC"))),
+            both
+        );
+
+        assert!(pipeline.set_filter(
+            "risk",
+            SinkFilter {
+                participants: vec!["acct-2".to_string()],
+                ..SinkFilter::default()
+            },
+        ));
+        assert!(!pipeline.set_filter("missing", SinkFilter::default()));
+        assert_eq!(topics(&mut pipeline, &SinkEvent::Trade(&theirs)), both);
+        assert_eq!(pipeline.filters()["risk"].participants, vec!["acct-2"]);
+    }
+
+    #[test]
+    fn test_jsonl_sink_writes_batches_on_tick() {
+        let directory = std::env::temp_dir().join(format!("jsonl-sink-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        {
+            let mut sink = JsonlSink::new("events", &directory.to_string_lossy(), 4);
+            sink.accept(&SinkEvent::Delta(&change("BTC")));
+            sink.accept(&SinkEvent::Delta(&change("ETH")));
+            sink.on_tick(0);
+        }
+        let contents = std::fs::read_to_string(directory.join("events.jsonl")).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"event":"delta","instrument_id":"BTC""#));
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
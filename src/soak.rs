@@ -0,0 +1,471 @@
+// src/soak.rs
+//! Soak runs: the engine driven by generated load for hours, watched for
+//! invariant violations and memory growth, ending in a pass/fail report
+
+use crate::alerts::ALERTS_TOPIC;
+use crate::config::soak::SoakConfig;
+use crate::engine::{self, EngineConfig};
+use crate::helpers::types::OrderType;
+use crate::helpers::{EngineCommand, OrderCancelPayload, OrderCreatePayload, OrderModifyPayload};
+use crate::lanes;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use pricelevel::{Side, TimeInForce};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+/// Mid price the generated orders are placed around
+const MID_PRICE: u64 = 10_000;
+
+/// Seeded stream of order commands that keeps books at a steady size
+///
+/// Most commands rest passive orders a few ticks from the mid; the rest cancel
+/// or resize resting orders, or send small market orders that trade. Once an
+/// instrument has `max_resting_orders` orders, its oldest is cancelled next.
+pub struct LoadGenerator {
+    instruments: Vec<String>,
+    max_resting_orders: usize,
+    /// State of the xorshift generator behind every draw
+    rng: u64,
+    next_order_id: u64,
+    /// Orders believed resting, oldest first; some may have been filled since
+    resting: HashMap<String, VecDeque<u64>>,
+}
+
+impl LoadGenerator {
+    pub fn new(config: &SoakConfig) -> Self {
+        Self {
+            instruments: config.instruments.clone(),
+            max_resting_orders: config.max_resting_orders.max(1),
+            // xorshift never leaves an all-zero state
+            rng: config.seed.max(1),
+            next_order_id: 1,
+            resting: HashMap::new(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// Uniform draw from `low..=high`
+    fn between(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low + 1)
+    }
+
+    pub fn next_command(&mut self) -> EngineCommand {
+        let index = self.next_u64() as usize % self.instruments.len();
+        let instrument_id = self.instruments[index].clone();
+        let resting_count = self.resting.get(&instrument_id).map_or(0, VecDeque::len);
+        if resting_count >= self.max_resting_orders {
+            let order_id = self.take_resting(&instrument_id, 0);
+            return cancel(instrument_id, order_id);
+        }
+        let side = if self.next_u64().is_multiple_of(2) {
+            Side::Buy
+        } else {
+            Side::Sell
+        };
+        match self.between(0, 99) {
+            0..60 => {
+                let distance = self.between(1, 20);
+                let price = match side {
+                    Side::Buy => MID_PRICE - distance,
+                    Side::Sell => MID_PRICE + distance,
+                };
+                let quantity = self.between(1, 100);
+                let order_id = self.new_order_id();
+                self.resting
+                    .entry(instrument_id.clone())
+                    .or_default()
+                    .push_back(order_id);
+                create(
+                    instrument_id,
+                    order_id,
+                    side,
+                    price,
+                    quantity,
+                    OrderType::LIMIT,
+                )
+            }
+            60..80 if resting_count > 0 => {
+                let position = self.between(0, resting_count as u64 - 1) as usize;
+                let order_id = self.take_resting(&instrument_id, position);
+                cancel(instrument_id, order_id)
+            }
+            80..90 if resting_count > 0 => {
+                let position = self.between(0, resting_count as u64 - 1) as usize;
+                let order_id = self.resting[&instrument_id][position];
+                let distance = self.between(1, 20);
+                let price = match side {
+                    Side::Buy => MID_PRICE - distance,
+                    Side::Sell => MID_PRICE + distance,
+                };
+                EngineCommand::OrderModify(OrderModifyPayload {
+                    instrument_id,
+                    order_id,
+                    price,
+                    quantity: self.between(1, 100),
+                    client_order_id: None,
+                    participant_id: None,
+                    sequence: None,
+                })
+            }
+            _ => {
+                let order_id = self.new_order_id();
+                let quantity = self.between(1, 50);
+                create(
+                    instrument_id,
+                    order_id,
+                    side,
+                    0,
+                    quantity,
+                    OrderType::MARKET,
+                )
+            }
+        }
+    }
+
+    fn new_order_id(&mut self) -> u64 {
+        let order_id = self.next_order_id;
+        self.next_order_id += 1;
+        order_id
+    }
+
+    fn take_resting(&mut self, instrument_id: &str, position: usize) -> u64 {
+        self.resting
+            .get_mut(instrument_id)
+            .and_then(|orders| orders.remove(position))
+            .unwrap_or_default()
+    }
+}
+
+fn create(
+    instrument_id: String,
+    order_id: u64,
+    side: Side,
+    price: u64,
+    quantity: u64,
+    order_type: OrderType,
+) -> EngineCommand {
+    EngineCommand::OrderCreate(OrderCreatePayload {
+        order_id,
+        instrument_id,
+        quantity,
+        price,
+        side,
+        time_in_force: TimeInForce::Gtc,
+        order_type,
+        participant_id: None,
+        oms_id: None,
+        client_order_id: None,
+        tags: OrderTags::new(),
+        visible_quantity: None,
+        hidden_quantity: None,
+        liquidation: false,
+        min_quantity: None,
+        all_or_none: false,
+        sweep_limit: None,
+        sequence: None,
+    })
+}
+
+fn cancel(instrument_id: String, order_id: u64) -> EngineCommand {
+    EngineCommand::OrderCancel(OrderCancelPayload {
+        order_id,
+        instrument_id,
+        client_order_id: None,
+        participant_id: None,
+        sequence: None,
+    })
+}
+
+/// Resident memory of the process at some point of the run
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MemorySample {
+    pub elapsed_secs: u64,
+    pub resident_bytes: u64,
+}
+
+/// Outcome of a soak run
+#[derive(Debug, Default, Serialize)]
+pub struct SoakReport {
+    pub passed: bool,
+    /// Why the run failed; empty when it passed
+    pub failures: Vec<String>,
+    pub elapsed_secs: u64,
+    pub commands_sent: u64,
+    pub commands_per_sec: f64,
+    pub invariant_violations: u64,
+    /// Every alert the engine raised, violations included
+    pub alerts: u64,
+    pub engine_crashed: bool,
+    /// Resident memory once the warmup was over
+    pub baseline_resident_bytes: Option<u64>,
+    pub peak_resident_bytes: Option<u64>,
+    pub final_resident_bytes: Option<u64>,
+    pub memory_growth_pct: Option<f64>,
+    pub samples: Vec<MemorySample>,
+}
+
+impl SoakReport {
+    /// Works out memory growth and the verdict from what was collected
+    fn finish(&mut self, config: &SoakConfig) {
+        self.commands_per_sec = self.commands_sent as f64 / self.elapsed_secs.max(1) as f64;
+        let after_warmup: Vec<u64> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.elapsed_secs >= config.warmup_secs)
+            .map(|sample| sample.resident_bytes)
+            .collect();
+        self.baseline_resident_bytes = after_warmup.first().copied();
+        self.final_resident_bytes = after_warmup.last().copied();
+        self.peak_resident_bytes = self
+            .samples
+            .iter()
+            .map(|sample| sample.resident_bytes)
+            .max();
+        if let (Some(baseline), Some(last)) =
+            (self.baseline_resident_bytes, self.final_resident_bytes)
+        {
+            self.memory_growth_pct =
+                Some((last as f64 - baseline as f64) / baseline.max(1) as f64 * 100.0);
+        }
+
+        let mut failures = Vec::new();
+        if self.engine_crashed {
+            failures.push("the engine task stopped before the end of the run".to_string());
+        }
+        if self.commands_sent == 0 {
+            failures.push("no commands were processed".to_string());
+        }
+        if self.invariant_violations > 0 {
+            failures.push(format!(
+                "{} invariant violations were reported",
+                self.invariant_violations
+            ));
+        }
+        match self.memory_growth_pct {
+            Some(growth) if growth > config.max_memory_growth_pct => failures.push(format!(
+                "resident memory grew {:.1}% after warmup, more than the allowed {:.1}%",
+                growth, config.max_memory_growth_pct
+            )),
+            Some(_) => {}
+            None => failures.push(
+                "resident memory could not be measured after warmup, \
+                 so growth cannot be ruled out"
+                    .to_string(),
+            ),
+        }
+        self.passed = failures.is_empty();
+        self.failures = failures;
+    }
+}
+
+/// Resident set size of this process, from `/proc/self/status`
+fn resident_bytes() -> Option<u64> {
+    parse_resident_bytes(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+fn parse_resident_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Runs the engine under generated load for `duration_secs`, without Kafka
+///
+/// Everything the engine publishes is discarded except alerts, which are
+/// counted. Invariant checks run after every command as configured in
+/// `engine_config.diagnostics`.
+pub async fn run(config: SoakConfig, mut engine_config: EngineConfig) -> SoakReport {
+    info!(
+        "Soak run for {}s at {} commands/s on {:?}",
+        config.duration_secs, config.commands_per_sec, config.instruments
+    );
+    let (publisher, mut outbound) = Publisher::channel(65_536);
+    let alerts = Arc::new(AtomicU64::new(0));
+    let violations = Arc::new(AtomicU64::new(0));
+    let drain = {
+        let (alerts, violations) = (alerts.clone(), violations.clone());
+        tokio::spawn(async move {
+            while let Some(message) = outbound.recv().await {
+                if message.topic != ALERTS_TOPIC {
+                    continue;
+                }
+                alerts.fetch_add(1, Ordering::Relaxed);
+                if message.payload.contains(r#""kind":"invariant_violation""#) {
+                    violations.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        })
+    };
+    // Generated books are not worth keeping
+    engine_config.shutdown.snapshot_dir = None;
+    let (tx, rx) = lanes::channel(1024, 256);
+    let mut engine = tokio::spawn(engine::run_engine(rx, publisher, engine_config));
+
+    let mut generator = LoadGenerator::new(&config);
+    let mut report = SoakReport::default();
+    let started = Instant::now();
+    let duration = Duration::from_secs(config.duration_secs);
+    let sample_interval = Duration::from_secs(config.sample_interval_secs.max(1));
+    let mut next_sample = Duration::ZERO;
+    // Commands go out in batches every 10ms to approach the configured rate
+    let mut pace = tokio::time::interval(Duration::from_millis(10));
+    let mut owed = 0.0;
+    while started.elapsed() < duration && !engine.is_finished() {
+        pace.tick().await;
+        owed += config.commands_per_sec as f64 / 100.0;
+        while owed >= 1.0 {
+            if tx.send(generator.next_command()).await.is_err() {
+                break;
+            }
+            report.commands_sent += 1;
+            owed -= 1.0;
+        }
+        let elapsed = started.elapsed();
+        if elapsed >= next_sample {
+            next_sample = elapsed + sample_interval;
+            if let Some(resident_bytes) = resident_bytes() {
+                report.samples.push(MemorySample {
+                    elapsed_secs: elapsed.as_secs(),
+                    resident_bytes,
+                });
+            }
+            info!(
+                "Soak at {}s: {} commands, {} invariant violations, {} MiB resident",
+                elapsed.as_secs(),
+                report.commands_sent,
+                violations.load(Ordering::Relaxed),
+                report
+                    .samples
+                    .last()
+                    .map_or(0, |sample| sample.resident_bytes >> 20)
+            );
+        }
+    }
+    report.elapsed_secs = started.elapsed().as_secs();
+    report.engine_crashed = engine.is_finished();
+    drop(tx);
+    if let Err(e) = (&mut engine).await {
+        error!("Engine task failed during the soak run: {}", e);
+        report.engine_crashed = true;
+    }
+    let _ = drain.await;
+    report.alerts = alerts.load(Ordering::Relaxed);
+    report.invariant_violations = violations.load(Ordering::Relaxed);
+    report.finish(&config);
+    report
+}
+
+/// Logs the report and writes it to `report_path`, returning whether the run passed
+pub fn publish_report(config: &SoakConfig, report: &SoakReport) -> bool {
+    let json = serde_json::to_string_pretty(report).unwrap_or_default();
+    if report.passed {
+        info!("Soak run passed:\n{}", json);
+    } else {
+        error!("Soak run failed: {}\n{}", report.failures.join("; "), json);
+    }
+    if let Some(path) = &config.report_path {
+        let written = std::path::Path::new(path)
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(path, &json));
+        if let Err(e) = written {
+            error!("Failed to write soak report to {}: {}", path, e);
+        }
+    }
+    report.passed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SoakConfig {
+        SoakConfig {
+            instruments: vec!["A".to_string(), "B".to_string()],
+            max_resting_orders: 50,
+            warmup_secs: 10,
+            ..SoakConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_generated_load_is_repeatable_and_bounded() {
+        let describe = |command: EngineCommand| format!("{command:?}");
+        let mut first = LoadGenerator::new(&config());
+        let mut second = LoadGenerator::new(&config());
+        for _ in 0..10_000 {
+            assert_eq!(
+                describe(first.next_command()),
+                describe(second.next_command())
+            );
+        }
+        for instrument in ["A", "B"] {
+            assert!(first.resting[instrument].len() <= 50);
+        }
+        let kinds: Vec<String> = (0..1_000)
+            .map(|_| describe(first.next_command()))
+            .map(|command| command.split('(').next().unwrap().to_string())
+            .collect();
+        for kind in ["OrderCreate", "OrderCancel", "OrderModify"] {
+            assert!(kinds.iter().any(|k| k == kind), "no {kind} generated");
+        }
+    }
+
+    fn report(samples: &[(u64, u64)], violations: u64) -> SoakReport {
+        let mut report = SoakReport {
+            elapsed_secs: 100,
+            commands_sent: 1_000,
+            invariant_violations: violations,
+            samples: samples
+                .iter()
+                .map(|&(elapsed_secs, resident_bytes)| MemorySample {
+                    elapsed_secs,
+                    resident_bytes,
+                })
+                .collect(),
+            ..SoakReport::default()
+        };
+        report.finish(&config());
+        report
+    }
+
+    #[test]
+    fn test_report_fails_on_violations_or_memory_growth() {
+        // Growth during warmup does not count
+        let healthy = report(&[(0, 100), (10, 400), (50, 420), (100, 410)], 0);
+        assert!(healthy.passed, "{:?}", healthy.failures);
+        assert_eq!(healthy.memory_growth_pct, Some(2.5));
+        assert_eq!(healthy.peak_resident_bytes, Some(420));
+        assert_eq!(healthy.commands_per_sec, 10.0);
+
+        let leaking = report(&[(10, 400), (100, 480)], 0);
+        assert!(!leaking.passed);
+        assert_eq!(leaking.failures.len(), 1);
+
+        let broken = report(&[(10, 400), (100, 400)], 3);
+        assert_eq!(
+            broken.failures,
+            vec!["3 invariant violations were reported".to_string()]
+        );
+
+        assert!(!report(&[(0, 400)], 0).passed);
+    }
+
+    #[test]
+    fn test_resident_memory_is_read_from_proc_status() {
+        let status = "Name:\torderbook-rust\nVmPeak:\t  20000 kB\nVmRSS:\t   1536 kB\n";
+        assert_eq!(parse_resident_bytes(status), Some(1536 * 1024));
+        assert_eq!(parse_resident_bytes("Name:\tx\n"), None);
+    }
+}
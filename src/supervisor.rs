@@ -0,0 +1,259 @@
+// src/supervisor.rs
+use crate::alerts::{Alert, AlertKind, emit_alert};
+use crate::config::supervisor::{PanicPolicy, SupervisorConfig};
+use crate::engine::{self, EngineConfig};
+use crate::lanes::CommandLanes;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::snapshot::OrderBookSnapshot;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use crate::watchdog::Progress;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Time given to the publisher to send the panic alert before the process exits
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
+
+/// Copy of every book, taken by the engine for a restarted engine to start from
+///
+/// Held outside the engine task so it survives the task's panic.
+#[derive(Clone, Default)]
+pub struct Checkpoints {
+    inner: Arc<Mutex<Checkpoint>>,
+}
+
+#[derive(Default)]
+struct Checkpoint {
+    taken_at: u64,
+    books: Vec<OrderBookSnapshot>,
+}
+
+impl Checkpoints {
+    /// Replaces the checkpoint with the current state of every book
+    pub fn store(&self, manager: &BookManagerStd<OrderTags>, now: u64) {
+        // Snapshot before locking, the books can be large
+        let books = manager
+            .symbols()
+            .iter()
+            .filter_map(|symbol| manager.get_book(symbol))
+            .map(|book| book.create_snapshot(usize::MAX))
+            .collect();
+        *self.inner.lock().unwrap_or_else(PoisonError::into_inner) = Checkpoint {
+            taken_at: now,
+            books,
+        };
+    }
+
+    /// Recreates the checkpointed books in an empty manager, returning how many
+    /// were restored and when the checkpoint was taken
+    pub fn restore(&self, manager: &mut BookManagerStd<OrderTags>) -> Option<(usize, u64)> {
+        let checkpoint = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        if checkpoint.taken_at == 0 {
+            return None;
+        }
+        let mut restored = 0;
+        for snapshot in &checkpoint.books {
+            manager.add_book(&snapshot.symbol);
+            let Some(book) = manager.get_book(&snapshot.symbol) else {
+                continue;
+            };
+            match book.restore_from_snapshot(snapshot.clone()) {
+                Ok(()) => restored += 1,
+                Err(e) => warn!(
+                    "Failed to restore {} from checkpoint: {}",
+                    snapshot.symbol, e
+                ),
+            }
+        }
+        Some((restored, checkpoint.taken_at))
+    }
+}
+
+/// Restarts within a sliding window, so a crash loop ends in a shutdown
+struct RestartBudget {
+    max_restarts: usize,
+    window_ms: u64,
+    restarts: VecDeque<u64>,
+}
+
+impl RestartBudget {
+    fn new(config: &SupervisorConfig) -> Self {
+        Self {
+            max_restarts: config.max_restarts as usize,
+            window_ms: config.restart_window_secs * 1_000,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Counts a restart at `now`, or returns false if the budget is spent
+    fn try_restart(&mut self, now: u64) -> bool {
+        while let Some(&at) = self.restarts.front()
+            && now.saturating_sub(at) >= self.window_ms
+        {
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= self.max_restarts {
+            return false;
+        }
+        self.restarts.push_back(now);
+        true
+    }
+}
+
+/// Details of an engine panic alert
+#[derive(Debug, Serialize)]
+struct EnginePanic<'a> {
+    panic: &'a str,
+    restarting: bool,
+    /// Restarts within the current window, this one included
+    recent_restarts: usize,
+}
+
+/// Runs the engine, watching for its task to die
+///
+/// A panic is alerted on, then handled per the policy: the process exits, or
+/// the engine is respawned on the same command channel with the books of the
+/// last checkpoint. Commands queued meanwhile are kept; the one that panicked
+/// and any applied since the checkpoint are lost. Returns once the command
+/// channel closes.
+pub async fn supervise(
+    rx: CommandLanes,
+    publisher: Publisher,
+    config: EngineConfig,
+    progress: Progress,
+) {
+    let supervisor = config.supervisor.clone();
+    // Locked by the running engine; a panic unwinds its guard and frees it
+    let rx = Arc::new(tokio::sync::Mutex::new(rx));
+    let checkpoints = (supervisor.policy == PanicPolicy::Restart).then(Checkpoints::default);
+    let mut budget = RestartBudget::new(&supervisor);
+    loop {
+        let task = tokio::spawn({
+            let rx = rx.clone();
+            let publisher = publisher.clone();
+            let config = config.clone();
+            let checkpoints = checkpoints.clone();
+            let progress = progress.clone();
+            async move {
+                let mut rx = rx.lock_owned().await;
+                engine::run_engine_with(&mut rx, publisher, config, checkpoints, progress).await;
+            }
+        });
+        let panic = match task.await {
+            Ok(()) => return,
+            Err(e) if e.is_panic() => panic_message(e.into_panic()),
+            Err(e) => e.to_string(),
+        };
+        // The command being applied is gone with the task
+        progress.lost();
+        let restarting =
+            supervisor.policy == PanicPolicy::Restart && budget.try_restart(current_time_millis());
+        let message = if restarting {
+            format!("Engine task panicked, restarting: {}", panic)
+        } else {
+            format!("Engine task panicked, shutting down: {}", panic)
+        };
+        emit_alert(
+            &publisher,
+            Alert::new(AlertKind::EnginePanic, "engine", message).with_details(&EnginePanic {
+                panic: &panic,
+                restarting,
+                recent_restarts: budget.restarts.len(),
+            }),
+        );
+        if !restarting {
+            if supervisor.policy == PanicPolicy::Restart {
+                error!(
+                    "Engine panicked {} times within {} s, giving up",
+                    budget.restarts.len() + 1,
+                    supervisor.restart_window_secs
+                );
+            }
+            tokio::time::sleep(SHUTDOWN_GRACE).await;
+            std::process::exit(1);
+        }
+        tokio::time::sleep(Duration::from_millis(supervisor.restart_delay_ms)).await;
+        info!("Restarting engine task");
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    #[test]
+    fn test_restart_budget_slides() {
+        let mut budget = RestartBudget::new(&SupervisorConfig {
+            max_restarts: 2,
+            restart_window_secs: 10,
+            ..SupervisorConfig::default()
+        });
+        assert!(budget.try_restart(1_000));
+        assert!(budget.try_restart(2_000));
+        assert!(!budget.try_restart(3_000));
+        // The first restart has left the window
+        assert!(budget.try_restart(11_000));
+        assert!(!budget.try_restart(11_500));
+    }
+
+    #[test]
+    fn test_checkpoint_restores_resting_orders() {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        let checkpoints = Checkpoints::default();
+        assert_eq!(checkpoints.restore(&mut manager), None);
+
+        manager.add_book("BTC");
+        let book = manager.get_book("BTC").unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            5,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(2),
+            101,
+            3,
+            Side::Sell,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+        let hash = book.create_snapshot(usize::MAX).state_hash();
+        checkpoints.store(&manager, 42);
+
+        let mut restarted = BookManagerStd::<OrderTags>::new();
+        assert_eq!(checkpoints.restore(&mut restarted), Some((1, 42)));
+        let book = restarted.get_book("BTC").unwrap();
+        assert_eq!(book.create_snapshot(usize::MAX).state_hash(), hash);
+        // Restored orders can still be cancelled by id
+        assert!(book.cancel_order(OrderId::from_u64(1)).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("book {} broke", "BTC")).unwrap_err();
+        assert_eq!(panic_message(payload), "book BTC broke");
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload), "static");
+    }
+}
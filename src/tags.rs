@@ -0,0 +1,118 @@
+// src/tags.rs
+use crate::orderbook::OrderExtraFields;
+use crate::orderbook::trade::TradeResult;
+use pricelevel::OrderId;
+use std::collections::BTreeMap;
+
+/// Free-form key/value metadata of an order, such as a strategy or a routing
+/// hint. Kept as the order's extra fields in the book and echoed in the order
+/// and trade events it appears in.
+pub type OrderTags = BTreeMap<String, String>;
+
+/// Tag the engine puts on liquidation orders, so they are known as such while
+/// they rest and in the trades they take part in
+pub const LIQUIDATION_TAG: &str = "liquidation";
+
+/// Sets or clears the liquidation tag; only the order's `liquidation` flag
+/// decides it, whatever tags the order came with
+pub fn mark_liquidation(tags: &mut OrderTags, liquidation: bool) {
+    if liquidation {
+        tags.insert(LIQUIDATION_TAG.to_string(), "true".to_string());
+    } else {
+        tags.remove(LIQUIDATION_TAG);
+    }
+}
+
+/// Whether tags are those of a liquidation order
+pub fn is_liquidation(tags: &OrderTags) -> bool {
+    tags.get(LIQUIDATION_TAG)
+        .is_some_and(|value| value == "true")
+}
+
+/// Tags of an order that took part in a trade, empty when it has none
+pub fn trade_tags(trade_result: &TradeResult, order_id: OrderId) -> OrderTags {
+    trade_result
+        .extra_fields
+        .iter()
+        .find(|entry| entry.order_id == order_id)
+        .and_then(|entry| serde_json::from_value(entry.fields.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Adds the tags of the incoming order to a trade result that lacks them, as
+/// the book only knows the tags of orders once they rest
+pub fn attach_taker_tags(trade_result: &mut TradeResult, tags: &OrderTags) {
+    let taker = trade_result.match_result.order_id;
+    if tags.is_empty()
+        || trade_result
+            .extra_fields
+            .iter()
+            .any(|entry| entry.order_id == taker)
+    {
+        return;
+    }
+    if let Ok(fields) = serde_json::to_value(tags) {
+        trade_result.extra_fields.insert(
+            0,
+            OrderExtraFields {
+                order_id: taker,
+                fields,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::OrderBook;
+    use crate::orderbook::trade::TradeResult;
+    use pricelevel::{Side, TimeInForce};
+    use std::sync::{Arc, Mutex};
+
+    fn tags(strategy: &str) -> OrderTags {
+        OrderTags::from([("strategy".to_string(), strategy.to_string())])
+    }
+
+    #[test]
+    fn test_trade_results_carry_maker_and_taker_tags() {
+        let trades = Arc::new(Mutex::new(Vec::new()));
+        let sink = trades.clone();
+        let mut book = OrderBook::<OrderTags>::with_trade_listener(
+            "BTC",
+            Arc::new(move |trade: &TradeResult| sink.lock().unwrap().push(trade.clone())),
+        );
+        book.serialize_extra_fields();
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            5,
+            Side::Sell,
+            TimeInForce::Gtc,
+            Some(tags("mm")),
+        )
+        .unwrap();
+        book.match_limit_order(OrderId::from_u64(2), 5, Side::Buy, 100)
+            .unwrap();
+
+        let mut trade = trades.lock().unwrap().remove(0);
+        assert_eq!(trade_tags(&trade, OrderId::from_u64(1)), tags("mm"));
+        assert!(trade_tags(&trade, OrderId::from_u64(2)).is_empty());
+        attach_taker_tags(&mut trade, &tags("twap"));
+        assert_eq!(trade_tags(&trade, OrderId::from_u64(2)), tags("twap"));
+        // Tags the book already reported are kept
+        attach_taker_tags(&mut trade, &tags("other"));
+        assert_eq!(trade_tags(&trade, OrderId::from_u64(2)), tags("twap"));
+    }
+
+    #[test]
+    fn test_only_the_flag_makes_a_liquidation() {
+        let mut forged = tags("twap");
+        forged.insert(LIQUIDATION_TAG.to_string(), "true".to_string());
+        mark_liquidation(&mut forged, false);
+        assert_eq!(forged, tags("twap"));
+        mark_liquidation(&mut forged, true);
+        assert!(is_liquidation(&forged));
+        assert_eq!(forged["strategy"], "twap");
+    }
+}
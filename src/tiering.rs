@@ -0,0 +1,383 @@
+// src/tiering.rs
+use crate::config::tiering::TieringConfig;
+use crate::expiry::ExpiryManager;
+use crate::helpers::{EngineCommand, InstrumentCreatePayload, handle_instrument_create};
+use crate::orderbook::OrderBook;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::snapshot::OrderBookSnapshotPackage;
+use crate::tags::OrderTags;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+const COLD_EXTENSION: &str = "json";
+
+/// A book written to disk, with the create command it is rebuilt from
+#[derive(Debug, Serialize, Deserialize)]
+struct ColdBook {
+    create: serde_json::Value,
+    snapshot: OrderBookSnapshotPackage,
+    evicted_at: u64,
+}
+
+/// Evicts books that went without a command for a while to disk, and brings
+/// them back ahead of the next command naming them
+///
+/// A hydrated book is rebuilt by its create command, so it has the settings it
+/// was created with, and restored from the snapshot taken on eviction. Flight
+/// recorders and trade tapes start empty again. Books restored from a
+/// supervisor checkpoint stay hot, having no create command to be rebuilt by.
+pub struct BookTiering {
+    config: TieringConfig,
+    directory: PathBuf,
+    /// Create command of every hot book, as JSON
+    creates: HashMap<String, serde_json::Value>,
+    /// When each hot book last saw a command
+    last_command: HashMap<String, u64>,
+    /// Instruments whose book is on disk
+    cold: HashSet<String>,
+}
+
+impl BookTiering {
+    pub fn new(config: TieringConfig) -> Self {
+        let directory = PathBuf::from(&config.directory);
+        Self {
+            config,
+            directory,
+            creates: HashMap::new(),
+            last_command: HashMap::new(),
+            cold: HashSet::new(),
+        }
+    }
+
+    /// Finds the books left cold on disk by an earlier run, dropping the copies
+    /// of them `manager` was restored with
+    ///
+    /// A cold file always holds the newest state of its book, since any command
+    /// naming the book would have hydrated it and removed the file.
+    pub fn load(&mut self, manager: &mut BookManagerStd<OrderTags>) {
+        if !self.config.enabled {
+            return;
+        }
+        match self.cold_files() {
+            Ok(cold) => self.cold = cold,
+            Err(e) => error!(
+                "Failed to read the cold books in {}: {}",
+                self.directory.display(),
+                e
+            ),
+        }
+        for instrument_id in &self.cold {
+            manager.remove_book(instrument_id);
+            self.creates.remove(instrument_id);
+        }
+        if !self.cold.is_empty() {
+            info!("{} books are cold on disk", self.cold.len());
+        }
+    }
+
+    pub fn is_cold(&self, instrument_id: &str) -> bool {
+        self.cold.contains(instrument_id)
+    }
+
+    /// Every instrument whose book is on disk
+    pub fn cold_instruments(&self) -> Vec<String> {
+        self.cold.iter().cloned().collect()
+    }
+
+    /// Notes the create command of a new book and when the instrument of a
+    /// command last saw one
+    pub fn record(&mut self, cmd: &EngineCommand, manager: &BookManagerStd<OrderTags>, now: u64) {
+        if !self.config.enabled {
+            return;
+        }
+        match cmd {
+            EngineCommand::InstrumentCreate(create) if !manager.has_book(&create.instrument_id) => {
+                match serde_json::to_value(create) {
+                    Ok(create_json) => {
+                        self.creates
+                            .insert(create.instrument_id.clone(), create_json);
+                    }
+                    Err(e) => warn!(
+                        "{} cannot be evicted, failed to serialize its create command: {}",
+                        create.instrument_id, e
+                    ),
+                }
+            }
+            EngineCommand::InstrumentDelete(delete) => {
+                self.creates.remove(&delete.instrument_id);
+                self.last_command.remove(&delete.instrument_id);
+                return;
+            }
+            _ => {}
+        }
+        if let Some(instrument_id) = cmd.instrument_id() {
+            self.last_command.insert(instrument_id.to_string(), now);
+        }
+    }
+
+    /// Writes every book idle for `idle_ms` to disk and removes it from
+    /// `manager`, unless `keep_hot` holds for it
+    ///
+    /// Returns the instruments evicted.
+    pub fn evict_idle<F>(
+        &mut self,
+        manager: &mut BookManagerStd<OrderTags>,
+        now: u64,
+        keep_hot: F,
+    ) -> Vec<String>
+    where
+        F: Fn(&str, &OrderBook<OrderTags>) -> bool,
+    {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+        let idle: Vec<String> = self
+            .last_command
+            .iter()
+            .filter(|&(_, &last)| now.saturating_sub(last) >= self.config.idle_ms)
+            .map(|(instrument_id, _)| instrument_id.clone())
+            .collect();
+        let mut evicted = Vec::new();
+        for instrument_id in idle {
+            let Some(book) = manager.get_book(&instrument_id) else {
+                self.last_command.remove(&instrument_id);
+                continue;
+            };
+            let Some(create) = self.creates.get(&instrument_id) else {
+                continue;
+            };
+            if keep_hot(&instrument_id, book) {
+                continue;
+            }
+            let written = book
+                .create_snapshot_package(usize::MAX)
+                .map_err(|e| io::Error::other(e.to_string()))
+                .and_then(|snapshot| {
+                    self.write(
+                        &instrument_id,
+                        &ColdBook {
+                            create: create.clone(),
+                            snapshot,
+                            evicted_at: now,
+                        },
+                    )
+                });
+            if let Err(e) = written {
+                error!(
+                    "Failed to evict {}, it stays in memory: {}",
+                    instrument_id, e
+                );
+                continue;
+            }
+            manager.remove_book(&instrument_id);
+            self.creates.remove(&instrument_id);
+            self.last_command.remove(&instrument_id);
+            self.cold.insert(instrument_id.clone());
+            evicted.push(instrument_id);
+        }
+        if !evicted.is_empty() {
+            info!(
+                "Evicted {} idle books, {} books are cold",
+                evicted.len(),
+                self.cold.len()
+            );
+        }
+        evicted
+    }
+
+    /// Brings a cold book back into `manager`, returning whether it did
+    ///
+    /// A book that fails to load stays cold, so nothing is lost and the next
+    /// command naming it tries again.
+    pub fn hydrate(
+        &mut self,
+        manager: &mut BookManagerStd<OrderTags>,
+        expiries: &mut ExpiryManager,
+        instrument_id: &str,
+    ) -> bool {
+        if !self.cold.contains(instrument_id) {
+            return false;
+        }
+        let path = self.path(instrument_id);
+        let cold = match read_cold_book(&path) {
+            Ok(cold) => cold,
+            Err(e) => {
+                error!("Failed to load cold book {}: {}", path.display(), e);
+                return false;
+            }
+        };
+        let create: InstrumentCreatePayload = match serde_json::from_value(cold.create.clone()) {
+            Ok(create) => create,
+            Err(e) => {
+                error!(
+                    "Failed to hydrate {}, its create command no longer parses: {}",
+                    instrument_id, e
+                );
+                return false;
+            }
+        };
+        handle_instrument_create(manager, expiries, create);
+        if let Some(book) = manager.get_book(instrument_id)
+            && let Err(e) = book.restore_from_snapshot_package(cold.snapshot)
+        {
+            error!("Failed to restore cold book {}: {}", instrument_id, e);
+            manager.remove_book(instrument_id);
+            return false;
+        }
+        self.forget_cold(instrument_id);
+        self.creates.insert(instrument_id.to_string(), cold.create);
+        info!("Hydrated {}, cold since {}", instrument_id, cold.evicted_at);
+        true
+    }
+
+    /// Snapshots of every cold book, for snapshots of the write-ahead log
+    pub fn cold_snapshots(&self) -> io::Result<Vec<OrderBookSnapshotPackage>> {
+        let mut snapshots = Vec::new();
+        for instrument_id in &self.cold {
+            snapshots.push(read_cold_book(&self.path(instrument_id))?.snapshot);
+        }
+        Ok(snapshots)
+    }
+
+    /// Create commands of the hot books, handed from a recovering engine to the
+    /// one that goes on
+    pub fn take_creates(&mut self) -> HashMap<String, serde_json::Value> {
+        std::mem::take(&mut self.creates)
+    }
+
+    /// Takes over the create commands recorded by a recovering engine
+    pub fn adopt(&mut self, creates: HashMap<String, serde_json::Value>) {
+        self.creates.extend(creates);
+    }
+
+    fn forget_cold(&mut self, instrument_id: &str) {
+        self.cold.remove(instrument_id);
+        let path = self.path(instrument_id);
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("Failed to remove cold book {}: {}", path.display(), e);
+        }
+    }
+
+    fn path(&self, instrument_id: &str) -> PathBuf {
+        self.directory
+            .join(format!("{instrument_id}.{COLD_EXTENSION}"))
+    }
+
+    /// Instruments with a cold file in the directory
+    fn cold_files(&self) -> io::Result<HashSet<String>> {
+        let mut files = HashSet::new();
+        if !self.directory.exists() {
+            return Ok(files);
+        }
+        for entry in std::fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == COLD_EXTENSION)
+                && let Some(instrument_id) = path.file_stem().and_then(|stem| stem.to_str())
+            {
+                files.insert(instrument_id.to_string());
+            }
+        }
+        Ok(files)
+    }
+
+    /// Writes a cold book aside and renames it into place
+    fn write(&self, instrument_id: &str, cold: &ColdBook) -> io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.path(instrument_id);
+        let partial = path.with_extension(format!("{COLD_EXTENSION}.partial"));
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        serde_json::to_writer(&mut writer, cold).map_err(io::Error::other)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        std::fs::rename(&partial, &path)
+    }
+}
+
+fn read_cold_book(path: &Path) -> io::Result<ColdBook> {
+    let reader = BufReader::new(File::open(path)?);
+    serde_json::from_reader(reader).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::instruments::InstrumentEventsConfig;
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    fn create(instrument_id: &str) -> EngineCommand {
+        EngineCommand::InstrumentCreate(
+            serde_json::from_value(serde_json::json!({
+                "instrument_id": instrument_id,
+                "trade_tape_capacity": 16,
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_idle_books_go_cold_and_come_back_as_they_were() {
+        let directory = std::env::temp_dir().join(format!("cold-books-{}", uuid::Uuid::new_v4()));
+        let config = TieringConfig {
+            enabled: true,
+            directory: directory.to_string_lossy().into_owned(),
+            idle_ms: 1_000,
+            sweep_interval_ms: 100,
+        };
+        let mut tiering = BookTiering::new(config.clone());
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        let mut expiries = ExpiryManager::new(InstrumentEventsConfig::default());
+        for (instrument_id, now) in [("BTC", 0), ("ETH", 500)] {
+            let cmd = create(instrument_id);
+            tiering.record(&cmd, &manager, now);
+            let EngineCommand::InstrumentCreate(create) = cmd else {
+                unreachable!()
+            };
+            handle_instrument_create(&mut manager, &mut expiries, create);
+        }
+        let book = manager.get_book("BTC").unwrap();
+        book.add_limit_order(
+            OrderId::from_u64(1),
+            100,
+            5,
+            Side::Buy,
+            TimeInForce::Gtc,
+            None,
+        )
+        .unwrap();
+
+        // ETH has seen a command too recently, and books kept hot stay
+        assert!(
+            tiering
+                .evict_idle(&mut manager, 1_000, |_, _| true)
+                .is_empty()
+        );
+        assert_eq!(
+            tiering.evict_idle(&mut manager, 1_000, |_, _| false),
+            ["BTC"]
+        );
+        assert!(!manager.has_book("BTC"));
+        assert!(tiering.is_cold("BTC"));
+        assert_eq!(tiering.cold_snapshots().unwrap().len(), 1);
+
+        // A restarted engine finds the book still cold, even if restored
+        let mut tiering = BookTiering::new(config);
+        manager.add_book("BTC");
+        tiering.load(&mut manager);
+        assert!(!manager.has_book("BTC"));
+        assert_eq!(tiering.cold_instruments(), ["BTC"]);
+        assert!(tiering.hydrate(&mut manager, &mut expiries, "BTC"));
+        let book = manager.get_book("BTC").unwrap();
+        assert_eq!(book.best_bid(), Some(100));
+        assert!(book.trade_tape().is_some());
+        assert!(!tiering.is_cold("BTC"));
+        assert!(!tiering.hydrate(&mut manager, &mut expiries, "BTC"));
+        assert!(tiering.cold_files().unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(directory);
+    }
+}
@@ -0,0 +1,350 @@
+// src/trade_producer.rs
+use crate::config::trades::{TradeReportConfig, TradeSerializer};
+use crate::orderbook::scale::{InstrumentScale, NumberFormat, ScaledValue};
+use crate::orderbook::trade::{BookContext, TradeEvent};
+use crate::orderbook::{OrderBook, TapeTrade};
+use crate::publisher::Publisher;
+use crate::tags::{OrderTags, is_liquidation, trade_tags};
+use pricelevel::{OrderId, Side};
+use serde::Serialize;
+use tracing::warn;
+use uuid::Uuid;
+
+/// The result of a match on a book, as published on the trade topic
+#[derive(Debug, Serialize)]
+pub struct TradeExecution<'a> {
+    pub instrument_id: &'a str,
+    /// The incoming order that matched
+    pub order_id: OrderId,
+    pub executed_quantity: ScaledValue,
+    pub remaining_quantity: ScaledValue,
+    pub is_complete: bool,
+    pub fills: Vec<ExecutionFill>,
+    pub timestamp: u64,
+    /// Always false, telling executions apart from block trades on the same topic
+    pub off_book: bool,
+    /// The book's touch when the order arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book_context: Option<ScaledBookContext>,
+    /// Tags of the incoming order
+    #[serde(skip_serializing_if = "OrderTags::is_empty")]
+    pub tags: OrderTags,
+    /// Whether the incoming order or any order it filled against is a
+    /// liquidation
+    pub liquidation: bool,
+}
+
+/// [`BookContext`] in the instrument's scale
+#[derive(Debug, Serialize)]
+pub struct ScaledBookContext {
+    pub best_bid: Option<ScaledValue>,
+    pub best_ask: Option<ScaledValue>,
+    pub spread: Option<ScaledValue>,
+    pub bid_depth_at_touch: ScaledValue,
+    pub ask_depth_at_touch: ScaledValue,
+}
+
+impl ScaledBookContext {
+    pub fn new(context: &BookContext, scale: InstrumentScale, format: NumberFormat) -> Self {
+        let price = |price: Option<u64>| price.map(|price| scale.price(price, format));
+        Self {
+            best_bid: price(context.best_bid),
+            best_ask: price(context.best_ask),
+            spread: price(context.spread),
+            bid_depth_at_touch: scale.quantity(context.bid_depth_at_touch, format),
+            ask_depth_at_touch: scale.quantity(context.ask_depth_at_touch, format),
+        }
+    }
+}
+
+/// A fill of an execution against one resting order
+#[derive(Debug, Serialize)]
+pub struct ExecutionFill {
+    pub trade_id: Uuid,
+    pub price: ScaledValue,
+    pub quantity: ScaledValue,
+    pub aggressor_side: Side,
+    pub maker_order_id: OrderId,
+    pub timestamp: u64,
+    #[serde(skip_serializing_if = "OrderTags::is_empty")]
+    pub maker_tags: OrderTags,
+}
+
+impl<'a> TradeExecution<'a> {
+    pub fn new(
+        event: &'a TradeEvent,
+        book: &OrderBook<OrderTags>,
+        config: &TradeReportConfig,
+    ) -> Self {
+        let scale = book.scale();
+        let format = config.number_format;
+        let result = &event.trade_result.match_result;
+        let fills: Vec<ExecutionFill> = result
+            .transactions
+            .as_vec()
+            .iter()
+            .map(|transaction| ExecutionFill {
+                trade_id: transaction.transaction_id,
+                price: scale.price(transaction.price, format),
+                quantity: scale.quantity(transaction.quantity, format),
+                aggressor_side: transaction.taker_side,
+                maker_order_id: transaction.maker_order_id,
+                timestamp: transaction.timestamp,
+                maker_tags: trade_tags(&event.trade_result, transaction.maker_order_id),
+            })
+            .collect();
+        let tags = trade_tags(&event.trade_result, result.order_id);
+        let liquidation =
+            is_liquidation(&tags) || fills.iter().any(|fill| is_liquidation(&fill.maker_tags));
+        Self {
+            instrument_id: &event.symbol,
+            order_id: result.order_id,
+            executed_quantity: scale.quantity(result.executed_quantity(), format),
+            remaining_quantity: scale.quantity(result.remaining_quantity, format),
+            is_complete: result.is_complete,
+            fills,
+            timestamp: event.timestamp,
+            off_book: false,
+            book_context: event
+                .trade_result
+                .book_context
+                .map(|context| ScaledBookContext::new(&context, scale, format)),
+            tags,
+            liquidation,
+        }
+    }
+
+    /// Encodes the execution as the configured serializer writes it
+    pub fn serialize(&self, serializer: TradeSerializer) -> Result<String, serde_json::Error> {
+        match serializer {
+            TradeSerializer::Json => serde_json::to_string(self),
+            TradeSerializer::Csv => Ok(self
+                .fills
+                .iter()
+                .map(|fill| {
+                    format!(
+                        "{},{},{},{},{},{},{},{}",
+                        fill.trade_id,
+                        self.instrument_id,
+                        self.order_id,
+                        fill.maker_order_id,
+                        fill.aggressor_side,
+                        fill.price,
+                        fill.quantity,
+                        fill.timestamp
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+        }
+    }
+}
+
+/// A trade from a book's tape as published on the tape topic
+#[derive(Debug, Serialize)]
+pub struct TapePrint<'a> {
+    pub instrument_id: &'a str,
+    #[serde(flatten)]
+    pub trade: TapeTrade,
+}
+
+/// Publishes every match on a book to the trade topic, and each of its fills
+/// to the tape topic, keyed by instrument
+pub struct TradeProducer {
+    config: TradeReportConfig,
+}
+
+impl TradeProducer {
+    pub fn new(config: TradeReportConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn on_trade_event(
+        &self,
+        event: &TradeEvent,
+        book: &OrderBook<OrderTags>,
+        publisher: &Publisher,
+    ) {
+        if event.trade_result.match_result.transactions.is_empty() {
+            return;
+        }
+        if self.config.publish_tape {
+            self.publish_tape(event, book, publisher);
+        }
+        if !self.config.publish_executions {
+            return;
+        }
+        let execution = TradeExecution::new(event, book, &self.config);
+        match execution.serialize(self.config.serializer) {
+            Ok(payload) => {
+                publisher.publish_serialized(&self.config.trades_topic, &event.symbol, payload)
+            }
+            Err(e) => warn!(
+                "Failed to serialize execution of {} on {}: {}",
+                execution.order_id, event.symbol, e
+            ),
+        }
+    }
+
+    /// Prints the fills of a match as the book's tape recorded them, so the
+    /// tape topic and the tape queries share one sequence
+    fn publish_tape(&self, event: &TradeEvent, book: &OrderBook<OrderTags>, publisher: &Publisher) {
+        let Some(tape) = book.trade_tape() else {
+            return;
+        };
+        for transaction in event.trade_result.match_result.transactions.as_vec() {
+            let Some(trade) = tape.find(transaction.transaction_id) else {
+                warn!(
+                    "Trade {} on {} has left the tape before it was printed",
+                    transaction.transaction_id, event.symbol
+                );
+                continue;
+            };
+            let print = TapePrint {
+                instrument_id: &event.symbol,
+                trade,
+            };
+            publisher.publish(&self.config.tape_topic, &event.symbol, &print);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::manager::{BookManager, BookManagerStd};
+    use pricelevel::TimeInForce;
+
+    /// Rests two asks and sweeps them with a market buy
+    fn swept_book() -> (BookManagerStd<OrderTags>, TradeEvent) {
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        let book = manager.get_book_mut("BTC").unwrap();
+        book.set_scale(InstrumentScale {
+            price_decimals: 2,
+            quantity_decimals: 0,
+        });
+        book.enable_trade_tape(16);
+        for (id, price) in [(1, 10_000), (2, 10_050)] {
+            book.add_limit_order(
+                OrderId::from_u64(id),
+                price,
+                5,
+                Side::Sell,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        book.submit_market_order(OrderId::from_u64(3), 8, Side::Buy)
+            .unwrap();
+        let mut events = manager.drain_trade_events();
+        assert_eq!(events.len(), 1);
+        (manager, events.remove(0))
+    }
+
+    #[test]
+    fn test_execution_lists_every_fill() {
+        let (manager, event) = swept_book();
+        let config = TradeReportConfig {
+            number_format: NumberFormat::Decimal,
+            ..TradeReportConfig::default()
+        };
+        let execution = TradeExecution::new(&event, manager.get_book("BTC").unwrap(), &config);
+        let json: serde_json::Value =
+            serde_json::from_str(&execution.serialize(TradeSerializer::Json).unwrap()).unwrap();
+
+        assert_eq!(json["instrument_id"], "BTC");
+        assert_eq!(json["executed_quantity"], "8");
+        assert_eq!(json["remaining_quantity"], "0");
+        assert_eq!(json["is_complete"], true);
+        assert_eq!(json["off_book"], false);
+        let fills = json["fills"].as_array().unwrap();
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills[0]["price"], "100.00");
+        assert_eq!(fills[1]["price"], "100.50");
+        assert_eq!(fills[1]["quantity"], "3");
+        // The touch the market buy saw before sweeping two levels
+        let context = &json["book_context"];
+        assert_eq!(context["best_bid"], serde_json::Value::Null);
+        assert_eq!(context["best_ask"], "100.00");
+        assert_eq!(context["spread"], serde_json::Value::Null);
+        assert_eq!(context["ask_depth_at_touch"], "5");
+        assert_eq!(context["bid_depth_at_touch"], "0");
+    }
+
+    #[test]
+    fn test_csv_writes_a_line_per_fill() {
+        let (manager, event) = swept_book();
+        let execution = TradeExecution::new(
+            &event,
+            manager.get_book("BTC").unwrap(),
+            &TradeReportConfig::default(),
+        );
+        let csv = execution.serialize(TradeSerializer::Csv).unwrap();
+        let lines: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+
+        let id = |id: u64| OrderId::from_u64(id).to_string();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0][1..7],
+            ["BTC", id(3).as_str(), id(1).as_str(), "BUY", "10000", "5"]
+        );
+        assert_eq!(
+            lines[1][1..7],
+            ["BTC", id(3).as_str(), id(2).as_str(), "BUY", "10050", "3"]
+        );
+    }
+
+    #[test]
+    fn test_executions_are_keyed_by_instrument() {
+        let (manager, event) = swept_book();
+        let (publisher, mut outbound) = Publisher::channel(8);
+        let book = manager.get_book("BTC").unwrap();
+
+        TradeProducer::new(TradeReportConfig::default()).on_trade_event(&event, book, &publisher);
+        let message = outbound.try_recv().unwrap();
+        assert_eq!(message.topic, "trade.executed");
+        assert_eq!(message.key, "BTC");
+
+        TradeProducer::new(TradeReportConfig {
+            publish_executions: false,
+            ..TradeReportConfig::default()
+        })
+        .on_trade_event(&event, book, &publisher);
+        assert!(outbound.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_tape_prints_each_fill_in_sequence() {
+        let (manager, event) = swept_book();
+        let (publisher, mut outbound) = Publisher::channel(8);
+        let book = manager.get_book("BTC").unwrap();
+        let producer = TradeProducer::new(TradeReportConfig {
+            publish_executions: false,
+            publish_tape: true,
+            ..TradeReportConfig::default()
+        });
+
+        producer.on_trade_event(&event, book, &publisher);
+        let prints: Vec<serde_json::Value> = std::iter::from_fn(|| outbound.try_recv().ok())
+            .map(|message| {
+                assert_eq!(
+                    (message.topic.as_str(), message.key.as_str()),
+                    ("trade.tape", "BTC")
+                );
+                serde_json::from_str(&message.payload).unwrap()
+            })
+            .collect();
+        let sequences: Vec<_> = prints
+            .iter()
+            .map(|print| print["sequence"].clone())
+            .collect();
+        assert_eq!(sequences, [1, 2]);
+        let id = |id: u64| serde_json::to_value(OrderId::from_u64(id)).unwrap();
+        assert_eq!(prints[1]["taker_order_id"], id(3));
+        assert_eq!(prints[1]["maker_order_id"], id(2));
+        assert_eq!(prints[1]["aggressor_side"], "BUY");
+        assert_eq!(prints[1]["price"], 10_050);
+    }
+}
@@ -0,0 +1,271 @@
+//! Splitting of oversized messages into chunks, and their reassembly
+//!
+//! A payload larger than the broker's maximum message size is published as a
+//! package of chunks sharing a package id. Each chunk carries its part number, the
+//! number of parts and a SHA-256 checksum of the whole payload in message headers,
+//! so consumers can reassemble the package with a [`ChunkAssembler`] and verify it.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Header carrying the id shared by every chunk of a package
+pub const HEADER_PACKAGE_ID: &str = "chunk-package-id";
+/// Header carrying the zero-based part number of a chunk
+pub const HEADER_PART: &str = "chunk-part";
+/// Header carrying the number of parts in the package
+pub const HEADER_PARTS: &str = "chunk-parts";
+/// Header carrying the hex SHA-256 checksum of the whole payload
+pub const HEADER_CHECKSUM: &str = "chunk-checksum";
+
+/// Reassembly metadata of one chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHeader {
+    pub package_id: String,
+    /// Zero-based part number
+    pub part: u32,
+    /// Number of parts in the package
+    pub parts: u32,
+    /// Hex SHA-256 checksum of the whole payload
+    pub checksum: String,
+}
+
+impl ChunkHeader {
+    /// Header name and value pairs, as attached to the message
+    pub fn to_headers(&self) -> [(&'static str, String); 4] {
+        [
+            (HEADER_PACKAGE_ID, self.package_id.clone()),
+            (HEADER_PART, self.part.to_string()),
+            (HEADER_PARTS, self.parts.to_string()),
+            (HEADER_CHECKSUM, self.checksum.clone()),
+        ]
+    }
+
+    /// Reads chunk metadata from message headers; `None` if the message is not a chunk
+    pub fn from_headers<'a, I>(headers: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (&'a str, &'a [u8])>,
+    {
+        let (mut package_id, mut part, mut parts, mut checksum) = (None, None, None, None);
+        for (name, value) in headers {
+            let value = std::str::from_utf8(value).ok()?;
+            match name {
+                HEADER_PACKAGE_ID => package_id = Some(value.to_string()),
+                HEADER_PART => part = value.parse().ok(),
+                HEADER_PARTS => parts = value.parse().ok(),
+                HEADER_CHECKSUM => checksum = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        Some(Self {
+            package_id: package_id?,
+            part: part?,
+            parts: parts?,
+            checksum: checksum?,
+        })
+    }
+}
+
+/// Hex SHA-256 checksum of a payload
+pub fn checksum(payload: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(payload))
+}
+
+/// Splits a payload into chunks of at most `max_chunk_bytes` each
+pub fn split_payload<'a>(
+    payload: &'a [u8],
+    max_chunk_bytes: usize,
+    package_id: &str,
+) -> Vec<(ChunkHeader, &'a [u8])> {
+    let checksum = checksum(payload);
+    let chunks: Vec<&[u8]> = payload.chunks(max_chunk_bytes.max(1)).collect();
+    let parts = chunks.len() as u32;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(part, bytes)| {
+            let header = ChunkHeader {
+                package_id: package_id.to_string(),
+                part: part as u32,
+                parts,
+                checksum: checksum.clone(),
+            };
+            (header, bytes)
+        })
+        .collect()
+}
+
+/// Errors reassembling a chunked package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkError {
+    /// The part number is outside the package
+    InvalidPart {
+        package_id: String,
+        part: u32,
+        parts: u32,
+    },
+    /// The chunk disagrees with earlier chunks on the part count or checksum
+    InconsistentHeader(String),
+    /// The reassembled payload does not match the package checksum
+    ChecksumMismatch(String),
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkError::InvalidPart {
+                package_id,
+                part,
+                parts,
+            } => write!(
+                f,
+                "Part {part} out of range for {parts} parts in {package_id}"
+            ),
+            ChunkError::InconsistentHeader(id) => {
+                write!(f, "Chunk headers disagree within package {id}")
+            }
+            ChunkError::ChecksumMismatch(id) => write!(f, "Checksum mismatch for package {id}"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkError {}
+
+struct PendingPackage {
+    parts: Vec<Option<Vec<u8>>>,
+    received: usize,
+    checksum: String,
+    first_seen: u64,
+}
+
+/// Collects chunks until their package is complete
+#[derive(Default)]
+pub struct ChunkAssembler {
+    pending: HashMap<String, PendingPackage>,
+}
+
+impl ChunkAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk, returning the whole payload once every part has arrived
+    ///
+    /// Duplicate chunks are ignored. A package that fails its checksum is discarded.
+    pub fn push(
+        &mut self,
+        header: &ChunkHeader,
+        bytes: &[u8],
+        now: u64,
+    ) -> Result<Option<Vec<u8>>, ChunkError> {
+        if header.part >= header.parts {
+            return Err(ChunkError::InvalidPart {
+                package_id: header.package_id.clone(),
+                part: header.part,
+                parts: header.parts,
+            });
+        }
+        let package = self
+            .pending
+            .entry(header.package_id.clone())
+            .or_insert_with(|| PendingPackage {
+                parts: vec![None; header.parts as usize],
+                received: 0,
+                checksum: header.checksum.clone(),
+                first_seen: now,
+            });
+        if package.parts.len() != header.parts as usize || package.checksum != header.checksum {
+            return Err(ChunkError::InconsistentHeader(header.package_id.clone()));
+        }
+        let slot = &mut package.parts[header.part as usize];
+        if slot.is_none() {
+            *slot = Some(bytes.to_vec());
+            package.received += 1;
+        }
+        if package.received < package.parts.len() {
+            return Ok(None);
+        }
+
+        let Some(package) = self.pending.remove(&header.package_id) else {
+            return Ok(None);
+        };
+        let payload: Vec<u8> = package.parts.into_iter().flatten().flatten().collect();
+        if checksum(&payload) != package.checksum {
+            return Err(ChunkError::ChecksumMismatch(header.package_id.clone()));
+        }
+        Ok(Some(payload))
+    }
+
+    /// Drops incomplete packages whose first chunk arrived before `cutoff`,
+    /// returning their ids
+    pub fn evict_before(&mut self, cutoff: u64) -> Vec<String> {
+        let stale: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, package)| package.first_seen < cutoff)
+            .map(|(package_id, _)| package_id.clone())
+            .collect();
+        for package_id in &stale {
+            self.pending.remove(package_id);
+        }
+        stale
+    }
+
+    /// Number of incomplete packages
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_reassemble_out_of_order() {
+        let payload: Vec<u8> = (0..250u32).map(|i| (i % 256) as u8).collect();
+        let chunks = split_payload(&payload, 100, "pkg-1");
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|(header, _)| header.parts == 3));
+
+        let mut assembler = ChunkAssembler::new();
+        for index in [2, 0, 0] {
+            let (header, bytes) = &chunks[index];
+            assert_eq!(assembler.push(header, bytes, 0), Ok(None));
+        }
+        let (header, bytes) = &chunks[1];
+        assert_eq!(assembler.push(header, bytes, 0), Ok(Some(payload)));
+        assert_eq!(assembler.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_headers_round_trip() {
+        let (header, _) = split_payload(b"hello", 2, "pkg-2").remove(1);
+        let pairs = header.to_headers();
+        let parsed =
+            ChunkHeader::from_headers(pairs.iter().map(|(name, value)| (*name, value.as_bytes())));
+        assert_eq!(parsed, Some(header));
+        assert_eq!(ChunkHeader::from_headers([("other", &b"1"[..])]), None);
+    }
+
+    #[test]
+    fn test_corrupt_chunk_fails_checksum() {
+        let chunks = split_payload(b"abcdef", 3, "pkg-3");
+        let mut assembler = ChunkAssembler::new();
+        assembler.push(&chunks[0].0, b"xyz", 0).unwrap();
+        assert_eq!(
+            assembler.push(&chunks[1].0, chunks[1].1, 0),
+            Err(ChunkError::ChecksumMismatch("pkg-3".to_string()))
+        );
+        assert_eq!(assembler.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_stale_packages_are_evicted() {
+        let chunks = split_payload(b"abcdef", 3, "pkg-4");
+        let mut assembler = ChunkAssembler::new();
+        assembler.push(&chunks[0].0, chunks[0].1, 10).unwrap();
+        assert!(assembler.evict_before(10).is_empty());
+        assert_eq!(assembler.evict_before(11), vec!["pkg-4".to_string()]);
+    }
+}
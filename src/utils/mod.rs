@@ -1,2 +1,3 @@
+pub mod chunking;
 pub mod time;
 pub use time::current_time_millis;
@@ -2,8 +2,39 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Returns the current time in milliseconds since UNIX epoch
 pub fn current_time_millis() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_millis() as u64
+    skew::apply(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_millis() as u64,
+    )
+}
+
+#[cfg(not(feature = "chaos"))]
+mod skew {
+    #[inline]
+    pub(super) fn apply(now: u64) -> u64 {
+        now
+    }
+}
+
+/// Offset added to every clock reading, so chaos tests can move time
+#[cfg(feature = "chaos")]
+pub mod skew {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    static CLOCK_SKEW_MS: AtomicI64 = AtomicI64::new(0);
+
+    /// Skews every later `current_time_millis` reading by `skew_ms`, which may be negative
+    pub fn set_clock_skew_ms(skew_ms: i64) {
+        CLOCK_SKEW_MS.store(skew_ms, Ordering::Relaxed);
+    }
+
+    pub fn clock_skew_ms() -> i64 {
+        CLOCK_SKEW_MS.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn apply(now: u64) -> u64 {
+        now.saturating_add_signed(clock_skew_ms())
+    }
 }
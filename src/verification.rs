@@ -0,0 +1,289 @@
+// src/verification.rs
+use crate::alerts::{Alert, AlertKind, emit_alert};
+use crate::config::verification::VerificationConfig;
+use crate::orderbook::OrderBook;
+use crate::orderbook::snapshot::OrderBookSnapshot;
+use crate::publisher::Publisher;
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tracing::{error, info};
+
+/// Hash of an instrument's book after the `command_index`th command on it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateHash {
+    pub instrument_id: String,
+    pub command_index: u64,
+    pub hash: String,
+    pub timestamp: u64,
+}
+
+/// First point at which a replayed book no longer matches the recorded run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Divergence {
+    /// Earliest command that can have caused it, the one after the last match
+    pub first_command: u64,
+    /// Command after which the hashes differ
+    pub command_index: u64,
+    pub expected: String,
+    pub actual: String,
+}
+
+enum Mode {
+    /// Publish hashes for a later replay to be checked against
+    Record,
+    /// Compare hashes with those of a recorded run
+    Verify {
+        expected: HashMap<(String, u64), String>,
+        /// Last command index whose hash matched, per instrument
+        matched: HashMap<String, u64>,
+        /// Instruments already reported; every later hash differs too
+        diverged: HashSet<String>,
+    },
+}
+
+/// Hashes each book every `interval_commands` commands on it, and either
+/// publishes the hashes or checks them against a recording
+///
+/// Only hashes taken at the same command index are compared, so a replay
+/// checks every recorded hash when it runs with the recording's interval or
+/// with a divisor of it.
+pub struct StateVerifier {
+    config: VerificationConfig,
+    mode: Mode,
+    /// Commands seen per instrument
+    commands: HashMap<String, u64>,
+}
+
+impl StateVerifier {
+    pub fn new(mut config: VerificationConfig) -> Self {
+        let mode = match config.expected_path.as_deref().filter(|_| config.enabled) {
+            Some(path) => match load_expected(path) {
+                Ok(expected) => {
+                    info!(
+                        "Verifying book state against {} recorded hashes from {}",
+                        expected.len(),
+                        path
+                    );
+                    Mode::Verify {
+                        expected,
+                        matched: HashMap::new(),
+                        diverged: HashSet::new(),
+                    }
+                }
+                Err(e) => {
+                    // Publishing instead would mix this run's hashes into the recording
+                    error!(
+                        "Book state verification disabled, failed to read {}: {}",
+                        path, e
+                    );
+                    config.enabled = false;
+                    Mode::Record
+                }
+            },
+            None => Mode::Record,
+        };
+        Self {
+            config,
+            mode,
+            commands: HashMap::new(),
+        }
+    }
+
+    /// Counts a command on an instrument, hashing its book when one is due.
+    /// A missing book, e.g. after a delete, hashes as an empty one.
+    pub fn on_command(
+        &mut self,
+        instrument_id: &str,
+        book: Option<&OrderBook<OrderTags>>,
+        publisher: &Publisher,
+    ) {
+        if !self.config.enabled {
+            return;
+        }
+        let count = self.commands.entry(instrument_id.to_string()).or_default();
+        *count += 1;
+        let command_index = *count;
+        if !command_index.is_multiple_of(self.config.interval_commands.max(1)) {
+            return;
+        }
+        if let Mode::Verify { diverged, .. } = &self.mode
+            && diverged.contains(instrument_id)
+        {
+            return;
+        }
+        let hash = match book {
+            Some(book) => book.create_snapshot(usize::MAX).state_hash(),
+            None => OrderBookSnapshot {
+                symbol: instrument_id.to_string(),
+                timestamp: 0,
+                bids: Vec::new(),
+                asks: Vec::new(),
+                extra_fields: Vec::new(),
+            }
+            .state_hash(),
+        };
+        match &self.mode {
+            Mode::Record => publisher.publish(
+                &self.config.topic,
+                instrument_id,
+                &StateHash {
+                    instrument_id: instrument_id.to_string(),
+                    command_index,
+                    hash,
+                    timestamp: current_time_millis(),
+                },
+            ),
+            Mode::Verify { .. } => {
+                if let Some(divergence) = self.verify(instrument_id, command_index, hash) {
+                    let message = format!(
+                        "Replay diverges from the recorded run within commands {}..={}",
+                        divergence.first_command, divergence.command_index
+                    );
+                    emit_alert(
+                        publisher,
+                        Alert::new(AlertKind::ReplayDivergence, instrument_id, message)
+                            .with_details(&divergence),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Compares a hash with the recorded one, returning the first divergence
+    /// of an instrument. Indexes the recording has no hash for are skipped.
+    fn verify(
+        &mut self,
+        instrument_id: &str,
+        command_index: u64,
+        hash: String,
+    ) -> Option<Divergence> {
+        let Mode::Verify {
+            expected,
+            matched,
+            diverged,
+        } = &mut self.mode
+        else {
+            return None;
+        };
+        if diverged.contains(instrument_id) {
+            return None;
+        }
+        let recorded = expected.get(&(instrument_id.to_string(), command_index))?;
+        if *recorded == hash {
+            matched.insert(instrument_id.to_string(), command_index);
+            return None;
+        }
+        diverged.insert(instrument_id.to_string());
+        Some(Divergence {
+            first_command: matched.get(instrument_id).copied().unwrap_or(0) + 1,
+            command_index,
+            expected: recorded.clone(),
+            actual: hash,
+        })
+    }
+}
+
+/// Reads recorded hashes, one `StateHash` per line
+fn load_expected(path: &str) -> std::io::Result<HashMap<(String, u64), String>> {
+    let mut expected = HashMap::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: StateHash = serde_json::from_str(line).map_err(std::io::Error::other)?;
+        expected.insert((record.instrument_id, record.command_index), record.hash);
+    }
+    Ok(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    fn book(orders: &[(u64, u64, u64, Side)]) -> OrderBook<OrderTags> {
+        let book = OrderBook::new("BTC");
+        for (id, price, quantity, side) in orders {
+            book.add_limit_order(
+                OrderId::from_u64(*id),
+                *price,
+                *quantity,
+                *side,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        }
+        book
+    }
+
+    fn hash(book: &OrderBook<OrderTags>) -> String {
+        book.create_snapshot(usize::MAX).state_hash()
+    }
+
+    #[test]
+    fn test_state_hash_ignores_time_but_not_state() {
+        let orders = [(1, 100, 5, Side::Buy), (2, 101, 3, Side::Sell)];
+        let first = book(&orders);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert_eq!(hash(&first), hash(&book(&orders)));
+
+        let resized = book(&[(1, 100, 6, Side::Buy), (2, 101, 3, Side::Sell)]);
+        assert_ne!(hash(&first), hash(&resized));
+        // Same levels, but a different order resting in them
+        let renumbered = book(&[(7, 100, 5, Side::Buy), (2, 101, 3, Side::Sell)]);
+        assert_ne!(hash(&first), hash(&renumbered));
+    }
+
+    #[test]
+    fn test_verify_reports_first_divergence_only() {
+        let path = std::env::temp_dir().join(format!("state-hashes-{}.jsonl", std::process::id()));
+        let recorded: String = [(10, "a"), (20, "b"), (30, "c")]
+            .iter()
+            .map(|(command_index, hash)| {
+                serde_json::to_string(&StateHash {
+                    instrument_id: "BTC".to_string(),
+                    command_index: *command_index,
+                    hash: hash.to_string(),
+                    timestamp: 0,
+                })
+                .unwrap()
+                    + "\n"
+            })
+            .collect();
+        std::fs::write(&path, recorded).unwrap();
+        let mut verifier = StateVerifier::new(VerificationConfig {
+            enabled: true,
+            interval_commands: 10,
+            expected_path: Some(path.to_string_lossy().into_owned()),
+            ..VerificationConfig::default()
+        });
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(verifier.verify("BTC", 10, "a".to_string()), None);
+        // Not in the recording
+        assert_eq!(verifier.verify("ETH", 10, "x".to_string()), None);
+        assert_eq!(
+            verifier.verify("BTC", 20, "x".to_string()),
+            Some(Divergence {
+                first_command: 11,
+                command_index: 20,
+                expected: "b".to_string(),
+                actual: "x".to_string(),
+            })
+        );
+        assert_eq!(verifier.verify("BTC", 30, "y".to_string()), None);
+    }
+
+    #[test]
+    fn test_unreadable_recording_disables_verification() {
+        let verifier = StateVerifier::new(VerificationConfig {
+            enabled: true,
+            expected_path: Some("/nonexistent/state-hashes.jsonl".to_string()),
+            ..VerificationConfig::default()
+        });
+        assert!(!verifier.config.enabled);
+    }
+}
@@ -0,0 +1,791 @@
+// src/wal.rs
+use crate::client_orders::{ClientOrder, ClientOrderIds};
+use crate::config::topics::CommandKind;
+use crate::config::wal::{FsyncPolicy, WalConfig};
+use crate::helpers::EngineCommand;
+use crate::orderbook::manager::{BookManager, BookManagerStd};
+use crate::orderbook::snapshot::OrderBookSnapshotPackage;
+use crate::risk::{OpenOrder, ParticipantRisk};
+use crate::tags::OrderTags;
+use crate::utils::current_time_millis;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{info, warn};
+
+const SEGMENT_EXTENSION: &str = "wal";
+/// Extension of snapshot files; files being written carry `.partial` after it
+const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+/// One command as logged
+#[derive(Debug, Serialize, Deserialize)]
+struct WalEntry {
+    /// Position in the log, starting at 0 and continuing across restarts
+    seq: u64,
+    timestamp: u64,
+    kind: CommandKind,
+    /// The payload as `EngineCommand::to_value` renders it
+    command: serde_json::Value,
+}
+
+/// A command as stored by the log, without its position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedCommand {
+    pub kind: CommandKind,
+    pub command: serde_json::Value,
+}
+
+impl LoggedCommand {
+    pub fn parse(&self) -> Result<Option<EngineCommand>, serde_json::Error> {
+        EngineCommand::from_value(self.kind, self.command.clone())
+    }
+}
+
+/// Commands that set up instruments and the engine rather than trade
+///
+/// Not all of their effects show in the orders of a book, so a snapshot carries
+/// them forward in place of the segments it replaces.
+fn is_setting(kind: CommandKind) -> bool {
+    matches!(
+        kind,
+        CommandKind::InstrumentCreate
+            | CommandKind::InstrumentDelete
+            | CommandKind::InstrumentAdjust
+            | CommandKind::Halt
+            | CommandKind::Resume
+            | CommandKind::AuctionStart
+            | CommandKind::AuctionUncross
+            | CommandKind::Admin
+            | CommandKind::IndexDefine
+            | CommandKind::KillSwitch
+    )
+}
+
+/// State of an engine after applying every logged command before `seq`
+///
+/// Written into the log's directory, so the segments holding those commands
+/// can be dropped. Only the newest snapshot is kept.
+#[derive(Serialize, Deserialize)]
+pub struct JournalSnapshot {
+    pub seq: u64,
+    pub taken_at: u64,
+    /// Every setting command before `seq`, in log order, applied before the
+    /// books are restored
+    pub settings: Vec<LoggedCommand>,
+    pub books: Vec<OrderBookSnapshotPackage>,
+    pub client_orders: Vec<ClientOrder>,
+    /// Participants of the resting orders, for their open order limits
+    #[serde(default)]
+    pub open_orders: Vec<OpenOrder>,
+}
+
+/// Segments dropped by a compaction
+#[derive(Debug, Default, PartialEq)]
+pub struct Compaction {
+    pub segments: usize,
+    pub bytes: u64,
+}
+
+/// What `verify` found in a log directory
+#[derive(Debug, Default)]
+pub struct Verification {
+    pub segments: usize,
+    pub bytes: u64,
+    pub entries: u64,
+    /// Sequence covered by the newest snapshot, if there is one
+    pub snapshot_seq: Option<u64>,
+    /// Segments ending in a write torn by a crash, which replay skips
+    pub torn_writes: usize,
+    /// Anything that would make recovery lose or misapply commands
+    pub problems: Vec<String>,
+}
+
+/// Files of the log with the given extension, in name order
+fn files(directory: &Path, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == extension) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Segment files of the log, oldest first; each is named after the sequence of
+/// its first entry and its position among the segments, zero-padded so names
+/// sort in log order and a restart never appends to a previous run's segment
+fn segments(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    files(directory, SEGMENT_EXTENSION)
+}
+
+/// Sequence of the first entry of a segment and its position, from its name
+fn segment_position(path: &Path) -> Option<(u64, u64)> {
+    let (seq, index) = path.file_stem()?.to_str()?.split_once('-')?;
+    Some((seq.parse().ok()?, index.parse().ok()?))
+}
+
+/// Reads every entry of the log in order
+///
+/// An unreadable line is a write torn by a crash; it can only be the last line
+/// of a segment, since every restart begins a new one, so reading carries on
+/// with the next segment.
+fn read_entries(directory: &Path, mut visit: impl FnMut(WalEntry)) -> io::Result<()> {
+    if !directory.exists() {
+        return Ok(());
+    }
+    for segment in segments(directory)? {
+        for line in BufReader::new(File::open(&segment)?).lines() {
+            match serde_json::from_str::<WalEntry>(&line?) {
+                Ok(entry) => visit(entry),
+                Err(e) => {
+                    warn!("Skipping unreadable entry in {}: {}", segment.display(), e);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies every command of the log from `from_seq` on in order, returning how
+/// many were applied
+pub fn replay(
+    directory: &Path,
+    from_seq: u64,
+    mut apply: impl FnMut(EngineCommand),
+) -> io::Result<usize> {
+    let mut applied = 0;
+    read_entries(directory, |entry| {
+        if entry.seq < from_seq {
+            return;
+        }
+        match EngineCommand::from_value(entry.kind, entry.command) {
+            Ok(Some(command)) => {
+                apply(command);
+                applied += 1;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Skipping logged command {} that no longer parses: {}",
+                entry.seq, e
+            ),
+        }
+    })?;
+    Ok(applied)
+}
+
+fn read_snapshot(path: &Path) -> io::Result<JournalSnapshot> {
+    let reader = BufReader::new(File::open(path)?);
+    serde_json::from_reader(reader).map_err(io::Error::other)
+}
+
+/// The newest readable snapshot of the log, if any
+pub fn latest_snapshot(directory: &Path) -> io::Result<Option<JournalSnapshot>> {
+    if !directory.exists() {
+        return Ok(None);
+    }
+    for path in files(directory, SNAPSHOT_EXTENSION)?.iter().rev() {
+        match read_snapshot(path) {
+            Ok(snapshot) => return Ok(Some(snapshot)),
+            Err(e) => warn!("Skipping unreadable snapshot {}: {}", path.display(), e),
+        }
+    }
+    Ok(None)
+}
+
+/// Writes a snapshot aside and renames it into place, then removes the older ones
+fn write_snapshot(directory: &Path, snapshot: &JournalSnapshot) -> io::Result<()> {
+    let name = format!("{:020}.{SNAPSHOT_EXTENSION}", snapshot.seq);
+    let path = directory.join(&name);
+    let partial = directory.join(format!("{name}.partial"));
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    serde_json::to_writer(&mut writer, snapshot).map_err(io::Error::other)?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    std::fs::rename(&partial, &path)?;
+    File::open(directory)?.sync_all()?;
+    for older in files(directory, SNAPSHOT_EXTENSION)? {
+        if older != path {
+            std::fs::remove_file(older)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the segments holding only commands before `covered_seq` once they
+/// are past retention
+///
+/// A segment is past retention once its last write is `retention_ms` old, or
+/// once the covered segments newer than it and itself add up to more than
+/// `retention_bytes`. The last segment is never deleted, being the one written.
+fn compact(
+    directory: &Path,
+    covered_seq: u64,
+    config: &WalConfig,
+    now: u64,
+) -> io::Result<Compaction> {
+    let segments = segments(directory)?;
+    let covered = segments
+        .windows(2)
+        .take_while(|pair| segment_position(&pair[1]).is_some_and(|(seq, _)| seq <= covered_seq))
+        .count();
+    let mut compaction = Compaction::default();
+    let mut covered_bytes = 0;
+    for segment in segments[..covered].iter().rev() {
+        let metadata = std::fs::metadata(segment)?;
+        let written_at = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |age| age.as_millis() as u64);
+        covered_bytes += metadata.len();
+        if now.saturating_sub(written_at) < config.retention_ms
+            && covered_bytes <= config.retention_bytes
+        {
+            continue;
+        }
+        std::fs::remove_file(segment)?;
+        compaction.segments += 1;
+        compaction.bytes += metadata.len();
+    }
+    Ok(compaction)
+}
+
+/// Checks that the log can be recovered from: the newest snapshot reads and
+/// its books match their checksums, and every command from it on is present,
+/// in sequence and still parses
+pub fn verify(directory: &Path) -> io::Result<Verification> {
+    let mut verification = Verification::default();
+    if !directory.exists() {
+        return Ok(verification);
+    }
+    let snapshots = files(directory, SNAPSHOT_EXTENSION)?;
+    if let Some(path) = snapshots.last() {
+        match read_snapshot(path) {
+            Ok(snapshot) => {
+                verification.snapshot_seq = Some(snapshot.seq);
+                for package in &snapshot.books {
+                    if let Err(e) = package.validate() {
+                        verification.problems.push(format!(
+                            "{}: book {} is damaged: {}",
+                            path.display(),
+                            package.snapshot.symbol,
+                            e
+                        ));
+                    }
+                }
+                for setting in &snapshot.settings {
+                    if let Err(e) = setting.parse() {
+                        verification.problems.push(format!(
+                            "{}: a {:?} command no longer parses: {}",
+                            path.display(),
+                            setting.kind,
+                            e
+                        ));
+                    }
+                }
+            }
+            Err(e) => {
+                verification
+                    .problems
+                    .push(format!("{} is unreadable: {}", path.display(), e))
+            }
+        }
+    }
+    let mut expected = verification.snapshot_seq.unwrap_or(0);
+    for segment in segments(directory)? {
+        verification.segments += 1;
+        verification.bytes += std::fs::metadata(&segment)?.len();
+        let Some((first_seq, _)) = segment_position(&segment) else {
+            verification
+                .problems
+                .push(format!("{} is not named as a segment", segment.display()));
+            continue;
+        };
+        // Segments before the snapshot may be kept for retention
+        expected = expected.min(first_seq);
+        let mut lines = BufReader::new(File::open(&segment)?).lines().peekable();
+        while let Some(line) = lines.next() {
+            let entry = match serde_json::from_str::<WalEntry>(&line?) {
+                Ok(entry) => entry,
+                Err(_) if lines.peek().is_none() => {
+                    verification.torn_writes += 1;
+                    break;
+                }
+                Err(e) => {
+                    verification.problems.push(format!(
+                        "{}: unreadable entry after command {}: {}",
+                        segment.display(),
+                        expected,
+                        e
+                    ));
+                    break;
+                }
+            };
+            verification.entries += 1;
+            if entry.seq != expected {
+                verification.problems.push(format!(
+                    "{}: found command {} where command {} was due",
+                    segment.display(),
+                    entry.seq,
+                    expected
+                ));
+            }
+            if let Err(e) = EngineCommand::from_value(entry.kind, entry.command) {
+                verification.problems.push(format!(
+                    "{}: command {} no longer parses: {}",
+                    segment.display(),
+                    entry.seq,
+                    e
+                ));
+            }
+            expected = entry.seq + 1;
+        }
+    }
+    Ok(verification)
+}
+
+/// Append-only log of the commands an engine applies, split into segment files
+///
+/// Commands are written straight to the file without buffering in the process,
+/// so once `append` returns a command survives the process crashing.
+pub struct WriteAheadLog {
+    config: WalConfig,
+    directory: PathBuf,
+    file: File,
+    segment_bytes: u64,
+    segment_started_at: u64,
+    next_seq: u64,
+    last_sync_at: u64,
+    /// Setting commands of the whole log, for the next snapshot
+    settings: Vec<LoggedCommand>,
+}
+
+impl WriteAheadLog {
+    /// Opens the log in `config.directory`, continuing after its last entry in
+    /// a new segment
+    pub fn open(config: WalConfig) -> io::Result<Self> {
+        let directory = PathBuf::from(&config.directory);
+        std::fs::create_dir_all(&directory)?;
+        let (covered_seq, mut settings) = latest_snapshot(&directory)?
+            .map_or((0, Vec::new()), |snapshot| {
+                (snapshot.seq, snapshot.settings)
+            });
+        let mut next_seq = covered_seq;
+        read_entries(&directory, |entry| {
+            next_seq = next_seq.max(entry.seq + 1);
+            if entry.seq >= covered_seq && is_setting(entry.kind) {
+                settings.push(LoggedCommand {
+                    kind: entry.kind,
+                    command: entry.command,
+                });
+            }
+        })?;
+        let file = Self::create_segment(&directory, next_seq)?;
+        info!(
+            "Write-ahead log in {} continues at command {}",
+            directory.display(),
+            next_seq
+        );
+        let now = current_time_millis();
+        Ok(Self {
+            config,
+            directory,
+            file,
+            segment_bytes: 0,
+            segment_started_at: now,
+            next_seq,
+            last_sync_at: now,
+            settings,
+        })
+    }
+
+    fn create_segment(directory: &Path, first_seq: u64) -> io::Result<File> {
+        // Compaction removes segments, so positions continue from the last one
+        // rather than counting the files
+        let index = segments(directory)?
+            .iter()
+            .filter_map(|segment| segment_position(segment))
+            .map(|(_, index)| index + 1)
+            .max()
+            .unwrap_or(0);
+        let path = directory.join(format!("{first_seq:020}-{index:020}.{SEGMENT_EXTENSION}"));
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(path)?;
+        // Make the new file's directory entry durable as well
+        File::open(directory)?.sync_all()?;
+        Ok(file)
+    }
+
+    /// Writes a command to the log, to be called before it is applied
+    pub fn append(&mut self, command: &EngineCommand) -> io::Result<()> {
+        let now = current_time_millis();
+        let segment_expired = self.segment_bytes > 0
+            && self.config.segment_ms > 0
+            && now.saturating_sub(self.segment_started_at) >= self.config.segment_ms;
+        if self.segment_bytes >= self.config.segment_bytes || segment_expired {
+            self.file.sync_data()?;
+            self.file = Self::create_segment(&self.directory, self.next_seq)?;
+            self.segment_bytes = 0;
+            self.segment_started_at = now;
+        }
+        let entry = WalEntry {
+            seq: self.next_seq,
+            timestamp: now,
+            kind: command.kind(),
+            command: command.to_value().map_err(io::Error::other)?,
+        };
+        let mut line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        if is_setting(entry.kind) {
+            self.settings.push(LoggedCommand {
+                kind: entry.kind,
+                command: entry.command,
+            });
+        }
+        self.next_seq += 1;
+        self.segment_bytes += line.len() as u64;
+        let due = match self.config.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Interval => {
+                now.saturating_sub(self.last_sync_at) >= self.config.fsync_interval_ms
+            }
+            FsyncPolicy::Never => false,
+        };
+        if due {
+            self.sync(now)?;
+        }
+        Ok(())
+    }
+
+    fn sync(&mut self, now: u64) -> io::Result<()> {
+        self.last_sync_at = now;
+        self.file.sync_data()
+    }
+
+    /// Forces everything appended to disk, e.g. before the engine stops
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.sync(current_time_millis())
+    }
+
+    /// Snapshots the books as of the last command appended, then deletes the
+    /// segments it covers that are past retention
+    ///
+    /// A segment is only covered once a newer one was started, so compaction
+    /// keeps up with the log as segments rotate by size or age.
+    pub fn snapshot(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        client_orders: &ClientOrderIds,
+        risk: &ParticipantRisk,
+        now: u64,
+    ) -> io::Result<Compaction> {
+        self.snapshot_with(manager, Vec::new(), client_orders, risk, now)
+    }
+
+    /// Like `snapshot`, adding the books evicted from `manager` to disk
+    pub fn snapshot_with(
+        &mut self,
+        manager: &BookManagerStd<OrderTags>,
+        cold_books: Vec<OrderBookSnapshotPackage>,
+        client_orders: &ClientOrderIds,
+        risk: &ParticipantRisk,
+        now: u64,
+    ) -> io::Result<Compaction> {
+        let mut books = cold_books;
+        for symbol in manager.symbols() {
+            if let Some(book) = manager.get_book(&symbol) {
+                let package = book
+                    .create_snapshot_package(usize::MAX)
+                    .map_err(|e| io::Error::other(format!("{symbol}: {e}")))?;
+                books.push(package);
+            }
+        }
+        let snapshot = JournalSnapshot {
+            seq: self.next_seq,
+            taken_at: now,
+            settings: self.settings.clone(),
+            books,
+            client_orders: client_orders.entries(),
+            open_orders: risk.entries(),
+        };
+        write_snapshot(&self.directory, &snapshot)?;
+        compact(&self.directory, snapshot.seq, &self.config, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::risk::RiskConfig;
+    use crate::helpers::types::{AdminCommandPayload, OrderCancelPayload};
+    use pricelevel::{OrderId, Side, TimeInForce};
+
+    fn directory() -> PathBuf {
+        std::env::temp_dir().join(format!("wal-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn config(directory: &Path, segment_bytes: u64) -> WalConfig {
+        WalConfig {
+            enabled: true,
+            directory: directory.to_string_lossy().into_owned(),
+            segment_bytes,
+            fsync: FsyncPolicy::Always,
+            ..WalConfig::default()
+        }
+    }
+
+    fn cancel(order_id: u64) -> EngineCommand {
+        EngineCommand::OrderCancel(OrderCancelPayload {
+            order_id,
+            instrument_id: "BTC".to_string(),
+            client_order_id: None,
+            participant_id: None,
+            sequence: None,
+        })
+    }
+
+    fn instrument(instrument_id: &str) -> EngineCommand {
+        let payload = format!(r#"{{"instrument_id":"{instrument_id}"}}"#);
+        EngineCommand::parse(CommandKind::InstrumentCreate, &payload)
+            .unwrap()
+            .unwrap()
+    }
+
+    fn replayed(directory: &Path) -> Vec<EngineCommand> {
+        let mut commands = Vec::new();
+        replay(directory, 0, |command| commands.push(command)).unwrap();
+        commands
+    }
+
+    #[test]
+    fn test_commands_are_replayed_in_order_across_segments_and_restarts() {
+        let directory = directory();
+        let mut wal = WriteAheadLog::open(config(&directory, 1)).unwrap();
+        wal.append(&cancel(1)).unwrap();
+        wal.append(&cancel(2)).unwrap();
+        drop(wal);
+        let mut wal = WriteAheadLog::open(config(&directory, 1 << 20)).unwrap();
+        assert_eq!(wal.next_seq, 2);
+        wal.append(&EngineCommand::Admin(AdminCommandPayload::GetGlobalStats))
+            .unwrap();
+        wal.flush().unwrap();
+
+        assert_eq!(segments(&directory).unwrap().len(), 3);
+        let commands = replayed(&directory);
+        assert_eq!(commands.len(), 3);
+        assert!(matches!(&commands[0], EngineCommand::OrderCancel(cancel) if cancel.order_id == 1));
+        assert!(matches!(&commands[1], EngineCommand::OrderCancel(cancel) if cancel.order_id == 2));
+        assert!(matches!(
+            commands[2],
+            EngineCommand::Admin(AdminCommandPayload::GetGlobalStats)
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_payloads_read_back_as_received() {
+        let received = [
+            (
+                CommandKind::InstrumentCreate,
+                r#"{"instrument_id":"BTC","market_protection":{"ticks":5,"tick_size":10},"scale":{"price_decimals":2}}"#,
+            ),
+            (
+                CommandKind::OrderCreate,
+                r#"{"order_id":1,"instrument_id":"BTC","quantity":5,"price":100,"side":"Buy","time_in_force":"Gtc","order_type":"LIMIT","participant_id":"desk-1"}"#,
+            ),
+            (
+                CommandKind::InstrumentAdjust,
+                r#"{"instrument_id":"BTC","action_id":"split-1","ratio":2.0}"#,
+            ),
+            (
+                CommandKind::Admin,
+                r#"{"command":"set_feature","feature":"market_protection","enabled":false}"#,
+            ),
+        ];
+        let directory = directory();
+        let mut wal = WriteAheadLog::open(config(&directory, 1 << 20)).unwrap();
+        let mut expected = Vec::new();
+        for (kind, payload) in received {
+            let command = EngineCommand::parse(kind, payload).unwrap().unwrap();
+            wal.append(&command).unwrap();
+            expected.push(format!("{command:?}"));
+        }
+        let replayed: Vec<String> = replayed(&directory)
+            .iter()
+            .map(|command| format!("{command:?}"))
+            .collect();
+        assert_eq!(replayed, expected);
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_torn_final_write_is_skipped() {
+        let directory = directory();
+        let mut wal = WriteAheadLog::open(config(&directory, 1 << 20)).unwrap();
+        wal.append(&cancel(1)).unwrap();
+        wal.file.write_all(b"{\"seq\":1,\"timest").unwrap();
+        drop(wal);
+        // The next run starts at the same sequence
+        let mut wal = WriteAheadLog::open(config(&directory, 1 << 20)).unwrap();
+        wal.file.write_all(b"{\"seq\":1,\"kind").unwrap();
+        drop(wal);
+
+        let mut wal = WriteAheadLog::open(config(&directory, 1 << 20)).unwrap();
+        assert_eq!(wal.next_seq, 1);
+        wal.append(&cancel(2)).unwrap();
+        let orders: Vec<u64> = replayed(&directory)
+            .iter()
+            .map(|command| match command {
+                EngineCommand::OrderCancel(cancel) => cancel.order_id,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(orders, [1, 2]);
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_snapshot_drops_the_segments_it_covers() {
+        let directory = directory();
+        let mut config = config(&directory, 1);
+        config.retention_ms = 0;
+        config.retention_bytes = 0;
+        let mut wal = WriteAheadLog::open(config.clone()).unwrap();
+        wal.append(&instrument("BTC")).unwrap();
+        wal.append(&cancel(1)).unwrap();
+        wal.append(&cancel(2)).unwrap();
+
+        let mut manager = BookManagerStd::<OrderTags>::new();
+        manager.add_book("BTC");
+        manager
+            .get_book("BTC")
+            .unwrap()
+            .add_limit_order(
+                OrderId::from_u64(7),
+                100,
+                5,
+                Side::Buy,
+                TimeInForce::Gtc,
+                None,
+            )
+            .unwrap();
+        let mut client_orders = ClientOrderIds::new();
+        client_orders.register(Some("desk-1"), "abc", "BTC", 7);
+        let compaction = wal
+            .snapshot(
+                &manager,
+                &client_orders,
+                &ParticipantRisk::new(RiskConfig::default()),
+                current_time_millis(),
+            )
+            .unwrap();
+        // The segment being written stays, whatever it holds
+        assert_eq!(compaction.segments, 2);
+        assert_eq!(segments(&directory).unwrap().len(), 1);
+
+        let snapshot = latest_snapshot(&directory).unwrap().unwrap();
+        assert_eq!(snapshot.seq, 3);
+        assert_eq!(snapshot.books.len(), 1);
+        assert_eq!(snapshot.client_orders.len(), 1);
+        let settings: Vec<CommandKind> = snapshot.settings.iter().map(|s| s.kind).collect();
+        assert_eq!(settings, [CommandKind::InstrumentCreate]);
+        assert_eq!(replay(&directory, snapshot.seq, |_| {}).unwrap(), 0);
+
+        // A restart carries on after the snapshot, which holds the settings
+        drop(wal);
+        let mut wal = WriteAheadLog::open(config).unwrap();
+        assert_eq!(wal.next_seq, 3);
+        wal.append(&instrument("ETH")).unwrap();
+        assert_eq!(wal.settings.len(), 2);
+        let mut after = Vec::new();
+        replay(&directory, snapshot.seq, |command| after.push(command)).unwrap();
+        assert!(matches!(
+            &after[..],
+            [EngineCommand::InstrumentCreate(instrument)] if instrument.instrument_id == "ETH"
+        ));
+        let verification = verify(&directory).unwrap();
+        assert!(
+            verification.problems.is_empty(),
+            "{:?}",
+            verification.problems
+        );
+        assert_eq!(verification.snapshot_seq, Some(3));
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_covered_segments_are_kept_within_retention() {
+        let directory = directory();
+        let mut config = config(&directory, 1);
+        config.retention_ms = 60_000;
+        config.retention_bytes = u64::MAX;
+        let mut wal = WriteAheadLog::open(config.clone()).unwrap();
+        for order_id in 0..4 {
+            wal.append(&cancel(order_id)).unwrap();
+        }
+        let manager = BookManagerStd::<OrderTags>::new();
+        let now = current_time_millis();
+        let risk = ParticipantRisk::new(RiskConfig::default());
+        let kept = wal
+            .snapshot(&manager, &ClientOrderIds::new(), &risk, now)
+            .unwrap();
+        assert_eq!(kept, Compaction::default());
+
+        // Past the age limit every covered segment goes
+        let compaction = compact(&directory, wal.next_seq, &config, now + 60_000).unwrap();
+        assert_eq!(compaction.segments, 3);
+
+        // Beyond the size limit the oldest go first
+        for order_id in 4..9 {
+            wal.append(&cancel(order_id)).unwrap();
+        }
+        let segment = std::fs::metadata(&segments(&directory).unwrap()[0])
+            .unwrap()
+            .len();
+        config.retention_bytes = 2 * segment;
+        let compaction = compact(&directory, wal.next_seq, &config, now).unwrap();
+        assert_eq!(compaction.segments, 3);
+        let first_kept = segment_position(&segments(&directory).unwrap()[0]);
+        assert_eq!(first_kept.map(|(seq, _)| seq), Some(6));
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn test_verify_reports_missing_commands_and_torn_writes() {
+        let directory = directory();
+        let mut wal = WriteAheadLog::open(config(&directory, 1 << 20)).unwrap();
+        wal.append(&cancel(1)).unwrap();
+        wal.file.write_all(b"{\"seq\":1,\"timest").unwrap();
+        drop(wal);
+        let verification = verify(&directory).unwrap();
+        assert_eq!(verification.entries, 1);
+        assert_eq!(verification.torn_writes, 1);
+        assert!(verification.problems.is_empty());
+
+        // A segment lost from the middle of the log
+        let mut wal = WriteAheadLog::open(config(&directory, 1)).unwrap();
+        wal.append(&cancel(2)).unwrap();
+        wal.append(&cancel(3)).unwrap();
+        wal.append(&cancel(4)).unwrap();
+        drop(wal);
+        let segments = segments(&directory).unwrap();
+        std::fs::remove_file(&segments[2]).unwrap();
+        let verification = verify(&directory).unwrap();
+        assert_eq!(
+            verification.problems.len(),
+            1,
+            "{:?}",
+            verification.problems
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
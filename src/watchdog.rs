@@ -0,0 +1,285 @@
+// src/watchdog.rs
+use crate::alerts::{Alert, AlertKind, emit_alert};
+use crate::config::watchdog::WatchdogConfig;
+use crate::publisher::Publisher;
+use crate::rebalance::CommandConsumer;
+use crate::utils::current_time_millis;
+use rdkafka::Offset;
+use rdkafka::consumer::Consumer;
+use rdkafka::error::KafkaResult;
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+/// Commands handed to the engine and applied by it, shared between the
+/// consumer, the engine and the watchdog
+#[derive(Clone, Default)]
+pub struct Progress {
+    inner: Arc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    received: AtomicU64,
+    applied: AtomicU64,
+    last_applied_at: AtomicU64,
+    duplicates: AtomicU64,
+    delayed_dropped: AtomicU64,
+}
+
+impl Progress {
+    /// Counts a command sent to the engine
+    pub fn received(&self) {
+        self.inner.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a command the engine finished applying
+    pub fn applied(&self, now: u64) {
+        self.inner.applied.fetch_add(1, Ordering::Relaxed);
+        self.inner.last_applied_at.store(now, Ordering::Relaxed);
+    }
+
+    /// Counts a command the engine died applying, so it is not waited on
+    pub fn lost(&self) {
+        self.inner.applied.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a command the engine dropped as a redelivery, so it is not
+    /// waited on
+    pub fn duplicate(&self) {
+        self.inner.applied.fetch_add(1, Ordering::Relaxed);
+        self.inner.duplicates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts messages delayed feeds dropped because their buffer was full
+    pub fn delayed_dropped(&self, count: u64) {
+        self.inner
+            .delayed_dropped
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Commands sent to the engine since startup
+    pub fn commands_received(&self) -> u64 {
+        self.inner.received.load(Ordering::Relaxed)
+    }
+
+    /// Commands the engine applied, or died applying, since startup
+    pub fn commands_applied(&self) -> u64 {
+        self.inner.applied.load(Ordering::Relaxed)
+    }
+
+    /// Commands sent to the engine and not applied yet
+    pub fn queued(&self) -> u64 {
+        let applied = self.inner.applied.load(Ordering::Relaxed);
+        self.inner
+            .received
+            .load(Ordering::Relaxed)
+            .saturating_sub(applied)
+    }
+
+    /// Commands the engine dropped as redeliveries since startup
+    pub fn duplicates_dropped(&self) -> u64 {
+        self.inner.duplicates.load(Ordering::Relaxed)
+    }
+
+    /// Messages delayed feeds dropped since startup
+    pub fn delayed_messages_dropped(&self) -> u64 {
+        self.inner.delayed_dropped.load(Ordering::Relaxed)
+    }
+
+    /// When the engine last applied a command, 0 if it never has
+    pub fn last_applied_at(&self) -> u64 {
+        self.inner.last_applied_at.load(Ordering::Relaxed)
+    }
+}
+
+/// Decides when the lack of progress is a stall, once per stall
+struct StallDetector {
+    stall_ms: u64,
+    /// Start of the current wait, so an idle start or a restart is not a stall
+    watching_since: u64,
+    stalled: bool,
+}
+
+impl StallDetector {
+    fn new(stall_ms: u64, now: u64) -> Self {
+        Self {
+            stall_ms,
+            watching_since: now,
+            stalled: false,
+        }
+    }
+
+    /// Time without progress, if long enough for a stall to be possible
+    fn idle_for(&self, last_applied_at: u64, now: u64) -> Option<u64> {
+        let idle = now.saturating_sub(last_applied_at.max(self.watching_since));
+        (idle >= self.stall_ms).then_some(idle)
+    }
+
+    /// Returns how long processing has been stalled when a stall begins.
+    /// Progress, or nothing left to process, ends the stall.
+    fn check(&mut self, last_applied_at: u64, lag: u64, now: u64) -> Option<u64> {
+        match self.idle_for(last_applied_at, now) {
+            Some(idle) if lag > 0 => {
+                let began = !self.stalled;
+                self.stalled = true;
+                began.then_some(idle)
+            }
+            _ => {
+                if self.stalled {
+                    info!("Processing resumed");
+                }
+                self.stalled = false;
+                None
+            }
+        }
+    }
+
+    /// Waits a full period again, e.g. after the consumer was recreated
+    fn reset(&mut self, now: u64) {
+        self.watching_since = now;
+        self.stalled = false;
+    }
+}
+
+/// Details of a processing stall alert
+#[derive(Debug, Serialize)]
+struct ProcessingStall {
+    stalled_for_ms: u64,
+    /// Commands handed to the engine and not applied
+    queued_commands: u64,
+    /// Messages on the subscribed partitions not consumed yet, if measurable
+    consumer_lag: Option<u64>,
+    restarting_consumer: bool,
+}
+
+/// Messages on the consumer's assigned partitions past its position. A
+/// partition not consumed from yet counts all of its messages, a paused one
+/// none, as it is not expected to be consumed.
+pub fn consumer_lag(consumer: &CommandConsumer, timeout: Duration) -> KafkaResult<u64> {
+    let consumption = consumer.context().consumption();
+    let mut lag = 0;
+    for partition in consumer.position()?.elements() {
+        if consumption.is_paused(partition.topic(), partition.partition()) {
+            continue;
+        }
+        let (low, high) =
+            consumer.fetch_watermarks(partition.topic(), partition.partition(), timeout)?;
+        let position = match partition.offset() {
+            Offset::Offset(offset) => offset,
+            _ => low,
+        };
+        lag += high.saturating_sub(position).max(0) as u64;
+    }
+    Ok(lag)
+}
+
+/// Alerts when no command has been applied for `stall_secs` while commands are
+/// queued for the engine or waiting in Kafka, catching a wedged consumer stream
+/// or channel. With `restart_consumer`, `restart` is notified for the consumer
+/// loop to recreate its consumer, which it then publishes on `consumers`.
+pub async fn run(
+    config: WatchdogConfig,
+    progress: Progress,
+    consumers: watch::Receiver<Arc<CommandConsumer>>,
+    restart: Arc<Notify>,
+    publisher: Publisher,
+) {
+    let lag_timeout = Duration::from_millis(config.lag_timeout_ms);
+    let mut detector = StallDetector::new(config.stall_secs * 1_000, current_time_millis());
+    let mut tick = tokio::time::interval(Duration::from_millis(config.check_interval_ms.max(1)));
+    tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tick.tick().await;
+        let now = current_time_millis();
+        let last_applied_at = progress.last_applied_at();
+        if detector.idle_for(last_applied_at, now).is_none() {
+            detector.check(last_applied_at, 0, now);
+            continue;
+        }
+        // Only asked of the brokers once the engine has been idle for a while
+        let consumer = consumers.borrow().clone();
+        let consumer_lag = match tokio::task::spawn_blocking(move || {
+            consumer_lag(&consumer, lag_timeout).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|lag| lag)
+        {
+            Ok(lag) => Some(lag),
+            Err(e) => {
+                warn!("Failed to measure consumer lag: {}", e);
+                None
+            }
+        };
+        let queued_commands = progress.queued();
+        let lag = queued_commands + consumer_lag.unwrap_or(0);
+        let Some(stalled_for_ms) = detector.check(last_applied_at, lag, now) else {
+            continue;
+        };
+        emit_alert(
+            &publisher,
+            Alert::new(
+                AlertKind::ProcessingStall,
+                "engine",
+                format!(
+                    "No command applied for {} ms with {} commands waiting",
+                    stalled_for_ms, lag
+                ),
+            )
+            .with_details(&ProcessingStall {
+                stalled_for_ms,
+                queued_commands,
+                consumer_lag,
+                restarting_consumer: config.restart_consumer,
+            }),
+        );
+        if config.restart_consumer {
+            restart.notify_one();
+            detector.reset(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_tracks_queued_commands() {
+        let progress = Progress::default();
+        progress.received();
+        progress.received();
+        progress.received();
+        progress.applied(5);
+        progress.lost();
+        assert_eq!(progress.queued(), 1);
+        assert_eq!(progress.last_applied_at(), 5);
+    }
+
+    #[test]
+    fn test_stall_needs_lag_and_is_alerted_once() {
+        let mut detector = StallDetector::new(1_000, 0);
+        // Idle with nothing to process is not a stall
+        assert_eq!(detector.check(0, 0, 5_000), None);
+        assert_eq!(detector.check(4_500, 3, 5_000), None);
+        assert_eq!(detector.check(4_500, 3, 5_600), Some(1_100));
+        assert_eq!(detector.check(4_500, 3, 6_000), None);
+        // Progress ends the stall, so the next one is alerted again
+        assert_eq!(detector.check(6_100, 3, 6_200), None);
+        assert_eq!(detector.check(6_100, 3, 7_100), Some(1_000));
+    }
+
+    #[test]
+    fn test_reset_waits_a_full_period() {
+        let mut detector = StallDetector::new(1_000, 0);
+        assert_eq!(detector.check(0, 1, 2_000), Some(2_000));
+        detector.reset(2_000);
+        assert_eq!(detector.check(0, 1, 2_500), None);
+        assert_eq!(detector.check(0, 1, 3_000), Some(1_000));
+    }
+}